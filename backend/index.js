@@ -7,7 +7,6 @@ import { exec } from 'child_process'
 import express from 'express'
 import cors from 'cors'
 import fs from 'fs'
-import { animepaheRouter } from './animepahe/routes/search'
 import encUrls from '../../common/utils'
 import DiscordRPC from '../renderer/src/utils/discord'
 import WebSocket from 'ws'
@@ -978,10 +977,4 @@ app2.get('/proxy/image', async (req, res) => {
 //   console.log('Server running at http://localhost:64621')
 // })
 
-/* ------------------------------------------------------ */
-/* ---------------------- ANIMEPAHE --------------------- */
-/* ------------------------------------------------------ */
-
-app2.use('/animepahe', animepaheRouter)
-
 module.exports.extensionUrls = extensionUrls
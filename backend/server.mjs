@@ -5,9 +5,45 @@ import fs from 'fs'
 import path from 'path'
 import { fileURLToPath } from 'url'
 import chalk from 'chalk'
+import os from 'os'
+import { timingSafeEqual } from 'crypto'
 import { exec } from 'child_process'
-import { get } from 'http'
-export default function startZenshinServer() {
+import { get, request } from 'http'
+
+/* --------------------- APP RUNTIME -------------------- */
+// The app writes a per-launch token and the folders downloads are saved to into
+// backend-runtime.json in its config directory. Control routes require that token
+// and only touch paths inside those folders.
+const APP_IDENTIFIER = 'com.zanshin.app'
+const RUNTIME_FILE_NAME = 'backend-runtime.json'
+const TOKEN_HEADER = 'x-zanshin-token'
+
+// Origins the app's webview is served from (production and `npm run dev`)
+const APP_ORIGINS = [
+  'tauri://localhost',
+  'http://tauri.localhost',
+  'https://tauri.localhost',
+  'http://localhost:5173'
+]
+
+// Same directory as the app's `app_config_dir()`
+const defaultRuntimeFile = () => {
+  if (process.env.ZANSHIN_RUNTIME_FILE) return process.env.ZANSHIN_RUNTIME_FILE
+  const home = os.homedir()
+  let configDir
+  if (process.platform === 'win32') {
+    configDir = process.env.APPDATA || path.join(home, 'AppData', 'Roaming')
+  } else if (process.platform === 'darwin') {
+    configDir = path.join(home, 'Library', 'Application Support')
+  } else {
+    configDir = process.env.XDG_CONFIG_HOME || path.join(home, '.config')
+  }
+  return path.join(configDir, APP_IDENTIFIER, RUNTIME_FILE_NAME)
+}
+/* ------------------------------------------------------ */
+
+// `mediaPort` overrides the media server port the app shares in the runtime file
+export default function startZenshinServer({ mediaPort, runtimeFile = defaultRuntimeFile() } = {}) {
   // Get the current directory
   const __filename = fileURLToPath(import.meta.url)
   const __dirname = path.dirname(__filename)
@@ -24,7 +60,57 @@ export default function startZenshinServer() {
   const app = express()
   const client = new WebTorrent()
 
-  app.use(cors())
+  app.use(cors({ origin: APP_ORIGINS }))
+
+  // Re-read the runtime file whenever the app rewrites it
+  let runtimeCache = { mtimeMs: -1, value: null }
+  const loadRuntime = () => {
+    try {
+      const { mtimeMs } = fs.statSync(runtimeFile)
+      if (mtimeMs !== runtimeCache.mtimeMs) {
+        runtimeCache = { mtimeMs, value: JSON.parse(fs.readFileSync(runtimeFile, 'utf8')) }
+      }
+      return runtimeCache.value
+    } catch (err) {
+      console.error(chalk.bgRed('App runtime file unavailable:'), err.message)
+      return null
+    }
+  }
+
+  // Reject control requests that don't carry this launch's token
+  const requireAppToken = (req, res, next) => {
+    const expected = Buffer.from(loadRuntime()?.token || '')
+    const given = Buffer.from(req.get(TOKEN_HEADER) || '')
+    if (!expected.length || given.length !== expected.length || !timingSafeEqual(given, expected)) {
+      return res.status(403).send('Forbidden')
+    }
+    next()
+  }
+
+  // Canonicalize `target` (symlinks, `..`); `null` unless it exists inside a
+  // download root
+  const resolveInDownloadRoots = (target) => {
+    if (typeof target !== 'string' || !target) return null
+    let resolved
+    try {
+      resolved = fs.realpathSync.native(path.resolve(target))
+    } catch {
+      return null
+    }
+    const roots = (loadRuntime()?.downloadRoots || []).flatMap((root) => {
+      try {
+        return [fs.realpathSync.native(root)]
+      } catch {
+        return []
+      }
+    })
+    const inside = roots.some((root) => {
+      const relative = path.relative(root, resolved)
+      const outside = relative === '..' || relative.startsWith(`..${path.sep}`)
+      return !outside && !path.isAbsolute(relative)
+    })
+    return inside ? resolved : null
+  }
 
   // Ensure the temporary directory exists
   // if (!fs.existsSync(tempDir)) {
@@ -58,39 +144,87 @@ export default function startZenshinServer() {
   getLatestRelease()
   /* ------------------------------------------------------ */
 
-  /* ----------------- SEED EXISTING FILES ---------------- */
-  // Seed all existing files on server startup
-  const seedExistingFiles = () => {
-    fs.readdir(downloadsDir, (err, files) => {
-      if (err) {
-        console.error('Error reading downloads directory:', err)
-        return
-      }
+  /* ------------------- SEEDING CONTROL ------------------ */
+  // Seeding is driven by the app's seeding manager, which decides what to seed
+  // (ratio/time goals, metered connections, playback) and calls these routes
 
-      files.forEach((file) => {
-        const filePath = path.join(downloadsDir, file)
-
-        if (fs.lstatSync(filePath).isFile()) {
-          client.seed(filePath, { path: downloadsDir }, (torrent) => {
-            // console.log(`Seeding file: ${filePath}`);
-            // console.log(`Magnet URI: ${torrent.magnetURI}`);
-            console.log(chalk.bgBlue('Seeding started: '), chalk.cyan(torrent.name))
-            torrent.on('error', (err) => {
-              console.error(chalk.bgRed('Error seeding file:'), err)
-            })
-          })
-        }
+  // List all torrents with their transfer stats
+  app.get('/torrents', requireAppToken, (req, res) => {
+    const torrents = client.torrents.map((tor) => ({
+      infoHash: tor.infoHash,
+      magnetURI: tor.magnetURI,
+      name: tor.name,
+      path: tor.path,
+      length: tor.length,
+      downloaded: tor.downloaded,
+      uploaded: tor.uploaded,
+      uploadSpeed: tor.uploadSpeed,
+      progress: tor.progress,
+      ratio: tor.ratio,
+      numPeers: tor.numPeers,
+      paused: tor.paused,
+      done: tor.done
+    }))
+
+    res.status(200).json(torrents)
+  })
+
+  // Seed a file or folder inside the downloads folder that is not known to the
+  // client yet
+  app.post('/seed', requireAppToken, express.json(), (req, res) => {
+    const filePath = resolveInDownloadRoots(req.body?.path)
+    if (!filePath) {
+      return res.status(400).send('Path is not inside the downloads folder')
+    }
+
+    const onError = (err) => {
+      console.error(chalk.bgRed('Error seeding file:'), err)
+      if (!res.headersSent) res.status(500).send('Error seeding file')
+    }
+
+    try {
+      client.seed(filePath, { path: path.dirname(filePath) }, (torrent) => {
+        console.log(chalk.bgBlue('Seeding started: '), chalk.cyan(torrent.name))
+        torrent.on('error', onError)
+        res.status(200).json({
+          infoHash: torrent.infoHash,
+          magnetURI: torrent.magnetURI,
+          name: torrent.name,
+          path: torrent.path,
+          length: torrent.length
+        })
       })
-    })
-  }
+    } catch (err) {
+      onError(err)
+    }
+  })
 
-  // Call the function to start seeding existing files
-  seedExistingFiles()
-  /* ------------------------------------------------------ */
+  // Stop uploading without removing the torrent
+  app.post('/pause/:magnet', requireAppToken, async (req, res) => {
+    let tor = await client.get(req.params.magnet)
+    if (!tor) {
+      return res.status(404).send('Torrent not found')
+    }
 
-  app.get('/add/:magnet', async (req, res) => {
-    let magnet = req.params.magnet
+    tor.pause()
+    // pause() only blocks new peers, so drop the current connections as well
+    tor.wires.slice().forEach((wire) => wire.destroy())
+    res.status(200).send('Torrent paused')
+  })
+
+  app.post('/resume/:magnet', requireAppToken, async (req, res) => {
+    let tor = await client.get(req.params.magnet)
+    if (!tor) {
+      return res.status(404).send('Torrent not found')
+    }
+
+    tor.resume()
+    res.status(200).send('Torrent resumed')
+  })
+  /* ------------------------------------------------------ */
 
+  // Add a torrent (or return the files of a known one)
+  const addTorrent = async (magnet, options, res) => {
     /* ------------------------------------------------------ */
     // Check if the torrent is already added
     let existingTorrent = await client.get(magnet)
@@ -108,7 +242,7 @@ export default function startZenshinServer() {
     }
     /* ------------------------------------------------------ */
 
-    client.add(magnet, function (torrent) {
+    client.add(magnet, options, function (torrent) {
       let files = torrent.files.map((file) => ({
         name: file.name,
         length: file.length
@@ -117,6 +251,20 @@ export default function startZenshinServer() {
 
       res.status(200).json(files)
     })
+  }
+
+  // Stream a torrent, saving it to the client's default folder
+  app.get('/add/:magnet', async (req, res) => {
+    await addTorrent(req.params.magnet, {}, res)
+  })
+
+  // Downloads queued by the app are saved to a folder inside the downloads folder
+  app.post('/add/:magnet', requireAppToken, express.json(), async (req, res) => {
+    const folder = resolveInDownloadRoots(req.body?.path)
+    if (!folder) {
+      return res.status(400).send('Path is not inside the downloads folder')
+    }
+    await addTorrent(req.params.magnet, { path: folder }, res)
   })
 
   /* -------------------- GET METADATA -------------------- */
@@ -398,6 +546,49 @@ export default function startZenshinServer() {
     })
   })
 
+  /* ------------------- MEDIA SERVER PROXY ------------------ */
+  // Subtitle extraction and the stream proxy run in the app's media server (next
+  // to this port); forward them so the player can load everything from this port
+  const forwardMediaServer = (req, res) => {
+    const port = mediaPort ?? loadRuntime()?.mediaPort
+    if (!port) {
+      console.error(chalk.bgRed('Media server port unknown:'), 'the app has not shared it yet')
+      return res.status(503).send('Media server port unknown')
+    }
+
+    const headers = { accept: req.headers.accept || '*/*' }
+    for (const key of ['range', 'if-range']) {
+      if (req.headers[key]) headers[key] = req.headers[key]
+    }
+
+    const upstream = request(
+      {
+        host: '127.0.0.1',
+        port,
+        path: req.originalUrl,
+        method: req.method,
+        headers
+      },
+      (upstreamRes) => {
+        res.status(upstreamRes.statusCode)
+        for (const [key, value] of Object.entries(upstreamRes.headers)) {
+          if (!key.startsWith('access-control-')) res.setHeader(key, value)
+        }
+        upstreamRes.pipe(res)
+      }
+    )
+
+    upstream.on('error', (err) => {
+      console.error('Media server unavailable:', err.message)
+      if (!res.headersSent) res.status(502).send('Media server unavailable')
+    })
+    res.on('close', () => upstream.destroy())
+    upstream.end()
+  }
+
+  app.use('/subtitles', forwardMediaServer)
+  app.use('/stream', forwardMediaServer)
+
   // ping backend
   app.get('/ping', (req, res) => {
     res.status(200).send('pong')
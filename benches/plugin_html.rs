@@ -0,0 +1,86 @@
+//! Benchmarks of the plugin HTML extraction on large listing pages
+//!
+//! Run with `cargo bench --bench plugin_html`. Pages have 500 and 5,000 anime cards
+//! (roughly 0.3 MB and 3 MB), plus an episode table with the same number of rows.
+
+use app_lib::plugin_html::{self, HtmlRequest};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::json;
+
+const CARD: &str = r#"
+      <div class="anime-card" data-id="anime-{n}">
+        <a href="/anime/anime-{n}">
+          <img class="lazy" src="/img/placeholder.gif" data-src="https://cdn.example.com/covers/{n}.jpg" alt="">
+          <h3 class="title">Anime Title &amp; Subtitle {n}</h3>
+        </a>
+        <div class="genres"><span>Action</span><span>Fantasy</span><span>Drama</span></div>
+        <span class="rating">8.{n}</span>
+        <p class="synopsis">A rather long synopsis for anime {n}, with <em>emphasis</em>
+          and several lines of text so the page size is close to real listing pages.</p>
+      </div>"#;
+
+/// A listing page with `cards` cards and an episode table with as many rows
+fn listing_page(cards: usize) -> String {
+    let mut page = String::from("<!DOCTYPE html><html><head><title>Browse</title></head><body><main><div class=\"listing\">");
+    for n in 0..cards {
+        page.push_str(&CARD.replace("{n}", &n.to_string()));
+    }
+    page.push_str("</div><table class=\"episodes\"><tr><th>#</th><th>Title</th><th>Aired</th></tr>");
+    for n in 0..cards {
+        page.push_str(&format!("<tr><td>{n}</td><td>Episode <b>{n}</b></td><td>2024-01-01</td></tr>"));
+    }
+    page.push_str("</table></main></body></html>");
+    page
+}
+
+fn request(value: serde_json::Value) -> HtmlRequest {
+    serde_json::from_value(value).unwrap()
+}
+
+fn bench_listing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("listing");
+    group.sample_size(10);
+
+    for cards in [500, 5_000] {
+        let page = listing_page(cards);
+        group.throughput(Throughput::Bytes(page.len() as u64));
+
+        let text = request(json!({ "op": "text", "html": page, "selector": ".anime-card .title" }));
+        group.bench_with_input(BenchmarkId::new("text", cards), &text, |b, req| {
+            b.iter(|| plugin_html::extract(req).unwrap())
+        });
+
+        let attribute = request(json!({
+            "op": "attribute", "html": page, "selector": "img.lazy",
+            "attribute": "data-src", "baseUrl": "https://example.com/"
+        }));
+        group.bench_with_input(BenchmarkId::new("attribute", cards), &attribute, |b, req| {
+            b.iter(|| plugin_html::extract(req).unwrap())
+        });
+
+        let select = request(json!({
+            "op": "select", "html": page, "selector": ".anime-card", "baseUrl": "https://example.com/",
+            "fields": {
+                "id": { "attribute": "data-id" },
+                "title": { "selector": ".title" },
+                "url": { "selector": "a", "attribute": "href" },
+                "cover": { "selector": "img", "attribute": "data-src" },
+                "genres": { "selector": ".genres span", "all": true },
+                "rating": { "selector": ".rating" }
+            }
+        }));
+        group.bench_with_input(BenchmarkId::new("select", cards), &select, |b, req| {
+            b.iter(|| plugin_html::extract(req).unwrap())
+        });
+
+        let table = request(json!({ "op": "table", "html": page, "selector": "table.episodes", "headers": true }));
+        group.bench_with_input(BenchmarkId::new("table", cards), &table, |b, req| {
+            b.iter(|| plugin_html::extract(req).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_listing);
+criterion_main!(benches);
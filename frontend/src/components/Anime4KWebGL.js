@@ -0,0 +1,377 @@
+/**
+ * Anime4K WebGL Processor
+ *
+ * Runs the bundled Anime4K GLSL shaders (mpv user shaders, returned by
+ * `anime4k_get_shader_sources`) with WebGL2, for webviews without WebGPU.
+ *
+ * Supports the subset of the mpv user shader format Anime4K uses: fragment passes
+ * hooking NATIVE, MAIN, LINEAR or PREKERNEL with BIND, SAVE, WIDTH, HEIGHT and WHEN
+ * (RPN expressions). Compute passes and embedded textures are rejected.
+ * See: https://mpv.io/manual/master/#options-glsl-shader
+ */
+
+// Hook points in the order mpv runs them; the video frame flows through all of them
+const STAGES = ['NATIVE', 'MAIN', 'LINEAR', 'PREKERNEL']
+
+const VERTEX_SHADER = `#version 300 es
+in vec2 a_position;
+out vec2 v_pos;
+void main() {
+  v_pos = a_position * 0.5 + 0.5;
+  gl_Position = vec4(a_position, 0.0, 1.0);
+}
+`
+
+const BLIT_SHADER = `#version 300 es
+precision highp float;
+in vec2 v_pos;
+out vec4 fragColor;
+uniform sampler2D image;
+void main() {
+  fragColor = texture(image, v_pos);
+}
+`
+
+/**
+ * Split an mpv user shader into passes
+ * @param {string} name - Shader name, for errors
+ * @param {string} source - GLSL source
+ * @returns {Array<Object>} Passes with their directives and GLSL body
+ */
+export function parseUserShader(name, source) {
+  const passes = []
+  let pass = null
+  let inHeader = false
+
+  for (const line of source.split('\n')) {
+    const match = line.trim().match(/^\/\/!(\w+)\s*(.*)$/)
+    if (!match) {
+      inHeader = false
+      if (pass) pass.body.push(line)
+      continue
+    }
+    if (!inHeader) {
+      pass = { name, hooks: [], binds: [], save: null, width: null, height: null, when: null, body: [] }
+      passes.push(pass)
+      inHeader = true
+    }
+    const [, directive, value] = match
+    switch (directive) {
+      case 'HOOK':
+        pass.hooks.push(value.trim())
+        break
+      case 'BIND':
+        if (!pass.binds.includes(value.trim())) pass.binds.push(value.trim())
+        break
+      case 'SAVE':
+        pass.save = value.trim()
+        break
+      case 'WIDTH':
+        pass.width = value.trim()
+        break
+      case 'HEIGHT':
+        pass.height = value.trim()
+        break
+      case 'WHEN':
+        pass.when = value.trim()
+        break
+      case 'DESC':
+      case 'COMPONENTS':
+      case 'OFFSET':
+        break
+      default:
+        throw new Error(`${name}: //!${directive} is not supported by the WebGL renderer`)
+    }
+  }
+
+  for (const pass of passes) {
+    if (pass.hooks.length === 0) {
+      throw new Error(`${name}: pass without //!HOOK`)
+    }
+    const unsupported = pass.hooks.find((hook) => !STAGES.includes(hook))
+    if (unsupported) {
+      throw new Error(`${name}: hooking ${unsupported} is not supported by the WebGL renderer`)
+    }
+    pass.body = pass.body.join('\n')
+  }
+  return passes
+}
+
+/**
+ * Evaluate an RPN size or condition expression
+ * @param {string} expression - e.g. `OUTPUT.w MAIN.w / 1.200 >`
+ * @param {function(string): {width: number, height: number}} sizeOf - Texture sizes by name
+ * @returns {number}
+ */
+export function evaluateRpn(expression, sizeOf) {
+  const stack = []
+  for (const token of expression.split(/\s+/).filter(Boolean)) {
+    const size = token.match(/^(\w+)\.(w|width|h|height)$/)
+    if (size) {
+      const { width, height } = sizeOf(size[1])
+      stack.push(size[2].startsWith('w') ? width : height)
+      continue
+    }
+    if (token === '!') {
+      stack.push(stack.pop() ? 0 : 1)
+      continue
+    }
+    if ('+-*/<>='.includes(token) && token.length === 1) {
+      const b = stack.pop()
+      const a = stack.pop()
+      if (a === undefined || b === undefined) {
+        throw new Error(`Invalid expression: ${expression}`)
+      }
+      stack.push({
+        '+': a + b,
+        '-': a - b,
+        '*': a * b,
+        '/': a / b,
+        '<': a < b ? 1 : 0,
+        '>': a > b ? 1 : 0,
+        '=': a === b ? 1 : 0,
+      }[token])
+      continue
+    }
+    const number = Number(token)
+    if (Number.isNaN(number)) {
+      throw new Error(`Invalid token ${token} in expression: ${expression}`)
+    }
+    stack.push(number)
+  }
+  if (stack.length !== 1) {
+    throw new Error(`Invalid expression: ${expression}`)
+  }
+  return stack[0]
+}
+
+// mpv's texture macros for a bound texture
+function textureMacros(name) {
+  return `uniform sampler2D ${name}_raw;
+uniform vec2 ${name}_size;
+#define ${name}_pos v_pos
+#define ${name}_pt (vec2(1.0) / ${name}_size)
+#define ${name}_mul 1.0
+#define ${name}_rot mat2(1.0)
+#define ${name}_tex(pos) (${name}_mul * texture(${name}_raw, pos))
+#define ${name}_texOff(off) ${name}_tex(${name}_pos + ${name}_pt * vec2(off))
+`
+}
+
+function fragmentShader(pass) {
+  const binds = pass.binds.includes('HOOKED') ? pass.binds : [...pass.binds, 'HOOKED']
+  return `#version 300 es
+precision highp float;
+in vec2 v_pos;
+out vec4 fragColor;
+uniform vec2 target_size;
+uniform vec2 input_size;
+uniform int frame;
+uniform float random;
+${binds.map(textureMacros).join('')}
+${pass.body}
+
+void main() {
+  fragColor = hook();
+}
+`
+}
+
+function compile(gl, type, source, label) {
+  const shader = gl.createShader(type)
+  gl.shaderSource(shader, source)
+  gl.compileShader(shader)
+  if (!gl.getShaderParameter(shader, gl.COMPILE_STATUS)) {
+    const log = gl.getShaderInfoLog(shader)
+    gl.deleteShader(shader)
+    throw new Error(`Failed to compile ${label}: ${log}`)
+  }
+  return shader
+}
+
+function link(gl, vertexShader, fragmentSource, label) {
+  const program = gl.createProgram()
+  const fragment = compile(gl, gl.FRAGMENT_SHADER, fragmentSource, label)
+  gl.attachShader(program, vertexShader)
+  gl.attachShader(program, fragment)
+  gl.bindAttribLocation(program, 0, 'a_position')
+  gl.linkProgram(program)
+  gl.deleteShader(fragment)
+  if (!gl.getProgramParameter(program, gl.LINK_STATUS)) {
+    throw new Error(`Failed to link ${label}: ${gl.getProgramInfoLog(program)}`)
+  }
+  return program
+}
+
+function createTexture(gl) {
+  const texture = gl.createTexture()
+  gl.bindTexture(gl.TEXTURE_2D, texture)
+  gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR)
+  gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR)
+  gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE)
+  gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE)
+  return texture
+}
+
+/**
+ * Check that WebGL2 can run the shaders (they need float render targets)
+ * @returns {boolean}
+ */
+export function isWebGLSupported() {
+  try {
+    const gl = document.createElement('canvas').getContext('webgl2')
+    return gl !== null && gl.getExtension('EXT_color_buffer_float') !== null
+  } catch {
+    return false
+  }
+}
+
+/**
+ * Render a video through a shader chain onto a canvas, every frame
+ * @param {Object} options
+ * @param {HTMLVideoElement} options.video - Source video element
+ * @param {HTMLCanvasElement} options.canvas - Output canvas (its size is the target size)
+ * @param {{shaders: string[], sources: Object<string, string>}} options.chain - Shader
+ *   names in run order and the GLSL source of each shader
+ * @returns {function(): void} Stops rendering and frees the GPU resources
+ */
+export function renderShaderChain({ video, canvas, chain }) {
+  const gl = canvas.getContext('webgl2', { premultipliedAlpha: false })
+  if (!gl) {
+    throw new Error('WebGL2 is not available')
+  }
+  if (!gl.getExtension('EXT_color_buffer_float')) {
+    throw new Error('WebGL2 float render targets are not available')
+  }
+
+  // Each occurrence of a shader in the chain runs its own passes
+  const passes = chain.shaders.flatMap((shader) => {
+    const source = chain.sources[shader]
+    if (source === undefined) {
+      throw new Error(`Missing source of Anime4K shader ${shader}`)
+    }
+    return parseUserShader(shader, source)
+  })
+
+  const vertexShader = compile(gl, gl.VERTEX_SHADER, VERTEX_SHADER, 'vertex shader')
+  const programs = new Map()
+  for (const pass of passes) {
+    const source = fragmentShader(pass)
+    if (!programs.has(source)) {
+      programs.set(source, link(gl, vertexShader, source, pass.name))
+    }
+    pass.program = programs.get(source)
+    pass.target = { texture: createTexture(gl), framebuffer: gl.createFramebuffer(), width: 0, height: 0 }
+  }
+  const blit = link(gl, vertexShader, BLIT_SHADER, 'output')
+  gl.deleteShader(vertexShader)
+
+  const quad = gl.createBuffer()
+  gl.bindBuffer(gl.ARRAY_BUFFER, quad)
+  gl.bufferData(gl.ARRAY_BUFFER, new Float32Array([-1, -1, 1, -1, -1, 1, 1, 1]), gl.STATIC_DRAW)
+  gl.enableVertexAttribArray(0)
+  gl.vertexAttribPointer(0, 2, gl.FLOAT, false, 0, 0)
+
+  const input = { texture: createTexture(gl), width: 0, height: 0 }
+  let frame = 0
+  let stopped = false
+  let handle = null
+
+  const runPass = (pass, hooked, saved) => {
+    const output = { width: canvas.width, height: canvas.height }
+    const sizeOf = (name) => {
+      if (name === 'OUTPUT') return output
+      if (name === 'NATIVE_CROPPED') return input
+      if (name === 'HOOKED' || STAGES.includes(name)) return hooked
+      const texture = saved.get(name)
+      if (!texture) throw new Error(`${pass.name}: unknown texture ${name}`)
+      return texture
+    }
+    if (pass.when && !evaluateRpn(pass.when, sizeOf)) {
+      return null
+    }
+    const width = Math.max(1, Math.round(pass.width ? evaluateRpn(pass.width, sizeOf) : hooked.width))
+    const height = Math.max(1, Math.round(pass.height ? evaluateRpn(pass.height, sizeOf) : hooked.height))
+
+    const { target } = pass
+    if (target.width !== width || target.height !== height) {
+      gl.bindTexture(gl.TEXTURE_2D, target.texture)
+      gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA16F, width, height, 0, gl.RGBA, gl.HALF_FLOAT, null)
+      gl.bindFramebuffer(gl.FRAMEBUFFER, target.framebuffer)
+      gl.framebufferTexture2D(gl.FRAMEBUFFER, gl.COLOR_ATTACHMENT0, gl.TEXTURE_2D, target.texture, 0)
+      target.width = width
+      target.height = height
+    }
+
+    gl.useProgram(pass.program)
+    const binds = pass.binds.includes('HOOKED') ? pass.binds : [...pass.binds, 'HOOKED']
+    binds.forEach((name, unit) => {
+      const texture = name === 'HOOKED' || STAGES.includes(name) ? hooked : sizeOf(name)
+      gl.activeTexture(gl.TEXTURE0 + unit)
+      gl.bindTexture(gl.TEXTURE_2D, texture.texture)
+      gl.uniform1i(gl.getUniformLocation(pass.program, `${name}_raw`), unit)
+      gl.uniform2f(gl.getUniformLocation(pass.program, `${name}_size`), texture.width, texture.height)
+    })
+    gl.uniform2f(gl.getUniformLocation(pass.program, 'target_size'), output.width, output.height)
+    gl.uniform2f(gl.getUniformLocation(pass.program, 'input_size'), input.width, input.height)
+    gl.uniform1i(gl.getUniformLocation(pass.program, 'frame'), frame)
+    gl.uniform1f(gl.getUniformLocation(pass.program, 'random'), Math.random())
+
+    gl.bindFramebuffer(gl.FRAMEBUFFER, target.framebuffer)
+    gl.viewport(0, 0, width, height)
+    gl.drawArrays(gl.TRIANGLE_STRIP, 0, 4)
+    return target
+  }
+
+  const draw = () => {
+    if (stopped) return
+    if (video.readyState >= video.HAVE_CURRENT_DATA && video.videoWidth > 0) {
+      gl.activeTexture(gl.TEXTURE0)
+      gl.bindTexture(gl.TEXTURE_2D, input.texture)
+      gl.pixelStorei(gl.UNPACK_FLIP_Y_WEBGL, true)
+      gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, gl.RGBA, gl.UNSIGNED_BYTE, video)
+      input.width = video.videoWidth
+      input.height = video.videoHeight
+
+      // Passes run stage by stage, in chain order within a stage
+      let hooked = input
+      const saved = new Map()
+      for (const stage of STAGES) {
+        for (const pass of passes) {
+          if (!pass.hooks.includes(stage)) continue
+          const result = runPass(pass, hooked, saved)
+          if (!result) continue
+          if (pass.save) saved.set(pass.save, result)
+          else hooked = result
+        }
+      }
+
+      gl.bindFramebuffer(gl.FRAMEBUFFER, null)
+      gl.viewport(0, 0, canvas.width, canvas.height)
+      gl.useProgram(blit)
+      gl.activeTexture(gl.TEXTURE0)
+      gl.bindTexture(gl.TEXTURE_2D, hooked.texture)
+      gl.uniform1i(gl.getUniformLocation(blit, 'image'), 0)
+      gl.drawArrays(gl.TRIANGLE_STRIP, 0, 4)
+      frame++
+    }
+    handle = video.requestVideoFrameCallback
+      ? { video: video.requestVideoFrameCallback(draw) }
+      : { animation: requestAnimationFrame(draw) }
+  }
+  draw()
+
+  return () => {
+    stopped = true
+    if (handle?.video !== undefined) video.cancelVideoFrameCallback(handle.video)
+    if (handle?.animation !== undefined) cancelAnimationFrame(handle.animation)
+    for (const pass of passes) {
+      gl.deleteTexture(pass.target.texture)
+      gl.deleteFramebuffer(pass.target.framebuffer)
+    }
+    for (const program of programs.values()) gl.deleteProgram(program)
+    gl.deleteProgram(blit)
+    gl.deleteTexture(input.texture)
+    gl.deleteBuffer(quad)
+  }
+}
@@ -7,9 +7,14 @@
  * 
  * Based on anime4k-webgpu npm package
  * See: https://github.com/Anime4KWebBoost/Anime4K-WebGPU
+ *
+ * Without WebGPU, the bundled Anime4K GLSL shaders are run with WebGL2 instead
+ * (see Anime4KWebGL.js).
  */
 
 import { useEffect, useRef, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { isWebGLSupported, renderShaderChain } from './Anime4KWebGL'
 
 // Import preset modes from anime4k-webgpu
 // These are dynamically imported to handle WebGPU not being available
@@ -75,12 +80,34 @@ function getPresetClass(module, presetId) {
   }
 }
 
+/**
+ * Get the bundled Anime4K GLSL sources of a preset, for renderers that compile
+ * the original mpv shaders (the WebGL fallback)
+ * @param {string} presetId - The preset ID (e.g., 'mode-a', 'mode-b')
+ * @returns {Promise<{presetId: string, shaders: string[], sources: Object<string, string>}>}
+ *   Shader names in run order and the GLSL source of each shader
+ */
+export async function getAnime4KShaderSources(presetId) {
+  return invoke('anime4k_get_shader_sources', { presetId })
+}
+
+/**
+ * Pick the renderer: WebGPU, or WebGL2 running the bundled GLSL shaders
+ * @returns {Promise<'webgpu'|'webgl'|null>}
+ */
+async function detectBackend() {
+  if (await isWebGPUSupported()) return 'webgpu'
+  if (isWebGLSupported()) return 'webgl'
+  return null
+}
+
 /**
  * Anime4K WebGPU Processor Hook
  * 
  * This hook manages the WebGPU rendering pipeline for Anime4K processing.
  * It takes a video element, processes each frame through WebGPU shaders,
- * and renders the result to a canvas overlay.
+ * and renders the result to a canvas overlay. Without WebGPU, the preset's
+ * bundled GLSL shaders are run with WebGL2.
  * 
  * @param {Object} options
  * @param {HTMLVideoElement} options.video - Source video element
@@ -91,15 +118,16 @@ function getPresetClass(module, presetId) {
  */
 export function useAnime4KWebGPU({ video, presetId = 'mode-b', enabled = false, upscaleFactor = 2 }) {
   const canvasRef = useRef(null)
-  const [isSupported, setIsSupported] = useState(false)
+  const [backend, setBackend] = useState(null)
   const [isActive, setIsActive] = useState(false)
   const [error, setError] = useState(null)
   const renderingRef = useRef(false)
   const cleanupRef = useRef(null)
+  const isSupported = backend !== null
 
-  // Check WebGPU support on mount
+  // Pick the renderer on mount
   useEffect(() => {
-    isWebGPUSupported().then(setIsSupported)
+    detectBackend().then(setBackend)
   }, [])
 
   // Initialize or cleanup rendering
@@ -116,8 +144,36 @@ export function useAnime4KWebGPU({ video, presetId = 'mode-b', enabled = false,
 
     let cancelled = false
     
+    // Run the preset's bundled GLSL shaders with WebGL2
+    const initWebGLRenderer = async () => {
+      const chain = await getAnime4KShaderSources(presetId)
+      const canvas = canvasRef.current
+      if (!canvas || cancelled) return
+
+      canvas.width = (video.videoWidth || 1920) * upscaleFactor
+      canvas.height = (video.videoHeight || 1080) * upscaleFactor
+      const stop = renderShaderChain({ video, canvas, chain })
+      if (cancelled) {
+        stop()
+        return
+      }
+
+      setIsActive(true)
+      setError(null)
+      renderingRef.current = true
+      cleanupRef.current = () => {
+        renderingRef.current = false
+        stop()
+      }
+    }
+
     const initRenderer = async () => {
       try {
+        if (backend === 'webgl') {
+          await initWebGLRenderer()
+          return
+        }
+
         const module = await loadAnime4KModule()
         if (!module || cancelled) return
         
@@ -167,9 +223,9 @@ export function useAnime4KWebGPU({ video, presetId = 'mode-b', enabled = false,
           // Canvas will be hidden/removed by parent component
         }
       } catch (err) {
-        console.error('Anime4K WebGPU initialization failed:', err)
+        console.error(`Anime4K ${backend === 'webgl' ? 'WebGL' : 'WebGPU'} initialization failed:`, err)
         if (!cancelled) {
-          setError(err.message || 'WebGPU initialization failed')
+          setError(err.message || err || 'Anime4K initialization failed')
           setIsActive(false)
         }
       }
@@ -184,11 +240,12 @@ export function useAnime4KWebGPU({ video, presetId = 'mode-b', enabled = false,
         cleanupRef.current = null
       }
     }
-  }, [enabled, video, presetId, isSupported, upscaleFactor])
+  }, [enabled, video, presetId, isSupported, backend, upscaleFactor])
 
   return {
     canvasRef,
     isSupported,
+    backend,
     isActive,
     error
   }
@@ -296,5 +353,6 @@ export default {
   isWebGPUSupported,
   useAnime4KWebGPU,
   Anime4KCanvas,
-  getWebGPUInfo
+  getWebGPUInfo,
+  getAnime4KShaderSources
 }
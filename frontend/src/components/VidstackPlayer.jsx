@@ -268,8 +268,9 @@ function extractPlaybackInfo(event) {
 
 /**
  * Hook to use Rust Anime4K backend with fallback to JavaScript config
+ * @param {string|null} animeId - Anime whose override applies (before the profile and global config)
  */
-function useAnime4KRust() {
+function useAnime4KRust(animeId = null) {
   const [presets, setPresets] = useState([])
   const [config, setConfig] = useState(null)
   const [isRustAvailable, setIsRustAvailable] = useState(false)
@@ -280,7 +281,7 @@ function useAnime4KRust() {
         if (window.api?.anime4k) {
           // Try to use Rust backend
           const rustPresets = await window.api.anime4k.getPresets()
-          const rustConfig = await window.api.anime4k.getConfig()
+          const rustConfig = await window.api.anime4k.getConfig(animeId)
           setPresets(rustPresets)
           setConfig(rustConfig)
           setIsRustAvailable(true)
@@ -302,12 +303,14 @@ function useAnime4KRust() {
       }
     }
     initAnime4K()
-  }, [])
+  }, [animeId])
 
   const setAnime4KConfig = useCallback(async (enabled, presetId) => {
     try {
       if (isRustAvailable && window.api?.anime4k) {
-        const newConfig = await window.api.anime4k.setConfig(enabled, presetId)
+        // Keep changing the anime's override once it has one
+        const scope = config?.source === 'anime' ? 'anime' : null
+        const newConfig = await window.api.anime4k.setConfig(enabled, presetId, { scope, animeId })
         setConfig(newConfig)
         return newConfig
       } else {
@@ -326,7 +329,7 @@ function useAnime4KRust() {
       console.error('Failed to set Anime4K config:', error)
       return null
     }
-  }, [isRustAvailable])
+  }, [isRustAvailable, config, animeId])
 
   const recommendPreset = useCallback(async () => {
     try {
@@ -1134,6 +1137,7 @@ const VidstackPlayer = forwardRef(function VidstackPlayer(
     subtitles = [],
     anime4kEnabled = false,
     anime4kPreset = DEFAULT_ANIME4K_PRESET_ID,
+    animeId = null,
     className = '',
     showAnime4KControls = true,
     showMiracastControls = true,
@@ -1151,7 +1155,7 @@ const VidstackPlayer = forwardRef(function VidstackPlayer(
     presets, 
     config, 
     setAnime4KConfig
-  } = useAnime4KRust()
+  } = useAnime4KRust(animeId)
 
   // Use WebGPU-based Anime4K rendering for real sharpening effects
   const {
@@ -1,53 +1,40 @@
-const DEFAULT_PORT = 64621
-let BASE_URL = `http://localhost:${DEFAULT_PORT}/animepahe`
+import { api } from '../../utils/tauri-api'
 
-async function getBaseUrl() {
-  // Only try to get settings if window.api is available (Tauri context)
-  if (typeof window !== 'undefined' && window.api && typeof window.api.getSettingsJson === 'function') {
-    try {
-      const response = await window.api.getSettingsJson()
-      if (response && response.backendPort) {
-        return `http://localhost:${response.backendPort}/animepahe`
-      }
-    } catch {
-      // Silently fall back to default if API fails
-    }
-  }
-  return BASE_URL
-}
+// AnimePahe requests run in the app (src/animepahe.rs); results keep the shapes the
+// old backend routes returned
 
 export const animepaheLatest = async (page = 1) => {
-  const baseUrl = await getBaseUrl()
-  const response = await fetch(`${baseUrl}/latest?page=${page}`)
-  return response.json()
+  try {
+    return await api.animepahe.latest(page)
+  } catch (error) {
+    return { error: String(error) }
+  }
 }
-export const animepaheDetails = async (page = 1) => {
-  const baseUrl = await getBaseUrl()
-  const response = await fetch(`${baseUrl}/details?id=${page}`)
-  return response.json()
+
+export const animepaheDetails = async (id) => {
+  const details = await api.animepahe.details(id)
+  return {
+    title: details.title,
+    cover: details.cover,
+    desc: details.description,
+    anilist_id: details.anilistId
+  }
 }
 
-export const animepaheEpisodes = async (id, page = 1) => {
-  const baseUrl = await getBaseUrl()
-  const response = await fetch(`${baseUrl}/getallepisodes?id=${id}`)
-  const data = await response.json()
+export const animepaheEpisodes = async (id) => {
+  const data = await api.animepahe.allEpisodes(id)
   return { data: data.episodes }
 }
+
 export const animepaheEpisodesOfPage = async (id, page = 1) => {
-  const baseUrl = await getBaseUrl()
-  const response = await fetch(`${baseUrl}/getepisodesofpage?id=${id}&page=${page}`)
-  const data = await response.json()
-  return { data: data.episodes }
+  const data = await api.animepahe.episodes(id, page)
+  return { data: data.data }
 }
 
 export const animepahePlay = async (id, episode) => {
-  const baseUrl = await getBaseUrl()
-  const response = await fetch(`${baseUrl}/play?id=${id}&episode=${episode}`)
-  return response.json()
+  return api.animepahe.streams(id, episode)
 }
 
 export const animepaheSearch = async (query) => {
-  const baseUrl = await getBaseUrl()
-  const response = await fetch(`${baseUrl}/search?q=${query}`)
-  return response.json()
+  return api.animepahe.search(query)
 }
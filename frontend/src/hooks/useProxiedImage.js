@@ -1,8 +1,7 @@
 /**
  * React hook for loading images that may need CORS proxy
  * 
- * Uses Tauri's HTTP plugin to fetch images from CORS-restricted domains
- * and converts them to data URLs for display.
+ * Resolves remote images to the app's cached `zimg://` image proxy URLs.
  */
 
 import { useState, useEffect, useRef, useMemo } from 'react'
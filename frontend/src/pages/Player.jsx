@@ -395,6 +395,7 @@ export default function Player(query) {
                   autoPlay={true}
                   showAnime4KControls={true}
                   showMiracastControls={true}
+                  animeId={animeId ? String(animeId) : null}
                   className="rounded-lg overflow-hidden"
                 />
               ) : (
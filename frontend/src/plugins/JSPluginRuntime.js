@@ -12,7 +12,7 @@
  * - Plugin lifecycle management
  */
 
-import { fetch as tauriFetch } from '@tauri-apps/plugin-http'
+import { invoke } from '@tauri-apps/api/core'
 
 // ============================================================================
 // Plugin Types
@@ -43,10 +43,15 @@ export const STREAM_FORMAT = {
 
 /**
  * HTTP Client wrapper for plugin web scraping
- * Uses Tauri's HTTP plugin for making requests with full TLS support
+ * Requests go through the app's plugin_fetch command, which checks the plugin's permissions
  */
 class PluginHttpClient {
-  constructor(config = {}) {
+  // Session the app issued when it loaded this plugin; plugin code can't read or replace it
+  #session
+
+  constructor(pluginId, config = {}, session = null) {
+    this.pluginId = pluginId
+    this.#session = session
     this.userAgent = config.userAgent || 'Ayoto/2.5.4'
     this.defaultTimeout = config.timeout || 30000
     this.rateLimitMs = config.rateLimitMs || 0
@@ -104,32 +109,27 @@ class PluginHttpClient {
     }
 
     try {
-      const fetchOptions = {
+      const request = {
+        url,
         method,
         headers,
-        connectTimeout: options.timeout || this.defaultTimeout
+        timeoutMs: options.timeout || this.defaultTimeout
       }
 
       if (options.body) {
         if (typeof options.body === 'object') {
-          fetchOptions.body = JSON.stringify(options.body)
+          request.body = JSON.stringify(options.body)
           headers['Content-Type'] = 'application/json'
         } else {
-          fetchOptions.body = options.body
+          request.body = String(options.body)
         }
       }
 
-      const response = await tauriFetch(url, fetchOptions)
-      const responseText = await response.text()
-
-      return {
-        status: response.status,
-        statusText: response.statusText,
-        headers: Object.fromEntries(response.headers.entries()),
-        body: responseText,
-        ok: response.ok,
-        url: response.url
+      if (!this.#session) {
+        throw new Error('Plugin is not installed in the app')
       }
+      // Permissions, allowlist, rate limit and size cap are enforced by the app
+      return await invoke('plugin_fetch', { session: this.#session, request })
     } catch (error) {
       return {
         status: 0,
@@ -398,9 +398,9 @@ export const HtmlParser = {
  * Context provided to plugins for accessing host functionality
  */
 export class PluginContext {
-  constructor(pluginId, config = {}) {
+  constructor(pluginId, config = {}, session = null) {
     this.pluginId = pluginId
-    this.http = new PluginHttpClient(config)
+    this.http = new PluginHttpClient(pluginId, config, session)
     this.html = HtmlParser
     this.storage = new PluginStorage(pluginId)
   }
@@ -504,10 +504,13 @@ class PluginStorage {
  * Represents a loaded JavaScript plugin instance
  */
 export class JSPlugin {
+  // Session from plugin_load, handed only to this plugin's context
+  #session = null
+
   constructor(manifest, code) {
     this.manifest = manifest
     this.code = code
-    this.context = new PluginContext(manifest.id, manifest.scrapingConfig)
+    this.context = null
     this.instance = null
     this.initialized = false
   }
@@ -519,6 +522,13 @@ export class JSPlugin {
     if (this.initialized) return
 
     try {
+      // The app issues the session the plugin's requests are made with when it loads
+      // the plugin; plugins that aren't installed in the app get none
+      this.#session = await invoke('plugin_load', { pluginId: this.manifest.id })
+        .then((loaded) => loaded.session ?? null)
+        .catch(() => null)
+      this.context = new PluginContext(this.manifest.id, this.manifest.scrapingConfig, this.#session)
+
       // Create a sandboxed function from the plugin code
       // The plugin code should export a class or object with the provider methods
       const pluginModule = this._createPluginModule()
@@ -583,6 +593,10 @@ export class JSPlugin {
     if (this.instance && typeof this.instance.shutdown === 'function') {
       await this.instance.shutdown()
     }
+    if (this.#session) {
+      await invoke('plugin_unload', { session: this.#session }).catch(() => {})
+      this.#session = null
+    }
     this.initialized = false
     this.instance = null
   }
@@ -38,11 +38,18 @@ export const api = {
     getPresets: () => invoke('anime4k_get_presets'),
     getPreset: (presetId) => invoke('anime4k_get_preset', { presetId }),
     getRequirements: (presetId) => invoke('anime4k_get_requirements', { presetId }),
-    getConfig: () => invoke('anime4k_get_config'),
-    setConfig: (enabled, presetId) => invoke('anime4k_set_config', { enabled, presetId }),
-    toggle: (enabled) => invoke('anime4k_toggle', { enabled }),
-    getCssFilter: () => invoke('anime4k_get_css_filter'),
+    // scope: 'global' | 'profile' | 'anime'; profileId defaults to the active profile
+    getConfig: (animeId = null, profileId = null) => invoke('anime4k_get_config', { animeId, profileId }),
+    setConfig: (enabled, presetId, { scope = null, animeId = null, profileId = null } = {}) =>
+      invoke('anime4k_set_config', { enabled, presetId, scope, animeId, profileId }),
+    clearConfig: (scope, { animeId = null, profileId = null } = {}) =>
+      invoke('anime4k_clear_config', { scope, animeId, profileId }),
+    getOverrides: () => invoke('anime4k_get_overrides'),
+    toggle: (enabled, animeId = null, profileId = null) => invoke('anime4k_toggle', { enabled, animeId, profileId }),
+    getCssFilter: (animeId = null, profileId = null) => invoke('anime4k_get_css_filter', { animeId, profileId }),
     recommendPreset: (gpuInfo) => invoke('anime4k_recommend_preset', { gpuInfo }),
+    getMpvArgs: (presetId) => invoke('anime4k_get_mpv_args', { presetId }),
+    getShaderSources: (presetId) => invoke('anime4k_get_shader_sources', { presetId }),
   },
   
   // User Profiles
@@ -82,6 +89,213 @@ export const api = {
     setAutoReconnect: (enabled) => invoke('miracast_set_auto_reconnect', { enabled }),
     getConnectionHealth: () => invoke('miracast_get_connection_health'),
   },
+
+  // Release name parser (Rust backend)
+  releases: {
+    parse: (name) => invoke('release_parse', { name }),
+    parseMany: (names) => invoke('release_parse_many', { names }),
+  },
+
+  // Torrent search (Rust backend)
+  torrents: {
+    search: (query, page, sources, profileId) =>
+      invoke('torrent_search', { query, page, sources, profileId }),
+    getSources: () => invoke('torrent_get_sources'),
+  },
+
+  // Download queue
+  downloads: {
+    list: () => invoke('download_list'),
+    addTorrent: (title, magnet) => invoke('download_add_torrent', { title, magnet }),
+    addHls: (title, url, headers, quality) => invoke('download_add_hls', { title, url, headers, quality }),
+    cancel: (id) => invoke('download_cancel', { id }),
+    retry: (id) => invoke('download_retry', { id }),
+    clearFinished: () => invoke('download_clear_finished'),
+  },
+
+  // RSS subscriptions
+  subscriptions: {
+    list: (profileId) => invoke('subscription_list', { profileId }),
+    add: (input, profileId) => invoke('subscription_add', { input, profileId }),
+    update: (id, input) => invoke('subscription_update', { id, input }),
+    remove: (id) => invoke('subscription_remove', { id }),
+    setEnabled: (id, enabled) => invoke('subscription_set_enabled', { id, enabled }),
+    getConfig: () => invoke('subscription_get_config'),
+    setConfig: (config) => invoke('subscription_set_config', { config }),
+    checkNow: () => invoke('subscription_check_now'),
+  },
+
+  // Seeding manager
+  seeding: {
+    list: () => invoke('seeding_list'),
+    getConfig: () => invoke('seeding_get_config'),
+    setConfig: (config) => invoke('seeding_set_config', { config }),
+    setItemPolicy: (infohash, policy) => invoke('seeding_set_item_policy', { infohash, policy }),
+    stop: (infohash) => invoke('seeding_stop', { infohash }),
+    resume: (infohash) => invoke('seeding_resume', { infohash }),
+    addPath: (path) => invoke('seeding_add_path', { path }),
+    forget: (infohash) => invoke('seeding_forget', { infohash }),
+    setMetered: (metered) => invoke('seeding_set_metered', { metered }),
+    setPlaying: (playing) => invoke('seeding_set_playing', { playing }),
+  },
+
+  // Subtitle extraction
+  subtitles: {
+    open: (source) => invoke('subtitle_open', { source }),
+    getTrack: (source, track, format) => invoke('subtitle_get_track', { source, track, format }),
+    extractFonts: (source) => invoke('subtitle_extract_fonts', { source }),
+    close: (source) => invoke('subtitle_close', { source }),
+    convertAss: (content) => invoke('subtitle_convert_ass', { content }),
+    getServerUrl: () => invoke('media_server_get_url'),
+  },
+
+  // Header-injecting stream proxy (HLS and direct streams)
+  streamProxy: {
+    register: (url, headers, ttlSecs) => invoke('stream_proxy_register', { registration: { url, headers, ttlSecs } }),
+    revoke: (token) => invoke('stream_proxy_revoke', { token }),
+    list: () => invoke('stream_proxy_list'),
+  },
+
+  // Stream source ranking and failover
+  streams: {
+    select: (streams, profileId = null, probe = true) => invoke('stream_select', { streams, profileId, probe }),
+    nextFallback: (sessionId, failedUrl = null) => invoke('stream_next_fallback', { sessionId, failedUrl }),
+    release: (sessionId) => invoke('stream_selection_release', { sessionId }),
+  },
+
+  // Episode number mapping between providers and AniList
+  mappings: {
+    get: (id, source = null, refresh = false) => invoke('episode_mapping_get', { id, source, refresh }),
+    resolve: (anilistIds, number, numbering, season = null) =>
+      invoke('episode_mapping_resolve', { anilistIds, number, numbering, season }),
+    linkProvider: (pluginId, providerAnimeId, anilistIds, episodes, numbering = null, season = null) =>
+      invoke('episode_mapping_link_provider', { pluginId, providerAnimeId, anilistIds, episodes, numbering, season }),
+    fromProvider: (pluginId, providerAnimeId, episodeId) =>
+      invoke('episode_mapping_from_provider', { pluginId, providerAnimeId, episodeId }),
+    toProvider: (pluginId, providerAnimeId, anilistId, episode) =>
+      invoke('episode_mapping_to_provider', { pluginId, providerAnimeId, anilistId, episode }),
+    unlinkProvider: (pluginId, providerAnimeId) =>
+      invoke('episode_mapping_unlink_provider', { pluginId, providerAnimeId }),
+    clearCache: () => invoke('episode_mapping_clear_cache'),
+  },
+
+  // AnimePahe provider
+  animepahe: {
+    search: (query) => invoke('animepahe_search', { query }),
+    latest: (page = 1) => invoke('animepahe_latest', { page }),
+    details: (animeSession) => invoke('animepahe_details', { animeSession }),
+    episodes: (animeSession, page = 1) => invoke('animepahe_episodes', { animeSession, page }),
+    allEpisodes: (animeSession) => invoke('animepahe_all_episodes', { animeSession }),
+    streams: (animeSession, episodeSession) => invoke('animepahe_streams', { animeSession, episodeSession }),
+    setCookies: (cookies) => invoke('animepahe_set_cookies', { cookies }),
+  },
+
+  // Native hoster stream extraction
+  hosters: {
+    extract: (url, referer = null) => invoke('hoster_extract', { url, referer }),
+    info: (url) => invoke('hoster_info', { url }),
+    list: () => invoke('hoster_list'),
+  },
+
+  // Media probing and player selection
+  playback: {
+    probe: (source) => invoke('media_probe', { source }),
+    getCapabilities: () => invoke('playback_get_capabilities'),
+    recommend: (source, capabilities) => invoke('playback_recommend', { source, capabilities }),
+  },
+
+  // Intro/outro skip segments
+  skip: {
+    getSegments: (request) => invoke('skip_get_segments', { request }),
+    getProviders: () => invoke('skip_get_providers'),
+    clearCache: (anilistId) => invoke('skip_clear_cache', { anilistId }),
+  },
+
+  // Disk cache of the zimg:// image proxy
+  imageCache: {
+    getStats: () => invoke('image_cache_get_stats'),
+    clear: () => invoke('image_cache_clear'),
+  },
+
+  // ZPE packages
+  zpe: {
+    inspect: (path) => invoke('zpe_inspect', { path }),
+    // Unsigned packages are rejected unless allowUnsigned is set (developer packages)
+    verify: (path, allowUnsigned = false) => invoke('zpe_verify', { path, allowUnsigned }),
+    listTrustedKeys: () => invoke('zpe_list_trusted_keys'),
+    trustKey: (key) => invoke('zpe_trust_key', { key }),
+    untrustKey: (keyId) => invoke('zpe_untrust_key', { keyId }),
+  },
+
+  // Plugin registry
+  plugins: {
+    list: (profileId = null) => invoke('plugin_list', { profileId }),
+    installZpe: (path, allowUnsigned = false) =>
+      invoke('plugin_install', { source: { type: 'zpe', path, allowUnsigned } }),
+    installJs: (manifest, code) => invoke('plugin_install', { source: { type: 'js', manifest, code } }),
+    uninstall: (pluginId) => invoke('plugin_uninstall', { pluginId }),
+    enable: (pluginId, profileId = null) => invoke('plugin_enable', { pluginId, profileId }),
+    disable: (pluginId, profileId = null) => invoke('plugin_disable', { pluginId, profileId }),
+    rollback: (pluginId) => invoke('plugin_rollback', { pluginId }),
+    load: (pluginId, profileId = null) => invoke('plugin_load', { pluginId, profileId }),
+    unload: (session) => invoke('plugin_unload', { session }),
+    fetch: (session, request) => invoke('plugin_fetch', { session, request }),
+    getAuditLog: (pluginId = null, limit = 100) => invoke('plugin_audit_log', { pluginId, limit }),
+    call: (pluginId, method, args = []) => invoke('plugin_call', { pluginId, method, args }),
+    search: (pluginId, query, page = 1) => invoke('plugin_search', { pluginId, query, page }),
+    getEpisodes: (pluginId, animeId, page = 1) => invoke('plugin_get_episodes', { pluginId, animeId, page }),
+    getStreams: (pluginId, animeId, episodeId) => invoke('plugin_get_streams', { pluginId, animeId, episodeId }),
+    unloadHost: (pluginId) => invoke('plugin_host_unload', { pluginId }),
+    // Storage of the plugin a session from `load` belongs to
+    storage: {
+      get: (session, key) => invoke('plugin_storage_get', { session, key }),
+      set: (session, key, value, ttlMs = null) => invoke('plugin_storage_set', { session, key, value, ttlMs }),
+      remove: (session, key) => invoke('plugin_storage_remove', { session, key }),
+      clear: (session) => invoke('plugin_storage_clear', { session }),
+      keys: (session) => invoke('plugin_storage_keys', { session }),
+      getUsage: (session) => invoke('plugin_storage_usage', { session }),
+    },
+    updates: {
+      check: () => invoke('plugin_updates_check'),
+      list: () => invoke('plugin_updates_list'),
+      apply: (pluginId) => invoke('plugin_updates_apply', { pluginId }),
+      dismiss: (pluginId) => invoke('plugin_updates_dismiss', { pluginId }),
+      getConfig: () => invoke('plugin_updates_get_config'),
+      setConfig: (config) => invoke('plugin_updates_set_config', { config }),
+    },
+    repos: {
+      list: () => invoke('plugin_repo_list'),
+      add: (url, keyId = null) => invoke('plugin_repo_add', { url, keyId }),
+      remove: (repositoryId) => invoke('plugin_repo_remove', { repositoryId }),
+      refresh: (repositoryId = null) => invoke('plugin_repo_refresh', { repositoryId }),
+      available: (updatesOnly = false) => invoke('plugin_repo_available', { updatesOnly }),
+      install: (pluginId, repositoryId = null) => invoke('plugin_repo_install', { pluginId, repositoryId }),
+    },
+  },
+
+  // Native HTML extraction (CSS selectors)
+  // Search across all media provider plugins, grouped by AniList entry
+  search: {
+    all: (query, page = 1, options = {}) =>
+      invoke('search_aggregate', {
+        query,
+        page,
+        pluginIds: options.pluginIds ?? null,
+        timeoutMs: options.timeoutMs ?? null,
+        matchAnilist: options.matchAnilist ?? true,
+      }),
+  },
+
+  html: {
+    extract: (request) => invoke('html_extract', { request }),
+    text: (html, selector) => invoke('html_extract', { request: { op: 'text', html, selector } }),
+    attribute: (html, selector, attribute, baseUrl = null) =>
+      invoke('html_extract', { request: { op: 'attribute', html, selector, attribute, baseUrl } }),
+    select: (html, selector, fields, baseUrl = null) =>
+      invoke('html_extract', { request: { op: 'select', html, selector, fields, baseUrl } }),
+    table: (html, selector = 'table', headers = false) =>
+      invoke('html_extract', { request: { op: 'table', html, selector, headers } }),
+  },
 };
 
 // For backward compatibility - make API available on window
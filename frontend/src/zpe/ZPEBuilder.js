@@ -11,10 +11,12 @@
 import {
   ZPE_MAGIC_BYTES,
   ZPE_FORMAT_VERSION,
+  ZPE_SIGNATURE_ALGORITHM,
   calculateHash,
   calculateIntegrityHash,
   encryptData,
   generateEncryptionKey,
+  signData,
   auditPluginCode
 } from './ZPESecurity.js'
 
@@ -87,6 +89,7 @@ const ZPE_ENCRYPTION_TYPE = {
  * @property {boolean} [encrypt] - Encrypt plugin package
  * @property {boolean} [sign] - Sign plugin package
  * @property {CryptoKey} [signingKey] - Private key for signing
+ * @property {string} [signingKeyId] - ID of the signing key in the app's trusted key store
  * @property {boolean} [includeSourceMap] - Include source map
  * @property {Object} [assets] - Additional assets to include
  * @property {boolean} [strictMode] - Strict security validation
@@ -124,6 +127,14 @@ export class ZPEBuilder {
     const errors = []
     const warnings = []
 
+    if (opts.sign && (!opts.signingKey || !opts.signingKeyId)) {
+      return {
+        success: false,
+        errors: ['Signing requires signingKey and signingKeyId'],
+        warnings
+      }
+    }
+
     // Step 1: Validate manifest
     const manifestValidation = validateZPEManifest(manifest)
     if (!manifestValidation.valid) {
@@ -253,7 +264,26 @@ export class ZPEBuilder {
     const metadataData = encoder.encode(JSON.stringify(metadata))
     sections.push(this._wrapSection(ZPE_SECTION.METADATA, metadataData))
 
-    // Combine all sections
+    // Section 5: Signature (must be last - it signs every byte before it)
+    if (opts.sign) {
+      const signature = await signData(this._concat(sections), opts.signingKey)
+      const signatureData = encoder.encode(JSON.stringify({
+        algorithm: ZPE_SIGNATURE_ALGORITHM,
+        keyId: opts.signingKeyId,
+        signature
+      }))
+      sections.push(this._wrapSection(ZPE_SECTION.SIGNATURE, signatureData))
+    }
+
+    return this._concat(sections)
+  }
+
+  /**
+   * Combine sections into one buffer
+   * @param {Uint8Array[]} sections - Sections in file order
+   * @returns {Uint8Array} Combined data
+   */
+  _concat(sections) {
     const totalSize = sections.reduce((sum, s) => sum + s.length, 0)
     const result = new Uint8Array(totalSize)
     let offset = 0
@@ -547,6 +577,13 @@ export class ZPEParser {
       assets = JSON.parse(decoder.decode(assetsSection.data))
     }
 
+    // Extract signature (verified by the app before plugins are loaded)
+    const signatureSection = sections.find(s => s.type === ZPE_SECTION.SIGNATURE)
+    let signature = null
+    if (signatureSection) {
+      signature = JSON.parse(decoder.decode(signatureSection.data))
+    }
+
     return {
       header,
       manifest,
@@ -555,6 +592,7 @@ export class ZPEParser {
       assets,
       encrypted: header.encrypted,
       signed: header.signed,
+      signature,
       encryptedCodeData: header.encrypted ? codeSection.data : null
     }
   }
@@ -11,6 +11,7 @@
  * @version 1.0.0
  */
 
+import { invoke } from '@tauri-apps/api/core'
 import { zpePluginManager, ZPEPlugin } from './ZPERuntime.js'
 import { ZPE_PLUGIN_TYPE, ZPE_STREAM_FORMAT, ZPE_PLUGIN_STATE } from './ZPEManifest.js'
 
@@ -189,6 +190,10 @@ class ZPEPluginAPI {
 
   /**
    * Search for anime across all enabled media providers
+   *
+   * Returns one unmerged list per provider. Plugins installed in the app's plugin
+   * registry can be searched natively with `api.search.all()` (search_aggregator.rs),
+   * which groups the results per anime and matches them to AniList.
    * @param {string} query - Search query
    * @param {number} [page=1] - Page number
    * @returns {Promise<Object>} Combined search results from all providers
@@ -312,11 +317,24 @@ class ZPEPluginAPI {
   }
 
   /**
-   * Extract stream from a hoster URL using stream providers
+   * Extract stream from a hoster URL, using the native extractors first and
+   * stream providers for hosters they don't cover
    * @param {string} url - Hoster URL
    * @returns {Promise<StreamSource|null>} Extracted stream or null
    */
   async extractStream(url) {
+    try {
+      const info = await invoke('hoster_info', { url })
+      if (info.supported) {
+        const streams = await invoke('hoster_extract', { url, referer: null })
+        if (streams.length > 0) {
+          return { ...streams[0], provider: 'native' }
+        }
+      }
+    } catch (error) {
+      console.warn(`[ZPE API] Native extraction failed for ${url}:`, error)
+    }
+
     const providers = this.getStreamProviders().filter(p => p.enabled && p.capabilities?.extractStream)
     
     for (const provider of providers) {
@@ -356,6 +374,15 @@ class ZPEPluginAPI {
    * @returns {Promise<Object>} Hoster info with supported provider
    */
   async getHosterInfo(url) {
+    try {
+      const info = await invoke('hoster_info', { url })
+      if (info.supported) {
+        return { name: info.name, supported: true, key: info.id, provider: 'native' }
+      }
+    } catch (error) {
+      // Fall back to stream providers
+    }
+
     const providers = this.getStreamProviders().filter(p => p.enabled && p.capabilities?.getHosterInfo)
     
     for (const provider of providers) {
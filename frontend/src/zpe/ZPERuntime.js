@@ -8,7 +8,7 @@
  * - Plugin lifecycle management
  */
 
-import { fetch as tauriFetch } from '@tauri-apps/plugin-http'
+import { invoke } from '@tauri-apps/api/core'
 
 import {
   ZPE_PLUGIN_STATE,
@@ -54,11 +54,15 @@ const MAX_PLUGINS = 50
 
 /**
  * Secure HTTP client for plugin use
- * Enforces domain allowlists and rate limiting
+ * Checks domain allowlists and rate limits early; the app enforces them in plugin_fetch
  */
 class ZPEHttpClient {
-  constructor(pluginId, config = {}) {
+  // Session the app issued when it loaded this plugin; plugin code can't read or replace it
+  #session
+
+  constructor(pluginId, config = {}, session = null) {
     this.pluginId = pluginId
+    this.#session = session
     this.userAgent = config.userAgent || 'Ayoto-ZPE/1.0'
     this.defaultTimeout = config.timeout || DEFAULT_TIMEOUT
     this.rateLimitMs = config.rateLimitMs || DEFAULT_RATE_LIMIT
@@ -142,32 +146,27 @@ class ZPEHttpClient {
     }
 
     try {
-      const fetchOptions = {
+      const request = {
+        url,
         method,
         headers,
-        connectTimeout: options.timeout || this.defaultTimeout
+        timeoutMs: options.timeout || this.defaultTimeout
       }
 
       if (options.body) {
         if (typeof options.body === 'object') {
-          fetchOptions.body = JSON.stringify(options.body)
+          request.body = JSON.stringify(options.body)
           headers['Content-Type'] = 'application/json'
         } else {
-          fetchOptions.body = options.body
+          request.body = String(options.body)
         }
       }
 
-      const response = await tauriFetch(url, fetchOptions)
-      const body = await response.text()
-
-      return {
-        status: response.status,
-        statusText: response.statusText,
-        headers: Object.fromEntries(response.headers.entries()),
-        body,
-        ok: response.ok,
-        url: response.url
+      if (!this.#session) {
+        throw new Error('Plugin is not installed in the app')
       }
+      // Permissions, allowlist, rate limit and size cap are enforced by the app
+      return await invoke('plugin_fetch', { session: this.#session, request })
     } catch (error) {
       console.error(`[ZPE:${this.pluginId}] HTTP error:`, error)
       return {
@@ -553,7 +552,7 @@ const ZPEHtmlParser = {
  * Context provided to plugins
  */
 class ZPEContext {
-  constructor(pluginId, manifest) {
+  constructor(pluginId, manifest, session = null) {
     this.pluginId = pluginId
     this.manifest = manifest
     
@@ -563,7 +562,7 @@ class ZPEContext {
       timeout: manifest.config?.timeout,
       rateLimitMs: manifest.config?.rateLimitMs,
       allowedDomains: manifest.security?.allowedDomains
-    })
+    }, session)
     
     // Create secure storage
     this.storage = new ZPEStorage(pluginId)
@@ -609,12 +608,20 @@ class ZPEContext {
 
 /**
  * Represents a loaded ZPE plugin
+ *
+ * Plugins installed in the app's plugin registry should be created with
+ * `{ hosted: true }`: their code then runs in the app's isolated plugin host
+ * (plugin_host.rs) instead of the webview, and calls go through `plugin_call`.
  */
 export class ZPEPlugin {
-  constructor(manifest, code) {
+  // Session from plugin_load, handed only to this plugin's context
+  #session = null
+
+  constructor(manifest, code, options = {}) {
     this.manifest = manifest
     this.code = code
     this.id = manifest.id
+    this.hosted = options.hosted === true
     this.state = ZPE_PLUGIN_STATE.UNLOADED
     this.context = null
     this.instance = null
@@ -633,8 +640,22 @@ export class ZPEPlugin {
     this.state = ZPE_PLUGIN_STATE.LOADING
 
     try {
+      if (this.hosted) {
+        // The host loads the plugin on its first call
+        this.state = ZPE_PLUGIN_STATE.ACTIVE
+        this.loadedAt = new Date()
+        this.errorMessage = null
+        return
+      }
+
+      // The app issues the session the plugin's requests are made with when it loads
+      // the plugin; plugins that aren't installed in the app get none
+      this.#session = await invoke('plugin_load', { pluginId: this.id })
+        .then((loaded) => loaded.session ?? null)
+        .catch(() => null)
+
       // Create context
-      this.context = new ZPEContext(this.id, this.manifest)
+      this.context = new ZPEContext(this.id, this.manifest, this.#session)
 
       // Execute plugin code in sandbox
       this.instance = executeSandboxed(this.code, this.id, this.context)
@@ -666,13 +687,20 @@ export class ZPEPlugin {
     }
 
     try {
-      if (this.instance && typeof this.instance.shutdown === 'function') {
+      if (this.hosted) {
+        await invoke('plugin_host_unload', { pluginId: this.id })
+      } else if (this.instance && typeof this.instance.shutdown === 'function') {
         await this.instance.shutdown()
       }
     } catch (error) {
       console.error(`[ZPE] Error during shutdown of '${this.id}':`, error)
     }
 
+    if (this.#session) {
+      await invoke('plugin_unload', { session: this.#session }).catch(() => {})
+      this.#session = null
+    }
+
     this.state = ZPE_PLUGIN_STATE.UNLOADED
     this.instance = null
     this.context = null
@@ -694,6 +722,10 @@ export class ZPEPlugin {
       throw new Error(`Plugin '${this.id}' is not active`)
     }
 
+    if (this.hosted) {
+      return invoke('plugin_call', { pluginId: this.id, method, args })
+    }
+
     if (!this.instance?.[method]) {
       throw new Error(`Plugin '${this.id}' does not implement '${method}'`)
     }
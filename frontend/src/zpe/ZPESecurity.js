@@ -41,6 +41,11 @@ export const ZPE_ENCRYPTION = {
  */
 export const ZPE_HASH_ALGORITHM = 'SHA-256'
 
+/**
+ * Package signature algorithm (ECDSA on P-384 over a SHA-256 digest, raw r||s)
+ */
+export const ZPE_SIGNATURE_ALGORITHM = 'ECDSA-P384-SHA256'
+
 // ============================================================================
 // Cryptographic Utilities
 // ============================================================================
@@ -211,6 +216,25 @@ export async function signCode(code, privateKey) {
   return btoa(String.fromCharCode(...new Uint8Array(signature)))
 }
 
+/**
+ * Sign binary data (used for the signature section of .zpe packages)
+ * @param {Uint8Array} data - Data to sign
+ * @param {CryptoKey} privateKey - Private signing key
+ * @returns {Promise<string>} Base64-encoded signature
+ */
+export async function signData(data, privateKey) {
+  const signature = await crypto.subtle.sign(
+    {
+      name: 'ECDSA',
+      hash: { name: ZPE_HASH_ALGORITHM }
+    },
+    privateKey,
+    data
+  )
+
+  return btoa(String.fromCharCode(...new Uint8Array(signature)))
+}
+
 /**
  * Verify code signature
  * @param {string} code - Code that was signed
@@ -473,6 +497,9 @@ export function createSandboxContext(pluginId, allowedGlobals = {}) {
 
 /**
  * Execute plugin code in a sandboxed environment
+ *
+ * This only hides globals; the code still runs in the webview. Installed plugins run
+ * in the app's plugin host instead (see ZPEPlugin's `hosted` option).
  * @param {string} code - Plugin code
  * @param {string} pluginId - Plugin ID
  * @param {Object} context - Plugin context (http, storage, etc.)
@@ -780,6 +807,7 @@ export default {
   ZPE_FORMAT_VERSION,
   ZPE_ENCRYPTION,
   ZPE_HASH_ALGORITHM,
+  ZPE_SIGNATURE_ALGORITHM,
   calculateHash,
   calculateIntegrityHash,
   verifyIntegrityHash,
@@ -788,6 +816,7 @@ export default {
   decryptData,
   generateSigningKeyPair,
   signCode,
+  signData,
   verifySignature,
   exportPublicKey,
   importPublicKey,
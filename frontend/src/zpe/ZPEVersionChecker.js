@@ -6,6 +6,10 @@
  * - Compare versions to detect updates
  * - Download plugin updates
  * - Verify release integrity
+ *
+ * Plugins installed in the app's plugin registry are checked, verified and staged by
+ * the app (plugin_updates.rs, `api.plugins.updates`); this module is kept for plugins
+ * loaded in the webview.
  */
 
 import { fetch as tauriFetch } from '@tauri-apps/plugin-http'
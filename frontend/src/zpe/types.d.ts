@@ -381,6 +381,10 @@ export interface ZPEStreamSource {
   server?: string;
   /** Required headers */
   headers?: Record<string, string>;
+  /** Subtitled or dubbed (otherwise guessed from the server and quality labels) */
+  audio?: 'sub' | 'dub';
+  /** Audio language (e.g. 'ja', 'en') */
+  audioLang?: string;
 }
 
 /**
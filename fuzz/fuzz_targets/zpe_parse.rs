@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(package) = app_lib::zpe::parse(data) {
+        assert!(package.signed_len <= data.len());
+    }
+    let options = app_lib::zpe::VerifyOptions { allow_unsigned: true };
+    let _ = app_lib::zpe::verify(data, &[], &options);
+});
@@ -12,6 +12,8 @@
  *   --output, -o     Output directory (default: current directory)
  *   --minify, -m     Minify the plugin code
  *   --encrypt, -e    Encrypt the plugin (experimental)
+ *   --sign, -k       Sign the plugin with a private key (JWK file)
+ *   --key-id         ID of the signing key in the app's trusted key store
  *   --strict, -s     Enable strict security validation
  *   --verbose, -v    Verbose output
  *   --help, -h       Show help
@@ -22,7 +24,7 @@
 import { readFileSync, writeFileSync, existsSync, readdirSync, statSync, mkdirSync } from 'fs';
 import { join, basename, dirname, extname, resolve } from 'path';
 import { fileURLToPath } from 'url';
-import { createHash, randomBytes, createCipheriv } from 'crypto';
+import { createHash, randomBytes, createCipheriv, createPrivateKey, sign } from 'crypto';
 
 // Get __filename equivalent in ES modules (for potential future use)
 const __filename = fileURLToPath(import.meta.url);
@@ -56,6 +58,9 @@ const ZPE_ENCRYPTION_TYPE = {
   AES_GCM: 0x01
 };
 
+// ECDSA on P-384 over a SHA-256 digest, raw r||s signature
+const ZPE_SIGNATURE_ALGORITHM = 'ECDSA-P384-SHA256';
+
 // Dangerous patterns for security audit
 const DANGEROUS_PATTERNS = [
   { pattern: /\beval\s*\(/g, reason: 'eval() is not allowed' },
@@ -108,6 +113,8 @@ function parseArgs() {
     output: process.cwd(),
     minify: false,
     encrypt: false,
+    sign: null,
+    keyId: null,
     strict: true,
     verbose: false,
     help: false
@@ -124,6 +131,10 @@ function parseArgs() {
       options.minify = true;
     } else if (arg === '--encrypt' || arg === '-e') {
       options.encrypt = true;
+    } else if (arg === '--sign' || arg === '-k') {
+      options.sign = args[++i];
+    } else if (arg === '--key-id') {
+      options.keyId = args[++i];
     } else if (arg === '--strict' || arg === '-s') {
       options.strict = true;
     } else if (arg === '--no-strict') {
@@ -155,6 +166,8 @@ Options:
   --output, -o     Output directory for the .zpe file (default: current directory)
   --minify, -m     Minify the plugin code
   --encrypt, -e    Encrypt the plugin package (experimental)
+  --sign, -k       Sign the package with a P-384 private key (JWK file)
+  --key-id         Signing key ID (default: the JWK "kid", or the file name)
   --strict, -s     Enable strict security validation (default: enabled)
   --no-strict      Disable strict security validation
   --verbose, -v    Verbose output
@@ -164,6 +177,7 @@ Examples:
   node build-plugin.mjs ./my-plugin
   node build-plugin.mjs ./my-plugin -o ./dist
   node build-plugin.mjs ./my-plugin --minify --verbose
+  node build-plugin.mjs ./my-plugin --sign ./keys/publisher.jwk.json
 
 Plugin Structure:
   plugin-folder/
@@ -477,7 +491,7 @@ function buildHeader(options) {
   // Flags
   let flags = 0;
   if (options.encrypt) flags |= 0x01;
-  // if (options.sign) flags |= 0x02;
+  if (options.signingKey) flags |= 0x02;
   header[6] = flags & 0xFF;
   header[7] = (flags >> 8) & 0xFF;
   
@@ -564,16 +578,44 @@ function buildZPE(manifest, code, assets, options) {
   const metadataData = Buffer.from(JSON.stringify(metadata), 'utf8');
   sections.push(wrapSection(ZPE_SECTION.METADATA, metadataData));
   
+  // Section 5: Signature (must be last - it signs every byte before it)
+  if (options.signingKey) {
+    const signature = sign('sha256', Buffer.concat(sections), {
+      key: options.signingKey,
+      dsaEncoding: 'ieee-p1363'
+    });
+    const signatureData = Buffer.from(JSON.stringify({
+      algorithm: ZPE_SIGNATURE_ALGORITHM,
+      keyId: options.keyId,
+      signature: signature.toString('base64')
+    }), 'utf8');
+    sections.push(wrapSection(ZPE_SECTION.SIGNATURE, signatureData));
+  }
+  
   // Combine all sections
   return Buffer.concat(sections);
 }
 
+function loadSigningKey(keyPath, keyId) {
+  if (!existsSync(keyPath)) {
+    throw new Error(`Signing key not found: ${keyPath}`);
+  }
+  const jwk = JSON.parse(readFileSync(keyPath, 'utf8'));
+  if (jwk.kty !== 'EC' || jwk.crv !== 'P-384' || !jwk.d) {
+    throw new Error('Signing key must be a private P-384 EC key in JWK format');
+  }
+  return {
+    signingKey: createPrivateKey({ key: jwk, format: 'jwk' }),
+    keyId: keyId || jwk.kid || basename(keyPath).replace(/\.(jwk\.)?json$/, '')
+  };
+}
+
 // ============================================================================
 // Main Build Function
 // ============================================================================
 
 async function buildPlugin(options) {
-  const { pluginFolder, output, minify, encrypt, strict, verbose } = options;
+  const { pluginFolder, output, minify, encrypt, sign: signKeyPath, keyId, strict, verbose } = options;
   
   log(`Building plugin from: ${pluginFolder}`);
   
@@ -640,9 +682,13 @@ async function buildPlugin(options) {
   if (verbose) log('Loading assets...');
   const assets = loadAssets(pluginFolder, manifest, verbose);
   
+  // Load signing key
+  const signing = signKeyPath ? loadSigningKey(signKeyPath, keyId) : {};
+  if (signing.keyId) log(`Signing with key: ${signing.keyId}`);
+  
   // Build ZPE file
   if (verbose) log('Building ZPE package...');
-  const zpeData = buildZPE(manifest, code, assets, { encrypt, minify });
+  const zpeData = buildZPE(manifest, code, assets, { encrypt, minify, ...signing });
   
   // Calculate file hash
   const fileHash = calculateHash(zpeData);
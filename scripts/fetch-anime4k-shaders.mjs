@@ -0,0 +1,182 @@
+#!/usr/bin/env node
+/**
+ * Anime4K Shader Fetch Script
+ *
+ * Downloads the Anime4K GLSL shaders used by the app's presets into
+ * resources/anime4k, where they are bundled with the app (see src/anime4k.rs).
+ *
+ * Files are fetched from a pinned Anime4K commit and must match the SHA-256 pinned
+ * for them below; tags can move, commits and hashes can't.
+ *
+ * Runs before every `tauri build` (`beforeBuildCommand`): shaders already in the
+ * folder are only verified, missing ones are downloaded, and the build fails if
+ * any file is still missing or doesn't match its pinned hash.
+ *
+ * Usage:
+ *   node scripts/fetch-anime4k-shaders.mjs [--force] [--check]
+ *   node scripts/fetch-anime4k-shaders.mjs --pin <ref>
+ *
+ * Options:
+ *   --force    Download files that already exist
+ *   --check    Only verify the bundled files, without downloading
+ *   --pin      Resolve an Anime4K tag or branch to its commit, download the files
+ *              and pin the commit and their hashes in this script (review the diff)
+ */
+
+import { existsSync, mkdirSync, readFileSync, writeFileSync } from 'fs';
+import { join, dirname, basename } from 'path';
+import { fileURLToPath } from 'url';
+import { createHash } from 'crypto';
+
+const scriptPath = fileURLToPath(import.meta.url);
+const scriptDir = dirname(scriptPath);
+const outputDir = join(scriptDir, '..', 'resources', 'anime4k');
+
+const REPOSITORY = 'bloc97/Anime4K';
+
+// Must match `anime4k::shaders::ALL`
+const SHADERS = [
+  'Anime4K_Clamp_Highlights',
+  'Anime4K_Restore_CNN_S',
+  'Anime4K_Restore_CNN_Soft_M',
+  'Anime4K_Upscale_CNN_x2_S',
+  'Anime4K_Upscale_CNN_x2_M',
+  'Anime4K_Upscale_CNN_x2_L',
+  'Anime4K_Upscale_Denoise_CNN_x2_VL',
+  'Anime4K_AutoDownscalePre_x2',
+  'Anime4K_AutoDownscalePre_x4',
+];
+
+const FILES = [...SHADERS.map((shader) => `${shader}.glsl`), 'LICENSE'];
+
+// Written by --pin: the Anime4K commit the files come from and their SHA-256
+// BEGIN PINNED
+const COMMIT = null;
+const SHA256 = {};
+// END PINNED
+
+function parseArgs(argv) {
+  const options = { pin: null, force: false, check: false };
+  for (let i = 0; i < argv.length; i++) {
+    if (argv[i] === '--pin') options.pin = argv[++i];
+    else if (argv[i] === '--force') options.force = true;
+    else if (argv[i] === '--check') options.check = true;
+    else throw new Error(`Unknown option: ${argv[i]}`);
+  }
+  return options;
+}
+
+async function fetchOk(url) {
+  const response = await fetch(url, { headers: { 'User-Agent': 'zanshin-shader-fetch' } });
+  if (!response.ok) {
+    throw new Error(`${url} failed with status ${response.status}`);
+  }
+  return response;
+}
+
+function sha256(data) {
+  return createHash('sha256').update(data).digest('hex');
+}
+
+// Bundled files that are missing or don't match their pinned hash
+function findInvalid() {
+  return FILES.filter((file) => {
+    const target = join(outputDir, file);
+    return !SHA256[file] || !existsSync(target) || sha256(readFileSync(target)) !== SHA256[file];
+  });
+}
+
+// Paths of the files in the Anime4K tree at `commit` (shaders live in subfolders of glsl/)
+async function findPaths(commit) {
+  const tree = await (await fetchOk(`https://api.github.com/repos/${REPOSITORY}/git/trees/${commit}?recursive=1`)).json();
+  const paths = new Map(
+    tree.tree
+      .filter((entry) => entry.type === 'blob' && entry.path.endsWith('.glsl'))
+      .map((entry) => [basename(entry.path), entry.path])
+  );
+  paths.set('LICENSE', 'LICENSE');
+  return paths;
+}
+
+async function fetchFile(commit, paths, file) {
+  const path = paths.get(file);
+  if (!path) {
+    throw new Error(`${file} not found in ${REPOSITORY}@${commit}`);
+  }
+  const url = `https://raw.githubusercontent.com/${REPOSITORY}/${commit}/${encodeURI(path)}`;
+  return Buffer.from(await (await fetchOk(url)).arrayBuffer());
+}
+
+async function download(force) {
+  if (!COMMIT) {
+    throw new Error('No Anime4K commit is pinned; run with --pin <ref> first');
+  }
+  const paths = await findPaths(COMMIT);
+  for (const file of FILES) {
+    const target = join(outputDir, file);
+    if (!force && existsSync(target) && sha256(readFileSync(target)) === SHA256[file]) {
+      console.log(`  ${file}: already present`);
+      continue;
+    }
+    const data = await fetchFile(COMMIT, paths, file);
+    const hash = sha256(data);
+    if (hash !== SHA256[file]) {
+      throw new Error(`${file} from ${REPOSITORY}@${COMMIT} has sha256 ${hash}, expected ${SHA256[file]}`);
+    }
+    writeFileSync(target, data);
+    console.log(`  ${file}: ${paths.get(file)}`);
+  }
+}
+
+// Resolve `ref` to a commit, download the files from it and pin their hashes
+async function pin(ref) {
+  const { sha: commit } = await (await fetchOk(`https://api.github.com/repos/${REPOSITORY}/commits/${encodeURIComponent(ref)}`)).json();
+  const paths = await findPaths(commit);
+  const hashes = {};
+  for (const file of FILES) {
+    const data = await fetchFile(commit, paths, file);
+    // mpv user shaders declare at least one hook
+    if (file.endsWith('.glsl') && !data.toString('utf8').includes('//!HOOK')) {
+      throw new Error(`${paths.get(file)} is not an mpv user shader`);
+    }
+    writeFileSync(join(outputDir, file), data);
+    hashes[file] = sha256(data);
+  }
+
+  const block = [
+    '// BEGIN PINNED',
+    `const COMMIT = '${commit}'; // ${ref}`,
+    'const SHA256 = {',
+    ...FILES.map((file) => `  '${file}': '${hashes[file]}',`),
+    '};',
+    '// END PINNED',
+  ].join('\n');
+  const script = readFileSync(scriptPath, 'utf8');
+  writeFileSync(scriptPath, script.replace(/^\/\/ BEGIN PINNED$[\s\S]*?^\/\/ END PINNED$/m, block));
+  console.log(`Pinned ${REPOSITORY}@${ref} (${commit}) in ${scriptPath}`);
+}
+
+async function main() {
+  const { pin: ref, force, check } = parseArgs(process.argv.slice(2));
+  mkdirSync(outputDir, { recursive: true });
+
+  if (ref) {
+    await pin(ref);
+    return;
+  }
+
+  if (!check && (force || findInvalid().length > 0)) {
+    await download(force);
+  }
+
+  const invalid = findInvalid();
+  if (invalid.length > 0) {
+    throw new Error(`Anime4K files missing or not matching their pinned sha256 in ${outputDir}: ${invalid.join(', ')}`);
+  }
+  console.log(`Anime4K shaders from ${REPOSITORY}@${COMMIT} are in ${outputDir}`);
+}
+
+main().catch((error) => {
+  console.error(`Error: ${error.message}`);
+  process.exit(1);
+});
@@ -3,10 +3,25 @@
 //! This module provides high-performance Anime4K shader configuration and management
 //! through the Rust backend. It handles shader preset definitions, GPU capability detection,
 //! and configuration persistence.
+//!
+//! The Anime4K GLSL shaders are bundled in `resources/anime4k` (fetched with
+//! `scripts/fetch-anime4k-shaders.mjs`). A preset becomes an mpv `--glsl-shaders` chain
+//! for the external player, and the webview renderer gets the raw shader sources.
+//!
+//! The configuration is resolved in layers: a per-anime override, then the profile's
+//! `anime4k_preset` and `anime4k_enabled`, then the global default. Overrides and the global default are kept
+//! in the `anime4k.json` store. Overrides are shared by all profiles because they follow
+//! the source (an old 480p show wants Mode A+A, a modern 1080p one Mode A).
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use tauri::State;
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::profiles::{self, ProfileSettings, ProfileState};
 
 // =============================================================================
 // Anime4K Shader Name Constants
@@ -23,8 +38,39 @@ pub mod shaders {
     pub const UPSCALE_DENOISE_CNN_X2_VL: &str = "Anime4K_Upscale_Denoise_CNN_x2_VL";
     pub const AUTO_DOWNSCALE_PRE_X2: &str = "Anime4K_AutoDownscalePre_x2";
     pub const AUTO_DOWNSCALE_PRE_X4: &str = "Anime4K_AutoDownscalePre_x4";
+
+    /// Every shader used by a preset (the files bundled in `resources/anime4k`)
+    pub const ALL: &[&str] = &[
+        CLAMP_HIGHLIGHTS,
+        RESTORE_CNN_S,
+        RESTORE_CNN_SOFT_M,
+        UPSCALE_CNN_X2_S,
+        UPSCALE_CNN_X2_M,
+        UPSCALE_CNN_X2_L,
+        UPSCALE_DENOISE_CNN_X2_VL,
+        AUTO_DOWNSCALE_PRE_X2,
+        AUTO_DOWNSCALE_PRE_X4,
+    ];
 }
 
+/// Store file for Anime4K configuration
+const ANIME4K_STORE_FILE: &str = "anime4k.json";
+
+/// Store key for the global default
+const GLOBAL_CONFIG_KEY: &str = "global";
+
+/// Store key for the per-anime overrides
+const ANIME_OVERRIDES_KEY: &str = "animeOverrides";
+
+/// Folder of the bundled shaders, relative to the resource directory
+const SHADER_RESOURCE_DIR: &str = "resources/anime4k";
+
+/// Separator of mpv path lists (`--glsl-shaders=a.glsl:b.glsl`)
+#[cfg(windows)]
+const MPV_PATH_LIST_SEPARATOR: &str = ";";
+#[cfg(not(windows))]
+const MPV_PATH_LIST_SEPARATOR: &str = ":";
+
 /// Performance level for Anime4K presets
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -73,7 +119,7 @@ pub struct PerformanceRequirements {
 }
 
 /// Anime4K configuration state
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Anime4KConfig {
     /// Whether Anime4K is enabled
@@ -94,6 +140,35 @@ impl Default for Anime4KConfig {
     }
 }
 
+impl Anime4KConfig {
+    /// A configuration with its CSS filter filled in
+    pub fn new(enabled: bool, preset_id: &str) -> Self {
+        Anime4KConfig {
+            enabled,
+            preset_id: preset_id.to_string(),
+            css_filter: enabled.then(|| get_css_filter(preset_id)),
+        }
+    }
+}
+
+/// Layer an Anime4K configuration comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigLayer {
+    Global,
+    Profile,
+    Anime,
+}
+
+/// The configuration in effect and the layer it comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedAnime4KConfig {
+    #[serde(flatten)]
+    pub config: Anime4KConfig,
+    pub source: ConfigLayer,
+}
+
 /// GPU information detected from the system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -108,15 +183,36 @@ pub struct GpuInfo {
     pub webgl2_support: bool,
 }
 
+/// Shader sources of a preset, for the webview renderer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Anime4KShaderChain {
+    /// Preset ID
+    pub preset_id: String,
+    /// Shader names in the order they run (a shader can run more than once)
+    pub shaders: Vec<String>,
+    /// GLSL source by shader name
+    pub sources: BTreeMap<String, String>,
+}
+
 /// Application state for Anime4K
 pub struct Anime4KState {
+    /// Global default
     pub config: Mutex<Anime4KConfig>,
+    /// Overrides by anime ID
+    anime_overrides: Mutex<HashMap<String, Anime4KConfig>>,
+    /// Loaded shader sources by name
+    shader_sources: Mutex<HashMap<String, String>>,
+    loaded: Mutex<bool>,
 }
 
 impl Default for Anime4KState {
     fn default() -> Self {
         Anime4KState {
             config: Mutex::new(Anime4KConfig::default()),
+            anime_overrides: Mutex::new(HashMap::new()),
+            shader_sources: Mutex::new(HashMap::new()),
+            loaded: Mutex::new(false),
         }
     }
 }
@@ -311,6 +407,281 @@ pub fn recommend_preset(gpu_info: Option<&GpuInfo>) -> String {
     }
 }
 
+// =============================================================================
+// Configuration Layers
+// =============================================================================
+
+/// Resolve the configuration in effect: anime override, then the profile's preset,
+/// then the global default
+pub fn resolve_config(
+    global: &Anime4KConfig,
+    profile: Option<&ProfileSettings>,
+    anime_override: Option<&Anime4KConfig>,
+) -> ResolvedAnime4KConfig {
+    let profile_preset = profile.and_then(|settings| settings.anime4k_preset.as_deref());
+    let (enabled, preset_id, source) = match (anime_override, profile_preset) {
+        (Some(config), _) => (config.enabled, config.preset_id.as_str(), ConfigLayer::Anime),
+        // Profiles saved without the flag turned Anime4K off with the "none" preset
+        (None, Some(preset)) => (
+            preset != "none" && profile.and_then(|s| s.anime4k_enabled).unwrap_or(true),
+            preset,
+            ConfigLayer::Profile,
+        ),
+        (None, None) => (global.enabled, global.preset_id.as_str(), ConfigLayer::Global),
+    };
+    ResolvedAnime4KConfig {
+        config: Anime4KConfig::new(enabled, preset_id),
+        source,
+    }
+}
+
+/// Load the global default and overrides from the store
+fn ensure_anime4k_loaded(app: &AppHandle, state: &Anime4KState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(ANIME4K_STORE_FILE) {
+        if let Some(value) = store.get(GLOBAL_CONFIG_KEY) {
+            match serde_json::from_value::<Anime4KConfig>(value.clone()) {
+                Ok(config) => *state.config.lock().unwrap() = config,
+                Err(e) => log::warn!("Failed to deserialize Anime4K config: {}", e),
+            }
+        }
+        if let Some(value) = store.get(ANIME_OVERRIDES_KEY) {
+            match serde_json::from_value::<HashMap<String, Anime4KConfig>>(value.clone()) {
+                Ok(overrides) => *state.anime_overrides.lock().unwrap() = overrides,
+                Err(e) => log::warn!("Failed to deserialize Anime4K overrides: {}", e),
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save the global default and overrides to the store
+fn save_anime4k_to_store(app: &AppHandle, state: &Anime4KState) -> Result<(), String> {
+    let store = app
+        .store(ANIME4K_STORE_FILE)
+        .map_err(|e| format!("Failed to open Anime4K store: {}", e))?;
+
+    let global = {
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("Failed to lock config: {}", e))?;
+        serde_json::to_value(&*config).map_err(|e| format!("Failed to serialize Anime4K config: {}", e))?
+    };
+    let overrides = {
+        let overrides = state
+            .anime_overrides
+            .lock()
+            .map_err(|e| format!("Failed to lock Anime4K overrides: {}", e))?;
+        serde_json::to_value(&*overrides).map_err(|e| format!("Failed to serialize Anime4K overrides: {}", e))?
+    };
+
+    store.set(GLOBAL_CONFIG_KEY, global);
+    store.set(ANIME_OVERRIDES_KEY, overrides);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save Anime4K store: {}", e))?;
+
+    Ok(())
+}
+
+/// The given profile, or the active one
+fn target_profile(app: &AppHandle, profile_id: Option<String>) -> Option<String> {
+    profile_id.or_else(|| {
+        app.try_state::<ProfileState>()
+            .and_then(|profile_state| profiles::active_profile_id(app, &profile_state))
+    })
+}
+
+/// A non-empty anime ID
+fn anime_key(anime_id: Option<&str>) -> Result<String, String> {
+    anime_id
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .ok_or_else(|| "An anime ID is required for an anime override".to_string())
+}
+
+/// The configuration in effect for a profile and anime
+fn current_config(
+    app: &AppHandle,
+    state: &Anime4KState,
+    profile_id: Option<String>,
+    anime_id: Option<&str>,
+) -> Result<ResolvedAnime4KConfig, String> {
+    ensure_anime4k_loaded(app, state);
+
+    let profile = target_profile(app, profile_id).and_then(|id| {
+        let profile_state = app.try_state::<ProfileState>()?;
+        profiles::resolve_profile_settings(app, &profile_state, Some(&id))
+    });
+    let global = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock config: {}", e))?
+        .clone();
+    let overrides = state
+        .anime_overrides
+        .lock()
+        .map_err(|e| format!("Failed to lock Anime4K overrides: {}", e))?;
+    let anime_override = anime_id.and_then(|id| overrides.get(id.trim()));
+
+    Ok(resolve_config(&global, profile.as_ref(), anime_override))
+}
+
+/// Write a configuration to one layer (`None` clears the layer)
+fn write_config(
+    app: &AppHandle,
+    state: &Anime4KState,
+    layer: ConfigLayer,
+    config: Option<Anime4KConfig>,
+    profile_id: Option<String>,
+    anime_id: Option<&str>,
+) -> Result<(), String> {
+    if let Some(config) = &config {
+        if get_preset_by_id(&config.preset_id).is_none() {
+            return Err(format!("Unknown Anime4K preset: {}", config.preset_id));
+        }
+    }
+    ensure_anime4k_loaded(app, state);
+
+    match layer {
+        ConfigLayer::Global => {
+            *state
+                .config
+                .lock()
+                .map_err(|e| format!("Failed to lock config: {}", e))? = config.unwrap_or_default();
+            save_anime4k_to_store(app, state)
+        }
+        ConfigLayer::Anime => {
+            let key = anime_key(anime_id)?;
+            {
+                let mut overrides = state
+                    .anime_overrides
+                    .lock()
+                    .map_err(|e| format!("Failed to lock Anime4K overrides: {}", e))?;
+                match config {
+                    Some(config) => overrides.insert(key, config),
+                    None => overrides.remove(&key),
+                };
+            }
+            save_anime4k_to_store(app, state)
+        }
+        ConfigLayer::Profile => {
+            let profile_id =
+                target_profile(app, profile_id).ok_or_else(|| "No active profile".to_string())?;
+            let profile_state = app
+                .try_state::<ProfileState>()
+                .ok_or_else(|| "Profiles are not available".to_string())?;
+            profiles::update_profile_settings(app, &profile_state, &profile_id, |settings| {
+                settings.anime4k_enabled = config.as_ref().map(|c| c.enabled);
+                settings.anime4k_preset = config.map(|c| c.preset_id);
+            })
+            .map(|_| ())
+        }
+    }
+}
+
+// =============================================================================
+// Shader Pipeline
+// =============================================================================
+
+/// File name of a bundled shader
+pub fn shader_file_name(shader: &str) -> String {
+    format!("{}.glsl", shader)
+}
+
+/// Shaders of a preset in the order they run
+fn preset_shaders(preset_id: &str) -> Result<Vec<String>, String> {
+    get_preset_by_id(preset_id)
+        .map(|p| p.shaders)
+        .ok_or_else(|| format!("Unknown Anime4K preset: {}", preset_id))
+}
+
+/// Paths of a preset's shaders in `dir` (every file must exist)
+pub fn shader_paths(preset_id: &str, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    preset_shaders(preset_id)?
+        .iter()
+        .map(|shader| {
+            let path = dir.join(shader_file_name(shader));
+            if path.is_file() {
+                Ok(path)
+            } else {
+                Err(format!(
+                    "Anime4K shader {} is not bundled (run scripts/fetch-anime4k-shaders.mjs)",
+                    shader
+                ))
+            }
+        })
+        .collect()
+}
+
+/// mpv arguments applying a preset (none for "Off")
+pub fn mpv_args(preset_id: &str, dir: &Path) -> Result<Vec<String>, String> {
+    let paths = shader_paths(preset_id, dir)?;
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let chain: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+    Ok(vec![format!("--glsl-shaders={}", chain.join(MPV_PATH_LIST_SEPARATOR))])
+}
+
+/// Read a bundled shader
+pub fn read_shader(dir: &Path, shader: &str) -> Result<String, String> {
+    // Only known names, so a name can't point outside the folder
+    if !shaders::ALL.contains(&shader) {
+        return Err(format!("Unknown Anime4K shader: {}", shader));
+    }
+    let path = dir.join(shader_file_name(shader));
+    let source = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Anime4K shader {}: {}", shader, e))?;
+    if !source.contains("//!HOOK") {
+        return Err(format!("{} is not an mpv user shader", path.display()));
+    }
+    Ok(source)
+}
+
+/// Shader sources of a preset, reading shaders missing from `cache`
+pub fn load_shader_chain(
+    preset_id: &str,
+    dir: &Path,
+    cache: &mut HashMap<String, String>,
+) -> Result<Anime4KShaderChain, String> {
+    let shaders = preset_shaders(preset_id)?;
+    let mut sources = BTreeMap::new();
+    for shader in &shaders {
+        if sources.contains_key(shader) {
+            continue;
+        }
+        let source = match cache.get(shader) {
+            Some(source) => source.clone(),
+            None => {
+                let source = read_shader(dir, shader)?;
+                cache.insert(shader.clone(), source.clone());
+                source
+            }
+        };
+        sources.insert(shader.clone(), source);
+    }
+    Ok(Anime4KShaderChain {
+        preset_id: preset_id.to_string(),
+        shaders,
+        sources,
+    })
+}
+
+/// Folder of the bundled shaders
+fn shader_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .resolve(SHADER_RESOURCE_DIR, BaseDirectory::Resource)
+        .map_err(|e| format!("Failed to resolve Anime4K shader folder: {}", e))
+}
+
 // =============================================================================
 // Tauri Commands
 // =============================================================================
@@ -334,80 +705,107 @@ pub fn anime4k_get_requirements(preset_id: String) -> Option<PerformanceRequirem
         .map(|p| get_performance_requirements(p.performance))
 }
 
-/// Get the current Anime4K configuration
+/// Get the Anime4K configuration in effect for a profile (the active one by default)
+/// and, optionally, an anime
 #[tauri::command]
-pub fn anime4k_get_config(state: State<'_, Anime4KState>) -> Result<Anime4KConfig, String> {
-    state
-        .config
-        .lock()
-        .map(|config| config.clone())
-        .map_err(|e| format!("Failed to lock config: {}", e))
+pub fn anime4k_get_config(
+    profile_id: Option<String>,
+    anime_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, Anime4KState>,
+) -> Result<ResolvedAnime4KConfig, String> {
+    current_config(&app, &state, profile_id, anime_id.as_deref())
 }
 
-/// Set the Anime4K configuration
+/// Set the Anime4K configuration on one layer. Without a scope, the profile's preset is
+/// set when a profile is active and the global default otherwise.
 #[tauri::command]
 pub fn anime4k_set_config(
     enabled: bool,
     preset_id: String,
+    scope: Option<ConfigLayer>,
+    profile_id: Option<String>,
+    anime_id: Option<String>,
+    app: AppHandle,
     state: State<'_, Anime4KState>,
-) -> Result<Anime4KConfig, String> {
-    let mut config = state
-        .config
-        .lock()
-        .map_err(|e| format!("Failed to lock config: {}", e))?;
-    
-    config.enabled = enabled;
-    config.preset_id = preset_id.clone();
-    config.css_filter = if enabled {
-        Some(get_css_filter(&preset_id))
+) -> Result<ResolvedAnime4KConfig, String> {
+    let profile_id = target_profile(&app, profile_id);
+    let scope = scope.unwrap_or(if profile_id.is_some() {
+        ConfigLayer::Profile
     } else {
-        None
-    };
+        ConfigLayer::Global
+    });
+    let config = Anime4KConfig::new(enabled, &preset_id);
+    write_config(&app, &state, scope, Some(config), profile_id.clone(), anime_id.as_deref())?;
     
-    log::info!("Anime4K config updated: enabled={}, preset={}", enabled, preset_id);
+    log::info!("Anime4K config updated: enabled={}, preset={}, scope={:?}", enabled, preset_id, scope);
     
-    Ok(config.clone())
+    current_config(&app, &state, profile_id, anime_id.as_deref())
+}
+
+/// Clear one layer (an anime override, the profile's preset, or the global default),
+/// so the next layer applies
+#[tauri::command]
+pub fn anime4k_clear_config(
+    scope: ConfigLayer,
+    profile_id: Option<String>,
+    anime_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, Anime4KState>,
+) -> Result<ResolvedAnime4KConfig, String> {
+    let profile_id = target_profile(&app, profile_id);
+    write_config(&app, &state, scope, None, profile_id.clone(), anime_id.as_deref())?;
+    current_config(&app, &state, profile_id, anime_id.as_deref())
+}
+
+/// Get the per-anime overrides
+#[tauri::command]
+pub fn anime4k_get_overrides(
+    app: AppHandle,
+    state: State<'_, Anime4KState>,
+) -> Result<HashMap<String, Anime4KConfig>, String> {
+    ensure_anime4k_loaded(&app, &state);
+    state
+        .anime_overrides
+        .lock()
+        .map(|overrides| overrides.clone())
+        .map_err(|e| format!("Failed to lock Anime4K overrides: {}", e))
 }
 
-/// Enable or disable Anime4K
+/// Enable or disable Anime4K on the layer the configuration in effect comes from
 #[tauri::command]
 pub fn anime4k_toggle(
     enabled: bool,
+    profile_id: Option<String>,
+    anime_id: Option<String>,
+    app: AppHandle,
     state: State<'_, Anime4KState>,
-) -> Result<Anime4KConfig, String> {
-    let mut config = state
-        .config
-        .lock()
-        .map_err(|e| format!("Failed to lock config: {}", e))?;
+) -> Result<ResolvedAnime4KConfig, String> {
+    let profile_id = target_profile(&app, profile_id);
+    let current = current_config(&app, &state, profile_id.clone(), anime_id.as_deref())?;
     
-    config.enabled = enabled;
-    if enabled && config.preset_id == "none" {
-        config.preset_id = "mode-b".to_string();
+    let mut preset_id = current.config.preset_id;
+    if enabled && preset_id == "none" {
+        preset_id = "mode-b".to_string();
     }
-    config.css_filter = if enabled {
-        Some(get_css_filter(&config.preset_id))
-    } else {
-        None
-    };
+    let config = Anime4KConfig::new(enabled, &preset_id);
+    write_config(&app, &state, current.source, Some(config), profile_id.clone(), anime_id.as_deref())?;
     
-    log::info!("Anime4K toggled: enabled={}", enabled);
+    log::info!("Anime4K toggled: enabled={}, scope={:?}", enabled, current.source);
     
-    Ok(config.clone())
+    current_config(&app, &state, profile_id, anime_id.as_deref())
 }
 
-/// Get CSS filter for current config
+/// Get CSS filter for the configuration in effect
 #[tauri::command]
-pub fn anime4k_get_css_filter(state: State<'_, Anime4KState>) -> Result<String, String> {
-    let config = state
-        .config
-        .lock()
-        .map_err(|e| format!("Failed to lock config: {}", e))?;
-    
-    if config.enabled {
-        Ok(get_css_filter(&config.preset_id))
-    } else {
-        Ok("none".to_string())
-    }
+pub fn anime4k_get_css_filter(
+    profile_id: Option<String>,
+    anime_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, Anime4KState>,
+) -> Result<String, String> {
+    let current = current_config(&app, &state, profile_id, anime_id.as_deref())?;
+    Ok(current.config.css_filter.unwrap_or_else(|| "none".to_string()))
 }
 
 /// Get recommended preset based on provided GPU info
@@ -416,6 +814,27 @@ pub fn anime4k_recommend_preset(gpu_info: Option<GpuInfo>) -> String {
     recommend_preset(gpu_info.as_ref())
 }
 
+/// Get the mpv arguments applying a preset with the bundled shaders
+#[tauri::command]
+pub fn anime4k_get_mpv_args(app: AppHandle, preset_id: String) -> Result<Vec<String>, String> {
+    mpv_args(&preset_id, &shader_dir(&app)?)
+}
+
+/// Get the GLSL sources of a preset for the webview renderer
+#[tauri::command]
+pub fn anime4k_get_shader_sources(
+    app: AppHandle,
+    preset_id: String,
+    state: State<'_, Anime4KState>,
+) -> Result<Anime4KShaderChain, String> {
+    let dir = shader_dir(&app)?;
+    let mut cache = state
+        .shader_sources
+        .lock()
+        .map_err(|e| format!("Failed to lock shader cache: {}", e))?;
+    load_shader_chain(&preset_id, &dir, &mut cache)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -474,4 +893,109 @@ mod tests {
         let low_reqs = get_performance_requirements(PerformanceLevel::Low);
         assert_eq!(low_reqs.min_vram_gb, 1);
     }
+
+    #[test]
+    fn test_resolve_config() {
+        let global = Anime4KConfig::new(true, "mode-b");
+
+        let resolved = resolve_config(&global, None, None);
+        assert_eq!(resolved.source, ConfigLayer::Global);
+        assert_eq!(resolved.config, global);
+
+        // The profile's preset beats the global default
+        let mut profile = ProfileSettings {
+            anime4k_preset: Some("mode-c".to_string()),
+            ..ProfileSettings::default()
+        };
+        let resolved = resolve_config(&global, Some(&profile), None);
+        assert_eq!(resolved.source, ConfigLayer::Profile);
+        assert_eq!(resolved.config, Anime4KConfig::new(true, "mode-c"));
+
+        // Turning it off keeps the preset
+        profile.anime4k_enabled = Some(false);
+        let resolved = resolve_config(&global, Some(&profile), None);
+        assert_eq!(resolved.source, ConfigLayer::Profile);
+        assert_eq!(resolved.config, Anime4KConfig::new(false, "mode-c"));
+        assert_eq!(resolved.config.css_filter, None);
+        profile.anime4k_enabled = Some(true);
+        assert!(resolve_config(&global, Some(&profile), None).config.enabled);
+
+        // Profiles saved before the flag turned it off with "none"
+        let legacy = ProfileSettings {
+            anime4k_preset: Some("none".to_string()),
+            ..ProfileSettings::default()
+        };
+        assert!(!resolve_config(&global, Some(&legacy), None).config.enabled);
+
+        // An anime override beats both
+        let old_show = Anime4KConfig::new(true, "mode-a+a");
+        let resolved = resolve_config(&global, Some(&profile), Some(&old_show));
+        assert_eq!(resolved.source, ConfigLayer::Anime);
+        assert_eq!(resolved.config.preset_id, "mode-a+a");
+        assert_eq!(resolved.config.css_filter.as_deref(), Some(get_css_filter("mode-a+a").as_str()));
+
+        // Stored configs from before the layers still load, and the layer is flattened in
+        let stored: Anime4KConfig =
+            serde_json::from_str(r#"{"enabled":true,"presetId":"mode-a","cssFilter":null}"#).unwrap();
+        let value = serde_json::to_value(resolve_config(&stored, None, None)).unwrap();
+        assert_eq!(value["presetId"], "mode-a");
+        assert_eq!(value["source"], "global");
+    }
+
+    /// A shader folder with stand-in hooks for every bundled shader
+    fn shader_dir_fixture(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zanshin-anime4k-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for shader in shaders::ALL {
+            let source = format!("//!DESC {}\n//!HOOK MAIN\n//!BIND HOOKED\nvec4 hook() {{ return HOOKED_tex(HOOKED_pos); }}\n", shader);
+            std::fs::write(dir.join(shader_file_name(shader)), source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_mpv_args() {
+        let dir = shader_dir_fixture("mpv");
+        let args = mpv_args("mode-a+a", &dir).unwrap();
+        assert_eq!(args.len(), 1);
+        let chain: Vec<&str> = args[0]
+            .strip_prefix("--glsl-shaders=")
+            .unwrap()
+            .split(MPV_PATH_LIST_SEPARATOR)
+            .collect();
+        let names: Vec<String> = chain
+            .iter()
+            .map(|p| Path::new(p).file_stem().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, get_preset_by_id("mode-a+a").unwrap().shaders);
+
+        assert!(mpv_args("none", &dir).unwrap().is_empty());
+        assert!(mpv_args("mode-z", &dir).is_err());
+
+        std::fs::remove_file(dir.join(shader_file_name(shaders::UPSCALE_CNN_X2_L))).unwrap();
+        assert!(mpv_args("mode-c", &dir).unwrap_err().contains(shaders::UPSCALE_CNN_X2_L));
+        assert!(mpv_args("mode-a", &dir).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shader_sources() {
+        let dir = shader_dir_fixture("sources");
+        let mut cache = HashMap::new();
+        let chain = load_shader_chain("mode-c+a", &dir, &mut cache).unwrap();
+        assert_eq!(chain.shaders.len(), 8);
+        // Shaders that run twice are sent once
+        assert_eq!(chain.sources.len(), 7);
+        assert!(chain.sources[shaders::RESTORE_CNN_S].contains("//!HOOK MAIN"));
+        assert_eq!(cache.len(), 7);
+
+        // Cached sources survive the file going away
+        std::fs::remove_file(dir.join(shader_file_name(shaders::RESTORE_CNN_S))).unwrap();
+        assert!(load_shader_chain("mode-a", &dir, &mut cache).is_ok());
+
+        std::fs::write(dir.join(shader_file_name(shaders::RESTORE_CNN_SOFT_M)), "<html>404</html>").unwrap();
+        assert!(load_shader_chain("mode-b", &dir, &mut cache).unwrap_err().contains("not an mpv user shader"));
+        assert!(read_shader(&dir, "../secrets").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,812 @@
+//! AnimePahe Provider
+//!
+//! Native AnimePahe support, replacing the Express routes of the Node backend
+//! (`backend/animepahe`) and its p.a.c.k.e.r unpacker (`common/unpacker.js`):
+//!
+//! - search (`/api?m=search`), latest releases (`/api?m=airing`) and anime details
+//!   (the `/anime/{session}` page)
+//! - episode lists, one page at a time or all pages (`/api?m=release`)
+//! - streams: the `/play/{anime}/{episode}` page lists one kwik embed per
+//!   fansub/resolution/audio; each embed hides its HLS URL in a packed
+//!   `eval(function(p,a,c,k,e,d){...})` script, which is unpacked with the shared
+//!   `deobfuscate` utilities without running any JavaScript
+//!
+//! AnimePahe sits behind DDoS-Guard, which answers 403 until the client holds its
+//! `__ddg*` cookies. On a 403 the client runs the DDoS-Guard check (`check.js` names a
+//! `/.well-known/ddos-guard/id/...` path on the site, which sets the cookies) and
+//! retries once. When that isn't enough, cookies captured from the browser window
+//! (`open_animepahe`) can be handed over with `animepahe_set_cookies`. Cookies are kept
+//! per host and only sent back to the host that set them; the site's cookies are kept
+//! in the `animepahe.json` store.
+//!
+//! API results keep AnimePahe's field names, which the extension's components read.
+
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tauri_plugin_http::reqwest::{self, header, StatusCode, Url};
+use tauri_plugin_store::StoreExt;
+
+use crate::deobfuscate;
+use crate::plugin_html;
+
+/// Store file name for the site cookies
+const ANIMEPAHE_STORE_FILE: &str = "animepahe.json";
+
+/// Store key for the site cookies
+const COOKIES_KEY: &str = "cookies";
+
+/// AnimePahe site
+const ANIMEPAHE_BASE_URL: &str = "https://animepahe.si";
+
+/// DDoS-Guard check script, which names the path that sets the cookies
+const DDOS_GUARD_CHECK_URL: &str = "https://check.ddos-guard.net/check.js";
+
+/// Request timeout
+const REQUEST_TIMEOUT_SECS: u64 = 20;
+
+/// Most pages fetched for a full episode list
+const MAX_EPISODE_PAGES: u32 = 100;
+
+/// Shown when DDoS-Guard still blocks requests after the check
+const BLOCKED_MESSAGE: &str =
+    "AnimePahe is blocking requests (DDoS-Guard). Open AnimePahe in the browser window until it loads, then try again";
+
+/// A page of an AnimePahe API listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiPage<T> {
+    #[serde(default)]
+    pub total: u32,
+    #[serde(default)]
+    pub per_page: u32,
+    #[serde(default = "default_page")]
+    pub current_page: u32,
+    #[serde(default = "default_page")]
+    pub last_page: u32,
+    #[serde(default = "Vec::new")]
+    pub data: Vec<T>,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+/// A search result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub session: String,
+    pub title: String,
+    #[serde(default)]
+    pub poster: Option<String>,
+    /// `type`, `episodes`, `status`, `season`, `year`, `score`, ...
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// An episode of an anime (or of the latest releases, with `anime_session` and
+/// `anime_title`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Episode {
+    pub session: String,
+    pub episode: f64,
+    #[serde(default)]
+    pub snapshot: Option<String>,
+    /// `anime_id`, `episode2`, `audio`, `duration`, `created_at`, ...
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// All episodes of an anime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeList {
+    pub total: u32,
+    pub episodes: Vec<Episode>,
+}
+
+/// Details from an anime page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeDetails {
+    pub title: Option<String>,
+    pub cover: Option<String>,
+    pub description: Option<String>,
+    pub anilist_id: Option<u64>,
+    pub mal_id: Option<u64>,
+}
+
+/// A kwik embed listed on a play page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedSource {
+    pub url: String,
+    pub fansub: Option<String>,
+    pub resolution: Option<String>,
+    /// `jpn` or `eng`
+    pub audio: Option<String>,
+}
+
+/// A playable stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaheStream {
+    /// HLS playlist
+    pub video_src: String,
+    pub fansub: Option<String>,
+    pub resolution: Option<String>,
+    pub audio: Option<String>,
+    /// Headers the playlist and segments must be requested with
+    pub headers: HashMap<String, String>,
+}
+
+/// A cookie (as captured from the browser window)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+}
+
+/// HTTP client for AnimePahe with its cookie jar
+pub struct AnimePaheClient {
+    http: reqwest::Client,
+    base_url: Url,
+    ddos_guard_check_url: String,
+    /// Cookies by the host that set them
+    cookies: Mutex<HashMap<String, BTreeMap<String, String>>>,
+}
+
+/// AnimePahe state: the client (or why it couldn't be created) and whether the
+/// stored cookies were loaded
+pub struct AnimePaheState {
+    client: Result<AnimePaheClient, String>,
+    loaded: Mutex<bool>,
+}
+
+impl Default for AnimePaheState {
+    fn default() -> Self {
+        AnimePaheState {
+            client: AnimePaheClient::new(ANIMEPAHE_BASE_URL, DDOS_GUARD_CHECK_URL),
+            loaded: Mutex::new(false),
+        }
+    }
+}
+
+impl AnimePaheState {
+    fn client(&self) -> Result<&AnimePaheClient, String> {
+        self.client.as_ref().map_err(Clone::clone)
+    }
+}
+
+// =============================================================================
+// Page Parsing
+// =============================================================================
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("valid selector")
+}
+
+/// Number after a prefix in a link, e.g. `154587` in `//anilist.co/anime/154587`
+fn id_after(href: &str, prefix: &str) -> Option<u64> {
+    let start = href.find(prefix)? + prefix.len();
+    let digits: String = href[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Parse an anime page
+pub fn parse_anime_page(html: &str) -> AnimeDetails {
+    let document = Html::parse_document(html);
+    let meta = |attribute: &str, name: &str| {
+        document
+            .select(&selector(&format!("meta[{}=\"{}\"]", attribute, name)))
+            .next()
+            .and_then(|m| m.value().attr("content"))
+            .map(plugin_html::normalize_text)
+            .filter(|s| !s.is_empty())
+    };
+    let first_text = |css: &str| {
+        document
+            .select(&selector(css))
+            .next()
+            .map(plugin_html::element_text)
+            .filter(|s| !s.is_empty())
+    };
+
+    let title = first_text(".title-wrapper h1 span")
+        .or_else(|| meta("property", "og:title"))
+        .or_else(|| first_text("title").map(|t| t.trim_end_matches(":: animepahe").trim().to_string()));
+    let cover = document
+        .select(&selector(".anime-poster a"))
+        .next()
+        .and_then(|a| a.value().attr("href"))
+        .map(String::from)
+        .or_else(|| meta("property", "og:image"));
+    let description = first_text(".anime-synopsis")
+        .or_else(|| meta("name", "description"))
+        .or_else(|| meta("property", "og:description"));
+
+    let links: Vec<&str> = document
+        .select(&selector("a[href]"))
+        .filter_map(|a| a.value().attr("href"))
+        .collect();
+    let anilist_id = links
+        .iter()
+        .find_map(|href| id_after(href, "anilist.co/anime/"))
+        .or_else(|| meta("name", "anilist").and_then(|id| id.parse().ok()));
+    let mal_id = links.iter().find_map(|href| id_after(href, "myanimelist.net/anime/"));
+
+    AnimeDetails { title, cover, description, anilist_id, mal_id }
+}
+
+/// The kwik embeds of a play page (buttons without a source are skipped)
+pub fn parse_play_page(html: &str) -> Vec<EmbedSource> {
+    let document = Html::parse_document(html);
+    let mut sources: Vec<EmbedSource> = Vec::new();
+    for button in document.select(&selector("button[data-src]")) {
+        let element = button.value();
+        let url = element.attr("data-src").unwrap_or("").trim();
+        if !url.starts_with("http") || sources.iter().any(|s| s.url == url) {
+            continue;
+        }
+        let attr = |name: &str| element.attr(name).map(str::trim).filter(|v| !v.is_empty()).map(String::from);
+        sources.push(EmbedSource {
+            url: url.to_string(),
+            fansub: attr("data-fansub"),
+            resolution: attr("data-resolution"),
+            audio: attr("data-audio"),
+        });
+    }
+    sources
+}
+
+/// The HLS URL of a kwik embed page
+pub fn parse_kwik_page(html: &str) -> Result<String, String> {
+    deobfuscate::unpack_all(html)
+        .iter()
+        .find_map(|code| deobfuscate::js_url_value(code, "source"))
+        .ok_or_else(|| "No stream URL in the embed".to_string())
+}
+
+/// The path the DDoS-Guard check script sends the browser to
+pub fn parse_ddos_guard_check(script: &str) -> Option<String> {
+    let start = script.find("/.well-known/ddos-guard/")?;
+    let path: String = script[start..]
+        .chars()
+        .take_while(|c| !matches!(c, '\'' | '"' | '`' | ' ' | ';' | ')'))
+        .collect();
+    Some(path)
+}
+
+// =============================================================================
+// Client
+// =============================================================================
+
+impl AnimePaheClient {
+    pub fn new(base_url: &str, ddos_guard_check_url: &str) -> Result<Self, String> {
+        let base_url = Url::parse(base_url).map_err(|e| format!("Invalid AnimePahe URL: {}", e))?;
+        if base_url.host_str().is_none() {
+            return Err(format!("Invalid AnimePahe URL: {} has no host", base_url));
+        }
+        let http = reqwest::Client::builder()
+            .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(AnimePaheClient {
+            http,
+            base_url,
+            ddos_guard_check_url: ddos_guard_check_url.to_string(),
+            cookies: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn site_host(&self) -> &str {
+        self.base_url.host_str().unwrap_or_default()
+    }
+
+    /// The site's cookies
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.cookies
+            .lock()
+            .unwrap()
+            .get(self.site_host())
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| Cookie { name: name.clone(), value: value.clone() })
+            .collect()
+    }
+
+    /// Add cookies for the site
+    pub fn set_cookies(&self, cookies: &[Cookie]) {
+        let mut jar = self.cookies.lock().unwrap();
+        let site = jar.entry(self.site_host().to_string()).or_default();
+        for cookie in cookies {
+            if !cookie.name.is_empty() {
+                site.insert(cookie.name.clone(), cookie.value.clone());
+            }
+        }
+    }
+
+    /// Cookie header for a request to `url`, with the cookies its host set
+    fn cookie_header(&self, url: &Url) -> Option<String> {
+        let jar = self.cookies.lock().unwrap();
+        let cookies = jar.get(url.host_str()?)?;
+        (!cookies.is_empty()).then(|| {
+            cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+
+    /// Keep the cookies a response sets, for the host that sent it
+    fn store_cookies(&self, response: &reqwest::Response) {
+        let Some(host) = response.url().host_str() else {
+            return;
+        };
+        let mut jar = self.cookies.lock().unwrap();
+        let jar = jar.entry(host.to_string()).or_default();
+        for value in response.headers().get_all(header::SET_COOKIE) {
+            let Some(pair) = value.to_str().ok().and_then(|v| v.split(';').next()) else {
+                continue;
+            };
+            if let Some((name, value)) = pair.split_once('=') {
+                let name = name.trim();
+                if !name.is_empty() {
+                    jar.insert(name.to_string(), value.trim().to_string());
+                }
+            }
+        }
+    }
+
+    fn url(&self, path: &str) -> Result<Url, String> {
+        self.base_url.join(path).map_err(|e| format!("Invalid AnimePahe URL: {}", e))
+    }
+
+    async fn send(&self, url: &Url, referer: Option<&str>) -> Result<(StatusCode, String), String> {
+        let mut request = self.http.get(url.clone());
+        if let Some(cookies) = self.cookie_header(url) {
+            request = request.header(header::COOKIE, cookies);
+        }
+        if let Some(referer) = referer {
+            request = request.header(header::REFERER, referer);
+        }
+        let response = request.send().await.map_err(|e| format!("Request failed: {}", e))?;
+        self.store_cookies(&response);
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+        Ok((status, body))
+    }
+
+    /// Run the DDoS-Guard check to get its cookies
+    async fn solve_ddos_guard(&self) -> Result<(), String> {
+        let check_url = Url::parse(&self.ddos_guard_check_url).map_err(|e| format!("Invalid check URL: {}", e))?;
+        let referer = self.base_url.to_string();
+        let (status, script) = self.send(&check_url, Some(&referer)).await?;
+        if !status.is_success() {
+            return Err(format!("DDoS-Guard check failed with status {}", status));
+        }
+        let path = parse_ddos_guard_check(&script).ok_or_else(|| "Unexpected DDoS-Guard check script".to_string())?;
+        let (status, _) = self.send(&self.url(&path)?, Some(&referer)).await?;
+        if !status.is_success() {
+            return Err(format!("DDoS-Guard check failed with status {}", status));
+        }
+        log::info!("Passed the DDoS-Guard check for {}", self.base_url);
+        Ok(())
+    }
+
+    /// GET a page of the site, passing the DDoS-Guard check when asked to
+    async fn get(&self, url: &Url) -> Result<String, String> {
+        let referer = self.base_url.to_string();
+        let (mut status, mut body) = self.send(url, Some(&referer)).await?;
+        if status == StatusCode::FORBIDDEN {
+            if let Err(e) = self.solve_ddos_guard().await {
+                log::warn!("{}", e);
+                return Err(BLOCKED_MESSAGE.to_string());
+            }
+            (status, body) = self.send(url, Some(&referer)).await?;
+            if status == StatusCode::FORBIDDEN {
+                return Err(BLOCKED_MESSAGE.to_string());
+            }
+        }
+        if !status.is_success() {
+            return Err(format!("AnimePahe request failed with status {}", status));
+        }
+        Ok(body)
+    }
+
+    async fn get_api<T: serde::de::DeserializeOwned>(&self, params: &[(&str, &str)]) -> Result<T, String> {
+        let mut url = self.url("/api")?;
+        url.query_pairs_mut().extend_pairs(params);
+        let body = self.get(&url).await?;
+        serde_json::from_str(&body).map_err(|e| format!("Invalid AnimePahe response: {}", e))
+    }
+
+    pub async fn search(&self, query: &str) -> Result<ApiPage<SearchResult>, String> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(ApiPage { total: 0, per_page: 0, current_page: 1, last_page: 1, data: Vec::new() });
+        }
+        self.get_api(&[("m", "search"), ("q", query)]).await
+    }
+
+    pub async fn latest(&self, page: u32) -> Result<ApiPage<Episode>, String> {
+        self.get_api(&[("m", "airing"), ("page", &page.max(1).to_string())]).await
+    }
+
+    pub async fn details(&self, anime_session: &str) -> Result<AnimeDetails, String> {
+        let body = self.get(&self.url(&format!("/anime/{}", anime_session))?).await?;
+        Ok(parse_anime_page(&body))
+    }
+
+    pub async fn episodes(&self, anime_session: &str, page: u32) -> Result<ApiPage<Episode>, String> {
+        self.get_api(&[
+            ("m", "release"),
+            ("id", anime_session),
+            ("sort", "episode_asc"),
+            ("page", &page.max(1).to_string()),
+        ])
+        .await
+    }
+
+    /// Every episode, walking the pages in order
+    pub async fn all_episodes(&self, anime_session: &str) -> Result<EpisodeList, String> {
+        let mut episodes = Vec::new();
+        let mut page = 1;
+        loop {
+            let result = self.episodes(anime_session, page).await?;
+            episodes.extend(result.data);
+            if result.current_page >= result.last_page || page >= MAX_EPISODE_PAGES {
+                break;
+            }
+            page = result.current_page + 1;
+        }
+        Ok(EpisodeList { total: episodes.len() as u32, episodes })
+    }
+
+    /// Streams of an episode (embeds that fail are skipped)
+    pub async fn streams(&self, anime_session: &str, episode_session: &str) -> Result<Vec<PaheStream>, String> {
+        let play_url = self.url(&format!("/play/{}/{}", anime_session, episode_session))?;
+        let embeds = parse_play_page(&self.get(&play_url).await?);
+        if embeds.is_empty() {
+            return Err("No kwik embeds on the play page".to_string());
+        }
+
+        let referer = self.base_url.to_string();
+        let mut streams = Vec::new();
+        let mut last_error = None;
+        for embed in embeds {
+            let result = match Url::parse(&embed.url) {
+                Ok(url) => match self.send(&url, Some(&referer)).await {
+                    Ok((status, body)) if status.is_success() => parse_kwik_page(&body).map(|src| (url, src)),
+                    Ok((status, _)) => Err(format!("Embed request failed with status {}", status)),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(format!("Invalid embed URL: {}", e)),
+            };
+            match result {
+                Ok((url, video_src)) => {
+                    // The CDN only serves kwik's player
+                    let origin = url.origin().ascii_serialization();
+                    streams.push(PaheStream {
+                        video_src,
+                        fansub: embed.fansub,
+                        resolution: embed.resolution,
+                        audio: embed.audio,
+                        headers: HashMap::from([
+                            ("Referer".to_string(), format!("{}/", origin)),
+                            ("Origin".to_string(), origin),
+                        ]),
+                    });
+                }
+                Err(e) => {
+                    log::warn!("Failed to extract {}: {}", embed.url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if streams.is_empty() {
+            return Err(format!(
+                "No streams could be extracted: {}",
+                last_error.unwrap_or_else(|| "no embeds".to_string())
+            ));
+        }
+        Ok(streams)
+    }
+}
+
+// =============================================================================
+// Persistence Functions
+// =============================================================================
+
+/// Load the stored cookies once
+fn ensure_cookies_loaded(app: &AppHandle, state: &AnimePaheState, client: &AnimePaheClient) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(ANIMEPAHE_STORE_FILE) {
+        if let Some(value) = store.get(COOKIES_KEY) {
+            match serde_json::from_value::<Vec<Cookie>>(value.clone()) {
+                Ok(cookies) => client.set_cookies(&cookies),
+                Err(e) => log::warn!("Failed to deserialize AnimePahe cookies: {}", e),
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save the cookies to the store
+fn save_cookies_to_store(app: &AppHandle, client: &AnimePaheClient) -> Result<(), String> {
+    let store = app
+        .store(ANIMEPAHE_STORE_FILE)
+        .map_err(|e| format!("Failed to open AnimePahe store: {}", e))?;
+    store.set(COOKIES_KEY, serde_json::to_value(client.cookies()).unwrap_or_default());
+    store
+        .save()
+        .map_err(|e| format!("Failed to save AnimePahe store: {}", e))
+}
+
+/// Run a client request (the stored cookies are loaded before it starts), saving the
+/// site's cookies if they changed
+async fn with_client<'a, T, F>(
+    app: &AppHandle,
+    state: &'a AnimePaheState,
+    request: impl FnOnce(&'a AnimePaheClient) -> F,
+) -> Result<T, String>
+where
+    F: std::future::Future<Output = Result<T, String>>,
+{
+    let client = state.client()?;
+    ensure_cookies_loaded(app, state, client);
+    let before = client.cookies();
+    let result = request(client).await;
+    if client.cookies() != before {
+        if let Err(e) = save_cookies_to_store(app, client) {
+            log::warn!("{}", e);
+        }
+    }
+    result
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Search AnimePahe
+#[tauri::command]
+pub async fn animepahe_search(
+    query: String,
+    app: AppHandle,
+    state: State<'_, AnimePaheState>,
+) -> Result<ApiPage<SearchResult>, String> {
+    with_client(&app, &state, |client| client.search(&query)).await
+}
+
+/// Latest releases
+#[tauri::command]
+pub async fn animepahe_latest(
+    page: Option<u32>,
+    app: AppHandle,
+    state: State<'_, AnimePaheState>,
+) -> Result<ApiPage<Episode>, String> {
+    with_client(&app, &state, |client| client.latest(page.unwrap_or(1))).await
+}
+
+/// Details of an anime
+#[tauri::command]
+pub async fn animepahe_details(
+    anime_session: String,
+    app: AppHandle,
+    state: State<'_, AnimePaheState>,
+) -> Result<AnimeDetails, String> {
+    with_client(&app, &state, |client| client.details(&anime_session)).await
+}
+
+/// One page of an anime's episodes
+#[tauri::command]
+pub async fn animepahe_episodes(
+    anime_session: String,
+    page: Option<u32>,
+    app: AppHandle,
+    state: State<'_, AnimePaheState>,
+) -> Result<ApiPage<Episode>, String> {
+    with_client(&app, &state, |client| client.episodes(&anime_session, page.unwrap_or(1))).await
+}
+
+/// All episodes of an anime
+#[tauri::command]
+pub async fn animepahe_all_episodes(
+    anime_session: String,
+    app: AppHandle,
+    state: State<'_, AnimePaheState>,
+) -> Result<EpisodeList, String> {
+    with_client(&app, &state, |client| client.all_episodes(&anime_session)).await
+}
+
+/// Streams of an episode
+#[tauri::command]
+pub async fn animepahe_streams(
+    anime_session: String,
+    episode_session: String,
+    app: AppHandle,
+    state: State<'_, AnimePaheState>,
+) -> Result<Vec<PaheStream>, String> {
+    let streams = with_client(&app, &state, |client| client.streams(&anime_session, &episode_session)).await?;
+    log::info!("Extracted {} AnimePahe streams for {}", streams.len(), episode_session);
+    Ok(streams)
+}
+
+/// Add cookies captured from the browser window
+#[tauri::command]
+pub fn animepahe_set_cookies(
+    cookies: Vec<Cookie>,
+    app: AppHandle,
+    state: State<'_, AnimePaheState>,
+) -> Result<(), String> {
+    let client = state.client()?;
+    ensure_cookies_loaded(&app, &state, client);
+    client.set_cookies(&cookies);
+    save_cookies_to_store(&app, client)
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, TestResponse};
+
+    const ANIME_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/anime.html");
+    const PLAY_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/play.html");
+    const KWIK_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/kwik_embed.html");
+    const SEARCH_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/search.json");
+    const RELEASE_PAGE1_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/release_page1.json");
+    const RELEASE_PAGE2_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/release_page2.json");
+    const DDOS_GUARD_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/ddos_guard_check.js");
+
+    /// Serves the fixtures like AnimePahe behind DDoS-Guard: the site answers 403
+    /// until the request carries the `__ddg2_` cookie from the check path
+    fn mock_site() -> String {
+        serve(|request| {
+            let passed = request.header("cookie").is_some_and(|c| c.contains("__ddg2_=Xk2m9"));
+            match request.path.as_str() {
+                "/check.js" => TestResponse::ok(DDOS_GUARD_FIXTURE),
+                "/.well-known/ddos-guard/id/WaEVx0qDRGWM3Vad" => {
+                    TestResponse::ok("").header("Set-Cookie", "__ddg2_=Xk2m9; Path=/; HttpOnly")
+                }
+                _ if !passed => TestResponse::status("403 Forbidden").header("Server", "ddos-guard"),
+                "/api?m=release&id=frieren&sort=episode_asc&page=1" => TestResponse::ok(RELEASE_PAGE1_FIXTURE),
+                "/api?m=release&id=frieren&sort=episode_asc&page=2" => TestResponse::ok(RELEASE_PAGE2_FIXTURE),
+                "/play/frieren/ep1" => {
+                    let kwik_url = format!("http://{}/e/", request.header("host").unwrap_or_default());
+                    TestResponse::ok(PLAY_FIXTURE.replace("https://kwik.si/e/", &kwik_url))
+                }
+                // One embed is broken
+                "/e/Hn7xB4pTq9Mc" => TestResponse::ok("<html></html>"),
+                p if p.starts_with("/e/") => TestResponse::ok(KWIK_FIXTURE),
+                _ => TestResponse::status("404 Not Found"),
+            }
+        })
+    }
+
+    #[test]
+    fn test_parse_kwik_page() {
+        let url = parse_kwik_page(KWIK_FIXTURE).unwrap();
+        assert_eq!(
+            url,
+            "https://vault-12.owocdn.top/stream/12/04/4f1c0a9e7d2b6c3a8e5f9b0d1c2a3e4f/uwu.m3u8"
+        );
+        assert!(parse_kwik_page("<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_parse_pages() {
+        let details = parse_anime_page(ANIME_FIXTURE);
+        assert_eq!(details.title.as_deref(), Some("Sousou no Frieren"));
+        assert_eq!(details.anilist_id, Some(154587));
+        assert_eq!(details.mal_id, Some(52991));
+        assert!(details.cover.unwrap().starts_with("https://i.animepahe.si/posters/1c0bd3d8"));
+        assert!(details.description.unwrap().starts_with("The adventure is over"));
+
+        let embeds = parse_play_page(PLAY_FIXTURE);
+        assert_eq!(embeds.len(), 4);
+        assert_eq!(embeds[0].url, "https://kwik.si/e/Gq3vW8nZr2Lk");
+        assert_eq!(embeds[0].resolution.as_deref(), Some("360"));
+        assert_eq!(embeds[3].fansub.as_deref(), Some("Yameii"));
+        assert_eq!(embeds[3].audio.as_deref(), Some("eng"));
+
+        // Fallbacks when the page layout changes
+        let minimal = "<html><head><title>Dandadan :: animepahe</title><meta name=\"anilist\" content=\"171018\"></head></html>";
+        let details = parse_anime_page(minimal);
+        assert_eq!(details.title.as_deref(), Some("Dandadan"));
+        assert_eq!(details.anilist_id, Some(171018));
+    }
+
+    #[test]
+    fn test_api_fixtures() {
+        let search: ApiPage<SearchResult> = serde_json::from_str(SEARCH_FIXTURE).unwrap();
+        assert_eq!(search.data.len(), 2);
+        assert_eq!(search.data[0].session, "2b1f4c6e-3d5a-7b9c-1e2f-4a6b8c0d2e4f");
+        assert_eq!(search.data[0].extra["year"], 2023);
+
+        // Unknown fields are passed through under their AnimePahe names
+        let page: ApiPage<Episode> = serde_json::from_str(RELEASE_PAGE1_FIXTURE).unwrap();
+        assert_eq!((page.current_page, page.last_page), (1, 2));
+        let value = serde_json::to_value(&page.data[0]).unwrap();
+        assert_eq!(value["anime_id"], 5423);
+        assert_eq!(value["episode"], 1.0);
+
+        assert_eq!(
+            parse_ddos_guard_check(DDOS_GUARD_FIXTURE).as_deref(),
+            Some("/.well-known/ddos-guard/id/WaEVx0qDRGWM3Vad")
+        );
+    }
+
+    #[test]
+    fn test_ddos_guard_and_pagination() {
+        let base = mock_site();
+        let client = AnimePaheClient::new(&base, &format!("{}/check.js", base)).unwrap();
+
+        let list = tauri::async_runtime::block_on(client.all_episodes("frieren")).unwrap();
+        assert_eq!(list.total, 3);
+        let numbers: Vec<f64> = list.episodes.iter().map(|e| e.episode).collect();
+        assert_eq!(numbers, [1.0, 2.0, 3.0]);
+        assert_eq!(
+            client.cookies(),
+            [Cookie { name: "__ddg2_".to_string(), value: "Xk2m9".to_string() }]
+        );
+
+        let streams = tauri::async_runtime::block_on(client.streams("frieren", "ep1")).unwrap();
+        assert_eq!(streams.len(), 3);
+        assert!(streams.iter().all(|s| s.video_src.ends_with("/uwu.m3u8")));
+        assert_eq!(streams[0].resolution.as_deref(), Some("360"));
+        assert_eq!(streams[1].resolution.as_deref(), Some("1080"));
+        assert_eq!(streams[0].headers["Referer"], format!("{}/", base));
+    }
+
+    #[test]
+    fn test_blocked_without_check() {
+        let base = mock_site();
+        // The check script can't be found: the block is reported
+        let client = AnimePaheClient::new(&base, &format!("{}/missing.js", base)).unwrap();
+        let error = tauri::async_runtime::block_on(client.details("frieren")).unwrap_err();
+        assert_eq!(error, BLOCKED_MESSAGE);
+
+        // Cookies from the browser window are enough
+        client.set_cookies(&[Cookie { name: "__ddg2_".to_string(), value: "Xk2m9".to_string() }]);
+        let page = tauri::async_runtime::block_on(client.episodes("frieren", 2)).unwrap();
+        assert_eq!(page.data.len(), 1);
+    }
+
+    #[test]
+    fn test_cookies_per_host() {
+        assert!(AnimePaheClient::new("not a url", DDOS_GUARD_CHECK_URL).is_err());
+
+        let client = AnimePaheClient::new(ANIMEPAHE_BASE_URL, DDOS_GUARD_CHECK_URL).unwrap();
+        client.set_cookies(&[
+            Cookie { name: "__ddg2_".to_string(), value: "Xk2m9".to_string() },
+            Cookie { name: "__ddg1_".to_string(), value: "a1".to_string() },
+        ]);
+        let site = Url::parse("https://animepahe.si/api?m=search").unwrap();
+        assert_eq!(client.cookie_header(&site).as_deref(), Some("__ddg1_=a1; __ddg2_=Xk2m9"));
+
+        // The site's cookies never reach the embed host or the check host
+        let embed = Url::parse("https://kwik.si/e/Gq3vW8nZr2Lk").unwrap();
+        assert_eq!(client.cookie_header(&embed), None);
+        assert_eq!(client.cookie_header(&Url::parse(DDOS_GUARD_CHECK_URL).unwrap()), None);
+        assert_eq!(client.cookies().len(), 2);
+    }
+}
@@ -0,0 +1,366 @@
+//! Audio Fingerprinting
+//!
+//! This module computes compact audio fingerprints (one 32-bit hash per 32 ms frame,
+//! following the Haitsma–Kalker scheme) and finds the longest stretch of audio two
+//! fingerprints share. Episodes of the same series share their opening and ending
+//! songs, so the common stretch between two episodes is the intro (or outro).
+//!
+//! Audio is decoded with `ffmpeg` when it is installed (`ZANSHIN_FFMPEG` can point
+//! at a specific binary); the fingerprint itself only needs mono PCM samples.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Sample rate fingerprints are computed at
+pub const SAMPLE_RATE: u32 = 8000;
+
+/// Analysis window (256 ms)
+const FRAME_SIZE: usize = 2048;
+
+/// Step between frames (32 ms)
+const HOP_SIZE: usize = 256;
+
+/// Duration of one fingerprint frame in milliseconds
+pub const FRAME_MS: u64 = HOP_SIZE as u64 * 1000 / SAMPLE_RATE as u64;
+
+/// Number of energy bands (one more than the hash bits)
+const BAND_COUNT: usize = 33;
+
+/// Frequency range of the energy bands
+const MIN_FREQUENCY: f64 = 300.0;
+const MAX_FREQUENCY: f64 = 2000.0;
+
+/// Frames whose hashes differ in at most this many bits are considered equal
+const MAX_BIT_ERRORS: u32 = 10;
+
+/// Unmatched frames tolerated inside a matching stretch (~0.5 s)
+const MAX_GAP_FRAMES: usize = 16;
+
+/// Hashes occurring more often than this are ignored when voting (silence, drones)
+const MAX_HASH_OCCURRENCES: usize = 32;
+
+/// Number of candidate alignments checked in detail
+const CANDIDATE_OFFSETS: usize = 8;
+
+/// Fingerprint of an audio window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fingerprint {
+    /// Position of the first frame in the episode
+    pub offset_ms: u64,
+    pub hashes: Vec<u32>,
+}
+
+/// A stretch of audio found in two fingerprints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FingerprintMatch {
+    pub a_start_ms: u64,
+    pub a_end_ms: u64,
+    pub b_start_ms: u64,
+    pub b_end_ms: u64,
+    /// Share of frames in the stretch that matched (0–1)
+    pub density: f32,
+}
+
+// =============================================================================
+// Fingerprinting
+// =============================================================================
+
+/// In-place radix-2 FFT (`re.len()` must be a power of two)
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        for k in 0..len / 2 {
+            let (sin, cos) = (angle * k as f64).sin_cos();
+            for start in (0..n).step_by(len) {
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// FFT bin boundaries of the logarithmically spaced bands
+fn band_edges() -> Vec<usize> {
+    let ratio = (MAX_FREQUENCY / MIN_FREQUENCY).powf(1.0 / BAND_COUNT as f64);
+    (0..=BAND_COUNT)
+        .map(|i| {
+            let frequency = MIN_FREQUENCY * ratio.powi(i as i32);
+            (frequency * FRAME_SIZE as f64 / SAMPLE_RATE as f64).round() as usize
+        })
+        .collect()
+}
+
+/// Fingerprint mono samples at [`SAMPLE_RATE`]; `offset_ms` is where they start
+pub fn fingerprint(samples: &[f32], offset_ms: u64) -> Fingerprint {
+    let edges = band_edges();
+    let window: Vec<f64> = (0..FRAME_SIZE)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / FRAME_SIZE as f64).cos())
+        .collect();
+
+    let mut hashes = Vec::new();
+    let mut previous: Option<Vec<f64>> = None;
+    let mut re = vec![0.0; FRAME_SIZE];
+    let mut im = vec![0.0; FRAME_SIZE];
+
+    let mut start = 0;
+    while start + FRAME_SIZE <= samples.len() {
+        for i in 0..FRAME_SIZE {
+            re[i] = samples[start + i] as f64 * window[i];
+            im[i] = 0.0;
+        }
+        fft(&mut re, &mut im);
+
+        let energies: Vec<f64> = edges
+            .windows(2)
+            .map(|edge| (edge[0]..edge[1].max(edge[0] + 1)).map(|k| re[k] * re[k] + im[k] * im[k]).sum())
+            .collect();
+
+        if let Some(prev) = &previous {
+            let mut hash = 0u32;
+            for m in 0..BAND_COUNT - 1 {
+                let diff = (energies[m] - energies[m + 1]) - (prev[m] - prev[m + 1]);
+                if diff > 0.0 {
+                    hash |= 1 << m;
+                }
+            }
+            hashes.push(hash);
+        }
+        previous = Some(energies);
+        start += HOP_SIZE;
+    }
+
+    // The first hash belongs to the second frame
+    Fingerprint { offset_ms: offset_ms + FRAME_MS, hashes }
+}
+
+// =============================================================================
+// Matching
+// =============================================================================
+
+/// Find the longest stretch shared by two fingerprints whose length is within
+/// `min_ms..=max_ms`
+pub fn find_common_segment(
+    a: &Fingerprint,
+    b: &Fingerprint,
+    min_ms: u64,
+    max_ms: u64,
+) -> Option<FingerprintMatch> {
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, hash) in a.hashes.iter().enumerate() {
+        index.entry(*hash).or_default().push(i);
+    }
+
+    // Vote for alignments (a index - b index) using exactly matching hashes
+    let mut votes: HashMap<isize, u32> = HashMap::new();
+    for (j, hash) in b.hashes.iter().enumerate() {
+        if let Some(positions) = index.get(hash).filter(|p| p.len() <= MAX_HASH_OCCURRENCES) {
+            for i in positions {
+                *votes.entry(*i as isize - j as isize).or_default() += 1;
+            }
+        }
+    }
+    let mut candidates: Vec<(isize, u32)> = votes.into_iter().filter(|(_, v)| *v >= 3).collect();
+    candidates.sort_by_key(|(offset, votes)| (std::cmp::Reverse(*votes), *offset));
+    candidates.truncate(CANDIDATE_OFFSETS);
+
+    let min_frames = (min_ms / FRAME_MS) as usize;
+    let max_frames = (max_ms / FRAME_MS) as usize;
+    let mut best: Option<(usize, usize, isize, f32)> = None;
+
+    for (offset, _) in candidates {
+        // Aligned range of b
+        let first = (-offset).max(0) as usize;
+        let last = (a.hashes.len() as isize - offset).min(b.hashes.len() as isize);
+        if last <= first as isize {
+            continue;
+        }
+
+        let mut run: Option<(usize, usize, usize)> = None; // (start, last match, matches)
+        let close = |run: (usize, usize, usize), best: &mut Option<(usize, usize, isize, f32)>| {
+            let (start, end, matches) = run;
+            let length = end - start + 1;
+            let density = matches as f32 / length as f32;
+            if length >= min_frames
+                && length <= max_frames
+                && density >= 0.5
+                && best.map_or(true, |(s, e, _, _)| length > e - s + 1)
+            {
+                *best = Some((start, end, offset, density));
+            }
+        };
+
+        for j in first..last as usize {
+            let i = (j as isize + offset) as usize;
+            if (a.hashes[i] ^ b.hashes[j]).count_ones() > MAX_BIT_ERRORS {
+                continue;
+            }
+            run = match run {
+                Some((start, end, matches)) if j - end <= MAX_GAP_FRAMES => Some((start, j, matches + 1)),
+                Some(previous) => {
+                    close(previous, &mut best);
+                    Some((j, j, 1))
+                }
+                None => Some((j, j, 1)),
+            };
+        }
+        if let Some(run) = run {
+            close(run, &mut best);
+        }
+    }
+
+    best.map(|(start, end, offset, density)| {
+        let a_start = (start as isize + offset) as u64;
+        let b_start = start as u64;
+        let frames = (end - start + 1) as u64;
+        FingerprintMatch {
+            a_start_ms: a.offset_ms + a_start * FRAME_MS,
+            a_end_ms: a.offset_ms + (a_start + frames) * FRAME_MS,
+            b_start_ms: b.offset_ms + b_start * FRAME_MS,
+            b_end_ms: b.offset_ms + (b_start + frames) * FRAME_MS,
+            density,
+        }
+    })
+}
+
+// =============================================================================
+// Decoding
+// =============================================================================
+
+/// Locate the `ffmpeg` binary (`ZANSHIN_FFMPEG`, then `PATH`)
+pub fn find_ffmpeg() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("ZANSHIN_FFMPEG").map(PathBuf::from) {
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+    let name = if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Decode a window of a file or URL into mono samples at [`SAMPLE_RATE`]
+/// (blocking; run it on a blocking thread)
+pub fn decode_audio(
+    ffmpeg: &PathBuf,
+    input: &str,
+    start_ms: u64,
+    duration_ms: u64,
+) -> Result<Vec<f32>, String> {
+    let output = Command::new(ffmpeg)
+        .args(["-nostdin", "-hide_banner", "-loglevel", "error"])
+        .args(["-ss", &format!("{:.3}", start_ms as f64 / 1000.0)])
+        .args(["-t", &format!("{:.3}", duration_ms as f64 / 1000.0)])
+        .args(["-i", input])
+        .args(["-vn", "-sn", "-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-f", "s16le", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !output.status.success() && output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed: {}", stderr.lines().last().unwrap_or("unknown error")));
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic noise with a slowly changing spectrum, like music
+    fn audio(seed: u64, seconds: f64) -> Vec<f32> {
+        let mut state = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let count = (seconds * SAMPLE_RATE as f64) as usize;
+        let mut low = 0.0f32;
+        (0..count)
+            .map(|i| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let noise = ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5;
+                // Alternate between brighter and darker sections every 200 ms
+                let mix = if (i / 1600) % 2 == 0 { 0.2 } else { 0.8 };
+                low = low * mix + noise * (1.0 - mix);
+                low + noise * 0.3
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fft_of_sine() {
+        let n = 64;
+        let mut re: Vec<f64> = (0..n).map(|i| (2.0 * std::f64::consts::PI * 4.0 * i as f64 / n as f64).sin()).collect();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+        let magnitudes: Vec<f64> = re.iter().zip(&im).map(|(r, i)| (r * r + i * i).sqrt()).collect();
+        let peak = (0..n / 2).max_by(|a, b| magnitudes[*a].total_cmp(&magnitudes[*b])).unwrap();
+        assert_eq!(peak, 4);
+        assert!((magnitudes[4] - n as f64 / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fingerprint_is_deterministic() {
+        let samples = audio(1, 5.0);
+        let a = fingerprint(&samples, 0);
+        assert_eq!(a, fingerprint(&samples, 0));
+        // One hash per hop after the first full frame
+        assert_eq!(a.hashes.len(), (samples.len() - FRAME_SIZE) / HOP_SIZE);
+        assert_eq!(a.offset_ms, FRAME_MS);
+    }
+
+    #[test]
+    fn test_find_shared_opening() {
+        let opening = audio(42, 30.0);
+        // Positions that don't line up with the frame grid
+        let episode_a = [audio(1, 10.01), opening.clone(), audio(2, 10.0)].concat();
+        let episode_b = [audio(3, 25.0), opening, audio(4, 5.0)].concat();
+
+        let a = fingerprint(&episode_a, 0);
+        let b = fingerprint(&episode_b, 60_000);
+        let found = find_common_segment(&a, &b, 15_000, 150_000).expect("opening not found");
+
+        let close = |actual: u64, expected: u64| (actual as i64 - expected as i64).abs() < 600;
+        assert!(close(found.a_start_ms, 10_010), "{:?}", found);
+        assert!(close(found.a_end_ms, 40_010), "{:?}", found);
+        assert!(close(found.b_start_ms, 85_000), "{:?}", found);
+        assert!(close(found.b_end_ms, 115_000), "{:?}", found);
+    }
+
+    #[test]
+    fn test_unrelated_audio_has_no_match() {
+        let a = fingerprint(&audio(7, 40.0), 0);
+        let b = fingerprint(&audio(8, 40.0), 0);
+        assert_eq!(find_common_segment(&a, &b, 15_000, 150_000), None);
+    }
+}
@@ -0,0 +1,166 @@
+//! Torrent Backend Runtime
+//!
+//! The torrent backend only accepts control requests (seeding, pausing, adding
+//! downloads) from the app. At startup the app generates a random per-launch token
+//! and writes it to `backend-runtime.json` in the app config directory, together
+//! with the folders the backend may read from and save to and the port the media
+//! server actually bound. The backend reads that file, requires the token in the
+//! `x-zanshin-token` header, rejects paths outside the download roots, and forwards
+//! media routes to the media server port.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::downloads::resolve_downloads_folder;
+
+/// File the backend reads its runtime configuration from
+pub const RUNTIME_FILE_NAME: &str = "backend-runtime.json";
+
+/// Header carrying the per-launch token on control requests
+pub const TOKEN_HEADER: &str = "x-zanshin-token";
+
+/// Random bytes in a token (hex-encoded to twice the length)
+const TOKEN_BYTES: usize = 32;
+
+/// Runtime configuration shared with the torrent backend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendRuntime {
+    /// Token the backend requires on control routes
+    pub token: String,
+    /// Folders the backend may seed from and save downloads to
+    pub download_roots: Vec<String>,
+    /// Port of the media server; `None` until it is listening
+    pub media_port: Option<u16>,
+}
+
+/// Backend runtime state: the token of this launch
+pub struct BackendRuntimeState {
+    token: String,
+}
+
+impl Default for BackendRuntimeState {
+    fn default() -> Self {
+        // An empty token is never accepted by the backend, so failing here only
+        // disables the control routes
+        let token = generate_token().unwrap_or_else(|e| {
+            log::error!("Failed to generate backend token: {}", e);
+            String::new()
+        });
+        BackendRuntimeState { token }
+    }
+}
+
+// =============================================================================
+// Helper Functions
+// =============================================================================
+
+/// Generate a random token from the OS CSPRNG, hex-encoded
+pub fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("No system randomness: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Token of this launch
+pub fn token(app: &AppHandle) -> String {
+    app.state::<BackendRuntimeState>().token.clone()
+}
+
+/// Whether `path` lies inside one of `roots`, after resolving symlinks and `..`
+pub fn is_within_roots(path: &Path, roots: &[PathBuf]) -> bool {
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| path.starts_with(root))
+}
+
+/// Folders the backend may seed from and save downloads to
+pub fn download_roots(app: &AppHandle) -> Result<Vec<PathBuf>, String> {
+    Ok(vec![resolve_downloads_folder(app)?])
+}
+
+/// Path of the runtime file
+pub fn runtime_file_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(RUNTIME_FILE_NAME))
+        .map_err(|e| format!("Failed to resolve app config directory: {}", e))
+}
+
+/// Write the runtime file for the backend; call again whenever the download roots
+/// or the media server port change
+pub fn write_runtime_file(app: &AppHandle) -> Result<(), String> {
+    let runtime = BackendRuntime {
+        token: token(app),
+        download_roots: download_roots(app)?
+            .iter()
+            .map(|root| root.to_string_lossy().to_string())
+            .collect(),
+        media_port: crate::media_server::port(app),
+    };
+    let body = serde_json::to_vec_pretty(&runtime)
+        .map_err(|e| format!("Failed to serialize backend runtime: {}", e))?;
+
+    let path = runtime_file_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+    }
+
+    // Write a private temporary file and rename it, so the backend never reads a
+    // partial file and other users can't read the token
+    let temp = path.with_extension("json.tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&temp)
+        .map_err(|e| format!("Failed to write '{}': {}", temp.display(), e))?;
+    file.write_all(&body)
+        .map_err(|e| format!("Failed to write '{}': {}", temp.display(), e))?;
+    drop(file);
+    std::fs::rename(&temp, &path)
+        .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_token() {
+        let a = generate_token().unwrap();
+        let b = generate_token().unwrap();
+        assert_eq!(a.len(), TOKEN_BYTES * 2);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_is_within_roots() {
+        let root = std::env::temp_dir().join(format!("zanshin-roots-{}", std::process::id()));
+        let inside = root.join("Show").join("ep01.mkv");
+        std::fs::create_dir_all(inside.parent().unwrap()).unwrap();
+        std::fs::write(&inside, b"").unwrap();
+        let roots = vec![root.clone()];
+
+        assert!(is_within_roots(&inside, &roots));
+        assert!(is_within_roots(&root, &roots));
+        assert!(!is_within_roots(&root.join("Show").join("..").join(".."), &roots));
+        assert!(!is_within_roots(&root.join("missing.mkv"), &roots));
+        assert!(!is_within_roots(&std::env::temp_dir(), &roots));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
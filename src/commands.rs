@@ -391,7 +391,13 @@ pub async fn save_to_settings(
     store.set("settings", settings_value);
     store.save()
         .map_err(|e| format!("Failed to save settings: {}", e))?;
-    
+    drop(settings);
+
+    // The torrent backend only accepts paths inside the downloads folder
+    if key == "downloadsFolder" {
+        crate::backend_runtime::write_runtime_file(&app)?;
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,387 @@
+//! Deobfuscation Utilities
+//!
+//! Shared helpers for reading stream URLs out of hoster pages without running their
+//! JavaScript:
+//!
+//! - JavaScript string literals and `key = '...'` / `key: "..."` assignments
+//! - Dean Edwards p.a.c.k.e.r scripts (`eval(function(p,a,c,k,e,d){...})`), used by
+//!   kwik, Filemoon and many other players
+//! - the string transforms hosters chain together (ROT13, lenient Base64, character
+//!   shifts, case swaps, reversal, hex decoding)
+//! - URLs embedded in text
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use std::collections::HashMap;
+
+/// Marker of a packed script
+pub const PACKER_MARKER: &str = "eval(function(p,a,c,k,e,";
+
+/// Largest keyword list accepted from a packed script
+const MAX_PACKED_KEYWORDS: usize = 100_000;
+
+/// Base64 that accepts missing or extra padding
+const LENIENT_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+// =============================================================================
+// JavaScript Literals
+// =============================================================================
+
+/// Parse a JavaScript string literal at the start of `input`, returning the string and
+/// the rest of the input
+pub fn parse_js_string(input: &str) -> Option<(String, &str)> {
+    let mut chars = input.char_indices();
+    let (_, quote) = chars.next().filter(|(_, c)| matches!(c, '\'' | '"' | '`'))?;
+    let mut out = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+                match escaped {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    'x' | 'u' => {
+                        let len = if escaped == 'x' { 2 } else { 4 };
+                        let hex: String = (0..len).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                        let decoded = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)?;
+                        out.push(decoded);
+                    }
+                    // Line continuation
+                    '\n' => {}
+                    other => out.push(other),
+                }
+            }
+            c if c == quote => return Some((out, &input[i + c.len_utf8()..])),
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+/// Parse an unsigned integer at the start of `input`
+fn parse_js_number(input: &str) -> Option<(usize, &str)> {
+    let end = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let value = input[..end].parse().ok()?;
+    Some((value, &input[end..]))
+}
+
+/// Skip whitespace and an expected character
+pub fn expect_char(input: &str, expected: char) -> Option<&str> {
+    input.trim_start().strip_prefix(expected).map(str::trim_start)
+}
+
+/// String values assigned to `key` in code, in order (`key = '...'`, `key: "..."`,
+/// `"key": "..."`)
+pub fn js_string_values(code: &str, key: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut offset = 0;
+    while let Some(i) = code[offset..].find(key) {
+        let start = offset + i;
+        offset = start + key.len();
+
+        // Whole identifiers only (`file` must not match `profile`)
+        let before = code[..start].chars().next_back();
+        if before.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+            continue;
+        }
+        let mut rest = &code[offset..];
+        rest = rest.strip_prefix(['"', '\'']).unwrap_or(rest);
+        let Some(value) = expect_char(rest, '=').or_else(|| expect_char(rest, ':')) else {
+            continue;
+        };
+        if value.starts_with('=') {
+            // A comparison
+            continue;
+        }
+        if let Some((value, _)) = parse_js_string(value) {
+            values.push(value);
+        }
+    }
+    values
+}
+
+/// First http(s) URL assigned to `key` in code
+pub fn js_url_value(code: &str, key: &str) -> Option<String> {
+    js_string_values(code, key)
+        .into_iter()
+        .find(|v| v.starts_with("http") || v.starts_with("//"))
+        .map(|v| if v.starts_with("//") { format!("https:{}", v) } else { v })
+}
+
+// =============================================================================
+// Packed JavaScript
+// =============================================================================
+
+/// Encode a keyword index the way the packer's `e(c)` does
+pub fn packer_encode(mut value: usize, radix: usize) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[value % radix]);
+        value /= radix;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+/// Unpack a Dean Edwards p.a.c.k.e.r script
+/// (`eval(function(p,a,c,k,e,d){...}('payload',radix,count,'k|e|y|s'.split('|'),0,{}))`)
+///
+/// Every word of the payload that encodes a keyword index is replaced by that keyword,
+/// which is what the packed function does when evaluated. Radixes above 62 (the
+/// "High ASCII" variant) aren't supported.
+pub fn unpack_packer(script: &str) -> Result<String, String> {
+    let start = script
+        .find(PACKER_MARKER)
+        .ok_or_else(|| "No packed script found".to_string())?;
+    // The arguments follow the function body (`...return p}('...`)
+    let body = &script[start..];
+    let args = body
+        .find("return p}")
+        .and_then(|i| expect_char(&body[i + "return p}".len()..], '('))
+        .ok_or_else(|| "Malformed packed script".to_string())?;
+
+    let malformed = || "Malformed packed script arguments".to_string();
+    let (payload, rest) = parse_js_string(args).ok_or_else(malformed)?;
+    let rest = expect_char(rest, ',').ok_or_else(malformed)?;
+    let (radix, rest) = parse_js_number(rest).ok_or_else(malformed)?;
+    let rest = expect_char(rest, ',').ok_or_else(malformed)?;
+    let (count, rest) = parse_js_number(rest).ok_or_else(malformed)?;
+    let rest = expect_char(rest, ',').ok_or_else(malformed)?;
+    let (keywords, _) = parse_js_string(rest).ok_or_else(malformed)?;
+
+    if !(2..=62).contains(&radix) {
+        return Err(format!("Unsupported packer radix {}", radix));
+    }
+    if count > MAX_PACKED_KEYWORDS {
+        return Err(format!("Too many packed keywords ({})", count));
+    }
+
+    let keywords: Vec<&str> = keywords.split('|').collect();
+    let dictionary: HashMap<String, &str> = (0..count)
+        .filter_map(|i| {
+            let keyword = keywords.get(i).copied().unwrap_or("");
+            (!keyword.is_empty()).then(|| (packer_encode(i, radix), keyword))
+        })
+        .collect();
+
+    // Replace whole words (`\b\w+\b`)
+    let mut out = String::with_capacity(payload.len() * 2);
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| {
+        if !word.is_empty() {
+            out.push_str(dictionary.get(word.as_str()).copied().unwrap_or(word));
+            word.clear();
+        }
+    };
+    for c in payload.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut word, &mut out);
+    Ok(out)
+}
+
+/// Unpack every packed script of a page (scripts that fail to unpack are skipped)
+pub fn unpack_all(html: &str) -> Vec<String> {
+    let mut unpacked = Vec::new();
+    let mut offset = 0;
+    while let Some(i) = html[offset..].find(PACKER_MARKER) {
+        let start = offset + i;
+        match unpack_packer(&html[start..]) {
+            Ok(code) => unpacked.push(code),
+            Err(e) => log::debug!("Skipping packed script: {}", e),
+        }
+        offset = start + PACKER_MARKER.len();
+    }
+    unpacked
+}
+
+// =============================================================================
+// String Transforms
+// =============================================================================
+
+/// ROT13 of ASCII letters
+pub fn rot13(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| match c {
+            'a'..='z' => (((c as u8 - b'a') + 13) % 26 + b'a') as char,
+            'A'..='Z' => (((c as u8 - b'A') + 13) % 26 + b'A') as char,
+            _ => c,
+        })
+        .collect()
+}
+
+/// Decode Base64 (padding optional, whitespace ignored) as Latin-1 text, like `atob`
+pub fn base64_decode(input: &str) -> Option<String> {
+    let cleaned: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = LENIENT_BASE64.decode(cleaned.as_bytes()).ok()?;
+    Some(bytes.into_iter().map(char::from).collect())
+}
+
+/// Shift every character code down by `shift`
+pub fn shift_chars(input: &str, shift: u32) -> String {
+    input
+        .chars()
+        .filter_map(|c| (c as u32).checked_sub(shift).and_then(char::from_u32))
+        .collect()
+}
+
+/// Swap the case of ASCII letters
+pub fn swap_case(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect()
+}
+
+pub fn reverse(input: &str) -> String {
+    input.chars().rev().collect()
+}
+
+/// Decode pairs of hex digits into characters
+pub fn hex_to_string(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+    bytes
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .map(char::from)
+        })
+        .collect()
+}
+
+// =============================================================================
+// URLs
+// =============================================================================
+
+/// http(s) URLs in text that contain `needle` (e.g. `.m3u8`), in order
+pub fn find_urls(text: &str, needle: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let mut offset = 0;
+    while let Some(i) = text[offset..].find("http") {
+        let start = offset + i;
+        let url: String = text[start..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '\'' | '`' | '<' | '>' | '\\' | ')'))
+            .collect();
+        offset = start + url.len().max(4);
+        if (url.starts_with("http://") || url.starts_with("https://")) && url.contains(needle) && !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// Stream format from a URL
+pub fn detect_format(url: &str) -> &'static str {
+    let lower = url.to_ascii_lowercase();
+    let path = lower.split(['?', '#']).next().unwrap_or(&lower);
+    if path.contains(".m3u8") || path.contains("/hls/") {
+        "m3u8"
+    } else if path.contains(".mpd") || path.contains("/dash/") {
+        "dash"
+    } else if path.ends_with(".mkv") {
+        "mkv"
+    } else if path.ends_with(".webm") {
+        "webm"
+    } else {
+        "mp4"
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KWIK_FIXTURE: &str = include_str!("../tests/fixtures/animepahe/kwik_embed.html");
+
+    #[test]
+    fn test_unpack_packer() {
+        let unpacked = unpack_all(KWIK_FIXTURE);
+        assert_eq!(unpacked.len(), 2);
+        assert!(unpacked[0].starts_with("var _0x1=[\"kwik\",\"ready\"];"));
+        assert_eq!(
+            js_url_value(&unpacked[1], "source").as_deref(),
+            Some("https://vault-12.owocdn.top/stream/12/04/4f1c0a9e7d2b6c3a8e5f9b0d1c2a3e4f/uwu.m3u8")
+        );
+
+        // Small hand-packed script with an escaped quote and an empty keyword
+        let script = r"eval(function(p,a,c,k,e,d){e=function(c){return c};return p}('2 0=\'1\';b(0)',36,12,'x|hello|var||||||||||'.split('|'),0,{}))";
+        assert_eq!(unpack_packer(script).unwrap(), "var x='hello';b(x)");
+
+        assert_eq!(packer_encode(0, 62), "0");
+        assert_eq!(packer_encode(36, 62), "A");
+        assert_eq!(packer_encode(62, 62), "10");
+        assert!(unpack_packer("var x = 1;").is_err());
+        assert!(unpack_packer("eval(function(p,a,c,k,e,d){return p}('x',95,1,'y'.split('|'),0,{}))").is_err());
+    }
+
+    #[test]
+    fn test_js_string_values() {
+        let code = r#"var profile="x";jwplayer("v").setup({sources:[{file:"https://a.example/hls/master.m3u8?t=1"}],"file" : '//b.example/v.mp4',image:"p.jpg"});if(file=="")"#;
+        assert_eq!(
+            js_string_values(code, "file"),
+            ["https://a.example/hls/master.m3u8?t=1", "//b.example/v.mp4"]
+        );
+        assert_eq!(js_url_value(code, "image"), None);
+        assert_eq!(js_string_values("const source = \"https://a/b.m3u8\";", "source"), ["https://a/b.m3u8"]);
+
+        assert_eq!(parse_js_string(r"'a\x41é\'b'"), Some(("aAé'b".to_string(), "")));
+        assert_eq!(parse_js_string("'unterminated"), None);
+    }
+
+    #[test]
+    fn test_string_transforms() {
+        assert_eq!(rot13("Hello, World"), "Uryyb, Jbeyq");
+        assert_eq!(base64_decode("aGVsbG8").as_deref(), Some("hello"));
+        assert_eq!(base64_decode("aGVs\nbG8="), Some("hello".to_string()));
+        assert_eq!(base64_decode("not base64!"), None);
+        assert_eq!(shift_chars("khoor", 3), "hello");
+        assert_eq!(swap_case("aBc1"), "AbC1");
+        assert_eq!(reverse("abc"), "cba");
+        assert_eq!(hex_to_string("68690a").as_deref(), Some("hi\n"));
+        assert_eq!(hex_to_string("6"), None);
+    }
+
+    #[test]
+    fn test_find_urls() {
+        let text = r#"<a href="https://x.example/a.m3u8">x</a> 'http://y.example/b.mp4' url(https://x.example/a.m3u8)"#;
+        assert_eq!(find_urls(text, ".m3u8"), ["https://x.example/a.m3u8"]);
+        assert_eq!(find_urls(text, ""), ["https://x.example/a.m3u8", "http://y.example/b.mp4"]);
+
+        assert_eq!(detect_format("https://a/master.m3u8?token=1"), "m3u8");
+        assert_eq!(detect_format("https://a/video.mkv"), "mkv");
+        assert_eq!(detect_format("https://a/dash/manifest"), "dash");
+        assert_eq!(detect_format("https://a/get_video?id=1"), "mp4");
+    }
+}
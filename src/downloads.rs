@@ -0,0 +1,718 @@
+//! Download Queue
+//!
+//! This module keeps a persistent queue of downloads that are saved into
+//! `Settings.downloads_folder`. Torrent jobs are handed off to the torrent backend
+//! running on `backend_port`, whose progress is polled back into the queue; HLS jobs
+//! are downloaded by the app itself (see `hls_download`). Every change is broadcast
+//! to the frontend with the `downloads-updated` event.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_store::StoreExt;
+
+use crate::commands::AppState;
+
+/// Store file name for the download queue
+const DOWNLOADS_STORE_FILE: &str = "downloads.json";
+
+/// Store key for download jobs
+const DOWNLOADS_KEY: &str = "jobs";
+
+/// Event emitted whenever a job changes
+pub const DOWNLOADS_UPDATED_EVENT: &str = "downloads-updated";
+
+/// Default port of the torrent backend (matches the default settings)
+pub const DEFAULT_BACKEND_PORT: u16 = 64621;
+
+/// Timeout for requests to the local torrent backend
+const BACKEND_TIMEOUT_SECS: u64 = 10;
+
+/// Timeout for adding a torrent (the backend answers once metadata is fetched)
+const BACKEND_ADD_TIMEOUT_SECS: u64 = 120;
+
+/// What is being downloaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DownloadSource {
+    /// A torrent, transferred by the torrent backend
+    #[serde(rename_all = "camelCase")]
+    Torrent { magnet: String, infohash: String },
+    /// An HLS stream, downloaded and remuxed by the app
+    #[serde(rename_all = "camelCase")]
+    Hls {
+        url: String,
+        /// Headers added to every request (Referer, User-Agent, Cookie, ...)
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// Preferred maximum video height (e.g. 1080); the best variant if unset
+        #[serde(default)]
+        quality: Option<u32>,
+    },
+}
+
+/// Download job status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    /// Waiting to be started
+    Queued,
+    /// Transfer in progress
+    Downloading,
+    /// Finished successfully
+    Completed,
+    /// Failed (see `error`)
+    Failed,
+    /// Cancelled by the user
+    Cancelled,
+}
+
+impl DownloadStatus {
+    /// Whether the job is still queued or running
+    pub fn is_active(self) -> bool {
+        matches!(self, DownloadStatus::Queued | DownloadStatus::Downloading)
+    }
+}
+
+/// A queued or finished download
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadJob {
+    /// Unique job identifier
+    pub id: String,
+    /// Display title
+    pub title: String,
+    /// What is being downloaded
+    pub source: DownloadSource,
+    /// Destination folder
+    pub destination: String,
+    /// Current status
+    pub status: DownloadStatus,
+    /// Progress between 0.0 and 1.0
+    pub progress: f64,
+    /// Bytes downloaded so far
+    pub downloaded_bytes: u64,
+    /// Total size in bytes (if known)
+    pub total_bytes: Option<u64>,
+    /// Error message for failed jobs
+    pub error: Option<String>,
+    /// What created the job (e.g. `subscription:<id>`)
+    pub origin: Option<String>,
+    /// Creation timestamp (Unix milliseconds)
+    pub created_at: i64,
+    /// Last update timestamp (Unix milliseconds)
+    pub updated_at: i64,
+}
+
+/// Download queue state (in-memory cache of the store)
+pub struct DownloadQueueState {
+    jobs: Mutex<Vec<DownloadJob>>,
+    loaded: Mutex<bool>,
+}
+
+impl Default for DownloadQueueState {
+    fn default() -> Self {
+        DownloadQueueState {
+            jobs: Mutex::new(Vec::new()),
+            loaded: Mutex::new(false),
+        }
+    }
+}
+
+/// Torrent details reported by the torrent backend's `/details` route
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TorrentDetails {
+    pub name: Option<String>,
+    pub length: u64,
+    pub downloaded: u64,
+    pub uploaded: u64,
+    pub download_speed: f64,
+    pub upload_speed: f64,
+    pub progress: f64,
+    pub ratio: f64,
+    pub num_peers: u32,
+}
+
+/// A torrent known to the torrent backend, as reported by its `/torrents` route
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackendTorrent {
+    pub info_hash: String,
+    #[serde(rename = "magnetURI")]
+    pub magnet_uri: String,
+    pub name: Option<String>,
+    /// Folder the torrent's content is saved in
+    pub path: Option<String>,
+    pub length: u64,
+    pub downloaded: u64,
+    /// Bytes uploaded since the backend added the torrent
+    pub uploaded: u64,
+    pub upload_speed: f64,
+    pub progress: f64,
+    pub ratio: f64,
+    pub num_peers: u32,
+    pub paused: bool,
+    pub done: bool,
+}
+
+/// HTTP client for the torrent backend on `backend_port`
+pub struct TorrentBackend {
+    base_url: String,
+    /// Per-launch token the backend requires on control routes
+    token: String,
+    client: reqwest::Client,
+}
+
+impl TorrentBackend {
+    /// Create a client for the backend on the given port
+    pub fn new(port: u16, token: String) -> Self {
+        TorrentBackend {
+            base_url: format!("http://127.0.0.1:{}", port),
+            token,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(BACKEND_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Create a client for the port configured in the settings
+    pub fn from_settings(app: &AppHandle) -> Self {
+        TorrentBackend::new(backend_port(app), crate::backend_runtime::token(app))
+    }
+
+    /// Start a control request carrying the backend token
+    fn control_request(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}{}", self.base_url, path))
+            .header(crate::backend_runtime::TOKEN_HEADER, &self.token)
+    }
+
+    /// Add a torrent, saving its files into `path`
+    pub async fn add(&self, magnet: &str, path: &str) -> Result<(), String> {
+        let request = serde_json::json!({ "path": path });
+        self.control_request(&format!("/add/{}", encode_component(magnet)))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(request.to_string())
+            .timeout(Duration::from_secs(BACKEND_ADD_TIMEOUT_SECS))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Torrent backend rejected download: {}", e))?;
+        Ok(())
+    }
+
+    /// Get transfer details; `None` if the backend doesn't know the torrent
+    pub async fn details(&self, magnet: &str) -> Result<Option<TorrentDetails>, String> {
+        let url = format!("{}/details/{}", self.base_url, encode_component(magnet));
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Torrent backend unavailable: {}", e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body = response
+            .error_for_status()
+            .map_err(|e| format!("Torrent backend error: {}", e))?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read torrent details: {}", e))?;
+        serde_json::from_str(&body)
+            .map(Some)
+            .map_err(|e| format!("Invalid torrent details: {}", e))
+    }
+
+    /// List all torrents the backend knows about
+    pub async fn list(&self) -> Result<Vec<BackendTorrent>, String> {
+        let body = self
+            .client
+            .get(format!("{}/torrents", self.base_url))
+            .header(crate::backend_runtime::TOKEN_HEADER, &self.token)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Torrent backend unavailable: {}", e))?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read torrent list: {}", e))?;
+        serde_json::from_str(&body).map_err(|e| format!("Invalid torrent list: {}", e))
+    }
+
+    /// Create a torrent for a local file or folder and seed it
+    pub async fn seed(&self, path: &str) -> Result<BackendTorrent, String> {
+        let request = serde_json::json!({ "path": path });
+        let body = self
+            .control_request("/seed")
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(request.to_string())
+            .timeout(Duration::from_secs(BACKEND_ADD_TIMEOUT_SECS))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Torrent backend failed to seed '{}': {}", path, e))?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read seed response: {}", e))?;
+        serde_json::from_str(&body).map_err(|e| format!("Invalid seed response: {}", e))
+    }
+
+    /// Stop uploading a torrent without removing it
+    pub async fn pause(&self, infohash: &str) -> Result<(), String> {
+        self.control("pause", infohash).await
+    }
+
+    /// Resume a paused torrent
+    pub async fn resume(&self, infohash: &str) -> Result<(), String> {
+        self.control("resume", infohash).await
+    }
+
+    async fn control(&self, action: &str, infohash: &str) -> Result<(), String> {
+        self.control_request(&format!("/{}/{}", action, encode_component(infohash)))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Torrent backend failed to {} torrent: {}", action, e))?;
+        Ok(())
+    }
+
+    /// Remove a torrent from the backend (files are kept)
+    pub async fn remove(&self, magnet: &str) -> Result<(), String> {
+        let url = format!("{}/remove/{}", self.base_url, encode_component(magnet));
+        let response = self
+            .client
+            .delete(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Torrent backend unavailable: {}", e))?;
+        if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(format!("Torrent backend failed to remove torrent: {}", response.status()))
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Generate a unique job ID
+fn generate_job_id() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("download_{}", timestamp % 1_000_000_000_000)
+}
+
+/// Percent-encode a URL path segment or query value
+pub fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Port of the torrent backend configured in the settings
+pub fn backend_port(app: &AppHandle) -> u16 {
+    app.state::<AppState>()
+        .settings
+        .lock()
+        .ok()
+        .and_then(|s| s.backend_port)
+        .unwrap_or(DEFAULT_BACKEND_PORT)
+}
+
+/// Resolve the folder downloads are saved to: `Settings.downloads_folder`, or the
+/// system download directory when it isn't configured
+pub fn resolve_downloads_folder(app: &AppHandle) -> Result<PathBuf, String> {
+    let configured = app
+        .state::<AppState>()
+        .settings
+        .lock()
+        .map_err(|e| format!("Failed to lock settings: {}", e))?
+        .downloads_folder
+        .clone();
+
+    match configured {
+        Some(folder) if !folder.trim().is_empty() => Ok(PathBuf::from(folder)),
+        _ => app
+            .path()
+            .download_dir()
+            .map(|dir| dir.join("zanshin"))
+            .map_err(|e| format!("No downloads folder configured: {}", e)),
+    }
+}
+
+// =============================================================================
+// Persistence Functions
+// =============================================================================
+
+/// Load jobs from the store once
+fn ensure_jobs_loaded(app: &AppHandle, state: &DownloadQueueState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(DOWNLOADS_STORE_FILE) {
+        if let Some(value) = store.get(DOWNLOADS_KEY) {
+            match serde_json::from_value::<Vec<DownloadJob>>(value.clone()) {
+                Ok(jobs) => *state.jobs.lock().unwrap() = jobs,
+                Err(e) => log::warn!("Failed to deserialize download jobs: {}", e),
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save jobs to the store
+fn save_jobs_to_store(app: &AppHandle, jobs: &[DownloadJob]) -> Result<(), String> {
+    let store = app
+        .store(DOWNLOADS_STORE_FILE)
+        .map_err(|e| format!("Failed to open downloads store: {}", e))?;
+    let value = serde_json::to_value(jobs)
+        .map_err(|e| format!("Failed to serialize download jobs: {}", e))?;
+    store.set(DOWNLOADS_KEY, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save download jobs: {}", e))
+}
+
+// =============================================================================
+// Queue Operations
+// =============================================================================
+
+/// Add a job to the queue and start it
+pub fn enqueue(
+    app: &AppHandle,
+    title: String,
+    source: DownloadSource,
+    origin: Option<String>,
+) -> Result<DownloadJob, String> {
+    let state = app.state::<DownloadQueueState>();
+    ensure_jobs_loaded(app, &state);
+
+    let destination = resolve_downloads_folder(app)?;
+    std::fs::create_dir_all(&destination)
+        .map_err(|e| format!("Failed to create downloads folder: {}", e))?;
+
+    let now = get_current_timestamp();
+    let job = DownloadJob {
+        id: generate_job_id(),
+        title,
+        source,
+        destination: destination.to_string_lossy().to_string(),
+        status: DownloadStatus::Queued,
+        progress: 0.0,
+        downloaded_bytes: 0,
+        total_bytes: None,
+        error: None,
+        origin,
+        created_at: now,
+        updated_at: now,
+    };
+
+    {
+        let mut jobs = state
+            .jobs
+            .lock()
+            .map_err(|e| format!("Failed to lock download queue: {}", e))?;
+        // The same source is only queued once while it is active
+        if let Some(existing) = jobs
+            .iter()
+            .find(|j| j.status.is_active() && j.source == job.source)
+        {
+            return Ok(existing.clone());
+        }
+        jobs.push(job.clone());
+        save_jobs_to_store(app, &jobs)?;
+    }
+
+    let _ = app.emit(DOWNLOADS_UPDATED_EVENT, &job);
+    log::info!("Queued download: {} -> {}", job.title, job.destination);
+
+    let handle = app.clone();
+    let queued = job.clone();
+    tauri::async_runtime::spawn(async move {
+        start_job(&handle, queued).await;
+    });
+
+    Ok(job)
+}
+
+/// Apply a change to a job, persist the queue and notify the frontend
+pub fn update_job<F>(app: &AppHandle, id: &str, change: F) -> Option<DownloadJob>
+where
+    F: FnOnce(&mut DownloadJob),
+{
+    let state = app.state::<DownloadQueueState>();
+    ensure_jobs_loaded(app, &state);
+
+    let updated = {
+        let mut jobs = state.jobs.lock().ok()?;
+        let job = jobs.iter_mut().find(|j| j.id == id)?;
+        change(job);
+        job.updated_at = get_current_timestamp();
+        let updated = job.clone();
+        if let Err(e) = save_jobs_to_store(app, &jobs) {
+            log::warn!("{}", e);
+        }
+        updated
+    };
+
+    let _ = app.emit(DOWNLOADS_UPDATED_EVENT, &updated);
+    Some(updated)
+}
+
+/// Hand a queued job to the engine responsible for it
+async fn start_job(app: &AppHandle, job: DownloadJob) {
+    let result = match &job.source {
+        DownloadSource::Torrent { magnet, .. } => {
+            TorrentBackend::from_settings(app)
+                .add(magnet, &job.destination)
+                .await
+        }
+        DownloadSource::Hls { .. } => {
+            // Runs in the app and reports its own progress and completion
+            update_job(app, &job.id, |j| j.status = DownloadStatus::Downloading);
+            crate::hls_download::start(app, job);
+            return;
+        }
+    };
+
+    update_job(app, &job.id, |j| match result {
+        Ok(()) => j.status = DownloadStatus::Downloading,
+        Err(e) => {
+            log::warn!("Download '{}' failed to start: {}", j.title, e);
+            j.status = DownloadStatus::Failed;
+            j.error = Some(e);
+        }
+    });
+}
+
+/// Poll the torrent backend for progress of all running torrent jobs (and resume HLS
+/// jobs interrupted by an app restart)
+pub async fn refresh_progress(app: &AppHandle) -> Result<Vec<DownloadJob>, String> {
+    let state = app.state::<DownloadQueueState>();
+    ensure_jobs_loaded(app, &state);
+
+    let running: Vec<DownloadJob> = state
+        .jobs
+        .lock()
+        .map_err(|e| format!("Failed to lock download queue: {}", e))?
+        .iter()
+        .filter(|j| j.status == DownloadStatus::Downloading)
+        .cloned()
+        .collect();
+
+    let backend = TorrentBackend::from_settings(app);
+    for job in running {
+        let magnet = match &job.source {
+            DownloadSource::Torrent { magnet, .. } => magnet,
+            DownloadSource::Hls { .. } => {
+                if !crate::hls_download::is_running(app, &job.id) {
+                    // Picks up the segments downloaded before the restart
+                    crate::hls_download::start(app, job);
+                }
+                continue;
+            }
+        };
+        match backend.details(magnet).await {
+            Ok(Some(details)) => {
+                update_job(app, &job.id, |j| {
+                    j.progress = details.progress.clamp(0.0, 1.0);
+                    j.downloaded_bytes = details.downloaded;
+                    if details.length > 0 {
+                        j.total_bytes = Some(details.length);
+                    }
+                    if details.progress >= 1.0 {
+                        j.status = DownloadStatus::Completed;
+                    }
+                });
+            }
+            Ok(None) => {
+                // The backend restarted and forgot the torrent; hand it over again
+                let handle = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    start_job(&handle, job).await;
+                });
+            }
+            Err(e) => log::debug!("Skipping progress refresh: {}", e),
+        }
+    }
+
+    list_jobs(app)
+}
+
+/// Get all jobs, newest first
+pub fn list_jobs(app: &AppHandle) -> Result<Vec<DownloadJob>, String> {
+    let state = app.state::<DownloadQueueState>();
+    ensure_jobs_loaded(app, &state);
+
+    let mut jobs = state
+        .jobs
+        .lock()
+        .map_err(|e| format!("Failed to lock download queue: {}", e))?
+        .clone();
+    jobs.sort_by_key(|j| std::cmp::Reverse(j.created_at));
+    Ok(jobs)
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// List all download jobs (refreshing progress of running ones)
+#[tauri::command]
+pub async fn download_list(app: AppHandle) -> Result<Vec<DownloadJob>, String> {
+    refresh_progress(&app).await
+}
+
+/// Queue a torrent download into the downloads folder
+#[tauri::command]
+pub fn download_add_torrent(
+    title: String,
+    magnet: String,
+    app: AppHandle,
+) -> Result<DownloadJob, String> {
+    let infohash = crate::torrent_search::infohash_from_magnet(&magnet)
+        .ok_or("Invalid magnet link")?;
+    enqueue(&app, title, DownloadSource::Torrent { magnet, infohash }, None)
+}
+
+/// Queue an HLS stream download into the downloads folder
+#[tauri::command]
+pub fn download_add_hls(
+    title: String,
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    quality: Option<u32>,
+    app: AppHandle,
+) -> Result<DownloadJob, String> {
+    let parsed = reqwest::Url::parse(&url).map_err(|e| format!("Invalid stream URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Only http(s) streams can be downloaded".to_string());
+    }
+    let source = DownloadSource::Hls {
+        url,
+        headers: headers.unwrap_or_default(),
+        quality,
+    };
+    enqueue(&app, title, source, None)
+}
+
+/// Cancel an active download
+#[tauri::command]
+pub async fn download_cancel(id: String, app: AppHandle) -> Result<DownloadJob, String> {
+    let job = list_jobs(&app)?
+        .into_iter()
+        .find(|j| j.id == id)
+        .ok_or_else(|| format!("Download '{}' not found", id))?;
+
+    if job.status.is_active() {
+        match &job.source {
+            DownloadSource::Torrent { magnet, .. } => {
+                TorrentBackend::from_settings(&app).remove(magnet).await?
+            }
+            DownloadSource::Hls { .. } => crate::hls_download::cancel(&app, &id),
+        }
+    }
+
+    update_job(&app, &id, |j| {
+        if j.status.is_active() {
+            j.status = DownloadStatus::Cancelled;
+        }
+    })
+    .ok_or_else(|| format!("Download '{}' not found", id))
+}
+
+/// Retry a failed or cancelled download
+#[tauri::command]
+pub fn download_retry(id: String, app: AppHandle) -> Result<DownloadJob, String> {
+    let job = update_job(&app, &id, |j| {
+        if !j.status.is_active() && j.status != DownloadStatus::Completed {
+            j.status = DownloadStatus::Queued;
+            j.error = None;
+        }
+    })
+    .ok_or_else(|| format!("Download '{}' not found", id))?;
+
+    if job.status == DownloadStatus::Queued {
+        let handle = app.clone();
+        let queued = job.clone();
+        tauri::async_runtime::spawn(async move {
+            start_job(&handle, queued).await;
+        });
+    }
+    Ok(job)
+}
+
+/// Remove finished, failed and cancelled jobs from the list (files are kept)
+#[tauri::command]
+pub fn download_clear_finished(
+    app: AppHandle,
+    state: State<'_, DownloadQueueState>,
+) -> Result<usize, String> {
+    ensure_jobs_loaded(&app, &state);
+
+    let mut jobs = state
+        .jobs
+        .lock()
+        .map_err(|e| format!("Failed to lock download queue: {}", e))?;
+    let before = jobs.len();
+    jobs.retain(|j| j.status.is_active());
+    save_jobs_to_store(&app, &jobs)?;
+    Ok(before - jobs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_component() {
+        assert_eq!(encode_component("magnet:?xt=urn:btih:ab"), "magnet%3A%3Fxt%3Durn%3Abtih%3Aab");
+        assert_eq!(encode_component("C:\\Anime Folder"), "C%3A%5CAnime%20Folder");
+    }
+
+    #[test]
+    fn test_status_is_active() {
+        assert!(DownloadStatus::Queued.is_active());
+        assert!(DownloadStatus::Downloading.is_active());
+        assert!(!DownloadStatus::Completed.is_active());
+        assert!(!DownloadStatus::Cancelled.is_active());
+    }
+
+    #[test]
+    fn test_torrent_details_deserialize() {
+        let details: TorrentDetails = serde_json::from_str(
+            r#"{"name":"ep.mkv","length":100,"downloaded":50,"uploaded":10,
+                "downloadSpeed":1.5,"uploadSpeed":0,"progress":0.5,"ratio":0.1,"numPeers":3}"#,
+        )
+        .unwrap();
+        assert_eq!(details.length, 100);
+        assert_eq!(details.num_peers, 3);
+        assert!((details.progress - 0.5).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,889 @@
+//! Episode Mapping
+//!
+//! AniList splits long shows into one entry per cour ("The Final Season" and "The Final
+//! Season Part 2"), while providers and TVDB number the same episodes per season or
+//! absolutely. Progress is synced to AniList, so a provider's "episode 20" has to become
+//! "Part 2, episode 4". This module keeps the ani.zip mappings of an entry (the IDs of the
+//! entry on MAL, AniDB, Kitsu, TVDB, ... and every episode's TVDB season/episode and
+//! absolute number) and translates between three numberings:
+//!
+//! - `anilist`: `1..=episodes` of one AniList entry
+//! - `season`: episode within a TVDB season, continuing across split cours
+//! - `absolute`: counted from the first episode of the show
+//!
+//! Mappings are cached on disk (`anime-mappings/` in the cache folder), refreshed after a
+//! day (sooner while episodes are missing) and used stale when ani.zip is unreachable.
+//! A provider's episode list is linked to one or more AniList entries with
+//! `episode_mapping_link_provider`, which detects the numbering the provider uses and
+//! stores `episode ID -> (AniList ID, episode)` so the player can report progress against
+//! the right entry.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_store::StoreExt;
+
+/// Store file name for provider links
+const MAPPING_STORE_FILE: &str = "episode_mappings.json";
+
+/// Store key for provider links
+const PROVIDER_LINKS_KEY: &str = "providerLinks";
+
+/// ani.zip API
+const ANIZIP_BASE_URL: &str = "https://api.ani.zip";
+
+/// Request timeout
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+
+/// How long a mapping is cached (incomplete mappings are refreshed sooner)
+const MAPPING_TTL_MS: i64 = 24 * 60 * 60 * 1000;
+const INCOMPLETE_MAPPING_TTL_MS: i64 = 6 * 60 * 60 * 1000;
+
+/// Site an ID belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdSource {
+    #[default]
+    Anilist,
+    Mal,
+    Anidb,
+    Kitsu,
+}
+
+impl IdSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            IdSource::Anilist => "anilist",
+            IdSource::Mal => "mal",
+            IdSource::Anidb => "anidb",
+            IdSource::Kitsu => "kitsu",
+        }
+    }
+
+    /// ani.zip query parameter
+    fn query_param(&self) -> &'static str {
+        match self {
+            IdSource::Anilist => "anilist_id",
+            IdSource::Mal => "mal_id",
+            IdSource::Anidb => "anidb_id",
+            IdSource::Kitsu => "kitsu_id",
+        }
+    }
+}
+
+/// How a list of episodes is numbered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Numbering {
+    /// Episode of one AniList entry
+    Anilist,
+    /// Episode within a TVDB season
+    Season,
+    /// Episode counted from the start of the show
+    Absolute,
+}
+
+/// IDs of an entry on other sites
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MappingIds {
+    pub anilist_id: Option<u64>,
+    pub mal_id: Option<u64>,
+    pub anidb_id: Option<u64>,
+    pub kitsu_id: Option<u64>,
+    pub tvdb_id: Option<u64>,
+    pub tmdb_id: Option<u64>,
+    pub imdb_id: Option<String>,
+    pub livechart_id: Option<u64>,
+}
+
+/// A regular episode of an entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MappedEpisode {
+    /// Episode number on AniList
+    pub episode: u32,
+    /// TVDB season
+    pub season: Option<u32>,
+    /// Episode within the TVDB season
+    pub season_episode: Option<u32>,
+    pub absolute: Option<u32>,
+    pub anidb_eid: Option<u64>,
+    pub tvdb_id: Option<u64>,
+    pub title: Option<String>,
+    pub air_date: Option<String>,
+}
+
+/// IDs and episodes of an entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeMapping {
+    pub ids: MappingIds,
+    pub title: Option<String>,
+    /// Episode count (may be higher than the number of mapped episodes while airing)
+    pub episode_count: Option<u32>,
+    /// Regular episodes ordered by number (specials are left out)
+    pub episodes: Vec<MappedEpisode>,
+    pub fetched_at: i64,
+}
+
+/// An episode in all three numberings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedEpisode {
+    pub anilist_id: u64,
+    pub episode: u32,
+    pub season: Option<u32>,
+    pub season_episode: Option<u32>,
+    pub absolute: Option<u32>,
+}
+
+/// An episode returned by a plugin's `getEpisodes` (`ZPEEpisode`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderEpisode {
+    pub id: String,
+    pub number: f64,
+}
+
+/// A provider anime linked to AniList entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderLink {
+    pub plugin_id: String,
+    pub provider_anime_id: String,
+    /// Linked entries, in airing order
+    pub anilist_ids: Vec<u64>,
+    pub numbering: Numbering,
+    pub season: Option<u32>,
+    /// AniList episode of each provider episode ID
+    pub episodes: HashMap<String, ResolvedEpisode>,
+    /// Provider episodes that didn't map to any entry
+    pub unmapped: Vec<String>,
+    pub linked_at: i64,
+}
+
+/// Episode mapping state: HTTP client, mapping cache and provider links
+pub struct EpisodeMappingState {
+    client: reqwest::Client,
+    /// Mappings by `source-id`
+    mappings: Mutex<HashMap<String, AnimeMapping>>,
+    /// Provider links by `plugin_id:provider_anime_id`
+    links: Mutex<HashMap<String, ProviderLink>>,
+    loaded: Mutex<bool>,
+}
+
+impl Default for EpisodeMappingState {
+    fn default() -> Self {
+        EpisodeMappingState {
+            client: reqwest::Client::builder()
+                .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+                .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+            mappings: Mutex::new(HashMap::new()),
+            links: Mutex::new(HashMap::new()),
+            loaded: Mutex::new(false),
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+/// A number that may be sent as a string
+fn value_u64(value: Option<&Value>) -> Option<u64> {
+    match value? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn value_u32(value: Option<&Value>) -> Option<u32> {
+    value_u64(value).and_then(|n| u32::try_from(n).ok())
+}
+
+/// Parse an ani.zip `/mappings` response
+pub fn parse_anizip(body: &str, fetched_at: i64) -> Result<AnimeMapping, String> {
+    let root: Value = serde_json::from_str(body).map_err(|e| format!("Invalid mapping response: {}", e))?;
+    let ids_value = root
+        .get("mappings")
+        .filter(|m| m.is_object())
+        .ok_or_else(|| "The mapping response has no IDs".to_string())?;
+
+    let ids = MappingIds {
+        anilist_id: value_u64(ids_value.get("anilist_id")),
+        mal_id: value_u64(ids_value.get("mal_id")),
+        anidb_id: value_u64(ids_value.get("anidb_id")),
+        kitsu_id: value_u64(ids_value.get("kitsu_id")),
+        tvdb_id: value_u64(ids_value.get("thetvdb_id")),
+        tmdb_id: value_u64(ids_value.get("themoviedb_id")),
+        imdb_id: ids_value
+            .get("imdb_id")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(String::from),
+        livechart_id: value_u64(ids_value.get("livechart_id")),
+    };
+
+    let titles = root.get("titles");
+    let title = ["en", "x-jat", "ja"]
+        .iter()
+        .find_map(|lang| titles.and_then(|t| t.get(*lang)).and_then(|t| t.as_str()))
+        .map(String::from);
+
+    // Regular episodes are keyed by their number, specials by "S1", "S2", ...
+    let mut episodes: Vec<MappedEpisode> = root
+        .get("episodes")
+        .and_then(|e| e.as_object())
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|(key, value)| {
+                    let episode = key.parse::<u32>().ok().filter(|n| *n > 0)?;
+                    let title = value.get("title");
+                    Some(MappedEpisode {
+                        episode,
+                        season: value_u32(value.get("seasonNumber")),
+                        season_episode: value_u32(value.get("episodeNumber")),
+                        absolute: value_u32(value.get("absoluteEpisodeNumber")),
+                        anidb_eid: value_u64(value.get("anidbEid")),
+                        tvdb_id: value_u64(value.get("tvdbId")),
+                        title: ["en", "x-jat"]
+                            .iter()
+                            .find_map(|lang| title.and_then(|t| t.get(*lang)).and_then(|t| t.as_str()))
+                            .map(String::from),
+                        air_date: value
+                            .get("airDate")
+                            .or_else(|| value.get("airdate"))
+                            .and_then(|v| v.as_str())
+                            .map(String::from),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    episodes.sort_by_key(|e| e.episode);
+
+    Ok(AnimeMapping {
+        ids,
+        title,
+        episode_count: value_u32(root.get("episodeCount")).filter(|n| *n > 0),
+        episodes,
+        fetched_at,
+    })
+}
+
+// =============================================================================
+// Numbering
+// =============================================================================
+
+impl AnimeMapping {
+    fn find(&self, episode: u32) -> Option<&MappedEpisode> {
+        self.episodes.iter().find(|e| e.episode == episode)
+    }
+
+    /// Whether every episode is mapped
+    fn is_complete(&self) -> bool {
+        self.episode_count.map_or(true, |count| self.episodes.len() >= count as usize)
+    }
+}
+
+fn resolved(mapping: &AnimeMapping, episode: &MappedEpisode) -> Option<ResolvedEpisode> {
+    Some(ResolvedEpisode {
+        anilist_id: mapping.ids.anilist_id?,
+        episode: episode.episode,
+        season: episode.season,
+        season_episode: episode.season_episode,
+        absolute: episode.absolute,
+    })
+}
+
+/// TVDB season most regular episodes of the first entry belong to
+pub fn default_season(mappings: &[AnimeMapping]) -> Option<u32> {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    for season in mappings.first()?.episodes.iter().filter_map(|e| e.season) {
+        if season > 0 {
+            *counts.entry(season).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map(|(season, _)| season)
+}
+
+/// Find an episode in the given entries
+///
+/// `anilist` numbers refer to the first entry, `season` numbers to `season` (or the
+/// first entry's season) and `absolute` numbers to the whole show.
+pub fn resolve_episode(
+    mappings: &[AnimeMapping],
+    number: u32,
+    numbering: Numbering,
+    season: Option<u32>,
+) -> Option<ResolvedEpisode> {
+    match numbering {
+        Numbering::Anilist => {
+            let mapping = mappings.first()?;
+            match mapping.find(number) {
+                Some(episode) => resolved(mapping, episode),
+                // Not on TVDB yet, but AniList already lists it
+                None if number > 0 && mapping.episode_count.is_some_and(|count| number <= count) => {
+                    Some(ResolvedEpisode {
+                        anilist_id: mapping.ids.anilist_id?,
+                        episode: number,
+                        season: None,
+                        season_episode: None,
+                        absolute: None,
+                    })
+                }
+                None => None,
+            }
+        }
+        Numbering::Season => {
+            let season = season.or_else(|| default_season(mappings))?;
+            mappings.iter().find_map(|mapping| {
+                mapping
+                    .episodes
+                    .iter()
+                    .find(|e| e.season == Some(season) && e.season_episode == Some(number))
+                    .and_then(|e| resolved(mapping, e))
+            })
+        }
+        Numbering::Absolute => mappings.iter().find_map(|mapping| {
+            mapping
+                .episodes
+                .iter()
+                .find(|e| e.absolute == Some(number))
+                .and_then(|e| resolved(mapping, e))
+        }),
+    }
+}
+
+/// Guess how a provider numbers its episodes from the numbers it lists
+///
+/// The numbering that resolves the most numbers wins; ties go to `anilist`, then
+/// `season`.
+pub fn detect_numbering(mappings: &[AnimeMapping], numbers: &[u32], season: Option<u32>) -> Numbering {
+    let hits = |numbering: Numbering| {
+        numbers
+            .iter()
+            .filter(|n| resolve_episode(mappings, **n, numbering, season).is_some())
+            .count()
+    };
+    let mut best = (Numbering::Anilist, hits(Numbering::Anilist));
+    for numbering in [Numbering::Season, Numbering::Absolute] {
+        let count = hits(numbering);
+        if count > best.1 {
+            best = (numbering, count);
+        }
+    }
+    best.0
+}
+
+/// Map a provider's episodes to AniList episodes
+///
+/// Fractional episodes (recaps such as "12.5") are left unmapped.
+pub fn link_episodes(
+    mappings: &[AnimeMapping],
+    episodes: &[ProviderEpisode],
+    numbering: Option<Numbering>,
+    season: Option<u32>,
+) -> (Numbering, HashMap<String, ResolvedEpisode>, Vec<String>) {
+    let whole = |number: f64| (number.fract() == 0.0 && number >= 1.0).then_some(number as u32);
+    let numbering = numbering.unwrap_or_else(|| {
+        let numbers: Vec<u32> = episodes.iter().filter_map(|e| whole(e.number)).collect();
+        detect_numbering(mappings, &numbers, season)
+    });
+
+    let mut linked = HashMap::new();
+    let mut unmapped = Vec::new();
+    for episode in episodes {
+        match whole(episode.number).and_then(|n| resolve_episode(mappings, n, numbering, season)) {
+            Some(resolved) => {
+                linked.insert(episode.id.clone(), resolved);
+            }
+            None => unmapped.push(episode.id.clone()),
+        }
+    }
+    (numbering, linked, unmapped)
+}
+
+// =============================================================================
+// Fetching & Cache
+// =============================================================================
+
+fn mapping_key(source: IdSource, id: u64) -> String {
+    format!("{}-{}", source.as_str(), id)
+}
+
+/// Whether a cached mapping is still fresh
+fn is_cache_fresh(mapping: &AnimeMapping, now: i64) -> bool {
+    let ttl = if mapping.is_complete() { MAPPING_TTL_MS } else { INCOMPLETE_MAPPING_TTL_MS };
+    now - mapping.fetched_at < ttl
+}
+
+fn mapping_cache_folder(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("anime-mappings"))
+        .map_err(|e| format!("Failed to resolve cache folder: {}", e))
+}
+
+fn read_cached_mapping(app: &AppHandle, key: &str) -> Option<AnimeMapping> {
+    let path = mapping_cache_folder(app).ok()?.join(format!("{}.json", key));
+    let data = std::fs::read(path).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn write_cached_mapping(app: &AppHandle, key: &str, mapping: &AnimeMapping) -> Result<(), String> {
+    let folder = mapping_cache_folder(app)?;
+    std::fs::create_dir_all(&folder).map_err(|e| format!("Failed to create mapping cache folder: {}", e))?;
+    let data = serde_json::to_vec(mapping).map_err(|e| format!("Failed to serialize mapping: {}", e))?;
+    std::fs::write(folder.join(format!("{}.json", key)), data)
+        .map_err(|e| format!("Failed to write mapping cache: {}", e))
+}
+
+async fn fetch_mapping(client: &reqwest::Client, source: IdSource, id: u64) -> Result<AnimeMapping, String> {
+    let url = format!("{}/mappings?{}={}", ANIZIP_BASE_URL, source.query_param(), id);
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    let status = response.status();
+    if status.as_u16() == 404 {
+        return Err(format!("No mapping for {} ID {}", source.as_str(), id));
+    }
+    if !status.is_success() {
+        return Err(format!("Request failed with status {}", status));
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    parse_anizip(&body, get_current_timestamp())
+}
+
+/// Get the mapping of an entry from memory, the disk cache or ani.zip
+///
+/// A stale mapping is returned when ani.zip can't be reached.
+pub async fn get_mapping(
+    app: &AppHandle,
+    state: &EpisodeMappingState,
+    source: IdSource,
+    id: u64,
+    refresh: bool,
+) -> Result<AnimeMapping, String> {
+    let key = mapping_key(source, id);
+    let now = get_current_timestamp();
+
+    let cached = state
+        .mappings
+        .lock()
+        .unwrap()
+        .get(&key)
+        .cloned()
+        .or_else(|| read_cached_mapping(app, &key));
+    if let Some(mapping) = &cached {
+        if !refresh && is_cache_fresh(mapping, now) {
+            state.mappings.lock().unwrap().insert(key, mapping.clone());
+            return Ok(mapping.clone());
+        }
+    }
+
+    match fetch_mapping(&state.client, source, id).await {
+        Ok(mapping) => {
+            let mut keys = vec![key];
+            if let Some(anilist_id) = mapping.ids.anilist_id {
+                let anilist_key = mapping_key(IdSource::Anilist, anilist_id);
+                if !keys.contains(&anilist_key) {
+                    keys.push(anilist_key);
+                }
+            }
+            let mut mappings = state.mappings.lock().unwrap();
+            for key in keys {
+                if let Err(e) = write_cached_mapping(app, &key, &mapping) {
+                    log::warn!("{}", e);
+                }
+                mappings.insert(key, mapping.clone());
+            }
+            Ok(mapping)
+        }
+        Err(e) => match cached {
+            Some(mapping) => {
+                log::warn!("Using a stale mapping for {} ID {}: {}", source.as_str(), id, e);
+                state.mappings.lock().unwrap().insert(key, mapping.clone());
+                Ok(mapping)
+            }
+            None => Err(format!("Failed to fetch the mapping for {} ID {}: {}", source.as_str(), id, e)),
+        },
+    }
+}
+
+/// Get the mappings of several AniList entries, in order
+async fn get_anilist_mappings(
+    app: &AppHandle,
+    state: &EpisodeMappingState,
+    anilist_ids: &[u64],
+) -> Result<Vec<AnimeMapping>, String> {
+    if anilist_ids.is_empty() {
+        return Err("No AniList entries given".to_string());
+    }
+    let mut mappings = Vec::with_capacity(anilist_ids.len());
+    for id in anilist_ids {
+        let mut mapping = get_mapping(app, state, IdSource::Anilist, *id, false).await?;
+        // Results are only useful with the AniList ID they were asked for
+        mapping.ids.anilist_id = Some(*id);
+        mappings.push(mapping);
+    }
+    Ok(mappings)
+}
+
+// =============================================================================
+// Persistence Functions
+// =============================================================================
+
+fn link_key(plugin_id: &str, provider_anime_id: &str) -> String {
+    format!("{}:{}", plugin_id, provider_anime_id)
+}
+
+/// Load the provider links from the store once
+fn ensure_links_loaded(app: &AppHandle, state: &EpisodeMappingState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(MAPPING_STORE_FILE) {
+        if let Some(value) = store.get(PROVIDER_LINKS_KEY) {
+            match serde_json::from_value::<HashMap<String, ProviderLink>>(value.clone()) {
+                Ok(links) => *state.links.lock().unwrap() = links,
+                Err(e) => log::warn!("Failed to deserialize provider episode links: {}", e),
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save the provider links to the store
+fn save_links_to_store(app: &AppHandle, state: &EpisodeMappingState) -> Result<(), String> {
+    let store = app
+        .store(MAPPING_STORE_FILE)
+        .map_err(|e| format!("Failed to open episode mapping store: {}", e))?;
+
+    let links_value = {
+        let links = state
+            .links
+            .lock()
+            .map_err(|e| format!("Failed to lock provider links: {}", e))?;
+        serde_json::to_value(&*links).map_err(|e| format!("Failed to serialize provider links: {}", e))?
+    };
+
+    store.set(PROVIDER_LINKS_KEY, links_value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save episode mapping store: {}", e))
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Get the ID and episode mapping of an entry (by AniList ID unless `source` is given)
+#[tauri::command]
+pub async fn episode_mapping_get(
+    id: u64,
+    source: Option<IdSource>,
+    refresh: Option<bool>,
+    app: AppHandle,
+    state: State<'_, EpisodeMappingState>,
+) -> Result<AnimeMapping, String> {
+    get_mapping(&app, &state, source.unwrap_or_default(), id, refresh.unwrap_or(false)).await
+}
+
+/// Translate an episode number of the given AniList entries into all three numberings
+///
+/// Returns `None` when the number isn't part of any of the entries.
+#[tauri::command]
+pub async fn episode_mapping_resolve(
+    anilist_ids: Vec<u64>,
+    number: u32,
+    numbering: Numbering,
+    season: Option<u32>,
+    app: AppHandle,
+    state: State<'_, EpisodeMappingState>,
+) -> Result<Option<ResolvedEpisode>, String> {
+    let mappings = get_anilist_mappings(&app, &state, &anilist_ids).await?;
+    Ok(resolve_episode(&mappings, number, numbering, season))
+}
+
+/// Link a provider's episode list to AniList entries
+///
+/// Pass every entry the provider's anime covers, in airing order (both parts of a
+/// split-cour season). The numbering is detected unless given.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn episode_mapping_link_provider(
+    plugin_id: String,
+    provider_anime_id: String,
+    anilist_ids: Vec<u64>,
+    episodes: Vec<ProviderEpisode>,
+    numbering: Option<Numbering>,
+    season: Option<u32>,
+    app: AppHandle,
+    state: State<'_, EpisodeMappingState>,
+) -> Result<ProviderLink, String> {
+    ensure_links_loaded(&app, &state);
+    let mappings = get_anilist_mappings(&app, &state, &anilist_ids).await?;
+    let (numbering, linked, unmapped) = link_episodes(&mappings, &episodes, numbering, season);
+
+    let link = ProviderLink {
+        plugin_id: plugin_id.clone(),
+        provider_anime_id: provider_anime_id.clone(),
+        anilist_ids,
+        numbering,
+        season,
+        episodes: linked,
+        unmapped,
+        linked_at: get_current_timestamp(),
+    };
+    log::info!(
+        "Linked {}:{} with {:?} numbering ({} episodes mapped, {} unmapped)",
+        plugin_id,
+        provider_anime_id,
+        link.numbering,
+        link.episodes.len(),
+        link.unmapped.len()
+    );
+
+    state
+        .links
+        .lock()
+        .unwrap()
+        .insert(link_key(&plugin_id, &provider_anime_id), link.clone());
+    save_links_to_store(&app, &state)?;
+    Ok(link)
+}
+
+/// AniList entry and episode of a linked provider episode
+#[tauri::command]
+pub fn episode_mapping_from_provider(
+    plugin_id: String,
+    provider_anime_id: String,
+    episode_id: String,
+    app: AppHandle,
+    state: State<'_, EpisodeMappingState>,
+) -> Result<Option<ResolvedEpisode>, String> {
+    ensure_links_loaded(&app, &state);
+    let links = state.links.lock().unwrap();
+    Ok(links
+        .get(&link_key(&plugin_id, &provider_anime_id))
+        .and_then(|link| link.episodes.get(&episode_id))
+        .cloned())
+}
+
+/// Provider episode ID of an AniList episode
+#[tauri::command]
+pub fn episode_mapping_to_provider(
+    plugin_id: String,
+    provider_anime_id: String,
+    anilist_id: u64,
+    episode: u32,
+    app: AppHandle,
+    state: State<'_, EpisodeMappingState>,
+) -> Result<Option<String>, String> {
+    ensure_links_loaded(&app, &state);
+    let links = state.links.lock().unwrap();
+    Ok(links.get(&link_key(&plugin_id, &provider_anime_id)).and_then(|link| {
+        link.episodes
+            .iter()
+            .find(|(_, e)| e.anilist_id == anilist_id && e.episode == episode)
+            .map(|(id, _)| id.clone())
+    }))
+}
+
+/// Remove a provider link
+#[tauri::command]
+pub fn episode_mapping_unlink_provider(
+    plugin_id: String,
+    provider_anime_id: String,
+    app: AppHandle,
+    state: State<'_, EpisodeMappingState>,
+) -> Result<(), String> {
+    ensure_links_loaded(&app, &state);
+    let removed = state
+        .links
+        .lock()
+        .unwrap()
+        .remove(&link_key(&plugin_id, &provider_anime_id))
+        .is_some();
+    if removed {
+        save_links_to_store(&app, &state)?;
+    }
+    Ok(())
+}
+
+/// Delete the cached mappings
+#[tauri::command]
+pub fn episode_mapping_clear_cache(app: AppHandle, state: State<'_, EpisodeMappingState>) -> Result<(), String> {
+    state.mappings.lock().unwrap().clear();
+    let folder = mapping_cache_folder(&app)?;
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder).map_err(|e| format!("Failed to clear mapping cache: {}", e))?;
+    }
+    log::info!("Cleared episode mapping cache");
+    Ok(())
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Attack on Titan: The Final Season (16 episodes) and Part 2 (12 episodes), both
+    /// TVDB season 4
+    const PART1_FIXTURE: &str = include_str!("../tests/fixtures/anizip_final_season.json");
+    const PART2_FIXTURE: &str = include_str!("../tests/fixtures/anizip_final_season_part2.json");
+
+    fn final_season() -> Vec<AnimeMapping> {
+        vec![
+            parse_anizip(PART1_FIXTURE, 1_000).unwrap(),
+            parse_anizip(PART2_FIXTURE, 1_000).unwrap(),
+        ]
+    }
+
+    fn provider_episodes(numbers: &[f64]) -> Vec<ProviderEpisode> {
+        numbers
+            .iter()
+            .map(|n| ProviderEpisode { id: format!("ep-{}", n), number: *n })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_anizip() {
+        let part2 = parse_anizip(PART2_FIXTURE, 1_000).unwrap();
+        assert_eq!(part2.ids.anilist_id, Some(131681));
+        assert_eq!(part2.ids.mal_id, Some(48583));
+        assert_eq!(part2.ids.tvdb_id, Some(267440));
+        assert_eq!(part2.ids.imdb_id.as_deref(), Some("tt2560140"));
+        assert_eq!(part2.ids.livechart_id, None);
+        assert_eq!(part2.title.as_deref(), Some("Attack on Titan Final Season Part 2"));
+        assert_eq!(part2.episode_count, Some(12));
+
+        // The special ("S1") is left out
+        assert_eq!(part2.episodes.len(), 12);
+        let first = &part2.episodes[0];
+        assert_eq!(first.episode, 1);
+        assert_eq!((first.season, first.season_episode, first.absolute), (Some(4), Some(17), Some(76)));
+        assert_eq!(first.air_date.as_deref(), Some("2022-01-10"));
+        assert_eq!(part2.episodes[11].episode, 12);
+
+        assert!(parse_anizip("{}", 0).is_err());
+        assert!(parse_anizip("not json", 0).is_err());
+    }
+
+    #[test]
+    fn test_resolve_episode() {
+        let mappings = final_season();
+        let part2 = &mappings[1..];
+
+        let by_absolute = resolve_episode(part2, 80, Numbering::Absolute, None).unwrap();
+        assert_eq!((by_absolute.anilist_id, by_absolute.episode), (131681, 5));
+        assert_eq!(by_absolute.season_episode, Some(21));
+
+        let by_season = resolve_episode(part2, 17, Numbering::Season, None).unwrap();
+        assert_eq!((by_season.episode, by_season.absolute), (1, Some(76)));
+
+        let by_anilist = resolve_episode(part2, 12, Numbering::Anilist, None).unwrap();
+        assert_eq!((by_anilist.season_episode, by_anilist.absolute), (Some(28), Some(87)));
+
+        assert_eq!(resolve_episode(part2, 13, Numbering::Anilist, None), None);
+        assert_eq!(resolve_episode(part2, 3, Numbering::Season, None), None);
+        assert_eq!(resolve_episode(part2, 17, Numbering::Season, Some(3)), None);
+
+        // Season numbers continue across the split cour
+        let part1_ep = resolve_episode(&mappings, 3, Numbering::Season, None).unwrap();
+        assert_eq!((part1_ep.anilist_id, part1_ep.episode), (110277, 3));
+        let part2_ep = resolve_episode(&mappings, 20, Numbering::Season, None).unwrap();
+        assert_eq!((part2_ep.anilist_id, part2_ep.episode), (131681, 4));
+    }
+
+    #[test]
+    fn test_detect_numbering() {
+        let mappings = final_season();
+        let part2 = &mappings[1..];
+
+        let anilist: Vec<u32> = (1..=12).collect();
+        let season: Vec<u32> = (17..=28).collect();
+        let absolute: Vec<u32> = (76..=87).collect();
+        assert_eq!(detect_numbering(part2, &anilist, None), Numbering::Anilist);
+        assert_eq!(detect_numbering(part2, &season, None), Numbering::Season);
+        assert_eq!(detect_numbering(part2, &absolute, None), Numbering::Absolute);
+
+        // A provider listing the whole season as one anime
+        let whole_season: Vec<u32> = (1..=28).collect();
+        assert_eq!(detect_numbering(&mappings, &whole_season, None), Numbering::Season);
+
+        assert_eq!(detect_numbering(part2, &[], None), Numbering::Anilist);
+    }
+
+    #[test]
+    fn test_link_split_cour_provider() {
+        let mappings = final_season();
+        let mut numbers: Vec<f64> = (1..=28).map(f64::from).collect();
+        numbers.push(16.5);
+        let episodes = provider_episodes(&numbers);
+
+        let (numbering, linked, unmapped) = link_episodes(&mappings, &episodes, None, None);
+        assert_eq!(numbering, Numbering::Season);
+        assert_eq!(linked.len(), 28);
+        assert_eq!(unmapped, vec!["ep-16.5".to_string()]);
+
+        let ep16 = &linked["ep-16"];
+        assert_eq!((ep16.anilist_id, ep16.episode), (110277, 16));
+        let ep17 = &linked["ep-17"];
+        assert_eq!((ep17.anilist_id, ep17.episode, ep17.absolute), (131681, 1, Some(76)));
+
+        // Forced numbering
+        let (numbering, linked, unmapped) =
+            link_episodes(&mappings[1..], &provider_episodes(&[1.0, 2.0]), Some(Numbering::Absolute), None);
+        assert_eq!(numbering, Numbering::Absolute);
+        assert!(linked.is_empty());
+        assert_eq!(unmapped.len(), 2);
+    }
+
+    #[test]
+    fn test_cache_freshness() {
+        let mut mapping = parse_anizip(PART2_FIXTURE, 1_000).unwrap();
+        mapping.fetched_at = 0;
+        assert!(is_cache_fresh(&mapping, MAPPING_TTL_MS - 1));
+        assert!(!is_cache_fresh(&mapping, MAPPING_TTL_MS));
+
+        // Airing: episodes without TVDB data yet are refreshed sooner
+        mapping.episode_count = Some(13);
+        assert!(!is_cache_fresh(&mapping, INCOMPLETE_MAPPING_TTL_MS));
+
+        assert_eq!(mapping_key(IdSource::Mal, 48583), "mal-48583");
+    }
+}
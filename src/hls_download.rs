@@ -0,0 +1,973 @@
+//! HLS Downloads
+//!
+//! This module saves HLS (`m3u8`) streams, typically returned by plugin `getStreams`
+//! calls, as a single file in `Settings.downloads_folder`. The variant closest to the
+//! preferred quality is picked from the master playlist, its segments are fetched
+//! concurrently with retries (decrypting AES-128 segments), and the result is remuxed
+//! into an MP4 with ffmpeg, or kept as MPEG-TS when ffmpeg is not available.
+//!
+//! Jobs run inside the download queue and report their progress to it. Segments are
+//! kept in a hidden work folder next to the output until the job finishes, so a failed
+//! or interrupted job resumes where it stopped.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest::{self, Url};
+
+use crate::audio_fingerprint::find_ffmpeg;
+use crate::downloads::{self, DownloadJob, DownloadSource, DownloadStatus};
+
+/// Number of segments fetched at the same time
+const SEGMENT_CONCURRENCY: usize = 4;
+
+/// Attempts per request before the job fails
+const FETCH_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry (doubled after every attempt)
+const RETRY_DELAY_MS: u64 = 1000;
+
+/// Timeout of a single request
+const REQUEST_TIMEOUT_SECS: u64 = 60;
+
+/// Maximum size of a playlist
+const MAX_PLAYLIST_BYTES: usize = 8 * 1024 * 1024;
+
+/// Minimum time between progress updates of a job
+const PROGRESS_INTERVAL_MS: i64 = 1000;
+
+/// A variant stream of a master playlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub uri: String,
+    /// Peak bit rate in bits per second
+    pub bandwidth: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Group of the separate audio renditions this variant plays with
+    pub audio_group: Option<String>,
+}
+
+/// An alternative audio rendition of a master playlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioRendition {
+    pub group_id: String,
+    /// Media playlist (`None` when the audio is muxed into the variants)
+    pub uri: Option<String>,
+    pub language: Option<String>,
+    pub default: bool,
+}
+
+/// A master playlist
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MasterPlaylist {
+    pub variants: Vec<Variant>,
+    pub audio: Vec<AudioRendition>,
+}
+
+/// Encryption of a segment
+#[derive(Debug, Clone, PartialEq)]
+pub enum Encryption {
+    None,
+    /// AES-128-CBC with PKCS#7 padding; without an IV the media sequence number is used
+    Aes128 { key_uri: String, iv: Option<[u8; 16]> },
+}
+
+/// A media segment (or init section)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub uri: String,
+    /// Byte range as (offset, length)
+    pub byte_range: Option<(u64, u64)>,
+    pub encryption: Encryption,
+    /// Media sequence number
+    pub sequence: u64,
+    /// Duration in seconds
+    pub duration: f64,
+}
+
+/// A media playlist
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaPlaylist {
+    /// Init section of fragmented MP4 streams (`EXT-X-MAP`)
+    pub init: Option<Segment>,
+    pub segments: Vec<Segment>,
+    /// Whether the playlist is complete (`EXT-X-ENDLIST`)
+    pub ended: bool,
+}
+
+/// A parsed playlist
+#[derive(Debug, Clone, PartialEq)]
+pub enum Playlist {
+    Master(MasterPlaylist),
+    Media(MediaPlaylist),
+}
+
+/// HLS download state: cancellation flags of running jobs
+#[derive(Default)]
+pub struct HlsDownloadState {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Playlist Parsing
+// =============================================================================
+
+/// Parse an attribute list (`KEY=value,KEY="quoted, value"`)
+fn parse_attributes(list: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = list.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => match after.find(',') {
+                Some(end) => (&after[..end], &after[end..]),
+                None => (after, ""),
+            },
+        };
+        attributes.insert(key.trim().to_uppercase(), value.to_string());
+        rest = after.trim_start_matches(',').trim_start();
+    }
+    attributes
+}
+
+/// Parse a 128-bit hexadecimal IV (`0x...`)
+fn parse_iv(value: &str) -> Result<[u8; 16], String> {
+    let hex = value.trim().trim_start_matches("0x").trim_start_matches("0X");
+    if hex.len() != 32 || !hex.is_ascii() {
+        return Err(format!("Invalid IV: {}", value));
+    }
+    let mut iv = [0u8; 16];
+    for (i, byte) in iv.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("Invalid IV: {}", value))?;
+    }
+    Ok(iv)
+}
+
+/// Parse a byte range (`length[@offset]`); without an offset it follows `previous_end`
+fn parse_byte_range(value: &str, previous_end: u64) -> Result<(u64, u64), String> {
+    let invalid = || format!("Invalid byte range: {}", value);
+    let (length, offset) = match value.trim().split_once('@') {
+        Some((length, offset)) => (length, offset.parse().map_err(|_| invalid())?),
+        None => (value.trim(), previous_end),
+    };
+    Ok((offset, length.parse().map_err(|_| invalid())?))
+}
+
+/// Parse a master or media playlist; URIs are resolved against `base`
+pub fn parse_playlist(body: &str, base: &Url) -> Result<Playlist, String> {
+    if !body.trim_start_matches('\u{feff}').trim_start().starts_with("#EXTM3U") {
+        return Err("Not an HLS playlist".to_string());
+    }
+    let resolve = |uri: &str| {
+        base.join(uri.trim())
+            .map(|url| url.to_string())
+            .map_err(|_| format!("Invalid URI in playlist: {}", uri))
+    };
+
+    let mut master = MasterPlaylist::default();
+    let mut is_master = false;
+    let mut pending_variant: Option<Variant> = None;
+
+    let mut init = None;
+    let mut segments = Vec::new();
+    let mut encryption = Encryption::None;
+    let mut sequence = 0u64;
+    let mut duration = 0.0;
+    let mut byte_range = None;
+    let mut range_end = 0u64;
+    let mut ended = false;
+
+    for line in body.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(tag) = line.strip_prefix('#') {
+            let (name, value) = tag.split_once(':').unwrap_or((tag, ""));
+            match name {
+                "EXT-X-STREAM-INF" => {
+                    is_master = true;
+                    let attributes = parse_attributes(value);
+                    let resolution = attributes
+                        .get("RESOLUTION")
+                        .and_then(|r| r.split_once(['x', 'X']));
+                    pending_variant = Some(Variant {
+                        uri: String::new(),
+                        bandwidth: attributes
+                            .get("BANDWIDTH")
+                            .and_then(|b| b.parse().ok())
+                            .unwrap_or(0),
+                        width: resolution.and_then(|(w, _)| w.parse().ok()),
+                        height: resolution.and_then(|(_, h)| h.parse().ok()),
+                        audio_group: attributes.get("AUDIO").cloned(),
+                    });
+                }
+                "EXT-X-MEDIA" => {
+                    is_master = true;
+                    let attributes = parse_attributes(value);
+                    if attributes.get("TYPE").map(String::as_str) != Some("AUDIO") {
+                        continue;
+                    }
+                    if let Some(group_id) = attributes.get("GROUP-ID") {
+                        master.audio.push(AudioRendition {
+                            group_id: group_id.clone(),
+                            uri: attributes.get("URI").map(|uri| resolve(uri)).transpose()?,
+                            language: attributes.get("LANGUAGE").cloned(),
+                            default: attributes.get("DEFAULT").is_some_and(|d| d == "YES"),
+                        });
+                    }
+                }
+                "EXT-X-MEDIA-SEQUENCE" => sequence = value.trim().parse().unwrap_or(0),
+                "EXTINF" => {
+                    duration = value
+                        .split(',')
+                        .next()
+                        .and_then(|d| d.trim().parse().ok())
+                        .unwrap_or(0.0);
+                }
+                "EXT-X-BYTERANGE" => byte_range = Some(parse_byte_range(value, range_end)?),
+                "EXT-X-KEY" => {
+                    let attributes = parse_attributes(value);
+                    encryption = match attributes.get("METHOD").map(String::as_str) {
+                        None | Some("NONE") => Encryption::None,
+                        Some("AES-128") => {
+                            let uri = attributes.get("URI").ok_or("EXT-X-KEY without URI")?;
+                            Encryption::Aes128 {
+                                key_uri: resolve(uri)?,
+                                iv: attributes.get("IV").map(|iv| parse_iv(iv)).transpose()?,
+                            }
+                        }
+                        Some(method) => return Err(format!("Unsupported HLS encryption: {}", method)),
+                    };
+                }
+                "EXT-X-MAP" => {
+                    let attributes = parse_attributes(value);
+                    let uri = attributes.get("URI").ok_or("EXT-X-MAP without URI")?;
+                    init = Some(Segment {
+                        uri: resolve(uri)?,
+                        byte_range: attributes
+                            .get("BYTERANGE")
+                            .map(|r| parse_byte_range(r, 0))
+                            .transpose()?,
+                        encryption: encryption.clone(),
+                        sequence,
+                        duration: 0.0,
+                    });
+                }
+                "EXT-X-ENDLIST" => ended = true,
+                _ => {}
+            }
+            continue;
+        }
+
+        if let Some(mut variant) = pending_variant.take() {
+            variant.uri = resolve(line)?;
+            master.variants.push(variant);
+            continue;
+        }
+        if is_master {
+            continue;
+        }
+
+        let range = byte_range.take();
+        if let Some((offset, length)) = range {
+            range_end = offset + length;
+        }
+        segments.push(Segment {
+            uri: resolve(line)?,
+            byte_range: range,
+            encryption: encryption.clone(),
+            sequence,
+            duration,
+        });
+        sequence += 1;
+        duration = 0.0;
+    }
+
+    if is_master {
+        if master.variants.is_empty() {
+            return Err("Master playlist has no variants".to_string());
+        }
+        return Ok(Playlist::Master(master));
+    }
+    if segments.is_empty() {
+        return Err("Playlist has no segments".to_string());
+    }
+    Ok(Playlist::Media(MediaPlaylist { init, segments, ended }))
+}
+
+/// Pick the best variant not taller than `max_height` (or the smallest one if all are
+/// taller); without a preference the best variant is picked
+pub fn select_variant(variants: &[Variant], max_height: Option<u32>) -> Option<&Variant> {
+    let target = match max_height {
+        Some(height) if variants.iter().any(|v| v.height.is_some()) => height,
+        _ => return variants.iter().max_by_key(|v| (v.height.unwrap_or(0), v.bandwidth)),
+    };
+    variants
+        .iter()
+        .filter(|v| v.height.is_some_and(|h| h <= target))
+        .max_by_key(|v| (v.height, v.bandwidth))
+        .or_else(|| {
+            variants
+                .iter()
+                .filter(|v| v.height.is_some())
+                .min_by_key(|v| (v.height, std::cmp::Reverse(v.bandwidth)))
+        })
+}
+
+/// Separate audio rendition to download along with a variant (if its audio isn't muxed in)
+pub fn select_audio<'a>(master: &'a MasterPlaylist, variant: &Variant) -> Option<&'a AudioRendition> {
+    let group = variant.audio_group.as_deref()?;
+    let renditions: Vec<&AudioRendition> =
+        master.audio.iter().filter(|a| a.group_id == group).collect();
+    let rendition = renditions
+        .iter()
+        .find(|a| a.default)
+        .or_else(|| renditions.first())?;
+    rendition.uri.as_ref().map(|_| *rendition)
+}
+
+// =============================================================================
+// Decryption
+// =============================================================================
+
+/// IV of a segment without an explicit IV: its media sequence number
+pub fn sequence_iv(sequence: u64) -> [u8; 16] {
+    let mut iv = [0u8; 16];
+    iv[8..].copy_from_slice(&sequence.to_be_bytes());
+    iv
+}
+
+/// Decrypt an AES-128-CBC segment
+pub fn decrypt_aes128(data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> Result<Vec<u8>, String> {
+    use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+
+    cbc::Decryptor::<aes::Aes128>::new(key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| "Failed to decrypt segment (wrong key or corrupted data)".to_string())
+}
+
+// =============================================================================
+// Fetching
+// =============================================================================
+
+/// HTTP client with the stream's request headers
+struct Fetcher {
+    client: reqwest::Client,
+    headers: HashMap<String, String>,
+}
+
+impl Fetcher {
+    fn new(headers: HashMap<String, String>) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(Fetcher { client, headers })
+    }
+
+    async fn fetch(&self, url: &str, range: Option<(u64, u64)>) -> Result<Vec<u8>, String> {
+        let mut request = self.client.get(url);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some((offset, length)) = range {
+            let last = offset + length.saturating_sub(1);
+            request = request.header("range", format!("bytes={}-{}", offset, last));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("HTTP {}", status));
+        }
+        let data = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?
+            .to_vec();
+
+        // Servers that ignore the range send the whole resource
+        match range {
+            Some((offset, length)) if status.as_u16() == 200 => {
+                let start = (offset as usize).min(data.len());
+                let end = (offset + length).min(data.len() as u64) as usize;
+                Ok(data[start..end].to_vec())
+            }
+            _ => Ok(data),
+        }
+    }
+
+    /// Fetch with retries and exponential backoff
+    async fn fetch_retrying(
+        &self,
+        url: &str,
+        range: Option<(u64, u64)>,
+        stop: &AtomicBool,
+    ) -> Result<Vec<u8>, String> {
+        let mut attempt = 1;
+        loop {
+            match self.fetch(url, range).await {
+                Ok(data) => return Ok(data),
+                Err(e) if attempt < FETCH_ATTEMPTS && !stop.load(Ordering::Relaxed) => {
+                    log::debug!("Retrying {} (attempt {}): {}", url, attempt, e);
+                    tokio::time::sleep(Duration::from_millis(RETRY_DELAY_MS << (attempt - 1))).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn playlist(&self, url: &Url) -> Result<Playlist, String> {
+        let body = self.fetch_retrying(url.as_str(), None, &AtomicBool::new(false)).await?;
+        if body.len() > MAX_PLAYLIST_BYTES {
+            return Err("Playlist is too large".to_string());
+        }
+        parse_playlist(&String::from_utf8_lossy(&body), url)
+    }
+
+    async fn media_playlist(&self, uri: &str) -> Result<MediaPlaylist, String> {
+        let url = Url::parse(uri).map_err(|e| format!("Invalid playlist URL: {}", e))?;
+        match self.playlist(&url).await? {
+            Playlist::Media(media) => Ok(media),
+            Playlist::Master(_) => Err("Variant playlist is a master playlist".to_string()),
+        }
+    }
+
+    /// Fetch a segment and decrypt it
+    async fn segment(
+        &self,
+        segment: &Segment,
+        keys: &HashMap<String, [u8; 16]>,
+        stop: &AtomicBool,
+    ) -> Result<Vec<u8>, String> {
+        let data = self.fetch_retrying(&segment.uri, segment.byte_range, stop).await?;
+        match &segment.encryption {
+            Encryption::None => Ok(data),
+            Encryption::Aes128 { key_uri, iv } => {
+                let key = keys.get(key_uri).ok_or("Missing decryption key")?;
+                decrypt_aes128(&data, key, &iv.unwrap_or_else(|| sequence_iv(segment.sequence)))
+            }
+        }
+    }
+
+    /// Fetch every key a playlist uses
+    async fn keys(
+        &self,
+        playlist: &MediaPlaylist,
+        stop: &AtomicBool,
+    ) -> Result<HashMap<String, [u8; 16]>, String> {
+        let mut keys = HashMap::new();
+        for segment in playlist.init.iter().chain(&playlist.segments) {
+            if let Encryption::Aes128 { key_uri, .. } = &segment.encryption {
+                if keys.contains_key(key_uri) {
+                    continue;
+                }
+                let key: [u8; 16] = self
+                    .fetch_retrying(key_uri, None, stop)
+                    .await
+                    .map_err(|e| format!("Failed to fetch key: {}", e))?
+                    .try_into()
+                    .map_err(|_| "Invalid AES-128 key length".to_string())?;
+                keys.insert(key_uri.clone(), key);
+            }
+        }
+        Ok(keys)
+    }
+}
+
+// =============================================================================
+// Download Jobs
+// =============================================================================
+
+/// Progress of a job across all its tracks
+struct Progress {
+    app: AppHandle,
+    job_id: String,
+    total: usize,
+    done: AtomicUsize,
+    bytes: AtomicU64,
+    last_report: Mutex<i64>,
+}
+
+impl Progress {
+    /// Count a finished segment and report to the queue now and then
+    fn advance(&self, bytes: u64) {
+        let done = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+        let downloaded = self.bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+
+        let now = get_current_timestamp();
+        match self.last_report.lock() {
+            Ok(mut last) if done >= self.total || now - *last >= PROGRESS_INTERVAL_MS => *last = now,
+            _ => return,
+        }
+        // Remuxing takes the last percent
+        let progress = done as f64 / self.total.max(1) as f64 * 0.99;
+        downloads::update_job(&self.app, &self.job_id, |j| {
+            j.progress = progress;
+            j.downloaded_bytes = downloaded;
+        });
+    }
+}
+
+/// Folder holding the segments of a job until it finishes
+fn work_dir(job: &DownloadJob) -> PathBuf {
+    Path::new(&job.destination).join(format!(".{}.hls", job.id))
+}
+
+/// Write a file atomically so that existing segment files are always complete
+fn write_part(path: &Path, data: &[u8]) -> Result<(), String> {
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, data)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Download the init section and segments of a track; returns the files in order
+async fn download_track(
+    fetcher: Arc<Fetcher>,
+    playlist: MediaPlaylist,
+    dir: PathBuf,
+    progress: Arc<Progress>,
+    cancelled: Arc<AtomicBool>,
+) -> Result<Vec<PathBuf>, String> {
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create work folder: {}", e))?;
+    let keys = Arc::new(fetcher.keys(&playlist, &cancelled).await?);
+
+    let mut files = Vec::with_capacity(playlist.segments.len() + 1);
+    if let Some(init) = &playlist.init {
+        let path = dir.join("init.mp4");
+        if !path.exists() {
+            let data = fetcher.segment(init, &keys, &cancelled).await?;
+            write_part(&path, &data)?;
+        }
+        progress.advance(0);
+        files.push(path);
+    }
+    files.extend((0..playlist.segments.len()).map(|i| dir.join(format!("{:06}.seg", i))));
+
+    let segments = Arc::new(playlist.segments);
+    let next = Arc::new(AtomicUsize::new(0));
+    let failed = Arc::new(AtomicBool::new(false));
+    let workers: Vec<_> = (0..SEGMENT_CONCURRENCY.min(segments.len()))
+        .map(|_| {
+            let (fetcher, keys, segments, next, failed, progress, cancelled, dir) = (
+                fetcher.clone(),
+                keys.clone(),
+                segments.clone(),
+                next.clone(),
+                failed.clone(),
+                progress.clone(),
+                cancelled.clone(),
+                dir.clone(),
+            );
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let segment = match segments.get(index) {
+                        Some(segment) => segment,
+                        None => return Ok(()),
+                    };
+                    if cancelled.load(Ordering::Relaxed) || failed.load(Ordering::Relaxed) {
+                        return Err("Download stopped".to_string());
+                    }
+
+                    let path = dir.join(format!("{:06}.seg", index));
+                    let size = match std::fs::metadata(&path) {
+                        Ok(metadata) => metadata.len(),
+                        Err(_) => match fetcher.segment(segment, &keys, &cancelled).await {
+                            Ok(data) => {
+                                write_part(&path, &data)?;
+                                data.len() as u64
+                            }
+                            Err(e) => {
+                                failed.store(true, Ordering::Relaxed);
+                                return Err(format!("Segment {} failed: {}", index + 1, e));
+                            }
+                        },
+                    };
+                    progress.advance(size);
+                }
+            })
+        })
+        .collect();
+
+    let mut first_error = None;
+    for worker in workers {
+        let result = worker.await.map_err(|e| format!("Segment worker failed: {}", e)).and_then(|r| r);
+        if let Err(e) = result {
+            // Report the error that stopped the other workers
+            if first_error.is_none() || !e.starts_with("Download stopped") {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(files),
+    }
+}
+
+/// Concatenate files into one
+fn concat(parts: &[PathBuf], output: &Path) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    for part in parts {
+        let mut input = File::open(part).map_err(|e| format!("Failed to open {}: {}", part.display(), e))?;
+        std::io::copy(&mut input, &mut writer).map_err(|e| format!("Failed to join segments: {}", e))?;
+    }
+    writer.flush().map_err(|e| format!("Failed to join segments: {}", e))
+}
+
+/// Remux (and merge) inputs into an MP4 without re-encoding
+fn remux(ffmpeg: &Path, inputs: &[PathBuf], output: &Path) -> Result<(), String> {
+    let mut command = Command::new(ffmpeg);
+    command.args(["-nostdin", "-hide_banner", "-loglevel", "error", "-y"]);
+    for input in inputs {
+        command.arg("-i").arg(input);
+    }
+    for index in 0..inputs.len() {
+        command.args(["-map", &index.to_string()]);
+    }
+    let result = command
+        .args(["-c", "copy", "-movflags", "+faststart"])
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !result.status.success() {
+        let _ = std::fs::remove_file(output);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("ffmpeg failed: {}", stderr.lines().last().unwrap_or("unknown error")));
+    }
+    Ok(())
+}
+
+/// File name for a job title
+fn file_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "download".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// First free `{stem}.{extension}` / `{stem} (n).{extension}` path in a folder
+fn unique_path(dir: &Path, stem: &str, extension: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", stem, extension));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{} ({}).{}", stem, n, extension));
+        n += 1;
+    }
+    path
+}
+
+/// Join the downloaded tracks into the output file (blocking)
+fn assemble(job: &DownloadJob, work: &Path, tracks: &[Vec<PathBuf>], fragmented: bool) -> Result<PathBuf, String> {
+    let destination = Path::new(&job.destination);
+    let stem = file_stem(&job.title);
+    // Concatenated fragmented MP4 segments are a valid MP4 file
+    let extension = if fragmented { "mp4" } else { "ts" };
+
+    let mut joined = Vec::with_capacity(tracks.len());
+    for (index, parts) in tracks.iter().enumerate() {
+        let path = work.join(format!("track{}.{}", index, extension));
+        concat(parts, &path)?;
+        joined.push(path);
+    }
+
+    match find_ffmpeg() {
+        Some(ffmpeg) => {
+            let output = unique_path(destination, &stem, "mp4");
+            match remux(&ffmpeg, &joined, &output) {
+                Ok(()) => return Ok(output),
+                Err(e) if joined.len() == 1 => {
+                    log::warn!("Remuxing '{}' failed, keeping the {} file: {}", job.title, extension, e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        None if joined.len() > 1 => {
+            return Err("ffmpeg is required to merge separate audio and video streams".to_string())
+        }
+        None => {}
+    }
+
+    let output = unique_path(destination, &stem, extension);
+    std::fs::rename(&joined[0], &output).map_err(|e| format!("Failed to move download: {}", e))?;
+    Ok(output)
+}
+
+/// Download an HLS job into its destination folder
+async fn run(app: &AppHandle, job: &DownloadJob, cancelled: Arc<AtomicBool>) -> Result<PathBuf, String> {
+    let (url, headers, quality) = match &job.source {
+        DownloadSource::Hls { url, headers, quality } => (url, headers, *quality),
+        _ => return Err("Not an HLS download".to_string()),
+    };
+    let fetcher = Arc::new(Fetcher::new(headers.clone())?);
+    let base = Url::parse(url).map_err(|e| format!("Invalid stream URL: {}", e))?;
+
+    let mut tracks = Vec::new();
+    match fetcher.playlist(&base).await? {
+        Playlist::Media(media) => tracks.push(media),
+        Playlist::Master(master) => {
+            let variant = select_variant(&master.variants, quality).ok_or("No playable variant")?;
+            log::info!(
+                "Downloading '{}' at {}p ({} bit/s)",
+                job.title,
+                variant.height.unwrap_or(0),
+                variant.bandwidth
+            );
+            tracks.push(fetcher.media_playlist(&variant.uri).await?);
+            if let Some(audio) = select_audio(&master, variant).and_then(|a| a.uri.as_deref()) {
+                tracks.push(fetcher.media_playlist(audio).await?);
+            }
+        }
+    }
+    if tracks.iter().any(|t| !t.ended) {
+        log::warn!("'{}' is a live playlist, downloading the segments listed so far", job.title);
+    }
+
+    let fragmented = tracks[0].init.is_some();
+    let progress = Arc::new(Progress {
+        app: app.clone(),
+        job_id: job.id.clone(),
+        total: tracks.iter().map(|t| t.segments.len() + t.init.is_some() as usize).sum(),
+        done: AtomicUsize::new(0),
+        bytes: AtomicU64::new(0),
+        last_report: Mutex::new(0),
+    });
+
+    let work = work_dir(job);
+    let mut files = Vec::with_capacity(tracks.len());
+    for (index, track) in tracks.into_iter().enumerate() {
+        let dir = work.join(format!("track{}", index));
+        files.push(download_track(fetcher.clone(), track, dir, progress.clone(), cancelled.clone()).await?);
+    }
+
+    let job = job.clone();
+    let output = tauri::async_runtime::spawn_blocking(move || assemble(&job, &work, &files, fragmented))
+        .await
+        .map_err(|e| format!("Failed to assemble download: {}", e))??;
+    Ok(output)
+}
+
+/// Start downloading an HLS job in the background (no-op if it's already running)
+pub fn start(app: &AppHandle, job: DownloadJob) {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let state = app.state::<HlsDownloadState>();
+        let mut running = match state.running.lock() {
+            Ok(running) => running,
+            Err(_) => return,
+        };
+        if running.contains_key(&job.id) {
+            return;
+        }
+        running.insert(job.id.clone(), cancelled.clone());
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = run(&app, &job, cancelled.clone()).await;
+        if let Ok(mut running) = app.state::<HlsDownloadState>().running.lock() {
+            running.remove(&job.id);
+        }
+
+        if cancelled.load(Ordering::Relaxed) {
+            let _ = std::fs::remove_dir_all(work_dir(&job));
+            return;
+        }
+        match result {
+            Ok(output) => {
+                let _ = std::fs::remove_dir_all(work_dir(&job));
+                let size = std::fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+                log::info!("Downloaded '{}' to {}", job.title, output.display());
+                downloads::update_job(&app, &job.id, |j| {
+                    j.status = DownloadStatus::Completed;
+                    j.progress = 1.0;
+                    j.downloaded_bytes = size;
+                    j.total_bytes = Some(size);
+                });
+            }
+            Err(e) => {
+                log::warn!("HLS download '{}' failed: {}", job.title, e);
+                downloads::update_job(&app, &job.id, |j| {
+                    j.status = DownloadStatus::Failed;
+                    j.error = Some(e);
+                });
+            }
+        }
+    });
+}
+
+/// Whether a job is being downloaded right now
+pub fn is_running(app: &AppHandle, id: &str) -> bool {
+    app.state::<HlsDownloadState>()
+        .running
+        .lock()
+        .map(|running| running.contains_key(id))
+        .unwrap_or(false)
+}
+
+/// Stop a running job (its segments are deleted)
+pub fn cancel(app: &AppHandle, id: &str) {
+    if let Ok(running) = app.state::<HlsDownloadState>().running.lock() {
+        if let Some(cancelled) = running.get(id) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_master_and_select_variant() {
+        let body = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",NAME=\"Japanese, stereo\",LANGUAGE=\"ja\",DEFAULT=YES,URI=\"audio/ja.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e,mp4a.40.2\",AUDIO=\"aac\"\n\
+            360p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,RESOLUTION=1280x720,AUDIO=\"aac\"\n\
+            720p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,AUDIO=\"aac\"\n\
+            https://cdn.example.com/1080p.m3u8\n";
+        let base = Url::parse("https://example.com/show/master.m3u8?token=1").unwrap();
+        let master = match parse_playlist(body, &base).unwrap() {
+            Playlist::Master(master) => master,
+            other => panic!("expected a master playlist, got {:?}", other),
+        };
+
+        assert_eq!(master.variants.len(), 3);
+        assert_eq!(master.variants[0].uri, "https://example.com/show/360p.m3u8");
+        assert_eq!(master.variants[0].width, Some(640));
+        assert_eq!(master.variants[2].uri, "https://cdn.example.com/1080p.m3u8");
+
+        assert_eq!(select_variant(&master.variants, None).unwrap().height, Some(1080));
+        assert_eq!(select_variant(&master.variants, Some(720)).unwrap().height, Some(720));
+        assert_eq!(select_variant(&master.variants, Some(900)).unwrap().height, Some(720));
+        assert_eq!(select_variant(&master.variants, Some(240)).unwrap().height, Some(360));
+
+        let audio = select_audio(&master, &master.variants[1]).unwrap();
+        assert_eq!(audio.uri.as_deref(), Some("https://example.com/show/audio/ja.m3u8"));
+        assert_eq!(audio.language.as_deref(), Some("ja"));
+    }
+
+    #[test]
+    fn test_parse_media_playlist() {
+        let body = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-MEDIA-SEQUENCE:7\n\
+            #EXT-X-MAP:URI=\"init.mp4\",BYTERANGE=\"720@0\"\n\
+            #EXTINF:6.006,\n\
+            #EXT-X-BYTERANGE:1000@720\n\
+            media.mp4\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"https://keys.example.com/k1\",IV=0x000102030405060708090a0b0c0d0e0f\n\
+            #EXTINF:5.5,\n\
+            #EXT-X-BYTERANGE:500\n\
+            media.mp4\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"k2.key\"\n\
+            #EXTINF:4,\n\
+            seg3.m4s\n\
+            #EXT-X-ENDLIST\n";
+        let base = Url::parse("https://example.com/hls/index.m3u8").unwrap();
+        let media = match parse_playlist(body, &base).unwrap() {
+            Playlist::Media(media) => media,
+            other => panic!("expected a media playlist, got {:?}", other),
+        };
+
+        assert!(media.ended);
+        let init = media.init.unwrap();
+        assert_eq!(init.uri, "https://example.com/hls/init.mp4");
+        assert_eq!(init.byte_range, Some((0, 720)));
+
+        assert_eq!(media.segments.len(), 3);
+        assert_eq!(media.segments[0].sequence, 7);
+        assert_eq!(media.segments[0].duration, 6.006);
+        assert_eq!(media.segments[0].byte_range, Some((720, 1000)));
+        assert_eq!(media.segments[0].encryption, Encryption::None);
+        // A range without offset follows the previous one
+        assert_eq!(media.segments[1].byte_range, Some((1720, 500)));
+        assert_eq!(
+            media.segments[1].encryption,
+            Encryption::Aes128 {
+                key_uri: "https://keys.example.com/k1".to_string(),
+                iv: Some([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+            }
+        );
+        assert_eq!(
+            media.segments[2].encryption,
+            Encryption::Aes128 { key_uri: "https://example.com/hls/k2.key".to_string(), iv: None }
+        );
+        assert_eq!(media.segments[2].sequence, 9);
+
+        assert!(parse_playlist("<html>", &base).is_err());
+        assert!(parse_playlist("#EXTM3U\n#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"k\"\na.ts\n", &base).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_aes128() {
+        use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyIvInit};
+
+        let key = *b"0123456789abcdef";
+        let iv = sequence_iv(42);
+        assert_eq!(&iv[..8], &[0; 8]);
+        assert_eq!(iv[15], 42);
+
+        let plain: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let encrypted = cbc::Encryptor::<aes::Aes128>::new(&key.into(), &iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(&plain);
+        assert_eq!(encrypted.len(), 1008);
+        assert_eq!(decrypt_aes128(&encrypted, &key, &iv).unwrap(), plain);
+        assert!(decrypt_aes128(&encrypted, b"fedcba9876543210", &iv).is_err());
+    }
+
+    #[test]
+    fn test_attributes_and_file_names() {
+        let attributes = parse_attributes("TYPE=AUDIO,NAME=\"Main, 5.1\",DEFAULT=YES,uri=\"a.m3u8\"");
+        assert_eq!(attributes.get("NAME").map(String::as_str), Some("Main, 5.1"));
+        assert_eq!(attributes.get("DEFAULT").map(String::as_str), Some("YES"));
+        assert_eq!(attributes.get("URI").map(String::as_str), Some("a.m3u8"));
+
+        assert_eq!(parse_byte_range("100@20", 0), Ok((20, 100)));
+        assert!(parse_iv("0x1234").is_err());
+
+        assert_eq!(file_stem("Frieren: Episode 1/2?"), "Frieren_ Episode 1_2_");
+        assert_eq!(file_stem(" .. "), "download");
+    }
+}
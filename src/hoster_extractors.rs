@@ -0,0 +1,841 @@
+//! Hoster Extractors
+//!
+//! Native counterpart of `ZPEPluginAPI.extractStream` / `getHosterInfo`: resolves a
+//! hoster page URL (VOE, Filemoon, Streamtape, ...) into playable stream descriptors.
+//!
+//! Each hoster is a [`HosterExtractor`] that declares the URL patterns it handles and
+//! turns a fetched page into either the streams or the next page to fetch (an iframe,
+//! a JS redirect). Extractors never do HTTP themselves: [`run_extractor`] drives the
+//! steps with a page loader, which is the HTTP client in the app and a directory of
+//! saved pages in the fixture tests (`tests/fixtures/hosters/<extractor id>/`). When a
+//! hoster changes its page, re-saving the page makes the test fail instead of playback.
+//!
+//! Hosters without a native extractor are still handled by stream-provider plugins.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::State;
+use tauri_plugin_http::reqwest::{self, header, Url};
+
+use crate::deobfuscate;
+
+/// Hosters serve their players to browsers only
+const BROWSER_USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+/// Request timeout for a single page
+const REQUEST_TIMEOUT_SECS: u64 = 20;
+
+/// Most pages fetched for one hoster URL (embed, iframe, redirect, ...)
+const MAX_EXTRACT_STEPS: usize = 4;
+
+/// Largest page accepted from a hoster
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// A subtitle track served alongside a stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subtitle {
+    pub url: String,
+    pub label: Option<String>,
+    pub lang: Option<String>,
+}
+
+/// A playable stream (the shape of a `ZPEStreamSource`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedStream {
+    pub url: String,
+    /// `m3u8`, `mp4`, `mkv`, `webm` or `dash` (detected from the URL when empty)
+    pub format: String,
+    pub quality: String,
+    /// Display name of the hoster (the extractor's name when empty)
+    pub server: String,
+    /// Headers the player must send (hoster CDNs often check the Referer)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub subtitles: Vec<Subtitle>,
+}
+
+impl ExtractedStream {
+    /// A stream with the format and server filled in by [`run_extractor`]
+    pub fn new(url: impl Into<String>) -> Self {
+        ExtractedStream {
+            url: url.into(),
+            format: String::new(),
+            quality: "Auto".to_string(),
+            server: String::new(),
+            headers: HashMap::new(),
+            subtitles: Vec::new(),
+        }
+    }
+
+    /// Play with `Referer` (and `Origin`) set to the origin of `page_url`
+    pub fn with_referer_of(mut self, page_url: &str) -> Self {
+        if let Ok(url) = Url::parse(page_url) {
+            let origin = url.origin().ascii_serialization();
+            self.headers.insert("Referer".to_string(), format!("{}/", origin));
+            self.headers.insert("Origin".to_string(), origin);
+        }
+        self
+    }
+}
+
+/// A page to fetch (the previous page is sent as Referer unless set here)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageRequest {
+    pub url: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl PageRequest {
+    pub fn new(url: impl Into<String>) -> Self {
+        PageRequest { url: url.into(), headers: HashMap::new() }
+    }
+}
+
+/// A fetched page
+#[derive(Debug, Clone)]
+pub struct HosterPage {
+    /// URL after redirects
+    pub url: String,
+    pub body: String,
+    /// Number of pages fetched before this one
+    pub step: usize,
+}
+
+/// What an extractor made of a page
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtractStep {
+    /// The streams are behind another page
+    Fetch(PageRequest),
+    Streams(Vec<ExtractedStream>),
+}
+
+/// Extracts streams from the pages of one hoster
+pub trait HosterExtractor: Send + Sync {
+    /// Unique extractor identifier (also the fixture directory name)
+    fn id(&self) -> &'static str;
+
+    /// Display name
+    fn name(&self) -> &'static str;
+
+    /// URL patterns: `host` or `host/path-prefix`, where subdomains of the host match and
+    /// a host ending in `.*` matches any top-level domain (`voe.*`)
+    fn patterns(&self) -> &'static [&'static str];
+
+    /// Headers sent with the first request
+    fn request_headers(&self, _url: &str) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    /// Extract from a fetched page
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String>;
+}
+
+/// Whether a URL matches an extractor pattern
+pub fn matches_pattern(url: &Url, pattern: &str) -> bool {
+    let (host_pattern, path_prefix) = match pattern.find('/') {
+        Some(i) => (&pattern[..i], &pattern[i..]),
+        None => (pattern, ""),
+    };
+    let Some(host) = url.host_str().map(|h| h.to_ascii_lowercase()) else {
+        return false;
+    };
+    let host_matches = match host_pattern.strip_suffix(".*") {
+        Some(name) => host
+            .rsplit_once('.')
+            .is_some_and(|(rest, _)| rest == name || rest.ends_with(&format!(".{}", name))),
+        None => host == host_pattern || host.ends_with(&format!(".{}", host_pattern)),
+    };
+    host_matches && url.path().starts_with(path_prefix)
+}
+
+/// Resolve a possibly relative link against the page it appears on
+fn absolute_url(page_url: &str, link: &str) -> Option<String> {
+    let link = link.trim();
+    if link.starts_with("//") {
+        return Some(format!("https:{}", link));
+    }
+    Url::parse(page_url).and_then(|base| base.join(link)).ok().map(String::from)
+}
+
+// =============================================================================
+// Driver
+// =============================================================================
+
+/// Run an extractor from `url`, fetching pages with `load` (which returns the final URL
+/// and the body)
+pub async fn run_extractor<F, Fut>(
+    extractor: &dyn HosterExtractor,
+    url: &str,
+    referer: Option<&str>,
+    mut load: F,
+) -> Result<Vec<ExtractedStream>, String>
+where
+    F: FnMut(PageRequest) -> Fut,
+    Fut: Future<Output = Result<(String, String), String>>,
+{
+    let mut request = PageRequest { url: url.to_string(), headers: extractor.request_headers(url) };
+    let mut referer = referer.map(String::from);
+
+    for step in 0..MAX_EXTRACT_STEPS {
+        if let Some(referer) = &referer {
+            request.headers.entry("Referer".to_string()).or_insert_with(|| referer.clone());
+        }
+        let (page_url, body) = load(request).await?;
+        let page = HosterPage { url: page_url, body, step };
+        match extractor.extract(&page)? {
+            ExtractStep::Fetch(next) => {
+                referer = Some(page.url);
+                request = next;
+            }
+            ExtractStep::Streams(streams) if streams.is_empty() => {
+                return Err(format!("No streams found on {}", extractor.name()));
+            }
+            ExtractStep::Streams(mut streams) => {
+                for stream in &mut streams {
+                    if stream.format.is_empty() {
+                        stream.format = deobfuscate::detect_format(&stream.url).to_string();
+                    }
+                    if stream.server.is_empty() {
+                        stream.server = extractor.name().to_string();
+                    }
+                }
+                return Ok(streams);
+            }
+        }
+    }
+    Err(format!("{} needed more than {} pages", extractor.name(), MAX_EXTRACT_STEPS))
+}
+
+/// Fetch a hoster page
+async fn fetch_page(client: &reqwest::Client, request: PageRequest) -> Result<(String, String), String> {
+    let mut builder = client
+        .get(&request.url)
+        .header(header::ACCEPT, "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
+        .header(header::ACCEPT_LANGUAGE, "en-US,en;q=0.5");
+    for (name, value) in &request.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    let response = builder.send().await.map_err(|e| format!("Hoster request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Hoster request failed with status {}", response.status()));
+    }
+    let final_url = response.url().to_string();
+    let bytes = response.bytes().await.map_err(|e| format!("Failed to read hoster page: {}", e))?;
+    if bytes.len() > MAX_PAGE_BYTES {
+        return Err(format!("Hoster page is too large ({} bytes)", bytes.len()));
+    }
+    Ok((final_url, String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+// =============================================================================
+// Player Config Helpers
+// =============================================================================
+
+/// Whether a URL points at an image or subtitle rather than a video
+fn is_asset_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_ascii_lowercase();
+    [".vtt", ".srt", ".ass", ".jpg", ".jpeg", ".png", ".webp", ".gif"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+/// The first video `file:` of a JW Player / Plyr config
+fn player_file(code: &str) -> Option<String> {
+    deobfuscate::js_string_values(code, "file")
+        .into_iter()
+        .map(|v| if v.starts_with("//") { format!("https:{}", v) } else { v })
+        .find(|v| v.starts_with("http") && !is_asset_url(v))
+}
+
+/// Subtitle tracks of a JW Player config (`tracks: [{file, label, kind}]`)
+fn player_tracks(code: &str, page_url: &str) -> Vec<Subtitle> {
+    let Some(start) = code.find("tracks") else {
+        return Vec::new();
+    };
+    let rest = &code[start + "tracks".len()..];
+    let rest = rest.strip_prefix(['"', '\'']).unwrap_or(rest);
+    let Some(list) = deobfuscate::expect_char(rest, ':').and_then(|r| deobfuscate::expect_char(r, '[')) else {
+        return Vec::new();
+    };
+    let list = &list[..list.find(']').unwrap_or(list.len())];
+
+    let mut subtitles = Vec::new();
+    for track in list.split('}') {
+        let value = |key: &str| deobfuscate::js_string_values(track, key).into_iter().next();
+        let kind = value("kind").unwrap_or_else(|| "captions".to_string());
+        if !matches!(kind.as_str(), "captions" | "subtitles") {
+            continue;
+        }
+        let Some(url) = value("file").and_then(|file| absolute_url(page_url, &file)) else {
+            continue;
+        };
+        let label = value("label");
+        let lang = value("srclang").or_else(|| value("language"));
+        subtitles.push(Subtitle { url, label, lang });
+    }
+    subtitles
+}
+
+/// Text content of the first `<script>` whose opening tag contains `marker`
+fn script_content<'a>(html: &'a str, marker: &str) -> Option<&'a str> {
+    let mut offset = 0;
+    while let Some(i) = html[offset..].find("<script") {
+        let start = offset + i;
+        let tag_end = start + html[start..].find('>')?;
+        offset = tag_end;
+        if html[start..tag_end].contains(marker) {
+            let content = &html[tag_end + 1..];
+            return Some(&content[..content.find("</script>").unwrap_or(content.len())]);
+        }
+    }
+    None
+}
+
+// =============================================================================
+// Extractors
+// =============================================================================
+
+/// kwik (AnimePahe's player): packed script with `source='...m3u8'`
+pub struct KwikExtractor;
+
+impl HosterExtractor for KwikExtractor {
+    fn id(&self) -> &'static str {
+        "kwik"
+    }
+
+    fn name(&self) -> &'static str {
+        "Kwik"
+    }
+
+    fn patterns(&self) -> &'static [&'static str] {
+        &["kwik.*/e/"]
+    }
+
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+        let url = crate::animepahe::parse_kwik_page(&page.body)?;
+        Ok(ExtractStep::Streams(vec![ExtractedStream::new(url).with_referer_of(&page.url)]))
+    }
+}
+
+/// Filemoon: an iframe to the player, whose config is packed
+pub struct FilemoonExtractor;
+
+impl HosterExtractor for FilemoonExtractor {
+    fn id(&self) -> &'static str {
+        "filemoon"
+    }
+
+    fn name(&self) -> &'static str {
+        "Filemoon"
+    }
+
+    fn patterns(&self) -> &'static [&'static str] {
+        &["filemoon.*", "moonmov.*", "fmoon.*"]
+    }
+
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+        for code in deobfuscate::unpack_all(&page.body) {
+            if let Some(url) = player_file(&code) {
+                let mut stream = ExtractedStream::new(url).with_referer_of(&page.url);
+                stream.subtitles = player_tracks(&code, &page.url);
+                return Ok(ExtractStep::Streams(vec![stream]));
+            }
+        }
+
+        let document = scraper::Html::parse_document(&page.body);
+        let selector = scraper::Selector::parse("iframe[src]").expect("valid selector");
+        let iframe = document
+            .select(&selector)
+            .filter_map(|iframe| iframe.value().attr("src"))
+            .find_map(|src| absolute_url(&page.url, src));
+        match iframe {
+            Some(url) => {
+                let mut request = PageRequest::new(url);
+                request.headers.insert("Sec-Fetch-Dest".to_string(), "iframe".to_string());
+                Ok(ExtractStep::Fetch(request))
+            }
+            None => Err("No player found on the Filemoon page".to_string()),
+        }
+    }
+}
+
+/// VOE: a JS redirect to a mirror domain, then the sources in an obfuscated JSON script
+pub struct VoeExtractor;
+
+impl VoeExtractor {
+    /// ROT13 → strip junk markers → Base64 → shift by 3 → reverse → Base64 → JSON
+    fn decode(payload: &str) -> Option<serde_json::Value> {
+        let mut text = deobfuscate::rot13(payload);
+        for junk in ["@$", "^^", "~@", "%?", "*~", "!!", "#&"] {
+            text = text.replace(junk, "");
+        }
+        text.retain(|c| c != '_');
+        let shifted = deobfuscate::shift_chars(&deobfuscate::base64_decode(&text)?, 3);
+        let json = deobfuscate::base64_decode(&deobfuscate::reverse(&shifted))?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// The target of `window.location.href = '...'`
+    fn redirect(html: &str) -> Option<String> {
+        let start = html.find("window.location.href")? + "window.location.href".len();
+        let value = deobfuscate::expect_char(&html[start..], '=')?;
+        deobfuscate::parse_js_string(value).map(|(url, _)| url)
+    }
+}
+
+impl HosterExtractor for VoeExtractor {
+    fn id(&self) -> &'static str {
+        "voe"
+    }
+
+    fn name(&self) -> &'static str {
+        "VOE"
+    }
+
+    fn patterns(&self) -> &'static [&'static str] {
+        &["voe.*", "voe-network.*", "voesx.*"]
+    }
+
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+        if let Some(content) = script_content(&page.body, "application/json") {
+            let content = content.trim();
+            // The payload is a one-string JSON array
+            let payload = serde_json::from_str::<Vec<String>>(content)
+                .ok()
+                .and_then(|v| v.into_iter().next())
+                .unwrap_or_else(|| content.trim_start_matches("[\"").trim_end_matches("\"]").to_string());
+            let decoded = Self::decode(&payload).ok_or_else(|| "Failed to decode the VOE sources".to_string())?;
+            let url = ["source", "direct_access_url"]
+                .iter()
+                .find_map(|key| decoded.get(*key).and_then(|v| v.as_str()))
+                .ok_or_else(|| "No source in the VOE sources".to_string())?;
+            return Ok(ExtractStep::Streams(vec![ExtractedStream::new(url)]));
+        }
+
+        if let Some(url) = Self::redirect(&page.body).and_then(|url| absolute_url(&page.url, &url)) {
+            return Ok(ExtractStep::Fetch(PageRequest::new(url)));
+        }
+
+        // Older pages: `'hls': '<base64 or URL>'`
+        deobfuscate::js_string_values(&page.body, "hls")
+            .into_iter()
+            .find_map(|value| {
+                let value = if value.starts_with("http") { value } else { deobfuscate::base64_decode(&value)? };
+                value.starts_with("http").then_some(value)
+            })
+            .or_else(|| deobfuscate::find_urls(&page.body, ".m3u8").into_iter().next())
+            .map(|url| ExtractStep::Streams(vec![ExtractedStream::new(url)]))
+            .ok_or_else(|| "No sources on the VOE page".to_string())
+    }
+}
+
+/// Streamtape: the video link is assembled from two strings in `robotlink.innerHTML`
+pub struct StreamtapeExtractor;
+
+impl HosterExtractor for StreamtapeExtractor {
+    fn id(&self) -> &'static str {
+        "streamtape"
+    }
+
+    fn name(&self) -> &'static str {
+        "Streamtape"
+    }
+
+    fn patterns(&self) -> &'static [&'static str] {
+        &["streamtape.*", "streamta.pe"]
+    }
+
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+        // Decoy links come first; the last assignment is the real one
+        const MARKER: &str = "botlink').innerHTML";
+        let start = page.body.rfind(MARKER).ok_or_else(|| "No video link on the Streamtape page".to_string())?;
+        let malformed = || "Malformed Streamtape video link".to_string();
+        let rest = deobfuscate::expect_char(&page.body[start + MARKER.len()..], '=').ok_or_else(malformed)?;
+        let (prefix, rest) = deobfuscate::parse_js_string(rest).ok_or_else(malformed)?;
+        let rest = deobfuscate::expect_char(rest, '+').ok_or_else(malformed)?;
+        let rest = deobfuscate::expect_char(rest, '(').unwrap_or(rest);
+        let (suffix, mut rest) = deobfuscate::parse_js_string(rest).ok_or_else(malformed)?;
+
+        // `('xyz...').substring(1).substring(2)` drops the first characters
+        rest = rest.trim_start().strip_prefix(')').unwrap_or(rest);
+        let mut skip = 0;
+        while let Some(call) = rest.strip_prefix(".substring(").or_else(|| rest.strip_prefix(".substr(")) {
+            let digits: String = call.chars().take_while(|c| c.is_ascii_digit()).collect();
+            skip += digits.parse::<usize>().map_err(|_| malformed())?;
+            rest = call[digits.len()..].strip_prefix(')').ok_or_else(malformed)?;
+        }
+        let suffix: String = suffix.chars().skip(skip).collect();
+
+        let link = format!("{}{}", prefix, suffix);
+        let url = absolute_url(&page.url, &link).ok_or_else(malformed)?;
+        let mut stream = ExtractedStream::new(format!("{}&stream=1", url));
+        stream.format = "mp4".to_string();
+        Ok(ExtractStep::Streams(vec![stream]))
+    }
+}
+
+/// Vidmoly: a plain JW Player config; the CDN checks the Referer
+pub struct VidmolyExtractor;
+
+impl HosterExtractor for VidmolyExtractor {
+    fn id(&self) -> &'static str {
+        "vidmoly"
+    }
+
+    fn name(&self) -> &'static str {
+        "Vidmoly"
+    }
+
+    fn patterns(&self) -> &'static [&'static str] {
+        &["vidmoly.*"]
+    }
+
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+        let url = player_file(&page.body).ok_or_else(|| "No sources on the Vidmoly page".to_string())?;
+        let mut stream = ExtractedStream::new(url).with_referer_of(&page.url);
+        stream.subtitles = player_tracks(&page.body, &page.url);
+        Ok(ExtractStep::Streams(vec![stream]))
+    }
+}
+
+/// Vidoza: `sourcesCode: [{ src: "...", res: "720" }]`
+pub struct VidozaExtractor;
+
+impl HosterExtractor for VidozaExtractor {
+    fn id(&self) -> &'static str {
+        "vidoza"
+    }
+
+    fn name(&self) -> &'static str {
+        "Vidoza"
+    }
+
+    fn patterns(&self) -> &'static [&'static str] {
+        &["vidoza.*"]
+    }
+
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+        let start = page.body.find("sourcesCode").ok_or_else(|| "No sources on the Vidoza page".to_string())?;
+        let sources = &page.body[start..];
+        let sources = &sources[..sources.find(']').unwrap_or(sources.len())];
+        let url = deobfuscate::js_url_value(sources, "src").ok_or_else(|| "No sources on the Vidoza page".to_string())?;
+        let mut stream = ExtractedStream::new(url);
+        if let Some(res) = deobfuscate::js_string_values(sources, "res").into_iter().next() {
+            stream.quality = format!("{}p", res);
+        }
+        Ok(ExtractStep::Streams(vec![stream]))
+    }
+}
+
+/// SpeedFiles: the URL in `_0x5opu234` goes through a chain of encodings
+pub struct SpeedFilesExtractor;
+
+impl SpeedFilesExtractor {
+    fn decode(payload: &str) -> Option<String> {
+        use deobfuscate::{base64_decode, hex_to_string, reverse, shift_chars, swap_case};
+        let text = reverse(&swap_case(&base64_decode(payload)?));
+        let text = hex_to_string(&reverse(&base64_decode(&text)?))?;
+        base64_decode(&reverse(&swap_case(&shift_chars(&text, 3))))
+    }
+}
+
+impl HosterExtractor for SpeedFilesExtractor {
+    fn id(&self) -> &'static str {
+        "speedfiles"
+    }
+
+    fn name(&self) -> &'static str {
+        "SpeedFiles"
+    }
+
+    fn patterns(&self) -> &'static [&'static str] {
+        &["speedfiles.*"]
+    }
+
+    fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+        let payload = deobfuscate::js_string_values(&page.body, "_0x5opu234")
+            .into_iter()
+            .next()
+            .ok_or_else(|| "No sources on the SpeedFiles page".to_string())?;
+        let url = Self::decode(&payload)
+            .filter(|url| url.starts_with("http"))
+            .ok_or_else(|| "Failed to decode the SpeedFiles source".to_string())?;
+        Ok(ExtractStep::Streams(vec![ExtractedStream::new(url)]))
+    }
+}
+
+/// The built-in extractors
+pub fn default_extractors() -> Vec<Arc<dyn HosterExtractor>> {
+    vec![
+        Arc::new(KwikExtractor),
+        Arc::new(FilemoonExtractor),
+        Arc::new(VoeExtractor),
+        Arc::new(StreamtapeExtractor),
+        Arc::new(VidmolyExtractor),
+        Arc::new(VidozaExtractor),
+        Arc::new(SpeedFilesExtractor),
+    ]
+}
+
+// =============================================================================
+// State
+// =============================================================================
+
+/// Hoster info for `getHosterInfo`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HosterInfo {
+    pub supported: bool,
+    pub id: Option<String>,
+    pub name: String,
+}
+
+/// A registered extractor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HosterDescriptor {
+    pub id: String,
+    pub name: String,
+    pub patterns: Vec<String>,
+}
+
+/// Hoster extractor registry
+pub struct HosterState {
+    extractors: RwLock<Vec<Arc<dyn HosterExtractor>>>,
+    client: reqwest::Client,
+}
+
+impl Default for HosterState {
+    fn default() -> Self {
+        HosterState {
+            extractors: RwLock::new(default_extractors()),
+            client: reqwest::Client::builder()
+                .user_agent(BROWSER_USER_AGENT)
+                .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl HosterState {
+    /// Register an additional extractor (replaces an extractor with the same ID)
+    pub fn register_extractor(&self, extractor: Arc<dyn HosterExtractor>) {
+        if let Ok(mut extractors) = self.extractors.write() {
+            extractors.retain(|e| e.id() != extractor.id());
+            extractors.push(extractor);
+        }
+    }
+
+    fn extractors(&self) -> Vec<Arc<dyn HosterExtractor>> {
+        self.extractors.read().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    /// The extractor handling a URL
+    pub fn extractor_for(&self, url: &str) -> Option<Arc<dyn HosterExtractor>> {
+        let url = Url::parse(url).ok()?;
+        self.extractors()
+            .into_iter()
+            .find(|e| e.patterns().iter().any(|p| matches_pattern(&url, p)))
+    }
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Extract the streams of a hoster URL
+#[tauri::command]
+pub async fn hoster_extract(
+    state: State<'_, HosterState>,
+    url: String,
+    referer: Option<String>,
+) -> Result<Vec<ExtractedStream>, String> {
+    let extractor = state
+        .extractor_for(&url)
+        .ok_or_else(|| format!("No extractor for {}", url))?;
+    let client = &state.client;
+    run_extractor(extractor.as_ref(), &url, referer.as_deref(), |request| fetch_page(client, request))
+        .await
+        .inspect_err(|e| log::warn!("{} extraction failed for {}: {}", extractor.name(), url, e))
+}
+
+/// Which extractor handles a hoster URL
+#[tauri::command]
+pub fn hoster_info(state: State<'_, HosterState>, url: String) -> HosterInfo {
+    match state.extractor_for(&url) {
+        Some(extractor) => HosterInfo {
+            supported: true,
+            id: Some(extractor.id().to_string()),
+            name: extractor.name().to_string(),
+        },
+        None => HosterInfo { supported: false, id: None, name: "Unknown".to_string() },
+    }
+}
+
+/// List the registered extractors
+#[tauri::command]
+pub fn hoster_list(state: State<'_, HosterState>) -> Vec<HosterDescriptor> {
+    state
+        .extractors()
+        .iter()
+        .map(|e| HosterDescriptor {
+            id: e.id().to_string(),
+            name: e.name().to_string(),
+            patterns: e.patterns().iter().map(|p| p.to_string()).collect(),
+        })
+        .collect()
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    /// A saved extraction: the URL to extract, the pages the hoster served and the
+    /// streams expected from them
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FixtureCase {
+        url: String,
+        referer: Option<String>,
+        /// Page file by URL
+        pages: HashMap<String, String>,
+        expected: Vec<ExtractedStream>,
+    }
+
+    fn fixtures_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hosters")
+    }
+
+    /// Run a fixture case, serving pages from its directory
+    fn run_case(state: &HosterState, dir: &Path) -> Result<Vec<ExtractedStream>, String> {
+        let case: FixtureCase =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("case.json")).unwrap()).unwrap();
+        let extractor = state.extractor_for(&case.url).expect("an extractor for the case URL");
+        let id = dir.file_name().unwrap().to_string_lossy();
+        assert_eq!(extractor.id(), id, "case {} is handled by {}", id, extractor.id());
+
+        let load = |request: PageRequest| {
+            let page = case
+                .pages
+                .get(&request.url)
+                .ok_or_else(|| format!("Unexpected request to {}", request.url))
+                .and_then(|file| std::fs::read_to_string(dir.join(file)).map_err(|e| e.to_string()))
+                .map(|body| (request.url.clone(), body));
+            std::future::ready(page)
+        };
+        let streams = tauri::async_runtime::block_on(run_extractor(
+            extractor.as_ref(),
+            &case.url,
+            case.referer.as_deref(),
+            load,
+        ))?;
+        assert_eq!(streams, case.expected, "case {}", id);
+        Ok(streams)
+    }
+
+    #[test]
+    fn test_fixture_cases() {
+        let state = HosterState::default();
+        let mut covered = Vec::new();
+        for entry in std::fs::read_dir(fixtures_dir()).unwrap() {
+            let dir = entry.unwrap().path();
+            if let Err(e) = run_case(&state, &dir) {
+                panic!("case {}: {}", dir.display(), e);
+            }
+            covered.push(dir.file_name().unwrap().to_string_lossy().into_owned());
+        }
+
+        // Every built-in extractor ships with a saved page
+        for extractor in default_extractors() {
+            assert!(covered.iter().any(|id| id == extractor.id()), "no fixture for {}", extractor.id());
+        }
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert!(matches_pattern(&url("https://voe.sx/e/abc"), "voe.*"));
+        assert!(matches_pattern(&url("https://www.VOE.bar/abc"), "voe.*"));
+        assert!(!matches_pattern(&url("https://notvoe.sx/abc"), "voe.*"));
+        assert!(!matches_pattern(&url("https://voe.sx.evil.com/abc"), "voe.*"));
+        assert!(matches_pattern(&url("https://kwik.si/e/Hn7x"), "kwik.*/e/"));
+        assert!(!matches_pattern(&url("https://kwik.si/f/Hn7x"), "kwik.*/e/"));
+        assert!(matches_pattern(&url("https://cdn.streamta.pe/v/1"), "streamta.pe"));
+
+        let state = HosterState::default();
+        assert_eq!(state.extractor_for("https://filemoon.sx/e/x").map(|e| e.id()), Some("filemoon"));
+        assert!(state.extractor_for("https://example.com/e/x").is_none());
+        assert!(state.extractor_for("not a url").is_none());
+    }
+
+    #[test]
+    fn test_player_helpers() {
+        let code = r#"jwplayer("v").setup({sources:[{file:"https://cdn.example/v/master.m3u8"}],image:"https://cdn.example/p.jpg",tracks:[{file:"/subs/en.vtt",label:"English",kind:"captions"},{file:"https://cdn.example/thumbs.vtt",kind:"thumbnails"}]});"#;
+        assert_eq!(player_file(code).as_deref(), Some("https://cdn.example/v/master.m3u8"));
+        assert_eq!(
+            player_tracks(code, "https://player.example/e/1"),
+            vec![Subtitle {
+                url: "https://player.example/subs/en.vtt".to_string(),
+                label: Some("English".to_string()),
+                lang: None,
+            }]
+        );
+        assert_eq!(
+            script_content(r#"<script>a()</script><script type="application/json">["x"]</script>"#, "application/json"),
+            Some(r#"["x"]"#)
+        );
+    }
+
+    #[test]
+    fn test_run_extractor_steps() {
+        struct Looping;
+        impl HosterExtractor for Looping {
+            fn id(&self) -> &'static str {
+                "looping"
+            }
+            fn name(&self) -> &'static str {
+                "Looping"
+            }
+            fn patterns(&self) -> &'static [&'static str] {
+                &["looping.test"]
+            }
+            fn extract(&self, page: &HosterPage) -> Result<ExtractStep, String> {
+                Ok(ExtractStep::Fetch(PageRequest::new(format!("https://looping.test/{}", page.step + 1))))
+            }
+        }
+
+        let mut referers = Vec::new();
+        let result = tauri::async_runtime::block_on(run_extractor(
+            &Looping,
+            "https://looping.test/0",
+            Some("https://site.example/"),
+            |request| {
+                referers.push(request.headers.get("Referer").cloned().unwrap_or_default());
+                std::future::ready(Ok((request.url, String::new())))
+            },
+        ));
+        assert!(result.unwrap_err().contains("more than"));
+        assert_eq!(referers.len(), MAX_EXTRACT_STEPS);
+        assert_eq!(referers[0], "https://site.example/");
+        assert_eq!(referers[1], "https://looping.test/0");
+
+        // A changed page fails instead of returning nothing
+        let state = HosterState::default();
+        let extractor = state.extractor_for("https://vidoza.net/x.html").unwrap();
+        let page = HosterPage { url: "https://vidoza.net/x.html".to_string(), body: "<html></html>".to_string(), step: 0 };
+        assert!(extractor.extract(&page).is_err());
+    }
+}
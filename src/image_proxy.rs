@@ -0,0 +1,518 @@
+//! Image Proxy
+//!
+//! This module serves remote images (cover art, episode thumbnails, plugin artwork)
+//! to the webview through the `zimg://` URI scheme. Images are fetched from Rust with
+//! a browser User-Agent and the image host as Referer, so hosts that block hotlinking
+//! or cross-origin requests work, and are kept in an LRU disk cache in the app's
+//! cache folder, so covers survive restarts.
+//!
+//! URLs have the form `zimg://localhost/{percent-encoded url}[?w={width}]`
+//! (`http://zimg.localhost/...` on Windows and Android; the frontend builds them with
+//! `convertFileSrc`). With `w`, the image is scaled down to that width and served as
+//! a JPEG thumbnail (lossless WebP when it has transparency).
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::http::{self, header};
+use tauri::{AppHandle, Manager, State, UriSchemeContext, UriSchemeResponder, Wry};
+use tauri_plugin_http::reqwest::{self, Url};
+
+use crate::media_server::{parse_query, percent_decode};
+
+/// URI scheme the webview loads images from
+pub const PROTOCOL: &str = "zimg";
+
+/// Size cap of the disk cache
+pub const CACHE_LIMIT_BYTES: u64 = 256 * 1024 * 1024;
+
+/// The cache is trimmed to this share of the cap, so it isn't trimmed on every insert
+const CACHE_TRIM_PERCENT: u64 = 90;
+
+/// Maximum size of a fetched image
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Timeout for fetching an image
+const REQUEST_TIMEOUT_SECS: u64 = 20;
+
+/// Thumbnail widths are rounded up to a multiple of this, to limit cached variants
+const THUMBNAIL_WIDTH_STEP: u32 = 32;
+
+/// JPEG quality of opaque thumbnails
+const THUMBNAIL_JPEG_QUALITY: u8 = 82;
+
+/// Largest thumbnail width
+const MAX_THUMBNAIL_WIDTH: u32 = 2048;
+
+/// How long the webview may reuse a served image
+const BROWSER_CACHE_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Disk cache statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageCacheStats {
+    /// Total size of cached images in bytes
+    pub bytes: u64,
+    /// Number of cached images (originals and thumbnails)
+    pub files: usize,
+    /// Size cap in bytes
+    pub limit_bytes: u64,
+}
+
+/// A cached file
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    size: u64,
+    /// Last use (Unix milliseconds)
+    last_used: i64,
+}
+
+/// Image proxy state: the cache index and a shared HTTP client
+pub struct ImageProxyState {
+    /// Cached files by name; scanned from disk on first use
+    index: Mutex<Option<HashMap<String, CacheEntry>>>,
+    client: reqwest::Client,
+}
+
+impl Default for ImageProxyState {
+    fn default() -> Self {
+        ImageProxyState {
+            index: Mutex::new(None),
+            client: reqwest::Client::builder()
+                .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+                .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Disk Cache
+// =============================================================================
+
+/// Folder holding the cached images
+fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("images"))
+        .map_err(|e| format!("Failed to resolve cache folder: {}", e))
+}
+
+/// Cache file name of an image (and thumbnail width): the hex SHA-256 of its URL,
+/// which stays the same across builds
+fn cache_key(url: &str, width: Option<u32>) -> String {
+    let hash: String = Sha256::digest(url.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+    match width {
+        Some(width) => format!("{}-w{}", hash, width),
+        None => hash,
+    }
+}
+
+/// Scan the cache folder
+fn scan_cache(dir: &Path) -> HashMap<String, CacheEntry> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return HashMap::new(),
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() || name.ends_with(".tmp") {
+                return None;
+            }
+            let last_used = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+            Some((name, CacheEntry { size: metadata.len(), last_used }))
+        })
+        .collect()
+}
+
+/// Least recently used files to delete so the cache fits `target` bytes
+fn eviction_candidates(index: &HashMap<String, CacheEntry>, target: u64) -> Vec<String> {
+    let mut total: u64 = index.values().map(|e| e.size).sum();
+    let mut entries: Vec<(&String, &CacheEntry)> = index.iter().collect();
+    entries.sort_by_key(|(_, entry)| entry.last_used);
+
+    let mut evict = Vec::new();
+    for (name, entry) in entries {
+        if total <= target {
+            break;
+        }
+        total -= entry.size;
+        evict.push(name.clone());
+    }
+    evict
+}
+
+impl ImageProxyState {
+    /// Run `f` on the cache index, scanning the folder first if needed
+    fn with_index<T>(&self, dir: &Path, f: impl FnOnce(&mut HashMap<String, CacheEntry>) -> T) -> T {
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        f(index.get_or_insert_with(|| scan_cache(dir)))
+    }
+
+    /// Read a cached file and mark it as used
+    fn get(&self, dir: &Path, key: &str) -> Option<Vec<u8>> {
+        let path = dir.join(key);
+        let data = std::fs::read(&path).ok()?;
+        let now = get_current_timestamp();
+        self.with_index(dir, |index| {
+            index
+                .entry(key.to_string())
+                .or_insert(CacheEntry { size: data.len() as u64, last_used: now })
+                .last_used = now;
+        });
+        // Keep the order across restarts (best effort)
+        if let Ok(file) = std::fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(data)
+    }
+
+    /// Store a file and evict the least recently used ones beyond the cap
+    fn put(&self, dir: &Path, key: &str, data: &[u8]) {
+        let path = dir.join(key);
+        let temp = path.with_extension("tmp");
+        let written = std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&temp, data))
+            .and_then(|_| std::fs::rename(&temp, &path));
+        if let Err(e) = written {
+            log::warn!("Failed to cache image: {}", e);
+            return;
+        }
+
+        let evicted = self.with_index(dir, |index| {
+            index.insert(
+                key.to_string(),
+                CacheEntry { size: data.len() as u64, last_used: get_current_timestamp() },
+            );
+            if index.values().map(|e| e.size).sum::<u64>() <= CACHE_LIMIT_BYTES {
+                return Vec::new();
+            }
+            let evict = eviction_candidates(index, CACHE_LIMIT_BYTES / 100 * CACHE_TRIM_PERCENT);
+            for name in &evict {
+                index.remove(name);
+            }
+            evict
+        });
+        for name in evicted {
+            let _ = std::fs::remove_file(dir.join(name));
+        }
+    }
+
+    fn stats(&self, dir: &Path) -> ImageCacheStats {
+        self.with_index(dir, |index| ImageCacheStats {
+            bytes: index.values().map(|e| e.size).sum(),
+            files: index.len(),
+            limit_bytes: CACHE_LIMIT_BYTES,
+        })
+    }
+}
+
+// =============================================================================
+// Images
+// =============================================================================
+
+/// Detect the image type from its first bytes
+pub fn sniff_content_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => Some("image/avif"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        _ => {
+            let head = String::from_utf8_lossy(&data[..data.len().min(512)]).to_lowercase();
+            (head.trim_start().starts_with('<') && head.contains("<svg")).then_some("image/svg+xml")
+        }
+    }
+}
+
+/// Round a requested thumbnail width to a cached size
+fn thumbnail_width(requested: u32) -> u32 {
+    requested
+        .div_ceil(THUMBNAIL_WIDTH_STEP)
+        .saturating_mul(THUMBNAIL_WIDTH_STEP)
+        .clamp(THUMBNAIL_WIDTH_STEP, MAX_THUMBNAIL_WIDTH)
+}
+
+/// Scale an image down to `width` and encode it as JPEG (lossless WebP to keep
+/// transparency); `None` if the original is already as small (or can't be decoded,
+/// or is animated)
+pub fn make_thumbnail(data: &[u8], width: u32) -> Option<Vec<u8>> {
+    if matches!(sniff_content_type(data), Some("image/gif" | "image/svg+xml") | None) {
+        return None;
+    }
+    let decoded = image::load_from_memory(data).ok()?;
+    if decoded.width() <= width {
+        return None;
+    }
+
+    let resized = decoded.resize(width, u32::MAX, FilterType::Triangle);
+    let mut out = Vec::new();
+    let encoded = if resized.color().has_alpha() {
+        resized.to_rgba8().write_with_encoder(WebPEncoder::new_lossless(&mut out))
+    } else {
+        let encoder = JpegEncoder::new_with_quality(&mut out, THUMBNAIL_JPEG_QUALITY);
+        resized.to_rgb8().write_with_encoder(encoder)
+    };
+    encoded.ok()?;
+    // A small, already compressed original can still beat the thumbnail
+    (out.len() < data.len()).then_some(out)
+}
+
+/// Fetch an image with the headers image hosts expect from a browser
+async fn fetch_image(client: &reqwest::Client, url: &Url) -> Result<Vec<u8>, String> {
+    let referer = format!("{}://{}/", url.scheme(), url.host_str().unwrap_or_default());
+    let mut response = client
+        .get(url.clone())
+        .header("referer", referer)
+        .header("accept", "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8")
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read image: {}", e))?
+    {
+        if data.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err("Image is too large".to_string());
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    // Don't cache error pages served with a 200
+    if sniff_content_type(&data).is_none() {
+        return Err("Response is not an image".to_string());
+    }
+    Ok(data)
+}
+
+// =============================================================================
+// URI Scheme
+// =============================================================================
+
+/// Resolve a `zimg://` request to an image (cached, fetched or thumbnailed)
+async fn serve(app: &AppHandle, uri: &http::Uri) -> Result<Vec<u8>, (u16, String)> {
+    let target = percent_decode(uri.path().trim_start_matches('/'));
+    let url = Url::parse(&target).map_err(|_| (400, "Invalid image URL".to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err((400, "Only http(s) images can be proxied".to_string()));
+    }
+    let width = parse_query(uri.query())
+        .get("w")
+        .and_then(|w| w.parse().ok())
+        .map(thumbnail_width);
+
+    let state = app.state::<ImageProxyState>();
+    let dir = cache_dir(app).map_err(|e| (500, e))?;
+
+    let key = cache_key(url.as_str(), width);
+    if let Some(data) = state.get(&dir, &key) {
+        return Ok(data);
+    }
+
+    let original_key = cache_key(url.as_str(), None);
+    let original = match state.get(&dir, &original_key) {
+        Some(data) => data,
+        None => {
+            let data = fetch_image(&state.client, &url).await.map_err(|e| {
+                log::debug!("Image proxy failed for {}: {}", url, e);
+                (502, e)
+            })?;
+            state.put(&dir, &original_key, &data);
+            data
+        }
+    };
+
+    let width = match width {
+        Some(width) => width,
+        None => return Ok(original),
+    };
+    let (original, thumbnail) = tauri::async_runtime::spawn_blocking(move || {
+        let thumbnail = make_thumbnail(&original, width);
+        (original, thumbnail)
+    })
+    .await
+    .map_err(|e| (500, format!("Failed to create thumbnail: {}", e)))?;
+
+    match thumbnail {
+        Some(thumbnail) => {
+            state.put(&dir, &key, &thumbnail);
+            Ok(thumbnail)
+        }
+        None => Ok(original),
+    }
+}
+
+/// Handle a request of the `zimg://` URI scheme
+pub fn handle_protocol(
+    ctx: UriSchemeContext<'_, Wry>,
+    request: http::Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let builder = http::Response::builder().header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+        let response = match serve(&app, request.uri()).await {
+            Ok(data) => builder
+                .status(200)
+                .header(
+                    header::CONTENT_TYPE,
+                    sniff_content_type(&data).unwrap_or("application/octet-stream"),
+                )
+                .header(header::CACHE_CONTROL, format!("public, max-age={}", BROWSER_CACHE_SECS))
+                .body(data),
+            Err((status, message)) => builder
+                .status(status)
+                .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+                .body(message.into_bytes()),
+        };
+        match response {
+            Ok(response) => responder.respond(response),
+            Err(e) => log::error!("Failed to build image response: {}", e),
+        }
+    });
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Get the size of the image cache
+#[tauri::command]
+pub fn image_cache_get_stats(
+    app: AppHandle,
+    state: State<'_, ImageProxyState>,
+) -> Result<ImageCacheStats, String> {
+    Ok(state.stats(&cache_dir(&app)?))
+}
+
+/// Delete all cached images; returns the number of bytes freed
+#[tauri::command]
+pub fn image_cache_clear(app: AppHandle, state: State<'_, ImageProxyState>) -> Result<u64, String> {
+    let dir = cache_dir(&app)?;
+    let freed = state.stats(&dir).bytes;
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear image cache: {}", e))?;
+    }
+    *state.index.lock().map_err(|e| format!("Failed to lock image cache: {}", e))? = Some(HashMap::new());
+    log::info!("Cleared image cache ({} bytes)", freed);
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_content_type() {
+        assert_eq!(sniff_content_type(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0]), Some("image/jpeg"));
+        assert_eq!(sniff_content_type(b"\x89PNG\r\n\x1a\n"), Some("image/png"));
+        assert_eq!(sniff_content_type(b"RIFF\x10\0\0\0WEBPVP8L"), Some("image/webp"));
+        assert_eq!(sniff_content_type(b"\0\0\0\x1cftypavif"), Some("image/avif"));
+        assert_eq!(
+            sniff_content_type(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(sniff_content_type(b"<!DOCTYPE html><html>Blocked</html>"), None);
+    }
+
+    #[test]
+    fn test_cache_keys_and_widths() {
+        let url = "https://s4.anilist.co/file/anilistcdn/media/anime/cover/large/bx1.png";
+        assert_eq!(cache_key(url, None), cache_key(url, None));
+        assert_ne!(cache_key(url, None), cache_key("https://example.com/a.png", None));
+        assert!(cache_key(url, Some(320)).ends_with("-w320"));
+        // Stable across toolchains: the SHA-256 of the URL
+        assert_eq!(
+            cache_key("https://example.com/a.png", None),
+            "494a30704d4f32ac0b81739d18a66d3638d440cbc6f5669f6af66f840edee5ab"
+        );
+
+        assert_eq!(thumbnail_width(300), 320);
+        assert_eq!(thumbnail_width(320), 320);
+        assert_eq!(thumbnail_width(0), THUMBNAIL_WIDTH_STEP);
+        assert_eq!(thumbnail_width(10_000), MAX_THUMBNAIL_WIDTH);
+    }
+
+    #[test]
+    fn test_eviction_order() {
+        let index: HashMap<String, CacheEntry> = [("a", 400, 3), ("b", 300, 1), ("c", 300, 2)]
+            .into_iter()
+            .map(|(name, size, last_used)| (name.to_string(), CacheEntry { size, last_used }))
+            .collect();
+
+        assert!(eviction_candidates(&index, 1000).is_empty());
+        assert_eq!(eviction_candidates(&index, 700), vec!["b"]);
+        assert_eq!(eviction_candidates(&index, 500), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_make_thumbnail() {
+        let source = image::RgbImage::from_fn(640, 360, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(source)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let thumbnail = make_thumbnail(&png, 160).unwrap();
+        assert_eq!(sniff_content_type(&thumbnail), Some("image/jpeg"));
+        let decoded = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (160, 90));
+
+        // Photographic covers shrink too, which lossless encoding rarely managed
+        let mut seed = 1u32;
+        let noisy = image::RgbImage::from_fn(640, 360, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            image::Rgb([(seed >> 16) as u8, (seed >> 8) as u8, (seed >> 24) as u8])
+        });
+        let mut jpeg = Vec::new();
+        image::DynamicImage::ImageRgb8(noisy)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        assert!(make_thumbnail(&jpeg, 320).unwrap().len() < jpeg.len() / 2);
+
+        // Transparency is kept
+        let transparent = image::RgbaImage::from_pixel(640, 360, image::Rgba([255, 0, 0, 64]));
+        let mut alpha_png = Vec::new();
+        image::DynamicImage::ImageRgba8(transparent)
+            .write_to(&mut std::io::Cursor::new(&mut alpha_png), image::ImageFormat::Png)
+            .unwrap();
+        let thumbnail = make_thumbnail(&alpha_png, 160).unwrap();
+        assert_eq!(sniff_content_type(&thumbnail), Some("image/webp"));
+        assert!(image::load_from_memory(&thumbnail).unwrap().color().has_alpha());
+
+        // Never upscaled
+        assert!(make_thumbnail(&png, 1280).is_none());
+        assert!(make_thumbnail(b"not an image", 160).is_none());
+    }
+}
@@ -2,6 +2,37 @@ mod commands;
 pub mod anime4k;
 pub mod profiles;
 pub mod miracast;
+pub mod release_parser;
+pub mod torrent_search;
+pub mod downloads;
+pub mod subscriptions;
+pub mod seeding;
+pub mod matroska;
+pub mod subtitles;
+pub mod media_server;
+pub mod media_probe;
+pub mod audio_fingerprint;
+pub mod skip_segments;
+pub mod stream_proxy;
+pub mod hls_download;
+pub mod image_proxy;
+pub mod zpe;
+pub mod plugins;
+pub mod plugin_fetch;
+pub mod plugin_host;
+pub mod plugin_html;
+pub mod plugin_storage;
+pub mod plugin_updates;
+pub mod plugin_repos;
+pub mod search_aggregator;
+pub mod stream_selector;
+pub mod episode_mapping;
+pub mod animepahe;
+pub mod deobfuscate;
+pub mod hoster_extractors;
+pub mod backend_runtime;
+#[cfg(test)]
+mod test_server;
 
 use commands::*;
 use std::sync::Mutex;
@@ -38,6 +69,67 @@ pub fn run() {
   // Initialize Miracast state
   let miracast_state = miracast::MiracastState::default();
 
+  // Initialize torrent search state
+  let torrent_search_state = torrent_search::TorrentSearchState::default();
+
+  // Initialize download queue, HLS download and subscription state
+  let download_queue_state = downloads::DownloadQueueState::default();
+  let hls_download_state = hls_download::HlsDownloadState::default();
+  let subscription_state = subscriptions::SubscriptionState::default();
+
+  // Initialize seeding manager state
+  let seeding_state = seeding::SeedingState::default();
+
+  // Initialize subtitle extraction, media server and stream proxy state
+  let subtitle_state = subtitles::SubtitleState::default();
+  let media_server_state = media_server::MediaServerState::default();
+  let stream_proxy_state = stream_proxy::StreamProxyState::default();
+
+  // Initialize skip segment state
+  let skip_state = skip_segments::SkipState::default();
+
+  // Initialize image proxy state
+  let image_proxy_state = image_proxy::ImageProxyState::default();
+
+  // Initialize ZPE trusted key state
+  let zpe_state = zpe::ZpeState::default();
+
+  // Initialize plugin registry state
+  let plugin_state = plugins::PluginState::default();
+
+  // Initialize plugin network state
+  let plugin_fetch_state = plugin_fetch::PluginFetchState::default();
+
+  // Initialize plugin host state
+  let plugin_host_state = plugin_host::PluginHostState::default();
+
+  // Initialize plugin storage state
+  let plugin_storage_state = plugin_storage::PluginStorageState::default();
+
+  // Initialize plugin update state
+  let plugin_update_state = plugin_updates::PluginUpdateState::default();
+
+  // Initialize plugin repository state
+  let plugin_repo_state = plugin_repos::PluginRepoState::default();
+
+  // Initialize search aggregator state
+  let search_aggregator_state = search_aggregator::SearchAggregatorState::default();
+
+  // Initialize stream selector state
+  let stream_selector_state = stream_selector::StreamSelectorState::default();
+
+  // Initialize episode mapping state
+  let episode_mapping_state = episode_mapping::EpisodeMappingState::default();
+
+  // Initialize AnimePahe state
+  let animepahe_state = animepahe::AnimePaheState::default();
+
+  // Initialize hoster extractor state
+  let hoster_state = hoster_extractors::HosterState::default();
+
+  // Initialize torrent backend runtime state (per-launch token)
+  let backend_runtime_state = backend_runtime::BackendRuntimeState::default();
+
   #[allow(unused_mut)]
   let mut builder = tauri::Builder::default()
     .plugin(tauri_plugin_shell::init())
@@ -47,6 +139,7 @@ pub fn run() {
     .plugin(tauri_plugin_http::init())
     .plugin(tauri_plugin_websocket::init())
     .plugin(tauri_plugin_opener::init())
+    .plugin(tauri_plugin_notification::init())
     .plugin(tauri_plugin_store::Builder::default().build())
     .plugin(tauri_plugin_deep_link::init());
 
@@ -69,6 +162,31 @@ pub fn run() {
     .manage(anime4k_state)
     .manage(profile_state)
     .manage(miracast_state)
+    .manage(torrent_search_state)
+    .manage(download_queue_state)
+    .manage(hls_download_state)
+    .manage(subscription_state)
+    .manage(seeding_state)
+    .manage(subtitle_state)
+    .manage(media_server_state)
+    .manage(stream_proxy_state)
+    .manage(skip_state)
+    .manage(image_proxy_state)
+    .manage(zpe_state)
+    .manage(plugin_state)
+    .manage(plugin_fetch_state)
+    .manage(plugin_host_state)
+    .manage(plugin_storage_state)
+    .manage(plugin_update_state)
+    .manage(plugin_repo_state)
+    .manage(search_aggregator_state)
+    .manage(stream_selector_state)
+    .manage(episode_mapping_state)
+    .manage(animepahe_state)
+    .manage(hoster_state)
+    .manage(backend_runtime_state)
+    // Serve remote images through the disk cache (zimg://)
+    .register_asynchronous_uri_scheme_protocol(image_proxy::PROTOCOL, image_proxy::handle_protocol)
     .invoke_handler(tauri::generate_handler![
       // Window management commands
       minimize_window,
@@ -103,9 +221,13 @@ pub fn run() {
       anime4k::anime4k_get_requirements,
       anime4k::anime4k_get_config,
       anime4k::anime4k_set_config,
+      anime4k::anime4k_clear_config,
+      anime4k::anime4k_get_overrides,
       anime4k::anime4k_toggle,
       anime4k::anime4k_get_css_filter,
       anime4k::anime4k_recommend_preset,
+      anime4k::anime4k_get_mpv_args,
+      anime4k::anime4k_get_shader_sources,
       // Profile commands
       profiles::profile_get_all,
       profiles::profile_get,
@@ -137,6 +259,134 @@ pub fn run() {
       miracast::miracast_report_error,
       miracast::miracast_set_auto_reconnect,
       miracast::miracast_get_connection_health,
+      // Release name parser commands
+      release_parser::release_parse,
+      release_parser::release_parse_many,
+      // Torrent search commands
+      torrent_search::torrent_search,
+      torrent_search::torrent_get_sources,
+      // Download queue commands
+      downloads::download_list,
+      downloads::download_add_torrent,
+      downloads::download_add_hls,
+      downloads::download_cancel,
+      downloads::download_retry,
+      downloads::download_clear_finished,
+      // Subscription commands
+      subscriptions::subscription_list,
+      subscriptions::subscription_add,
+      subscriptions::subscription_update,
+      subscriptions::subscription_remove,
+      subscriptions::subscription_set_enabled,
+      subscriptions::subscription_get_config,
+      subscriptions::subscription_set_config,
+      subscriptions::subscription_check_now,
+      // Seeding manager commands
+      seeding::seeding_list,
+      seeding::seeding_get_config,
+      seeding::seeding_set_config,
+      seeding::seeding_set_item_policy,
+      seeding::seeding_stop,
+      seeding::seeding_resume,
+      seeding::seeding_add_path,
+      seeding::seeding_forget,
+      seeding::seeding_set_metered,
+      seeding::seeding_set_playing,
+      // Subtitle extraction commands
+      subtitles::subtitle_open,
+      subtitles::subtitle_get_track,
+      subtitles::subtitle_extract_fonts,
+      subtitles::subtitle_close,
+      subtitles::subtitle_convert_ass,
+      media_server::media_server_get_url,
+      // Stream proxy commands
+      stream_proxy::stream_proxy_register,
+      stream_proxy::stream_proxy_revoke,
+      stream_proxy::stream_proxy_list,
+      // Media probe commands
+      media_probe::media_probe,
+      media_probe::playback_get_capabilities,
+      media_probe::playback_recommend,
+      // Skip segment commands
+      skip_segments::skip_get_segments,
+      skip_segments::skip_get_providers,
+      skip_segments::skip_clear_cache,
+      // Image cache commands
+      image_proxy::image_cache_get_stats,
+      image_proxy::image_cache_clear,
+      // ZPE package commands
+      zpe::zpe_inspect,
+      zpe::zpe_verify,
+      zpe::zpe_list_trusted_keys,
+      zpe::zpe_trust_key,
+      zpe::zpe_untrust_key,
+      // Plugin registry commands
+      plugins::plugin_list,
+      plugins::plugin_install,
+      plugins::plugin_uninstall,
+      plugins::plugin_enable,
+      plugins::plugin_disable,
+      plugins::plugin_rollback,
+      plugins::plugin_load,
+      plugins::plugin_unload,
+      // Plugin network commands
+      plugin_fetch::plugin_fetch,
+      plugin_fetch::plugin_audit_log,
+      // Plugin host commands
+      plugin_host::plugin_call,
+      plugin_host::plugin_search,
+      plugin_host::plugin_get_episodes,
+      plugin_host::plugin_get_streams,
+      plugin_host::plugin_host_unload,
+      // Plugin HTML extraction commands
+      plugin_html::html_extract,
+      // Plugin storage commands
+      plugin_storage::plugin_storage_get,
+      plugin_storage::plugin_storage_set,
+      plugin_storage::plugin_storage_remove,
+      plugin_storage::plugin_storage_clear,
+      plugin_storage::plugin_storage_keys,
+      plugin_storage::plugin_storage_usage,
+      // Plugin update commands
+      plugin_updates::plugin_updates_check,
+      plugin_updates::plugin_updates_list,
+      plugin_updates::plugin_updates_apply,
+      plugin_updates::plugin_updates_dismiss,
+      plugin_updates::plugin_updates_get_config,
+      plugin_updates::plugin_updates_set_config,
+      // Plugin repository commands
+      plugin_repos::plugin_repo_list,
+      plugin_repos::plugin_repo_add,
+      plugin_repos::plugin_repo_remove,
+      plugin_repos::plugin_repo_refresh,
+      plugin_repos::plugin_repo_available,
+      plugin_repos::plugin_repo_install,
+      // Cross-provider search
+      search_aggregator::search_aggregate,
+      // Stream selection and failover
+      stream_selector::stream_select,
+      stream_selector::stream_next_fallback,
+      stream_selector::stream_selection_release,
+      // Provider <-> AniList episode mapping
+      episode_mapping::episode_mapping_get,
+      episode_mapping::episode_mapping_resolve,
+      episode_mapping::episode_mapping_link_provider,
+      episode_mapping::episode_mapping_from_provider,
+      episode_mapping::episode_mapping_to_provider,
+      episode_mapping::episode_mapping_unlink_provider,
+      episode_mapping::episode_mapping_clear_cache,
+      // AnimePahe provider
+      animepahe::animepahe_search,
+      animepahe::animepahe_latest,
+      animepahe::animepahe_details,
+      animepahe::animepahe_episodes,
+      animepahe::animepahe_all_episodes,
+      animepahe::animepahe_streams,
+      animepahe::animepahe_set_cookies,
+      // Hoster stream extraction
+      hoster_extractors::hoster_extract,
+      hoster_extractors::hoster_info,
+      hoster_extractors::hoster_list,
     ])
     .setup(|app| {
       // Enable logging in both debug and production builds
@@ -191,6 +441,23 @@ pub fn run() {
           }
         }
       }
+
+      // Share the backend token and download roots with the torrent backend
+      if let Err(e) = backend_runtime::write_runtime_file(app.handle()) {
+        log::error!("Failed to write torrent backend runtime file: {}", e);
+      }
+
+      // Poll RSS subscriptions in the background
+      subscriptions::start_scheduler(app.handle().clone());
+
+      // Check installed plugins for updates in the background
+      plugin_updates::start_scheduler(app.handle().clone());
+
+      // Apply the seeding policy to finished torrents
+      seeding::start_monitor(app.handle().clone());
+
+      // Serve subtitles (and other media routes) next to the backend port
+      media_server::start(app.handle().clone());
       
       Ok(())
     })
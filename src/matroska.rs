@@ -0,0 +1,1128 @@
+//! Matroska Demuxer
+//!
+//! This module is a small push-based EBML/Matroska parser. Bytes are fed as they
+//! arrive (from a file or a torrent stream that is still downloading) and events are
+//! emitted as soon as complete elements are available:
+//!
+//! - Segment info, tracks, chapters, attachments and the seek head
+//! - Blocks of subtitle tracks, with absolute timestamps and durations
+//!
+//! Clusters are never buffered as a whole, and video/audio blocks are skipped without
+//! being buffered, so memory use stays small even for multi-gigabyte files.
+
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+
+/// EBML and Matroska element IDs (marker bits included)
+pub mod ids {
+    pub const EBML: u32 = 0x1A45DFA3;
+    pub const DOC_TYPE: u32 = 0x4282;
+    pub const SEGMENT: u32 = 0x18538067;
+    pub const SEEK_HEAD: u32 = 0x114D9B74;
+    pub const SEEK: u32 = 0x4DBB;
+    pub const SEEK_ID: u32 = 0x53AB;
+    pub const SEEK_POSITION: u32 = 0x53AC;
+    pub const INFO: u32 = 0x1549A966;
+    pub const TIMECODE_SCALE: u32 = 0x2AD7B1;
+    pub const DURATION: u32 = 0x4489;
+    pub const TITLE: u32 = 0x7BA9;
+    pub const TRACKS: u32 = 0x1654AE6B;
+    pub const TRACK_ENTRY: u32 = 0xAE;
+    pub const TRACK_NUMBER: u32 = 0xD7;
+    pub const TRACK_UID: u32 = 0x73C5;
+    pub const TRACK_TYPE: u32 = 0x83;
+    pub const CODEC_ID: u32 = 0x86;
+    pub const CODEC_PRIVATE: u32 = 0x63A2;
+    pub const NAME: u32 = 0x536E;
+    pub const LANGUAGE: u32 = 0x22B59C;
+    pub const LANGUAGE_IETF: u32 = 0x22B59D;
+    pub const FLAG_DEFAULT: u32 = 0x88;
+    pub const FLAG_FORCED: u32 = 0x55AA;
+    pub const DEFAULT_DURATION: u32 = 0x23E383;
+    pub const VIDEO: u32 = 0xE0;
+    pub const PIXEL_WIDTH: u32 = 0xB0;
+    pub const PIXEL_HEIGHT: u32 = 0xBA;
+    pub const AUDIO: u32 = 0xE1;
+    pub const SAMPLING_FREQUENCY: u32 = 0xB5;
+    pub const CHANNELS: u32 = 0x9F;
+    pub const CONTENT_ENCODINGS: u32 = 0x6D80;
+    pub const CONTENT_ENCODING: u32 = 0x6240;
+    pub const CONTENT_ENCODING_SCOPE: u32 = 0x5032;
+    pub const CONTENT_ENCODING_TYPE: u32 = 0x5033;
+    pub const CONTENT_COMPRESSION: u32 = 0x5034;
+    pub const CONTENT_COMP_ALGO: u32 = 0x4254;
+    pub const CONTENT_COMP_SETTINGS: u32 = 0x4255;
+    pub const CLUSTER: u32 = 0x1F43B675;
+    pub const TIMECODE: u32 = 0xE7;
+    pub const SIMPLE_BLOCK: u32 = 0xA3;
+    pub const BLOCK_GROUP: u32 = 0xA0;
+    pub const BLOCK: u32 = 0xA1;
+    pub const BLOCK_DURATION: u32 = 0x9B;
+    pub const CUES: u32 = 0x1C53BB6B;
+    pub const CHAPTERS: u32 = 0x1043A770;
+    pub const EDITION_ENTRY: u32 = 0x45B9;
+    pub const CHAPTER_ATOM: u32 = 0xB6;
+    pub const CHAPTER_UID: u32 = 0x73C4;
+    pub const CHAPTER_TIME_START: u32 = 0x91;
+    pub const CHAPTER_TIME_END: u32 = 0x92;
+    pub const CHAPTER_FLAG_HIDDEN: u32 = 0x98;
+    pub const CHAPTER_DISPLAY: u32 = 0x80;
+    pub const CHAP_STRING: u32 = 0x85;
+    pub const CHAP_LANGUAGE: u32 = 0x437C;
+    pub const ATTACHMENTS: u32 = 0x1941A469;
+    pub const ATTACHED_FILE: u32 = 0x61A7;
+    pub const FILE_DESCRIPTION: u32 = 0x467E;
+    pub const FILE_NAME: u32 = 0x466E;
+    pub const FILE_MIME_TYPE: u32 = 0x4660;
+    pub const FILE_DATA: u32 = 0x465C;
+    pub const FILE_UID: u32 = 0x46AE;
+}
+
+/// Largest element that is buffered as a whole (attachments can hold many fonts)
+const MAX_BUFFERED_ELEMENT: u64 = 128 * 1024 * 1024;
+
+/// Consumed bytes are dropped from the buffer once this many accumulate
+const COMPACT_THRESHOLD: usize = 64 * 1024;
+
+/// Default timestamp scale (1 ms per tick)
+const DEFAULT_TIMECODE_SCALE: u64 = 1_000_000;
+
+/// Errors raised while demuxing
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatroskaError {
+    /// The data doesn't start with an EBML header
+    NotMatroska,
+    /// The data is corrupt
+    InvalidData(String),
+    /// The file uses a feature this demuxer doesn't handle
+    Unsupported(String),
+}
+
+impl std::fmt::Display for MatroskaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatroskaError::NotMatroska => write!(f, "Not a Matroska file"),
+            MatroskaError::InvalidData(msg) => write!(f, "Invalid Matroska data: {}", msg),
+            MatroskaError::Unsupported(msg) => write!(f, "Unsupported Matroska feature: {}", msg),
+        }
+    }
+}
+
+/// Kind of a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackKind {
+    Video,
+    Audio,
+    Subtitle,
+    Other,
+}
+
+impl TrackKind {
+    fn from_type(track_type: u64) -> Self {
+        match track_type {
+            1 => TrackKind::Video,
+            2 => TrackKind::Audio,
+            17 => TrackKind::Subtitle,
+            _ => TrackKind::Other,
+        }
+    }
+}
+
+/// Compression applied to a track's frames
+#[derive(Debug, Clone, PartialEq)]
+pub enum Compression {
+    Zlib,
+    /// Bytes stripped from the start of every frame
+    HeaderStripping(Vec<u8>),
+}
+
+/// A track of the file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackInfo {
+    /// Track number used by blocks
+    pub number: u64,
+    pub uid: u64,
+    pub kind: TrackKind,
+    /// Matroska codec ID (e.g. `S_TEXT/ASS`, `V_MPEGH/ISO/HEVC`)
+    pub codec_id: String,
+    pub name: Option<String>,
+    /// Language (BCP 47 when available, otherwise ISO 639-2)
+    pub language: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+    /// Default frame duration in nanoseconds
+    pub default_duration_ns: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<f64>,
+    /// Codec private data (the ASS header for ASS tracks), already decompressed
+    #[serde(skip)]
+    pub codec_private: Option<Vec<u8>>,
+    #[serde(skip)]
+    pub compression: Option<Compression>,
+}
+
+/// A file attached to the segment (usually a font)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub uid: u64,
+    pub name: String,
+    pub mime_type: String,
+    pub description: Option<String>,
+    pub size: u64,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    /// Whether the attachment is a font
+    pub fn is_font(&self) -> bool {
+        let mime = self.mime_type.to_lowercase();
+        let name = self.name.to_lowercase();
+        mime.starts_with("font/")
+            || mime.contains("truetype")
+            || mime.contains("opentype")
+            || mime.contains("font-")
+            || [".ttf", ".otf", ".ttc", ".woff", ".woff2"]
+                .iter()
+                .any(|ext| name.ends_with(ext))
+    }
+}
+
+/// A chapter of the default edition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    pub uid: u64,
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+    pub title: Option<String>,
+    pub hidden: bool,
+}
+
+/// An entry of the seek head
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekEntry {
+    pub id: u32,
+    /// Absolute byte offset of the element
+    pub offset: u64,
+}
+
+/// A block of a subtitle track
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub track: u64,
+    /// Absolute timestamp in milliseconds
+    pub timestamp_ms: i64,
+    pub duration_ms: Option<i64>,
+    pub data: Vec<u8>,
+}
+
+/// Something the parser found
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatroskaEvent {
+    Info {
+        duration_ms: Option<f64>,
+        title: Option<String>,
+    },
+    SeekHead(Vec<SeekEntry>),
+    Tracks(Vec<TrackInfo>),
+    Chapters(Vec<Chapter>),
+    Attachments(Vec<Attachment>),
+    /// A cluster starts at this absolute offset
+    Cluster(u64),
+    Block(Block),
+}
+
+/// Push-based Matroska parser
+pub struct MatroskaParser {
+    buf: Vec<u8>,
+    pos: usize,
+    /// Absolute offset of `buf[pos]`
+    offset: u64,
+    /// Bytes of a skipped element still to discard
+    skip: u64,
+    started: bool,
+    segment_data_offset: u64,
+    timecode_scale: u64,
+    cluster_timecode: i64,
+    tracks: Vec<TrackInfo>,
+}
+
+impl Default for MatroskaParser {
+    fn default() -> Self {
+        MatroskaParser {
+            buf: Vec::new(),
+            pos: 0,
+            offset: 0,
+            skip: 0,
+            started: false,
+            segment_data_offset: 0,
+            timecode_scale: DEFAULT_TIMECODE_SCALE,
+            cluster_timecode: 0,
+            tracks: Vec::new(),
+        }
+    }
+}
+
+impl MatroskaParser {
+    pub fn new() -> Self {
+        MatroskaParser::default()
+    }
+
+    /// Absolute offset of the next byte the parser expects
+    pub fn offset(&self) -> u64 {
+        self.offset + (self.buf.len() - self.pos) as u64
+    }
+
+    /// Tracks found so far
+    pub fn tracks(&self) -> &[TrackInfo] {
+        &self.tracks
+    }
+
+    /// Continue parsing at an absolute offset (must be the start of an element);
+    /// buffered data is discarded
+    pub fn seek(&mut self, offset: u64) {
+        self.buf.clear();
+        self.pos = 0;
+        self.skip = 0;
+        self.offset = offset;
+    }
+
+    /// Feed the next bytes and return the events that became available
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<MatroskaEvent>, MatroskaError> {
+        self.buf.extend_from_slice(data);
+        let mut events = Vec::new();
+        let result = self.drain(&mut events);
+
+        if self.pos >= COMPACT_THRESHOLD || self.pos == self.buf.len() {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+
+        result.map(|_| events)
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.pos += n;
+        self.offset += n as u64;
+    }
+
+    fn drain(&mut self, events: &mut Vec<MatroskaEvent>) -> Result<(), MatroskaError> {
+        loop {
+            if self.skip > 0 {
+                let available = (self.buf.len() - self.pos) as u64;
+                let n = self.skip.min(available) as usize;
+                self.consume(n);
+                self.skip -= n as u64;
+                if self.skip > 0 {
+                    return Ok(());
+                }
+            }
+
+            let avail = &self.buf[self.pos..];
+            let (id, id_len) = match read_id(avail)? {
+                Some(v) => v,
+                None => return Ok(()),
+            };
+            if !self.started && (id != ids::EBML) {
+                return Err(MatroskaError::NotMatroska);
+            }
+            let (size, size_len) = match read_size(&avail[id_len..])? {
+                Some(v) => v,
+                None => return Ok(()),
+            };
+            let header = id_len + size_len;
+
+            match id {
+                // Masters that are descended into instead of buffered
+                ids::SEGMENT | ids::CLUSTER => {
+                    if id == ids::SEGMENT {
+                        self.segment_data_offset = self.offset + header as u64;
+                    } else {
+                        events.push(MatroskaEvent::Cluster(self.offset));
+                    }
+                    self.consume(header);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let size = size.ok_or_else(|| {
+                MatroskaError::Unsupported(format!("unknown size for element {:#X}", id))
+            })?;
+
+            let buffered = matches!(
+                id,
+                ids::EBML
+                    | ids::INFO
+                    | ids::TRACKS
+                    | ids::SEEK_HEAD
+                    | ids::CHAPTERS
+                    | ids::ATTACHMENTS
+                    | ids::TIMECODE
+                    | ids::SIMPLE_BLOCK
+                    | ids::BLOCK_GROUP
+            );
+            if !buffered {
+                self.consume(header);
+                self.skip = size;
+                continue;
+            }
+
+            // Blocks of tracks we don't extract are skipped without buffering them
+            if id == ids::SIMPLE_BLOCK || id == ids::BLOCK_GROUP {
+                let body = &avail[header..];
+                let track = if id == ids::SIMPLE_BLOCK {
+                    read_vint(body)?.map(|(track, _)| track)
+                } else {
+                    peek_block_group_track(body)?
+                };
+                match track {
+                    Some(track) if !self.is_subtitle_track(track) => {
+                        self.consume(header);
+                        self.skip = size;
+                        continue;
+                    }
+                    Some(_) => {}
+                    None if (body.len() as u64) < size => return Ok(()),
+                    None => {}
+                }
+            }
+
+            if size > MAX_BUFFERED_ELEMENT {
+                return Err(MatroskaError::Unsupported(format!(
+                    "element {:#X} is too large ({} bytes)",
+                    id, size
+                )));
+            }
+            let total = header + size as usize;
+            if avail.len() < total {
+                return Ok(());
+            }
+
+            let body = avail[header..total].to_vec();
+            self.consume(total);
+            self.handle_element(id, &body, events)?;
+        }
+    }
+
+    fn is_subtitle_track(&self, number: u64) -> bool {
+        self.tracks
+            .iter()
+            .any(|t| t.number == number && t.kind == TrackKind::Subtitle)
+    }
+
+    fn handle_element(
+        &mut self,
+        id: u32,
+        body: &[u8],
+        events: &mut Vec<MatroskaEvent>,
+    ) -> Result<(), MatroskaError> {
+        match id {
+            ids::EBML => {
+                let doc_type = children(body)?
+                    .into_iter()
+                    .find(|(id, _)| *id == ids::DOC_TYPE)
+                    .map(|(_, data)| read_string(data))
+                    .unwrap_or_else(|| "matroska".to_string());
+                if doc_type != "matroska" && doc_type != "webm" {
+                    return Err(MatroskaError::NotMatroska);
+                }
+                self.started = true;
+            }
+            ids::INFO => {
+                let mut duration = None;
+                let mut title = None;
+                for (id, data) in children(body)? {
+                    match id {
+                        ids::TIMECODE_SCALE => self.timecode_scale = read_uint(data).max(1),
+                        ids::DURATION => duration = read_float(data),
+                        ids::TITLE => title = Some(read_string(data)),
+                        _ => {}
+                    }
+                }
+                let duration_ms =
+                    duration.map(|d| d * self.timecode_scale as f64 / 1_000_000.0);
+                events.push(MatroskaEvent::Info { duration_ms, title });
+            }
+            ids::SEEK_HEAD => {
+                let mut entries = Vec::new();
+                for (id, data) in children(body)? {
+                    if id != ids::SEEK {
+                        continue;
+                    }
+                    let mut seek_id = None;
+                    let mut position = None;
+                    for (child, value) in children(data)? {
+                        match child {
+                            ids::SEEK_ID => seek_id = Some(read_uint(value) as u32),
+                            ids::SEEK_POSITION => position = Some(read_uint(value)),
+                            _ => {}
+                        }
+                    }
+                    if let (Some(id), Some(position)) = (seek_id, position) {
+                        entries.push(SeekEntry { id, offset: self.segment_data_offset + position });
+                    }
+                }
+                events.push(MatroskaEvent::SeekHead(entries));
+            }
+            ids::TRACKS => {
+                let mut tracks = Vec::new();
+                for (id, data) in children(body)? {
+                    if id == ids::TRACK_ENTRY {
+                        tracks.push(parse_track_entry(data)?);
+                    }
+                }
+                self.tracks = tracks.clone();
+                events.push(MatroskaEvent::Tracks(tracks));
+            }
+            ids::CHAPTERS => {
+                events.push(MatroskaEvent::Chapters(parse_chapters(body)?));
+            }
+            ids::ATTACHMENTS => {
+                let mut attachments = Vec::new();
+                for (id, data) in children(body)? {
+                    if id == ids::ATTACHED_FILE {
+                        attachments.push(parse_attachment(data)?);
+                    }
+                }
+                events.push(MatroskaEvent::Attachments(attachments));
+            }
+            ids::TIMECODE => {
+                self.cluster_timecode = read_uint(body) as i64;
+            }
+            ids::SIMPLE_BLOCK => {
+                if let Some(block) = self.parse_block(body, None)? {
+                    events.push(MatroskaEvent::Block(block));
+                }
+            }
+            ids::BLOCK_GROUP => {
+                let mut block = None;
+                let mut duration = None;
+                for (id, data) in children(body)? {
+                    match id {
+                        ids::BLOCK => block = Some(data),
+                        ids::BLOCK_DURATION => duration = Some(read_uint(data)),
+                        _ => {}
+                    }
+                }
+                if let Some(data) = block {
+                    if let Some(block) = self.parse_block(data, duration)? {
+                        events.push(MatroskaEvent::Block(block));
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn parse_block(
+        &self,
+        data: &[u8],
+        duration: Option<u64>,
+    ) -> Result<Option<Block>, MatroskaError> {
+        let (track, len) = read_vint(data)?
+            .ok_or_else(|| MatroskaError::InvalidData("truncated block".to_string()))?;
+        if data.len() < len + 3 {
+            return Err(MatroskaError::InvalidData("truncated block".to_string()));
+        }
+        let relative = i16::from_be_bytes([data[len], data[len + 1]]) as i64;
+        let flags = data[len + 2];
+        if flags & 0x06 != 0 {
+            log::debug!("Skipping laced block on track {}", track);
+            return Ok(None);
+        }
+
+        let info = match self.tracks.iter().find(|t| t.number == track) {
+            Some(info) if info.kind == TrackKind::Subtitle => info,
+            _ => return Ok(None),
+        };
+        let payload = decompress(&data[len + 3..], info.compression.as_ref())?;
+
+        let scale = self.timecode_scale as i128;
+        let timestamp_ms = ((self.cluster_timecode + relative) as i128 * scale / 1_000_000) as i64;
+        let duration_ms = duration
+            .map(|d| (d as i128 * scale / 1_000_000) as i64)
+            .or_else(|| info.default_duration_ns.map(|ns| (ns / 1_000_000) as i64));
+
+        Ok(Some(Block { track, timestamp_ms, duration_ms, data: payload }))
+    }
+}
+
+// =============================================================================
+// Element Parsing
+// =============================================================================
+
+fn parse_track_entry(data: &[u8]) -> Result<TrackInfo, MatroskaError> {
+    let mut track = TrackInfo {
+        number: 0,
+        uid: 0,
+        kind: TrackKind::Other,
+        codec_id: String::new(),
+        name: None,
+        language: None,
+        default: true,
+        forced: false,
+        default_duration_ns: None,
+        width: None,
+        height: None,
+        channels: None,
+        sample_rate: None,
+        codec_private: None,
+        compression: None,
+    };
+    let mut language = None;
+    let mut language_ietf = None;
+    let mut private_compression = None;
+
+    for (id, value) in children(data)? {
+        match id {
+            ids::TRACK_NUMBER => track.number = read_uint(value),
+            ids::TRACK_UID => track.uid = read_uint(value),
+            ids::TRACK_TYPE => track.kind = TrackKind::from_type(read_uint(value)),
+            ids::CODEC_ID => track.codec_id = read_string(value),
+            ids::CODEC_PRIVATE => track.codec_private = Some(value.to_vec()),
+            ids::NAME => track.name = Some(read_string(value)),
+            ids::LANGUAGE => language = Some(read_string(value)),
+            ids::LANGUAGE_IETF => language_ietf = Some(read_string(value)),
+            ids::FLAG_DEFAULT => track.default = read_uint(value) != 0,
+            ids::FLAG_FORCED => track.forced = read_uint(value) != 0,
+            ids::DEFAULT_DURATION => track.default_duration_ns = Some(read_uint(value)),
+            ids::VIDEO => {
+                for (id, value) in children(value)? {
+                    match id {
+                        ids::PIXEL_WIDTH => track.width = Some(read_uint(value) as u32),
+                        ids::PIXEL_HEIGHT => track.height = Some(read_uint(value) as u32),
+                        _ => {}
+                    }
+                }
+            }
+            ids::AUDIO => {
+                for (id, value) in children(value)? {
+                    match id {
+                        ids::CHANNELS => track.channels = Some(read_uint(value) as u32),
+                        ids::SAMPLING_FREQUENCY => track.sample_rate = read_float(value),
+                        _ => {}
+                    }
+                }
+            }
+            ids::CONTENT_ENCODINGS => {
+                let (compression, scope) = parse_content_encodings(value)?;
+                if scope & 1 != 0 {
+                    track.compression = compression.clone();
+                }
+                if scope & 2 != 0 {
+                    private_compression = compression;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    track.language = language_ietf.or(language).filter(|l| l != "und");
+
+    // Codec private data is compressed too when the encoding scope says so
+    if let (Some(compression), Some(private)) = (&private_compression, &track.codec_private) {
+        track.codec_private = Some(decompress(private, Some(compression))?);
+    }
+    Ok(track)
+}
+
+/// Parse content encodings: the compression and its scope (1 = frames, 2 = codec private)
+fn parse_content_encodings(data: &[u8]) -> Result<(Option<Compression>, u64), MatroskaError> {
+    for (id, encoding) in children(data)? {
+        if id != ids::CONTENT_ENCODING {
+            continue;
+        }
+        let mut scope = 1;
+        let mut encoding_type = 0;
+        let mut compression = None;
+        for (id, value) in children(encoding)? {
+            match id {
+                ids::CONTENT_ENCODING_SCOPE => scope = read_uint(value),
+                ids::CONTENT_ENCODING_TYPE => encoding_type = read_uint(value),
+                ids::CONTENT_COMPRESSION => {
+                    let mut algo = 0;
+                    let mut settings = Vec::new();
+                    for (id, value) in children(value)? {
+                        match id {
+                            ids::CONTENT_COMP_ALGO => algo = read_uint(value),
+                            ids::CONTENT_COMP_SETTINGS => settings = value.to_vec(),
+                            _ => {}
+                        }
+                    }
+                    compression = match algo {
+                        0 => Some(Compression::Zlib),
+                        3 => Some(Compression::HeaderStripping(settings)),
+                        other => {
+                            return Err(MatroskaError::Unsupported(format!(
+                                "compression algorithm {}",
+                                other
+                            )))
+                        }
+                    };
+                }
+                _ => {}
+            }
+        }
+        if encoding_type != 0 {
+            return Err(MatroskaError::Unsupported("encrypted tracks".to_string()));
+        }
+        return Ok((compression, scope));
+    }
+    Ok((None, 0))
+}
+
+fn parse_attachment(data: &[u8]) -> Result<Attachment, MatroskaError> {
+    let mut attachment = Attachment {
+        uid: 0,
+        name: String::new(),
+        mime_type: String::new(),
+        description: None,
+        size: 0,
+        data: Vec::new(),
+    };
+    for (id, value) in children(data)? {
+        match id {
+            ids::FILE_UID => attachment.uid = read_uint(value),
+            ids::FILE_NAME => attachment.name = read_string(value),
+            ids::FILE_MIME_TYPE => attachment.mime_type = read_string(value),
+            ids::FILE_DESCRIPTION => attachment.description = Some(read_string(value)),
+            ids::FILE_DATA => {
+                attachment.size = value.len() as u64;
+                attachment.data = value.to_vec();
+            }
+            _ => {}
+        }
+    }
+    Ok(attachment)
+}
+
+/// Parse the chapters of the first edition (nested chapters are flattened)
+fn parse_chapters(data: &[u8]) -> Result<Vec<Chapter>, MatroskaError> {
+    let edition = match children(data)?.into_iter().find(|(id, _)| *id == ids::EDITION_ENTRY) {
+        Some((_, edition)) => edition,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut chapters = Vec::new();
+    collect_chapter_atoms(edition, &mut chapters)?;
+    chapters.sort_by_key(|c| c.start_ms);
+    Ok(chapters)
+}
+
+fn collect_chapter_atoms(data: &[u8], chapters: &mut Vec<Chapter>) -> Result<(), MatroskaError> {
+    for (id, atom) in children(data)? {
+        if id != ids::CHAPTER_ATOM {
+            continue;
+        }
+        // Chapter times are always in nanoseconds, independent of the timecode scale
+        let mut chapter = Chapter { uid: 0, start_ms: 0, end_ms: None, title: None, hidden: false };
+        for (id, value) in children(atom)? {
+            match id {
+                ids::CHAPTER_UID => chapter.uid = read_uint(value),
+                ids::CHAPTER_TIME_START => chapter.start_ms = read_uint(value) / 1_000_000,
+                ids::CHAPTER_TIME_END => chapter.end_ms = Some(read_uint(value) / 1_000_000),
+                ids::CHAPTER_FLAG_HIDDEN => chapter.hidden = read_uint(value) != 0,
+                ids::CHAPTER_DISPLAY if chapter.title.is_none() => {
+                    chapter.title = children(value)?
+                        .into_iter()
+                        .find(|(id, _)| *id == ids::CHAP_STRING)
+                        .map(|(_, s)| read_string(s));
+                }
+                _ => {}
+            }
+        }
+        chapters.push(chapter);
+        collect_chapter_atoms(atom, chapters)?;
+    }
+    Ok(())
+}
+
+/// Peek the track number of a block group from its first bytes (`None` if more
+/// data is needed or the group doesn't start with a block)
+fn peek_block_group_track(body: &[u8]) -> Result<Option<u64>, MatroskaError> {
+    let (id, id_len) = match read_id(body)? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    if id != ids::BLOCK {
+        return Ok(None);
+    }
+    let (_, size_len) = match read_size(&body[id_len..])? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    Ok(read_vint(&body[id_len + size_len..])?.map(|(track, _)| track))
+}
+
+/// Undo a track's content compression
+fn decompress(data: &[u8], compression: Option<&Compression>) -> Result<Vec<u8>, MatroskaError> {
+    match compression {
+        None => Ok(data.to_vec()),
+        Some(Compression::HeaderStripping(header)) => {
+            let mut out = header.clone();
+            out.extend_from_slice(data);
+            Ok(out)
+        }
+        Some(Compression::Zlib) => {
+            let mut out = Vec::new();
+            ZlibDecoder::new(data)
+                .read_to_end(&mut out)
+                .map_err(|e| MatroskaError::InvalidData(format!("zlib: {}", e)))?;
+            Ok(out)
+        }
+    }
+}
+
+// =============================================================================
+// EBML Primitives
+// =============================================================================
+
+/// Length of a variable-size integer from its first byte
+fn vint_length(first: u8) -> Option<usize> {
+    if first == 0 {
+        None
+    } else {
+        Some(first.leading_zeros() as usize + 1)
+    }
+}
+
+/// Read an element ID (marker bits kept); `None` if more data is needed
+pub fn read_id(data: &[u8]) -> Result<Option<(u32, usize)>, MatroskaError> {
+    let first = match data.first() {
+        Some(first) => *first,
+        None => return Ok(None),
+    };
+    let len = match vint_length(first) {
+        Some(len) if len <= 4 => len,
+        _ => return Err(MatroskaError::InvalidData(format!("invalid element ID byte {:#X}", first))),
+    };
+    if data.len() < len {
+        return Ok(None);
+    }
+    let id = data[..len].iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+    Ok(Some((id, len)))
+}
+
+/// Read a variable-size integer (marker bit removed); `None` if more data is needed
+pub fn read_vint(data: &[u8]) -> Result<Option<(u64, usize)>, MatroskaError> {
+    let first = match data.first() {
+        Some(first) => *first,
+        None => return Ok(None),
+    };
+    let len = vint_length(first)
+        .ok_or_else(|| MatroskaError::InvalidData("invalid variable-size integer".to_string()))?;
+    if data.len() < len {
+        return Ok(None);
+    }
+    let mut value = (first as u64) & (0xFF >> len);
+    for byte in &data[1..len] {
+        value = (value << 8) | *byte as u64;
+    }
+    Ok(Some((value, len)))
+}
+
+/// Read an element size; the inner `None` marks an unknown size
+fn read_size(data: &[u8]) -> Result<Option<(Option<u64>, usize)>, MatroskaError> {
+    Ok(read_vint(data)?.map(|(value, len)| {
+        let unknown = (1u64 << (7 * len)) - 1;
+        (if value == unknown { None } else { Some(value) }, len)
+    }))
+}
+
+/// Split a buffered master element into its children
+pub fn children(data: &[u8]) -> Result<Vec<(u32, &[u8])>, MatroskaError> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let truncated = || MatroskaError::InvalidData("truncated element".to_string());
+        let (id, id_len) = read_id(&data[pos..])?.ok_or_else(truncated)?;
+        let (size, size_len) = read_size(&data[pos + id_len..])?.ok_or_else(truncated)?;
+        let start = pos + id_len + size_len;
+        let end = match size {
+            Some(size) => start
+                .checked_add(size as usize)
+                .filter(|end| *end <= data.len())
+                .ok_or_else(truncated)?,
+            None => data.len(),
+        };
+        result.push((id, &data[start..end]));
+        pos = end;
+    }
+    Ok(result)
+}
+
+pub fn read_uint(data: &[u8]) -> u64 {
+    data.iter().take(8).fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+pub fn read_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes([data[0], data[1], data[2], data[3]]) as f64),
+        8 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            Some(f64::from_be_bytes(bytes))
+        }
+        _ => None,
+    }
+}
+
+pub fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    //! Helpers that build small Matroska files for tests
+
+    use super::ids;
+
+    /// Encode an element with a known size
+    pub fn element(id: u32, body: &[u8]) -> Vec<u8> {
+        let mut out = id_bytes(id);
+        out.extend(size_bytes(body.len() as u64));
+        out.extend_from_slice(body);
+        out
+    }
+
+    /// Encode the header of an element with unknown size
+    pub fn unknown_size_header(id: u32) -> Vec<u8> {
+        let mut out = id_bytes(id);
+        out.push(0xFF);
+        out
+    }
+
+    pub fn uint(id: u32, value: u64) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let first = bytes.iter().position(|b| *b != 0).unwrap_or(7);
+        element(id, &bytes[first..])
+    }
+
+    pub fn string(id: u32, value: &str) -> Vec<u8> {
+        element(id, value.as_bytes())
+    }
+
+    fn id_bytes(id: u32) -> Vec<u8> {
+        let bytes = id.to_be_bytes();
+        let first = bytes.iter().position(|b| *b != 0).unwrap_or(3);
+        bytes[first..].to_vec()
+    }
+
+    fn size_bytes(size: u64) -> Vec<u8> {
+        // Always use the 8-byte form to keep things simple
+        let mut out = vec![0x01];
+        out.extend_from_slice(&size.to_be_bytes()[1..]);
+        out
+    }
+
+    pub fn block(track: u8, relative: i16, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x80 | track];
+        out.extend_from_slice(&relative.to_be_bytes());
+        out.push(0x80);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// A file with a video track (1) and an ASS track (2), an attached font, a
+    /// chapter and one cluster holding a video frame and two subtitle blocks
+    pub fn sample_file() -> Vec<u8> {
+        let header = string(ids::DOC_TYPE, "matroska");
+
+        let ass_header = "[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\nFormat: Name, Fontname, Fontsize, Alignment\nStyle: Default,Arial,20,2\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n";
+        let tracks = [
+            element(
+                ids::TRACK_ENTRY,
+                &[
+                    uint(ids::TRACK_NUMBER, 1),
+                    uint(ids::TRACK_UID, 11),
+                    uint(ids::TRACK_TYPE, 1),
+                    string(ids::CODEC_ID, "V_MPEGH/ISO/HEVC"),
+                    element(ids::VIDEO, &[uint(ids::PIXEL_WIDTH, 1920), uint(ids::PIXEL_HEIGHT, 1080)].concat()),
+                ]
+                .concat(),
+            ),
+            element(
+                ids::TRACK_ENTRY,
+                &[
+                    uint(ids::TRACK_NUMBER, 2),
+                    uint(ids::TRACK_UID, 22),
+                    uint(ids::TRACK_TYPE, 17),
+                    string(ids::CODEC_ID, "S_TEXT/ASS"),
+                    string(ids::NAME, "English"),
+                    string(ids::LANGUAGE, "eng"),
+                    element(ids::CODEC_PRIVATE, ass_header.as_bytes()),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+
+        let chapters = element(
+            ids::EDITION_ENTRY,
+            &[element(
+                ids::CHAPTER_ATOM,
+                &[
+                    uint(ids::CHAPTER_UID, 1),
+                    uint(ids::CHAPTER_TIME_START, 90_000_000_000),
+                    uint(ids::CHAPTER_TIME_END, 180_000_000_000),
+                    element(ids::CHAPTER_DISPLAY, &string(ids::CHAP_STRING, "Opening")),
+                ]
+                .concat(),
+            )]
+            .concat(),
+        );
+
+        let attachments = element(
+            ids::ATTACHED_FILE,
+            &[
+                uint(ids::FILE_UID, 5),
+                string(ids::FILE_NAME, "Font.ttf"),
+                string(ids::FILE_MIME_TYPE, "application/x-truetype-font"),
+                element(ids::FILE_DATA, b"FONTDATA"),
+            ]
+            .concat(),
+        );
+
+        let cluster = [
+            uint(ids::TIMECODE, 1000),
+            element(ids::SIMPLE_BLOCK, &block(1, 0, &[0u8; 64])),
+            element(
+                ids::BLOCK_GROUP,
+                &[
+                    element(ids::BLOCK, &block(2, 500, b"0,0,Default,,0,0,0,,{\\i1}Hello{\\i0}\\Nworld")),
+                    uint(ids::BLOCK_DURATION, 2000),
+                ]
+                .concat(),
+            ),
+            element(
+                ids::BLOCK_GROUP,
+                &[
+                    element(ids::BLOCK, &block(2, 3000, b"1,0,Default,,0,0,0,,Second line")),
+                    uint(ids::BLOCK_DURATION, 1500),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+
+        [
+            element(ids::EBML, &header),
+            unknown_size_header(ids::SEGMENT),
+            element(ids::INFO, &uint(ids::TIMECODE_SCALE, 1_000_000)),
+            element(ids::TRACKS, &tracks),
+            element(ids::CHAPTERS, &chapters),
+            element(ids::ATTACHMENTS, &attachments),
+            unknown_size_header(ids::CLUSTER),
+            cluster,
+        ]
+        .concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::*;
+    use super::*;
+
+    fn parse_all(data: &[u8], chunk: usize) -> Vec<MatroskaEvent> {
+        let mut parser = MatroskaParser::new();
+        let mut events = Vec::new();
+        for piece in data.chunks(chunk) {
+            events.extend(parser.push(piece).unwrap());
+        }
+        events
+    }
+
+    #[test]
+    fn test_vint_and_id() {
+        assert_eq!(read_vint(&[0x81]).unwrap(), Some((1, 1)));
+        assert_eq!(read_vint(&[0x40, 0x02]).unwrap(), Some((2, 2)));
+        assert_eq!(read_vint(&[0x40]).unwrap(), None);
+        assert_eq!(read_id(&[0x1A, 0x45, 0xDF, 0xA3]).unwrap(), Some((ids::EBML, 4)));
+        assert_eq!(read_size(&[0xFF]).unwrap(), Some((None, 1)));
+        assert!(read_id(&[0x00]).is_err());
+    }
+
+    #[test]
+    fn test_parse_sample_file() {
+        let events = parse_all(&sample_file(), 4096);
+
+        let tracks = events
+            .iter()
+            .find_map(|e| match e {
+                MatroskaEvent::Tracks(tracks) => Some(tracks.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].kind, TrackKind::Video);
+        assert_eq!(tracks[0].height, Some(1080));
+        assert_eq!(tracks[1].codec_id, "S_TEXT/ASS");
+        assert_eq!(tracks[1].language.as_deref(), Some("eng"));
+        assert!(tracks[1].codec_private.is_some());
+
+        let blocks: Vec<&Block> = events
+            .iter()
+            .filter_map(|e| match e {
+                MatroskaEvent::Block(block) => Some(block),
+                _ => None,
+            })
+            .collect();
+        // The video frame is skipped, only subtitle blocks are returned
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].track, 2);
+        assert_eq!(blocks[0].timestamp_ms, 1500);
+        assert_eq!(blocks[0].duration_ms, Some(2000));
+        assert_eq!(blocks[1].timestamp_ms, 4000);
+
+        assert!(events.iter().any(|e| matches!(e,
+            MatroskaEvent::Chapters(chapters) if chapters[0].start_ms == 90_000
+                && chapters[0].title.as_deref() == Some("Opening"))));
+        assert!(events.iter().any(|e| matches!(e,
+            MatroskaEvent::Attachments(files) if files[0].is_font() && files[0].data == b"FONTDATA")));
+    }
+
+    #[test]
+    fn test_byte_by_byte_matches_single_push() {
+        let data = sample_file();
+        assert_eq!(parse_all(&data, 1), parse_all(&data, data.len()));
+    }
+
+    #[test]
+    fn test_rejects_non_matroska_and_truncation_is_not_an_error() {
+        let mut parser = MatroskaParser::new();
+        assert_eq!(parser.push(b"RIFF\x00\x00\x00\x00WAVE"), Err(MatroskaError::NotMatroska));
+
+        // A partially downloaded file yields what is complete so far
+        let data = sample_file();
+        let events = parse_all(&data[..data.len() - 20], 512);
+        assert!(events.iter().any(|e| matches!(e, MatroskaEvent::Tracks(_))));
+        let blocks = events.iter().filter(|e| matches!(e, MatroskaEvent::Block(_))).count();
+        assert_eq!(blocks, 1);
+    }
+
+    #[test]
+    fn test_header_stripping_and_zlib() {
+        let stripped = Compression::HeaderStripping(b"0,0,".to_vec());
+        assert_eq!(decompress(b"Default", Some(&stripped)).unwrap(), b"0,0,Default");
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"compressed subtitle").unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(
+            decompress(&compressed, Some(&Compression::Zlib)).unwrap(),
+            b"compressed subtitle"
+        );
+    }
+}
@@ -0,0 +1,1239 @@
+//! Media Probing
+//!
+//! This module reads the headers of MP4 and MKV files (codecs, profiles, bit depth,
+//! audio formats, duration, chapters) without decoding anything, from local files or
+//! from the first bytes of a stream, and decides whether the built-in player can play
+//! them or the external player should be used.
+//!
+//! Streams are read with range requests, so probing a torrent stream only downloads
+//! the header (plus the `moov` box of MP4 files that keep it at the end).
+
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+use tauri_plugin_http::reqwest;
+
+use crate::matroska::{self, MatroskaEvent, MatroskaParser, TrackInfo, TrackKind};
+
+/// Size of a single read while probing
+const PROBE_CHUNK_SIZE: u64 = 512 * 1024;
+
+/// Maximum number of bytes read for Matroska headers (large font attachments
+/// sometimes come before the first cluster)
+const MAX_MATROSKA_HEADER_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Maximum size of an MP4 `moov` box
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Maximum number of top-level MP4 boxes to walk before giving up
+const MAX_TOP_LEVEL_BOXES: usize = 64;
+
+/// Where media is read from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MediaSource {
+    File { path: String },
+    Url { url: String },
+}
+
+impl MediaSource {
+    /// Interpret a path or an `http(s)://` URL
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let source = source.trim();
+        if source.is_empty() {
+            return Err("Media source cannot be empty".to_string());
+        }
+        if source.starts_with("http://") || source.starts_with("https://") {
+            Ok(MediaSource::Url { url: source.to_string() })
+        } else {
+            Ok(MediaSource::File { path: source.to_string() })
+        }
+    }
+
+    /// The path or URL
+    pub fn key(&self) -> &str {
+        match self {
+            MediaSource::File { path } => path,
+            MediaSource::Url { url } => url,
+        }
+    }
+}
+
+/// Container format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    Matroska,
+    Mp4,
+}
+
+/// A video stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoStream {
+    pub track: u64,
+    /// Normalized codec name (`h264`, `hevc`, `av1`, `vp9`, ...)
+    pub codec: String,
+    /// Codec as stored in the container (Matroska codec ID or MP4 sample entry)
+    pub codec_id: String,
+    pub profile: Option<String>,
+    pub bit_depth: Option<u8>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+}
+
+/// An audio stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStream {
+    pub track: u64,
+    /// Normalized codec name (`aac`, `flac`, `opus`, `ac3`, ...)
+    pub codec: String,
+    pub codec_id: String,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<f64>,
+    pub language: Option<String>,
+    pub name: Option<String>,
+    pub default: bool,
+}
+
+/// A subtitle stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleStream {
+    pub track: u64,
+    /// Normalized codec name (`ass`, `srt`, `pgs`, ...)
+    pub codec: String,
+    pub codec_id: String,
+    pub language: Option<String>,
+    pub name: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+}
+
+/// A chapter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterInfo {
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+    pub title: Option<String>,
+}
+
+/// Everything the probe found out about a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub container: Container,
+    pub duration_ms: Option<u64>,
+    pub title: Option<String>,
+    pub video: Vec<VideoStream>,
+    pub audio: Vec<AudioStream>,
+    pub subtitles: Vec<SubtitleStream>,
+    pub chapters: Vec<ChapterInfo>,
+}
+
+/// Maximum bit depth the player decodes for a video codec
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoCodecSupport {
+    pub codec: String,
+    pub max_bit_depth: u8,
+}
+
+/// What the built-in player can play
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerCapabilities {
+    pub containers: Vec<Container>,
+    pub video: Vec<VideoCodecSupport>,
+    pub audio: Vec<String>,
+    /// Subtitle codecs that can be shown (text subtitles are extracted and converted)
+    pub subtitles: Vec<String>,
+}
+
+impl Default for PlayerCapabilities {
+    /// What the platform's webview usually supports; the frontend can pass its own
+    /// (e.g. from `MediaSource.isTypeSupported`) for a precise answer
+    fn default() -> Self {
+        let video = |codec: &str, max_bit_depth: u8| VideoCodecSupport {
+            codec: codec.to_string(),
+            max_bit_depth,
+        };
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        let subtitles = strings(&["ass", "ssa", "srt", "webvtt", "mov_text"]);
+
+        if cfg!(target_os = "macos") {
+            // WKWebView: no Matroska, but hardware HEVC
+            PlayerCapabilities {
+                containers: vec![Container::Mp4],
+                video: vec![video("h264", 8), video("hevc", 10)],
+                audio: strings(&["aac", "mp3", "flac", "opus", "alac", "ac3", "eac3", "pcm"]),
+                subtitles,
+            }
+        } else if cfg!(target_os = "linux") {
+            // WebKitGTK decodes through GStreamer
+            PlayerCapabilities {
+                containers: vec![Container::Mp4, Container::Matroska],
+                video: vec![video("h264", 10), video("hevc", 10), video("vp8", 8), video("vp9", 10), video("av1", 10)],
+                audio: strings(&["aac", "mp3", "flac", "opus", "vorbis", "ac3", "eac3", "pcm"]),
+                subtitles,
+            }
+        } else {
+            // WebView2 (Chromium): no HEVC without extensions, no Hi10P
+            PlayerCapabilities {
+                containers: vec![Container::Mp4, Container::Matroska],
+                video: vec![video("h264", 8), video("vp8", 8), video("vp9", 10), video("av1", 10)],
+                audio: strings(&["aac", "mp3", "flac", "opus", "vorbis", "pcm"]),
+                subtitles,
+            }
+        }
+    }
+}
+
+/// Which player to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackTarget {
+    Internal,
+    External,
+}
+
+/// Why a file can't (fully) be played in the built-in player
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaybackIssueKind {
+    Container,
+    VideoCodec,
+    BitDepth,
+    AudioCodec,
+    Subtitles,
+}
+
+/// A problem found for the built-in player
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackIssue {
+    pub kind: PlaybackIssueKind,
+    pub message: String,
+    /// Blocking issues make the file unplayable; others only affect some tracks
+    pub blocking: bool,
+    pub track: Option<u64>,
+}
+
+/// Result of `playback_recommend`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackRecommendation {
+    pub target: PlaybackTarget,
+    pub issues: Vec<PlaybackIssue>,
+    pub info: MediaInfo,
+}
+
+// =============================================================================
+// Codec Names
+// =============================================================================
+
+/// Normalize a Matroska codec ID
+fn matroska_codec(codec_id: &str) -> String {
+    let normalized = match codec_id {
+        "V_MPEG4/ISO/AVC" => "h264",
+        "V_MPEGH/ISO/HEVC" => "hevc",
+        "V_AV1" => "av1",
+        "V_VP9" => "vp9",
+        "V_VP8" => "vp8",
+        "V_MPEG2" => "mpeg2",
+        "V_MPEG4/ISO/ASP" | "V_MPEG4/ISO/SP" | "V_MPEG4/ISO/AP" => "mpeg4",
+        "A_FLAC" => "flac",
+        "A_OPUS" => "opus",
+        "A_VORBIS" => "vorbis",
+        "A_AC3" => "ac3",
+        "A_EAC3" => "eac3",
+        "A_TRUEHD" => "truehd",
+        "A_MPEG/L3" => "mp3",
+        "A_MPEG/L2" => "mp2",
+        "A_ALAC" => "alac",
+        "S_TEXT/ASS" | "S_ASS" => "ass",
+        "S_TEXT/SSA" | "S_SSA" => "ssa",
+        "S_TEXT/UTF8" | "S_TEXT/ASCII" => "srt",
+        "S_TEXT/WEBVTT" => "webvtt",
+        "S_HDMV/PGS" => "pgs",
+        "S_VOBSUB" => "vobsub",
+        id if id.starts_with("A_AAC") => "aac",
+        id if id.starts_with("A_DTS") => "dts",
+        id if id.starts_with("A_PCM") => "pcm",
+        id => return id.to_lowercase(),
+    };
+    normalized.to_string()
+}
+
+/// Normalize an MP4 sample entry type
+fn mp4_codec(entry: &str) -> String {
+    let normalized = match entry {
+        "avc1" | "avc3" => "h264",
+        "hvc1" | "hev1" => "hevc",
+        "av01" => "av1",
+        "vp09" => "vp9",
+        "vp08" => "vp8",
+        "mp4v" => "mpeg4",
+        "mp4a" => "aac",
+        "ac-3" => "ac3",
+        "ec-3" => "eac3",
+        "fLaC" => "flac",
+        "Opus" => "opus",
+        "alac" => "alac",
+        "dtsc" | "dtsh" | "dtsl" | "dtse" => "dts",
+        "mlpa" => "truehd",
+        ".mp3" => "mp3",
+        "lpcm" | "sowt" | "twos" | "ipcm" => "pcm",
+        "tx3g" => "mov_text",
+        "wvtt" => "webvtt",
+        "stpp" => "ttml",
+        entry => return entry.trim().to_lowercase(),
+    };
+    normalized.to_string()
+}
+
+/// Human readable codec name for messages
+fn codec_label(codec: &str) -> String {
+    match codec {
+        "h264" => "H.264".to_string(),
+        "hevc" => "HEVC".to_string(),
+        "eac3" => "E-AC-3".to_string(),
+        "ac3" => "AC-3".to_string(),
+        "truehd" => "TrueHD".to_string(),
+        other => other.to_uppercase(),
+    }
+}
+
+// =============================================================================
+// Codec Configuration
+// =============================================================================
+
+/// Profile and bit depth from a codec configuration record
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CodecDetails {
+    profile: Option<String>,
+    bit_depth: Option<u8>,
+}
+
+/// Parse an `avcC` record (AVCDecoderConfigurationRecord)
+fn parse_avc_config(data: &[u8]) -> CodecDetails {
+    let profile_idc = match data.get(1) {
+        Some(profile) => *profile,
+        None => return CodecDetails::default(),
+    };
+    let profile = match profile_idc {
+        66 => "Baseline",
+        77 => "Main",
+        88 => "Extended",
+        100 => "High",
+        110 => "High 10",
+        122 => "High 4:2:2",
+        244 => "High 4:4:4 Predictive",
+        _ => "",
+    };
+
+    // High profiles append chroma format and bit depth after the parameter sets
+    let mut bit_depth = None;
+    if matches!(profile_idc, 100 | 110 | 122 | 144 | 244) {
+        let mut pos = 6;
+        let sps_count = data.get(5).map_or(0, |b| b & 0x1F);
+        let mut valid = true;
+        for _ in 0..sps_count {
+            match data.get(pos..pos + 2) {
+                Some(len) => pos += 2 + u16::from_be_bytes([len[0], len[1]]) as usize,
+                None => valid = false,
+            }
+        }
+        let pps_count = data.get(pos).copied();
+        pos += 1;
+        for _ in 0..pps_count.unwrap_or(0) {
+            match data.get(pos..pos + 2) {
+                Some(len) => pos += 2 + u16::from_be_bytes([len[0], len[1]]) as usize,
+                None => valid = false,
+            }
+        }
+        if valid && pps_count.is_some() {
+            bit_depth = data.get(pos + 1).map(|b| (b & 0x07) + 8);
+        }
+    }
+    if bit_depth.is_none() {
+        bit_depth = match profile_idc {
+            66 | 77 | 88 | 100 => Some(8),
+            110 => Some(10),
+            _ => None,
+        };
+    }
+
+    CodecDetails {
+        profile: (!profile.is_empty()).then(|| profile.to_string()),
+        bit_depth,
+    }
+}
+
+/// Parse an `hvcC` record (HEVCDecoderConfigurationRecord)
+fn parse_hevc_config(data: &[u8]) -> CodecDetails {
+    let profile_idc = match data.get(1) {
+        Some(b) => b & 0x1F,
+        None => return CodecDetails::default(),
+    };
+    let profile = match profile_idc {
+        1 => Some("Main"),
+        2 => Some("Main 10"),
+        3 => Some("Main Still Picture"),
+        4 => Some("Range Extensions"),
+        _ => None,
+    };
+    let bit_depth = data.get(17).map(|b| (b & 0x07) + 8).or(match profile_idc {
+        1 | 3 => Some(8),
+        2 => Some(10),
+        _ => None,
+    });
+    CodecDetails {
+        profile: profile.map(str::to_string),
+        bit_depth,
+    }
+}
+
+/// Parse an `av1C` record (AV1CodecConfigurationRecord)
+fn parse_av1_config(data: &[u8]) -> CodecDetails {
+    let (profile, flags) = match (data.get(1), data.get(2)) {
+        (Some(profile), Some(flags)) => (profile >> 5, *flags),
+        _ => return CodecDetails::default(),
+    };
+    let high_bitdepth = flags & 0x40 != 0;
+    let twelve_bit = flags & 0x20 != 0;
+    CodecDetails {
+        profile: match profile {
+            0 => Some("Main".to_string()),
+            1 => Some("High".to_string()),
+            2 => Some("Professional".to_string()),
+            _ => None,
+        },
+        bit_depth: Some(match (high_bitdepth, twelve_bit) {
+            (true, true) => 12,
+            (true, false) => 10,
+            _ => 8,
+        }),
+    }
+}
+
+/// Parse an MP4 `vpcC` box body (version and flags already skipped)
+fn parse_vpcc(data: &[u8]) -> CodecDetails {
+    match (data.first(), data.get(2)) {
+        (Some(profile), Some(depth)) => CodecDetails {
+            profile: Some(format!("Profile {}", profile)),
+            bit_depth: Some(depth >> 4),
+        },
+        _ => CodecDetails::default(),
+    }
+}
+
+/// Parse the Matroska VP9 codec private feature list
+fn parse_vp9_private(data: &[u8]) -> CodecDetails {
+    let mut details = CodecDetails::default();
+    let mut pos = 0;
+    while let (Some(&id), Some(&len)) = (data.get(pos), data.get(pos + 1)) {
+        let value = data.get(pos + 2).copied();
+        match id {
+            1 => details.profile = value.map(|p| format!("Profile {}", p)),
+            3 => details.bit_depth = value,
+            _ => {}
+        }
+        pos += 2 + len as usize;
+    }
+    details
+}
+
+/// Profile and bit depth of a video codec from its configuration record
+fn codec_details(codec: &str, config: Option<&[u8]>) -> CodecDetails {
+    match (codec, config) {
+        ("h264", Some(config)) => parse_avc_config(config),
+        ("hevc", Some(config)) => parse_hevc_config(config),
+        ("av1", Some(config)) => parse_av1_config(config),
+        ("vp9", Some(config)) => parse_vp9_private(config),
+        ("vp8", _) => CodecDetails { profile: None, bit_depth: Some(8) },
+        _ => CodecDetails::default(),
+    }
+}
+
+// =============================================================================
+// Matroska
+// =============================================================================
+
+/// Collects Matroska header events into [`MediaInfo`]
+#[derive(Default)]
+struct MatroskaProbe {
+    duration_ms: Option<u64>,
+    title: Option<String>,
+    tracks: Vec<TrackInfo>,
+    chapters: Option<Vec<ChapterInfo>>,
+    chapters_offset: Option<u64>,
+    reached_cluster: bool,
+}
+
+impl MatroskaProbe {
+    fn apply(&mut self, events: Vec<MatroskaEvent>) {
+        for event in events {
+            match event {
+                MatroskaEvent::Info { duration_ms, title } => {
+                    self.duration_ms = duration_ms.map(|d| d.round() as u64);
+                    self.title = title;
+                }
+                MatroskaEvent::Tracks(tracks) => self.tracks = tracks,
+                MatroskaEvent::Chapters(chapters) => {
+                    self.chapters = Some(
+                        chapters
+                            .into_iter()
+                            .filter(|c| !c.hidden)
+                            .map(|c| ChapterInfo { start_ms: c.start_ms, end_ms: c.end_ms, title: c.title })
+                            .collect(),
+                    );
+                }
+                MatroskaEvent::SeekHead(entries) => {
+                    if let Some(entry) = entries.iter().find(|e| e.id == matroska::ids::CHAPTERS) {
+                        self.chapters_offset = Some(entry.offset);
+                    }
+                }
+                MatroskaEvent::Cluster(_) => self.reached_cluster = true,
+                _ => {}
+            }
+        }
+    }
+
+    fn finish(self) -> MediaInfo {
+        let mut info = MediaInfo {
+            container: Container::Matroska,
+            duration_ms: self.duration_ms,
+            title: self.title,
+            video: Vec::new(),
+            audio: Vec::new(),
+            subtitles: Vec::new(),
+            chapters: self.chapters.unwrap_or_default(),
+        };
+
+        for track in self.tracks {
+            let codec = matroska_codec(&track.codec_id);
+            match track.kind {
+                TrackKind::Video => {
+                    let details = codec_details(&codec, track.codec_private.as_deref());
+                    info.video.push(VideoStream {
+                        track: track.number,
+                        codec,
+                        codec_id: track.codec_id,
+                        profile: details.profile,
+                        bit_depth: details.bit_depth,
+                        width: track.width,
+                        height: track.height,
+                        frame_rate: track
+                            .default_duration_ns
+                            .filter(|d| *d > 0)
+                            .map(|d| 1_000_000_000.0 / d as f64),
+                    });
+                }
+                TrackKind::Audio => info.audio.push(AudioStream {
+                    track: track.number,
+                    codec,
+                    codec_id: track.codec_id,
+                    channels: track.channels,
+                    sample_rate: track.sample_rate,
+                    language: track.language,
+                    name: track.name,
+                    default: track.default,
+                }),
+                TrackKind::Subtitle => info.subtitles.push(SubtitleStream {
+                    track: track.number,
+                    codec,
+                    codec_id: track.codec_id,
+                    language: track.language,
+                    name: track.name,
+                    default: track.default,
+                    forced: track.forced,
+                }),
+                TrackKind::Other => {}
+            }
+        }
+        info
+    }
+}
+
+// =============================================================================
+// MP4
+// =============================================================================
+
+/// Iterate over the boxes in `data` as (type, body)
+fn mp4_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as u64;
+        let kind = [data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]];
+        let (size, header) = match size {
+            0 => ((data.len() - pos) as u64, 8),
+            1 => match data.get(pos + 8..pos + 16) {
+                Some(large) => (u64::from_be_bytes(large.try_into().unwrap_or_default()), 16),
+                None => break,
+            },
+            size => (size, 8),
+        };
+        if size < header as u64 || pos as u64 + size > data.len() as u64 {
+            break;
+        }
+        boxes.push((kind, &data[pos + header..pos + size as usize]));
+        pos += size as usize;
+    }
+    boxes
+}
+
+/// Find the first child box of a type
+fn mp4_child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    mp4_boxes(data).into_iter().find(|(k, _)| k == kind).map(|(_, body)| body)
+}
+
+fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    data.get(pos..pos + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Option<u32> {
+    data.get(pos..pos + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn be_u64(data: &[u8], pos: usize) -> Option<u64> {
+    data.get(pos..pos + 8).map(|b| u64::from_be_bytes(b.try_into().unwrap_or_default()))
+}
+
+/// Read (timescale, duration) of an `mvhd`/`mdhd` body
+fn mp4_header_times(data: &[u8]) -> Option<(u32, u64)> {
+    if data.first() == Some(&1) {
+        Some((be_u32(data, 20)?, be_u64(data, 24)?))
+    } else {
+        Some((be_u32(data, 12)?, be_u32(data, 16)? as u64))
+    }
+}
+
+/// Language of an `mdhd` body (packed ISO 639-2)
+fn mp4_language(mdhd: &[u8]) -> Option<String> {
+    let pos = if mdhd.first() == Some(&1) { 32 } else { 20 };
+    let packed = be_u16(mdhd, pos)?;
+    let language: String = [10, 5, 0]
+        .iter()
+        .map(|shift| (((packed >> shift) & 0x1F) as u8 + 0x60) as char)
+        .collect();
+    (language.chars().all(|c| c.is_ascii_lowercase()) && language != "und").then_some(language)
+}
+
+/// Object type of an `esds` body (tells AAC from MP3 in `mp4a` entries)
+fn mp4_esds_object_type(esds: &[u8]) -> Option<u8> {
+    // Descriptor length: up to four bytes of 7 bits each
+    fn descriptor(data: &[u8], pos: usize) -> Option<(u8, usize)> {
+        let tag = *data.get(pos)?;
+        let mut pos = pos + 1;
+        for _ in 0..4 {
+            let b = *data.get(pos)?;
+            pos += 1;
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+        Some((tag, pos))
+    }
+
+    let (tag, mut pos) = descriptor(esds, 4)?;
+    if tag != 0x03 {
+        return None;
+    }
+    let flags = *esds.get(pos + 2)?;
+    pos += 3;
+    if flags & 0x80 != 0 {
+        pos += 2;
+    }
+    if flags & 0x40 != 0 {
+        pos += 1 + *esds.get(pos)? as usize;
+    }
+    if flags & 0x20 != 0 {
+        pos += 2;
+    }
+    let (tag, pos) = descriptor(esds, pos)?;
+    (tag == 0x04).then(|| esds.get(pos).copied()).flatten()
+}
+
+/// Parse a Nero `chpl` chapter list (times in 100 ns units)
+fn mp4_chapters(chpl: &[u8]) -> Vec<ChapterInfo> {
+    let mut pos = if chpl.first() == Some(&1) { 8 } else { 4 };
+    let count = chpl.get(pos).copied().unwrap_or(0);
+    pos += 1;
+
+    let mut chapters: Vec<ChapterInfo> = Vec::new();
+    for _ in 0..count {
+        let (start, len) = match (be_u64(chpl, pos), chpl.get(pos + 8)) {
+            (Some(start), Some(len)) => (start, *len as usize),
+            _ => break,
+        };
+        let title = chpl
+            .get(pos + 9..pos + 9 + len)
+            .map(|t| String::from_utf8_lossy(t).to_string())
+            .filter(|t| !t.is_empty());
+        pos += 9 + len;
+        chapters.push(ChapterInfo { start_ms: start / 10_000, end_ms: None, title });
+    }
+    // Each chapter ends where the next one starts
+    for i in 1..chapters.len() {
+        chapters[i - 1].end_ms = Some(chapters[i].start_ms);
+    }
+    chapters
+}
+
+/// Parse a `moov` box body
+fn parse_mp4_moov(moov: &[u8]) -> MediaInfo {
+    let mut info = MediaInfo {
+        container: Container::Mp4,
+        duration_ms: mp4_child(moov, b"mvhd")
+            .and_then(mp4_header_times)
+            .filter(|(scale, _)| *scale > 0)
+            .map(|(scale, duration)| duration * 1000 / scale as u64),
+        title: None,
+        video: Vec::new(),
+        audio: Vec::new(),
+        subtitles: Vec::new(),
+        chapters: mp4_child(moov, b"udta")
+            .and_then(|udta| mp4_child(udta, b"chpl"))
+            .map(mp4_chapters)
+            .unwrap_or_default(),
+    };
+
+    for (kind, trak) in mp4_boxes(moov) {
+        if &kind != b"trak" {
+            continue;
+        }
+        let track = mp4_child(trak, b"tkhd")
+            .and_then(|tkhd| be_u32(tkhd, if tkhd.first() == Some(&1) { 20 } else { 12 }))
+            .unwrap_or(0) as u64;
+        let mdia = match mp4_child(trak, b"mdia") {
+            Some(mdia) => mdia,
+            None => continue,
+        };
+        let mdhd = mp4_child(mdia, b"mdhd");
+        let language = mdhd.and_then(mp4_language);
+        let handler = mp4_child(mdia, b"hdlr").and_then(|h| h.get(8..12)).unwrap_or_default();
+        let stbl = match mp4_child(mdia, b"minf").and_then(|m| mp4_child(m, b"stbl")) {
+            Some(stbl) => stbl,
+            None => continue,
+        };
+        // stsd: version/flags, entry count, then sample entries
+        let (entry_type, entry) = match mp4_child(stbl, b"stsd")
+            .and_then(|stsd| stsd.get(8..))
+            .and_then(|entries| mp4_boxes(entries).into_iter().next())
+        {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let entry_name = String::from_utf8_lossy(&entry_type).to_string();
+        let mut codec = mp4_codec(&entry_name);
+
+        match handler {
+            b"vide" => {
+                let children = entry.get(78..).unwrap_or_default();
+                let details = match codec.as_str() {
+                    "h264" => mp4_child(children, b"avcC").map(parse_avc_config),
+                    "hevc" => mp4_child(children, b"hvcC").map(parse_hevc_config),
+                    "av1" => mp4_child(children, b"av1C").map(parse_av1_config),
+                    "vp9" => mp4_child(children, b"vpcC").and_then(|v| v.get(4..)).map(parse_vpcc),
+                    _ => None,
+                }
+                .unwrap_or_else(|| codec_details(&codec, None));
+                // First stts entry: (sample count, sample delta)
+                let frame_rate = match (
+                    mdhd.and_then(mp4_header_times),
+                    mp4_child(stbl, b"stts").and_then(|stts| be_u32(stts, 12)),
+                ) {
+                    (Some((scale, _)), Some(delta)) if delta > 0 => Some(scale as f64 / delta as f64),
+                    _ => None,
+                };
+                info.video.push(VideoStream {
+                    track,
+                    codec,
+                    codec_id: entry_name,
+                    profile: details.profile,
+                    bit_depth: details.bit_depth,
+                    width: be_u16(entry, 24).map(u32::from),
+                    height: be_u16(entry, 26).map(u32::from),
+                    frame_rate,
+                });
+            }
+            b"soun" => {
+                if codec == "aac" {
+                    let object_type = entry
+                        .get(28..)
+                        .and_then(|children| mp4_child(children, b"esds"))
+                        .and_then(mp4_esds_object_type);
+                    if matches!(object_type, Some(0x69) | Some(0x6B)) {
+                        codec = "mp3".to_string();
+                    }
+                }
+                info.audio.push(AudioStream {
+                    track,
+                    codec,
+                    codec_id: entry_name,
+                    channels: be_u16(entry, 16).map(u32::from),
+                    sample_rate: be_u32(entry, 24).map(|rate| (rate >> 16) as f64),
+                    language,
+                    name: None,
+                    default: info.audio.is_empty(),
+                });
+            }
+            b"subt" | b"text" | b"sbtl" => info.subtitles.push(SubtitleStream {
+                track,
+                codec,
+                codec_id: entry_name,
+                language,
+                name: None,
+                default: false,
+                forced: false,
+            }),
+            _ => {}
+        }
+    }
+    info
+}
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+/// Reads byte ranges from a file or a stream
+struct ProbeReader {
+    source: MediaSource,
+    client: Option<reqwest::Client>,
+}
+
+impl ProbeReader {
+    fn new(source: MediaSource) -> Result<Self, String> {
+        let client = match source {
+            MediaSource::Url { .. } => Some(
+                reqwest::Client::builder()
+                    .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+                    .timeout(Duration::from_secs(60))
+                    .build()
+                    .map_err(|e| format!("Failed to create HTTP client: {}", e))?,
+            ),
+            MediaSource::File { .. } => None,
+        };
+        Ok(ProbeReader { source, client })
+    }
+
+    /// Read up to `len` bytes at `offset` (fewer at the end of the file)
+    async fn read_at(&self, offset: u64, len: u64) -> Result<Vec<u8>, String> {
+        match (&self.source, &self.client) {
+            (MediaSource::Url { url }, Some(client)) => {
+                let mut response = client
+                    .get(url)
+                    .header("Range", format!("bytes={}-{}", offset, offset + len - 1))
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to read stream: {}", e))?;
+
+                match response.status().as_u16() {
+                    206 => {}
+                    416 => return Ok(Vec::new()),
+                    200 if offset == 0 => {}
+                    200 => return Err("The stream doesn't support range requests".to_string()),
+                    status => return Err(format!("Stream request failed with status {}", status)),
+                }
+
+                // Servers that ignore the range send everything; stop after `len`
+                let mut data = Vec::new();
+                while let Some(chunk) = response
+                    .chunk()
+                    .await
+                    .map_err(|e| format!("Failed to read stream: {}", e))?
+                {
+                    data.extend_from_slice(&chunk);
+                    if data.len() as u64 >= len {
+                        data.truncate(len as usize);
+                        break;
+                    }
+                }
+                Ok(data)
+            }
+            (source, _) => {
+                let path = source.key().to_string();
+                tauri::async_runtime::spawn_blocking(move || {
+                    let mut file = std::fs::File::open(&path)
+                        .map_err(|e| format!("Failed to open '{}': {}", path, e))?;
+                    file.seek(SeekFrom::Start(offset))
+                        .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+                    let mut data = Vec::new();
+                    file.take(len)
+                        .read_to_end(&mut data)
+                        .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+                    Ok(data)
+                })
+                .await
+                .map_err(|e| format!("Failed to read file: {}", e))?
+            }
+        }
+    }
+}
+
+/// Probe a Matroska file: read up to the first cluster, then the chapters if the
+/// seek head points past it
+async fn probe_matroska(reader: &ProbeReader) -> Result<MediaInfo, String> {
+    let mut parser = MatroskaParser::new();
+    let mut probe = MatroskaProbe::default();
+    let mut offset = 0;
+
+    while !probe.reached_cluster && offset < MAX_MATROSKA_HEADER_BYTES {
+        let chunk = reader.read_at(offset, PROBE_CHUNK_SIZE).await?;
+        if chunk.is_empty() {
+            break;
+        }
+        offset += chunk.len() as u64;
+        probe.apply(parser.push(&chunk).map_err(|e| e.to_string())?);
+    }
+
+    if probe.chapters.is_none() {
+        if let Some(chapters_offset) = probe.chapters_offset.filter(|o| *o >= offset) {
+            parser.seek(chapters_offset);
+            let chunk = reader.read_at(chapters_offset, PROBE_CHUNK_SIZE).await?;
+            // Chapters are small; ignore errors from whatever follows them
+            if let Ok(events) = parser.push(&chunk) {
+                probe.apply(events);
+            }
+        }
+    }
+
+    if probe.tracks.is_empty() {
+        return Err("No tracks found in the file header".to_string());
+    }
+    Ok(probe.finish())
+}
+
+/// Probe an MP4 file: walk the top-level boxes until `moov`
+async fn probe_mp4(reader: &ProbeReader) -> Result<MediaInfo, String> {
+    let mut offset = 0;
+    for _ in 0..MAX_TOP_LEVEL_BOXES {
+        let header = reader.read_at(offset, 16).await?;
+        let size = match be_u32(&header, 0) {
+            Some(size) => size as u64,
+            None => break,
+        };
+        let (size, header_len) = match size {
+            1 => (be_u64(&header, 8).ok_or("Truncated MP4 box header")?, 16),
+            size => (size, 8),
+        };
+
+        if header.get(4..8) == Some(b"moov") {
+            if size > MAX_MOOV_SIZE {
+                return Err("MP4 moov box is too large".to_string());
+            }
+            let moov = reader.read_at(offset + header_len, size.saturating_sub(header_len)).await?;
+            return Ok(parse_mp4_moov(&moov));
+        }
+        // Size 0 means the box extends to the end of the file
+        if size < header_len {
+            break;
+        }
+        offset += size;
+    }
+    Err("No moov box found in the MP4 file".to_string())
+}
+
+/// Probe a file or stream
+pub async fn probe(source: MediaSource) -> Result<MediaInfo, String> {
+    let reader = ProbeReader::new(source)?;
+    let magic = reader.read_at(0, 12).await?;
+
+    if magic.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        probe_matroska(&reader).await
+    } else if matches!(magic.get(4..8), Some(b"ftyp") | Some(b"moov") | Some(b"free") | Some(b"mdat")) {
+        probe_mp4(&reader).await
+    } else {
+        Err("Unsupported container (expected MP4 or MKV)".to_string())
+    }
+}
+
+// =============================================================================
+// Recommendation
+// =============================================================================
+
+/// Decide which player to use for a probed file
+pub fn recommend(info: MediaInfo, capabilities: &PlayerCapabilities) -> PlaybackRecommendation {
+    let mut issues = Vec::new();
+
+    if !capabilities.containers.contains(&info.container) {
+        issues.push(PlaybackIssue {
+            kind: PlaybackIssueKind::Container,
+            message: format!("{:?} files aren't supported by the built-in player", info.container),
+            blocking: true,
+            track: None,
+        });
+    }
+
+    if let Some(video) = info.video.first() {
+        match capabilities.video.iter().find(|c| c.codec == video.codec) {
+            None => issues.push(PlaybackIssue {
+                kind: PlaybackIssueKind::VideoCodec,
+                message: format!("{} video isn't supported by the built-in player", codec_label(&video.codec)),
+                blocking: true,
+                track: Some(video.track),
+            }),
+            Some(support) if video.bit_depth.unwrap_or(8) > support.max_bit_depth => {
+                issues.push(PlaybackIssue {
+                    kind: PlaybackIssueKind::BitDepth,
+                    message: format!(
+                        "{}-bit {} video isn't supported by the built-in player",
+                        video.bit_depth.unwrap_or(8),
+                        codec_label(&video.codec)
+                    ),
+                    blocking: true,
+                    track: Some(video.track),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    let unsupported_audio: Vec<&AudioStream> = info
+        .audio
+        .iter()
+        .filter(|a| !capabilities.audio.contains(&a.codec))
+        .collect();
+    let all_audio_unsupported = !info.audio.is_empty() && unsupported_audio.len() == info.audio.len();
+    for audio in unsupported_audio {
+        let language = audio.language.as_deref().map(|l| format!(" ({})", l)).unwrap_or_default();
+        issues.push(PlaybackIssue {
+            kind: PlaybackIssueKind::AudioCodec,
+            message: format!(
+                "{} audio{} isn't supported by the built-in player",
+                codec_label(&audio.codec),
+                language
+            ),
+            blocking: all_audio_unsupported,
+            track: Some(audio.track),
+        });
+    }
+
+    for subtitle in info.subtitles.iter().filter(|s| !capabilities.subtitles.contains(&s.codec)) {
+        issues.push(PlaybackIssue {
+            kind: PlaybackIssueKind::Subtitles,
+            message: format!("{} subtitles can't be shown by the built-in player", codec_label(&subtitle.codec)),
+            blocking: false,
+            track: Some(subtitle.track),
+        });
+    }
+
+    let target = if issues.iter().any(|i| i.blocking) {
+        PlaybackTarget::External
+    } else {
+        PlaybackTarget::Internal
+    };
+    PlaybackRecommendation { target, issues, info }
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Read the container and codec information of a path or URL
+#[tauri::command]
+pub async fn media_probe(source: String) -> Result<MediaInfo, String> {
+    probe(MediaSource::parse(&source)?).await
+}
+
+/// Get the capabilities assumed for the built-in player on this platform
+#[tauri::command]
+pub fn playback_get_capabilities() -> PlayerCapabilities {
+    PlayerCapabilities::default()
+}
+
+/// Probe a path or URL and decide between the built-in and the external player.
+/// `capabilities` overrides the platform defaults (e.g. measured in the webview).
+#[tauri::command]
+pub async fn playback_recommend(
+    source: String,
+    capabilities: Option<PlayerCapabilities>,
+) -> Result<PlaybackRecommendation, String> {
+    let info = probe(MediaSource::parse(&source)?).await?;
+    let recommendation = recommend(info, &capabilities.unwrap_or_default());
+    log::info!(
+        "Playback recommendation for '{}': {:?} ({} issues)",
+        source,
+        recommendation.target,
+        recommendation.issues.len()
+    );
+    Ok(recommendation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::test_support::sample_file;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn capabilities() -> PlayerCapabilities {
+        PlayerCapabilities {
+            containers: vec![Container::Mp4, Container::Matroska],
+            video: vec![VideoCodecSupport { codec: "h264".to_string(), max_bit_depth: 8 }],
+            audio: vec!["aac".to_string(), "opus".to_string()],
+            subtitles: vec!["ass".to_string()],
+        }
+    }
+
+    fn audio(track: u64, codec: &str) -> AudioStream {
+        AudioStream {
+            track,
+            codec: codec.to_string(),
+            codec_id: codec.to_string(),
+            channels: Some(2),
+            sample_rate: Some(48000.0),
+            language: Some("jpn".to_string()),
+            name: None,
+            default: track == 2,
+        }
+    }
+
+    #[test]
+    fn test_codec_configs() {
+        // hvcC: Main 10 profile, bitDepthLumaMinus8 = 2
+        let mut hvcc = vec![0u8; 23];
+        hvcc[1] = 0x02;
+        hvcc[17] = 0xF8 | 2;
+        assert_eq!(
+            parse_hevc_config(&hvcc),
+            CodecDetails { profile: Some("Main 10".to_string()), bit_depth: Some(10) }
+        );
+
+        // avcC: High profile, one SPS and PPS, then chroma format and bit depth 10
+        let avcc = [1, 100, 0, 40, 0xFF, 0xE1, 0, 2, 0x67, 0x64, 1, 0, 1, 0x68, 0xFD, 0xFA, 0xF8];
+        assert_eq!(parse_avc_config(&avcc).bit_depth, Some(10));
+        assert_eq!(parse_avc_config(&[1, 77, 0, 30]).bit_depth, Some(8));
+
+        // av1C: Main profile, high bit depth
+        assert_eq!(parse_av1_config(&[0x81, 0x08, 0x4C, 0]).bit_depth, Some(10));
+        assert_eq!(parse_vp9_private(&[1, 1, 2, 3, 1, 10]).bit_depth, Some(10));
+    }
+
+    #[test]
+    fn test_probe_matroska_sample() {
+        let mut parser = MatroskaParser::new();
+        let mut probe = MatroskaProbe::default();
+        probe.apply(parser.push(&sample_file()).unwrap());
+        let info = probe.finish();
+
+        assert_eq!(info.container, Container::Matroska);
+        assert_eq!(info.video.len(), 1);
+        assert_eq!(info.video[0].codec, "hevc");
+        assert_eq!(info.video[0].width, Some(1920));
+        assert_eq!(info.subtitles[0].codec, "ass");
+        assert_eq!(info.subtitles[0].language.as_deref(), Some("eng"));
+        assert_eq!(
+            info.chapters,
+            vec![ChapterInfo { start_ms: 90_000, end_ms: Some(180_000), title: Some("Opening".to_string()) }]
+        );
+    }
+
+    #[test]
+    fn test_parse_mp4_moov() {
+        // mvhd v0: timescale 1000, duration 1_440_000 (24 minutes)
+        let mut mvhd = vec![0u8; 100];
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&1_440_000u32.to_be_bytes());
+
+        let mut tkhd = vec![0u8; 84];
+        tkhd[12..16].copy_from_slice(&1u32.to_be_bytes());
+        let mut mdhd = vec![0u8; 24];
+        mdhd[12..16].copy_from_slice(&24000u32.to_be_bytes());
+        let mut hdlr = vec![0u8; 24];
+        hdlr[8..12].copy_from_slice(b"vide");
+
+        let mut hvcc = vec![0u8; 23];
+        hvcc[1] = 0x02;
+        hvcc[17] = 0xFA;
+        let mut entry = vec![0u8; 78];
+        entry[24..26].copy_from_slice(&1920u16.to_be_bytes());
+        entry[26..28].copy_from_slice(&1080u16.to_be_bytes());
+        entry.extend(mp4_box(b"hvcC", &hvcc));
+        let stsd = [vec![0, 0, 0, 0, 0, 0, 0, 1], mp4_box(b"hev1", &entry)].concat();
+        let stts = [vec![0u8; 4], 1u32.to_be_bytes().to_vec(), 100u32.to_be_bytes().to_vec(), 1001u32.to_be_bytes().to_vec()]
+            .concat();
+        let stbl = [mp4_box(b"stsd", &stsd), mp4_box(b"stts", &stts)].concat();
+        let mdia = [
+            mp4_box(b"mdhd", &mdhd),
+            mp4_box(b"hdlr", &hdlr),
+            mp4_box(b"minf", &mp4_box(b"stbl", &stbl)),
+        ]
+        .concat();
+        let trak = [mp4_box(b"tkhd", &tkhd), mp4_box(b"mdia", &mdia)].concat();
+
+        let chpl = [vec![1, 0, 0, 0, 0, 0, 0, 0, 1], 900_000_000u64.to_be_bytes().to_vec(), vec![2], b"OP".to_vec()].concat();
+        let moov = [mp4_box(b"mvhd", &mvhd), mp4_box(b"trak", &trak), mp4_box(b"udta", &mp4_box(b"chpl", &chpl))].concat();
+
+        let info = parse_mp4_moov(&moov);
+        assert_eq!(info.duration_ms, Some(1_440_000));
+        assert_eq!(info.video[0].codec, "hevc");
+        assert_eq!(info.video[0].bit_depth, Some(10));
+        assert_eq!(info.video[0].height, Some(1080));
+        assert!((info.video[0].frame_rate.unwrap() - 23.976).abs() < 0.001);
+        assert_eq!(info.chapters[0].start_ms, 90_000);
+        assert_eq!(info.chapters[0].title.as_deref(), Some("OP"));
+    }
+
+    #[test]
+    fn test_recommend() {
+        let mut info = MediaInfo {
+            container: Container::Matroska,
+            duration_ms: None,
+            title: None,
+            video: vec![VideoStream {
+                track: 1,
+                codec: "h264".to_string(),
+                codec_id: "V_MPEG4/ISO/AVC".to_string(),
+                profile: Some("High".to_string()),
+                bit_depth: Some(8),
+                width: None,
+                height: None,
+                frame_rate: None,
+            }],
+            audio: vec![audio(2, "aac"), audio(3, "flac")],
+            subtitles: Vec::new(),
+            chapters: Vec::new(),
+        };
+
+        // An unsupported extra audio track is only a warning
+        let result = recommend(info.clone(), &capabilities());
+        assert_eq!(result.target, PlaybackTarget::Internal);
+        assert_eq!(result.issues.len(), 1);
+        assert!(!result.issues[0].blocking);
+
+        // FLAC only: nothing to play the audio with
+        info.audio = vec![audio(2, "flac")];
+        let result = recommend(info.clone(), &capabilities());
+        assert_eq!(result.target, PlaybackTarget::External);
+
+        // 10-bit H.264
+        info.audio = vec![audio(2, "aac")];
+        info.video[0].bit_depth = Some(10);
+        let result = recommend(info, &capabilities());
+        assert_eq!(result.target, PlaybackTarget::External);
+        assert_eq!(result.issues[0].kind, PlaybackIssueKind::BitDepth);
+        assert_eq!(result.issues[0].message, "10-bit H.264 video isn't supported by the built-in player");
+    }
+
+    #[test]
+    fn test_media_source_parse() {
+        assert_eq!(
+            MediaSource::parse(" https://example.com/a.mkv ").unwrap(),
+            MediaSource::Url { url: "https://example.com/a.mkv".to_string() }
+        );
+        assert_eq!(MediaSource::parse("/tmp/a.mp4").unwrap().key(), "/tmp/a.mp4");
+        assert!(MediaSource::parse("").is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! Local Media Server
+//!
+//! This module runs a small HTTP server on `127.0.0.1` for media services implemented
+//! in Rust: subtitle extraction (`/subtitles`) and the stream proxy (`/stream`). It
+//! listens on the port right after the backend port (or a random port when that one
+//! is taken) and shares the bound port through the backend runtime file; the torrent
+//! backend forwards the matching routes to it, so the player can load everything from
+//! the backend port like any other stream resource. Only the app's own origins may
+//! read responses cross-origin.
+
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpListener;
+
+use crate::{backend_runtime, downloads, stream_proxy, subtitles};
+
+/// The media server listens on `backend_port + MEDIA_SERVER_PORT_OFFSET`
+pub const MEDIA_SERVER_PORT_OFFSET: u16 = 1;
+
+/// Origins the app's webview is served from (production and `npm run dev`)
+pub const APP_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+    "http://localhost:5173",
+];
+
+/// Response body of all handlers (buffered or streamed)
+pub type HttpBody = BoxBody<Bytes, Infallible>;
+
+/// Response type of all handlers
+pub type HttpResponse = Response<HttpBody>;
+
+/// Media server state: the port it is listening on
+#[derive(Default)]
+pub struct MediaServerState {
+    port: Mutex<Option<u16>>,
+}
+
+// =============================================================================
+// Responses
+// =============================================================================
+
+/// Build a response with a body
+pub fn bytes_response(status: u16, content_type: &str, body: Vec<u8>) -> HttpResponse {
+    let mut response = Response::new(Full::new(Bytes::from(body)).boxed());
+    *response.status_mut() = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    if let Ok(value) = content_type.parse() {
+        response.headers_mut().insert(hyper::header::CONTENT_TYPE, value);
+    }
+    response
+}
+
+/// Build a plain-text response
+pub fn text_response(status: u16, text: &str) -> HttpResponse {
+    bytes_response(status, "text/plain; charset=utf-8", text.as_bytes().to_vec())
+}
+
+/// Build a JSON response
+pub fn json_response<T: Serialize>(value: &T) -> HttpResponse {
+    match serde_json::to_vec(value) {
+        Ok(body) => bytes_response(200, "application/json", body),
+        Err(e) => text_response(500, &format!("Failed to serialize response: {}", e)),
+    }
+}
+
+/// Allow the app's webview, and no other website, to read responses
+fn with_cors(mut response: HttpResponse, origin: Option<&str>) -> HttpResponse {
+    let headers = response.headers_mut();
+    headers.insert(hyper::header::VARY, hyper::header::HeaderValue::from_static("origin"));
+    let Some(origin) = origin.filter(|o| APP_ORIGINS.contains(o)) else {
+        return response;
+    };
+    if let Ok(value) = origin.parse() {
+        headers.insert("access-control-allow-origin", value);
+    }
+    headers.insert(
+        "access-control-allow-methods",
+        hyper::header::HeaderValue::from_static("GET, HEAD, OPTIONS"),
+    );
+    headers.insert("access-control-allow-headers", hyper::header::HeaderValue::from_static("*"));
+    headers.insert(
+        "access-control-expose-headers",
+        hyper::header::HeaderValue::from_static("*"),
+    );
+    response
+}
+
+// =============================================================================
+// Query Parsing
+// =============================================================================
+
+/// Decode `%XX` escapes (and `+` as space) of a URL component
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = |b: Option<&u8>| b.and_then(|b| (*b as char).to_digit(16));
+                match (hex(bytes.get(i + 1)), hex(bytes.get(i + 2))) {
+                    (Some(high), Some(low)) => {
+                        out.push((high * 16 + low) as u8);
+                        i += 3;
+                    }
+                    _ => {
+                        out.push(b'%');
+                        i += 1;
+                    }
+                }
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Parse a query string into decoded key/value pairs
+pub fn parse_query(query: Option<&str>) -> HashMap<String, String> {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (percent_decode(key), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+// =============================================================================
+// Server
+// =============================================================================
+
+/// Route a request
+async fn handle(app: AppHandle, request: Request<Incoming>) -> Result<HttpResponse, Infallible> {
+    let origin = request
+        .headers()
+        .get(hyper::header::ORIGIN)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    if request.method() == Method::OPTIONS {
+        return Ok(with_cors(bytes_response(204, "text/plain", Vec::new()), origin.as_deref()));
+    }
+
+    let path = request.uri().path().to_string();
+    let query = parse_query(request.uri().query());
+    let response = if let Some(rest) = path.strip_prefix("/subtitles/") {
+        subtitles::handle_http(&app, request.method(), rest, &query).await
+    } else if let Some(rest) = path.strip_prefix("/stream/") {
+        stream_proxy::handle_http(&app, request.method(), rest, &query, request.headers()).await
+    } else {
+        text_response(404, "Not found")
+    };
+    Ok(with_cors(response, origin.as_deref()))
+}
+
+/// Start the media server in the background
+pub fn start(app: AppHandle) {
+    let port = downloads::backend_port(&app)
+        .checked_add(MEDIA_SERVER_PORT_OFFSET)
+        .unwrap_or(0);
+
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                // The backend picks the actual port up from the runtime file
+                log::warn!("Media server port {} unavailable ({}), using a random port", port, e);
+                match TcpListener::bind(("127.0.0.1", 0)).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        log::error!("Failed to start media server: {}", e);
+                        return;
+                    }
+                }
+            }
+        };
+
+        let bound = listener.local_addr().map(|a| a.port()).unwrap_or(port);
+        if let Ok(mut state) = app.state::<MediaServerState>().port.lock() {
+            *state = Some(bound);
+        }
+        log::info!("Media server listening on 127.0.0.1:{}", bound);
+        if let Err(e) = backend_runtime::write_runtime_file(&app) {
+            log::error!("Failed to share the media server port with the backend: {}", e);
+        }
+
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::warn!("Media server accept failed: {}", e);
+                    continue;
+                }
+            };
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let service = service_fn(move |request| handle(app.clone(), request));
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    log::debug!("Media server connection closed: {}", e);
+                }
+            });
+        }
+    });
+}
+
+/// Port the media server is listening on, once it is listening
+pub fn port(app: &AppHandle) -> Option<u16> {
+    *app.state::<MediaServerState>().port.lock().ok()?
+}
+
+/// Base URL of the media server, once it is listening
+pub fn server_url(app: &AppHandle) -> Option<String> {
+    Some(format!("http://127.0.0.1:{}", port(app)?))
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Get the base URL of the media server
+#[tauri::command]
+pub fn media_server_get_url(app: AppHandle) -> Result<String, String> {
+    server_url(&app).ok_or_else(|| "Media server is not running".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("C%3A%5CAnime%5Cep%2001.mkv"), "C:\\Anime\\ep 01.mkv");
+        assert_eq!(percent_decode("a+b%"), "a b%");
+        assert_eq!(percent_decode("%zz%e3%81%82"), "%zzあ");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query(Some("src=http%3A%2F%2Flocalhost%2Fx%3Fa%3D1&flag&"));
+        assert_eq!(query.get("src").map(String::as_str), Some("http://localhost/x?a=1"));
+        assert_eq!(query.get("flag").map(String::as_str), Some(""));
+        assert!(parse_query(None).is_empty());
+    }
+}
@@ -0,0 +1,725 @@
+//! Plugin Host
+//!
+//! This module runs plugin code in an embedded QuickJS engine instead of the webview.
+//! Each plugin gets its own runtime on a dedicated thread, with a memory limit and a
+//! CPU-time limit per call (time spent waiting for host functions doesn't count).
+//!
+//! The only things plugin code can reach are the documented `context.http`,
+//! `context.html` and `context.storage` objects built by `plugin_prelude.js`. HTTP
+//! requests go through the same checks as `plugin_fetch`, and HTML is parsed natively by
+//! `plugin_html`. Provider calls (`search`, `getEpisodes`, `getStreams`, ...) are Tauri
+//! commands with timeouts.
+
+use crate::commands::AYOTO_VERSION;
+use crate::plugin_fetch::{self, PluginFetchRequest, PluginFetchResponse};
+use crate::plugin_html::{self, HtmlRequest};
+use crate::plugin_storage::{self, StorageOp};
+use crate::plugins::{self, LoadedPlugin};
+use crate::profiles::{self, ProfileState};
+use rquickjs::{Context, Ctx, Exception, Function, Object, Promise, Runtime};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// JavaScript that builds the plugin context and dispatcher
+const PRELUDE: &str = include_str!("plugin_prelude.js");
+
+/// Provider methods the host can call (each needs the matching manifest capability)
+pub const PLUGIN_METHODS: &[&str] = &[
+    "search",
+    "getPopular",
+    "getLatest",
+    "getEpisodes",
+    "getStreams",
+    "getAnimeDetails",
+    "extractStream",
+    "getHosterInfo",
+];
+
+/// Time allowed for loading a plugin and running its `init`
+const INIT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Time allowed for a provider call
+const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time allowed for stream extraction, which usually needs several requests
+const STREAM_CALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Resource limits of a plugin runtime
+#[derive(Debug, Clone, Copy)]
+pub struct HostLimits {
+    pub memory_bytes: usize,
+    pub stack_bytes: usize,
+    /// JavaScript execution time per call
+    pub cpu_time: Duration,
+}
+
+impl Default for HostLimits {
+    fn default() -> Self {
+        HostLimits {
+            memory_bytes: 64 * 1024 * 1024,
+            stack_bytes: 1024 * 1024,
+            cpu_time: Duration::from_secs(10),
+        }
+    }
+}
+
+/// What the host functions do on the app side
+pub trait PluginHostApi: Send + Sync + 'static {
+    /// Send an HTTP request for a plugin
+    fn fetch(&self, plugin_id: &str, request: PluginFetchRequest) -> Result<PluginFetchResponse, String>;
+    /// Run a storage operation for a plugin
+    fn storage(&self, plugin_id: &str, op: StorageOp) -> Result<Value, String>;
+}
+
+/// Host functions backed by the app
+struct AppHost(AppHandle);
+
+impl PluginHostApi for AppHost {
+    fn fetch(&self, plugin_id: &str, request: PluginFetchRequest) -> Result<PluginFetchResponse, String> {
+        tauri::async_runtime::block_on(plugin_fetch::fetch_for_plugin(&self.0, plugin_id, request))
+    }
+
+    fn storage(&self, plugin_id: &str, op: StorageOp) -> Result<Value, String> {
+        plugin_storage::run(&self.0, plugin_id, op)
+    }
+}
+
+// =============================================================================
+// Call Budget
+// =============================================================================
+
+/// Flags shared between a caller and the plugin thread for one call
+#[derive(Debug, Default)]
+struct CallBudget {
+    /// Set by the caller when it stops waiting
+    cancelled: AtomicBool,
+    /// Set by the interrupt handler when the CPU-time limit is hit
+    cpu_exceeded: AtomicBool,
+}
+
+/// Tracks JavaScript execution time of the running call
+#[derive(Default)]
+struct CallClock {
+    started: Cell<Option<Instant>>,
+    /// Time spent in host functions during the call
+    host_time: Cell<Duration>,
+    budget: RefCell<Option<Arc<CallBudget>>>,
+}
+
+impl CallClock {
+    fn start(&self, budget: Arc<CallBudget>) {
+        self.started.set(Some(Instant::now()));
+        self.host_time.set(Duration::ZERO);
+        *self.budget.borrow_mut() = Some(budget);
+    }
+
+    fn stop(&self) {
+        self.started.set(None);
+        *self.budget.borrow_mut() = None;
+    }
+
+    /// Run a host function, excluding its duration from the CPU time
+    fn in_host<T>(&self, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.host_time.set(self.host_time.get() + started.elapsed());
+        result
+    }
+
+    fn cancelled(&self) -> bool {
+        self.budget
+            .borrow()
+            .as_ref()
+            .is_some_and(|budget| budget.cancelled.load(Ordering::Relaxed))
+    }
+
+    /// Called by QuickJS while it runs code; `true` aborts execution
+    fn should_interrupt(&self, cpu_limit: Duration) -> bool {
+        let budget = self.budget.borrow();
+        let (Some(budget), Some(started)) = (budget.as_ref(), self.started.get()) else {
+            return false;
+        };
+        if budget.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        if started.elapsed().saturating_sub(self.host_time.get()) > cpu_limit {
+            budget.cpu_exceeded.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+}
+
+// =============================================================================
+// Plugin Thread
+// =============================================================================
+
+/// A call queued for a plugin thread
+struct Job {
+    method: String,
+    /// JSON array of arguments
+    args: String,
+    budget: Arc<CallBudget>,
+    reply: mpsc::Sender<Result<Value, String>>,
+}
+
+/// Message of a JavaScript error
+fn describe_error(ctx: &Ctx<'_>, error: rquickjs::Error) -> String {
+    match error {
+        rquickjs::Error::Exception => {
+            let value = ctx.catch();
+            if let Some(exception) = value.as_exception() {
+                exception.message().unwrap_or_else(|| "Unknown JavaScript error".to_string())
+            } else if let Some(message) = value.as_string().and_then(|s| s.to_string().ok()) {
+                message
+            } else {
+                "Unknown JavaScript error".to_string()
+            }
+        }
+        rquickjs::Error::WouldBlock => "Plugin returned a promise that never settled".to_string(),
+        error => error.to_string(),
+    }
+}
+
+/// Error of a failed call, naming the limit that stopped it
+fn call_error(ctx: &Ctx<'_>, error: rquickjs::Error, budget: &CallBudget, limits: &HostLimits) -> String {
+    let message = describe_error(ctx, error);
+    if budget.cpu_exceeded.load(Ordering::Relaxed) {
+        format!("Plugin exceeded its CPU time limit ({} ms)", limits.cpu_time.as_millis())
+    } else if budget.cancelled.load(Ordering::Relaxed) {
+        "Plugin call was cancelled".to_string()
+    } else if message.contains("out of memory") {
+        format!("Plugin exceeded its memory limit ({} MiB)", limits.memory_bytes / (1024 * 1024))
+    } else {
+        message
+    }
+}
+
+/// Build the host object passed to the prelude
+fn host_object<'js>(
+    ctx: &Ctx<'js>,
+    plugin_id: &str,
+    host: Arc<dyn PluginHostApi>,
+    clock: Rc<CallClock>,
+) -> rquickjs::Result<Object<'js>> {
+    let object = Object::new(ctx.clone())?;
+
+    let (id, api, call_clock) = (plugin_id.to_string(), host.clone(), clock.clone());
+    let http = Function::new(ctx.clone(), move |ctx: Ctx<'_>, payload: String| -> rquickjs::Result<String> {
+        if call_clock.cancelled() {
+            return Err(Exception::throw_message(&ctx, "Plugin call was cancelled"));
+        }
+        let request: PluginFetchRequest = serde_json::from_str(&payload)
+            .map_err(|e| Exception::throw_message(&ctx, &format!("Invalid request: {}", e)))?;
+        let response = match call_clock.in_host(|| api.fetch(&id, request)) {
+            Ok(response) => serde_json::to_value(response).unwrap_or(Value::Null),
+            // Same shape the webview clients return for failed requests
+            Err(error) => serde_json::json!({
+                "status": 0,
+                "statusText": "Network Error",
+                "headers": {},
+                "body": "",
+                "ok": false,
+                "error": error
+            }),
+        };
+        Ok(response.to_string())
+    })?;
+    object.set("http", http)?;
+
+    let (id, api, call_clock) = (plugin_id.to_string(), host, clock);
+    let storage = Function::new(ctx.clone(), move |ctx: Ctx<'_>, payload: String| -> rquickjs::Result<String> {
+        let op: StorageOp = serde_json::from_str(&payload)
+            .map_err(|e| Exception::throw_message(&ctx, &format!("Invalid storage request: {}", e)))?;
+        call_clock
+            .in_host(|| api.storage(&id, op))
+            .map(|value| value.to_string())
+            .map_err(|e| Exception::throw_message(&ctx, &e))
+    })?;
+    object.set("storage", storage)?;
+
+    let html = Function::new(ctx.clone(), |ctx: Ctx<'_>, payload: String| -> rquickjs::Result<String> {
+        serde_json::from_str::<HtmlRequest>(&payload)
+            .map_err(|e| format!("Invalid HTML request: {}", e))
+            .and_then(|request| plugin_html::extract(&request))
+            .map(|value| value.to_string())
+            .map_err(|e| Exception::throw_message(&ctx, &e))
+    })?;
+    object.set("html", html)?;
+
+    let id = plugin_id.to_string();
+    let log = Function::new(ctx.clone(), move |level: String, message: String| match level.as_str() {
+        "error" => log::error!("[Plugin:{}] {}", id, message),
+        "warn" => log::warn!("[Plugin:{}] {}", id, message),
+        _ => log::info!("[Plugin:{}] {}", id, message),
+    })?;
+    object.set("log", log)?;
+
+    Ok(object)
+}
+
+/// Plugin thread: loads the plugin, reports readiness, then runs calls until the
+/// instance is dropped
+fn run_plugin(
+    plugin: LoadedPlugin,
+    host: Arc<dyn PluginHostApi>,
+    limits: HostLimits,
+    init_budget: Arc<CallBudget>,
+    ready: mpsc::Sender<Result<(), String>>,
+    jobs: mpsc::Receiver<Job>,
+) {
+    let runtime = match Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            let _ = ready.send(Err(format!("Failed to create JavaScript runtime: {}", e)));
+            return;
+        }
+    };
+    runtime.set_memory_limit(limits.memory_bytes);
+    runtime.set_max_stack_size(limits.stack_bytes);
+    let clock = Rc::new(CallClock::default());
+    let interrupt_clock = clock.clone();
+    runtime.set_interrupt_handler(Some(Box::new(move || interrupt_clock.should_interrupt(limits.cpu_time))));
+
+    let context = match Context::full(&runtime) {
+        Ok(context) => context,
+        Err(e) => {
+            let _ = ready.send(Err(format!("Failed to create JavaScript context: {}", e)));
+            return;
+        }
+    };
+
+    context.with(|ctx| {
+        clock.start(init_budget.clone());
+        let setup = || -> rquickjs::Result<(Function<'_>, Promise<'_>)> {
+            let factory: Function = ctx.eval(PRELUDE)?;
+            let host = host_object(&ctx, &plugin.manifest.id, host, clock.clone())?;
+            let api: Object = factory.call((host, AYOTO_VERSION))?;
+            let load: Function = api.get("load")?;
+            let call: Function = api.get("call")?;
+            Ok((call, load.call((plugin.code.as_str(),))?))
+        };
+        let loaded = setup().and_then(|(call, init)| init.finish::<rquickjs::Value>().map(|_| call));
+        clock.stop();
+
+        let call = match loaded {
+            Ok(call) => {
+                let _ = ready.send(Ok(()));
+                call
+            }
+            Err(e) => {
+                let message = call_error(&ctx, e, &init_budget, &limits);
+                let _ = ready.send(Err(format!("Failed to load plugin {}: {}", plugin.manifest.id, message)));
+                return;
+            }
+        };
+
+        for job in jobs {
+            if job.budget.cancelled.load(Ordering::Relaxed) {
+                continue;
+            }
+            clock.start(job.budget.clone());
+            let result = call
+                .call::<_, Promise>((job.method.as_str(), job.args.as_str()))
+                .and_then(|promise| promise.finish::<String>())
+                .map_err(|e| call_error(&ctx, e, &job.budget, &limits))
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| format!("Invalid plugin result: {}", e)));
+            clock.stop();
+            let _ = job.reply.send(result);
+        }
+    });
+}
+
+/// A running plugin
+pub struct PluginInstance {
+    /// SHA-256 of the installed files the instance was started from
+    sha256: String,
+    jobs: mpsc::Sender<Job>,
+}
+
+impl PluginInstance {
+    /// Start a plugin thread and wait for the plugin to load
+    pub fn spawn(
+        plugin: LoadedPlugin,
+        sha256: String,
+        host: Arc<dyn PluginHostApi>,
+        limits: HostLimits,
+    ) -> Result<Self, String> {
+        let id = plugin.manifest.id.clone();
+        let (jobs, receiver) = mpsc::channel();
+        let (ready, ready_receiver) = mpsc::channel();
+        let init_budget = Arc::new(CallBudget::default());
+        let thread_budget = init_budget.clone();
+        std::thread::Builder::new()
+            .name(format!("plugin-{}", id))
+            .spawn(move || run_plugin(plugin, host, limits, thread_budget, ready, receiver))
+            .map_err(|e| format!("Failed to start plugin thread: {}", e))?;
+
+        match ready_receiver.recv_timeout(INIT_TIMEOUT) {
+            Ok(Ok(())) => Ok(PluginInstance { sha256, jobs }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => {
+                init_budget.cancelled.store(true, Ordering::Relaxed);
+                Err(format!("Plugin {} took too long to load", id))
+            }
+        }
+    }
+
+    /// Call a plugin method, waiting at most `timeout`
+    pub fn call(&self, method: &str, args: &[Value], timeout: Duration) -> Result<Value, String> {
+        let budget = Arc::new(CallBudget::default());
+        let (reply, receiver) = mpsc::channel();
+        let job = Job {
+            method: method.to_string(),
+            args: Value::Array(args.to_vec()).to_string(),
+            budget: budget.clone(),
+            reply,
+        };
+        self.jobs
+            .send(job)
+            .map_err(|_| "Plugin host has stopped".to_string())?;
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                budget.cancelled.store(true, Ordering::Relaxed);
+                Err(format!("Plugin call {} timed out after {} s", method, timeout.as_secs()))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err("Plugin host has stopped".to_string()),
+        }
+    }
+}
+
+// =============================================================================
+// State
+// =============================================================================
+
+/// Plugin host state: running plugin instances by ID
+#[derive(Default)]
+pub struct PluginHostState {
+    instances: Mutex<HashMap<String, Arc<PluginInstance>>>,
+}
+
+/// Stop a plugin's instance (e.g. after it was disabled or uninstalled)
+pub fn unload(app: &AppHandle, plugin_id: &str) {
+    if let Some(state) = app.try_state::<PluginHostState>() {
+        if let Ok(mut instances) = state.instances.lock() {
+            instances.remove(plugin_id);
+        }
+    }
+}
+
+/// Get the running instance of a plugin, starting it (or restarting it after an
+/// upgrade or rollback) as needed
+fn instance(app: &AppHandle, plugin_id: &str) -> Result<Arc<PluginInstance>, String> {
+    let plugin = plugins::get_plugin(app, plugin_id).ok_or_else(|| format!("Plugin '{}' is not installed", plugin_id))?;
+    let profile_id = profiles::active_profile_id(app, &app.state::<ProfileState>());
+    if !plugin.is_enabled_for(profile_id.as_deref()) {
+        return Err(format!("Plugin '{}' is disabled", plugin_id));
+    }
+
+    let state = app.state::<PluginHostState>();
+    if let Some(instance) = state
+        .instances
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin host: {}", e))?
+        .get(plugin_id)
+        .filter(|instance| instance.sha256 == plugin.current.sha256)
+    {
+        return Ok(instance.clone());
+    }
+
+    let loaded = plugins::load_plugin(app, plugin_id, profile_id)?;
+    let host: Arc<dyn PluginHostApi> = Arc::new(AppHost(app.clone()));
+    let instance = Arc::new(PluginInstance::spawn(loaded, plugin.current.sha256.clone(), host, HostLimits::default())?);
+    state
+        .instances
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin host: {}", e))?
+        .insert(plugin_id.to_string(), instance.clone());
+    log::info!("Started plugin {} {}", plugin_id, plugin.current.manifest.version);
+    Ok(instance)
+}
+
+/// Call a provider method of a plugin
+pub async fn call_plugin(app: AppHandle, plugin_id: String, method: &'static str, args: Vec<Value>) -> Result<Value, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let plugin =
+            plugins::get_plugin(&app, &plugin_id).ok_or_else(|| format!("Plugin '{}' is not installed", plugin_id))?;
+        let supported = plugin
+            .current
+            .manifest
+            .extra
+            .get("capabilities")
+            .and_then(|capabilities| capabilities.get(method))
+            .and_then(|enabled| enabled.as_bool())
+            .unwrap_or(false);
+        if !supported {
+            return Err(format!("Plugin does not support {}", method));
+        }
+
+        let timeout = match method {
+            "getStreams" | "extractStream" => STREAM_CALL_TIMEOUT,
+            _ => CALL_TIMEOUT,
+        };
+        instance(&app, &plugin_id)?.call(method, &args, timeout)
+    })
+    .await
+    .map_err(|e| format!("Plugin task failed: {}", e))?
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Call any provider method of a plugin
+#[tauri::command]
+pub async fn plugin_call(
+    plugin_id: String,
+    method: String,
+    args: Vec<Value>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    let method = PLUGIN_METHODS
+        .iter()
+        .find(|m| **m == method)
+        .ok_or_else(|| format!("Unknown plugin method '{}'", method))?;
+    call_plugin(app, plugin_id, method, args).await
+}
+
+/// Search a provider plugin
+#[tauri::command]
+pub async fn plugin_search(
+    plugin_id: String,
+    query: String,
+    page: Option<u32>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    call_plugin(app, plugin_id, "search", vec![query.into(), page.unwrap_or(1).into()]).await
+}
+
+/// Get the episodes of an anime from a provider plugin
+#[tauri::command]
+pub async fn plugin_get_episodes(
+    plugin_id: String,
+    anime_id: String,
+    page: Option<u32>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    call_plugin(app, plugin_id, "getEpisodes", vec![anime_id.into(), page.unwrap_or(1).into()]).await
+}
+
+/// Get the streams of an episode from a provider plugin
+#[tauri::command]
+pub async fn plugin_get_streams(
+    plugin_id: String,
+    anime_id: String,
+    episode_id: String,
+    app: AppHandle,
+) -> Result<Value, String> {
+    call_plugin(app, plugin_id, "getStreams", vec![anime_id.into(), episode_id.into()]).await
+}
+
+/// Stop a plugin's runtime; it is started again on the next call
+#[tauri::command]
+pub fn plugin_host_unload(plugin_id: String, app: AppHandle) -> Result<(), String> {
+    unload(&app, &plugin_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PluginFormat;
+
+    /// Host with canned responses and in-memory storage
+    #[derive(Default)]
+    struct MockHost {
+        bodies: HashMap<String, String>,
+        delay: Duration,
+        storage: Mutex<HashMap<String, Value>>,
+    }
+
+    impl PluginHostApi for MockHost {
+        fn fetch(&self, _plugin_id: &str, request: PluginFetchRequest) -> Result<PluginFetchResponse, String> {
+            std::thread::sleep(self.delay);
+            let body = self.bodies.get(&request.url).ok_or("Domain not allowed")?;
+            Ok(PluginFetchResponse {
+                status: 200,
+                status_text: "OK".to_string(),
+                headers: HashMap::new(),
+                body: body.clone(),
+                ok: true,
+                url: request.url,
+            })
+        }
+
+        fn storage(&self, _plugin_id: &str, op: StorageOp) -> Result<Value, String> {
+            let mut storage = self.storage.lock().unwrap();
+            match op {
+                StorageOp::Get { key } => Ok(storage.get(&key).cloned().unwrap_or(Value::Null)),
+                StorageOp::Set { key, value, .. } => Ok(storage.insert(key, value).into()),
+                _ => Err("Storage permission denied".to_string()),
+            }
+        }
+    }
+
+    fn spawn(code: &str, host: MockHost, limits: HostLimits) -> Result<PluginInstance, String> {
+        let plugin = LoadedPlugin {
+            manifest: serde_json::from_value(serde_json::json!({
+                "id": "test-plugin", "name": "Test", "version": "1.0.0", "pluginType": "mediaProvider"
+            }))
+            .unwrap(),
+            code: code.to_string(),
+            format: PluginFormat::Js,
+            session: None,
+        };
+        PluginInstance::spawn(plugin, String::new(), Arc::new(host), limits)
+    }
+
+    const PROVIDER: &str = r#"
+        module.exports = {
+          async init(ctx) {
+            this.ctx = ctx
+            ctx.storage.set('initialized', true)
+          },
+          async search(query, page) {
+            const response = await this.ctx.http.get(`https://example.com/search?q=${query}`)
+            const titles = this.ctx.html.extractText(response.body, 'h3.title')
+            return { results: titles.map(title => ({ title })), page, cached: this.ctx.storage.get('initialized') }
+          },
+          async getStreams() {
+            const response = await http.get('https://blocked.test/')
+            return { ok: response.ok, error: response.error }
+          }
+        }
+    "#;
+
+    #[test]
+    fn test_provider_calls() {
+        let mut host = MockHost::default();
+        host.bodies.insert(
+            "https://example.com/search?q=naruto".to_string(),
+            "<h3 class=\"title\">Naruto</h3><h3 class=\"title\">Naruto <b>Shippuden</b></h3>".to_string(),
+        );
+        let instance = spawn(PROVIDER, host, HostLimits::default()).unwrap();
+
+        let result = instance
+            .call("search", &["naruto".into(), 2.into()], CALL_TIMEOUT)
+            .unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "results": [{ "title": "Naruto" }, { "title": "Naruto Shippuden" }],
+                "page": 2,
+                "cached": true
+            })
+        );
+
+        // Failed requests resolve to an error response, like in the webview
+        let result = instance.call("getStreams", &[], CALL_TIMEOUT).unwrap();
+        assert_eq!(result, serde_json::json!({ "ok": false, "error": "Domain not allowed" }));
+
+        let error = instance.call("getEpisodes", &[], CALL_TIMEOUT).unwrap_err();
+        assert_eq!(error, "Plugin does not implement getEpisodes");
+    }
+
+    #[test]
+    fn test_only_context_is_exposed() {
+        let code = r#"
+            module.exports = {
+              async search() {
+                return {
+                  globals: [typeof fetch, typeof require, typeof process, typeof window,
+                            typeof localStorage, typeof __TAURI_INTERNALS__, typeof host, typeof std, typeof os],
+                  context: Object.keys(context),
+                  frozen: Object.isFrozen(context.http)
+                }
+              }
+            }
+        "#;
+        let instance = spawn(code, MockHost::default(), HostLimits::default()).unwrap();
+        let result = instance.call("search", &[], CALL_TIMEOUT).unwrap();
+        assert_eq!(result["globals"], serde_json::json!(vec!["undefined"; 9]));
+        assert_eq!(
+            result["context"],
+            serde_json::json!(["http", "html", "storage", "log", "getAyotoVersion"])
+        );
+        assert_eq!(result["frozen"], true);
+
+        // Storage errors surface as exceptions
+        let code = "module.exports = { async search() { context.storage.clear() } }";
+        let instance = spawn(code, MockHost::default(), HostLimits::default()).unwrap();
+        assert_eq!(
+            instance.call("search", &[], CALL_TIMEOUT).unwrap_err(),
+            "Storage permission denied"
+        );
+    }
+
+    #[test]
+    fn test_cpu_and_memory_limits() {
+        let limits = HostLimits {
+            memory_bytes: 16 * 1024 * 1024,
+            cpu_time: Duration::from_millis(200),
+            ..HostLimits::default()
+        };
+        let code = r#"
+            module.exports = {
+              async search() { while (true) {} },
+              async getLatest() {
+                const chunks = []
+                while (true) chunks.push(new Array(100000).fill('x'))
+              },
+              async getPopular() { return 'still alive' }
+            }
+        "#;
+        let instance = spawn(code, MockHost::default(), limits).unwrap();
+        assert_eq!(
+            instance.call("search", &[], CALL_TIMEOUT).unwrap_err(),
+            "Plugin exceeded its CPU time limit (200 ms)"
+        );
+        assert!(instance
+            .call("getLatest", &[], CALL_TIMEOUT)
+            .unwrap_err()
+            .contains("memory limit"));
+        // The runtime keeps working after a call was stopped
+        assert_eq!(instance.call("getPopular", &[], CALL_TIMEOUT).unwrap(), "still alive");
+
+        // A plugin that never finishes loading is rejected
+        let error = spawn("while (true) {}", MockHost::default(), limits).err().unwrap();
+        assert!(error.contains("CPU time limit"), "{}", error);
+    }
+
+    #[test]
+    fn test_call_timeout_excludes_host_time() {
+        let host = MockHost {
+            bodies: HashMap::from([("https://example.com/".to_string(), "slow".to_string())]),
+            delay: Duration::from_millis(300),
+            ..MockHost::default()
+        };
+        let limits = HostLimits {
+            cpu_time: Duration::from_millis(200),
+            ..HostLimits::default()
+        };
+        let code = r#"
+            module.exports = {
+              async search() { return http.get('https://example.com/').body },
+              async getLatest() { http.get('https://example.com/'); http.get('https://example.com/') }
+            }
+        "#;
+        let instance = spawn(code, host, limits).unwrap();
+        // Waiting for the request doesn't count towards the CPU-time limit
+        assert_eq!(instance.call("search", &[], CALL_TIMEOUT).unwrap(), "slow");
+
+        let error = instance
+            .call("getLatest", &[], Duration::from_millis(400))
+            .unwrap_err();
+        assert_eq!(error, "Plugin call getLatest timed out after 0 s");
+    }
+}
@@ -0,0 +1,419 @@
+//! Plugin HTML Extraction
+//!
+//! Native HTML parsing for plugins, replacing the regex-based `context.html` helpers.
+//! Documents are parsed with html5ever (via `scraper`) and queried with CSS selectors:
+//!
+//! - `text`: normalised text of each matching element
+//! - `html`: inner HTML of each matching element
+//! - `attribute`: an attribute of each matching element; URL attributes (`href`,
+//!   `src`, `data-src`, ...) are resolved against `baseUrl` if given
+//! - `select`: one object per matching element, with fields taken from the element or
+//!   its descendants (for listing pages, in a single parse)
+//! - `table`: the rows of a table as cell text, or as objects keyed by the header row
+//!
+//! Text normalisation decodes entities, drops `<script>`/`<style>` content, separates
+//! block elements and `<br>` with a space and collapses whitespace.
+//!
+//! Plugins in the plugin host reach this through `context.html`; the webview uses the
+//! `html_extract` command.
+
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use tauri_plugin_http::reqwest::Url;
+
+/// Largest document accepted (same as the largest plugin response)
+pub const MAX_HTML_BYTES: usize = crate::plugin_fetch::MAX_RESPONSE_BYTES;
+
+/// Elements whose text is never extracted
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "template", "noscript"];
+
+/// Elements separated from their neighbours by whitespace in extracted text
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption", "footer",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav", "ol", "p", "pre", "section",
+    "table", "tbody", "td", "tfoot", "th", "thead", "tr", "ul",
+];
+
+/// Attributes holding URLs, which are resolved against the request's base URL
+const URL_ATTRIBUTES: &[&str] = &[
+    "action", "data-href", "data-lazy-src", "data-original", "data-src", "data-url", "href", "poster", "src",
+];
+
+/// How a field of a `select` result is extracted
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldSpec {
+    /// Selector relative to the matched element (the element itself if missing)
+    #[serde(default)]
+    pub selector: Option<String>,
+    /// Attribute to read instead of the text
+    #[serde(default)]
+    pub attribute: Option<String>,
+    /// Return the inner HTML instead of the text
+    #[serde(default)]
+    pub html: bool,
+    /// Return every match as an array instead of the first one
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// An extraction request
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum HtmlRequest {
+    Text {
+        html: String,
+        selector: String,
+    },
+    Html {
+        html: String,
+        selector: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Attribute {
+        html: String,
+        selector: String,
+        attribute: String,
+        #[serde(default)]
+        base_url: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Select {
+        html: String,
+        selector: String,
+        fields: BTreeMap<String, FieldSpec>,
+        #[serde(default)]
+        base_url: Option<String>,
+    },
+    Table {
+        html: String,
+        selector: String,
+        #[serde(default)]
+        headers: bool,
+    },
+    Normalize {
+        text: String,
+    },
+}
+
+// =============================================================================
+// Text
+// =============================================================================
+
+/// Collapse runs of whitespace (including non-breaking spaces) and trim
+pub fn normalize_text(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace() || c == '\u{a0}')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn collect_text(element: ElementRef<'_>, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(el) => {
+                let name = el.name();
+                if SKIPPED_ELEMENTS.contains(&name) {
+                    continue;
+                }
+                let block = BLOCK_ELEMENTS.contains(&name);
+                if block {
+                    out.push(' ');
+                }
+                if let Some(child) = ElementRef::wrap(child) {
+                    collect_text(child, out);
+                }
+                if block {
+                    out.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Normalised text of an element
+pub fn element_text(element: ElementRef<'_>) -> String {
+    let mut text = String::new();
+    collect_text(element, &mut text);
+    normalize_text(&text)
+}
+
+// =============================================================================
+// Queries
+// =============================================================================
+
+fn parse_selector(selector: &str) -> Result<Selector, String> {
+    Selector::parse(selector).map_err(|e| format!("Invalid selector '{}': {}", selector, e))
+}
+
+fn parse_base_url(base_url: Option<&str>) -> Result<Option<Url>, String> {
+    base_url
+        .map(|url| Url::parse(url).map_err(|e| format!("Invalid base URL: {}", e)))
+        .transpose()
+}
+
+/// Read an attribute, resolving URL attributes against the base URL if given
+fn attribute_value(element: ElementRef<'_>, attribute: &str, base_url: Option<&Url>) -> Option<String> {
+    let value = element.value().attr(attribute)?.trim();
+    match base_url {
+        Some(base) if URL_ATTRIBUTES.contains(&attribute) => base.join(value).ok().map(String::from),
+        _ => Some(value.to_string()),
+    }
+}
+
+fn field_value(element: ElementRef<'_>, spec: &FieldSpec, base_url: Option<&Url>) -> Option<String> {
+    match &spec.attribute {
+        Some(attribute) => attribute_value(element, attribute, base_url),
+        None if spec.html => Some(element.inner_html()),
+        None => Some(element_text(element)),
+    }
+}
+
+/// Extract one field of a `select` item
+fn extract_field(item: ElementRef<'_>, spec: &FieldSpec, selector: Option<&Selector>, base_url: Option<&Url>) -> Value {
+    let matches: Vec<ElementRef<'_>> = match selector {
+        Some(selector) => item.select(selector).collect(),
+        None => vec![item],
+    };
+    if spec.all {
+        matches
+            .into_iter()
+            .filter_map(|element| field_value(element, spec, base_url))
+            .map(Value::String)
+            .collect()
+    } else {
+        matches
+            .into_iter()
+            .find_map(|element| field_value(element, spec, base_url))
+            .map_or(Value::Null, Value::String)
+    }
+}
+
+/// Cell text of a table's own rows (rows of nested tables are skipped)
+pub fn table_rows(table: ElementRef<'_>) -> Vec<Vec<String>> {
+    let rows = Selector::parse("tr").expect("valid selector");
+    table
+        .select(&rows)
+        .filter(|row| {
+            row.ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|el| el.value().name() == "table")
+                .is_some_and(|el| el.id() == table.id())
+        })
+        .map(|row| {
+            let mut cells = Vec::new();
+            for cell in row.children().filter_map(ElementRef::wrap) {
+                if !matches!(cell.value().name(), "td" | "th") {
+                    continue;
+                }
+                cells.push(element_text(cell));
+                // Keep columns aligned when a cell spans several
+                let span = cell
+                    .value()
+                    .attr("colspan")
+                    .and_then(|span| span.trim().parse::<usize>().ok())
+                    .unwrap_or(1)
+                    .clamp(1, 100);
+                cells.extend(std::iter::repeat(String::new()).take(span - 1));
+            }
+            cells
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect()
+}
+
+/// Rows keyed by the header row's cells
+fn rows_to_objects(rows: Vec<Vec<String>>) -> Value {
+    let mut rows = rows.into_iter();
+    let Some(headers) = rows.next() else {
+        return Value::Array(Vec::new());
+    };
+    rows.map(|row| {
+        let object: Map<String, Value> = headers
+            .iter()
+            .enumerate()
+            .filter(|(_, header)| !header.is_empty())
+            .map(|(i, header)| (header.clone(), row.get(i).cloned().map_or(Value::Null, Value::String)))
+            .collect();
+        Value::Object(object)
+    })
+    .collect()
+}
+
+/// Run an extraction request
+pub fn extract(request: &HtmlRequest) -> Result<Value, String> {
+    if let HtmlRequest::Normalize { text } = request {
+        return Ok(Value::String(normalize_text(text)));
+    }
+
+    let (source, selector) = match request {
+        HtmlRequest::Text { html, selector }
+        | HtmlRequest::Html { html, selector }
+        | HtmlRequest::Attribute { html, selector, .. }
+        | HtmlRequest::Select { html, selector, .. }
+        | HtmlRequest::Table { html, selector, .. } => (html, selector),
+        HtmlRequest::Normalize { .. } => unreachable!(),
+    };
+    if source.len() > MAX_HTML_BYTES {
+        return Err(format!("Document is too large ({} bytes)", source.len()));
+    }
+    let selector = parse_selector(selector)?;
+    let document = Html::parse_document(source);
+    let elements = document.select(&selector);
+
+    let value = match request {
+        HtmlRequest::Text { .. } => elements
+            .map(element_text)
+            .filter(|text| !text.is_empty())
+            .map(Value::String)
+            .collect(),
+        HtmlRequest::Html { .. } => elements.map(|el| Value::String(el.inner_html())).collect(),
+        HtmlRequest::Attribute {
+            attribute, base_url, ..
+        } => {
+            let base_url = parse_base_url(base_url.as_deref())?;
+            elements
+                .filter_map(|el| attribute_value(el, attribute, base_url.as_ref()))
+                .map(Value::String)
+                .collect()
+        }
+        HtmlRequest::Select { fields, base_url, .. } => {
+            let base_url = parse_base_url(base_url.as_deref())?;
+            let fields = fields
+                .iter()
+                .map(|(name, spec)| Ok((name, spec, spec.selector.as_deref().map(parse_selector).transpose()?)))
+                .collect::<Result<Vec<_>, String>>()?;
+            elements
+                .map(|item| {
+                    let object: Map<String, Value> = fields
+                        .iter()
+                        .map(|(name, spec, selector)| {
+                            ((*name).clone(), extract_field(item, spec, selector.as_ref(), base_url.as_ref()))
+                        })
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect()
+        }
+        HtmlRequest::Table { headers, .. } => {
+            let rows = elements.map(table_rows).next().unwrap_or_default();
+            if *headers {
+                rows_to_objects(rows)
+            } else {
+                serde_json::to_value(rows).unwrap_or_default()
+            }
+        }
+        HtmlRequest::Normalize { .. } => unreachable!(),
+    };
+    Ok(value)
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Run an HTML extraction request
+#[tauri::command]
+pub async fn html_extract(request: HtmlRequest) -> Result<Value, String> {
+    tauri::async_runtime::spawn_blocking(move || extract(&request))
+        .await
+        .map_err(|e| format!("HTML extraction failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const LISTING: &str = include_str!("../tests/fixtures/html/listing.html");
+
+    fn run(request: Value) -> Result<Value, String> {
+        extract(&serde_json::from_value(request).unwrap())
+    }
+
+    #[test]
+    fn test_text_normalisation() {
+        let html = "<ul><li class=\"t\">  Naruto&nbsp;<b>Shippuden</b>\n</li><li class=\"t\">One<br>Piece<script>x()</script></li><li class=\"t\"> </li></ul>";
+        assert_eq!(
+            run(json!({ "op": "text", "html": html, "selector": "li.t" })).unwrap(),
+            json!(["Naruto Shippuden", "One Piece"])
+        );
+        assert_eq!(
+            run(json!({ "op": "normalize", "text": " a \t\n b\u{a0}c " })).unwrap(),
+            json!("a b c")
+        );
+    }
+
+    #[test]
+    fn test_attributes_and_listing() {
+        let links = run(json!({
+            "op": "attribute", "html": LISTING, "selector": ".anime-card > a",
+            "attribute": "href", "baseUrl": "https://example.com/browse?page=2"
+        }))
+        .unwrap();
+        assert_eq!(links[0], "https://example.com/anime/frieren");
+        assert_eq!(links.as_array().unwrap().len(), 3);
+
+        let items = run(json!({
+            "op": "select", "html": LISTING, "selector": ".anime-card",
+            "baseUrl": "https://example.com/",
+            "fields": {
+                "id": { "attribute": "data-id" },
+                "title": { "selector": ".title" },
+                "url": { "selector": "a", "attribute": "href" },
+                "cover": { "selector": "img", "attribute": "data-src" },
+                "genres": { "selector": ".genres span", "all": true },
+                "rating": { "selector": ".rating" }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            items[0],
+            json!({
+                "id": "frieren",
+                "title": "Sousou no Frieren",
+                "url": "https://example.com/anime/frieren",
+                "cover": "https://cdn.example.com/covers/frieren.jpg",
+                "genres": ["Adventure", "Drama", "Fantasy"],
+                "rating": "9.1"
+            })
+        );
+        assert_eq!(items[2]["title"], "Kusuriya no Hitorigoto & Co.");
+        assert_eq!(items[2]["rating"], Value::Null);
+    }
+
+    #[test]
+    fn test_table_rows() {
+        let rows = run(json!({ "op": "table", "html": LISTING, "selector": "table.episodes" })).unwrap();
+        assert_eq!(
+            rows,
+            json!([
+                ["#", "Title", "Aired"],
+                ["1", "The Journey's End", "2023-09-29"],
+                ["2", "It Didn't Have to Be Magic...", "2023-09-29"],
+                ["3", "Recap nested", ""]
+            ])
+        );
+
+        let rows = run(json!({ "op": "table", "html": LISTING, "selector": "table.episodes", "headers": true })).unwrap();
+        assert_eq!(rows[0], json!({ "#": "1", "Title": "The Journey's End", "Aired": "2023-09-29" }));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(run(json!({ "op": "text", "html": "<p>x</p>", "selector": "p[" }))
+            .unwrap_err()
+            .starts_with("Invalid selector 'p['"));
+        assert!(run(json!({
+            "op": "attribute", "html": "<a href=\"/x\">x</a>", "selector": "a",
+            "attribute": "href", "baseUrl": "not a url"
+        }))
+        .unwrap_err()
+        .starts_with("Invalid base URL"));
+        assert_eq!(run(json!({ "op": "html", "html": "", "selector": "p" })).unwrap(), json!([]));
+    }
+}
@@ -0,0 +1,190 @@
+/**
+ * Plugin Host Prelude
+ *
+ * Evaluated in each plugin's QuickJS context (see plugin_host.rs) before the plugin
+ * code. It builds the documented `context` object (http, html, storage) on top of the
+ * host functions passed in, and returns the loader/dispatcher the host calls. The host
+ * functions themselves never become globals.
+ */
+(function (host, appVersion) {
+  'use strict'
+
+  const STREAM_FORMAT = Object.freeze({
+    M3U8: 'm3u8',
+    MP4: 'mp4',
+    MKV: 'mkv',
+    WEBM: 'webm',
+    TORRENT: 'torrent'
+  })
+
+  const PLUGIN_TYPE = Object.freeze({
+    MEDIA_PROVIDER: 'mediaProvider',
+    STREAM_PROVIDER: 'streamProvider'
+  })
+
+  // ==========================================================================
+  // HTTP (checked and sent by plugin_fetch)
+  // ==========================================================================
+
+  function request(method, url, options = {}) {
+    const req = {
+      url: String(url),
+      method,
+      headers: { ...(options.headers || {}) },
+      timeoutMs: options.timeout
+    }
+
+    if (options.body !== undefined && options.body !== null) {
+      if (typeof options.body === 'object') {
+        req.body = JSON.stringify(options.body)
+        req.headers['Content-Type'] = 'application/json'
+      } else {
+        req.body = String(options.body)
+      }
+    }
+
+    return JSON.parse(host.http(JSON.stringify(req)))
+  }
+
+  const http = Object.freeze({
+    request,
+    get: (url, options = {}) => request('GET', url, options),
+    post: (url, body, options = {}) => request('POST', url, { ...options, body }),
+    getJson(url, options = {}) {
+      const response = request('GET', url, {
+        ...options,
+        headers: { Accept: 'application/json', ...options.headers }
+      })
+      if (!response.ok) {
+        throw new Error(`HTTP ${response.status}: ${response.statusText}`)
+      }
+      return JSON.parse(response.body)
+    }
+  })
+
+  // ==========================================================================
+  // HTML (parsed natively by plugin_html)
+  // ==========================================================================
+
+  const extract = (request) => JSON.parse(host.html(JSON.stringify(request)))
+
+  const html = Object.freeze({
+    // CSS selector queries
+    select: (source, selector) => extract({ op: 'text', html: source, selector }),
+    innerHtml: (source, selector) => extract({ op: 'html', html: source, selector }),
+    attr: (source, selector, attribute, baseUrl) =>
+      extract({ op: 'attribute', html: source, selector, attribute, baseUrl }),
+    query: (source, selector, fields, baseUrl) =>
+      extract({ op: 'select', html: source, selector, fields, baseUrl }),
+    table: (source, selector = 'table', headers = false) =>
+      extract({ op: 'table', html: source, selector, headers }),
+    normalize: (text) => extract({ op: 'normalize', text: String(text) }),
+
+    // Helpers of the webview runtime; selectors are CSS now
+    extractText: (source, selector) => extract({ op: 'text', html: source, selector }),
+    extractAttribute: (source, tagName, attrName) =>
+      extract({ op: 'attribute', html: source, selector: `${tagName}[${attrName}]`, attribute: attrName }),
+    extractLinks: (source) => html.extractAttribute(source, 'a', 'href'),
+    extractImages(source) {
+      return [...html.extractAttribute(source, 'img', 'src'), ...html.extractAttribute(source, 'img', 'data-src')]
+    },
+    extractByClass: (source, className) => extract({ op: 'html', html: source, selector: `.${className}` }),
+    extractById(source, id) {
+      const [match] = extract({ op: 'html', html: source, selector: `[id="${id}"]` })
+      return match === undefined ? null : match
+    },
+    decodeEntities(source) {
+      const entities = {
+        '&amp;': '&', '&lt;': '<', '&gt;': '>', '&quot;': '"',
+        '&#39;': "'", '&nbsp;': ' ', '&ndash;': '–', '&mdash;': '—'
+      }
+      let result = source
+      for (const [entity, char] of Object.entries(entities)) {
+        result = result.replace(new RegExp(entity, 'g'), char)
+      }
+      return result
+        .replace(/&#(\d+);/g, (_, code) => String.fromCharCode(parseInt(code, 10)))
+        .replace(/&#x([0-9a-f]+);/gi, (_, code) => String.fromCharCode(parseInt(code, 16)))
+    },
+    extractJsonFromScript(source, varName = null) {
+      const regex = varName
+        ? new RegExp(`(?:var|let|const)\\s+${varName}\\s*=\\s*({[\\s\\S]*?});`, 'i')
+        : /<script[^>]*type=["']application\/(?:ld\+)?json["'][^>]*>([\s\S]*?)<\/script>/i
+      const match = source.match(regex)
+      try {
+        return match ? JSON.parse(match[1]) : null
+      } catch {
+        return null
+      }
+    }
+  })
+
+  // ==========================================================================
+  // Storage
+  // ==========================================================================
+
+  const storageCall = (request) => JSON.parse(host.storage(JSON.stringify(request)))
+
+  const storage = Object.freeze({
+    get(key, defaultValue = null) {
+      const value = storageCall({ op: 'get', key: String(key) })
+      return value === null || value === undefined ? defaultValue : value
+    },
+    // options.ttlMs: drop the value after this many milliseconds
+    set(key, value, options = {}) {
+      storageCall({ op: 'set', key: String(key), value: value === undefined ? null : value, ttlMs: options.ttlMs })
+    },
+    remove(key) {
+      return storageCall({ op: 'remove', key: String(key) })
+    },
+    clear() {
+      storageCall({ op: 'clear' })
+    },
+    keys() {
+      return storageCall({ op: 'keys' })
+    },
+    getUsage() {
+      return storageCall({ op: 'usage' })
+    }
+  })
+
+  // ==========================================================================
+  // Context and dispatch
+  // ==========================================================================
+
+  const context = Object.freeze({
+    http,
+    html,
+    storage,
+    log(level, message) {
+      host.log(String(level), String(message))
+    },
+    getAyotoVersion: () => appVersion
+  })
+
+  let plugin = null
+
+  return Object.freeze({
+    async load(code) {
+      const module = { exports: {} }
+      const factory = new Function(
+        'context', 'http', 'html', 'storage', 'STREAM_FORMAT', 'PLUGIN_TYPE', 'module', 'exports',
+        code
+      )
+      factory(context, http, html, storage, STREAM_FORMAT, PLUGIN_TYPE, module, module.exports)
+      plugin = module.exports
+
+      if (typeof plugin.init === 'function') {
+        await plugin.init(context)
+      }
+    },
+
+    async call(method, args) {
+      if (!plugin || typeof plugin[method] !== 'function') {
+        throw new Error(`Plugin does not implement ${method}`)
+      }
+      const result = await plugin[method](...JSON.parse(args))
+      return JSON.stringify(result === undefined ? null : result)
+    }
+  })
+})
@@ -0,0 +1,1043 @@
+//! Plugin Repositories
+//!
+//! Users add plugin repositories by the URL of their index file, a JSON document listing
+//! the repository's plugins, their versions with download URLs and SHA-256 checksums,
+//! and the repository's signing keys:
+//!
+//! ```json
+//! {
+//!   "formatVersion": 1,
+//!   "name": "Community Plugins",
+//!   "signingKeys": [{ "keyId": "community-2025", "publicKey": { "kty": "EC", "crv": "P-384", "x": "…", "y": "…" } }],
+//!   "plugins": [{
+//!     "id": "my-plugin", "name": "My Plugin", "pluginType": "media-provider",
+//!     "versions": [{ "version": "1.2.0", "url": "packages/my-plugin-1.2.0.zpe", "sha256": "…" }]
+//!   }]
+//! }
+//! ```
+//!
+//! Indexes are cached in `{app_data}/plugin-repos/` and merged into one catalog; when
+//! several repositories offer a plugin, the highest compatible version wins. The signing
+//! keys are pinned when a repository is added: a refresh that changes them is rejected
+//! (the cached index is kept), and packages installed from a repository must be signed
+//! by one of its pinned keys. Removing a repository revokes its keys, so plugins signed
+//! only by them stop loading.
+
+use crate::commands::AYOTO_VERSION;
+use crate::plugin_updates::compare_versions;
+use crate::plugins::{self, InstalledPlugin, PluginSource, PluginSummary};
+use crate::zpe::{self, TrustedKey, VerifyOptions};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_http::reqwest::{self, Url};
+use tauri_plugin_store::StoreExt;
+
+/// Store file name for added repositories
+const REPOS_STORE_FILE: &str = "plugin_repos.json";
+
+/// Store key for added repositories
+const REPOSITORIES_KEY: &str = "repositories";
+
+/// Folder of cached indexes (and downloads) in the app data folder
+const REPOS_DIR: &str = "plugin-repos";
+
+/// Event emitted when the repositories change
+pub const PLUGIN_REPOS_EVENT: &str = "plugin-repos-updated";
+
+/// Index format understood by this version
+const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// Largest accepted index file
+const MAX_INDEX_BYTES: usize = 4 * 1024 * 1024;
+
+/// Timeout of index requests and downloads
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A repository index file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepositoryIndex {
+    #[serde(default = "default_format_version")]
+    pub format_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// Keys the repository signs its packages with
+    #[serde(default)]
+    pub signing_keys: Vec<TrustedKey>,
+    #[serde(default)]
+    pub plugins: Vec<IndexPlugin>,
+}
+
+fn default_format_version() -> u32 {
+    INDEX_FORMAT_VERSION
+}
+
+/// A plugin listed in an index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexPlugin {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub plugin_type: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    pub versions: Vec<IndexVersion>,
+}
+
+/// A downloadable version of a plugin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexVersion {
+    pub version: String,
+    /// Package URL (relative URLs are resolved against the index URL)
+    pub url: String,
+    /// SHA-256 of the package (hex)
+    pub sha256: String,
+    #[serde(default)]
+    pub min_app_version: Option<String>,
+    #[serde(default)]
+    pub release_notes: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+}
+
+/// An added repository
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginRepository {
+    /// Derived from the URL
+    pub id: String,
+    pub url: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Signing keys pinned when the repository was added
+    pub keys: Vec<TrustedKey>,
+    pub plugin_count: usize,
+    pub added_at: i64,
+    /// Last successful refresh (Unix milliseconds)
+    pub refreshed_at: Option<i64>,
+    /// Error of the last refresh, if it failed
+    pub error: Option<String>,
+}
+
+/// A plugin offered by the added repositories
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailablePlugin {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub plugin_type: Option<String>,
+    pub author: Option<String>,
+    pub icon_url: Option<String>,
+    pub homepage: Option<String>,
+    /// Highest version compatible with this app
+    pub version: String,
+    pub release_notes: Option<String>,
+    pub published_at: Option<String>,
+    pub repository_id: String,
+    pub repository_name: String,
+    pub installed_version: Option<String>,
+    pub update_available: bool,
+}
+
+/// Plugin repository state (in-memory cache of the store and the cached indexes)
+#[derive(Default)]
+pub struct PluginRepoState {
+    repositories: Mutex<Vec<PluginRepository>>,
+    /// Cached indexes by repository ID
+    indexes: Mutex<HashMap<String, RepositoryIndex>>,
+    loaded: Mutex<bool>,
+}
+
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Index Validation
+// =============================================================================
+
+/// Repository ID for an index URL
+fn repository_id(url: &str) -> String {
+    zpe::sha256_hex(url.as_bytes())[..16].to_string()
+}
+
+/// Parse an http(s) URL
+fn parse_http_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("URL '{}' must use http or https", url));
+    }
+    Ok(parsed)
+}
+
+fn is_sha256(hex: &str) -> bool {
+    hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parse and validate an index, resolving package URLs against the index URL
+pub fn parse_index(data: &[u8], index_url: &Url) -> Result<RepositoryIndex, String> {
+    let mut index: RepositoryIndex =
+        serde_json::from_slice(data).map_err(|e| format!("Invalid repository index: {}", e))?;
+
+    if index.format_version != INDEX_FORMAT_VERSION {
+        return Err(format!("Unsupported index format version {}", index.format_version));
+    }
+    if index.name.trim().is_empty() {
+        return Err("The repository has no name".to_string());
+    }
+    if index.signing_keys.is_empty() {
+        return Err("The repository has no signing keys".to_string());
+    }
+    for (i, key) in index.signing_keys.iter().enumerate() {
+        if key.key_id.trim().is_empty() {
+            return Err("A signing key has no key ID".to_string());
+        }
+        if index.signing_keys[..i].iter().any(|k| k.key_id == key.key_id) {
+            return Err(format!("Signing key '{}' is listed twice", key.key_id));
+        }
+        key.public_key
+            .validate()
+            .map_err(|e| format!("Signing key '{}' is invalid: {}", key.key_id, e))?;
+    }
+
+    for i in 0..index.plugins.len() {
+        let plugin = &index.plugins[i];
+        if !zpe::is_valid_plugin_id(&plugin.id) {
+            return Err(format!("Invalid plugin ID '{}'", plugin.id));
+        }
+        if index.plugins[..i].iter().any(|p| p.id == plugin.id) {
+            return Err(format!("Plugin '{}' is listed twice", plugin.id));
+        }
+        if plugin.versions.is_empty() {
+            return Err(format!("Plugin '{}' has no versions", plugin.id));
+        }
+
+        let plugin = &mut index.plugins[i];
+        for version in &mut plugin.versions {
+            if !zpe::is_valid_version(&version.version) {
+                return Err(format!("Plugin '{}' has an invalid version '{}'", plugin.id, version.version));
+            }
+            if let Some(min) = version.min_app_version.as_deref() {
+                if zpe::parse_version(min).is_none() {
+                    return Err(format!("Plugin '{}' {} has an invalid minAppVersion '{}'", plugin.id, version.version, min));
+                }
+            }
+            if !is_sha256(&version.sha256) {
+                return Err(format!("Plugin '{}' {} has an invalid sha256", plugin.id, version.version));
+            }
+            version.sha256 = version.sha256.to_ascii_lowercase();
+
+            let url = index_url
+                .join(&version.url)
+                .map_err(|e| format!("Plugin '{}' {} has an invalid URL: {}", plugin.id, version.version, e))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(format!("Plugin '{}' {} must be downloaded over http or https", plugin.id, version.version));
+            }
+            version.url = url.to_string();
+        }
+    }
+    Ok(index)
+}
+
+/// Keys to pin for a newly added repository. A key ID may not name a different key than
+/// one already known (trusted or pinned by another repository), and `expected_key_id`
+/// lets the user require the key they were told about.
+fn keys_to_pin(
+    index: &RepositoryIndex,
+    known: &[TrustedKey],
+    expected_key_id: Option<&str>,
+    now: i64,
+) -> Result<Vec<TrustedKey>, String> {
+    if let Some(expected) = expected_key_id {
+        if !index.signing_keys.iter().any(|k| k.key_id == expected) {
+            return Err(format!("The repository is not signed with key '{}'", expected));
+        }
+    }
+    for key in &index.signing_keys {
+        if known.iter().any(|k| k.key_id == key.key_id && k.public_key != key.public_key) {
+            return Err(format!("Key ID '{}' already belongs to a different key", key.key_id));
+        }
+    }
+    Ok(index
+        .signing_keys
+        .iter()
+        .map(|key| TrustedKey {
+            added_at: now,
+            ..key.clone()
+        })
+        .collect())
+}
+
+/// Check a refreshed index's signing keys against the pinned ones
+fn check_pinned_keys(pinned: &[TrustedKey], index: &RepositoryIndex) -> Result<(), String> {
+    for key in &index.signing_keys {
+        match pinned.iter().find(|k| k.key_id == key.key_id) {
+            Some(pinned) if pinned.public_key == key.public_key => {}
+            Some(_) => {
+                return Err(format!(
+                    "Signing key '{}' changed; remove and add the repository again to trust the new key",
+                    key.key_id
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "The repository added signing key '{}'; remove and add the repository again to trust it",
+                    key.key_id
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Catalog
+// =============================================================================
+
+/// Whether a version runs on the given app version
+fn is_compatible(version: &IndexVersion, app_version: &str) -> bool {
+    match version.min_app_version.as_deref() {
+        Some(min) => zpe::parse_version(min) <= zpe::parse_version(app_version),
+        None => true,
+    }
+}
+
+/// Highest version of a plugin compatible with the app
+fn latest_version<'a>(plugin: &'a IndexPlugin, app_version: &str) -> Option<&'a IndexVersion> {
+    plugin
+        .versions
+        .iter()
+        .filter(|v| is_compatible(v, app_version))
+        .max_by(|a, b| compare_versions(&a.version, &b.version).unwrap_or(Ordering::Equal))
+}
+
+/// A repository with its cached index
+type Source<'a> = (&'a PluginRepository, &'a RepositoryIndex);
+
+/// A plugin release picked from the repositories
+struct Release<'a> {
+    repository: &'a PluginRepository,
+    plugin: &'a IndexPlugin,
+    version: &'a IndexVersion,
+}
+
+/// The best release of a plugin: the highest compatible version, from the repository
+/// added first on a tie (or only from `repository_id`)
+fn find_release<'a>(
+    sources: &[Source<'a>],
+    plugin_id: &str,
+    repository_id: Option<&str>,
+    app_version: &str,
+) -> Option<Release<'a>> {
+    let mut best: Option<Release<'a>> = None;
+    for (repository, index) in sources {
+        if repository_id.is_some_and(|id| id != repository.id) {
+            continue;
+        }
+        let Some(plugin) = index.plugins.iter().find(|p| p.id == plugin_id) else {
+            continue;
+        };
+        let Some(version) = latest_version(plugin, app_version) else {
+            continue;
+        };
+        let better = best
+            .as_ref()
+            .map_or(true, |b| compare_versions(&version.version, &b.version.version) == Some(Ordering::Greater));
+        if better {
+            best = Some(Release {
+                repository,
+                plugin,
+                version,
+            });
+        }
+    }
+    best
+}
+
+/// Merge the repositories into one catalog, marking installed and updatable plugins
+fn merge_available(sources: &[Source], installed: &[InstalledPlugin], app_version: &str) -> Vec<AvailablePlugin> {
+    let mut ids: Vec<&str> = sources
+        .iter()
+        .flat_map(|(_, index)| index.plugins.iter().map(|p| p.id.as_str()))
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut available: Vec<AvailablePlugin> = ids
+        .into_iter()
+        .filter_map(|id| find_release(sources, id, None, app_version))
+        .map(|release| {
+            let installed_version = installed
+                .iter()
+                .find(|p| p.id == release.plugin.id)
+                .map(|p| p.current.manifest.version.clone());
+            let update_available = installed_version.as_deref().is_some_and(|current| {
+                compare_versions(&release.version.version, current) == Some(Ordering::Greater)
+            });
+            AvailablePlugin {
+                id: release.plugin.id.clone(),
+                name: release.plugin.name.clone(),
+                description: release.plugin.description.clone(),
+                plugin_type: release.plugin.plugin_type.clone(),
+                author: release.plugin.author.clone(),
+                icon_url: release.plugin.icon_url.clone(),
+                homepage: release.plugin.homepage.clone(),
+                version: release.version.version.clone(),
+                release_notes: release.version.release_notes.clone(),
+                published_at: release.version.published_at.clone(),
+                repository_id: release.repository.id.clone(),
+                repository_name: release.repository.name.clone(),
+                installed_version,
+                update_available,
+            }
+        })
+        .collect();
+    available.sort_by_key(|p| (p.name.to_lowercase(), p.id.clone()));
+    available
+}
+
+/// Check a downloaded package against the index and the repository's pinned keys
+fn verify_package(
+    release: &Release,
+    data: &[u8],
+    installed: Option<&InstalledPlugin>,
+) -> Result<zpe::VerifiedPackage, String> {
+    let sha256 = zpe::sha256_hex(data);
+    if sha256 != release.version.sha256 {
+        return Err(format!("Checksum mismatch (expected {}, got {})", release.version.sha256, sha256));
+    }
+
+    let options = VerifyOptions::default();
+    let verified = zpe::verify(data, &release.repository.keys, &options).map_err(|e| e.to_string())?;
+    if verified.manifest.id != release.plugin.id {
+        return Err(format!("Package is for plugin '{}'", verified.manifest.id));
+    }
+    if compare_versions(&verified.manifest.version, &release.version.version) != Some(Ordering::Equal) {
+        return Err(format!(
+            "Package version {} doesn't match the index ({})",
+            verified.manifest.version, release.version.version
+        ));
+    }
+
+    if let Some(installed) = installed {
+        let current = &installed.current;
+        if current.signed_by.is_some() && current.signed_by != verified.signed_by {
+            return Err(format!(
+                "The installed plugin is signed by {}, the repository's package by {}",
+                current.signed_by.as_deref().unwrap_or_default(),
+                verified.signed_by.as_deref().unwrap_or("nobody")
+            ));
+        }
+        if compare_versions(&current.manifest.version, &verified.manifest.version) == Some(Ordering::Greater) {
+            return Err(format!(
+                "Plugin '{}' {} is newer than the repository's {}",
+                installed.id, current.manifest.version, verified.manifest.version
+            ));
+        }
+    }
+    Ok(verified)
+}
+
+// =============================================================================
+// Downloads
+// =============================================================================
+
+/// Fetches indexes and packages
+struct RepoClient {
+    client: reqwest::Client,
+}
+
+impl RepoClient {
+    fn new() -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(RepoClient { client })
+    }
+
+    async fn get(&self, url: &str, max_bytes: usize) -> Result<Vec<u8>, String> {
+        let mut response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("Request to {} failed: HTTP {}", url, response.status().as_u16()));
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| format!("Failed to read {}: {}", url, e))? {
+            if body.len() + chunk.len() > max_bytes {
+                return Err(format!("Download is larger than {} bytes", max_bytes));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    async fn fetch_index(&self, url: &Url) -> Result<RepositoryIndex, String> {
+        let data = self.get(url.as_str(), MAX_INDEX_BYTES).await?;
+        parse_index(&data, url)
+    }
+}
+
+// =============================================================================
+// Persistence
+// =============================================================================
+
+fn repos_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(REPOS_DIR))
+        .map_err(|e| format!("Failed to resolve app data folder: {}", e))
+}
+
+/// Load the repositories and their cached indexes if not already loaded
+fn ensure_repos_loaded(app: &AppHandle, state: &PluginRepoState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    let repositories = app
+        .store(REPOS_STORE_FILE)
+        .ok()
+        .and_then(|store| store.get(REPOSITORIES_KEY))
+        .and_then(|value| serde_json::from_value::<Vec<PluginRepository>>(value).ok())
+        .unwrap_or_default();
+
+    if let Ok(dir) = repos_dir(app) {
+        let mut indexes = state.indexes.lock().unwrap();
+        for repository in &repositories {
+            let path = dir.join(format!("{}.json", repository.id));
+            let index = std::fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice::<RepositoryIndex>(&data).ok());
+            match index {
+                Some(index) => {
+                    indexes.insert(repository.id.clone(), index);
+                }
+                None => log::warn!("No cached index for plugin repository {}", repository.url),
+            }
+        }
+    }
+    *state.repositories.lock().unwrap() = repositories;
+
+    *loaded = true;
+}
+
+/// Persist the repository list
+fn save_repos_to_store(app: &AppHandle, repositories: &[PluginRepository]) -> Result<(), String> {
+    let store = app
+        .store(REPOS_STORE_FILE)
+        .map_err(|e| format!("Failed to open plugin repository store: {}", e))?;
+    store.set(REPOSITORIES_KEY, serde_json::to_value(repositories).unwrap_or_default());
+    store
+        .save()
+        .map_err(|e| format!("Failed to save plugin repository store: {}", e))
+}
+
+/// Write a repository's index to the cache
+fn cache_index(app: &AppHandle, repository_id: &str, index: &RepositoryIndex) -> Result<(), String> {
+    let dir = repos_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.json", repository_id));
+    let staging = path.with_extension("json.tmp");
+    let data = serde_json::to_vec(index).map_err(|e| format!("Failed to serialize index: {}", e))?;
+    std::fs::write(&staging, data)
+        .and_then(|_| std::fs::rename(&staging, &path))
+        .map_err(|e| format!("Failed to cache index: {}", e))
+}
+
+fn emit_repositories(app: &AppHandle, state: &PluginRepoState) {
+    if let Ok(repositories) = state.repositories.lock() {
+        let _ = app.emit(PLUGIN_REPOS_EVENT, &*repositories);
+    }
+}
+
+/// Signing keys pinned by all added repositories
+pub fn pinned_keys(app: &AppHandle) -> Vec<TrustedKey> {
+    let state = app.state::<PluginRepoState>();
+    ensure_repos_loaded(app, &state);
+    let keys = state
+        .repositories
+        .lock()
+        .map(|repositories| repositories.iter().flat_map(|r| r.keys.clone()).collect())
+        .unwrap_or_default();
+    keys
+}
+
+/// Fetch a repository's index and update its record; the cached index is kept on errors
+async fn refresh_repository(app: &AppHandle, client: &RepoClient, repository: &mut PluginRepository) {
+    let url = match parse_http_url(&repository.url) {
+        Ok(url) => url,
+        Err(e) => {
+            repository.error = Some(e);
+            return;
+        }
+    };
+    let result = client.fetch_index(&url).await.and_then(|index| {
+        check_pinned_keys(&repository.keys, &index)?;
+        cache_index(app, &repository.id, &index)?;
+        Ok(index)
+    });
+
+    match result {
+        Ok(index) => {
+            repository.name = index.name.clone();
+            repository.description = index.description.clone();
+            repository.plugin_count = index.plugins.len();
+            repository.refreshed_at = Some(get_current_timestamp());
+            repository.error = None;
+            if let Ok(mut indexes) = app.state::<PluginRepoState>().indexes.lock() {
+                indexes.insert(repository.id.clone(), index);
+            }
+        }
+        Err(e) => {
+            log::warn!("Failed to refresh plugin repository {}: {}", repository.url, e);
+            repository.error = Some(e);
+        }
+    }
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// List added repositories
+#[tauri::command]
+pub fn plugin_repo_list(
+    app: AppHandle,
+    state: State<'_, PluginRepoState>,
+) -> Result<Vec<PluginRepository>, String> {
+    ensure_repos_loaded(&app, &state);
+    state
+        .repositories
+        .lock()
+        .map(|repositories| repositories.clone())
+        .map_err(|e| format!("Failed to lock plugin repositories: {}", e))
+}
+
+/// Add a repository by the URL of its index, pinning its signing keys. `key_id`
+/// optionally names a key the repository must be signed with.
+#[tauri::command]
+pub async fn plugin_repo_add(url: String, key_id: Option<String>, app: AppHandle) -> Result<PluginRepository, String> {
+    let state = app.state::<PluginRepoState>();
+    ensure_repos_loaded(&app, &state);
+    let url = parse_http_url(&url)?;
+    let id = repository_id(url.as_str());
+    let exists = state
+        .repositories
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin repositories: {}", e))?
+        .iter()
+        .any(|r| r.id == id);
+    if exists {
+        return Err(format!("Repository {} is already added", url));
+    }
+
+    let index = RepoClient::new()?.fetch_index(&url).await?;
+    let now = get_current_timestamp();
+    let keys = keys_to_pin(&index, &plugins::verification_keys(&app), key_id.as_deref(), now)?;
+    cache_index(&app, &id, &index)?;
+
+    let repository = PluginRepository {
+        id: id.clone(),
+        url: url.to_string(),
+        name: index.name.clone(),
+        description: index.description.clone(),
+        keys,
+        plugin_count: index.plugins.len(),
+        added_at: now,
+        refreshed_at: Some(now),
+        error: None,
+    };
+    {
+        let mut repositories = state
+            .repositories
+            .lock()
+            .map_err(|e| format!("Failed to lock plugin repositories: {}", e))?;
+        if repositories.iter().any(|r| r.id == id) {
+            return Err(format!("Repository {} is already added", url));
+        }
+        repositories.push(repository.clone());
+        if let Err(e) = save_repos_to_store(&app, &repositories) {
+            repositories.retain(|r| r.id != id);
+            return Err(e);
+        }
+    }
+    state
+        .indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin indexes: {}", e))?
+        .insert(id, index);
+
+    log::info!("Added plugin repository {} ({})", repository.name, repository.url);
+    emit_repositories(&app, &state);
+    Ok(repository)
+}
+
+/// Remove a repository and its pinned keys; returns whether it was added
+#[tauri::command]
+pub fn plugin_repo_remove(
+    repository_id: String,
+    app: AppHandle,
+    state: State<'_, PluginRepoState>,
+) -> Result<bool, String> {
+    ensure_repos_loaded(&app, &state);
+    {
+        let mut repositories = state
+            .repositories
+            .lock()
+            .map_err(|e| format!("Failed to lock plugin repositories: {}", e))?;
+        let Some(position) = repositories.iter().position(|r| r.id == repository_id) else {
+            return Ok(false);
+        };
+        let removed = repositories.remove(position);
+        if let Err(e) = save_repos_to_store(&app, &repositories) {
+            repositories.insert(position, removed);
+            return Err(e);
+        }
+        log::info!("Removed plugin repository {}", removed.url);
+    }
+
+    if let Ok(mut indexes) = state.indexes.lock() {
+        indexes.remove(&repository_id);
+    }
+    let path = repos_dir(&app)?.join(format!("{}.json", repository_id));
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            log::warn!("Failed to remove cached index {}: {}", path.display(), e);
+        }
+    }
+    emit_repositories(&app, &state);
+    Ok(true)
+}
+
+/// Refresh one repository, or all of them
+#[tauri::command]
+pub async fn plugin_repo_refresh(
+    repository_id: Option<String>,
+    app: AppHandle,
+) -> Result<Vec<PluginRepository>, String> {
+    let state = app.state::<PluginRepoState>();
+    ensure_repos_loaded(&app, &state);
+    let mut targets: Vec<PluginRepository> = state
+        .repositories
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin repositories: {}", e))?
+        .iter()
+        .filter(|r| repository_id.as_ref().map_or(true, |id| &r.id == id))
+        .cloned()
+        .collect();
+    if let Some(id) = &repository_id {
+        if targets.is_empty() {
+            return Err(format!("Repository '{}' is not added", id));
+        }
+    }
+
+    let client = RepoClient::new()?;
+    for repository in &mut targets {
+        refresh_repository(&app, &client, repository).await;
+    }
+
+    {
+        let mut repositories = state
+            .repositories
+            .lock()
+            .map_err(|e| format!("Failed to lock plugin repositories: {}", e))?;
+        // Repositories removed meanwhile stay removed
+        for repository in repositories.iter_mut() {
+            if let Some(refreshed) = targets.iter().find(|t| t.id == repository.id) {
+                *repository = refreshed.clone();
+            }
+        }
+        save_repos_to_store(&app, &repositories)?;
+    }
+    emit_repositories(&app, &state);
+    Ok(targets)
+}
+
+/// List the plugins offered by the added repositories (from the cached indexes)
+#[tauri::command]
+pub fn plugin_repo_available(
+    updates_only: Option<bool>,
+    app: AppHandle,
+    state: State<'_, PluginRepoState>,
+) -> Result<Vec<AvailablePlugin>, String> {
+    ensure_repos_loaded(&app, &state);
+    let repositories = state
+        .repositories
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin repositories: {}", e))?
+        .clone();
+    let indexes = state
+        .indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin indexes: {}", e))?
+        .clone();
+    let sources: Vec<Source> = repositories
+        .iter()
+        .filter_map(|r| indexes.get(&r.id).map(|index| (r, index)))
+        .collect();
+
+    let mut available = merge_available(&sources, &plugins::installed_plugins(&app), AYOTO_VERSION);
+    if updates_only.unwrap_or(false) {
+        available.retain(|p| p.update_available);
+    }
+    Ok(available)
+}
+
+/// Install (or update) a plugin from the repositories; `repository_id` picks a
+/// repository when several offer the plugin
+#[tauri::command]
+pub async fn plugin_repo_install(
+    plugin_id: String,
+    repository_id: Option<String>,
+    app: AppHandle,
+) -> Result<PluginSummary, String> {
+    let state = app.state::<PluginRepoState>();
+    ensure_repos_loaded(&app, &state);
+    let repositories = state
+        .repositories
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin repositories: {}", e))?
+        .clone();
+    let indexes = state
+        .indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin indexes: {}", e))?
+        .clone();
+    let sources: Vec<Source> = repositories
+        .iter()
+        .filter_map(|r| indexes.get(&r.id).map(|index| (r, index)))
+        .collect();
+    let release = find_release(&sources, &plugin_id, repository_id.as_deref(), AYOTO_VERSION)
+        .ok_or_else(|| format!("No repository offers a compatible version of plugin '{}'", plugin_id))?;
+
+    let data = RepoClient::new()?.get(&release.version.url, zpe::MAX_PACKAGE_BYTES).await?;
+    let installed = plugins::get_plugin(&app, &plugin_id);
+    verify_package(&release, &data, installed.as_ref())?;
+
+    let dir = repos_dir(&app)?.join("downloads");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}-{}.zpe", plugin_id, release.version.version));
+    std::fs::write(&path, &data).map_err(|e| format!("Failed to save download: {}", e))?;
+
+    let source = PluginSource::Zpe {
+        path: path.to_string_lossy().to_string(),
+        allow_unsigned: false,
+    };
+    let result = plugins::install_with_keys(&app, source, &release.repository.keys);
+    let _ = std::fs::remove_file(&path);
+    let summary = result?;
+    log::info!(
+        "Installed plugin {} {} from repository {}",
+        plugin_id, release.version.version, release.repository.name
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zpe::test_support::{trusted_keys, SIGNED, UNSIGNED};
+    use serde_json::{json, Value};
+
+    const INDEX_URL: &str = "https://plugins.example.com/index.json";
+
+    fn index_json(keys: Value, plugins: Value) -> Vec<u8> {
+        json!({ "name": "Example Plugins", "signingKeys": keys, "plugins": plugins })
+            .to_string()
+            .into_bytes()
+    }
+
+    fn fixture_entry(versions: Value) -> Value {
+        json!({ "id": "fixture-provider", "name": "Fixture Provider", "versions": versions })
+    }
+
+    fn version(version: &str, min_app_version: Option<&str>) -> Value {
+        json!({
+            "version": version,
+            "url": format!("packages/fixture-provider-{}.zpe", version),
+            "sha256": zpe::sha256_hex(SIGNED),
+            "minAppVersion": min_app_version
+        })
+    }
+
+    fn repository(id: &str, index: &RepositoryIndex) -> PluginRepository {
+        PluginRepository {
+            id: id.to_string(),
+            url: format!("https://{}.example.com/index.json", id),
+            name: id.to_string(),
+            description: None,
+            keys: index.signing_keys.clone(),
+            plugin_count: index.plugins.len(),
+            added_at: 0,
+            refreshed_at: None,
+            error: None,
+        }
+    }
+
+    fn installed(version: &str, signed_by: Option<&str>) -> InstalledPlugin {
+        serde_json::from_value(json!({
+            "id": "fixture-provider",
+            "current": {
+                "format": "zpe",
+                "manifest": {
+                    "id": "fixture-provider", "name": "Fixture Provider", "version": version,
+                    "pluginType": "media-provider"
+                },
+                "sha256": "", "signedBy": signed_by, "installedAt": 0
+            },
+            "enabled": true,
+            "installedAt": 0,
+            "updatedAt": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let url = Url::parse(INDEX_URL).unwrap();
+        let keys = json!(trusted_keys());
+        let index = parse_index(&index_json(keys.clone(), json!([fixture_entry(json!([version("1.2.0", None)]))])), &url).unwrap();
+        assert_eq!(index.format_version, 1);
+        assert_eq!(
+            index.plugins[0].versions[0].url,
+            "https://plugins.example.com/packages/fixture-provider-1.2.0.zpe"
+        );
+
+        assert_eq!(
+            parse_index(&index_json(json!([]), json!([])), &url).unwrap_err(),
+            "The repository has no signing keys"
+        );
+        let duplicate = json!([fixture_entry(json!([version("1.2.0", None)])), fixture_entry(json!([version("1.0.0", None)]))]);
+        assert_eq!(
+            parse_index(&index_json(keys.clone(), duplicate), &url).unwrap_err(),
+            "Plugin 'fixture-provider' is listed twice"
+        );
+        let bad_sha = json!([fixture_entry(json!([{ "version": "1.2.0", "url": "a.zpe", "sha256": "abc" }]))]);
+        assert!(parse_index(&index_json(keys.clone(), bad_sha), &url).unwrap_err().ends_with("invalid sha256"));
+        let bad_scheme = json!([fixture_entry(json!([{ "version": "1.2.0", "url": "file:///etc/passwd", "sha256": zpe::sha256_hex(SIGNED) }]))]);
+        assert!(parse_index(&index_json(keys, bad_scheme), &url).is_err());
+    }
+
+    #[test]
+    fn test_key_pinning() {
+        let url = Url::parse(INDEX_URL).unwrap();
+        let index = parse_index(&index_json(json!(trusted_keys()), json!([])), &url).unwrap();
+        let key_id = trusted_keys()[0].key_id.clone();
+
+        let pinned = keys_to_pin(&index, &[], Some(&key_id), 42).unwrap();
+        assert_eq!(pinned[0].added_at, 42);
+        assert!(check_pinned_keys(&pinned, &index).is_ok());
+        assert_eq!(
+            keys_to_pin(&index, &[], Some("other-key"), 42).unwrap_err(),
+            "The repository is not signed with key 'other-key'"
+        );
+
+        // The same key ID naming another key (swapped coordinates)
+        let mut changed = index.clone();
+        let key = &mut changed.signing_keys[0].public_key;
+        std::mem::swap(&mut key.x, &mut key.y);
+        assert!(keys_to_pin(&changed, &pinned, None, 42).is_err());
+        assert!(check_pinned_keys(&pinned, &changed).unwrap_err().starts_with("Signing key"));
+
+        let mut added = index.clone();
+        added.signing_keys[0].key_id = "rotated-key".to_string();
+        assert!(check_pinned_keys(&pinned, &added).unwrap_err().contains("added signing key 'rotated-key'"));
+    }
+
+    #[test]
+    fn test_merge_available() {
+        let url = Url::parse(INDEX_URL).unwrap();
+        let keys = json!(trusted_keys());
+        let first = parse_index(
+            &index_json(keys.clone(), json!([fixture_entry(json!([version("1.0.0", None), version("1.2.0", None)]))])),
+            &url,
+        )
+        .unwrap();
+        // A newer version that needs a future app version
+        let second = parse_index(
+            &index_json(keys, json!([fixture_entry(json!([version("1.1.0", None), version("2.0.0", Some("99.0.0"))]))])),
+            &url,
+        )
+        .unwrap();
+        let (a, b) = (repository("first", &first), repository("second", &second));
+        let sources = [(&b, &second), (&a, &first)];
+
+        let available = merge_available(&sources, &[], "2.5.0");
+        assert_eq!(available.len(), 1);
+        assert_eq!(available[0].version, "1.2.0");
+        assert_eq!(available[0].repository_id, "first");
+        assert!(!available[0].update_available);
+
+        let available = merge_available(&sources, &[installed("1.1.0", None)], "2.5.0");
+        assert_eq!(available[0].installed_version.as_deref(), Some("1.1.0"));
+        assert!(available[0].update_available);
+
+        assert_eq!(merge_available(&sources, &[], "100.0.0")[0].version, "2.0.0");
+        let release = find_release(&sources, "fixture-provider", Some("second"), "2.5.0").unwrap();
+        assert_eq!(release.version.version, "1.1.0");
+    }
+
+    #[test]
+    fn test_verify_package() {
+        let url = Url::parse(INDEX_URL).unwrap();
+        let index = parse_index(
+            &index_json(json!(trusted_keys()), json!([fixture_entry(json!([version("1.2.0", None)]))])),
+            &url,
+        )
+        .unwrap();
+        let repo = repository("example", &index);
+        let key_id = trusted_keys()[0].key_id.clone();
+        let release = |repository| Release {
+            repository,
+            plugin: &index.plugins[0],
+            version: &index.plugins[0].versions[0],
+        };
+
+        let verified = verify_package(&release(&repo), SIGNED, Some(&installed("1.0.0", Some(&key_id)))).unwrap();
+        assert_eq!(verified.signed_by.as_deref(), Some(key_id.as_str()));
+        assert!(verify_package(&release(&repo), UNSIGNED, None)
+            .unwrap_err()
+            .starts_with("Checksum mismatch"));
+
+        // Only the repository's own keys are accepted
+        let mut unpinned = repo.clone();
+        unpinned.keys.clear();
+        assert!(verify_package(&release(&unpinned), SIGNED, None).is_err());
+
+        assert_eq!(
+            verify_package(&release(&repo), SIGNED, Some(&installed("1.0.0", Some("other-key")))).unwrap_err(),
+            format!("The installed plugin is signed by other-key, the repository's package by {}", key_id)
+        );
+        assert!(verify_package(&release(&repo), SIGNED, Some(&installed("1.3.0", None)))
+            .unwrap_err()
+            .contains("is newer than"));
+    }
+}
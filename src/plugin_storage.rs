@@ -0,0 +1,543 @@
+//! Plugin Storage
+//!
+//! Persistent key-value storage for plugins (`context.storage`), kept in one JSON file
+//! per plugin in the app data folder instead of the webview's localStorage:
+//!
+//! - each plugin has its own namespace and a quota (`config.storageQuotaBytes` in the
+//!   manifest, 5 MiB by default), counted as key plus JSON value bytes
+//! - entries can expire (`ttlMs`), for cached scrape results; expired entries are
+//!   dropped when read and before quota checks
+//! - `storage:local` is required for persistent entries; plugins with only
+//!   `storage:cache` can store entries that expire (within a day by default)
+//! - a plugin's storage is deleted when it is uninstalled
+//! - the storage commands act for the plugin their session belongs to (issued by
+//!   `plugin_load`, see `plugins::PluginSessions`), so one plugin can't name another
+//!   plugin's storage; the plugin host runs operations for the plugin it hosts
+
+use crate::plugins::{self, InstalledPlugin};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Folder of the storage files in the app data folder
+const STORAGE_DIR: &str = "plugin-storage";
+
+/// Quota when the manifest doesn't set `config.storageQuotaBytes`
+pub const DEFAULT_QUOTA_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Largest quota a manifest can ask for
+pub const MAX_QUOTA_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Longest key accepted
+const MAX_KEY_LENGTH: usize = 512;
+
+/// Lifetime of entries written by plugins with only `storage:cache`
+const CACHE_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A stored value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredEntry {
+    value: Value,
+    /// Expiry time (ms since epoch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
+}
+
+impl StoredEntry {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Storage file contents
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StorageFile {
+    #[serde(default)]
+    entries: BTreeMap<String, StoredEntry>,
+}
+
+/// Storage usage of a plugin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    pub used: u64,
+    pub max: u64,
+    pub keys: usize,
+    pub percentage: f64,
+}
+
+/// A storage operation
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum StorageOp {
+    Get {
+        key: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Set {
+        key: String,
+        #[serde(default)]
+        value: Value,
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+    },
+    Remove {
+        key: String,
+    },
+    Clear,
+    Keys,
+    Usage,
+}
+
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Bytes an entry counts towards the quota
+fn entry_size(key: &str, entry: &StoredEntry) -> u64 {
+    (key.len() + entry.value.to_string().len()) as u64
+}
+
+// =============================================================================
+// Plugin Store
+// =============================================================================
+
+/// The storage of one plugin
+#[derive(Debug)]
+pub struct PluginStore {
+    path: PathBuf,
+    quota: u64,
+    entries: BTreeMap<String, StoredEntry>,
+    used: u64,
+}
+
+impl PluginStore {
+    /// Open a storage file (a missing or unreadable file starts empty)
+    pub fn open(path: PathBuf, quota: u64, now: i64) -> Self {
+        let entries = match std::fs::read(&path) {
+            Ok(data) => match serde_json::from_slice::<StorageFile>(&data) {
+                Ok(file) => file.entries,
+                Err(e) => {
+                    log::warn!("Ignoring unreadable plugin storage {}: {}", path.display(), e);
+                    BTreeMap::new()
+                }
+            },
+            Err(_) => BTreeMap::new(),
+        };
+        let mut store = PluginStore {
+            path,
+            quota,
+            entries,
+            used: 0,
+        };
+        store.prune(now);
+        store
+    }
+
+    /// Drop expired entries, returning how many were removed
+    pub fn prune(&mut self, now: i64) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !entry.is_expired(now));
+        self.used = self.entries.iter().map(|(key, entry)| entry_size(key, entry)).sum();
+        before - self.entries.len()
+    }
+
+    pub fn get(&self, key: &str, now: i64) -> Option<Value> {
+        self.entries
+            .get(key)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value.clone())
+    }
+
+    pub fn set(&mut self, key: &str, value: Value, ttl_ms: Option<u64>, now: i64) -> Result<(), String> {
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return Err(format!("Storage keys must be 1 to {} bytes long", MAX_KEY_LENGTH));
+        }
+        self.prune(now);
+
+        let entry = StoredEntry {
+            value,
+            expires_at: ttl_ms.map(|ttl| now.saturating_add(ttl.min(i64::MAX as u64) as i64)),
+        };
+        let size = entry_size(key, &entry);
+        let replaced = self.entries.get(key).map_or(0, |old| entry_size(key, old));
+        let used = self.used - replaced + size;
+        if used > self.quota {
+            return Err(format!(
+                "Storage quota exceeded ({} of {} bytes used)",
+                self.used, self.quota
+            ));
+        }
+
+        let previous = self.entries.insert(key.to_string(), entry);
+        if let Err(e) = self.save() {
+            match previous {
+                Some(previous) => self.entries.insert(key.to_string(), previous),
+                None => self.entries.remove(key),
+            };
+            return Err(e);
+        }
+        self.used = used;
+        Ok(())
+    }
+
+    /// Remove a key, returning whether it existed
+    pub fn remove(&mut self, key: &str) -> Result<bool, String> {
+        let Some(entry) = self.entries.remove(key) else {
+            return Ok(false);
+        };
+        self.used -= entry_size(key, &entry);
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.entries.clear();
+        self.used = 0;
+        self.save()
+    }
+
+    pub fn keys(&self, now: i64) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    pub fn usage(&self) -> StorageUsage {
+        StorageUsage {
+            used: self.used,
+            max: self.quota,
+            keys: self.entries.len(),
+            percentage: if self.quota == 0 {
+                100.0
+            } else {
+                self.used as f64 / self.quota as f64 * 100.0
+            },
+        }
+    }
+
+    /// Write the storage file (removing it when empty)
+    fn save(&self) -> Result<(), String> {
+        if self.entries.is_empty() {
+            if self.path.exists() {
+                std::fs::remove_file(&self.path).map_err(|e| format!("Failed to save plugin storage: {}", e))?;
+            }
+            return Ok(());
+        }
+
+        let file = StorageFile {
+            entries: self.entries.clone(),
+        };
+        let data = serde_json::to_vec(&file).map_err(|e| format!("Failed to save plugin storage: {}", e))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to save plugin storage: {}", e))?;
+        }
+        let staging = self.path.with_extension("json.tmp");
+        std::fs::write(&staging, data)
+            .and_then(|_| std::fs::rename(&staging, &self.path))
+            .map_err(|e| format!("Failed to save plugin storage: {}", e))
+    }
+}
+
+// =============================================================================
+// State
+// =============================================================================
+
+/// Plugin storage state: open stores by plugin ID
+#[derive(Default)]
+pub struct PluginStorageState {
+    stores: Mutex<HashMap<String, PluginStore>>,
+}
+
+fn storage_path(app: &AppHandle, plugin_id: &str) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(STORAGE_DIR).join(format!("{}.json", plugin_id)))
+        .map_err(|e| format!("Failed to resolve app data folder: {}", e))
+}
+
+/// Storage quota configured in a plugin's manifest
+fn quota(plugin: &InstalledPlugin) -> u64 {
+    plugin
+        .current
+        .manifest
+        .extra
+        .get("config")
+        .and_then(|config| config.get("storageQuotaBytes"))
+        .and_then(|bytes| bytes.as_u64())
+        .unwrap_or(DEFAULT_QUOTA_BYTES)
+        .min(MAX_QUOTA_BYTES)
+}
+
+/// Check a plugin's storage permissions, returning the operation to run (writes of
+/// plugins with only `storage:cache` always expire)
+fn check_permission(plugin: &InstalledPlugin, op: StorageOp) -> Result<StorageOp, String> {
+    let permissions = &plugin.current.manifest.permissions;
+    if permissions.iter().any(|p| p == "storage:local") {
+        return Ok(op);
+    }
+    if !permissions.iter().any(|p| p == "storage:cache") {
+        return Err(format!("Plugin '{}' doesn't have the storage:local permission", plugin.id));
+    }
+    Ok(match op {
+        StorageOp::Set { key, value, ttl_ms } => StorageOp::Set {
+            key,
+            value,
+            ttl_ms: Some(ttl_ms.unwrap_or(CACHE_TTL_MS).min(CACHE_TTL_MS)),
+        },
+        op => op,
+    })
+}
+
+/// Run a storage operation on a store
+pub fn apply(store: &mut PluginStore, op: StorageOp, now: i64) -> Result<Value, String> {
+    Ok(match op {
+        StorageOp::Get { key } => store.get(&key, now).unwrap_or(Value::Null),
+        StorageOp::Set { key, value, ttl_ms } => {
+            store.set(&key, value, ttl_ms, now)?;
+            Value::Null
+        }
+        StorageOp::Remove { key } => Value::Bool(store.remove(&key)?),
+        StorageOp::Clear => {
+            store.clear()?;
+            Value::Null
+        }
+        StorageOp::Keys => serde_json::to_value(store.keys(now)).unwrap_or_default(),
+        StorageOp::Usage => serde_json::to_value(store.usage()).unwrap_or_default(),
+    })
+}
+
+/// Run a storage operation for an installed plugin
+pub fn run(app: &AppHandle, plugin_id: &str, op: StorageOp) -> Result<Value, String> {
+    let plugin = plugins::get_plugin(app, plugin_id).ok_or_else(|| format!("Plugin '{}' is not installed", plugin_id))?;
+    let op = check_permission(&plugin, op)?;
+    let now = get_current_timestamp();
+
+    let state = app.state::<PluginStorageState>();
+    let mut stores = state
+        .stores
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin storage: {}", e))?;
+    let store = match stores.entry(plugin_id.to_string()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            entry.insert(PluginStore::open(storage_path(app, plugin_id)?, quota(&plugin), now))
+        }
+    };
+    // Upgrades can change the quota
+    store.quota = quota(&plugin);
+    apply(store, op, now)
+}
+
+/// Run a storage operation for the plugin a webview runtime session belongs to
+fn run_for_session(app: &AppHandle, session: &str, op: StorageOp) -> Result<Value, String> {
+    run(app, &plugins::session_plugin(app, session)?, op)
+}
+
+/// Delete a plugin's storage (after it was uninstalled)
+pub fn remove_plugin_data(app: &AppHandle, plugin_id: &str) {
+    if let Some(state) = app.try_state::<PluginStorageState>() {
+        if let Ok(mut stores) = state.stores.lock() {
+            stores.remove(plugin_id);
+        }
+    }
+    if let Ok(path) = storage_path(app, plugin_id) {
+        remove_file(&path);
+    }
+}
+
+fn remove_file(path: &Path) {
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(path) {
+            log::warn!("Failed to remove plugin storage {}: {}", path.display(), e);
+        }
+    }
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Get a value from the session's plugin storage
+#[tauri::command]
+pub fn plugin_storage_get(session: String, key: String, app: AppHandle) -> Result<Value, String> {
+    run_for_session(&app, &session, StorageOp::Get { key })
+}
+
+/// Set a value in the session's plugin storage, optionally expiring after `ttl_ms`
+#[tauri::command]
+pub fn plugin_storage_set(
+    session: String,
+    key: String,
+    value: Value,
+    ttl_ms: Option<u64>,
+    app: AppHandle,
+) -> Result<(), String> {
+    run_for_session(&app, &session, StorageOp::Set { key, value, ttl_ms }).map(|_| ())
+}
+
+/// Remove a value from the session's plugin storage
+#[tauri::command]
+pub fn plugin_storage_remove(session: String, key: String, app: AppHandle) -> Result<bool, String> {
+    run_for_session(&app, &session, StorageOp::Remove { key }).map(|removed| removed == Value::Bool(true))
+}
+
+/// Remove all values from the session's plugin storage
+#[tauri::command]
+pub fn plugin_storage_clear(session: String, app: AppHandle) -> Result<(), String> {
+    run_for_session(&app, &session, StorageOp::Clear).map(|_| ())
+}
+
+/// List the keys in the session's plugin storage
+#[tauri::command]
+pub fn plugin_storage_keys(session: String, app: AppHandle) -> Result<Vec<String>, String> {
+    let keys = run_for_session(&app, &session, StorageOp::Keys)?;
+    serde_json::from_value(keys).map_err(|e| e.to_string())
+}
+
+/// Get the session's plugin storage usage
+#[tauri::command]
+pub fn plugin_storage_usage(session: String, app: AppHandle) -> Result<StorageUsage, String> {
+    let usage = run_for_session(&app, &session, StorageOp::Usage)?;
+    serde_json::from_value(usage).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Empty folder for a test's storage
+    fn test_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zanshin-storage-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("plugin.json")
+    }
+
+    #[test]
+    fn test_values_persist() {
+        let path = test_path("persist");
+        let mut store = PluginStore::open(path.clone(), DEFAULT_QUOTA_BYTES, 0);
+        store.set("settings", json!({ "quality": "1080p" }), None, 0).unwrap();
+        store.set("token", json!("abc"), None, 0).unwrap();
+        assert!(store.remove("token").unwrap());
+        assert!(!store.remove("token").unwrap());
+
+        let store = PluginStore::open(path.clone(), DEFAULT_QUOTA_BYTES, 0);
+        assert_eq!(store.get("settings", 0), Some(json!({ "quality": "1080p" })));
+        assert_eq!(store.keys(0), vec!["settings"]);
+        assert_eq!(store.usage().used, ("settings".len() + r#"{"quality":"1080p"}"#.len()) as u64);
+
+        let mut store = store;
+        store.clear().unwrap();
+        assert!(!path.exists());
+        assert_eq!(store.set("", json!(1), None, 0).unwrap_err(), "Storage keys must be 1 to 512 bytes long");
+    }
+
+    #[test]
+    fn test_quota() {
+        let mut store = PluginStore::open(test_path("quota"), 20, 0);
+        store.set("a", json!("123456789"), None, 0).unwrap(); // 1 + 11 bytes
+        assert!(store
+            .set("b", json!("123456789"), None, 0)
+            .unwrap_err()
+            .starts_with("Storage quota exceeded"));
+        // Replacing a value only counts the difference
+        store.set("a", json!("1234567890123456"), None, 0).unwrap();
+        assert_eq!(store.usage().used, 19);
+        assert_eq!(store.get("b", 0), None);
+    }
+
+    #[test]
+    fn test_ttl() {
+        let path = test_path("ttl");
+        let mut store = PluginStore::open(path.clone(), 30, 1_000);
+        store.set("page:1", json!("cached result"), Some(500), 1_000).unwrap();
+        assert_eq!(store.get("page:1", 1_499), Some(json!("cached result")));
+        assert_eq!(store.get("page:1", 1_500), None);
+        assert!(store.keys(1_500).is_empty());
+
+        // Expired entries don't count towards the quota
+        store.set("page:2", json!("another result"), None, 1_500).unwrap();
+        let store = PluginStore::open(path, 30, 1_500);
+        assert_eq!(store.keys(1_500), vec!["page:2"]);
+    }
+
+    #[test]
+    fn test_sessions_reach_only_their_plugin() {
+        let sessions = plugins::PluginSessions::default();
+        let a = sessions.open("plugin-a").unwrap();
+        let b = sessions.open("plugin-b").unwrap();
+        let mut stores: HashMap<String, PluginStore> = ["plugin-a", "plugin-b"]
+            .into_iter()
+            .map(|id| (id.to_string(), PluginStore::open(test_path(id), DEFAULT_QUOTA_BYTES, 0)))
+            .collect();
+        // What the storage commands do: act for the session's plugin
+        let mut run = |session: &str, op: Value| -> Result<Value, String> {
+            let plugin_id = sessions.plugin(session)?;
+            apply(stores.get_mut(&plugin_id).unwrap(), serde_json::from_value(op).unwrap(), 0)
+        };
+
+        run(&a, json!({ "op": "set", "key": "token", "value": "secret" })).unwrap();
+        // Another plugin's ID is not a session
+        assert!(run("plugin-a", json!({ "op": "get", "key": "token" })).is_err());
+        // Plugin B only reaches its own storage
+        assert_eq!(run(&b, json!({ "op": "get", "key": "token" })).unwrap(), Value::Null);
+        run(&b, json!({ "op": "clear" })).unwrap();
+        assert_eq!(run(&a, json!({ "op": "get", "key": "token" })).unwrap(), json!("secret"));
+
+        run(&a, json!({ "op": "clear" })).unwrap();
+    }
+
+    #[test]
+    fn test_permissions() {
+        let plugin = |permissions: Value| -> InstalledPlugin {
+            serde_json::from_value(json!({
+                "id": "provider",
+                "current": {
+                    "format": "js",
+                    "manifest": {
+                        "id": "provider", "name": "Provider", "version": "1.0.0",
+                        "pluginType": "mediaProvider", "permissions": permissions,
+                        "config": { "storageQuotaBytes": 1u64 << 40 }
+                    },
+                    "sha256": "", "installedAt": 0
+                },
+                "enabled": true,
+                "installedAt": 0,
+                "updatedAt": 0
+            }))
+            .unwrap()
+        };
+        let set = || StorageOp::Set {
+            key: "k".to_string(),
+            value: json!(1),
+            ttl_ms: None,
+        };
+
+        assert_eq!(
+            check_permission(&plugin(json!(["network:http"])), set()).unwrap_err(),
+            "Plugin 'provider' doesn't have the storage:local permission"
+        );
+        assert!(matches!(
+            check_permission(&plugin(json!(["storage:local"])), set()).unwrap(),
+            StorageOp::Set { ttl_ms: None, .. }
+        ));
+        // Cache-only plugins can't keep entries forever
+        assert!(matches!(
+            check_permission(&plugin(json!(["storage:cache"])), set()).unwrap(),
+            StorageOp::Set { ttl_ms: Some(CACHE_TTL_MS), .. }
+        ));
+        assert_eq!(quota(&plugin(json!([]))), MAX_QUOTA_BYTES);
+    }
+}
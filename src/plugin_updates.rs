@@ -0,0 +1,909 @@
+//! Plugin Updates
+//!
+//! A background job checks installed plugins for updates, replacing the webview's
+//! `ZPEVersionChecker.js`. Plugins opt in with a GitHub `repository` in the manifest:
+//!
+//! 1. the repository's releases are listed and the highest version newer than the
+//!    installed one (semver, prereleases only if enabled) with a `.zpe` asset is picked
+//! 2. the package is downloaded and checked against the asset's SHA-256 digest (from
+//!    the GitHub API or a `<asset>.sha256` file in the release)
+//! 3. it is verified with the native ZPE verifier; it must be for the same plugin, and
+//!    if the installed version was signed, by the same key
+//! 4. the update is staged in the app data folder for the user to approve, or
+//!    installed right away when auto-apply is on
+//!
+//! The GitHub API base URL is part of the configuration, so a local mock server can
+//! stand in for GitHub.
+
+use crate::plugins::{self, InstalledPlugin, PluginSource, PluginSummary};
+use crate::zpe::{self, TrustedKey, VerifyOptions};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_http::reqwest::{self, Url};
+use tauri_plugin_store::StoreExt;
+
+/// Store file name for update settings and staged updates
+const UPDATES_STORE_FILE: &str = "plugin_updates.json";
+
+/// Store key for the configuration
+const CONFIG_KEY: &str = "config";
+
+/// Store key for staged updates
+const STAGED_KEY: &str = "staged";
+
+/// Folder of downloaded updates in the app data folder
+const UPDATES_DIR: &str = "plugin-updates";
+
+/// Event emitted when the staged updates change
+pub const PLUGIN_UPDATES_EVENT: &str = "plugin-updates-updated";
+
+/// Default GitHub API base URL
+pub const DEFAULT_GITHUB_API: &str = "https://api.github.com";
+
+/// Releases fetched per repository
+const RELEASES_PER_PAGE: u32 = 20;
+
+/// Shortest allowed interval between checks
+const MIN_CHECK_INTERVAL_HOURS: u32 = 1;
+
+/// Delay before the first check after startup
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+
+/// Timeout of GitHub API requests and downloads
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Update checker configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PluginUpdateConfig {
+    /// Whether the background job runs
+    pub enabled: bool,
+    /// Hours between two checks
+    pub check_interval_hours: u32,
+    /// Install verified updates without asking
+    pub auto_apply: bool,
+    /// Also offer prerelease versions
+    pub include_prereleases: bool,
+    /// GitHub API base URL
+    pub github_api_base: String,
+}
+
+impl Default for PluginUpdateConfig {
+    fn default() -> Self {
+        PluginUpdateConfig {
+            enabled: true,
+            check_interval_hours: 6,
+            auto_apply: false,
+            include_prereleases: false,
+            github_api_base: DEFAULT_GITHUB_API.to_string(),
+        }
+    }
+}
+
+/// A downloaded and verified update waiting to be installed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedUpdate {
+    pub plugin_id: String,
+    pub current_version: String,
+    pub version: String,
+    pub release_url: Option<String>,
+    pub release_notes: Option<String>,
+    pub published_at: Option<String>,
+    pub asset_name: String,
+    /// SHA-256 of the package (hex)
+    pub sha256: String,
+    /// Trusted key that signed the package
+    pub signed_by: Option<String>,
+    /// Downloaded package
+    pub path: String,
+    /// Staging timestamp (Unix milliseconds)
+    pub staged_at: i64,
+}
+
+/// Result of checking one plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheckResult {
+    pub plugin_id: String,
+    pub current_version: String,
+    /// Update found (staged, or installed when `applied`)
+    pub update: Option<StagedUpdate>,
+    pub applied: bool,
+    pub error: Option<String>,
+}
+
+/// `repository` field of a manifest
+#[derive(Debug, Clone, Deserialize)]
+struct GithubRepository {
+    #[serde(rename = "type")]
+    kind: String,
+    owner: String,
+    repo: String,
+}
+
+/// A GitHub release (fields used by the checker)
+#[derive(Debug, Clone, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    html_url: Option<String>,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+    /// `sha256:<hex>`, set by GitHub for newer uploads
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// Plugin update state (in-memory cache of the store)
+pub struct PluginUpdateState {
+    config: Mutex<PluginUpdateConfig>,
+    staged: Mutex<HashMap<String, StagedUpdate>>,
+    loaded: Mutex<bool>,
+    /// Prevents overlapping checks (scheduler tick vs. manual check)
+    checking: Mutex<bool>,
+}
+
+impl Default for PluginUpdateState {
+    fn default() -> Self {
+        PluginUpdateState {
+            config: Mutex::new(PluginUpdateConfig::default()),
+            staged: Mutex::new(HashMap::new()),
+            loaded: Mutex::new(false),
+            checking: Mutex::new(false),
+        }
+    }
+}
+
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Versions
+// =============================================================================
+
+/// Major, minor and patch numbers
+type VersionCore = (u64, u64, u64);
+
+/// Split a version into its numbers and prerelease part (`v` prefix and build
+/// metadata are ignored)
+fn split_version(version: &str) -> Option<(VersionCore, Option<&str>)> {
+    let version = version.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let version = version.split('+').next()?;
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+    Some((zpe::parse_version(core)?, pre))
+}
+
+/// Compare prerelease identifiers (numeric ones are lower than alphanumeric ones)
+fn compare_prerelease(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.split('.'), b.split('.'));
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Compare two versions by semver precedence (`None` if either is invalid)
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    let (a_core, a_pre) = split_version(a)?;
+    let (b_core, b_pre) = split_version(b)?;
+    Some(a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_prerelease(a, b),
+    }))
+}
+
+fn is_newer(version: &str, current: &str) -> bool {
+    compare_versions(version, current) == Some(Ordering::Greater)
+}
+
+// =============================================================================
+// Discovery and Verification
+// =============================================================================
+
+/// GitHub repository configured in a plugin's manifest
+fn repository(plugin: &InstalledPlugin) -> Option<GithubRepository> {
+    let repository = plugin.current.manifest.extra.get("repository")?;
+    let repository: GithubRepository = serde_json::from_value(repository.clone()).ok()?;
+    let valid = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (repository.kind == "github" && valid(&repository.owner) && valid(&repository.repo)).then_some(repository)
+}
+
+/// Pick the newest release with a package that is newer than the installed version
+fn pick_release<'a>(
+    releases: &'a [GithubRelease],
+    current_version: &str,
+    include_prereleases: bool,
+) -> Option<(&'a GithubRelease, &'a GithubAsset)> {
+    releases
+        .iter()
+        .filter(|release| !release.draft && (include_prereleases || !release.prerelease))
+        .filter(|release| is_newer(&release.tag_name, current_version))
+        .filter_map(|release| {
+            let asset = release.assets.iter().find(|asset| asset.name.ends_with(".zpe"))?;
+            Some((release, asset))
+        })
+        .max_by(|(a, _), (b, _)| compare_versions(&a.tag_name, &b.tag_name).unwrap_or(Ordering::Equal))
+}
+
+/// Read a SHA-256 checksum (`sha256:<hex>`, or the first word of a `sha256sum` line)
+fn parse_checksum(text: &str) -> Option<String> {
+    let text = text.trim();
+    let hex = text.strip_prefix("sha256:").unwrap_or(text).split_whitespace().next()?;
+    (hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| hex.to_ascii_lowercase())
+}
+
+/// Check a downloaded package: checksum, signature, plugin ID, version and signer
+fn verify_update(
+    plugin: &InstalledPlugin,
+    data: &[u8],
+    expected_sha256: &str,
+    keys: &[TrustedKey],
+) -> Result<zpe::VerifiedPackage, String> {
+    let sha256 = zpe::sha256_hex(data);
+    if sha256 != expected_sha256 {
+        return Err(format!("Checksum mismatch (expected {}, got {})", expected_sha256, sha256));
+    }
+
+    // A signed plugin may only be updated by a package signed with the same key
+    let options = VerifyOptions {
+        allow_unsigned: plugin.current.signed_by.is_none(),
+    };
+    let verified = zpe::verify(data, keys, &options).map_err(|e| e.to_string())?;
+    if verified.manifest.id != plugin.id {
+        return Err(format!("Package is for plugin '{}'", verified.manifest.id));
+    }
+    if !is_newer(&verified.manifest.version, &plugin.current.manifest.version) {
+        return Err(format!(
+            "Package version {} is not newer than {}",
+            verified.manifest.version, plugin.current.manifest.version
+        ));
+    }
+    if plugin.current.signed_by.is_some() && verified.signed_by != plugin.current.signed_by {
+        return Err(format!(
+            "Package is signed by {}, not by {}",
+            verified.signed_by.as_deref().unwrap_or("nobody"),
+            plugin.current.signed_by.as_deref().unwrap_or_default()
+        ));
+    }
+    Ok(verified)
+}
+
+/// Looks up and downloads updates from GitHub (or a server with the same API)
+pub struct UpdateSource {
+    client: reqwest::Client,
+    api_base: String,
+    include_prereleases: bool,
+}
+
+impl UpdateSource {
+    pub fn new(config: &PluginUpdateConfig) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(UpdateSource {
+            client,
+            api_base: config.github_api_base.trim_end_matches('/').to_string(),
+            include_prereleases: config.include_prereleases,
+        })
+    }
+
+    async fn get(&self, url: &str, accept: &str) -> Result<Vec<u8>, String> {
+        let mut response = self
+            .client
+            .get(url)
+            .header("Accept", accept)
+            .send()
+            .await
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("Request to {} failed: HTTP {}", url, response.status().as_u16()));
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| format!("Failed to read {}: {}", url, e))? {
+            if body.len() + chunk.len() > zpe::MAX_PACKAGE_BYTES {
+                return Err(format!("Download is larger than {} bytes", zpe::MAX_PACKAGE_BYTES));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    async fn releases(&self, repository: &GithubRepository) -> Result<Vec<GithubRelease>, String> {
+        let url = format!(
+            "{}/repos/{}/{}/releases?per_page={}",
+            self.api_base, repository.owner, repository.repo, RELEASES_PER_PAGE
+        );
+        let body = self.get(&url, "application/vnd.github+json").await?;
+        serde_json::from_slice(&body).map_err(|e| format!("Invalid GitHub releases response: {}", e))
+    }
+
+    /// Expected checksum of a package asset
+    async fn checksum(&self, release: &GithubRelease, asset: &GithubAsset) -> Result<String, String> {
+        if let Some(checksum) = asset.digest.as_deref().and_then(parse_checksum) {
+            return Ok(checksum);
+        }
+        let sidecar = format!("{}.sha256", asset.name);
+        let sidecar = release
+            .assets
+            .iter()
+            .find(|a| a.name == sidecar)
+            .ok_or_else(|| format!("Release {} has no checksum for {}", release.tag_name, asset.name))?;
+        let body = self.get(&sidecar.browser_download_url, "application/octet-stream").await?;
+        parse_checksum(&String::from_utf8_lossy(&body))
+            .ok_or_else(|| format!("Invalid checksum file {}", sidecar.name))
+    }
+
+    /// Look for an update of a plugin; a verified package is written to `dir`
+    pub async fn check_plugin(
+        &self,
+        plugin: &InstalledPlugin,
+        keys: &[TrustedKey],
+        dir: &Path,
+    ) -> Result<Option<StagedUpdate>, String> {
+        let Some(repository) = repository(plugin) else {
+            return Ok(None);
+        };
+        let current_version = &plugin.current.manifest.version;
+        let releases = self.releases(&repository).await?;
+        let Some((release, asset)) = pick_release(&releases, current_version, self.include_prereleases) else {
+            return Ok(None);
+        };
+
+        let checksum = self.checksum(release, asset).await?;
+        let data = self.get(&asset.browser_download_url, "application/octet-stream").await?;
+        let verified = verify_update(plugin, &data, &checksum, keys)?;
+        let version = verified.manifest.version.clone();
+
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let path = dir.join(format!("{}-{}.zpe", plugin.id, version));
+        let staging = path.with_extension("zpe.tmp");
+        std::fs::write(&staging, &data)
+            .and_then(|_| std::fs::rename(&staging, &path))
+            .map_err(|e| format!("Failed to save update: {}", e))?;
+
+        Ok(Some(StagedUpdate {
+            plugin_id: plugin.id.clone(),
+            current_version: current_version.clone(),
+            version,
+            release_url: release.html_url.clone(),
+            release_notes: release.body.clone(),
+            published_at: release.published_at.clone(),
+            asset_name: asset.name.clone(),
+            sha256: verified.sha256,
+            signed_by: verified.signed_by,
+            path: path.to_string_lossy().to_string(),
+            staged_at: get_current_timestamp(),
+        }))
+    }
+}
+
+// =============================================================================
+// Persistence
+// =============================================================================
+
+/// Load the configuration and staged updates from the store if not already loaded
+fn ensure_updates_loaded(app: &AppHandle, state: &PluginUpdateState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(UPDATES_STORE_FILE) {
+        if let Some(config) = store
+            .get(CONFIG_KEY)
+            .and_then(|value| serde_json::from_value::<PluginUpdateConfig>(value).ok())
+        {
+            *state.config.lock().unwrap() = config;
+        }
+        if let Some(staged) = store
+            .get(STAGED_KEY)
+            .and_then(|value| serde_json::from_value::<HashMap<String, StagedUpdate>>(value).ok())
+        {
+            *state.staged.lock().unwrap() = staged;
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Persist the configuration and staged updates
+fn save_updates_to_store(app: &AppHandle, state: &PluginUpdateState) -> Result<(), String> {
+    let store = app
+        .store(UPDATES_STORE_FILE)
+        .map_err(|e| format!("Failed to open plugin updates store: {}", e))?;
+
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin update config: {}", e))?
+        .clone();
+    let staged = state
+        .staged
+        .lock()
+        .map_err(|e| format!("Failed to lock staged updates: {}", e))?
+        .clone();
+    store.set(CONFIG_KEY, serde_json::to_value(config).unwrap_or_default());
+    store.set(STAGED_KEY, serde_json::to_value(staged).unwrap_or_default());
+    store
+        .save()
+        .map_err(|e| format!("Failed to save plugin updates store: {}", e))
+}
+
+fn updates_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(UPDATES_DIR))
+        .map_err(|e| format!("Failed to resolve app data folder: {}", e))
+}
+
+fn remove_package(update: &StagedUpdate) {
+    let path = Path::new(&update.path);
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(path) {
+            log::warn!("Failed to remove update {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn emit_staged(app: &AppHandle, state: &PluginUpdateState) {
+    if let Ok(staged) = state.staged.lock() {
+        let list: Vec<&StagedUpdate> = staged.values().collect();
+        let _ = app.emit(PLUGIN_UPDATES_EVENT, &list);
+    }
+}
+
+// =============================================================================
+// Checking and Applying
+// =============================================================================
+
+/// Install a staged update
+fn apply_update(app: &AppHandle, update: &StagedUpdate) -> Result<PluginSummary, String> {
+    let data = zpe::read_package(Path::new(&update.path))?;
+    if zpe::sha256_hex(&data) != update.sha256 {
+        return Err("The downloaded update was modified; check for updates again".to_string());
+    }
+    let summary = plugins::install_with_keys(
+        app,
+        PluginSource::Zpe {
+            path: update.path.clone(),
+            allow_unsigned: update.signed_by.is_none(),
+        },
+        &plugins::verification_keys(app),
+    )?;
+    remove_package(update);
+    Ok(summary)
+}
+
+/// Check all installed plugins with a repository for updates
+pub async fn check_updates(app: &AppHandle) -> Result<Vec<UpdateCheckResult>, String> {
+    let state = app.state::<PluginUpdateState>();
+    ensure_updates_loaded(app, &state);
+    {
+        let mut checking = state
+            .checking
+            .lock()
+            .map_err(|e| format!("Failed to lock update check: {}", e))?;
+        if *checking {
+            return Err("An update check is already running".to_string());
+        }
+        *checking = true;
+    }
+
+    let result = run_check(app, &state).await;
+    if let Ok(mut checking) = state.checking.lock() {
+        *checking = false;
+    }
+    result
+}
+
+async fn run_check(app: &AppHandle, state: &PluginUpdateState) -> Result<Vec<UpdateCheckResult>, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin update config: {}", e))?
+        .clone();
+    let source = UpdateSource::new(&config)?;
+    let keys = plugins::verification_keys(app);
+    let dir = updates_dir(app)?;
+    let installed = plugins::installed_plugins(app);
+
+    // Drop staged updates that no longer apply (uninstalled, or installed another way)
+    {
+        let mut staged = state
+            .staged
+            .lock()
+            .map_err(|e| format!("Failed to lock staged updates: {}", e))?;
+        staged.retain(|id, update| {
+            let current = installed.iter().find(|p| &p.id == id);
+            let keep = current.is_some_and(|p| is_newer(&update.version, &p.current.manifest.version));
+            if !keep {
+                remove_package(update);
+            }
+            keep
+        });
+    }
+
+    let mut results = Vec::new();
+    for plugin in installed.iter().filter(|p| repository(p).is_some()) {
+        let mut result = UpdateCheckResult {
+            plugin_id: plugin.id.clone(),
+            current_version: plugin.current.manifest.version.clone(),
+            update: None,
+            applied: false,
+            error: None,
+        };
+
+        match source.check_plugin(plugin, &keys, &dir).await {
+            Ok(Some(update)) if config.auto_apply => match apply_update(app, &update) {
+                Ok(_) => {
+                    log::info!("Updated plugin {} to {}", update.plugin_id, update.version);
+                    result.applied = true;
+                    if let Ok(mut staged) = state.staged.lock() {
+                        staged.remove(&plugin.id);
+                    }
+                    result.update = Some(update);
+                }
+                Err(e) => {
+                    log::warn!("Failed to apply update of plugin {}: {}", plugin.id, e);
+                    result.error = Some(e);
+                    if let Ok(mut staged) = state.staged.lock() {
+                        staged.insert(plugin.id.clone(), update.clone());
+                    }
+                    result.update = Some(update);
+                }
+            },
+            Ok(Some(update)) => {
+                log::info!("Staged update of plugin {} to {}", update.plugin_id, update.version);
+                if let Ok(mut staged) = state.staged.lock() {
+                    if let Some(old) = staged.insert(plugin.id.clone(), update.clone()) {
+                        if old.path != update.path {
+                            remove_package(&old);
+                        }
+                    }
+                }
+                result.update = Some(update);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("Update check of plugin {} failed: {}", plugin.id, e);
+                result.error = Some(e);
+            }
+        }
+        results.push(result);
+    }
+
+    save_updates_to_store(app, state)?;
+    emit_staged(app, state);
+    Ok(results)
+}
+
+/// Start the background job that checks for plugin updates
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        loop {
+            let state = app.state::<PluginUpdateState>();
+            ensure_updates_loaded(&app, &state);
+            let config = state
+                .config
+                .lock()
+                .map(|c| c.clone())
+                .unwrap_or_default();
+
+            if config.enabled {
+                if let Err(e) = check_updates(&app).await {
+                    log::warn!("Plugin update check failed: {}", e);
+                }
+            }
+
+            let hours = config.check_interval_hours.max(MIN_CHECK_INTERVAL_HOURS);
+            tokio::time::sleep(Duration::from_secs(u64::from(hours) * 3600)).await;
+        }
+    });
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Check all plugins for updates now
+#[tauri::command]
+pub async fn plugin_updates_check(app: AppHandle) -> Result<Vec<UpdateCheckResult>, String> {
+    check_updates(&app).await
+}
+
+/// List staged updates
+#[tauri::command]
+pub fn plugin_updates_list(
+    app: AppHandle,
+    state: State<'_, PluginUpdateState>,
+) -> Result<Vec<StagedUpdate>, String> {
+    ensure_updates_loaded(&app, &state);
+    let staged = state
+        .staged
+        .lock()
+        .map_err(|e| format!("Failed to lock staged updates: {}", e))?;
+    let mut list: Vec<StagedUpdate> = staged.values().cloned().collect();
+    list.sort_by(|a, b| a.plugin_id.cmp(&b.plugin_id));
+    Ok(list)
+}
+
+/// Install a staged update
+#[tauri::command]
+pub fn plugin_updates_apply(
+    plugin_id: String,
+    app: AppHandle,
+    state: State<'_, PluginUpdateState>,
+) -> Result<PluginSummary, String> {
+    ensure_updates_loaded(&app, &state);
+    let update = state
+        .staged
+        .lock()
+        .map_err(|e| format!("Failed to lock staged updates: {}", e))?
+        .get(&plugin_id)
+        .cloned()
+        .ok_or_else(|| format!("No update staged for plugin '{}'", plugin_id))?;
+
+    let summary = apply_update(&app, &update)?;
+    log::info!("Updated plugin {} to {}", plugin_id, update.version);
+    state
+        .staged
+        .lock()
+        .map_err(|e| format!("Failed to lock staged updates: {}", e))?
+        .remove(&plugin_id);
+    save_updates_to_store(&app, &state)?;
+    emit_staged(&app, &state);
+    Ok(summary)
+}
+
+/// Discard a staged update (it is offered again on the next check)
+#[tauri::command]
+pub fn plugin_updates_dismiss(
+    plugin_id: String,
+    app: AppHandle,
+    state: State<'_, PluginUpdateState>,
+) -> Result<(), String> {
+    ensure_updates_loaded(&app, &state);
+    let removed = state
+        .staged
+        .lock()
+        .map_err(|e| format!("Failed to lock staged updates: {}", e))?
+        .remove(&plugin_id);
+    if let Some(update) = removed {
+        remove_package(&update);
+        save_updates_to_store(&app, &state)?;
+        emit_staged(&app, &state);
+    }
+    Ok(())
+}
+
+/// Get the update checker configuration
+#[tauri::command]
+pub fn plugin_updates_get_config(
+    app: AppHandle,
+    state: State<'_, PluginUpdateState>,
+) -> Result<PluginUpdateConfig, String> {
+    ensure_updates_loaded(&app, &state);
+    state
+        .config
+        .lock()
+        .map(|c| c.clone())
+        .map_err(|e| format!("Failed to lock plugin update config: {}", e))
+}
+
+/// Update the update checker configuration (the interval takes effect after the current one)
+#[tauri::command]
+pub fn plugin_updates_set_config(
+    config: PluginUpdateConfig,
+    app: AppHandle,
+    state: State<'_, PluginUpdateState>,
+) -> Result<PluginUpdateConfig, String> {
+    ensure_updates_loaded(&app, &state);
+
+    let mut config = config;
+    config.check_interval_hours = config.check_interval_hours.max(MIN_CHECK_INTERVAL_HOURS);
+    let api_base = Url::parse(config.github_api_base.trim()).map_err(|e| format!("Invalid GitHub API URL: {}", e))?;
+    if !matches!(api_base.scheme(), "http" | "https") {
+        return Err("The GitHub API URL must use http or https".to_string());
+    }
+    config.github_api_base = config.github_api_base.trim().trim_end_matches('/').to_string();
+
+    *state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock plugin update config: {}", e))? = config.clone();
+    save_updates_to_store(&app, &state)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zpe::test_support::{trusted_keys, SIGNED, UNSIGNED};
+    use serde_json::json;
+    use crate::test_server::serve_routes;
+
+    /// The fixture plugin (1.2.0 in the packages) installed at another version
+    fn installed(version: &str, signed_by: Option<&str>) -> InstalledPlugin {
+        serde_json::from_value(json!({
+            "id": "fixture-provider",
+            "current": {
+                "format": "zpe",
+                "manifest": {
+                    "id": "fixture-provider", "name": "Fixture Provider", "version": version,
+                    "pluginType": "media-provider",
+                    "repository": { "type": "github", "owner": "zanshin", "repo": "fixture-provider" }
+                },
+                "sha256": "", "signedBy": signed_by, "installedAt": 0
+            },
+            "enabled": true,
+            "installedAt": 0,
+            "updatedAt": 0
+        }))
+        .unwrap()
+    }
+
+    fn release(tag: &str, prerelease: bool, assets: &[&str]) -> GithubRelease {
+        serde_json::from_value(json!({
+            "tag_name": tag,
+            "prerelease": prerelease,
+            "assets": assets.iter().map(|name| json!({
+                "name": name, "browser_download_url": format!("https://example.com/{}", name)
+            })).collect::<Vec<_>>()
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        let order = |a, b| compare_versions(a, b).unwrap();
+        assert_eq!(order("v1.2.0", "1.2.0"), Ordering::Equal);
+        assert_eq!(order("1.10.0", "1.9.9"), Ordering::Greater);
+        assert_eq!(order("1.2.0-beta.1", "1.2.0"), Ordering::Less);
+        assert_eq!(order("1.2.0-beta.11", "1.2.0-beta.2"), Ordering::Greater);
+        assert_eq!(order("1.2.0-alpha", "1.2.0-alpha.1"), Ordering::Less);
+        assert_eq!(order("1.2.0-1", "1.2.0-alpha"), Ordering::Less);
+        assert_eq!(order("1.2.0+build.5", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2", "1.2.0"), None);
+    }
+
+    #[test]
+    fn test_pick_release() {
+        let releases = vec![
+            release("v1.3.0-beta.1", true, &["plugin.zpe"]),
+            release("v1.2.1", false, &["plugin.zpe", "plugin.zpe.sha256"]),
+            release("v1.2.5", false, &["source.zip"]),
+            release("v1.1.0", false, &["plugin.zpe"]),
+        ];
+        let pick = |current, pre| pick_release(&releases, current, pre).map(|(r, _)| r.tag_name.as_str());
+        assert_eq!(pick("1.2.0", false), Some("v1.2.1"));
+        assert_eq!(pick("1.2.0", true), Some("v1.3.0-beta.1"));
+        assert_eq!(pick("1.2.1", false), None);
+    }
+
+    #[test]
+    fn test_verify_update() {
+        let keys = trusted_keys();
+        let key_id = keys[0].key_id.clone();
+        let signed_sha = zpe::sha256_hex(SIGNED);
+        let unsigned_sha = zpe::sha256_hex(UNSIGNED);
+
+        let verified = verify_update(&installed("1.0.0", Some(&key_id)), SIGNED, &signed_sha, &keys).unwrap();
+        assert_eq!(verified.manifest.version, "1.2.0");
+
+        assert!(verify_update(&installed("1.0.0", None), UNSIGNED, &signed_sha, &keys)
+            .unwrap_err()
+            .starts_with("Checksum mismatch"));
+        // A signed plugin can't be replaced by an unsigned package
+        assert!(verify_update(&installed("1.0.0", Some(&key_id)), UNSIGNED, &unsigned_sha, &keys).is_err());
+        // ...or by a package signed with another key
+        assert_eq!(
+            verify_update(&installed("1.0.0", Some("other-key")), SIGNED, &signed_sha, &keys).unwrap_err(),
+            format!("Package is signed by {}, not by other-key", key_id)
+        );
+        assert_eq!(
+            verify_update(&installed("1.2.0", None), UNSIGNED, &unsigned_sha, &keys).unwrap_err(),
+            "Package version 1.2.0 is not newer than 1.2.0"
+        );
+    }
+
+    #[test]
+    fn test_check_plugin_with_mock_github() {
+        // Release assets are served by a second server, like GitHub's download host
+        let assets_base = serve_routes(HashMap::from([
+            ("/fixture-provider-1.2.0.zpe".to_string(), SIGNED.to_vec()),
+            (
+                "/fixture-provider-1.2.0.zpe.sha256".to_string(),
+                format!("{}  fixture-provider-1.2.0.zpe\n", zpe::sha256_hex(SIGNED)).into_bytes(),
+            ),
+        ]));
+
+        let releases = json!([{
+            "tag_name": "v1.2.0",
+            "html_url": "https://github.com/zanshin/fixture-provider/releases/tag/v1.2.0",
+            "body": "Fixes search",
+            "assets": [
+                { "name": "fixture-provider-1.2.0.zpe",
+                  "browser_download_url": format!("{}/fixture-provider-1.2.0.zpe", assets_base) },
+                { "name": "fixture-provider-1.2.0.zpe.sha256",
+                  "browser_download_url": format!("{}/fixture-provider-1.2.0.zpe.sha256", assets_base) }
+            ]
+        }]);
+        let api_base = serve_routes(HashMap::from([(
+            "/repos/zanshin/fixture-provider/releases?per_page=20".to_string(),
+            releases.to_string().into_bytes(),
+        )]));
+
+        let config = PluginUpdateConfig {
+            github_api_base: api_base,
+            ..PluginUpdateConfig::default()
+        };
+        let source = UpdateSource::new(&config).unwrap();
+        let dir = std::env::temp_dir().join(format!("zanshin-updates-{}", std::process::id()));
+        let keys = trusted_keys();
+        let plugin = installed("1.0.0", Some(&keys[0].key_id));
+
+        let update = tauri::async_runtime::block_on(source.check_plugin(&plugin, &keys, &dir))
+            .unwrap()
+            .unwrap();
+        assert_eq!(update.version, "1.2.0");
+        assert_eq!(update.release_notes.as_deref(), Some("Fixes search"));
+        assert_eq!(update.signed_by.as_deref(), Some(keys[0].key_id.as_str()));
+        assert_eq!(std::fs::read(&update.path).unwrap(), SIGNED);
+
+        // Nothing newer once 1.2.0 is installed
+        let plugin = installed("1.2.0", Some(&keys[0].key_id));
+        assert_eq!(tauri::async_runtime::block_on(source.check_plugin(&plugin, &keys, &dir)).unwrap(), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
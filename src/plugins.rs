@@ -0,0 +1,977 @@
+//! Plugin Registry
+//!
+//! This module owns the installed plugins: ZPE packages and plain JS plugins are stored
+//! in `{app_data}/plugins/{id}/{version}/` and tracked in the `plugins.json` store.
+//! Manifests are validated here (ID, version, plugin type, permissions, capabilities,
+//! minimum app version) instead of in the webview.
+//!
+//! Upgrades are atomic: the new version is written to a staging folder and renamed into
+//! place, and the registry only switches to it once that succeeded. The previous version
+//! is kept on disk so it can be rolled back. Plugins are enabled globally by default and
+//! can be enabled or disabled per profile.
+//!
+//! Plugins run in the webview are identified by a session, never by an ID they pass:
+//! `plugin_load` issues a random session with the verified code, the runtime keeps it
+//! out of the plugin's sandbox, and `plugin_fetch` and the storage commands act for
+//! the plugin the session belongs to. Loading a plugin again, unloading, disabling or
+//! uninstalling it revokes its session.
+
+use crate::commands::AYOTO_VERSION;
+use crate::plugin_repos;
+use crate::plugin_updates::compare_versions;
+use crate::profiles::{self, ProfileState};
+use crate::zpe::{self, ZpeManifest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+/// Folder (in the app data folder) holding installed plugins
+const PLUGINS_DIR: &str = "plugins";
+
+/// Store file name for the plugin registry
+const PLUGINS_STORE_FILE: &str = "plugins.json";
+
+/// Store key for installed plugins
+const PLUGINS_KEY: &str = "plugins";
+
+/// Install info written next to each installed version
+const VERSION_INFO_FILE: &str = "version.json";
+
+/// Plugin types (ZPE names and the JS runtime's names)
+pub const PLUGIN_TYPES: &[&str] = &[
+    "media-provider",
+    "stream-provider",
+    "utility",
+    "theme",
+    "integration",
+    "mediaProvider",
+    "streamProvider",
+];
+
+/// Permissions a plugin may request
+pub const PLUGIN_PERMISSIONS: &[&str] = &[
+    "network:http",
+    "network:websocket",
+    "storage:local",
+    "storage:cache",
+    "ui:notification",
+    "ui:dialog",
+    "ui:settings",
+    "system:clipboard",
+    "system:process",
+];
+
+/// How a plugin is packaged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginFormat {
+    /// A `.zpe` package, verified again every time it is loaded
+    Zpe,
+    /// Plain JavaScript code with a manifest
+    Js,
+}
+
+impl PluginFormat {
+    /// File name of the plugin code in a version folder
+    fn file_name(self) -> &'static str {
+        match self {
+            PluginFormat::Zpe => "plugin.zpe",
+            PluginFormat::Js => "plugin.js",
+        }
+    }
+}
+
+/// What to install
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PluginSource {
+    /// A `.zpe` package on disk
+    #[serde(rename_all = "camelCase")]
+    Zpe {
+        path: String,
+        /// Accept an unsigned developer package (signed packages are required
+        /// otherwise)
+        #[serde(default)]
+        allow_unsigned: bool,
+    },
+    /// A JS plugin
+    #[serde(rename_all = "camelCase")]
+    Js { manifest: Value, code: String },
+}
+
+/// One installed version of a plugin (stored as `version.json`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginVersion {
+    pub format: PluginFormat,
+    pub manifest: ZpeManifest,
+    /// SHA-256 of the package or code file (hex)
+    pub sha256: String,
+    /// Trusted key that signed the package
+    #[serde(default)]
+    pub signed_by: Option<String>,
+    pub installed_at: i64,
+}
+
+/// An installed plugin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledPlugin {
+    pub id: String,
+    pub current: PluginVersion,
+    /// Version kept on disk for rollback
+    #[serde(default)]
+    pub previous_version: Option<String>,
+    /// Enabled state for profiles without an override (and when no profile is active)
+    pub enabled: bool,
+    /// Per-profile overrides (profile ID -> enabled)
+    #[serde(default)]
+    pub profile_enabled: HashMap<String, bool>,
+    pub installed_at: i64,
+    pub updated_at: i64,
+}
+
+impl InstalledPlugin {
+    /// Whether the plugin is enabled for a profile
+    pub fn is_enabled_for(&self, profile_id: Option<&str>) -> bool {
+        profile_id
+            .and_then(|id| self.profile_enabled.get(id).copied())
+            .unwrap_or(self.enabled)
+    }
+}
+
+/// Installed plugin as listed for a profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginSummary {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub plugin_type: Option<String>,
+    pub format: PluginFormat,
+    pub permissions: Vec<String>,
+    pub signed_by: Option<String>,
+    /// Installed from an unsigned developer package or JS code
+    pub unsigned: bool,
+    pub previous_version: Option<String>,
+    /// Enabled for the requested (or active) profile
+    pub enabled: bool,
+    pub updated_at: i64,
+}
+
+impl PluginSummary {
+    fn new(plugin: &InstalledPlugin, profile_id: Option<&str>) -> Self {
+        let manifest = &plugin.current.manifest;
+        PluginSummary {
+            id: plugin.id.clone(),
+            name: manifest.name.clone(),
+            version: manifest.version.clone(),
+            plugin_type: manifest.plugin_type.clone(),
+            format: plugin.current.format,
+            permissions: manifest.permissions.clone(),
+            signed_by: plugin.current.signed_by.clone(),
+            unsigned: plugin.current.signed_by.is_none(),
+            previous_version: plugin.previous_version.clone(),
+            enabled: plugin.is_enabled_for(profile_id),
+            updated_at: plugin.updated_at,
+        }
+    }
+}
+
+/// A plugin ready to be run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedPlugin {
+    pub manifest: ZpeManifest,
+    pub code: String,
+    pub format: PluginFormat,
+    /// Session the webview runtime makes the plugin's requests with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+/// A validated plugin, not yet written to disk
+#[derive(Debug)]
+struct PreparedPlugin {
+    version: PluginVersion,
+    data: Vec<u8>,
+}
+
+/// Sessions of the plugins loaded by the webview runtimes
+#[derive(Default)]
+pub struct PluginSessions {
+    /// Plugin IDs by session
+    sessions: Mutex<HashMap<String, String>>,
+}
+
+impl PluginSessions {
+    /// Issue a session for a plugin, revoking the one it had
+    pub fn open(&self, plugin_id: &str) -> Result<String, String> {
+        let session = crate::backend_runtime::generate_token()?;
+        let mut sessions = self
+            .sessions
+            .lock()
+            .map_err(|e| format!("Failed to lock plugin sessions: {}", e))?;
+        sessions.retain(|_, id| id != plugin_id);
+        sessions.insert(session.clone(), plugin_id.to_string());
+        Ok(session)
+    }
+
+    /// Revoke a session
+    pub fn close(&self, session: &str) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(session);
+        }
+    }
+
+    /// Revoke the session of a plugin
+    pub fn close_plugin(&self, plugin_id: &str) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.retain(|_, id| id != plugin_id);
+        }
+    }
+
+    /// Plugin a session belongs to
+    pub fn plugin(&self, session: &str) -> Result<String, String> {
+        self.sessions
+            .lock()
+            .map_err(|e| format!("Failed to lock plugin sessions: {}", e))?
+            .get(session)
+            .cloned()
+            .ok_or_else(|| "Invalid or revoked plugin session".to_string())
+    }
+}
+
+/// Plugin registry state
+#[derive(Default)]
+pub struct PluginState {
+    plugins: Mutex<HashMap<String, InstalledPlugin>>,
+    loaded: Mutex<bool>,
+    sessions: PluginSessions,
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Validation
+// =============================================================================
+
+/// Validate a manifest and check it against the running app version
+pub fn validate_manifest(manifest: &ZpeManifest) -> Result<(), String> {
+    zpe::validate_manifest(manifest).map_err(|e| e.to_string())?;
+
+    match manifest.plugin_type.as_deref() {
+        Some(plugin_type) if PLUGIN_TYPES.contains(&plugin_type) => {}
+        Some(plugin_type) => return Err(format!("Unknown plugin type '{}'", plugin_type)),
+        None => return Err("Plugin type (pluginType) is required".to_string()),
+    }
+
+    if let Some(permission) = manifest
+        .permissions
+        .iter()
+        .find(|p| !PLUGIN_PERMISSIONS.contains(&p.as_str()))
+    {
+        return Err(format!("Unknown permission '{}'", permission));
+    }
+
+    match manifest.extra.get("capabilities") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(capabilities)) => {
+            if let Some((name, _)) = capabilities.iter().find(|(_, v)| !v.is_boolean()) {
+                return Err(format!("Capability '{}' must be true or false", name));
+            }
+        }
+        Some(_) => return Err("Capabilities must be an object".to_string()),
+    }
+
+    if let Some(min) = manifest.min_app_version.as_deref() {
+        let required = zpe::parse_version(min);
+        let current = zpe::parse_version(AYOTO_VERSION);
+        if required > current {
+            return Err(format!(
+                "{} requires app version {} or newer (running {})",
+                manifest.name, min, AYOTO_VERSION
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify and validate a plugin to install
+fn prepare(source: PluginSource, keys: &[zpe::TrustedKey]) -> Result<PreparedPlugin, String> {
+    let (format, data, manifest, signed_by) = match source {
+        PluginSource::Zpe { path, allow_unsigned } => {
+            let data = zpe::read_package(Path::new(&path))?;
+            let options = zpe::VerifyOptions { allow_unsigned };
+            let verified = zpe::verify(&data, keys, &options).map_err(|e| e.to_string())?;
+            (PluginFormat::Zpe, data, verified.manifest, verified.signed_by)
+        }
+        PluginSource::Js { manifest, code } => {
+            if code.trim().is_empty() {
+                return Err("Plugin code is empty".to_string());
+            }
+            if code.len() > zpe::MAX_PACKAGE_BYTES {
+                return Err(format!("Plugin code is too large ({} bytes)", code.len()));
+            }
+            let manifest: ZpeManifest =
+                serde_json::from_value(manifest).map_err(|e| format!("Invalid manifest: {}", e))?;
+            (PluginFormat::Js, code.into_bytes(), manifest, None)
+        }
+    };
+    validate_manifest(&manifest)?;
+
+    Ok(PreparedPlugin {
+        version: PluginVersion {
+            format,
+            manifest,
+            sha256: zpe::sha256_hex(&data),
+            signed_by,
+            installed_at: get_current_timestamp(),
+        },
+        data,
+    })
+}
+
+// =============================================================================
+// Installation
+// =============================================================================
+
+/// Folder of an installed version
+fn version_dir(root: &Path, id: &str, version: &str) -> PathBuf {
+    root.join(id).join(version)
+}
+
+/// Read the install info of an installed version
+fn read_version_info(root: &Path, id: &str, version: &str) -> Result<PluginVersion, String> {
+    let path = version_dir(root, id, version).join(VERSION_INFO_FILE);
+    let data = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Write a plugin version to disk: into a staging folder first, then renamed into place.
+/// Returns the record to store; nothing on disk is removed yet.
+fn write_version(
+    root: &Path,
+    existing: Option<&InstalledPlugin>,
+    prepared: PreparedPlugin,
+) -> Result<InstalledPlugin, String> {
+    let manifest = &prepared.version.manifest;
+    let (id, version) = (manifest.id.clone(), manifest.version.clone());
+
+    if let Some(existing) = existing {
+        let installed = &existing.current.manifest.version;
+        // Same semver precedence as updates and repositories, so prereleases count
+        if compare_versions(&version, installed) == Some(Ordering::Less) {
+            return Err(format!(
+                "{} {} is older than the installed version {}; roll back instead",
+                id, version, installed
+            ));
+        }
+        if existing.current.signed_by.is_some() && prepared.version.signed_by.is_none() {
+            return Err(format!(
+                "{} {} is signed; an unsigned package can't replace it",
+                id, installed
+            ));
+        }
+    }
+
+    let plugin_dir = root.join(&id);
+    let staging = plugin_dir.join(format!(".staging-{}", get_current_timestamp()));
+    let write = || -> std::io::Result<()> {
+        std::fs::create_dir_all(&staging)?;
+        std::fs::write(staging.join(prepared.version.format.file_name()), &prepared.data)?;
+        let info = serde_json::to_vec_pretty(&prepared.version)?;
+        std::fs::write(staging.join(VERSION_INFO_FILE), info)?;
+
+        // Reinstalling the same version replaces its folder
+        let target = version_dir(root, &id, &version);
+        if target.exists() {
+            let replaced = plugin_dir.join(format!(".replaced-{}", get_current_timestamp()));
+            std::fs::rename(&target, &replaced)?;
+            if let Err(e) = std::fs::rename(&staging, &target) {
+                let _ = std::fs::rename(&replaced, &target);
+                return Err(e);
+            }
+            let _ = std::fs::remove_dir_all(&replaced);
+            Ok(())
+        } else {
+            std::fs::rename(&staging, &target)
+        }
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(format!("Failed to install {} {}: {}", id, version, e));
+    }
+
+    let now = get_current_timestamp();
+    Ok(match existing {
+        Some(existing) => {
+            let installed = &existing.current.manifest.version;
+            InstalledPlugin {
+                current: prepared.version,
+                previous_version: if *installed == version {
+                    existing.previous_version.clone()
+                } else {
+                    Some(installed.clone())
+                },
+                updated_at: now,
+                ..existing.clone()
+            }
+        }
+        None => InstalledPlugin {
+            id,
+            current: prepared.version,
+            previous_version: None,
+            enabled: true,
+            profile_enabled: HashMap::new(),
+            installed_at: now,
+            updated_at: now,
+        },
+    })
+}
+
+/// Remove every version folder except the current and previous one
+fn prune_versions(root: &Path, plugin: &InstalledPlugin) {
+    let keep = [Some(&plugin.current.manifest.version), plugin.previous_version.as_ref()];
+    let Ok(entries) = std::fs::read_dir(root.join(&plugin.id)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !keep.contains(&Some(&name)) {
+            if let Err(e) = std::fs::remove_dir_all(entry.path()) {
+                log::warn!("Failed to remove old plugin files {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+/// Switch a plugin back to its previous version (the current one becomes the previous)
+fn rollback_version(root: &Path, plugin: &InstalledPlugin) -> Result<InstalledPlugin, String> {
+    let previous = plugin
+        .previous_version
+        .clone()
+        .ok_or_else(|| format!("Plugin '{}' has no previous version", plugin.id))?;
+    let version = read_version_info(root, &plugin.id, &previous)?;
+    validate_manifest(&version.manifest)?;
+
+    Ok(InstalledPlugin {
+        current: version,
+        previous_version: Some(plugin.current.manifest.version.clone()),
+        updated_at: get_current_timestamp(),
+        ..plugin.clone()
+    })
+}
+
+/// Read and check the code of the current version
+fn load_code(root: &Path, plugin: &InstalledPlugin, keys: &[zpe::TrustedKey]) -> Result<LoadedPlugin, String> {
+    let current = &plugin.current;
+    let path = version_dir(root, &plugin.id, &current.manifest.version).join(current.format.file_name());
+    let data = zpe::read_package(&path)?;
+    if zpe::sha256_hex(&data) != current.sha256 {
+        return Err(format!("Files of plugin '{}' were modified", plugin.id));
+    }
+
+    let code = match current.format {
+        PluginFormat::Zpe => {
+            // Keys may have been revoked since the package was installed
+            let options = zpe::VerifyOptions {
+                allow_unsigned: current.signed_by.is_none(),
+            };
+            zpe::verify(&data, keys, &options).map_err(|e| e.to_string())?.code
+        }
+        PluginFormat::Js => String::from_utf8(data).map_err(|_| "Plugin code is not valid UTF-8".to_string())?,
+    };
+
+    Ok(LoadedPlugin {
+        manifest: current.manifest.clone(),
+        code,
+        format: current.format,
+        session: None,
+    })
+}
+
+// =============================================================================
+// Persistence
+// =============================================================================
+
+/// Folder holding installed plugins
+fn plugins_root(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(PLUGINS_DIR))
+        .map_err(|e| format!("Failed to resolve app data folder: {}", e))
+}
+
+/// Load the registry from the store if not already loaded
+fn ensure_plugins_loaded(app: &AppHandle, state: &PluginState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(PLUGINS_STORE_FILE) {
+        if let Some(value) = store.get(PLUGINS_KEY) {
+            match serde_json::from_value::<HashMap<String, InstalledPlugin>>(value.clone()) {
+                Ok(plugins) => *state.plugins.lock().unwrap() = plugins,
+                Err(e) => log::warn!("Failed to deserialize plugins: {}", e),
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save the registry to the store
+fn save_plugins_to_store(app: &AppHandle, plugins: &HashMap<String, InstalledPlugin>) -> Result<(), String> {
+    let store = app
+        .store(PLUGINS_STORE_FILE)
+        .map_err(|e| format!("Failed to open plugin store: {}", e))?;
+    let value = serde_json::to_value(plugins).map_err(|e| format!("Failed to serialize plugins: {}", e))?;
+    store.set(PLUGINS_KEY, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save plugin store: {}", e))
+}
+
+/// Profile to use: the given one, else the active one
+fn resolve_profile(app: &AppHandle, profile_id: Option<String>) -> Option<String> {
+    profile_id.or_else(|| profiles::active_profile_id(app, &app.state::<ProfileState>()))
+}
+
+/// Get an installed plugin
+pub fn get_plugin(app: &AppHandle, plugin_id: &str) -> Option<InstalledPlugin> {
+    let state = app.state::<PluginState>();
+    ensure_plugins_loaded(app, &state);
+    let plugin = state.plugins.lock().ok()?.get(plugin_id).cloned();
+    plugin
+}
+
+/// Get all installed plugins
+pub fn installed_plugins(app: &AppHandle) -> Vec<InstalledPlugin> {
+    let state = app.state::<PluginState>();
+    ensure_plugins_loaded(app, &state);
+    let plugins = state
+        .plugins
+        .lock()
+        .map(|plugins| plugins.values().cloned().collect())
+        .unwrap_or_default();
+    plugins
+}
+
+/// Get the verified code of a plugin enabled for a profile (the active one by default)
+pub fn load_plugin(app: &AppHandle, plugin_id: &str, profile_id: Option<String>) -> Result<LoadedPlugin, String> {
+    let root = plugins_root(app)?;
+    let plugin = get_plugin(app, plugin_id).ok_or_else(|| format!("Plugin '{}' is not installed", plugin_id))?;
+    if !plugin.is_enabled_for(resolve_profile(app, profile_id).as_deref()) {
+        return Err(format!("Plugin '{}' is disabled", plugin_id));
+    }
+    load_code(&root, &plugin, &verification_keys(app))
+}
+
+/// Plugin a webview runtime session belongs to
+pub fn session_plugin(app: &AppHandle, session: &str) -> Result<String, String> {
+    app.state::<PluginState>().sessions.plugin(session)
+}
+
+/// Keys installed packages are verified with: the trusted keys and the keys pinned by
+/// plugin repositories
+pub fn verification_keys(app: &AppHandle) -> Vec<zpe::TrustedKey> {
+    let mut keys = zpe::trusted_keys(app);
+    keys.extend(plugin_repos::pinned_keys(app));
+    keys
+}
+
+/// Install a plugin, or upgrade it atomically when it is already installed
+pub fn install(app: &AppHandle, source: PluginSource) -> Result<PluginSummary, String> {
+    install_with_keys(app, source, &zpe::trusted_keys(app))
+}
+
+/// Install a plugin, accepting only packages signed with one of `keys`
+pub fn install_with_keys(app: &AppHandle, source: PluginSource, keys: &[zpe::TrustedKey]) -> Result<PluginSummary, String> {
+    let state = app.state::<PluginState>();
+    ensure_plugins_loaded(app, &state);
+    let root = plugins_root(app)?;
+    let prepared = prepare(source, keys)?;
+
+    let mut plugins = state
+        .plugins
+        .lock()
+        .map_err(|e| format!("Failed to lock plugins: {}", e))?;
+    let id = prepared.version.manifest.id.clone();
+    let version = prepared.version.manifest.version.clone();
+    let existing = plugins.get(&id).cloned();
+    let newly_written = !version_dir(&root, &id, &version).exists();
+
+    let plugin = write_version(&root, existing.as_ref(), prepared)?;
+    if let Err(e) = commit_plugin(app, &mut plugins, plugin.clone()) {
+        // The registry still points at the old version
+        if newly_written {
+            let _ = std::fs::remove_dir_all(version_dir(&root, &id, &version));
+        }
+        return Err(e);
+    }
+    prune_versions(&root, &plugin);
+
+    match existing {
+        Some(existing) => log::info!(
+            "Upgraded plugin {} from {} to {}",
+            id, existing.current.manifest.version, version
+        ),
+        None => log::info!("Installed plugin {} {}", id, version),
+    }
+    Ok(PluginSummary::new(&plugin, resolve_profile(app, None).as_deref()))
+}
+
+/// Replace a plugin's record and persist it; the record is only kept if saving worked
+fn commit_plugin(
+    app: &AppHandle,
+    plugins: &mut HashMap<String, InstalledPlugin>,
+    plugin: InstalledPlugin,
+) -> Result<(), String> {
+    let old = plugins.insert(plugin.id.clone(), plugin.clone());
+    if let Err(e) = save_plugins_to_store(app, plugins) {
+        match old {
+            Some(old) => plugins.insert(plugin.id.clone(), old),
+            None => plugins.remove(&plugin.id),
+        };
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Enable or disable a plugin for a profile (or globally when no profile is active)
+fn set_enabled(app: &AppHandle, state: &PluginState, plugin_id: &str, profile_id: Option<String>, enabled: bool) -> Result<(), String> {
+    ensure_plugins_loaded(app, state);
+    let profile_id = resolve_profile(app, profile_id);
+    let mut plugins = state
+        .plugins
+        .lock()
+        .map_err(|e| format!("Failed to lock plugins: {}", e))?;
+    let mut plugin = plugins
+        .get(plugin_id)
+        .cloned()
+        .ok_or_else(|| format!("Plugin '{}' is not installed", plugin_id))?;
+
+    match profile_id {
+        Some(profile_id) => {
+            plugin.profile_enabled.insert(profile_id, enabled);
+        }
+        None => plugin.enabled = enabled,
+    }
+    commit_plugin(app, &mut plugins, plugin)
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// List installed plugins with their enabled state for a profile (the active one by default)
+#[tauri::command]
+pub fn plugin_list(
+    profile_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, PluginState>,
+) -> Result<Vec<PluginSummary>, String> {
+    ensure_plugins_loaded(&app, &state);
+    let profile_id = resolve_profile(&app, profile_id);
+    let plugins = state
+        .plugins
+        .lock()
+        .map_err(|e| format!("Failed to lock plugins: {}", e))?;
+
+    let mut list: Vec<PluginSummary> = plugins
+        .values()
+        .map(|p| PluginSummary::new(p, profile_id.as_deref()))
+        .collect();
+    list.sort_by_key(|p| p.name.to_lowercase());
+    Ok(list)
+}
+
+/// Install a plugin, or upgrade it atomically when it is already installed
+#[tauri::command]
+pub fn plugin_install(source: PluginSource, app: AppHandle) -> Result<PluginSummary, String> {
+    install(&app, source)
+}
+
+/// Uninstall a plugin and remove its files
+#[tauri::command]
+pub fn plugin_uninstall(
+    plugin_id: String,
+    app: AppHandle,
+    state: State<'_, PluginState>,
+) -> Result<(), String> {
+    ensure_plugins_loaded(&app, &state);
+    let root = plugins_root(&app)?;
+    let mut plugins = state
+        .plugins
+        .lock()
+        .map_err(|e| format!("Failed to lock plugins: {}", e))?;
+
+    let removed = plugins
+        .remove(&plugin_id)
+        .ok_or_else(|| format!("Plugin '{}' is not installed", plugin_id))?;
+    if let Err(e) = save_plugins_to_store(&app, &plugins) {
+        plugins.insert(plugin_id, removed);
+        return Err(e);
+    }
+
+    let dir = root.join(&plugin_id);
+    if dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            log::warn!("Failed to remove plugin files {}: {}", dir.display(), e);
+        }
+    }
+    crate::plugin_host::unload(&app, &plugin_id);
+    state.sessions.close_plugin(&plugin_id);
+    crate::plugin_storage::remove_plugin_data(&app, &plugin_id);
+    log::info!("Uninstalled plugin {}", plugin_id);
+    Ok(())
+}
+
+/// Enable a plugin for a profile (the active one by default)
+#[tauri::command]
+pub fn plugin_enable(
+    plugin_id: String,
+    profile_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, PluginState>,
+) -> Result<(), String> {
+    set_enabled(&app, &state, &plugin_id, profile_id, true)
+}
+
+/// Disable a plugin for a profile (the active one by default)
+#[tauri::command]
+pub fn plugin_disable(
+    plugin_id: String,
+    profile_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, PluginState>,
+) -> Result<(), String> {
+    set_enabled(&app, &state, &plugin_id, profile_id, false)?;
+    crate::plugin_host::unload(&app, &plugin_id);
+    state.sessions.close_plugin(&plugin_id);
+    Ok(())
+}
+
+/// Switch a plugin back to the version installed before the last upgrade
+#[tauri::command]
+pub fn plugin_rollback(
+    plugin_id: String,
+    app: AppHandle,
+    state: State<'_, PluginState>,
+) -> Result<PluginSummary, String> {
+    ensure_plugins_loaded(&app, &state);
+    let root = plugins_root(&app)?;
+    let mut plugins = state
+        .plugins
+        .lock()
+        .map_err(|e| format!("Failed to lock plugins: {}", e))?;
+    let plugin = plugins
+        .get(&plugin_id)
+        .ok_or_else(|| format!("Plugin '{}' is not installed", plugin_id))?;
+
+    let rolled_back = rollback_version(&root, plugin)?;
+    commit_plugin(&app, &mut plugins, rolled_back.clone())?;
+    log::info!(
+        "Rolled back plugin {} to {}",
+        plugin_id, rolled_back.current.manifest.version
+    );
+    Ok(PluginSummary::new(&rolled_back, resolve_profile(&app, None).as_deref()))
+}
+
+/// Get the verified code of an enabled plugin and a new session for the webview
+/// runtime to run it with (its previous session is revoked)
+#[tauri::command]
+pub fn plugin_load(
+    plugin_id: String,
+    profile_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, PluginState>,
+) -> Result<LoadedPlugin, String> {
+    let mut loaded = load_plugin(&app, &plugin_id, profile_id)?;
+    loaded.session = Some(state.sessions.open(&plugin_id)?);
+    Ok(loaded)
+}
+
+/// Revoke the session of a plugin the webview runtime unloaded
+#[tauri::command]
+pub fn plugin_unload(session: String, state: State<'_, PluginState>) -> Result<(), String> {
+    state.sessions.close(&session);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zpe::test_support::{trusted_keys, SIGNED, UNSIGNED};
+
+    /// Empty folder for a test's plugins
+    fn test_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("zanshin-plugins-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn js_source(version: &str, code: &str) -> PluginSource {
+        PluginSource::Js {
+            manifest: serde_json::json!({
+                "id": "js-provider",
+                "name": "JS Provider",
+                "version": version,
+                "pluginType": "mediaProvider",
+                "permissions": ["network:http"],
+                "capabilities": { "search": true }
+            }),
+            code: code.to_string(),
+        }
+    }
+
+    fn manifest(value: Value) -> ZpeManifest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_validate_manifest() {
+        let valid = serde_json::json!({
+            "id": "some-plugin", "name": "Some", "version": "1.0.0", "pluginType": "utility"
+        });
+        assert!(validate_manifest(&manifest(valid.clone())).is_ok());
+
+        let with = |key: &str, value: Value| {
+            let mut m = valid.clone();
+            m[key] = value;
+            validate_manifest(&manifest(m))
+        };
+        assert!(with("pluginType", "virus".into()).unwrap_err().contains("plugin type"));
+        assert!(with("permissions", serde_json::json!(["system:root"])).unwrap_err().contains("system:root"));
+        assert!(with("capabilities", serde_json::json!({ "search": "yes" })).is_err());
+        assert!(with("capabilities", serde_json::json!(["search"])).is_err());
+        assert!(with("minAppVersion", "999.0.0".into()).unwrap_err().contains("requires app version"));
+        assert!(with("minAppVersion", AYOTO_VERSION.into()).is_ok());
+    }
+
+    #[test]
+    fn test_install_zpe_verifies_package() {
+        let root = test_root("zpe");
+        let path = root.join("fixture.zpe");
+        std::fs::write(&path, SIGNED).unwrap();
+        let source = |allow_unsigned| PluginSource::Zpe {
+            path: path.to_string_lossy().to_string(),
+            allow_unsigned,
+        };
+
+        // Signed by a key that isn't trusted
+        assert!(prepare(source(false), &[]).unwrap_err().contains("untrusted key"));
+
+        let keys = trusted_keys();
+        let plugin = write_version(&root, None, prepare(source(false), &keys).unwrap()).unwrap();
+        assert_eq!(plugin.id, "fixture-provider");
+        assert_eq!(plugin.current.signed_by.as_deref(), Some("zanshin-test"));
+        assert!(plugin.enabled);
+
+        let loaded = load_code(&root, &plugin, &keys).unwrap();
+        assert!(loaded.code.contains("async search(query"));
+
+        // An unsigned package can't replace the signed install
+        std::fs::write(&path, UNSIGNED).unwrap();
+        let unsigned = prepare(source(true), &keys).unwrap();
+        assert!(write_version(&root, Some(&plugin), unsigned).unwrap_err().contains("is signed"));
+        // Revoking the key stops the plugin from loading
+        assert!(load_code(&root, &plugin, &[]).is_err());
+
+        // Files changed on disk are refused
+        std::fs::write(&path, UNSIGNED).unwrap();
+        let file = version_dir(&root, "fixture-provider", "1.2.0").join("plugin.zpe");
+        std::fs::write(&file, UNSIGNED).unwrap();
+        assert!(load_code(&root, &plugin, &keys).unwrap_err().contains("modified"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_upgrade_and_rollback() {
+        let root = test_root("upgrade");
+        let mut v1 = write_version(&root, None, prepare(js_source("1.0.0", "// v1"), &[]).unwrap()).unwrap();
+        v1.profile_enabled.insert("profile_1".to_string(), false);
+
+        let v2 = write_version(&root, Some(&v1), prepare(js_source("1.1.0", "// v2"), &[]).unwrap()).unwrap();
+        assert_eq!(v2.current.manifest.version, "1.1.0");
+        assert_eq!(v2.previous_version.as_deref(), Some("1.0.0"));
+        assert!(!v2.is_enabled_for(Some("profile_1")), "profile state survives upgrades");
+        assert_eq!(load_code(&root, &v2, &[]).unwrap().code, "// v2");
+
+        // Downgrades must go through rollback
+        let older = prepare(js_source("0.9.0", "// old"), &[]).unwrap();
+        assert!(write_version(&root, Some(&v2), older).unwrap_err().contains("roll back"));
+        let prerelease = prepare(js_source("1.1.0-beta.1", "// beta"), &[]).unwrap();
+        assert!(write_version(&root, Some(&v2), prerelease).unwrap_err().contains("roll back"));
+
+        let v3 = write_version(&root, Some(&v2), prepare(js_source("1.2.0", "// v3"), &[]).unwrap()).unwrap();
+        prune_versions(&root, &v3);
+        let mut versions: Vec<String> = std::fs::read_dir(root.join("js-provider"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        versions.sort();
+        assert_eq!(versions, vec!["1.1.0", "1.2.0"]);
+
+        let rolled_back = rollback_version(&root, &v3).unwrap();
+        assert_eq!(rolled_back.current.manifest.version, "1.1.0");
+        assert_eq!(rolled_back.previous_version.as_deref(), Some("1.2.0"));
+        assert_eq!(load_code(&root, &rolled_back, &[]).unwrap().code, "// v2");
+        // Rolling back again returns to the newer version
+        assert_eq!(rollback_version(&root, &rolled_back).unwrap().current.manifest.version, "1.2.0");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_failed_install_keeps_current_version() {
+        let root = test_root("failed");
+        let v1 = write_version(&root, None, prepare(js_source("1.0.0", "// v1"), &[]).unwrap()).unwrap();
+
+        // A file where the plugin folder's staging area would go makes the write fail
+        std::fs::remove_dir_all(root.join("js-provider")).unwrap();
+        std::fs::write(root.join("js-provider"), b"not a folder").unwrap();
+        let result = write_version(&root, Some(&v1), prepare(js_source("2.0.0", "// v2"), &[]).unwrap());
+        assert!(result.unwrap_err().starts_with("Failed to install js-provider 2.0.0"));
+
+        assert!(matches!(prepare(js_source("1.0.0", "  "), &[]), Err(e) if e.contains("empty")));
+        assert!(prepare(js_source("1.0", "// v1"), &[]).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_sessions() {
+        let sessions = PluginSessions::default();
+        let a = sessions.open("plugin-a").unwrap();
+        let b = sessions.open("plugin-b").unwrap();
+        assert_eq!(sessions.plugin(&a).unwrap(), "plugin-a");
+        assert_eq!(sessions.plugin(&b).unwrap(), "plugin-b");
+        // A plugin ID is not a session
+        assert!(sessions.plugin("plugin-b").is_err());
+
+        // Loading a plugin again revokes its old session
+        let reloaded = sessions.open("plugin-a").unwrap();
+        assert!(sessions.plugin(&a).is_err());
+        assert_eq!(sessions.plugin(&reloaded).unwrap(), "plugin-a");
+
+        sessions.close(&reloaded);
+        assert!(sessions.plugin(&reloaded).is_err());
+        sessions.close_plugin("plugin-b");
+        assert!(sessions.plugin(&b).is_err());
+        assert!(sessions.sessions.lock().unwrap().is_empty());
+    }
+}
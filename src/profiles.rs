@@ -97,6 +97,10 @@ pub struct ProfileSettings {
     pub subtitles_enabled: bool,
     /// Anime4K preset preference
     pub anime4k_preset: Option<String>,
+    /// Whether the Anime4K preset is applied (kept apart so turning it off keeps the
+    /// preset; `None` on profiles saved before, where a `"none"` preset meant off)
+    #[serde(default)]
+    pub anime4k_enabled: Option<bool>,
     /// Custom profile settings (extensible)
     #[serde(default)]
     pub custom: HashMap<String, serde_json::Value>,
@@ -244,6 +248,58 @@ fn ensure_profiles_loaded(app: &AppHandle, state: &ProfileState) {
     *loaded = true;
 }
 
+/// Get the settings of a profile, falling back to the active profile when no ID is given.
+/// Used by other modules that rank or filter content by profile preferences.
+pub fn resolve_profile_settings(
+    app: &AppHandle,
+    state: &ProfileState,
+    profile_id: Option<&str>,
+) -> Option<ProfileSettings> {
+    ensure_profiles_loaded(app, state);
+
+    let id = match profile_id {
+        Some(id) => id.to_string(),
+        None => state.active_profile_id.lock().ok()?.clone()?,
+    };
+
+    let profiles = state.profiles.lock().ok()?;
+    profiles.get(&id).map(|p| p.settings.clone())
+}
+
+/// Get the ID of the active profile, if any
+pub fn active_profile_id(app: &AppHandle, state: &ProfileState) -> Option<String> {
+    ensure_profiles_loaded(app, state);
+    state.active_profile_id.lock().ok()?.clone()
+}
+
+/// Change the settings of a profile and persist them
+pub fn update_profile_settings(
+    app: &AppHandle,
+    state: &ProfileState,
+    profile_id: &str,
+    update: impl FnOnce(&mut ProfileSettings),
+) -> Result<UserProfile, String> {
+    ensure_profiles_loaded(app, state);
+
+    let mut profiles = state
+        .profiles
+        .lock()
+        .map_err(|e| format!("Failed to lock profiles: {}", e))?;
+
+    let profile = profiles
+        .get_mut(profile_id)
+        .ok_or_else(|| format!("Profile '{}' not found", profile_id))?;
+
+    update(&mut profile.settings);
+
+    let updated_profile = profile.clone();
+
+    // Persist changes
+    save_profiles_to_store(app, &profiles)?;
+
+    Ok(updated_profile)
+}
+
 // =============================================================================
 // Tauri Commands
 // =============================================================================
@@ -482,23 +538,7 @@ pub fn profile_update_settings(
     app: AppHandle,
     state: State<'_, ProfileState>,
 ) -> Result<UserProfile, String> {
-    ensure_profiles_loaded(&app, &state);
-    
-    let mut profiles = state
-        .profiles
-        .lock()
-        .map_err(|e| format!("Failed to lock profiles: {}", e))?;
-    
-    let profile = profiles
-        .get_mut(&profile_id)
-        .ok_or_else(|| format!("Profile '{}' not found", profile_id))?;
-    
-    profile.settings = settings;
-    
-    let updated_profile = profile.clone();
-    
-    // Persist changes
-    save_profiles_to_store(&app, &profiles)?;
+    let updated_profile = update_profile_settings(&app, &state, &profile_id, |s| *s = settings)?;
     
     log::info!("Profile settings updated: {}", profile_id);
     
@@ -0,0 +1,839 @@
+//! Release Name Parser
+//!
+//! This module extracts structured information from fansub filenames and torrent
+//! titles such as `[SubsPlease] Frieren - 12 (1080p) [ABCD1234].mkv`. It is used by
+//! torrent searches (Nyaa/AnimeTosho), seeding of existing files and offline library
+//! imports, so it only relies on plain string handling and has no I/O.
+//!
+//! Supported naming styles:
+//! - Fansub style: `[Group] Title - 01v2 [1080p][CRC32].mkv`
+//! - Scene style: `Title.S01E12.1080p.WEB.H264-GROUP.mkv`
+//! - Batches: `[Group] Title (01-12) [BD 1080p] [Batch]`, `Title S02 1080p`
+
+use serde::{Deserialize, Serialize};
+
+/// File extensions that are stripped from the end of a release name
+const KNOWN_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "avi", "webm", "m4v", "ts", "ogm", "wmv", "flv", "torrent",
+];
+
+/// Keywords marking a release as a batch or season pack
+const BATCH_KEYWORDS: &[&str] = &["batch", "complete", "bdbox", "boxset"];
+
+/// Largest number still treated as an episode when it is not preceded by " - "
+/// (anything above is more likely a year or part of the title)
+const MAX_BARE_EPISODE: u32 = 1899;
+
+/// Structured information extracted from a release name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedRelease {
+    /// Anime title with all release metadata removed
+    pub title: String,
+    /// Episode number (first episode of a range)
+    pub episode: Option<u32>,
+    /// Last episode of a range (e.g. `01-12`)
+    pub episode_end: Option<u32>,
+    /// Season number (`S2`, `Season 2`, `2nd Season`)
+    pub season: Option<u32>,
+    /// Vertical resolution in pixels (e.g. 1080)
+    pub resolution: Option<u32>,
+    /// Release group (`[SubsPlease]` or scene-style `-GROUP` suffix)
+    pub release_group: Option<String>,
+    /// Normalized video codec (`H.264`, `H.265`, `AV1`, ...)
+    pub video_codec: Option<String>,
+    /// Video bit depth (8 or 10)
+    pub bit_depth: Option<u8>,
+    /// Normalized audio codecs in order of appearance
+    pub audio_codecs: Vec<String>,
+    /// Normalized source (`BD`, `WEB`, `DVD`, `TV`)
+    pub source: Option<String>,
+    /// Release year, when tagged separately (e.g. `(2011)`)
+    pub year: Option<u32>,
+    /// Release version (`v2` re-releases)
+    pub version: Option<u32>,
+    /// CRC32 checksum tag
+    pub checksum: Option<String>,
+    /// File extension (lowercase, without dot)
+    pub extension: Option<String>,
+    /// Whether the release is a batch/season pack rather than a single episode
+    pub is_batch: bool,
+}
+
+/// A piece of metadata recognized from one or more tokens
+#[derive(Debug, Clone, PartialEq)]
+enum Meta {
+    Resolution(u32),
+    VideoCodec(&'static str),
+    BitDepth(u8),
+    AudioCodec(&'static str),
+    Source(&'static str),
+    Season(u32),
+    Episode(EpisodeMatch),
+    SeasonEpisode(u32, EpisodeMatch),
+    Version(u32),
+    Batch,
+    Checksum(String),
+    Year(u32),
+}
+
+/// Episode number, optional range end and optional version
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EpisodeMatch {
+    start: u32,
+    end: Option<u32>,
+    version: Option<u32>,
+}
+
+/// A run of text either inside or outside of brackets
+struct Segment {
+    text: String,
+    bracketed: bool,
+}
+
+/// Mutable parse state shared between segments
+struct Parser {
+    release: ParsedRelease,
+    title_tokens: Vec<String>,
+    title_done: bool,
+    batch_keyword: bool,
+}
+
+/// Parse a release name into its components
+pub fn parse_release_name(input: &str) -> ParsedRelease {
+    // Only the file name is relevant when a full path is passed
+    let name = input
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(input)
+        .trim();
+
+    let mut parser = Parser {
+        release: ParsedRelease::default(),
+        title_tokens: Vec::new(),
+        title_done: false,
+        batch_keyword: false,
+    };
+
+    let name = parser.strip_extension(name);
+    let segments = split_segments(&name);
+
+    for (index, segment) in segments.iter().enumerate() {
+        if segment.bracketed {
+            // A leading bracket is the release group unless it is clearly
+            // technical metadata such as `[1080p]` (group names like `Hi10` do exist)
+            if index == 0 && parser.release.release_group.is_none() {
+                let content = segment.text.trim();
+                let is_meta = parse_episode_token(content).is_some()
+                    || tokenize_bracket(content)
+                        .iter()
+                        .any(|t| parse_resolution(&t.to_ascii_lowercase()).is_some());
+                if !content.is_empty() && !is_meta {
+                    parser.release.release_group = Some(content.to_string());
+                    continue;
+                }
+            }
+            parser.scan_bracket(&segment.text);
+        } else {
+            parser.scan_text(&segment.text);
+        }
+    }
+
+    parser.finish()
+}
+
+impl Parser {
+    /// Remove a known file extension and remember it
+    fn strip_extension(&mut self, name: &str) -> String {
+        if let Some(pos) = name.rfind('.') {
+            let ext = name[pos + 1..].to_ascii_lowercase();
+            if KNOWN_EXTENSIONS.contains(&ext.as_str()) {
+                self.release.extension = Some(ext);
+                return name[..pos].to_string();
+            }
+        }
+        name.to_string()
+    }
+
+    /// Scan the contents of a bracketed segment; never contributes to the title
+    fn scan_bracket(&mut self, content: &str) {
+        let trimmed = content.trim();
+
+        // Whole-bracket checksum, year or bare episode number
+        if is_crc32(trimmed) {
+            self.release.checksum = Some(trimmed.to_ascii_uppercase());
+            return;
+        }
+        if let Some(year) = parse_year(trimmed) {
+            self.release.year = Some(year);
+            return;
+        }
+        if self.release.episode.is_none() {
+            if let Some(ep) = parse_episode_token(trimmed) {
+                self.apply(Meta::Episode(ep));
+                self.title_done = true;
+                return;
+            }
+        }
+
+        let tokens = tokenize_bracket(trimmed);
+        let mut found_meta = false;
+        let mut i = 0;
+        while i < tokens.len() {
+            if let Some((meta, consumed)) = classify_sequence(&tokens[i..], true) {
+                self.apply(meta);
+                found_meta = true;
+                i += consumed;
+            } else {
+                i += 1;
+            }
+        }
+
+        // Metadata after the title means the title has ended
+        if found_meta && !self.title_tokens.is_empty() {
+            self.title_done = true;
+        }
+    }
+
+    /// Scan a segment outside of brackets, collecting title words
+    fn scan_text(&mut self, text: &str) {
+        let normalized = normalize_separators(text);
+        let tokens: Vec<String> = normalized.split_whitespace().map(str::to_string).collect();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let tok = tokens[i].as_str();
+            let rest = &tokens[i..];
+            let is_last = i + 1 == tokens.len();
+
+            // "Title - 01", "Title - 01 ~ 12"
+            if is_dash(tok) {
+                if self.release.episode.is_none() {
+                    if let Some((ep, consumed)) = match_episode_run(&tokens[i + 1..], true) {
+                        self.apply(Meta::Episode(ep));
+                        self.title_done = true;
+                        i += 1 + consumed;
+                        continue;
+                    }
+                }
+                if !self.title_done {
+                    self.title_tokens.push(tok.to_string());
+                }
+                i += 1;
+                continue;
+            }
+
+            if let Some((meta, consumed)) = classify_sequence(rest, false) {
+                self.apply(meta);
+                self.title_done = true;
+                i += consumed;
+                continue;
+            }
+
+            // Bare episode number right before metadata or the end of the segment
+            if self.release.episode.is_none() && !self.title_tokens.is_empty() {
+                if let Some((ep, consumed)) = match_episode_run(rest, false) {
+                    let next = tokens.get(i + consumed).map(String::as_str);
+                    // A number right before a season marker belongs to the title
+                    // ("Mob Psycho 100 S3 - 01")
+                    let at_boundary = match classify_sequence(&tokens[i + consumed..], false) {
+                        Some((Meta::Season(_), _)) => false,
+                        Some(_) => true,
+                        None => next.is_none(),
+                    };
+                    let plausible = ep.start <= MAX_BARE_EPISODE || tok.starts_with('0');
+                    if at_boundary && plausible {
+                        self.apply(Meta::Episode(ep));
+                        self.title_done = true;
+                        i += consumed;
+                        continue;
+                    }
+                }
+            }
+
+            // Scene-style "H264-GROUP" suffix on the last token
+            if is_last && self.release.release_group.is_none() {
+                if let Some((left, group)) = tok.rsplit_once('-') {
+                    if !group.is_empty() {
+                        if let Some(meta) = classify_token(left, false) {
+                            self.apply(meta);
+                            self.release.release_group = Some(group.to_string());
+                            self.title_done = true;
+                            i += 1;
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            if !self.title_done {
+                self.title_tokens.push(tok.to_string());
+            }
+            i += 1;
+        }
+    }
+
+    /// Store a recognized piece of metadata (first occurrence wins)
+    fn apply(&mut self, meta: Meta) {
+        let release = &mut self.release;
+        match meta {
+            Meta::Resolution(r) => {
+                release.resolution.get_or_insert(r);
+            }
+            Meta::VideoCodec(c) => {
+                release.video_codec.get_or_insert_with(|| c.to_string());
+            }
+            Meta::BitDepth(d) => {
+                release.bit_depth.get_or_insert(d);
+            }
+            Meta::AudioCodec(c) => {
+                if !release.audio_codecs.iter().any(|a| a == c) {
+                    release.audio_codecs.push(c.to_string());
+                }
+            }
+            Meta::Source(s) => {
+                release.source.get_or_insert_with(|| s.to_string());
+            }
+            Meta::Season(s) => {
+                release.season.get_or_insert(s);
+            }
+            Meta::Episode(ep) => self.apply_episode(ep),
+            Meta::SeasonEpisode(s, ep) => {
+                release.season.get_or_insert(s);
+                self.apply_episode(ep);
+            }
+            Meta::Version(v) => {
+                release.version.get_or_insert(v);
+            }
+            Meta::Batch => self.batch_keyword = true,
+            Meta::Checksum(c) => {
+                release.checksum.get_or_insert(c);
+            }
+            Meta::Year(y) => {
+                release.year.get_or_insert(y);
+            }
+        }
+    }
+
+    fn apply_episode(&mut self, ep: EpisodeMatch) {
+        if self.release.episode.is_some() {
+            return;
+        }
+        self.release.episode = Some(ep.start);
+        self.release.episode_end = ep.end;
+        if let Some(v) = ep.version {
+            self.release.version.get_or_insert(v);
+        }
+    }
+
+    fn finish(mut self) -> ParsedRelease {
+        // Drop dangling separators from the end of the title
+        while self
+            .title_tokens
+            .last()
+            .map(|t| is_dash(t) || t == ":" || t == "|")
+            .unwrap_or(false)
+        {
+            self.title_tokens.pop();
+        }
+        while self.title_tokens.first().map(|t| is_dash(t)).unwrap_or(false) {
+            self.title_tokens.remove(0);
+        }
+
+        let mut release = self.release;
+        release.title = self.title_tokens.join(" ");
+        release.is_batch = self.batch_keyword
+            || release.episode_end.is_some()
+            || (release.episode.is_none() && release.season.is_some());
+        release
+    }
+}
+
+// =============================================================================
+// Tokenization
+// =============================================================================
+
+/// Split a name into bracketed and unbracketed segments
+fn split_segments(name: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut closing: Option<char> = None;
+
+    for c in name.chars() {
+        match closing {
+            Some(close) if c == close => {
+                segments.push(Segment { text: std::mem::take(&mut current), bracketed: true });
+                closing = None;
+            }
+            Some(_) => current.push(c),
+            None => {
+                let close = match c {
+                    '[' => Some(']'),
+                    '(' => Some(')'),
+                    '{' => Some('}'),
+                    '【' => Some('】'),
+                    _ => None,
+                };
+                if let Some(close) = close {
+                    if !current.trim().is_empty() {
+                        segments.push(Segment { text: std::mem::take(&mut current), bracketed: false });
+                    }
+                    current.clear();
+                    closing = Some(close);
+                } else {
+                    current.push(c);
+                }
+            }
+        }
+    }
+
+    // An unterminated bracket is treated as plain text
+    if !current.trim().is_empty() {
+        segments.push(Segment { text: current, bracketed: false });
+    }
+    segments
+}
+
+/// Split bracket contents like `BD 1080p HEVC, FLAC` into tokens
+fn tokenize_bracket(content: &str) -> Vec<String> {
+    content
+        .split(|c: char| c.is_whitespace() || c == '_' || c == ',' || c == '+')
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Replace underscores with spaces and, for dot-separated scene names,
+/// dots with spaces (keeping `5.1`, `2.0` and `H.264` intact)
+fn normalize_separators(text: &str) -> String {
+    let text = text.replace('_', " ");
+    let trimmed = text.trim();
+    if trimmed.contains(char::is_whitespace) || !trimmed.contains('.') {
+        return text;
+    }
+
+    let chars: Vec<char> = trimmed.chars().collect();
+    let mut out = String::with_capacity(chars.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if c != '.' {
+            out.push(c);
+            continue;
+        }
+        let prev = if i > 0 { Some(chars[i - 1]) } else { None };
+        // Channel layouts are single digits on both sides ("5.1"), unlike "E12.1080p"
+        let single_digit = |idx: Option<usize>, step: isize| {
+            let Some(idx) = idx else { return false };
+            let is_digit = |j: isize| {
+                j >= 0 && chars.get(j as usize).is_some_and(|c| c.is_ascii_digit())
+            };
+            is_digit(idx as isize) && !is_digit(idx as isize + step)
+        };
+        let between_digits = single_digit(i.checked_sub(1), -1) && single_digit(Some(i + 1), 1);
+        let codec_dot = prev.is_some_and(|p| matches!(p, 'h' | 'H' | 'x' | 'X'))
+            && chars.get(i + 1..i + 3) == Some(&['2', '6'][..]);
+        if between_digits {
+            out.push('.');
+        } else if !codec_dot {
+            out.push(' ');
+        }
+    }
+    out
+}
+
+fn is_dash(token: &str) -> bool {
+    matches!(token, "-" | "–" | "—")
+}
+
+// =============================================================================
+// Token Classification
+// =============================================================================
+
+/// Classify a run of tokens starting at `tokens[0]`; returns the metadata and
+/// how many tokens it consumed
+fn classify_sequence(tokens: &[String], bracketed: bool) -> Option<(Meta, usize)> {
+    let first = tokens.first()?;
+    let lower = first.to_ascii_lowercase();
+    let next = tokens.get(1).map(|t| t.to_ascii_lowercase());
+
+    // "Season 2"
+    if lower == "season" {
+        if let Some(n) = next.as_deref().and_then(parse_small_number) {
+            return Some((Meta::Season(n), 2));
+        }
+    }
+
+    // "2nd Season", "Second Season"
+    if next.as_deref() == Some("season") {
+        if let Some(n) = parse_ordinal(&lower) {
+            return Some((Meta::Season(n), 2));
+        }
+    }
+
+    // "Episode 12", "Ep 12"
+    if matches!(lower.as_str(), "episode" | "ep" | "ep.") {
+        if let Some((ep, consumed)) = match_episode_run(&tokens[1..], false) {
+            return Some((Meta::Episode(ep), 1 + consumed));
+        }
+    }
+
+    // "10 bit", "10 bits"
+    if matches!(next.as_deref(), Some("bit") | Some("bits")) {
+        if let Ok(depth) = lower.parse::<u8>() {
+            if depth == 8 || depth == 10 || depth == 12 {
+                return Some((Meta::BitDepth(depth), 2));
+            }
+        }
+    }
+
+    // "01 ~ 12" or "01 - 12" inside brackets
+    if bracketed {
+        if let Some((ep, consumed)) = match_episode_run(tokens, true) {
+            if ep.end.is_some() {
+                return Some((Meta::Episode(ep), consumed));
+            }
+        }
+    }
+
+    classify_token(first, bracketed).map(|meta| (meta, 1))
+}
+
+/// Classify a single token without context
+fn classify_token(token: &str, bracketed: bool) -> Option<Meta> {
+    let lower = token.to_ascii_lowercase();
+    let compact: String = lower.chars().filter(|c| !matches!(c, '.' | '-' | '_')).collect();
+
+    if let Some(r) = parse_resolution(&lower) {
+        return Some(Meta::Resolution(r));
+    }
+
+    let video = match compact.as_str() {
+        "x264" | "h264" | "avc" => Some("H.264"),
+        "x265" | "h265" | "hevc" | "hevc2" => Some("H.265"),
+        "av1" => Some("AV1"),
+        "vp9" => Some("VP9"),
+        "xvid" => Some("XviD"),
+        "divx" => Some("DivX"),
+        _ => None,
+    };
+    if let Some(codec) = video {
+        return Some(Meta::VideoCodec(codec));
+    }
+
+    match compact.as_str() {
+        "10bit" | "10bits" | "hi10" | "hi10p" | "yuv420p10" => return Some(Meta::BitDepth(10)),
+        "8bit" | "8bits" => return Some(Meta::BitDepth(8)),
+        _ => {}
+    }
+
+    if let Some(audio) = parse_audio_codec(&lower) {
+        return Some(Meta::AudioCodec(audio));
+    }
+
+    let source = match compact.as_str() {
+        "bd" | "bdrip" | "bluray" | "brrip" | "bdremux" | "bdmv" | "bddisc" => Some("BD"),
+        "web" | "webdl" | "webrip" | "cr" | "amzn" | "nf" | "dsnp" | "hidive" | "adn"
+        | "funi" | "bili" | "baha" | "abema" => Some("WEB"),
+        "dvd" | "dvdrip" | "dvd5" | "dvd9" => Some("DVD"),
+        "hdtv" | "tvrip" => Some("TV"),
+        _ => None,
+    };
+    if let Some(source) = source {
+        return Some(Meta::Source(source));
+    }
+
+    if BATCH_KEYWORDS.contains(&compact.as_str()) {
+        return Some(Meta::Batch);
+    }
+
+    if let Some(meta) = parse_season_episode(&lower) {
+        return Some(meta);
+    }
+
+    if let Some(v) = parse_version(&lower) {
+        return Some(Meta::Version(v));
+    }
+
+    if bracketed {
+        if is_crc32(token) {
+            return Some(Meta::Checksum(token.to_ascii_uppercase()));
+        }
+        if let Some(year) = parse_year(token) {
+            return Some(Meta::Year(year));
+        }
+    }
+
+    None
+}
+
+/// Match an episode number or range at the start of `tokens`:
+/// `01`, `01v2`, `01-12`, `01 ~ 12` and, if `allow_dash` is set, `01 - 12`
+fn match_episode_run(tokens: &[String], allow_dash: bool) -> Option<(EpisodeMatch, usize)> {
+    let first = parse_episode_token(tokens.first()?)?;
+    if first.end.is_none() {
+        let sep = tokens.get(1).map(String::as_str);
+        if sep == Some("~") || (allow_dash && sep.is_some_and(is_dash)) {
+            if let Some(last) = tokens.get(2).and_then(|t| parse_episode_token(t)) {
+                if last.end.is_none() && last.start > first.start {
+                    let ep = EpisodeMatch {
+                        start: first.start,
+                        end: Some(last.start),
+                        version: first.version.or(last.version),
+                    };
+                    return Some((ep, 3));
+                }
+            }
+        }
+    }
+    Some((first, 1))
+}
+
+/// Parse `12`, `12v2`, `01-12`, `01~12`, `E05` into an episode match
+fn parse_episode_token(token: &str) -> Option<EpisodeMatch> {
+    let lower = token.to_ascii_lowercase();
+
+    if let Some((a, b)) = lower.split_once(['-', '~']) {
+        let start = parse_numbered_version(a)?;
+        let end = parse_numbered_version(b)?;
+        if end.0 <= start.0 {
+            return None;
+        }
+        return Some(EpisodeMatch {
+            start: start.0,
+            end: Some(end.0),
+            version: start.1.or(end.1),
+        });
+    }
+
+    let (start, version) = parse_numbered_version(&lower)?;
+    Some(EpisodeMatch { start, end: None, version })
+}
+
+/// Parse `12` or `12v2` (at most 4 digits)
+fn parse_numbered_version(text: &str) -> Option<(u32, Option<u32>)> {
+    let (number, version) = match text.split_once('v') {
+        Some((n, v)) => (n, Some(v.parse::<u32>().ok()?)),
+        None => (text, None),
+    };
+    if number.is_empty() || number.len() > 4 || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((number.parse().ok()?, version))
+}
+
+/// Parse `S01E12`, `S01E12-E13`, `S01E12-13`, `S2`, `E12`, `EP12`
+fn parse_season_episode(lower: &str) -> Option<Meta> {
+    if let Some(rest) = lower.strip_prefix('s') {
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if digits.is_empty() || digits.len() > 2 {
+            return None;
+        }
+        let season: u32 = digits.parse().ok()?;
+        let after = &rest[digits.len()..];
+        if after.is_empty() {
+            return Some(Meta::Season(season));
+        }
+        let episode_part = after.strip_prefix('e')?;
+        let episode_part = episode_part.replace("-e", "-");
+        let ep = parse_episode_token(&episode_part)?;
+        return Some(Meta::SeasonEpisode(season, ep));
+    }
+
+    let rest = lower
+        .strip_prefix("ep")
+        .or_else(|| lower.strip_prefix('e'))?;
+    let rest = rest.trim_start_matches('.');
+    if rest.is_empty() || !rest.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    parse_episode_token(rest).map(Meta::Episode)
+}
+
+/// Parse `1080p`, `720p`, `1920x1080`, `4k`
+fn parse_resolution(lower: &str) -> Option<u32> {
+    if lower == "4k" || lower == "uhd" {
+        return Some(2160);
+    }
+    if let Some(number) = lower.strip_suffix('p').or_else(|| lower.strip_suffix('i')) {
+        if (3..=4).contains(&number.len()) && number.bytes().all(|b| b.is_ascii_digit()) {
+            let value: u32 = number.parse().ok()?;
+            if (240..=4320).contains(&value) {
+                return Some(value);
+            }
+        }
+        return None;
+    }
+    let (w, h) = lower.split_once('x')?;
+    let valid = |s: &str| (3..=4).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit());
+    if valid(w) && valid(h) {
+        return h.parse().ok();
+    }
+    None
+}
+
+/// Recognize audio codec tokens, including channel suffixes (`AAC2.0`, `DDP5.1`)
+fn parse_audio_codec(lower: &str) -> Option<&'static str> {
+    let compact: String = lower.chars().filter(|c| *c != '-').collect();
+    audio_codec_name(&compact).or_else(|| {
+        // Strip a trailing channel layout such as "2.0" or "5.1"
+        let base = compact.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        if base.len() < compact.len() {
+            audio_codec_name(base)
+        } else {
+            None
+        }
+    })
+}
+
+fn audio_codec_name(compact: &str) -> Option<&'static str> {
+    match compact {
+        "aac" | "heaac" => Some("AAC"),
+        "flac" => Some("FLAC"),
+        "opus" => Some("Opus"),
+        "ac3" | "dd" => Some("AC-3"),
+        "eac3" | "ddp" | "dd+" => Some("E-AC-3"),
+        "dts" | "dtshd" | "dtshdma" => Some("DTS"),
+        "truehd" => Some("TrueHD"),
+        "mp3" => Some("MP3"),
+        "vorbis" => Some("Vorbis"),
+        "lpcm" | "pcm" => Some("PCM"),
+        _ => None,
+    }
+}
+
+/// Parse `v2`
+fn parse_version(lower: &str) -> Option<u32> {
+    let digits = lower.strip_prefix('v')?;
+    if digits.len() == 1 && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return digits.parse().ok();
+    }
+    None
+}
+
+/// Parse small integers used with "Season N"
+fn parse_small_number(text: &str) -> Option<u32> {
+    if text.len() <= 2 {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Parse ordinals like `2nd` or `second`
+fn parse_ordinal(lower: &str) -> Option<u32> {
+    let words = ["first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth"];
+    if let Some(pos) = words.iter().position(|w| *w == lower) {
+        return Some(pos as u32 + 1);
+    }
+    let number = lower
+        .strip_suffix("st")
+        .or_else(|| lower.strip_suffix("nd"))
+        .or_else(|| lower.strip_suffix("rd"))
+        .or_else(|| lower.strip_suffix("th"))?;
+    parse_small_number(number)
+}
+
+/// A 4-digit year between 1950 and 2099
+fn parse_year(text: &str) -> Option<u32> {
+    if text.len() != 4 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year: u32 = text.parse().ok()?;
+    if (1950..=2099).contains(&year) {
+        Some(year)
+    } else {
+        None
+    }
+}
+
+/// An 8-character hexadecimal CRC32 tag
+fn is_crc32(text: &str) -> bool {
+    text.len() == 8 && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Parse a single release name
+#[tauri::command]
+pub fn release_parse(name: String) -> ParsedRelease {
+    parse_release_name(&name)
+}
+
+/// Parse several release names at once (e.g. a whole search result page)
+#[tauri::command]
+pub fn release_parse_many(names: Vec<String>) -> Vec<ParsedRelease> {
+    names.iter().map(|n| parse_release_name(n)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Real-world release names with the fields we expect to extract
+    const FIXTURES: &str = include_str!("../tests/fixtures/release_names.json");
+
+    #[test]
+    fn test_fixture_corpus() {
+        let fixtures: Vec<serde_json::Value> = serde_json::from_str(FIXTURES).unwrap();
+        assert!(fixtures.len() >= 50);
+
+        let mut failures = Vec::new();
+        for fixture in &fixtures {
+            let name = fixture["name"].as_str().unwrap();
+            let parsed = serde_json::to_value(parse_release_name(name)).unwrap();
+            for (key, expected) in fixture["expected"].as_object().unwrap() {
+                if &parsed[key] != expected {
+                    failures.push(format!(
+                        "{}: {} = {} (expected {})",
+                        name, key, parsed[key], expected
+                    ));
+                }
+            }
+        }
+        assert!(failures.is_empty(), "fixture mismatches:\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_parse_subsplease() {
+        let release = parse_release_name("[SubsPlease] Frieren - 12 (1080p) [ABCD1234].mkv");
+        assert_eq!(release.title, "Frieren");
+        assert_eq!(release.episode, Some(12));
+        assert_eq!(release.resolution, Some(1080));
+        assert_eq!(release.release_group.as_deref(), Some("SubsPlease"));
+        assert_eq!(release.checksum.as_deref(), Some("ABCD1234"));
+        assert_eq!(release.extension.as_deref(), Some("mkv"));
+        assert!(!release.is_batch);
+    }
+
+    #[test]
+    fn test_parse_version_and_range() {
+        let release = parse_release_name("[Group] Title - 05v2 [720p]");
+        assert_eq!(release.episode, Some(5));
+        assert_eq!(release.version, Some(2));
+
+        let batch = parse_release_name("[Group] Title (01-12) [BD 1080p]");
+        assert_eq!(batch.episode, Some(1));
+        assert_eq!(batch.episode_end, Some(12));
+        assert!(batch.is_batch);
+    }
+
+    #[test]
+    fn test_parse_scene_style() {
+        let release = parse_release_name("Frieren.Beyond.Journeys.End.S01E12.1080p.WEB.H264-VARYG.mkv");
+        assert_eq!(release.title, "Frieren Beyond Journeys End");
+        assert_eq!(release.season, Some(1));
+        assert_eq!(release.episode, Some(12));
+        assert_eq!(release.video_codec.as_deref(), Some("H.264"));
+        assert_eq!(release.release_group.as_deref(), Some("VARYG"));
+    }
+
+    #[test]
+    fn test_parse_garbage_input() {
+        assert_eq!(parse_release_name("").title, "");
+        let release = parse_release_name("[[[(((");
+        assert!(release.episode.is_none());
+        let release = parse_release_name("- - -");
+        assert_eq!(release.title, "");
+    }
+}
@@ -0,0 +1,770 @@
+//! Cross-Provider Search
+//!
+//! Searches every enabled media-provider plugin at once, replacing
+//! `ZPEPluginAPI.searchAll`, which returned one unmerged list per provider. Providers
+//! are called concurrently through the plugin host, each with its own timeout, and a
+//! failing or slow provider only shows up in the per-provider status.
+//!
+//! The query is also sent to AniList, and every provider result is fuzzy-matched to the
+//! AniList entries: an AniList or MAL ID given by the plugin wins, otherwise titles
+//! (including synonyms) are compared and the release year and episode count break ties
+//! between seasons. Results are returned as one group per anime with the provider
+//! sources listed under it; results without a match are grouped by title.
+
+use crate::plugin_host;
+use crate::plugins::{self, InstalledPlugin};
+use crate::profiles::{self, ProfileState};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_http::reqwest;
+
+/// AniList GraphQL endpoint
+const ANILIST_GRAPHQL_URL: &str = "https://graphql.anilist.co";
+
+/// AniList entries fetched per search
+const ANILIST_RESULTS: u32 = 15;
+
+/// How long AniList search results are reused
+const ANILIST_CACHE_TTL_MS: i64 = 10 * 60 * 1000;
+
+/// Default time a provider gets to answer
+const DEFAULT_PROVIDER_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Timeout of AniList requests
+const ANILIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lowest score for a provider result to be matched to an AniList entry
+const MATCH_THRESHOLD: f64 = 0.7;
+
+/// Plugin types searched
+const MEDIA_PROVIDER_TYPES: &[&str] = &["media-provider", "mediaProvider"];
+
+const ANILIST_SEARCH_QUERY: &str = "query ($search: String, $perPage: Int) {
+  Page(perPage: $perPage) {
+    media(search: $search, type: ANIME, sort: SEARCH_MATCH) {
+      id idMal
+      title { romaji english native userPreferred }
+      synonyms seasonYear startDate { year } episodes format status
+      coverImage { large }
+    }
+  }
+}";
+
+/// An AniList entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnilistMedia {
+    pub id: u64,
+    #[serde(default)]
+    pub id_mal: Option<u64>,
+    pub title: AnilistTitle,
+    #[serde(default)]
+    pub synonyms: Vec<String>,
+    #[serde(default)]
+    pub season_year: Option<i32>,
+    #[serde(default)]
+    pub start_date: Option<AnilistDate>,
+    #[serde(default)]
+    pub episodes: Option<u32>,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub cover_image: Option<AnilistCover>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnilistTitle {
+    pub romaji: Option<String>,
+    pub english: Option<String>,
+    pub native: Option<String>,
+    pub user_preferred: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnilistDate {
+    pub year: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnilistCover {
+    pub large: Option<String>,
+}
+
+impl AnilistMedia {
+    fn year(&self) -> Option<i32> {
+        self.season_year.or_else(|| self.start_date.as_ref().and_then(|d| d.year))
+    }
+
+    fn display_title(&self) -> String {
+        let title = &self.title;
+        title
+            .user_preferred
+            .clone()
+            .or_else(|| title.romaji.clone())
+            .or_else(|| title.english.clone())
+            .unwrap_or_else(|| self.id.to_string())
+    }
+
+    /// All titles the entry is known by
+    fn titles(&self) -> impl Iterator<Item = &str> {
+        let title = &self.title;
+        [&title.romaji, &title.english, &title.native, &title.user_preferred]
+            .into_iter()
+            .flatten()
+            .chain(self.synonyms.iter())
+            .map(String::as_str)
+    }
+}
+
+/// The fields of a provider result used for matching (`ZPEAnime`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderAnime {
+    pub id: String,
+    pub title: String,
+    pub alt_titles: Vec<String>,
+    pub anilist_id: Option<u64>,
+    pub mal_id: Option<u64>,
+    pub year: Option<i32>,
+    pub episode_count: Option<u32>,
+    pub cover: Option<String>,
+    /// The result as returned by the plugin
+    pub raw: Value,
+}
+
+/// One provider's answer
+#[derive(Debug, Clone)]
+pub struct ProviderResults {
+    pub plugin_id: String,
+    pub plugin_name: String,
+    pub results: Vec<ProviderAnime>,
+}
+
+/// A provider result listed under a group
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSource {
+    pub plugin_id: String,
+    pub plugin_name: String,
+    pub anime_id: String,
+    pub title: String,
+    /// Confidence of the AniList match (0-1)
+    pub match_score: Option<f64>,
+    pub item: Value,
+}
+
+/// One anime with the provider results for it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchGroup {
+    /// `anilist:<id>`, or `title:<normalized title>` for unmatched results
+    pub key: String,
+    pub title: String,
+    pub anilist: Option<AnilistMedia>,
+    pub cover: Option<String>,
+    pub year: Option<i32>,
+    pub episode_count: Option<u32>,
+    pub sources: Vec<ProviderSource>,
+}
+
+/// How a provider's search went
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSearchStatus {
+    pub plugin_id: String,
+    pub name: String,
+    pub result_count: usize,
+    pub has_next_page: bool,
+    pub elapsed_ms: u64,
+    pub timed_out: bool,
+    pub error: Option<String>,
+}
+
+/// Result of an aggregated search
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregatedSearch {
+    pub query: String,
+    pub page: u32,
+    pub groups: Vec<SearchGroup>,
+    pub providers: Vec<ProviderSearchStatus>,
+    /// Set when AniList couldn't be searched (results are then grouped by title)
+    pub anilist_error: Option<String>,
+}
+
+/// Search aggregator state: AniList client and recent AniList searches
+pub struct SearchAggregatorState {
+    client: reqwest::Client,
+    anilist_cache: Mutex<HashMap<String, (i64, Vec<AnilistMedia>)>>,
+}
+
+impl Default for SearchAggregatorState {
+    fn default() -> Self {
+        SearchAggregatorState {
+            client: reqwest::Client::builder()
+                .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+                .timeout(ANILIST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            anilist_cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Provider Results
+// =============================================================================
+
+/// Read an ID that plugins may return as a number or a string
+fn value_id(value: Option<&Value>) -> Option<u64> {
+    match value? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn value_string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse a `ZPEPaginatedResult<ZPEAnime>`; results without an ID or title are skipped.
+/// Returns the results and whether there is a next page.
+pub fn parse_provider_results(value: &Value) -> (Vec<ProviderAnime>, bool) {
+    let items = value
+        .get("results")
+        .or_else(|| value.get("items"))
+        .and_then(Value::as_array)
+        .or_else(|| value.as_array());
+    let has_next_page = value.get("hasNextPage").and_then(Value::as_bool).unwrap_or(false);
+
+    let results = items
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            Some(ProviderAnime {
+                id: value_string(item.get("id"))?,
+                title: value_string(item.get("title"))?,
+                alt_titles: item
+                    .get("altTitles")
+                    .and_then(Value::as_array)
+                    .map(|titles| titles.iter().filter_map(|t| value_string(Some(t))).collect())
+                    .unwrap_or_default(),
+                anilist_id: value_id(item.get("anilistId")),
+                mal_id: value_id(item.get("malId")),
+                year: value_id(item.get("year")).and_then(|y| i32::try_from(y).ok()),
+                episode_count: value_id(item.get("episodeCount")).and_then(|n| u32::try_from(n).ok()),
+                cover: value_string(item.get("cover")),
+                raw: item.clone(),
+            })
+        })
+        .collect();
+    (results, has_next_page)
+}
+
+/// Enabled media providers that can search, for the active profile
+fn search_providers(app: &AppHandle, plugin_ids: Option<&[String]>) -> Vec<InstalledPlugin> {
+    let profile_id = profiles::active_profile_id(app, &app.state::<ProfileState>());
+    let mut providers: Vec<InstalledPlugin> = plugins::installed_plugins(app)
+        .into_iter()
+        .filter(|p| p.is_enabled_for(profile_id.as_deref()))
+        .filter(|p| {
+            let manifest = &p.current.manifest;
+            manifest
+                .plugin_type
+                .as_deref()
+                .is_some_and(|t| MEDIA_PROVIDER_TYPES.contains(&t))
+                && manifest
+                    .extra
+                    .get("capabilities")
+                    .and_then(|c| c.get("search"))
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false)
+        })
+        .filter(|p| plugin_ids.map_or(true, |ids| ids.contains(&p.id)))
+        .collect();
+    providers.sort_by(|a, b| a.current.manifest.name.cmp(&b.current.manifest.name));
+    providers
+}
+
+// =============================================================================
+// Matching
+// =============================================================================
+
+/// Numbers in a normalized title (`2nd` counts as 2), which tell seasons apart
+fn title_numbers(normalized: &str) -> HashSet<u64> {
+    normalized
+        .split(' ')
+        .filter_map(|word| {
+            let digits: String = word.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+fn bigrams(text: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = text.chars().filter(|c| *c != ' ').collect();
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+/// Similarity of two titles (0-1): Dice coefficient of character bigrams, at least 0.8
+/// when one title starts with the other (`Frieren` / `Frieren: Beyond Journey's End`),
+/// reduced when the titles carry different numbers
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (crate::subscriptions::normalize_title(a), crate::subscriptions::normalize_title(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut score = if a == b {
+        1.0
+    } else {
+        let (left, right) = (bigrams(&a), bigrams(&b));
+        let mut unused = right.clone();
+        let mut common = 0;
+        for pair in &left {
+            if let Some(position) = unused.iter().position(|p| p == pair) {
+                unused.swap_remove(position);
+                common += 1;
+            }
+        }
+        let dice = if left.is_empty() || right.is_empty() {
+            0.0
+        } else {
+            2.0 * common as f64 / (left.len() + right.len()) as f64
+        };
+
+        let (short, long) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+        let is_prefix = short.len() >= 4 && long.starts_with(short.as_str()) && long[short.len()..].starts_with(' ');
+        if is_prefix {
+            dice.max(0.8)
+        } else {
+            dice
+        }
+    };
+
+    if title_numbers(&a) != title_numbers(&b) {
+        score *= 0.8;
+    }
+    score
+}
+
+/// How well a provider result matches an AniList entry (0-1)
+pub fn match_score(anime: &ProviderAnime, media: &AnilistMedia) -> f64 {
+    // IDs from the plugin are authoritative either way
+    if let Some(id) = anime.anilist_id {
+        return if id == media.id { 1.0 } else { 0.0 };
+    }
+    if let (Some(id), Some(mal)) = (anime.mal_id, media.id_mal) {
+        return if id == mal { 1.0 } else { 0.0 };
+    }
+
+    let mut score = std::iter::once(&anime.title)
+        .chain(anime.alt_titles.iter())
+        .flat_map(|title| media.titles().map(move |candidate| title_similarity(title, candidate)))
+        .fold(0.0, f64::max);
+
+    if let (Some(year), Some(media_year)) = (anime.year, media.year()) {
+        match (year - media_year).abs() {
+            0 => score += 0.1,
+            1 => {}
+            _ => score -= 0.25,
+        }
+    }
+    // Airing shows list fewer episodes than they will have
+    let airing = media.status.as_deref() == Some("RELEASING");
+    if let (Some(count), Some(episodes)) = (anime.episode_count, media.episodes) {
+        if count == episodes {
+            score += 0.1;
+        } else if !airing {
+            score -= 0.15;
+        }
+    }
+    score.clamp(0.0, 1.0)
+}
+
+/// Best AniList match of a provider result: index into `media` and score
+fn best_match(anime: &ProviderAnime, media: &[AnilistMedia]) -> Option<(usize, f64)> {
+    media
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (i, match_score(anime, m)))
+        .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+        // On a tie the entry ranked higher by AniList wins
+        .fold(None, |best: Option<(usize, f64)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+}
+
+/// Group provider results by anime. Matched groups come first, in AniList's order;
+/// unmatched results follow in the order they were found.
+pub fn group_results(media: &[AnilistMedia], providers: &[ProviderResults]) -> Vec<SearchGroup> {
+    let mut matched: Vec<Option<SearchGroup>> = vec![None; media.len()];
+    let mut unmatched: Vec<SearchGroup> = Vec::new();
+
+    for provider in providers {
+        for anime in &provider.results {
+            let found = best_match(anime, media);
+            let source = ProviderSource {
+                plugin_id: provider.plugin_id.clone(),
+                plugin_name: provider.plugin_name.clone(),
+                anime_id: anime.id.clone(),
+                title: anime.title.clone(),
+                match_score: found.map(|(_, score)| (score * 100.0).round() / 100.0),
+                item: anime.raw.clone(),
+            };
+
+            match found {
+                Some((index, _)) => {
+                    let entry = &media[index];
+                    matched[index]
+                        .get_or_insert_with(|| SearchGroup {
+                            key: format!("anilist:{}", entry.id),
+                            title: entry.display_title(),
+                            anilist: Some(entry.clone()),
+                            cover: entry.cover_image.as_ref().and_then(|c| c.large.clone()),
+                            year: entry.year(),
+                            episode_count: entry.episodes,
+                            sources: Vec::new(),
+                        })
+                        .sources
+                        .push(source);
+                }
+                None => {
+                    let key = format!("title:{}", crate::subscriptions::normalize_title(&anime.title));
+                    match unmatched.iter_mut().find(|g| g.key == key) {
+                        Some(group) => {
+                            group.cover = group.cover.take().or_else(|| anime.cover.clone());
+                            group.year = group.year.or(anime.year);
+                            group.episode_count = group.episode_count.or(anime.episode_count);
+                            group.sources.push(source);
+                        }
+                        None => unmatched.push(SearchGroup {
+                            key,
+                            title: anime.title.clone(),
+                            anilist: None,
+                            cover: anime.cover.clone(),
+                            year: anime.year,
+                            episode_count: anime.episode_count,
+                            sources: vec![source],
+                        }),
+                    }
+                }
+            }
+        }
+    }
+
+    matched.into_iter().flatten().chain(unmatched).collect()
+}
+
+// =============================================================================
+// Searching
+// =============================================================================
+
+/// Search AniList (cached for a few minutes per query)
+async fn search_anilist(state: &SearchAggregatorState, query: &str) -> Result<Vec<AnilistMedia>, String> {
+    let key = crate::subscriptions::normalize_title(query);
+    let now = get_current_timestamp();
+    if let Ok(cache) = state.anilist_cache.lock() {
+        if let Some((fetched_at, media)) = cache.get(&key) {
+            if now - fetched_at < ANILIST_CACHE_TTL_MS {
+                return Ok(media.clone());
+            }
+        }
+    }
+
+    let body = json!({
+        "query": ANILIST_SEARCH_QUERY,
+        "variables": { "search": query, "perPage": ANILIST_RESULTS }
+    });
+    let response = state
+        .client
+        .post(ANILIST_GRAPHQL_URL)
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|e| format!("AniList request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("AniList request failed: HTTP {}", response.status().as_u16()));
+    }
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read AniList response: {}", e))?;
+    let media = parse_anilist_response(&text)?;
+
+    if let Ok(mut cache) = state.anilist_cache.lock() {
+        cache.retain(|_, (fetched_at, _)| now - *fetched_at < ANILIST_CACHE_TTL_MS);
+        cache.insert(key, (now, media.clone()));
+    }
+    Ok(media)
+}
+
+/// Read the entries of an AniList `Page.media` response
+fn parse_anilist_response(text: &str) -> Result<Vec<AnilistMedia>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| format!("Invalid AniList response: {}", e))?;
+    if let Some(message) = value.pointer("/errors/0/message").and_then(Value::as_str) {
+        return Err(format!("AniList error: {}", message));
+    }
+    let media = value
+        .pointer("/data/Page/media")
+        .cloned()
+        .ok_or_else(|| "AniList response has no results".to_string())?;
+    serde_json::from_value(media).map_err(|e| format!("Invalid AniList response: {}", e))
+}
+
+/// Search one provider with a timeout
+async fn search_provider(
+    app: AppHandle,
+    plugin: InstalledPlugin,
+    query: String,
+    page: u32,
+    timeout: Duration,
+) -> (ProviderSearchStatus, Vec<ProviderAnime>) {
+    let started = Instant::now();
+    let call = plugin_host::call_plugin(app, plugin.id.clone(), "search", vec![query.into(), page.into()]);
+    let result = tokio::time::timeout(timeout, call).await;
+
+    let mut status = ProviderSearchStatus {
+        plugin_id: plugin.id.clone(),
+        name: plugin.current.manifest.name.clone(),
+        result_count: 0,
+        has_next_page: false,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        timed_out: false,
+        error: None,
+    };
+    let results = match result {
+        Ok(Ok(value)) => {
+            let (results, has_next_page) = parse_provider_results(&value);
+            status.result_count = results.len();
+            status.has_next_page = has_next_page;
+            results
+        }
+        Ok(Err(e)) => {
+            log::warn!("Search of provider {} failed: {}", plugin.id, e);
+            status.error = Some(e);
+            Vec::new()
+        }
+        Err(_) => {
+            log::warn!("Search of provider {} timed out", plugin.id);
+            status.timed_out = true;
+            status.error = Some(format!("No answer within {} ms", timeout.as_millis()));
+            Vec::new()
+        }
+    };
+    (status, results)
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Search all enabled media providers (or the given ones) and group the results by
+/// anime, matched against AniList unless `match_anilist` is false
+#[tauri::command]
+pub async fn search_aggregate(
+    query: String,
+    page: Option<u32>,
+    plugin_ids: Option<Vec<String>>,
+    timeout_ms: Option<u64>,
+    match_anilist: Option<bool>,
+    app: AppHandle,
+    state: State<'_, SearchAggregatorState>,
+) -> Result<AggregatedSearch, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Err("Search query cannot be empty".to_string());
+    }
+    let page = page.unwrap_or(1).max(1);
+    let timeout = timeout_ms.map(Duration::from_millis).unwrap_or(DEFAULT_PROVIDER_TIMEOUT);
+
+    let providers = search_providers(&app, plugin_ids.as_deref());
+    if providers.is_empty() {
+        return Err("No enabled media provider can search".to_string());
+    }
+
+    let handles: Vec<_> = providers
+        .into_iter()
+        .map(|plugin| tauri::async_runtime::spawn(search_provider(app.clone(), plugin, query.clone(), page, timeout)))
+        .collect();
+
+    let (media, anilist_error) = if match_anilist.unwrap_or(true) {
+        match search_anilist(&state, &query).await {
+            Ok(media) => (media, None),
+            Err(e) => {
+                log::warn!("AniList search for '{}' failed: {}", query, e);
+                (Vec::new(), Some(e))
+            }
+        }
+    } else {
+        (Vec::new(), None)
+    };
+
+    let mut statuses = Vec::new();
+    let mut results = Vec::new();
+    for handle in handles {
+        match handle.await {
+            Ok((status, anime)) => {
+                results.push(ProviderResults {
+                    plugin_id: status.plugin_id.clone(),
+                    plugin_name: status.name.clone(),
+                    results: anime,
+                });
+                statuses.push(status);
+            }
+            Err(e) => log::warn!("Provider search task failed: {}", e),
+        }
+    }
+
+    let groups = group_results(&media, &results);
+    log::info!(
+        "Search '{}' found {} anime across {} providers ({} failed)",
+        query,
+        groups.len(),
+        statuses.len(),
+        statuses.iter().filter(|s| s.error.is_some()).count()
+    );
+    Ok(AggregatedSearch {
+        query,
+        page,
+        groups,
+        providers: statuses,
+        anilist_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANILIST_FIXTURE: &str = include_str!("../tests/fixtures/anilist_search.json");
+
+    fn media() -> Vec<AnilistMedia> {
+        parse_anilist_response(ANILIST_FIXTURE).unwrap()
+    }
+
+    fn anime(item: Value) -> ProviderAnime {
+        let (mut results, _) = parse_provider_results(&json!({ "results": [item] }));
+        results.remove(0)
+    }
+
+    fn provider(id: &str, items: Value) -> ProviderResults {
+        ProviderResults {
+            plugin_id: id.to_string(),
+            plugin_name: id.to_uppercase(),
+            results: parse_provider_results(&json!({ "results": items })).0,
+        }
+    }
+
+    #[test]
+    fn test_parse_provider_results() {
+        let (results, has_next_page) = parse_provider_results(&json!({
+            "results": [
+                { "id": 123, "title": " Frieren ", "anilistId": "154587", "episodeCount": 28 },
+                { "id": "no-title" },
+                { "title": "No ID" },
+                { "id": "aot", "title": "Attack on Titan", "altTitles": ["Shingeki no Kyojin", 5], "year": 2013 }
+            ],
+            "hasNextPage": true
+        }));
+        assert!(has_next_page);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "123");
+        assert_eq!(results[0].title, "Frieren");
+        assert_eq!(results[0].anilist_id, Some(154587));
+        assert_eq!(results[1].alt_titles, vec!["Shingeki no Kyojin", "5"]);
+        assert_eq!(results[1].year, Some(2013));
+    }
+
+    #[test]
+    fn test_title_similarity() {
+        assert_eq!(title_similarity("Frieren: Beyond Journey's End", "frieren beyond journey s end"), 1.0);
+        assert!(title_similarity("Frieren", "Frieren: Beyond Journey's End") >= 0.8);
+        assert!(title_similarity("Shingeki no Kyojin 2nd Season", "Shingeki no Kyojin Season 2") > 0.8);
+        // Another season
+        assert!(title_similarity("Attack on Titan", "Attack on Titan Season 2") < MATCH_THRESHOLD);
+        assert!(title_similarity("Naruto", "Bleach") < 0.2);
+        assert_eq!(title_similarity("", "Bleach"), 0.0);
+    }
+
+    #[test]
+    fn test_match_score() {
+        let media = media();
+        let best = |item| best_match(&anime(item), &media).map(|(i, _)| media[i].id);
+
+        assert_eq!(best(json!({ "id": "1", "title": "Attack on Titan", "year": 2013, "episodeCount": 25 })), Some(16498));
+        assert_eq!(best(json!({ "id": "2", "title": "Attack on Titan Season 2" })), Some(20958));
+        assert_eq!(best(json!({ "id": "3", "title": "Shingeki no Kyojin 2nd Season" })), Some(20958));
+        // Synonyms and a title without the subtitle
+        assert_eq!(best(json!({ "id": "4", "title": "Frieren at the Funeral" })), Some(154587));
+        assert_eq!(best(json!({ "id": "5", "title": "Frieren", "year": 2023 })), Some(154587));
+        // The episode count tells the OVA apart
+        assert_eq!(best(json!({ "id": "6", "title": "Shingeki no Kyojin", "episodeCount": 25 })), Some(16498));
+        assert_eq!(best(json!({ "id": "7", "title": "Shingeki no Kyojin", "episodeCount": 3 })), Some(18397));
+        // IDs given by the plugin win over titles
+        assert_eq!(best(json!({ "id": "8", "title": "Something else", "malId": 25777 })), Some(20958));
+        assert_eq!(best(json!({ "id": "9", "title": "Attack on Titan", "anilistId": 1 })), None);
+        assert_eq!(best(json!({ "id": "10", "title": "Completely Unrelated Show" })), None);
+    }
+
+    #[test]
+    fn test_group_results() {
+        let media = media();
+        let providers = [
+            provider("alpha", json!([
+                { "id": "a-aot", "title": "Attack on Titan", "year": 2013 },
+                { "id": "a-frieren", "title": "Frieren", "year": 2023 },
+                { "id": "a-other", "title": "Mystery Show" }
+            ])),
+            provider("beta", json!([
+                { "id": "b-frieren", "title": "Sousou no Frieren" },
+                { "id": "b-other", "title": "Mystery  show!", "cover": "https://example.com/c.jpg" },
+                { "id": "b-aot2", "title": "Shingeki no Kyojin Season 2" }
+            ])),
+        ];
+
+        let groups = group_results(&media, &providers);
+        let keys: Vec<&str> = groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, ["anilist:16498", "anilist:20958", "anilist:154587", "title:mystery show"]);
+
+        let frieren = &groups[2];
+        assert_eq!(frieren.title, "Sousou no Frieren");
+        assert_eq!(frieren.episode_count, Some(28));
+        let sources: Vec<(&str, &str)> = frieren
+            .sources
+            .iter()
+            .map(|s| (s.plugin_id.as_str(), s.anime_id.as_str()))
+            .collect();
+        assert_eq!(sources, [("alpha", "a-frieren"), ("beta", "b-frieren")]);
+        assert_eq!(frieren.sources[1].match_score, Some(1.0));
+
+        let mystery = &groups[3];
+        assert!(mystery.anilist.is_none());
+        assert_eq!(mystery.sources.len(), 2);
+        assert_eq!(mystery.cover.as_deref(), Some("https://example.com/c.jpg"));
+
+        // Without AniList everything is grouped by title
+        assert_eq!(group_results(&[], &providers).len(), 5);
+    }
+}
@@ -0,0 +1,873 @@
+//! Seeding Manager
+//!
+//! This module decides which finished torrents keep seeding. Instead of the torrent
+//! backend re-seeding everything it finds on disk, every completed torrent is tracked
+//! here with its cumulative upload and seeding time, and a background monitor applies
+//! the seeding policy:
+//!
+//! - Ratio target and maximum seeding time, globally or overridden per torrent. A
+//!   torrent that reaches its goal is removed from the backend (files are kept).
+//! - "Stop seeding on metered connection" and "pause while playing" rules, based on
+//!   conditions reported by the frontend. These pause seeding until the condition ends.
+//!
+//! Tracked torrents are kept in `seeding.json` and restored on the next start until
+//! their goal is reached.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::downloads::{BackendTorrent, TorrentBackend};
+
+/// Store file name for seeding persistence
+const SEEDING_STORE_FILE: &str = "seeding.json";
+
+/// Store key for tracked torrents
+const ITEMS_KEY: &str = "items";
+
+/// Store key for the seeding configuration
+const CONFIG_KEY: &str = "config";
+
+/// Event emitted after every policy pass
+pub const SEEDING_UPDATED_EVENT: &str = "seeding-updated";
+
+/// Seconds between two policy passes
+const MONITOR_INTERVAL_SECS: u64 = 30;
+
+/// Ratio and time goals; `None` means no limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedingPolicy {
+    /// Stop once uploaded / size reaches this ratio
+    pub ratio_target: Option<f64>,
+    /// Stop after seeding for this many minutes
+    pub max_seeding_minutes: Option<u64>,
+}
+
+impl Default for SeedingPolicy {
+    fn default() -> Self {
+        SeedingPolicy {
+            ratio_target: Some(1.0),
+            max_seeding_minutes: Some(7 * 24 * 60),
+        }
+    }
+}
+
+/// Global seeding configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SeedingConfig {
+    /// Whether finished torrents are seeded at all
+    pub enabled: bool,
+    /// Goals for torrents without their own policy
+    pub policy: SeedingPolicy,
+    /// Pause seeding while the connection is metered
+    pub stop_on_metered: bool,
+    /// Pause seeding while a video is playing
+    pub pause_while_playing: bool,
+}
+
+impl Default for SeedingConfig {
+    fn default() -> Self {
+        SeedingConfig {
+            enabled: true,
+            policy: SeedingPolicy::default(),
+            stop_on_metered: true,
+            pause_while_playing: false,
+        }
+    }
+}
+
+/// Seeding status of a tracked torrent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeedingStatus {
+    /// Uploading to peers
+    Seeding,
+    /// Temporarily paused by a rule (see `pause_reason`)
+    Paused,
+    /// Ratio or time goal reached
+    Finished,
+    /// Stopped by the user
+    Stopped,
+}
+
+/// Why seeding is temporarily paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PauseReason {
+    /// Seeding is disabled globally
+    Disabled,
+    /// The connection is metered
+    Metered,
+    /// A video is playing
+    Playing,
+}
+
+/// A finished torrent tracked by the seeding manager
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedingItem {
+    /// Lowercase hex infohash
+    pub infohash: String,
+    /// Torrent name
+    pub name: String,
+    /// Magnet URI (used to restore seeding after a restart)
+    pub magnet: String,
+    /// Folder the content is saved in
+    pub folder: Option<String>,
+    /// Total size in bytes
+    pub size_bytes: u64,
+    /// Uploaded bytes across all sessions
+    pub uploaded_bytes: u64,
+    /// Current upload speed in bytes per second
+    pub upload_speed: f64,
+    /// Connected peers
+    pub peers: u32,
+    /// Time spent seeding in seconds
+    pub seeding_seconds: u64,
+    /// Current status
+    pub status: SeedingStatus,
+    /// Why seeding is paused (for `Paused`)
+    pub pause_reason: Option<PauseReason>,
+    /// Policy overriding the global one for this torrent
+    pub policy: Option<SeedingPolicy>,
+    /// When tracking started (Unix milliseconds)
+    pub added_at: i64,
+    /// When the goal was reached (Unix milliseconds)
+    pub finished_at: Option<i64>,
+    /// Upload counter last reported by the backend for this session
+    #[serde(default)]
+    pub session_uploaded: u64,
+}
+
+impl SeedingItem {
+    /// Upload ratio (uploaded / size)
+    pub fn ratio(&self) -> f64 {
+        if self.size_bytes == 0 {
+            0.0
+        } else {
+            self.uploaded_bytes as f64 / self.size_bytes as f64
+        }
+    }
+}
+
+/// Seeding item as returned to the frontend, with derived values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedingItemInfo {
+    #[serde(flatten)]
+    pub item: SeedingItem,
+    pub ratio: f64,
+    /// Policy in effect (the item's own or the global one)
+    pub effective_policy: SeedingPolicy,
+}
+
+/// Conditions reported by the frontend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedingConditions {
+    pub metered: bool,
+    pub playing: bool,
+}
+
+/// What the policy wants for a torrent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedingDecision {
+    /// Keep (or resume) seeding
+    Seed,
+    /// Pause until the reason goes away
+    Pause(PauseReason),
+    /// Goal reached, stop for good
+    Finish,
+}
+
+/// Seeding manager state (in-memory cache of the store)
+pub struct SeedingState {
+    items: Mutex<Vec<SeedingItem>>,
+    config: Mutex<SeedingConfig>,
+    conditions: Mutex<SeedingConditions>,
+    loaded: Mutex<bool>,
+    /// Whether tracked torrents were handed back to the backend after startup
+    restored: Mutex<bool>,
+    /// Timestamp of the previous policy pass (Unix milliseconds)
+    last_pass: Mutex<Option<i64>>,
+}
+
+impl Default for SeedingState {
+    fn default() -> Self {
+        SeedingState {
+            items: Mutex::new(Vec::new()),
+            config: Mutex::new(SeedingConfig::default()),
+            conditions: Mutex::new(SeedingConditions::default()),
+            loaded: Mutex::new(false),
+            restored: Mutex::new(false),
+            last_pass: Mutex::new(None),
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Policy
+// =============================================================================
+
+/// Policy in effect for an item
+pub fn effective_policy<'a>(item: &'a SeedingItem, config: &'a SeedingConfig) -> &'a SeedingPolicy {
+    item.policy.as_ref().unwrap_or(&config.policy)
+}
+
+/// Whether an item reached its ratio or time goal
+pub fn goal_reached(item: &SeedingItem, policy: &SeedingPolicy) -> bool {
+    let ratio_reached = policy
+        .ratio_target
+        .is_some_and(|target| item.size_bytes > 0 && item.ratio() >= target);
+    let time_reached = policy
+        .max_seeding_minutes
+        .is_some_and(|minutes| item.seeding_seconds >= minutes * 60);
+    ratio_reached || time_reached
+}
+
+/// Decide what to do with a tracked torrent
+pub fn decide(
+    item: &SeedingItem,
+    config: &SeedingConfig,
+    conditions: SeedingConditions,
+) -> SeedingDecision {
+    if goal_reached(item, effective_policy(item, config)) {
+        return SeedingDecision::Finish;
+    }
+    if !config.enabled {
+        return SeedingDecision::Pause(PauseReason::Disabled);
+    }
+    if config.stop_on_metered && conditions.metered {
+        return SeedingDecision::Pause(PauseReason::Metered);
+    }
+    if config.pause_while_playing && conditions.playing {
+        return SeedingDecision::Pause(PauseReason::Playing);
+    }
+    SeedingDecision::Seed
+}
+
+/// Add the backend's session upload counter to an item's cumulative upload. The
+/// counter restarts at zero whenever the backend re-adds the torrent.
+pub fn accumulate_upload(item: &mut SeedingItem, session_uploaded: u64) {
+    let delta = if session_uploaded >= item.session_uploaded {
+        session_uploaded - item.session_uploaded
+    } else {
+        session_uploaded
+    };
+    item.uploaded_bytes += delta;
+    item.session_uploaded = session_uploaded;
+}
+
+/// Create a tracked item for a finished backend torrent
+fn item_from_backend(torrent: &BackendTorrent, now: i64) -> SeedingItem {
+    SeedingItem {
+        infohash: torrent.info_hash.to_lowercase(),
+        name: torrent.name.clone().unwrap_or_else(|| torrent.info_hash.clone()),
+        magnet: torrent.magnet_uri.clone(),
+        folder: torrent.path.clone(),
+        size_bytes: torrent.length,
+        uploaded_bytes: 0,
+        upload_speed: 0.0,
+        peers: 0,
+        seeding_seconds: 0,
+        status: SeedingStatus::Seeding,
+        pause_reason: None,
+        policy: None,
+        added_at: now,
+        finished_at: None,
+        session_uploaded: 0,
+    }
+}
+
+// =============================================================================
+// Persistence Functions
+// =============================================================================
+
+/// Load items and config from the store once
+fn ensure_seeding_loaded(app: &AppHandle, state: &SeedingState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(SEEDING_STORE_FILE) {
+        if let Some(value) = store.get(ITEMS_KEY) {
+            match serde_json::from_value::<Vec<SeedingItem>>(value.clone()) {
+                Ok(mut items) => {
+                    // Session counters belong to the previous backend process
+                    for item in items.iter_mut() {
+                        item.session_uploaded = 0;
+                    }
+                    *state.items.lock().unwrap() = items;
+                }
+                Err(e) => log::warn!("Failed to deserialize seeding items: {}", e),
+            }
+        }
+        if let Some(value) = store.get(CONFIG_KEY) {
+            if let Ok(config) = serde_json::from_value::<SeedingConfig>(value.clone()) {
+                *state.config.lock().unwrap() = config;
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save items and config to the store
+fn save_seeding_to_store(app: &AppHandle, state: &SeedingState) -> Result<(), String> {
+    let store = app
+        .store(SEEDING_STORE_FILE)
+        .map_err(|e| format!("Failed to open seeding store: {}", e))?;
+
+    let items_value = {
+        let items = state
+            .items
+            .lock()
+            .map_err(|e| format!("Failed to lock seeding items: {}", e))?;
+        serde_json::to_value(&*items)
+            .map_err(|e| format!("Failed to serialize seeding items: {}", e))?
+    };
+    let config_value = {
+        let config = state
+            .config
+            .lock()
+            .map_err(|e| format!("Failed to lock seeding config: {}", e))?;
+        serde_json::to_value(&*config)
+            .map_err(|e| format!("Failed to serialize seeding config: {}", e))?
+    };
+
+    store.set(ITEMS_KEY, items_value);
+    store.set(CONFIG_KEY, config_value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save seeding state: {}", e))
+}
+
+// =============================================================================
+// Monitor
+// =============================================================================
+
+/// Backend call needed to apply a decision
+enum BackendAction {
+    Pause(String),
+    Resume(String),
+    Remove(String),
+}
+
+/// Sync stats from the backend and apply the policy to every tracked torrent
+pub async fn apply_policy(app: &AppHandle) -> Result<Vec<SeedingItemInfo>, String> {
+    let state = app.state::<SeedingState>();
+    ensure_seeding_loaded(app, &state);
+
+    let backend = TorrentBackend::from_settings(app);
+    let torrents = backend.list().await?;
+
+    let now = get_current_timestamp();
+    let elapsed_secs = {
+        let mut last_pass = state
+            .last_pass
+            .lock()
+            .map_err(|e| format!("Failed to lock seeding state: {}", e))?;
+        let elapsed = last_pass.map_or(0, |last| ((now - last).max(0) / 1000) as u64);
+        *last_pass = Some(now);
+        elapsed
+    };
+
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock seeding config: {}", e))?
+        .clone();
+    let conditions = *state
+        .conditions
+        .lock()
+        .map_err(|e| format!("Failed to lock seeding conditions: {}", e))?;
+
+    let mut actions = Vec::new();
+    {
+        let mut items = state
+            .items
+            .lock()
+            .map_err(|e| format!("Failed to lock seeding items: {}", e))?;
+
+        for torrent in torrents.iter().filter(|t| t.done || t.progress >= 1.0) {
+            let infohash = torrent.info_hash.to_lowercase();
+            let index = match items.iter().position(|i| i.infohash == infohash) {
+                Some(index) => index,
+                None => {
+                    items.push(item_from_backend(torrent, now));
+                    items.len() - 1
+                }
+            };
+            let item = &mut items[index];
+
+            // Stopped and finished torrents that are still loaded (e.g. being streamed)
+            // are left alone
+            if matches!(item.status, SeedingStatus::Stopped | SeedingStatus::Finished) {
+                continue;
+            }
+
+            accumulate_upload(item, torrent.uploaded);
+            if torrent.length > 0 {
+                item.size_bytes = torrent.length;
+            }
+            if torrent.path.is_some() {
+                item.folder = torrent.path.clone();
+            }
+            item.upload_speed = torrent.upload_speed;
+            item.peers = torrent.num_peers;
+            if item.status == SeedingStatus::Seeding && !torrent.paused {
+                item.seeding_seconds += elapsed_secs;
+            }
+
+            match decide(item, &config, conditions) {
+                SeedingDecision::Finish => {
+                    log::info!("Seeding goal reached for '{}'", item.name);
+                    item.status = SeedingStatus::Finished;
+                    item.pause_reason = None;
+                    item.finished_at = Some(now);
+                    item.upload_speed = 0.0;
+                    actions.push(BackendAction::Remove(infohash));
+                }
+                SeedingDecision::Pause(reason) => {
+                    item.status = SeedingStatus::Paused;
+                    item.pause_reason = Some(reason);
+                    if !torrent.paused {
+                        actions.push(BackendAction::Pause(infohash));
+                    }
+                }
+                SeedingDecision::Seed => {
+                    item.status = SeedingStatus::Seeding;
+                    item.pause_reason = None;
+                    if torrent.paused {
+                        actions.push(BackendAction::Resume(infohash));
+                    }
+                }
+            }
+        }
+    }
+
+    for action in actions {
+        let result = match &action {
+            BackendAction::Pause(hash) => backend.pause(hash).await,
+            BackendAction::Resume(hash) => backend.resume(hash).await,
+            BackendAction::Remove(hash) => backend.remove(hash).await,
+        };
+        if let Err(e) = result {
+            log::warn!("{}", e);
+        }
+    }
+
+    save_seeding_to_store(app, &state)?;
+
+    let list = list_items(&state)?;
+    let _ = app.emit(SEEDING_UPDATED_EVENT, &list);
+    Ok(list)
+}
+
+/// Hand tracked torrents that haven't reached their goal back to the backend
+async fn restore_seeding(app: &AppHandle) {
+    let state = app.state::<SeedingState>();
+    ensure_seeding_loaded(app, &state);
+
+    let pending: Vec<SeedingItem> = match state.items.lock() {
+        Ok(items) => items
+            .iter()
+            .filter(|i| matches!(i.status, SeedingStatus::Seeding | SeedingStatus::Paused))
+            .cloned()
+            .collect(),
+        Err(_) => return,
+    };
+
+    let backend = TorrentBackend::from_settings(app);
+    for item in pending {
+        let folder = match &item.folder {
+            Some(folder) if std::path::Path::new(folder).exists() => folder.clone(),
+            _ => {
+                log::warn!("Content of '{}' is gone, no longer seeding it", item.name);
+                if let Ok(mut items) = state.items.lock() {
+                    items.retain(|i| i.infohash != item.infohash);
+                }
+                continue;
+            }
+        };
+        if let Err(e) = backend.add(&item.magnet, &folder).await {
+            log::warn!("Failed to restore seeding of '{}': {}", item.name, e);
+        }
+    }
+
+    if let Err(e) = save_seeding_to_store(app, &state) {
+        log::warn!("{}", e);
+    }
+}
+
+/// Start the background monitor that applies the seeding policy
+pub fn start_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let state = app.state::<SeedingState>();
+            let restored = state.restored.lock().map(|r| *r).unwrap_or(true);
+
+            // Wait for the backend to come up before restoring anything
+            if !restored && TorrentBackend::from_settings(&app).list().await.is_ok() {
+                if let Ok(mut restored) = state.restored.lock() {
+                    *restored = true;
+                }
+                restore_seeding(&app).await;
+            }
+
+            if let Err(e) = apply_policy(&app).await {
+                log::debug!("Seeding policy pass skipped: {}", e);
+            }
+
+            tokio::time::sleep(Duration::from_secs(MONITOR_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+/// Get all tracked items with derived values
+fn list_items(state: &SeedingState) -> Result<Vec<SeedingItemInfo>, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock seeding config: {}", e))?
+        .clone();
+    let items = state
+        .items
+        .lock()
+        .map_err(|e| format!("Failed to lock seeding items: {}", e))?;
+
+    Ok(items
+        .iter()
+        .map(|item| SeedingItemInfo {
+            ratio: item.ratio(),
+            effective_policy: effective_policy(item, &config).clone(),
+            item: item.clone(),
+        })
+        .collect())
+}
+
+/// Apply a change to a tracked item and persist it
+fn update_item<F>(
+    app: &AppHandle,
+    state: &SeedingState,
+    infohash: &str,
+    change: F,
+) -> Result<SeedingItem, String>
+where
+    F: FnOnce(&mut SeedingItem),
+{
+    ensure_seeding_loaded(app, state);
+
+    let updated = {
+        let mut items = state
+            .items
+            .lock()
+            .map_err(|e| format!("Failed to lock seeding items: {}", e))?;
+        let item = items
+            .iter_mut()
+            .find(|i| i.infohash.eq_ignore_ascii_case(infohash))
+            .ok_or_else(|| format!("Torrent '{}' is not tracked", infohash))?;
+        change(item);
+        item.clone()
+    };
+
+    save_seeding_to_store(app, state)?;
+    Ok(updated)
+}
+
+/// Re-run the policy in the background after a condition or config change
+fn schedule_policy_pass(app: &AppHandle) {
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = apply_policy(&handle).await {
+            log::debug!("Seeding policy pass skipped: {}", e);
+        }
+    });
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// List tracked torrents with upload stats (refreshed from the backend when reachable)
+#[tauri::command]
+pub async fn seeding_list(app: AppHandle) -> Result<Vec<SeedingItemInfo>, String> {
+    match apply_policy(&app).await {
+        Ok(list) => Ok(list),
+        Err(e) => {
+            log::debug!("Listing seeding items without refresh: {}", e);
+            let state = app.state::<SeedingState>();
+            ensure_seeding_loaded(&app, &state);
+            list_items(&state)
+        }
+    }
+}
+
+/// Get the global seeding configuration
+#[tauri::command]
+pub fn seeding_get_config(
+    app: AppHandle,
+    state: State<'_, SeedingState>,
+) -> Result<SeedingConfig, String> {
+    ensure_seeding_loaded(&app, &state);
+
+    state
+        .config
+        .lock()
+        .map(|c| c.clone())
+        .map_err(|e| format!("Failed to lock seeding config: {}", e))
+}
+
+/// Update the global seeding configuration
+#[tauri::command]
+pub fn seeding_set_config(
+    config: SeedingConfig,
+    app: AppHandle,
+    state: State<'_, SeedingState>,
+) -> Result<SeedingConfig, String> {
+    ensure_seeding_loaded(&app, &state);
+
+    *state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock seeding config: {}", e))? = config.clone();
+    save_seeding_to_store(&app, &state)?;
+    schedule_policy_pass(&app);
+
+    log::info!("Seeding config updated: enabled={}", config.enabled);
+    Ok(config)
+}
+
+/// Set (or clear with `null`) the policy of a single torrent
+#[tauri::command]
+pub fn seeding_set_item_policy(
+    infohash: String,
+    policy: Option<SeedingPolicy>,
+    app: AppHandle,
+    state: State<'_, SeedingState>,
+) -> Result<SeedingItem, String> {
+    let item = update_item(&app, &state, &infohash, |item| item.policy = policy)?;
+    schedule_policy_pass(&app);
+    Ok(item)
+}
+
+/// Stop seeding a torrent until it is resumed (files are kept)
+#[tauri::command]
+pub async fn seeding_stop(infohash: String, app: AppHandle) -> Result<SeedingItem, String> {
+    let state = app.state::<SeedingState>();
+    let item = update_item(&app, &state, &infohash, |item| {
+        item.status = SeedingStatus::Stopped;
+        item.pause_reason = None;
+        item.upload_speed = 0.0;
+        item.peers = 0;
+    })?;
+
+    TorrentBackend::from_settings(&app).remove(&item.infohash).await?;
+    Ok(item)
+}
+
+/// Resume seeding a stopped or finished torrent. A finished torrent stops again on
+/// the next pass unless its policy was raised first.
+#[tauri::command]
+pub async fn seeding_resume(infohash: String, app: AppHandle) -> Result<SeedingItem, String> {
+    let state = app.state::<SeedingState>();
+    let item = update_item(&app, &state, &infohash, |item| {
+        item.status = SeedingStatus::Seeding;
+        item.pause_reason = None;
+        item.finished_at = None;
+        item.session_uploaded = 0;
+    })?;
+
+    let folder = item
+        .folder
+        .clone()
+        .ok_or_else(|| format!("Folder of '{}' is unknown", item.name))?;
+    TorrentBackend::from_settings(&app)
+        .add(&item.magnet, &folder)
+        .await?;
+    Ok(item)
+}
+
+/// Start seeding a local file or folder
+#[tauri::command]
+pub async fn seeding_add_path(path: String, app: AppHandle) -> Result<SeedingItem, String> {
+    if !std::path::Path::new(&path).exists() {
+        return Err(format!("'{}' does not exist", path));
+    }
+    // The backend refuses to seed anything outside the downloads folder
+    let roots = crate::backend_runtime::download_roots(&app)?;
+    if !crate::backend_runtime::is_within_roots(std::path::Path::new(&path), &roots) {
+        return Err(format!("'{}' is not inside the downloads folder", path));
+    }
+
+    let torrent = TorrentBackend::from_settings(&app).seed(&path).await?;
+    let item = item_from_backend(&torrent, get_current_timestamp());
+
+    let state = app.state::<SeedingState>();
+    ensure_seeding_loaded(&app, &state);
+    {
+        let mut items = state
+            .items
+            .lock()
+            .map_err(|e| format!("Failed to lock seeding items: {}", e))?;
+        items.retain(|i| i.infohash != item.infohash);
+        items.push(item.clone());
+    }
+    save_seeding_to_store(&app, &state)?;
+
+    log::info!("Seeding '{}'", item.name);
+    Ok(item)
+}
+
+/// Stop tracking a torrent (it is removed from the backend, files are kept)
+#[tauri::command]
+pub async fn seeding_forget(infohash: String, app: AppHandle) -> Result<(), String> {
+    let state = app.state::<SeedingState>();
+    ensure_seeding_loaded(&app, &state);
+
+    {
+        let mut items = state
+            .items
+            .lock()
+            .map_err(|e| format!("Failed to lock seeding items: {}", e))?;
+        let before = items.len();
+        items.retain(|i| !i.infohash.eq_ignore_ascii_case(&infohash));
+        if items.len() == before {
+            return Err(format!("Torrent '{}' is not tracked", infohash));
+        }
+    }
+    save_seeding_to_store(&app, &state)?;
+
+    TorrentBackend::from_settings(&app).remove(&infohash).await
+}
+
+/// Report whether the network connection is metered
+#[tauri::command]
+pub fn seeding_set_metered(
+    metered: bool,
+    app: AppHandle,
+    state: State<'_, SeedingState>,
+) -> Result<(), String> {
+    state
+        .conditions
+        .lock()
+        .map_err(|e| format!("Failed to lock seeding conditions: {}", e))?
+        .metered = metered;
+    schedule_policy_pass(&app);
+    Ok(())
+}
+
+/// Report whether a video is playing
+#[tauri::command]
+pub fn seeding_set_playing(
+    playing: bool,
+    app: AppHandle,
+    state: State<'_, SeedingState>,
+) -> Result<(), String> {
+    state
+        .conditions
+        .lock()
+        .map_err(|e| format!("Failed to lock seeding conditions: {}", e))?
+        .playing = playing;
+    schedule_policy_pass(&app);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(size: u64, uploaded: u64, seeding_seconds: u64) -> SeedingItem {
+        let mut item = item_from_backend(
+            &BackendTorrent {
+                info_hash: "ABCDEF".to_string(),
+                magnet_uri: "magnet:?xt=urn:btih:abcdef".to_string(),
+                name: Some("[SubsPlease] Frieren - 01 (1080p).mkv".to_string()),
+                length: size,
+                ..Default::default()
+            },
+            0,
+        );
+        item.uploaded_bytes = uploaded;
+        item.seeding_seconds = seeding_seconds;
+        item
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = SeedingConfig::default();
+        assert!(config.enabled);
+        assert!(config.stop_on_metered);
+        assert!(!config.pause_while_playing);
+        assert_eq!(config.policy.ratio_target, Some(1.0));
+    }
+
+    #[test]
+    fn test_goals_finish_seeding() {
+        let config = SeedingConfig::default();
+        let conditions = SeedingConditions::default();
+
+        assert_eq!(decide(&item(100, 50, 60), &config, conditions), SeedingDecision::Seed);
+        assert_eq!(decide(&item(100, 100, 60), &config, conditions), SeedingDecision::Finish);
+        assert_eq!(
+            decide(&item(100, 0, 7 * 24 * 3600), &config, conditions),
+            SeedingDecision::Finish
+        );
+
+        // A per-torrent policy replaces the global one
+        let mut keep = item(100, 300, 7 * 24 * 3600);
+        keep.policy = Some(SeedingPolicy { ratio_target: Some(5.0), max_seeding_minutes: None });
+        assert_eq!(decide(&keep, &config, conditions), SeedingDecision::Seed);
+    }
+
+    #[test]
+    fn test_conditions_pause_seeding() {
+        let mut config = SeedingConfig::default();
+        let metered = SeedingConditions { metered: true, playing: false };
+        let playing = SeedingConditions { metered: false, playing: true };
+
+        assert_eq!(
+            decide(&item(100, 0, 0), &config, metered),
+            SeedingDecision::Pause(PauseReason::Metered)
+        );
+        assert_eq!(decide(&item(100, 0, 0), &config, playing), SeedingDecision::Seed);
+
+        config.pause_while_playing = true;
+        assert_eq!(
+            decide(&item(100, 0, 0), &config, playing),
+            SeedingDecision::Pause(PauseReason::Playing)
+        );
+
+        config.enabled = false;
+        assert_eq!(
+            decide(&item(100, 0, 0), &config, SeedingConditions::default()),
+            SeedingDecision::Pause(PauseReason::Disabled)
+        );
+    }
+
+    #[test]
+    fn test_accumulate_upload_across_sessions() {
+        let mut seeding = item(100, 0, 0);
+        accumulate_upload(&mut seeding, 30);
+        accumulate_upload(&mut seeding, 50);
+        assert_eq!(seeding.uploaded_bytes, 50);
+
+        // Backend restarted: its counter starts from zero again
+        accumulate_upload(&mut seeding, 20);
+        assert_eq!(seeding.uploaded_bytes, 70);
+        assert!((seeding.ratio() - 0.7).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,958 @@
+//! Intro/Outro Skip Segments
+//!
+//! This module finds the segments of an episode that `auto_skip_intro` and
+//! `auto_skip_outro` skip over. Three layers are tried in order, and each kind of
+//! segment is taken from the first layer that found one:
+//!
+//! 1. Chapters of the file (MKV/MP4 chapters named "OP", "Opening", "ED", ...)
+//! 2. Remote timestamp providers (AniSkip by default), cached on disk
+//! 3. Audio fingerprints: the opening/ending audio shared with another episode of
+//!    the same series (needs `ffmpeg`, and a second episode to compare against)
+//!
+//! Remote providers are pluggable: implement [`SkipTimesProvider`] and register it on
+//! [`SkipState`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_store::StoreExt;
+
+use crate::audio_fingerprint::{self, Fingerprint};
+use crate::media_probe::{self, ChapterInfo, MediaSource};
+use crate::profiles::{self, ProfileState};
+
+/// Store file name for cached and detected segments
+const SKIP_STORE_FILE: &str = "skip_segments.json";
+
+/// Store keys
+const REMOTE_CACHE_KEY: &str = "remote";
+const DETECTED_KEY: &str = "detected";
+
+/// How long remote results are cached (empty results are retried sooner)
+const REMOTE_CACHE_TTL_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+const EMPTY_CACHE_TTL_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Maximum number of cached remote results
+const MAX_CACHE_ENTRIES: usize = 2000;
+
+/// Remote provider request timeout
+const PROVIDER_TIMEOUT_SECS: u64 = 10;
+
+/// AniSkip API
+const ANISKIP_BASE_URL: &str = "https://api.aniskip.com";
+
+/// Chapters longer than this aren't openings/endings
+const MAX_CHAPTER_SEGMENT_MS: u64 = 5 * 60 * 1000;
+
+/// Where openings (start of the episode) and endings (end) are searched for
+const INTRO_SEARCH_MS: u64 = 8 * 60 * 1000;
+const OUTRO_SEARCH_MS: u64 = 5 * 60 * 1000;
+
+/// Accepted length of a fingerprinted opening/ending
+const MIN_SONG_MS: u64 = 20 * 1000;
+const MAX_SONG_MS: u64 = 150 * 1000;
+
+/// Number of other episodes a fingerprint is compared with
+const MAX_FINGERPRINT_PEERS: usize = 3;
+
+/// Kind of a skippable segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentKind {
+    Intro,
+    Outro,
+    Recap,
+    Preview,
+}
+
+/// Which layer found a segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentOrigin {
+    Chapters,
+    Remote,
+    Fingerprint,
+}
+
+/// A skippable segment of an episode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkipSegment {
+    pub kind: SegmentKind,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub origin: SegmentOrigin,
+    /// Remote provider ID
+    pub provider: Option<String>,
+}
+
+/// What to find segments for
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkipRequest {
+    /// Path or URL of the episode (for chapters and fingerprints)
+    pub source: Option<String>,
+    pub anilist_id: Option<u64>,
+    /// MyAnimeList ID (AniSkip is keyed by it)
+    pub mal_id: Option<u64>,
+    pub episode: u32,
+    pub duration_ms: Option<u64>,
+    pub profile_id: Option<String>,
+    /// Run audio fingerprinting if the other layers found nothing (slow)
+    #[serde(default)]
+    pub fingerprint: bool,
+}
+
+/// A layer that failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkipLayerError {
+    pub origin: SegmentOrigin,
+    pub message: String,
+}
+
+/// Segments of an episode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeSegments {
+    pub episode: u32,
+    pub duration_ms: Option<u64>,
+    pub segments: Vec<SkipSegment>,
+    /// Profile settings, so the player knows whether to skip automatically
+    pub auto_skip_intro: bool,
+    pub auto_skip_outro: bool,
+    pub errors: Vec<SkipLayerError>,
+}
+
+/// Remote provider info for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkipProviderInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// What a remote provider is asked for
+#[derive(Debug, Clone, Copy)]
+pub struct SkipQuery {
+    pub anilist_id: Option<u64>,
+    pub mal_id: Option<u64>,
+    pub episode: u32,
+    pub duration_ms: Option<u64>,
+}
+
+/// A source of crowd-sourced skip timestamps
+pub trait SkipTimesProvider: Send + Sync {
+    /// Unique provider identifier
+    fn id(&self) -> &'static str;
+
+    /// Display name
+    fn name(&self) -> &'static str;
+
+    /// Build the request URL (`None` if the query lacks the IDs this provider needs)
+    fn request_url(&self, query: &SkipQuery) -> Option<String>;
+
+    /// Parse a response body into segments
+    fn parse_response(&self, body: &str) -> Result<Vec<SkipSegment>, String>;
+}
+
+/// A cached remote result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedSegments {
+    segments: Vec<SkipSegment>,
+    fetched_at: i64,
+}
+
+/// Skip segment state: providers, remote cache and fingerprint results
+pub struct SkipState {
+    providers: RwLock<Vec<Arc<dyn SkipTimesProvider>>>,
+    client: reqwest::Client,
+    /// Remote results by `provider:id:episode`
+    cache: Mutex<HashMap<String, CachedSegments>>,
+    /// Fingerprinted segments by `anilist_id:episode`
+    detected: Mutex<HashMap<String, Vec<SkipSegment>>>,
+    loaded: Mutex<bool>,
+}
+
+impl Default for SkipState {
+    fn default() -> Self {
+        SkipState {
+            providers: RwLock::new(vec![Arc::new(AniSkipProvider::default())]),
+            client: reqwest::Client::builder()
+                .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+                .timeout(Duration::from_secs(PROVIDER_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+            cache: Mutex::new(HashMap::new()),
+            detected: Mutex::new(HashMap::new()),
+            loaded: Mutex::new(false),
+        }
+    }
+}
+
+impl SkipState {
+    /// Register an additional provider (replaces a provider with the same ID)
+    pub fn register_provider(&self, provider: Arc<dyn SkipTimesProvider>) {
+        if let Ok(mut providers) = self.providers.write() {
+            providers.retain(|p| p.id() != provider.id());
+            providers.push(provider);
+        }
+    }
+
+    fn providers(&self) -> Vec<Arc<dyn SkipTimesProvider>> {
+        self.providers.read().map(|p| p.clone()).unwrap_or_default()
+    }
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Chapters
+// =============================================================================
+
+/// Classify a chapter title
+pub fn classify_chapter(title: &str) -> Option<SegmentKind> {
+    if title.contains("オープニング") {
+        return Some(SegmentKind::Intro);
+    }
+    if title.contains("エンディング") {
+        return Some(SegmentKind::Outro);
+    }
+    if title.contains("予告") {
+        return Some(SegmentKind::Preview);
+    }
+
+    let normalized: String = title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let words: Vec<&str> = normalized.split_whitespace().collect();
+    let first = words.first().copied()?;
+    let numbered = |word: &str, prefix: &str| {
+        word.strip_prefix(prefix).is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+    };
+
+    if numbered(first, "op") || numbered(first, "ncop") || matches!(first, "opening" | "intro") {
+        Some(SegmentKind::Intro)
+    } else if numbered(first, "ed")
+        || numbered(first, "nced")
+        || matches!(first, "ending" | "outro" | "credits")
+    {
+        Some(SegmentKind::Outro)
+    } else if matches!(first, "recap" | "previously") {
+        Some(SegmentKind::Recap)
+    } else if words.contains(&"preview") || words.starts_with(&["next", "episode"]) {
+        Some(SegmentKind::Preview)
+    } else {
+        None
+    }
+}
+
+/// Build segments from chapters; a chapter without an end runs to the next one
+pub fn segments_from_chapters(chapters: &[ChapterInfo], duration_ms: Option<u64>) -> Vec<SkipSegment> {
+    let mut sorted: Vec<&ChapterInfo> = chapters.iter().collect();
+    sorted.sort_by_key(|c| c.start_ms);
+
+    sorted
+        .iter()
+        .enumerate()
+        .filter_map(|(i, chapter)| {
+            let kind = classify_chapter(chapter.title.as_deref()?)?;
+            let end_ms = chapter
+                .end_ms
+                .or_else(|| sorted.get(i + 1).map(|next| next.start_ms))
+                .or(duration_ms)?;
+            let length = end_ms.checked_sub(chapter.start_ms)?;
+            (length > 0 && length <= MAX_CHAPTER_SEGMENT_MS).then_some(SkipSegment {
+                kind,
+                start_ms: chapter.start_ms,
+                end_ms,
+                origin: SegmentOrigin::Chapters,
+                provider: None,
+            })
+        })
+        .collect()
+}
+
+// =============================================================================
+// Remote Providers
+// =============================================================================
+
+/// AniSkip (crowd-sourced timestamps keyed by MyAnimeList ID)
+pub struct AniSkipProvider {
+    base_url: String,
+}
+
+impl Default for AniSkipProvider {
+    fn default() -> Self {
+        AniSkipProvider { base_url: ANISKIP_BASE_URL.to_string() }
+    }
+}
+
+impl AniSkipProvider {
+    /// Create a provider pointing at a different AniSkip instance
+    pub fn with_base_url(base_url: &str) -> Self {
+        AniSkipProvider { base_url: base_url.trim_end_matches('/').to_string() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AniSkipResponse {
+    found: bool,
+    #[serde(default)]
+    results: Vec<AniSkipResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AniSkipResult {
+    interval: AniSkipInterval,
+    skip_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AniSkipInterval {
+    start_time: f64,
+    end_time: f64,
+}
+
+impl SkipTimesProvider for AniSkipProvider {
+    fn id(&self) -> &'static str {
+        "aniskip"
+    }
+
+    fn name(&self) -> &'static str {
+        "AniSkip"
+    }
+
+    fn request_url(&self, query: &SkipQuery) -> Option<String> {
+        let mal_id = query.mal_id?;
+        let length = query.duration_ms.map_or(0, |d| d / 1000);
+        Some(format!(
+            "{}/v2/skip-times/{}/{}?types[]=op&types[]=ed&types[]=mixed-op&types[]=mixed-ed&types[]=recap&episodeLength={}",
+            self.base_url, mal_id, query.episode, length
+        ))
+    }
+
+    fn parse_response(&self, body: &str) -> Result<Vec<SkipSegment>, String> {
+        let response: AniSkipResponse =
+            serde_json::from_str(body).map_err(|e| format!("Invalid AniSkip response: {}", e))?;
+        if !response.found {
+            return Ok(Vec::new());
+        }
+
+        Ok(response
+            .results
+            .into_iter()
+            .filter_map(|result| {
+                let kind = match result.skip_type.as_str() {
+                    "op" | "mixed-op" => SegmentKind::Intro,
+                    "ed" | "mixed-ed" => SegmentKind::Outro,
+                    "recap" => SegmentKind::Recap,
+                    "preview" => SegmentKind::Preview,
+                    _ => return None,
+                };
+                let start_ms = (result.interval.start_time.max(0.0) * 1000.0).round() as u64;
+                let end_ms = (result.interval.end_time.max(0.0) * 1000.0).round() as u64;
+                (end_ms > start_ms).then(|| SkipSegment {
+                    kind,
+                    start_ms,
+                    end_ms,
+                    origin: SegmentOrigin::Remote,
+                    provider: Some(self.id().to_string()),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Fetch segments from one provider (a 404 means no timestamps were submitted)
+async fn fetch_provider(
+    client: &reqwest::Client,
+    provider: &dyn SkipTimesProvider,
+    url: &str,
+) -> Result<Vec<SkipSegment>, String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+
+    if status.as_u16() == 404 {
+        return Ok(provider.parse_response(&body).unwrap_or_default());
+    }
+    if !status.is_success() {
+        return Err(format!("Request failed with status {}", status));
+    }
+    provider.parse_response(&body)
+}
+
+/// Cache key of a remote result
+fn cache_key(provider: &str, query: &SkipQuery) -> String {
+    match (query.mal_id, query.anilist_id) {
+        (Some(mal), _) => format!("{}:mal{}:{}", provider, mal, query.episode),
+        (None, Some(anilist)) => format!("{}:al{}:{}", provider, anilist, query.episode),
+        (None, None) => format!("{}:none:{}", provider, query.episode),
+    }
+}
+
+/// Whether a cached result is still fresh
+fn is_cache_fresh(entry: &CachedSegments, now: i64) -> bool {
+    let ttl = if entry.segments.is_empty() { EMPTY_CACHE_TTL_MS } else { REMOTE_CACHE_TTL_MS };
+    now - entry.fetched_at < ttl
+}
+
+/// Query the providers in order until one has segments (using the cache)
+async fn remote_segments(
+    app: &AppHandle,
+    state: &SkipState,
+    query: &SkipQuery,
+) -> Result<Vec<SkipSegment>, String> {
+    let mut errors = Vec::new();
+    for provider in state.providers() {
+        let url = match provider.request_url(query) {
+            Some(url) => url,
+            None => continue,
+        };
+        let key = cache_key(provider.id(), query);
+        let now = get_current_timestamp();
+
+        let cached = state
+            .cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(&key).filter(|e| is_cache_fresh(e, now)).cloned());
+        let segments = match cached {
+            Some(entry) => entry.segments,
+            None => match fetch_provider(&state.client, provider.as_ref(), &url).await {
+                Ok(segments) => {
+                    if let Ok(mut cache) = state.cache.lock() {
+                        cache.insert(key, CachedSegments { segments: segments.clone(), fetched_at: now });
+                        if cache.len() > MAX_CACHE_ENTRIES {
+                            cache.retain(|_, e| is_cache_fresh(e, now));
+                        }
+                    }
+                    if let Err(e) = save_skip_store(app, state) {
+                        log::warn!("Failed to save skip segment cache: {}", e);
+                    }
+                    segments
+                }
+                Err(e) => {
+                    log::warn!("Skip provider '{}' failed: {}", provider.id(), e);
+                    errors.push(format!("{}: {}", provider.name(), e));
+                    continue;
+                }
+            },
+        };
+
+        if !segments.is_empty() {
+            return Ok(segments);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(Vec::new())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+// =============================================================================
+// Fingerprints
+// =============================================================================
+
+/// Cache folder for the fingerprints of a series
+fn fingerprint_folder(app: &AppHandle, anilist_id: u64) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("fingerprints").join(anilist_id.to_string()))
+        .map_err(|e| format!("Failed to resolve cache folder: {}", e))
+}
+
+fn fingerprint_file(folder: &Path, episode: u32, kind: SegmentKind) -> PathBuf {
+    let kind = if kind == SegmentKind::Intro { "intro" } else { "outro" };
+    folder.join(format!("{}-{}.json", episode, kind))
+}
+
+/// Load the stored fingerprints of other episodes, nearest episodes first
+fn load_peer_fingerprints(folder: &Path, episode: u32, kind: SegmentKind) -> Vec<(u32, Fingerprint)> {
+    let suffix = if kind == SegmentKind::Intro { "-intro.json" } else { "-outro.json" };
+    let mut peers: Vec<u32> = std::fs::read_dir(folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.strip_suffix(suffix)?.parse().ok())
+                .filter(|peer| *peer != episode)
+                .collect()
+        })
+        .unwrap_or_default();
+    peers.sort_by_key(|peer| (peer.abs_diff(episode), *peer));
+
+    peers
+        .into_iter()
+        .filter_map(|peer| {
+            let content = std::fs::read_to_string(fingerprint_file(folder, peer, kind)).ok()?;
+            Some((peer, serde_json::from_str(&content).ok()?))
+        })
+        .take(MAX_FINGERPRINT_PEERS)
+        .collect()
+}
+
+/// Segments found by fingerprinting an episode
+struct FingerprintResult {
+    own: Vec<SkipSegment>,
+    /// The same songs in other episodes
+    peers: Vec<(u32, SkipSegment)>,
+}
+
+/// Fingerprint the intro and outro windows of an episode and compare them with
+/// other episodes of the series (blocking)
+fn fingerprint_episode(
+    folder: &Path,
+    input: &str,
+    episode: u32,
+    duration_ms: Option<u64>,
+) -> Result<FingerprintResult, String> {
+    let ffmpeg = audio_fingerprint::find_ffmpeg()
+        .ok_or_else(|| "ffmpeg is required for fingerprinting but wasn't found".to_string())?;
+    std::fs::create_dir_all(folder).map_err(|e| format!("Failed to create fingerprint folder: {}", e))?;
+
+    let mut windows = vec![(SegmentKind::Intro, 0, duration_ms.map_or(INTRO_SEARCH_MS, |d| d.min(INTRO_SEARCH_MS)))];
+    if let Some(duration) = duration_ms.filter(|d| *d > INTRO_SEARCH_MS + OUTRO_SEARCH_MS) {
+        windows.push((SegmentKind::Outro, duration - OUTRO_SEARCH_MS, OUTRO_SEARCH_MS));
+    }
+
+    let mut own = Vec::new();
+    let mut peers_found = Vec::new();
+    for (kind, start_ms, length_ms) in windows {
+        let path = fingerprint_file(folder, episode, kind);
+        let fingerprint = match std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Fingerprint>(&content).ok())
+        {
+            Some(fingerprint) => fingerprint,
+            None => {
+                let samples = audio_fingerprint::decode_audio(&ffmpeg, input, start_ms, length_ms)?;
+                let fingerprint = audio_fingerprint::fingerprint(&samples, start_ms);
+                let json = serde_json::to_string(&fingerprint)
+                    .map_err(|e| format!("Failed to serialize fingerprint: {}", e))?;
+                std::fs::write(&path, json).map_err(|e| format!("Failed to save fingerprint: {}", e))?;
+                fingerprint
+            }
+        };
+
+        for (peer, peer_fingerprint) in load_peer_fingerprints(folder, episode, kind) {
+            if let Some(found) =
+                audio_fingerprint::find_common_segment(&fingerprint, &peer_fingerprint, MIN_SONG_MS, MAX_SONG_MS)
+            {
+                let segment = |start_ms, end_ms| SkipSegment {
+                    kind,
+                    start_ms,
+                    end_ms,
+                    origin: SegmentOrigin::Fingerprint,
+                    provider: None,
+                };
+                own.push(segment(found.a_start_ms, found.a_end_ms));
+                peers_found.push((peer, segment(found.b_start_ms, found.b_end_ms)));
+                break;
+            }
+        }
+    }
+    Ok(FingerprintResult { own, peers: peers_found })
+}
+
+// =============================================================================
+// Service
+// =============================================================================
+
+/// Combine layers: each kind comes from the first layer that found it
+pub fn merge_segments(layers: Vec<Vec<SkipSegment>>) -> Vec<SkipSegment> {
+    let mut merged: Vec<SkipSegment> = Vec::new();
+    for layer in layers {
+        let kinds: Vec<SegmentKind> = merged.iter().map(|s| s.kind).collect();
+        merged.extend(layer.into_iter().filter(|s| !kinds.contains(&s.kind)));
+    }
+    merged.sort_by_key(|s| (s.start_ms, s.end_ms));
+    merged
+}
+
+fn detected_key(anilist_id: u64, episode: u32) -> String {
+    format!("{}:{}", anilist_id, episode)
+}
+
+/// Find the skip segments of an episode
+pub async fn find_segments(app: &AppHandle, state: &SkipState, request: &SkipRequest) -> EpisodeSegments {
+    ensure_skip_loaded(app, state);
+    let mut errors = Vec::new();
+    let mut duration_ms = request.duration_ms;
+    let source = request.source.as_deref().map(MediaSource::parse).transpose().unwrap_or(None);
+
+    // Layer 1: chapters
+    let mut chapters = Vec::new();
+    if let Some(source) = &source {
+        match media_probe::probe(source.clone()).await {
+            Ok(info) => {
+                duration_ms = duration_ms.or(info.duration_ms);
+                chapters = segments_from_chapters(&info.chapters, duration_ms);
+            }
+            Err(e) => errors.push(SkipLayerError { origin: SegmentOrigin::Chapters, message: e }),
+        }
+    }
+
+    // Layer 2: remote providers
+    let query = SkipQuery {
+        anilist_id: request.anilist_id,
+        mal_id: request.mal_id,
+        episode: request.episode,
+        duration_ms,
+    };
+    let remote = match remote_segments(app, state, &query).await {
+        Ok(segments) => segments,
+        Err(message) => {
+            errors.push(SkipLayerError { origin: SegmentOrigin::Remote, message });
+            Vec::new()
+        }
+    };
+
+    // Layer 3: fingerprints (earlier results first, then a new comparison on request)
+    let mut fingerprinted = request
+        .anilist_id
+        .and_then(|id| {
+            state.detected.lock().ok()?.get(&detected_key(id, request.episode)).cloned()
+        })
+        .unwrap_or_default();
+    let has = |segments: &[SkipSegment], kind| segments.iter().any(|s| s.kind == kind);
+    let missing = [SegmentKind::Intro, SegmentKind::Outro]
+        .into_iter()
+        .any(|kind| !has(&chapters, kind) && !has(&remote, kind) && !has(&fingerprinted, kind));
+
+    if request.fingerprint && missing {
+        match (request.anilist_id, &source) {
+            (Some(anilist_id), Some(source)) => {
+                match run_fingerprinting(app, state, anilist_id, request.episode, source, duration_ms).await {
+                    Ok(segments) => fingerprinted = segments,
+                    Err(message) => errors.push(SkipLayerError { origin: SegmentOrigin::Fingerprint, message }),
+                }
+            }
+            _ => errors.push(SkipLayerError {
+                origin: SegmentOrigin::Fingerprint,
+                message: "Fingerprinting needs a source and an AniList ID".to_string(),
+            }),
+        }
+    }
+
+    let settings = app
+        .try_state::<ProfileState>()
+        .and_then(|profile_state| {
+            profiles::resolve_profile_settings(app, &profile_state, request.profile_id.as_deref())
+        });
+
+    EpisodeSegments {
+        episode: request.episode,
+        duration_ms,
+        segments: merge_segments(vec![chapters, remote, fingerprinted]),
+        auto_skip_intro: settings.as_ref().is_some_and(|s| s.auto_skip_intro),
+        auto_skip_outro: settings.as_ref().is_some_and(|s| s.auto_skip_outro),
+        errors,
+    }
+}
+
+/// Fingerprint an episode and store what was found for it and its peers
+async fn run_fingerprinting(
+    app: &AppHandle,
+    state: &SkipState,
+    anilist_id: u64,
+    episode: u32,
+    source: &MediaSource,
+    duration_ms: Option<u64>,
+) -> Result<Vec<SkipSegment>, String> {
+    let folder = fingerprint_folder(app, anilist_id)?;
+    let input = source.key().to_string();
+    let FingerprintResult { own, peers } = tauri::async_runtime::spawn_blocking(move || {
+        fingerprint_episode(&folder, &input, episode, duration_ms)
+    })
+    .await
+    .map_err(|e| format!("Fingerprinting task failed: {}", e))??;
+
+    if let Ok(mut detected) = state.detected.lock() {
+        if !own.is_empty() {
+            detected.insert(detected_key(anilist_id, episode), own.clone());
+        }
+        for (peer, segment) in peers {
+            let entry = detected.entry(detected_key(anilist_id, peer)).or_default();
+            entry.retain(|s| s.kind != segment.kind);
+            entry.push(segment);
+        }
+    }
+    save_skip_store(app, state)?;
+
+    log::info!(
+        "Fingerprinting found {} segments for episode {} of {}",
+        own.len(),
+        episode,
+        anilist_id
+    );
+    Ok(own)
+}
+
+// =============================================================================
+// Persistence Functions
+// =============================================================================
+
+/// Load the remote cache and detected segments from the store once
+fn ensure_skip_loaded(app: &AppHandle, state: &SkipState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(SKIP_STORE_FILE) {
+        if let Some(value) = store.get(REMOTE_CACHE_KEY) {
+            match serde_json::from_value::<HashMap<String, CachedSegments>>(value.clone()) {
+                Ok(cache) => *state.cache.lock().unwrap() = cache,
+                Err(e) => log::warn!("Failed to deserialize skip segment cache: {}", e),
+            }
+        }
+        if let Some(value) = store.get(DETECTED_KEY) {
+            if let Ok(detected) = serde_json::from_value::<HashMap<String, Vec<SkipSegment>>>(value.clone()) {
+                *state.detected.lock().unwrap() = detected;
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save the remote cache and detected segments to the store
+fn save_skip_store(app: &AppHandle, state: &SkipState) -> Result<(), String> {
+    let store = app
+        .store(SKIP_STORE_FILE)
+        .map_err(|e| format!("Failed to open skip segment store: {}", e))?;
+
+    let cache_value = {
+        let cache = state
+            .cache
+            .lock()
+            .map_err(|e| format!("Failed to lock skip segment cache: {}", e))?;
+        serde_json::to_value(&*cache).map_err(|e| format!("Failed to serialize skip segment cache: {}", e))?
+    };
+    let detected_value = {
+        let detected = state
+            .detected
+            .lock()
+            .map_err(|e| format!("Failed to lock detected segments: {}", e))?;
+        serde_json::to_value(&*detected).map_err(|e| format!("Failed to serialize detected segments: {}", e))?
+    };
+
+    store.set(REMOTE_CACHE_KEY, cache_value);
+    store.set(DETECTED_KEY, detected_value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save skip segment store: {}", e))
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Get the skip segments of an episode
+#[tauri::command]
+pub async fn skip_get_segments(
+    request: SkipRequest,
+    app: AppHandle,
+    state: State<'_, SkipState>,
+) -> Result<EpisodeSegments, String> {
+    let segments = find_segments(&app, &state, &request).await;
+    log::info!(
+        "Found {} skip segments for episode {} ({} layer errors)",
+        segments.segments.len(),
+        request.episode,
+        segments.errors.len()
+    );
+    Ok(segments)
+}
+
+/// List the remote timestamp providers
+#[tauri::command]
+pub fn skip_get_providers(state: State<'_, SkipState>) -> Vec<SkipProviderInfo> {
+    state
+        .providers()
+        .iter()
+        .map(|p| SkipProviderInfo { id: p.id().to_string(), name: p.name().to_string() })
+        .collect()
+}
+
+/// Clear cached and detected segments (of one series, or everything)
+#[tauri::command]
+pub fn skip_clear_cache(
+    anilist_id: Option<u64>,
+    app: AppHandle,
+    state: State<'_, SkipState>,
+) -> Result<(), String> {
+    ensure_skip_loaded(&app, &state);
+    {
+        let mut cache = state
+            .cache
+            .lock()
+            .map_err(|e| format!("Failed to lock skip segment cache: {}", e))?;
+        let mut detected = state
+            .detected
+            .lock()
+            .map_err(|e| format!("Failed to lock detected segments: {}", e))?;
+        match anilist_id {
+            Some(id) => {
+                let prefix = format!("{}:", id);
+                let remote_marker = format!(":al{}:", id);
+                cache.retain(|key, _| !key.contains(&remote_marker));
+                detected.retain(|key, _| !key.starts_with(&prefix));
+            }
+            None => {
+                cache.clear();
+                detected.clear();
+            }
+        }
+    }
+
+    let folder = match anilist_id {
+        Some(id) => fingerprint_folder(&app, id)?,
+        None => fingerprint_folder(&app, 0)?
+            .parent()
+            .map(Path::to_path_buf)
+            .ok_or("Invalid fingerprint folder")?,
+    };
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder).map_err(|e| format!("Failed to remove fingerprints: {}", e))?;
+    }
+
+    save_skip_store(&app, &state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(start_ms: u64, end_ms: Option<u64>, title: &str) -> ChapterInfo {
+        ChapterInfo { start_ms, end_ms, title: Some(title.to_string()) }
+    }
+
+    #[test]
+    fn test_classify_chapter() {
+        assert_eq!(classify_chapter("OP"), Some(SegmentKind::Intro));
+        assert_eq!(classify_chapter("Opening Song"), Some(SegmentKind::Intro));
+        assert_eq!(classify_chapter("OP2"), Some(SegmentKind::Intro));
+        assert_eq!(classify_chapter("ED"), Some(SegmentKind::Outro));
+        assert_eq!(classify_chapter("Ending"), Some(SegmentKind::Outro));
+        assert_eq!(classify_chapter("オープニング"), Some(SegmentKind::Intro));
+        assert_eq!(classify_chapter("Next Episode Preview"), Some(SegmentKind::Preview));
+        assert_eq!(classify_chapter("Recap"), Some(SegmentKind::Recap));
+        assert_eq!(classify_chapter("Part A"), None);
+        assert_eq!(classify_chapter("Operation Start"), None);
+        assert_eq!(classify_chapter("Edward"), None);
+    }
+
+    #[test]
+    fn test_segments_from_chapters() {
+        let chapters = vec![
+            chapter(0, None, "Prologue"),
+            chapter(95_000, None, "Opening"),
+            chapter(185_000, None, "Part A"),
+            chapter(1_300_000, None, "Ending"),
+            chapter(1_390_000, None, "Preview"),
+        ];
+        let segments = segments_from_chapters(&chapters, Some(1_420_000));
+        let spans: Vec<_> = segments.iter().map(|s| (s.kind, s.start_ms, s.end_ms)).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (SegmentKind::Intro, 95_000, 185_000),
+                (SegmentKind::Outro, 1_300_000, 1_390_000),
+                (SegmentKind::Preview, 1_390_000, 1_420_000),
+            ]
+        );
+
+        // An "OP" chapter spanning half the episode isn't an opening
+        assert!(segments_from_chapters(&[chapter(0, Some(700_000), "OP")], None).is_empty());
+    }
+
+    #[test]
+    fn test_aniskip_provider() {
+        let provider = AniSkipProvider::with_base_url("https://skip.example/");
+        let query = SkipQuery { anilist_id: Some(1), mal_id: Some(21), episode: 5, duration_ms: Some(1_420_500) };
+        assert_eq!(
+            provider.request_url(&query).unwrap(),
+            "https://skip.example/v2/skip-times/21/5?types[]=op&types[]=ed&types[]=mixed-op&types[]=mixed-ed&types[]=recap&episodeLength=1420"
+        );
+        assert_eq!(provider.request_url(&SkipQuery { mal_id: None, ..query }), None);
+
+        let body = r#"{"found":true,"results":[
+            {"interval":{"startTime":88.5,"endTime":178.5},"skipType":"op","skipId":"a","episodeLength":1420.5},
+            {"interval":{"startTime":1300.0,"endTime":1390.0},"skipType":"mixed-ed","skipId":"b","episodeLength":1420.5}
+        ],"message":"Successfully found skip times","statusCode":200}"#;
+        let segments = provider.parse_response(body).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].kind, segments[0].start_ms, segments[0].end_ms), (SegmentKind::Intro, 88_500, 178_500));
+        assert_eq!(segments[1].kind, SegmentKind::Outro);
+        assert_eq!(segments[1].provider.as_deref(), Some("aniskip"));
+
+        let not_found = r#"{"found":false,"results":[],"message":"No results found","statusCode":404}"#;
+        assert!(provider.parse_response(not_found).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_segments_prefers_earlier_layers() {
+        let segment = |kind, start_ms, origin| SkipSegment { kind, start_ms, end_ms: start_ms + 90_000, origin, provider: None };
+        let merged = merge_segments(vec![
+            vec![segment(SegmentKind::Intro, 60_000, SegmentOrigin::Chapters)],
+            vec![
+                segment(SegmentKind::Intro, 62_000, SegmentOrigin::Remote),
+                segment(SegmentKind::Outro, 1_300_000, SegmentOrigin::Remote),
+            ],
+            vec![segment(SegmentKind::Outro, 1_290_000, SegmentOrigin::Fingerprint)],
+        ]);
+        let origins: Vec<_> = merged.iter().map(|s| (s.kind, s.origin)).collect();
+        assert_eq!(
+            origins,
+            vec![(SegmentKind::Intro, SegmentOrigin::Chapters), (SegmentKind::Outro, SegmentOrigin::Remote)]
+        );
+    }
+
+    #[test]
+    fn test_cache_freshness() {
+        let now = 10 * REMOTE_CACHE_TTL_MS;
+        let empty = CachedSegments { segments: Vec::new(), fetched_at: now - EMPTY_CACHE_TTL_MS - 1 };
+        assert!(!is_cache_fresh(&empty, now));
+        let found = CachedSegments {
+            segments: vec![SkipSegment {
+                kind: SegmentKind::Intro,
+                start_ms: 0,
+                end_ms: 90_000,
+                origin: SegmentOrigin::Remote,
+                provider: None,
+            }],
+            fetched_at: now - EMPTY_CACHE_TTL_MS - 1,
+        };
+        assert!(is_cache_fresh(&found, now));
+        assert_eq!(
+            cache_key("aniskip", &SkipQuery { anilist_id: Some(7), mal_id: None, episode: 3, duration_ms: None }),
+            "aniskip:al7:3"
+        );
+    }
+}
@@ -0,0 +1,580 @@
+//! Stream Proxy
+//!
+//! This module lets the webview play streams that need request headers it can't set
+//! (Referer, User-Agent, cookies), which is common for streams returned by plugin
+//! `getStreams` calls. A stream is registered with its headers and gets a token; the
+//! media server then serves it under `/stream/{token}/...`, injecting the headers
+//! into every upstream request.
+//!
+//! HLS playlists are rewritten so that variant playlists, segments, keys and init
+//! sections go through the proxy as well. Other responses are streamed through with
+//! byte-range support.
+//!
+//! Tokens are random, expire after a few minutes without use and only allow hosts
+//! the registered stream actually refers to, so other local processes can't use the
+//! proxy as an open relay. Local and private network hosts are never allowed, also
+//! when a public name resolves to one, so a playlist can't aim the injected headers
+//! at this machine or the LAN.
+
+use http_body_util::channel::Channel;
+use http_body_util::BodyExt;
+use hyper::body::Bytes;
+use hyper::{HeaderMap, Method, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_http::reqwest::{self, Url};
+
+use crate::downloads::encode_component;
+use crate::media_server::{self, HttpResponse};
+use crate::plugin_fetch::{is_local_host, is_local_host_name, PublicOnlyResolver};
+
+/// Default time a token stays valid without being used
+pub const STREAM_TOKEN_TTL_SECS: u64 = 10 * 60;
+
+/// Maximum lifetime of a token, however often it is used
+const MAX_TOKEN_LIFETIME_SECS: u64 = 12 * 60 * 60;
+
+/// Maximum size of a playlist
+const MAX_PLAYLIST_BYTES: usize = 8 * 1024 * 1024;
+
+/// Maximum number of upstream redirects
+const MAX_REDIRECTS: usize = 10;
+
+/// Upstream connect timeout
+const UPSTREAM_CONNECT_TIMEOUT_SECS: u64 = 15;
+
+/// Number of chunks buffered between upstream and the player
+const STREAM_BUFFER_CHUNKS: usize = 16;
+
+/// Request headers passed on from the player
+const FORWARDED_REQUEST_HEADERS: &[&str] = &["range", "if-range", "accept"];
+
+/// Response headers passed on to the player
+const FORWARDED_RESPONSE_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "content-range",
+    "accept-ranges",
+    "last-modified",
+    "etag",
+    "cache-control",
+];
+
+/// A stream to register
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamRegistration {
+    pub url: String,
+    /// Headers added to every upstream request (Referer, User-Agent, Cookie, ...)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Idle time before the token expires (default [`STREAM_TOKEN_TTL_SECS`])
+    pub ttl_secs: Option<u64>,
+}
+
+/// A registered stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxiedStream {
+    pub token: String,
+    /// URL to give the player
+    pub url: String,
+    /// Upstream URL
+    pub source_url: String,
+    pub expires_at: i64,
+}
+
+/// Proxy state of a token
+#[derive(Debug, Clone)]
+struct StreamEntry {
+    url: Url,
+    headers: Vec<(String, String)>,
+    /// Hosts this token may fetch from (the stream's host and hosts its playlists use)
+    allowed_hosts: HashSet<String>,
+    ttl_ms: i64,
+    created_at: i64,
+    expires_at: i64,
+}
+
+impl StreamEntry {
+    fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Extend the token after a request, up to its maximum lifetime
+    fn touch(&mut self, now: i64) {
+        let limit = self.created_at + MAX_TOKEN_LIFETIME_SECS as i64 * 1000;
+        self.expires_at = (now + self.ttl_ms).min(limit);
+    }
+
+    /// Whether this token may fetch `url`: a public http(s) URL on one of its hosts
+    fn allows(&self, url: &Url) -> bool {
+        matches!(url.scheme(), "http" | "https")
+            && !is_local_host(url)
+            && url.host_str().is_some_and(|host| self.allowed_hosts.contains(host))
+    }
+}
+
+/// Stream proxy state: registered streams by token
+pub struct StreamProxyState {
+    streams: Mutex<HashMap<String, StreamEntry>>,
+    client: reqwest::Client,
+}
+
+impl Default for StreamProxyState {
+    fn default() -> Self {
+        StreamProxyState {
+            streams: Mutex::new(HashMap::new()),
+            client: reqwest::Client::builder()
+                .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+                .dns_resolver(Arc::new(PublicOnlyResolver))
+                .redirect(reqwest::redirect::Policy::custom(|attempt| {
+                    if attempt.previous().len() > MAX_REDIRECTS {
+                        attempt.error("Too many redirects")
+                    } else if is_local_host(attempt.url()) {
+                        attempt.error("Redirect to a local or private address refused")
+                    } else {
+                        attempt.follow()
+                    }
+                }))
+                .connect_timeout(Duration::from_secs(UPSTREAM_CONNECT_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Generate an unguessable token from the OS CSPRNG
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate stream token: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Playlists
+// =============================================================================
+
+/// Whether a response is an HLS playlist
+pub fn is_playlist(content_type: Option<&str>, url: &Url) -> bool {
+    let content_type = content_type.unwrap_or_default().to_lowercase();
+    content_type.contains("mpegurl") || url.path().to_lowercase().ends_with(".m3u8")
+}
+
+/// Last path segment of a URL (gives players a file extension to go by)
+fn file_name(url: &Url) -> String {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back().map(str::to_string))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "stream".to_string());
+    encode_component(&name)
+}
+
+/// Proxy path of an upstream URL
+pub fn proxy_path(token: &str, url: &Url) -> String {
+    format!("/stream/{}/{}?u={}", token, file_name(url), encode_component(url.as_str()))
+}
+
+/// Rewrite a playlist so every URI goes through the proxy; returns the playlist and
+/// the hosts it refers to
+pub fn rewrite_playlist(body: &str, base: &Url, token: &str) -> (String, HashSet<String>) {
+    let mut hosts = HashSet::new();
+    let mut rewrite = |uri: &str| -> Option<String> {
+        let url = base.join(uri.trim()).ok()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        if let Some(host) = url.host_str() {
+            hosts.insert(host.to_string());
+        }
+        Some(proxy_path(token, &url))
+    };
+
+    let mut out = String::with_capacity(body.len() * 2);
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            out.push('\n');
+            continue;
+        }
+
+        if !trimmed.starts_with('#') {
+            out.push_str(&rewrite(trimmed).unwrap_or_else(|| trimmed.to_string()));
+            out.push('\n');
+            continue;
+        }
+
+        // Tags with URI attributes: EXT-X-KEY, EXT-X-MAP, EXT-X-MEDIA, EXT-X-I-FRAME-STREAM-INF, ...
+        let mut rest = trimmed;
+        while let Some(start) = rest.find("URI=\"") {
+            let value_start = start + 5;
+            let value_end = match rest[value_start..].find('"') {
+                Some(end) => value_start + end,
+                None => break,
+            };
+            let uri = &rest[value_start..value_end];
+            out.push_str(&rest[..value_start]);
+            out.push_str(&rewrite(uri).unwrap_or_else(|| uri.to_string()));
+            rest = &rest[value_end..];
+        }
+        out.push_str(rest);
+        out.push('\n');
+    }
+    (out, hosts)
+}
+
+// =============================================================================
+// HTTP Endpoint
+// =============================================================================
+
+/// Look up a token, extend it and check that it may fetch `target`
+fn authorize(app: &AppHandle, token: &str, target: Option<&str>) -> Result<StreamEntry, (u16, &'static str)> {
+    let state = app.state::<StreamProxyState>();
+    let mut streams = state
+        .streams
+        .lock()
+        .map_err(|_| (500, "Stream proxy unavailable"))?;
+    let now = get_current_timestamp();
+    streams.retain(|_, entry| !entry.is_expired(now));
+
+    let entry = streams
+        .get_mut(token)
+        .ok_or((403, "Invalid or expired stream token"))?;
+    entry.touch(now);
+
+    let mut entry = entry.clone();
+    if let Some(target) = target {
+        let url = Url::parse(target).map_err(|_| (400, "Invalid stream URL"))?;
+        if !entry.allows(&url) {
+            return Err((403, "URL not allowed for this stream"));
+        }
+        entry.url = url;
+    }
+    Ok(entry)
+}
+
+/// Remember the public hosts a token's playlists refer to
+fn allow_hosts(app: &AppHandle, token: &str, hosts: HashSet<String>) {
+    let state = app.state::<StreamProxyState>();
+    let mut streams = match state.streams.lock() {
+        Ok(streams) => streams,
+        Err(_) => return,
+    };
+    if let Some(entry) = streams.get_mut(token) {
+        entry
+            .allowed_hosts
+            .extend(hosts.into_iter().filter(|host| !is_local_host_name(host)));
+    }
+}
+
+/// Handle `/stream/{token}[/{name}][?u={url}]` requests of the local media server
+pub async fn handle_http(
+    app: &AppHandle,
+    method: &Method,
+    path: &str,
+    query: &HashMap<String, String>,
+    request_headers: &HeaderMap,
+) -> HttpResponse {
+    if method != Method::GET && method != Method::HEAD {
+        return media_server::text_response(405, "Method not allowed");
+    }
+    let token = path.split('/').next().unwrap_or_default();
+    let entry = match authorize(app, token, query.get("u").map(String::as_str)) {
+        Ok(entry) => entry,
+        Err((status, message)) => return media_server::text_response(status, message),
+    };
+
+    let client = app.state::<StreamProxyState>().client.clone();
+    let mut request = client.request(method.clone(), entry.url.clone());
+    for (name, value) in &entry.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    for name in FORWARDED_REQUEST_HEADERS {
+        if let Some(value) = request_headers.get(*name) {
+            request = request.header(*name, value.clone());
+        }
+    }
+
+    let mut upstream = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("Stream proxy request to {} failed: {}", entry.url, e);
+            return media_server::text_response(502, "Upstream request failed");
+        }
+    };
+    let status = upstream.status();
+    let content_type = upstream
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    if status.is_success() && method == Method::GET && is_playlist(content_type.as_deref(), upstream.url()) {
+        let base = upstream.url().clone();
+        let mut body = Vec::new();
+        loop {
+            match upstream.chunk().await {
+                Ok(Some(chunk)) => {
+                    body.extend_from_slice(&chunk);
+                    if body.len() > MAX_PLAYLIST_BYTES {
+                        return media_server::text_response(502, "Playlist is too large");
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => return media_server::text_response(502, &format!("Failed to read playlist: {}", e)),
+            }
+        }
+        let text = String::from_utf8_lossy(&body);
+        if text.trim_start_matches('\u{feff}').starts_with("#EXTM3U") {
+            let (playlist, hosts) = rewrite_playlist(&text, &base, token);
+            allow_hosts(app, token, hosts);
+            let mut response =
+                media_server::bytes_response(200, "application/vnd.apple.mpegurl", playlist.into_bytes());
+            response
+                .headers_mut()
+                .insert("cache-control", hyper::header::HeaderValue::from_static("no-cache"));
+            return response;
+        }
+        // Not actually a playlist: pass it on unchanged
+        return media_server::bytes_response(
+            status.as_u16(),
+            content_type.as_deref().unwrap_or("application/octet-stream"),
+            body,
+        );
+    }
+
+    // Stream everything else through
+    let (mut sender, body) = Channel::<Bytes>::new(STREAM_BUFFER_CHUNKS);
+    let mut response = Response::new(body.boxed());
+    *response.status_mut() = StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    for name in FORWARDED_RESPONSE_HEADERS {
+        if let Some(value) = upstream.headers().get(*name) {
+            response.headers_mut().insert(*name, value.clone());
+        }
+    }
+
+    if method == Method::GET {
+        tauri::async_runtime::spawn(async move {
+            // Dropping the sender early ends the body, which aborts the response
+            while let Ok(Some(chunk)) = upstream.chunk().await {
+                if sender.send_data(chunk).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    response
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Register a stream with the headers it needs and get a proxy URL for the player
+#[tauri::command]
+pub fn stream_proxy_register(
+    registration: StreamRegistration,
+    app: AppHandle,
+    state: State<'_, StreamProxyState>,
+) -> Result<ProxiedStream, String> {
+    let url = Url::parse(registration.url.trim()).map_err(|e| format!("Invalid stream URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("Only http(s) streams can be proxied".to_string());
+    }
+    let host = url.host_str().ok_or("Stream URL has no host")?.to_string();
+    if is_local_host(&url) {
+        return Err("Local and private network streams can't be proxied".to_string());
+    }
+    let server_url = media_server::server_url(&app).ok_or("Media server is not running")?;
+
+    let headers: Vec<(String, String)> = registration
+        .headers
+        .into_iter()
+        .filter(|(name, value)| {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_ok()
+                && reqwest::header::HeaderValue::from_str(value).is_ok()
+        })
+        .collect();
+
+    let now = get_current_timestamp();
+    let ttl_ms = registration.ttl_secs.unwrap_or(STREAM_TOKEN_TTL_SECS).clamp(30, MAX_TOKEN_LIFETIME_SECS) as i64 * 1000;
+    let token = generate_token()?;
+    let entry = StreamEntry {
+        url: url.clone(),
+        headers,
+        allowed_hosts: HashSet::from([host]),
+        ttl_ms,
+        created_at: now,
+        expires_at: now + ttl_ms,
+    };
+
+    let mut streams = state
+        .streams
+        .lock()
+        .map_err(|e| format!("Failed to lock stream proxy: {}", e))?;
+    streams.retain(|_, entry| !entry.is_expired(now));
+    streams.insert(token.clone(), entry);
+
+    log::info!("Registered proxied stream for {}", url.host_str().unwrap_or_default());
+    Ok(ProxiedStream {
+        url: format!("{}/stream/{}/{}", server_url, token, file_name(&url)),
+        token,
+        source_url: url.to_string(),
+        expires_at: now + ttl_ms,
+    })
+}
+
+/// Revoke a stream token
+#[tauri::command]
+pub fn stream_proxy_revoke(token: String, state: State<'_, StreamProxyState>) -> Result<(), String> {
+    state
+        .streams
+        .lock()
+        .map_err(|e| format!("Failed to lock stream proxy: {}", e))?
+        .remove(&token);
+    Ok(())
+}
+
+/// List the streams with valid tokens
+#[tauri::command]
+pub fn stream_proxy_list(app: AppHandle, state: State<'_, StreamProxyState>) -> Result<Vec<ProxiedStream>, String> {
+    let server_url = media_server::server_url(&app).unwrap_or_default();
+    let now = get_current_timestamp();
+    let streams = state
+        .streams
+        .lock()
+        .map_err(|e| format!("Failed to lock stream proxy: {}", e))?;
+
+    Ok(streams
+        .iter()
+        .filter(|(_, entry)| !entry.is_expired(now))
+        .map(|(token, entry)| ProxiedStream {
+            token: token.clone(),
+            url: format!("{}/stream/{}/{}", server_url, token, file_name(&entry.url)),
+            source_url: entry.url.to_string(),
+            expires_at: entry.expires_at,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_master_playlist() {
+        let base = Url::parse("https://cdn.example.com/hls/master.m3u8?sig=abc").unwrap();
+        let playlist = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"Japanese\",URI=\"audio/jpn.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,AUDIO=\"aud\"\n\
+            1080p/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000\n\
+            https://other.example.net/480p.m3u8\n";
+
+        let (rewritten, hosts) = rewrite_playlist(playlist, &base, "tok");
+        let lines: Vec<&str> = rewritten.lines().collect();
+        assert_eq!(
+            lines[1],
+            "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"Japanese\",URI=\"/stream/tok/jpn.m3u8?u=https%3A%2F%2Fcdn.example.com%2Fhls%2Faudio%2Fjpn.m3u8\""
+        );
+        assert_eq!(lines[2], "#EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,AUDIO=\"aud\"");
+        assert_eq!(lines[3], "/stream/tok/index.m3u8?u=https%3A%2F%2Fcdn.example.com%2Fhls%2F1080p%2Findex.m3u8");
+        assert_eq!(lines[5], "/stream/tok/480p.m3u8?u=https%3A%2F%2Fother.example.net%2F480p.m3u8");
+        assert_eq!(hosts, HashSet::from(["cdn.example.com".to_string(), "other.example.net".to_string()]));
+    }
+
+    #[test]
+    fn test_rewrite_media_playlist_keys_and_maps() {
+        let base = Url::parse("https://cdn.example.com/v/720/index.m3u8").unwrap();
+        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"../key.bin\",IV=0x1234\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXTINF:6.0,\nseg0.ts\n\
+            #EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"skd://drm\"\n\
+            #EXT-X-ENDLIST\n";
+
+        let (rewritten, _) = rewrite_playlist(playlist, &base, "t");
+        assert!(rewritten.contains("URI=\"/stream/t/key.bin?u=https%3A%2F%2Fcdn.example.com%2Fv%2Fkey.bin\",IV=0x1234"));
+        assert!(rewritten.contains("#EXT-X-MAP:URI=\"/stream/t/init.mp4?u="));
+        assert!(rewritten.contains("\n/stream/t/seg0.ts?u=https%3A%2F%2Fcdn.example.com%2Fv%2F720%2Fseg0.ts\n"));
+        // Non-HTTP key URIs are left alone
+        assert!(rewritten.contains("URI=\"skd://drm\""));
+        assert!(rewritten.ends_with("#EXT-X-ENDLIST\n"));
+    }
+
+    #[test]
+    fn test_is_playlist() {
+        let m3u8 = Url::parse("https://a.example/x/playlist.M3U8").unwrap();
+        let ts = Url::parse("https://a.example/x/seg.ts").unwrap();
+        assert!(is_playlist(None, &m3u8));
+        assert!(is_playlist(Some("application/vnd.apple.mpegurl"), &ts));
+        assert!(is_playlist(Some("audio/x-mpegURL"), &ts));
+        assert!(!is_playlist(Some("video/mp2t"), &ts));
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        let a = generate_token().unwrap();
+        let b = generate_token().unwrap();
+        assert_eq!(a.len(), 32);
+        assert_ne!(a, b);
+
+        let mut entry = StreamEntry {
+            url: Url::parse("https://a.example/").unwrap(),
+            headers: Vec::new(),
+            allowed_hosts: HashSet::new(),
+            ttl_ms: 60_000,
+            created_at: 0,
+            expires_at: 60_000,
+        };
+        assert!(!entry.is_expired(59_999));
+        entry.touch(50_000);
+        assert!(!entry.is_expired(100_000));
+        assert!(entry.is_expired(110_000));
+
+        // Use can't extend a token past its maximum lifetime
+        let limit = MAX_TOKEN_LIFETIME_SECS as i64 * 1000;
+        entry.touch(limit - 1000);
+        assert_eq!(entry.expires_at, limit);
+    }
+
+    #[test]
+    fn test_allowed_targets() {
+        let entry = StreamEntry {
+            url: Url::parse("https://cdn.example.com/master.m3u8").unwrap(),
+            headers: Vec::new(),
+            allowed_hosts: HashSet::from([
+                "cdn.example.com".to_string(),
+                "127.0.0.1".to_string(),
+                "[::1]".to_string(),
+                "192.168.1.10".to_string(),
+            ]),
+            ttl_ms: 60_000,
+            created_at: 0,
+            expires_at: 60_000,
+        };
+        assert!(entry.allows(&Url::parse("https://cdn.example.com/seg0.ts").unwrap()));
+        assert!(!entry.allows(&Url::parse("https://other.example.net/seg0.ts").unwrap()));
+        assert!(!entry.allows(&Url::parse("ftp://cdn.example.com/seg0.ts").unwrap()));
+
+        // Loopback, link-local and LAN hosts are refused even once listed
+        assert!(!entry.allows(&Url::parse("http://127.0.0.1:64621/torrents").unwrap()));
+        assert!(!entry.allows(&Url::parse("http://[::1]/seg0.ts").unwrap()));
+        assert!(!entry.allows(&Url::parse("http://192.168.1.10/seg0.ts").unwrap()));
+        assert!(is_local_host_name("169.254.169.254"));
+        assert!(is_local_host_name("[::1]"));
+        assert!(!is_local_host_name("cdn.example.com"));
+    }
+}
@@ -0,0 +1,821 @@
+//! Stream Selection
+//!
+//! Plugin `getStreams` calls return several sources (`{url, format, quality, server}`)
+//! and the player used to take the first one. This module probes the candidates and
+//! ranks them instead:
+//!
+//! - HLS streams: the playlist is fetched, the master playlist's variants give the
+//!   resolution (the variant the preferred quality would play) and audio languages, and
+//!   the first segment of that variant is requested to measure its latency
+//! - progressive files (`mp4`, `mkv`, `webm`): the first bytes are requested
+//! - torrents and DASH manifests are not probed
+//!
+//! Candidates are scored with the profile's `preferredQuality` and
+//! `preferredAudioLang` and its sub/dub preference (`custom.preferredAudioType`, or
+//! derived from the audio language). A selection is kept as a session, so when a
+//! source dies mid-episode the player can ask for the next one with
+//! `stream_next_fallback`, which re-probes the remaining candidates before returning one.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+use tauri_plugin_http::reqwest::{self, Url};
+
+use crate::hls_download::{self, Playlist};
+use crate::profiles::{self, ProfileSettings, ProfileState};
+use crate::torrent_search::parse_quality;
+
+/// Time a candidate gets to answer all probe requests
+const PROBE_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Candidates probed per selection (the rest are ranked without a probe)
+const MAX_PROBED_STREAMS: usize = 12;
+
+/// Candidates re-probed when looking for a fallback before one is returned untested
+const MAX_FALLBACK_PROBES: usize = 3;
+
+/// Bytes requested from a segment or file
+const PROBE_RANGE_BYTES: u64 = 64 * 1024;
+
+/// Maximum size of a probed playlist
+const MAX_PLAYLIST_BYTES: usize = 2 * 1024 * 1024;
+
+/// Selections are forgotten after this long without use
+const SESSION_TTL_MS: i64 = 6 * 60 * 60 * 1000;
+
+/// Sub or dub
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioType {
+    Sub,
+    Dub,
+}
+
+/// A stream source returned by a plugin (`ZPEStreamSource`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamCandidate {
+    pub url: String,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub quality: Option<String>,
+    #[serde(default)]
+    pub server: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub is_default: bool,
+    /// Language of the audio track
+    #[serde(default)]
+    pub audio_lang: Option<String>,
+    /// `sub` or `dub`
+    #[serde(default)]
+    pub audio: Option<AudioType>,
+    #[serde(default)]
+    pub is_dub: Option<bool>,
+    /// Other fields set by the plugin, passed through to the player
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+impl StreamCandidate {
+    fn is_hls(&self) -> bool {
+        match self.format.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            Some("m3u8" | "hls") => true,
+            Some(_) => false,
+            None => Url::parse(&self.url).is_ok_and(|url| url.path().ends_with(".m3u8")),
+        }
+    }
+
+    fn is_probeable(&self) -> bool {
+        let format = self.format.as_deref().map(str::to_ascii_lowercase);
+        !matches!(format.as_deref(), Some("torrent" | "dash" | "mpd")) && !self.url.starts_with("magnet:")
+    }
+
+    /// Sub or dub, from the plugin's fields or the server and quality labels
+    pub fn audio_type(&self) -> Option<AudioType> {
+        if let Some(audio) = self.audio {
+            return Some(audio);
+        }
+        if let Some(is_dub) = self.is_dub {
+            return Some(if is_dub { AudioType::Dub } else { AudioType::Sub });
+        }
+        let labels = [self.server.as_deref(), self.quality.as_deref()];
+        let words: Vec<String> = labels
+            .into_iter()
+            .flatten()
+            .flat_map(|label| label.split(|c: char| !c.is_alphanumeric()))
+            .map(str::to_lowercase)
+            .collect();
+        if words.iter().any(|w| matches!(w.as_str(), "dub" | "dubbed")) {
+            Some(AudioType::Dub)
+        } else if words
+            .iter()
+            .any(|w| matches!(w.as_str(), "sub" | "subbed" | "softsub" | "hardsub" | "raw"))
+        {
+            Some(AudioType::Sub)
+        } else {
+            None
+        }
+    }
+}
+
+/// What probing a candidate found
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamProbe {
+    pub alive: bool,
+    /// Time to the first response (playlist or file)
+    pub latency_ms: Option<u64>,
+    /// Time to the first segment of the picked HLS variant
+    pub segment_latency_ms: Option<u64>,
+    /// Height of the variant the preferred quality plays (or of the only stream)
+    pub height: Option<u32>,
+    /// Highest variant of a master playlist
+    pub max_height: Option<u32>,
+    pub bandwidth: Option<u64>,
+    /// Languages of the master playlist's audio renditions
+    pub audio_languages: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Ranking preferences
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamPreferences {
+    /// Preferred vertical resolution (e.g. 1080)
+    pub preferred_height: Option<u32>,
+    /// Preferred audio language (normalized to a two-letter code where known)
+    pub audio_lang: Option<String>,
+    pub audio_type: Option<AudioType>,
+}
+
+impl StreamPreferences {
+    /// Build ranking preferences from profile settings
+    pub fn from_profile(settings: &ProfileSettings) -> Self {
+        let audio_lang = settings
+            .preferred_audio_lang
+            .as_deref()
+            .filter(|lang| !lang.trim().is_empty())
+            .map(normalize_language);
+        let audio_type = settings
+            .custom
+            .get("preferredAudioType")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            // Japanese audio means subs, any other language a dub
+            .or_else(|| audio_lang.as_deref().map(|lang| if lang == "ja" { AudioType::Sub } else { AudioType::Dub }));
+        StreamPreferences {
+            preferred_height: settings.preferred_quality.as_deref().and_then(parse_quality),
+            audio_lang,
+            audio_type,
+        }
+    }
+}
+
+/// A ranked candidate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedStream {
+    /// Position in the plugin's list
+    pub index: usize,
+    pub score: i64,
+    pub stream: StreamCandidate,
+    pub probe: Option<StreamProbe>,
+}
+
+/// Result of a selection
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSelection {
+    /// Pass to `stream_next_fallback` when the playing source fails
+    pub session_id: String,
+    /// Candidates, best first
+    pub streams: Vec<RankedStream>,
+    /// Candidate to play (the best one that answered the probe)
+    pub selected: Option<RankedStream>,
+}
+
+/// A selection kept for failover
+#[derive(Debug, Clone)]
+struct SelectionSession {
+    streams: Vec<RankedStream>,
+    /// Position (in `streams`) of the candidate playing
+    current: Option<usize>,
+    failed: HashSet<usize>,
+    preferences: StreamPreferences,
+    last_used: i64,
+}
+
+impl SelectionSession {
+    fn new(streams: Vec<RankedStream>, preferences: StreamPreferences, now: i64) -> Self {
+        let failed: HashSet<usize> = streams
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.probe.as_ref().is_some_and(|p| !p.alive))
+            .map(|(i, _)| i)
+            .collect();
+        let current = (0..streams.len()).find(|i| !failed.contains(i));
+        SelectionSession {
+            streams,
+            current,
+            failed,
+            preferences,
+            last_used: now,
+        }
+    }
+
+    /// Mark the candidate with `url` as failed, or the one playing when `url` matches
+    /// no candidate (the player reports proxied URLs, not upstream ones)
+    fn mark_failed(&mut self, url: Option<&str>) {
+        let position = url
+            .and_then(|url| self.streams.iter().position(|s| s.stream.url == url))
+            .or(self.current);
+        if let Some(position) = position {
+            self.failed.insert(position);
+            if self.current == Some(position) {
+                self.current = None;
+            }
+        }
+    }
+
+    /// Candidates not known to fail, best first
+    fn remaining(&self) -> Vec<usize> {
+        (0..self.streams.len()).filter(|i| !self.failed.contains(i)).collect()
+    }
+}
+
+/// Stream selector state: HTTP client and selections kept for failover
+pub struct StreamSelectorState {
+    client: reqwest::Client,
+    sessions: Mutex<HashMap<String, SelectionSession>>,
+    counter: AtomicU64,
+}
+
+impl Default for StreamSelectorState {
+    fn default() -> Self {
+        StreamSelectorState {
+            client: reqwest::Client::builder()
+                .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+                .timeout(PROBE_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            sessions: Mutex::new(HashMap::new()),
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Ranking
+// =============================================================================
+
+/// Two-letter code for common language names and ISO 639-2 codes
+pub fn normalize_language(lang: &str) -> String {
+    let lang = lang.trim().to_lowercase();
+    let primary = lang.split(['-', '_']).next().unwrap_or_default();
+    let code = match primary {
+        "ja" | "jp" | "jpn" | "japanese" => "ja",
+        "en" | "eng" | "english" => "en",
+        "es" | "spa" | "spanish" => "es",
+        "pt" | "por" | "portuguese" => "pt",
+        "fr" | "fre" | "fra" | "french" => "fr",
+        "de" | "ger" | "deu" | "german" => "de",
+        "it" | "ita" | "italian" => "it",
+        "ru" | "rus" | "russian" => "ru",
+        "ko" | "kor" | "korean" => "ko",
+        "zh" | "chi" | "zho" | "chinese" => "zh",
+        "ar" | "ara" | "arabic" => "ar",
+        other => other,
+    };
+    code.to_string()
+}
+
+/// Score a candidate (higher is better)
+pub fn score_stream(stream: &StreamCandidate, probe: Option<&StreamProbe>, prefs: &StreamPreferences) -> i64 {
+    let mut score: i64 = 0;
+
+    if let Some(probe) = probe {
+        if !probe.alive {
+            return -10_000;
+        }
+        // Slow servers stall playback; cap the penalty so quality still decides
+        let latency = probe.segment_latency_ms.or(probe.latency_ms).unwrap_or(0);
+        score -= (latency as i64 / 10).min(300);
+    }
+
+    let height = probe
+        .and_then(|p| p.height)
+        .or_else(|| stream.quality.as_deref().and_then(parse_quality));
+    if let (Some(wanted), Some(actual)) = (prefs.preferred_height, height) {
+        if wanted == actual {
+            score += 500;
+        } else {
+            // Penalize by distance, slightly favouring higher resolutions
+            let diff = (wanted as i64 - actual as i64).abs();
+            score -= if actual > wanted { diff / 4 } else { diff / 2 };
+        }
+    } else if let Some(actual) = height {
+        score += actual as i64 / 10;
+    }
+
+    if let (Some(wanted), Some(actual)) = (prefs.audio_type, stream.audio_type()) {
+        score += if wanted == actual { 400 } else { -400 };
+    }
+
+    if let Some(wanted) = prefs.audio_lang.as_deref() {
+        let has_language = stream
+            .audio_lang
+            .iter()
+            .chain(probe.into_iter().flat_map(|p| p.audio_languages.iter()))
+            .any(|lang| normalize_language(lang) == wanted);
+        if has_language {
+            score += 200;
+        }
+    }
+
+    if stream.is_default {
+        score += 50;
+    }
+    score
+}
+
+/// Score and sort candidates, best first (the plugin's order breaks ties)
+pub fn rank_streams(
+    streams: Vec<StreamCandidate>,
+    mut probes: Vec<Option<StreamProbe>>,
+    prefs: &StreamPreferences,
+) -> Vec<RankedStream> {
+    probes.resize(streams.len(), None);
+    let mut ranked: Vec<RankedStream> = streams
+        .into_iter()
+        .zip(probes)
+        .enumerate()
+        .map(|(index, (stream, probe))| RankedStream {
+            index,
+            score: score_stream(&stream, probe.as_ref(), prefs),
+            stream,
+            probe,
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.index.cmp(&b.index)));
+    ranked
+}
+
+// =============================================================================
+// Probing
+// =============================================================================
+
+/// A response to a probe request
+struct ProbeResponse {
+    body: Vec<u8>,
+    latency_ms: u64,
+}
+
+/// Request a URL with the stream's headers, reading at most `limit` bytes
+async fn probe_get(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HashMap<String, String>,
+    range: Option<(u64, u64)>,
+    limit: usize,
+) -> Result<ProbeResponse, String> {
+    let started = Instant::now();
+    let mut request = client.get(url);
+    for (name, value) in headers {
+        if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_ok()
+            && reqwest::header::HeaderValue::from_str(value).is_ok()
+        {
+            request = request.header(name.as_str(), value.as_str());
+        }
+    }
+    if let Some((offset, last)) = range {
+        request = request.header("range", format!("bytes={}-{}", offset, last));
+    }
+
+    let mut response = request.send().await.map_err(|e| format!("Request failed: {}", e))?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status().as_u16()));
+    }
+
+    let mut body = Vec::new();
+    while body.len() < limit {
+        match response.chunk().await.map_err(|e| format!("Failed to read response: {}", e))? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    body.truncate(limit);
+    Ok(ProbeResponse { body, latency_ms })
+}
+
+/// Probe an HLS stream: playlist, picked variant and its first segment
+async fn probe_hls(
+    client: &reqwest::Client,
+    stream: &StreamCandidate,
+    preferred_height: Option<u32>,
+) -> Result<StreamProbe, String> {
+    let url = Url::parse(&stream.url).map_err(|e| format!("Invalid stream URL: {}", e))?;
+    let response = probe_get(client, url.as_str(), &stream.headers, None, MAX_PLAYLIST_BYTES).await?;
+    let mut probe = StreamProbe {
+        latency_ms: Some(response.latency_ms),
+        ..StreamProbe::default()
+    };
+
+    let mut playlist = hls_download::parse_playlist(&String::from_utf8_lossy(&response.body), &url)?;
+    if let Playlist::Master(master) = &playlist {
+        let variant = hls_download::select_variant(&master.variants, preferred_height)
+            .ok_or("Master playlist has no variants")?;
+        probe.height = variant.height;
+        probe.max_height = master.variants.iter().filter_map(|v| v.height).max();
+        probe.bandwidth = Some(variant.bandwidth).filter(|b| *b > 0);
+        probe.audio_languages = master.audio.iter().filter_map(|a| a.language.clone()).collect();
+
+        let variant_url = Url::parse(&variant.uri).map_err(|e| format!("Invalid variant URL: {}", e))?;
+        let response = probe_get(client, variant_url.as_str(), &stream.headers, None, MAX_PLAYLIST_BYTES).await?;
+        playlist = hls_download::parse_playlist(&String::from_utf8_lossy(&response.body), &variant_url)?;
+    }
+
+    let Playlist::Media(media) = playlist else {
+        return Err("Variant playlist is a master playlist".to_string());
+    };
+    let segment = media.segments.first().ok_or("Playlist has no segments")?;
+    let range = match segment.byte_range {
+        Some((offset, length)) => (offset, offset + length.clamp(1, PROBE_RANGE_BYTES) - 1),
+        None => (0, PROBE_RANGE_BYTES - 1),
+    };
+    let response = probe_get(client, &segment.uri, &stream.headers, Some(range), PROBE_RANGE_BYTES as usize).await?;
+    if response.body.is_empty() {
+        return Err("First segment is empty".to_string());
+    }
+    probe.segment_latency_ms = Some(response.latency_ms);
+    probe.alive = true;
+    Ok(probe)
+}
+
+/// Probe a progressive file by requesting its first bytes
+async fn probe_file(client: &reqwest::Client, stream: &StreamCandidate) -> Result<StreamProbe, String> {
+    let response = probe_get(client, &stream.url, &stream.headers, Some((0, PROBE_RANGE_BYTES - 1)), 1024).await?;
+    if response.body.is_empty() {
+        return Err("Empty response".to_string());
+    }
+    Ok(StreamProbe {
+        alive: true,
+        latency_ms: Some(response.latency_ms),
+        height: stream.quality.as_deref().and_then(parse_quality),
+        ..StreamProbe::default()
+    })
+}
+
+/// Probe a candidate; `None` for sources that aren't probed (torrents, DASH)
+pub async fn probe_stream(
+    client: &reqwest::Client,
+    stream: &StreamCandidate,
+    preferred_height: Option<u32>,
+) -> Option<StreamProbe> {
+    if !stream.is_probeable() {
+        return None;
+    }
+    let probe = async {
+        if stream.is_hls() {
+            probe_hls(client, stream, preferred_height).await
+        } else {
+            probe_file(client, stream).await
+        }
+    };
+    let probe = match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+        Ok(Ok(probe)) => probe,
+        Ok(Err(e)) => StreamProbe {
+            error: Some(e),
+            ..StreamProbe::default()
+        },
+        Err(_) => StreamProbe {
+            error: Some(format!("No answer within {} s", PROBE_TIMEOUT.as_secs())),
+            ..StreamProbe::default()
+        },
+    };
+    Some(probe)
+}
+
+/// Probe candidates concurrently (only the first [`MAX_PROBED_STREAMS`])
+async fn probe_streams(
+    client: &reqwest::Client,
+    streams: &[StreamCandidate],
+    preferred_height: Option<u32>,
+) -> Vec<Option<StreamProbe>> {
+    let handles: Vec<_> = streams
+        .iter()
+        .take(MAX_PROBED_STREAMS)
+        .map(|stream| {
+            let client = client.clone();
+            let stream = stream.clone();
+            tauri::async_runtime::spawn(async move { probe_stream(&client, &stream, preferred_height).await })
+        })
+        .collect();
+
+    let mut probes = Vec::new();
+    for handle in handles {
+        probes.push(handle.await.unwrap_or(None));
+    }
+    probes
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Probe and rank the streams of an episode with a profile's preferences (the active
+/// profile's by default). Pass `probe: false` to rank by the plugin's labels only.
+#[tauri::command]
+pub async fn stream_select(
+    streams: Vec<StreamCandidate>,
+    profile_id: Option<String>,
+    probe: Option<bool>,
+    app: AppHandle,
+    state: State<'_, StreamSelectorState>,
+    profile_state: State<'_, ProfileState>,
+) -> Result<StreamSelection, String> {
+    if streams.is_empty() {
+        return Err("No streams to select from".to_string());
+    }
+    let preferences = profiles::resolve_profile_settings(&app, &profile_state, profile_id.as_deref())
+        .map(|settings| StreamPreferences::from_profile(&settings))
+        .unwrap_or_default();
+
+    let probes = if probe.unwrap_or(true) {
+        probe_streams(&state.client, &streams, preferences.preferred_height).await
+    } else {
+        Vec::new()
+    };
+    let ranked = rank_streams(streams, probes, &preferences);
+
+    let now = get_current_timestamp();
+    let session = SelectionSession::new(ranked.clone(), preferences, now);
+    let selected = session.current.map(|i| session.streams[i].clone());
+    let session_id = format!("streams_{}_{}", now, state.counter.fetch_add(1, Ordering::Relaxed));
+    {
+        let mut sessions = state
+            .sessions
+            .lock()
+            .map_err(|e| format!("Failed to lock stream selections: {}", e))?;
+        sessions.retain(|_, s| now - s.last_used < SESSION_TTL_MS);
+        sessions.insert(session_id.clone(), session);
+    }
+
+    if let Some(selected) = &selected {
+        log::info!(
+            "Selected stream {} of {} ({})",
+            selected.index + 1,
+            ranked.len(),
+            selected.stream.server.as_deref().unwrap_or("unnamed server")
+        );
+    }
+    Ok(StreamSelection {
+        session_id,
+        streams: ranked,
+        selected,
+    })
+}
+
+/// Switch to the next source after the playing one (or `failed_url`) died. Remaining
+/// candidates are probed again, best first; returns `None` when none is left.
+#[tauri::command]
+pub async fn stream_next_fallback(
+    session_id: String,
+    failed_url: Option<String>,
+    state: State<'_, StreamSelectorState>,
+) -> Result<Option<RankedStream>, String> {
+    let (candidates, preferences) = {
+        let mut sessions = state
+            .sessions
+            .lock()
+            .map_err(|e| format!("Failed to lock stream selections: {}", e))?;
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or_else(|| format!("Stream selection '{}' has expired", session_id))?;
+        session.mark_failed(failed_url.as_deref());
+        session.last_used = get_current_timestamp();
+        let candidates: Vec<(usize, RankedStream)> = session
+            .remaining()
+            .into_iter()
+            .map(|i| (i, session.streams[i].clone()))
+            .collect();
+        (candidates, session.preferences.clone())
+    };
+
+    let mut probed = 0;
+    let mut next = None;
+    let mut dead = Vec::new();
+    for (position, mut candidate) in candidates {
+        if probed < MAX_FALLBACK_PROBES {
+            if let Some(probe) = probe_stream(&state.client, &candidate.stream, preferences.preferred_height).await {
+                probed += 1;
+                let alive = probe.alive;
+                candidate.probe = Some(probe);
+                if !alive {
+                    dead.push(position);
+                    continue;
+                }
+            }
+        }
+        next = Some((position, candidate));
+        break;
+    }
+
+    let mut sessions = state
+        .sessions
+        .lock()
+        .map_err(|e| format!("Failed to lock stream selections: {}", e))?;
+    let Some(session) = sessions.get_mut(&session_id) else {
+        return Ok(next.map(|(_, candidate)| candidate));
+    };
+    session.failed.extend(dead);
+    session.current = next.as_ref().map(|(position, _)| *position);
+    if let Some((position, candidate)) = &next {
+        session.streams[*position] = candidate.clone();
+        log::info!(
+            "Falling back to stream {} ({})",
+            candidate.index + 1,
+            candidate.stream.server.as_deref().unwrap_or("unnamed server")
+        );
+    } else {
+        log::warn!("No working stream left in selection {}", session_id);
+    }
+    Ok(next.map(|(_, candidate)| candidate))
+}
+
+/// Forget a selection (e.g. when the episode is closed)
+#[tauri::command]
+pub fn stream_selection_release(session_id: String, state: State<'_, StreamSelectorState>) -> Result<(), String> {
+    state
+        .sessions
+        .lock()
+        .map_err(|e| format!("Failed to lock stream selections: {}", e))?
+        .remove(&session_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_server::serve_routes;
+
+    fn candidate(value: Value) -> StreamCandidate {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn alive(height: Option<u32>, latency_ms: u64) -> Option<StreamProbe> {
+        Some(StreamProbe {
+            alive: true,
+            latency_ms: Some(latency_ms),
+            height,
+            ..StreamProbe::default()
+        })
+    }
+
+    fn dead() -> Option<StreamProbe> {
+        Some(StreamProbe {
+            error: Some("HTTP 404".to_string()),
+            ..StreamProbe::default()
+        })
+    }
+
+    #[test]
+    fn test_candidate_fields() {
+        let stream = candidate(json!({
+            "url": "https://cdn.example.com/ep1/master.m3u8", "quality": "1080p", "server": "Vidstream (Dub)",
+            "headers": { "Referer": "https://example.com/" }, "subtitles": [{ "lang": "en" }]
+        }));
+        assert!(stream.is_hls());
+        assert_eq!(stream.audio_type(), Some(AudioType::Dub));
+        // Unknown fields are passed through
+        assert_eq!(serde_json::to_value(&stream).unwrap()["subtitles"][0]["lang"], "en");
+
+        let stream = candidate(json!({ "url": "https://cdn.example.com/ep1.mp4", "format": "mp4", "quality": "HD sub" }));
+        assert!(!stream.is_hls());
+        assert_eq!(stream.audio_type(), Some(AudioType::Sub));
+        assert_eq!(candidate(json!({ "url": "x", "isDub": true, "server": "sub" })).audio_type(), Some(AudioType::Dub));
+        assert_eq!(candidate(json!({ "url": "x", "server": "Subsplease mirror" })).audio_type(), None);
+        assert!(!candidate(json!({ "url": "magnet:?xt=urn:btih:abc", "format": "torrent" })).is_probeable());
+
+        assert_eq!(normalize_language("JPN"), "ja");
+        assert_eq!(normalize_language("en-US"), "en");
+        assert_eq!(normalize_language("tl"), "tl");
+    }
+
+    #[test]
+    fn test_preferences_from_profile() {
+        let mut settings = ProfileSettings {
+            preferred_quality: Some("720p".to_string()),
+            preferred_audio_lang: Some("Japanese".to_string()),
+            ..ProfileSettings::default()
+        };
+        let prefs = StreamPreferences::from_profile(&settings);
+        assert_eq!(prefs.preferred_height, Some(720));
+        assert_eq!(prefs.audio_lang.as_deref(), Some("ja"));
+        assert_eq!(prefs.audio_type, Some(AudioType::Sub));
+
+        settings.preferred_audio_lang = Some("eng".to_string());
+        assert_eq!(StreamPreferences::from_profile(&settings).audio_type, Some(AudioType::Dub));
+        settings.custom.insert("preferredAudioType".to_string(), json!("sub"));
+        assert_eq!(StreamPreferences::from_profile(&settings).audio_type, Some(AudioType::Sub));
+    }
+
+    #[test]
+    fn test_rank_streams() {
+        let prefs = StreamPreferences {
+            preferred_height: Some(1080),
+            audio_lang: Some("ja".to_string()),
+            audio_type: Some(AudioType::Sub),
+        };
+        let streams = vec![
+            candidate(json!({ "url": "https://a/dub.m3u8", "quality": "1080p", "audio": "dub", "isDefault": true })),
+            candidate(json!({ "url": "https://b/sub-720.mp4", "quality": "720p", "audio": "sub" })),
+            candidate(json!({ "url": "https://c/sub.m3u8", "quality": "auto", "audio": "sub" })),
+            candidate(json!({ "url": "https://d/sub-1080.mp4", "quality": "1080p", "audio": "sub" })),
+        ];
+        let probes = vec![alive(Some(1080), 100), alive(Some(720), 50), alive(Some(1080), 900), dead()];
+
+        let ranked = rank_streams(streams.clone(), probes, &prefs);
+        let order: Vec<usize> = ranked.iter().map(|s| s.index).collect();
+        // The 1080p sub stream is dead, the slow master playlist still has 1080p subs
+        assert_eq!(order, [2, 1, 0, 3]);
+        assert_eq!(ranked[3].score, -10_000);
+
+        // Without probes the labels decide
+        let order: Vec<usize> = rank_streams(streams, Vec::new(), &prefs).iter().map(|s| s.index).collect();
+        assert_eq!(order, [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_fallback_session() {
+        let prefs = StreamPreferences::default();
+        let streams = (0..4)
+            .map(|i| candidate(json!({ "url": format!("https://s{}/video.mp4", i) })))
+            .collect();
+        let ranked = rank_streams(streams, vec![None, dead(), None, None], &prefs);
+        let mut session = SelectionSession::new(ranked, prefs, 0);
+
+        // The dead candidate was ranked last and is never offered
+        assert_eq!(session.current, Some(0));
+        assert_eq!(session.remaining(), [0, 1, 2]);
+        session.mark_failed(None);
+        assert_eq!(session.current, None);
+        assert_eq!(session.remaining(), [1, 2]);
+        session.current = Some(1);
+        session.mark_failed(Some("https://s3/video.mp4"));
+        assert_eq!(session.current, Some(1));
+        assert_eq!(session.remaining(), [1]);
+
+        // A proxied URL matches no candidate and fails the one playing
+        session.mark_failed(Some("http://127.0.0.1:64622/stream/tok/video.mp4"));
+        assert_eq!(session.current, None);
+        assert_eq!(session.remaining(), [] as [usize; 0]);
+        session.mark_failed(Some("https://unknown/video.mp4"));
+        assert_eq!(session.remaining(), [] as [usize; 0]);
+    }
+
+    #[test]
+    fn test_probe_hls_with_mock_server() {
+        let master = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",LANGUAGE=\"jpn\",NAME=\"Japanese\",DEFAULT=YES,URI=\"audio/ja.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,AUDIO=\"aud\"\n360/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,RESOLUTION=1280x720,AUDIO=\"aud\"\n720/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,AUDIO=\"aud\"\n1080/index.m3u8\n";
+        let media = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXTINF:4.0,\nseg0.ts\n#EXTINF:4.0,\nseg1.ts\n#EXT-X-ENDLIST\n";
+        let base = serve_routes(HashMap::from([
+            ("/ep1/master.m3u8".to_string(), master.as_bytes().to_vec()),
+            ("/ep1/720/index.m3u8".to_string(), media.as_bytes().to_vec()),
+            ("/ep1/720/seg0.ts".to_string(), vec![0x47; 188 * 4]),
+        ]));
+        let client = reqwest::Client::new();
+
+        let stream = candidate(json!({ "url": format!("{}/ep1/master.m3u8", base), "quality": "auto" }));
+        let probe = tauri::async_runtime::block_on(probe_stream(&client, &stream, Some(720))).unwrap();
+        assert!(probe.alive, "{:?}", probe.error);
+        assert_eq!(probe.height, Some(720));
+        assert_eq!(probe.max_height, Some(1080));
+        assert_eq!(probe.bandwidth, Some(2_800_000));
+        assert_eq!(probe.audio_languages, ["jpn"]);
+        assert!(probe.segment_latency_ms.is_some());
+
+        // The 1080p variant has no playlist on the server
+        let probe = tauri::async_runtime::block_on(probe_stream(&client, &stream, Some(1080))).unwrap();
+        assert!(!probe.alive);
+        assert_eq!(probe.error.as_deref(), Some("HTTP 404"));
+    }
+}
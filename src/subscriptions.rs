@@ -0,0 +1,794 @@
+//! RSS Subscriptions
+//!
+//! This module lets each profile subscribe to an anime, optionally restricted to a
+//! release group and resolution. A background scheduler polls the configured torrent
+//! sources (Nyaa RSS by default), matches new items with the release-name parser,
+//! queues the downloads and sends a native notification.
+//!
+//! Subscriptions, the scheduler configuration and the infohashes that were already
+//! handled are kept in `subscriptions.json`, so nothing is downloaded twice across
+//! restarts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_store::StoreExt;
+
+use crate::downloads::{self, DownloadSource};
+use crate::profiles::{self, ProfileState};
+use crate::torrent_search::{self, SearchPreferences, TorrentResult, TorrentSearchState};
+
+/// Store file name for subscriptions persistence
+const SUBSCRIPTIONS_STORE_FILE: &str = "subscriptions.json";
+
+/// Store key for the subscription list
+const SUBSCRIPTIONS_KEY: &str = "subscriptions";
+
+/// Store key for the scheduler configuration
+const CONFIG_KEY: &str = "config";
+
+/// Store key for infohashes that were already handled
+const SEEN_KEY: &str = "seen";
+
+/// Maximum number of remembered infohashes
+const MAX_SEEN_ENTRIES: usize = 2000;
+
+/// Shortest allowed polling interval
+const MIN_POLL_INTERVAL_MINUTES: u32 = 5;
+
+/// A subscription to new episodes of an anime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    /// Unique subscription identifier
+    pub id: String,
+    /// Profile that owns the subscription
+    pub profile_id: String,
+    /// Anime title used for searching and matching
+    pub title: String,
+    /// Alternative titles that also match (e.g. romaji/english)
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// AniList ID of the anime (if known)
+    pub anilist_id: Option<u64>,
+    /// Only match this release group (e.g. `SubsPlease`)
+    pub release_group: Option<String>,
+    /// Only match this vertical resolution (e.g. 1080)
+    pub resolution: Option<u32>,
+    /// Only match this season (releases without a season tag also match)
+    pub season: Option<u32>,
+    /// Last episode that was queued
+    pub last_episode: Option<u32>,
+    /// Whether the subscription is polled
+    pub enabled: bool,
+    /// Creation timestamp (Unix milliseconds)
+    pub created_at: i64,
+    /// Last time an episode was queued (Unix milliseconds)
+    pub last_matched_at: Option<i64>,
+}
+
+/// Fields accepted when creating or updating a subscription
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionInput {
+    pub title: Option<String>,
+    pub aliases: Option<Vec<String>>,
+    pub anilist_id: Option<u64>,
+    pub release_group: Option<String>,
+    pub resolution: Option<u32>,
+    pub season: Option<u32>,
+    pub last_episode: Option<u32>,
+}
+
+/// Scheduler configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SubscriptionConfig {
+    /// Whether the background scheduler runs
+    pub enabled: bool,
+    /// Minutes between two polls
+    pub poll_interval_minutes: u32,
+    /// Torrent source IDs that are polled
+    pub sources: Vec<String>,
+    /// Whether to send a native notification for queued episodes
+    pub notify: bool,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        SubscriptionConfig {
+            enabled: true,
+            poll_interval_minutes: 30,
+            sources: vec!["nyaa".to_string()],
+            notify: true,
+        }
+    }
+}
+
+/// An episode queued by a subscription check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionMatch {
+    pub subscription_id: String,
+    pub title: String,
+    pub episode: Option<u32>,
+    pub download_id: String,
+}
+
+/// Subscriptions state (in-memory cache of the store)
+pub struct SubscriptionState {
+    subscriptions: Mutex<Vec<Subscription>>,
+    config: Mutex<SubscriptionConfig>,
+    seen: Mutex<Vec<String>>,
+    loaded: Mutex<bool>,
+    /// Prevents overlapping checks (scheduler tick vs. manual check)
+    checking: Mutex<bool>,
+}
+
+impl Default for SubscriptionState {
+    fn default() -> Self {
+        SubscriptionState {
+            subscriptions: Mutex::new(Vec::new()),
+            config: Mutex::new(SubscriptionConfig::default()),
+            seen: Mutex::new(Vec::new()),
+            loaded: Mutex::new(false),
+            checking: Mutex::new(false),
+        }
+    }
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Generate a unique subscription ID
+fn generate_subscription_id() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("subscription_{}", timestamp % 1_000_000_000_000)
+}
+
+// =============================================================================
+// Matching
+// =============================================================================
+
+/// Normalize a title for comparison: lowercase alphanumerics separated by single spaces
+pub fn normalize_title(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Build the search query sent to the sources for a subscription
+pub fn build_query(subscription: &Subscription) -> String {
+    let mut query = String::new();
+    if let Some(group) = &subscription.release_group {
+        query.push_str(&format!("[{}] ", group));
+    }
+    query.push_str(subscription.title.trim());
+    if let Some(resolution) = subscription.resolution {
+        query.push_str(&format!(" {}p", resolution));
+    }
+    query
+}
+
+/// Check whether a torrent is a new episode for the subscription
+pub fn matches_subscription(subscription: &Subscription, result: &TorrentResult) -> bool {
+    let release = &result.release;
+    if release.is_batch || release.episode_end.is_some() {
+        return false;
+    }
+
+    let title = normalize_title(&release.title);
+    let title_matches = std::iter::once(&subscription.title)
+        .chain(subscription.aliases.iter())
+        .any(|candidate| normalize_title(candidate) == title);
+    if !title_matches {
+        return false;
+    }
+
+    if let Some(group) = &subscription.release_group {
+        let same_group = release
+            .release_group
+            .as_ref()
+            .is_some_and(|g| g.eq_ignore_ascii_case(group));
+        if !same_group {
+            return false;
+        }
+    }
+
+    if subscription.resolution.is_some() && release.resolution != subscription.resolution {
+        return false;
+    }
+
+    if let (Some(wanted), Some(season)) = (subscription.season, release.season) {
+        if wanted != season {
+            return false;
+        }
+    }
+
+    let episode = match release.episode {
+        Some(episode) => episode,
+        None => return false,
+    };
+
+    match subscription.last_episode {
+        Some(last) => episode > last,
+        // Without a known last episode only releases published after subscribing count,
+        // so subscribing to a running show doesn't queue its whole back catalogue
+        None => result
+            .published_at
+            .is_some_and(|published| published * 1000 >= subscription.created_at),
+    }
+}
+
+/// Pick the torrents to queue for a subscription: one per new episode, best ranked
+/// first (`results` must already be ranked)
+pub fn select_new_episodes<'a>(
+    subscription: &Subscription,
+    results: &'a [TorrentResult],
+    seen: &HashSet<String>,
+) -> Vec<&'a TorrentResult> {
+    let mut episodes = HashSet::new();
+    let mut selected: Vec<&TorrentResult> = results
+        .iter()
+        .filter(|r| !seen.contains(&r.infohash))
+        .filter(|r| matches_subscription(subscription, r))
+        .filter(|r| episodes.insert(r.release.episode))
+        .collect();
+    selected.sort_by_key(|r| r.release.episode);
+    selected
+}
+
+// =============================================================================
+// Persistence Functions
+// =============================================================================
+
+/// Load subscriptions, config and seen infohashes from the store once
+fn ensure_subscriptions_loaded(app: &AppHandle, state: &SubscriptionState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(SUBSCRIPTIONS_STORE_FILE) {
+        if let Some(value) = store.get(SUBSCRIPTIONS_KEY) {
+            match serde_json::from_value::<Vec<Subscription>>(value.clone()) {
+                Ok(subscriptions) => *state.subscriptions.lock().unwrap() = subscriptions,
+                Err(e) => log::warn!("Failed to deserialize subscriptions: {}", e),
+            }
+        }
+        if let Some(value) = store.get(CONFIG_KEY) {
+            if let Ok(config) = serde_json::from_value::<SubscriptionConfig>(value.clone()) {
+                *state.config.lock().unwrap() = config;
+            }
+        }
+        if let Some(value) = store.get(SEEN_KEY) {
+            if let Ok(seen) = serde_json::from_value::<Vec<String>>(value.clone()) {
+                *state.seen.lock().unwrap() = seen;
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save the current state to the store
+fn save_subscriptions_to_store(app: &AppHandle, state: &SubscriptionState) -> Result<(), String> {
+    let store = app
+        .store(SUBSCRIPTIONS_STORE_FILE)
+        .map_err(|e| format!("Failed to open subscriptions store: {}", e))?;
+
+    let subscriptions = state
+        .subscriptions
+        .lock()
+        .map_err(|e| format!("Failed to lock subscriptions: {}", e))?;
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock subscription config: {}", e))?;
+    let seen = state
+        .seen
+        .lock()
+        .map_err(|e| format!("Failed to lock seen releases: {}", e))?;
+
+    let subscriptions_value = serde_json::to_value(&*subscriptions)
+        .map_err(|e| format!("Failed to serialize subscriptions: {}", e))?;
+    let config_value = serde_json::to_value(&*config)
+        .map_err(|e| format!("Failed to serialize subscription config: {}", e))?;
+    let seen_value = serde_json::to_value(&*seen)
+        .map_err(|e| format!("Failed to serialize seen releases: {}", e))?;
+
+    store.set(SUBSCRIPTIONS_KEY, subscriptions_value);
+    store.set(CONFIG_KEY, config_value);
+    store.set(SEEN_KEY, seen_value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save subscriptions: {}", e))
+}
+
+// =============================================================================
+// Checking & Scheduling
+// =============================================================================
+
+/// Poll the sources for every enabled subscription and queue new episodes
+pub async fn check_subscriptions(app: &AppHandle) -> Result<Vec<SubscriptionMatch>, String> {
+    let state = app.state::<SubscriptionState>();
+    ensure_subscriptions_loaded(app, &state);
+
+    {
+        let mut checking = state
+            .checking
+            .lock()
+            .map_err(|e| format!("Failed to lock subscriptions: {}", e))?;
+        if *checking {
+            return Err("A subscription check is already running".to_string());
+        }
+        *checking = true;
+    }
+
+    let result = run_check(app, &state).await;
+
+    if let Ok(mut checking) = state.checking.lock() {
+        *checking = false;
+    }
+    result
+}
+
+async fn run_check(
+    app: &AppHandle,
+    state: &SubscriptionState,
+) -> Result<Vec<SubscriptionMatch>, String> {
+    let config = state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock subscription config: {}", e))?
+        .clone();
+    let subscriptions: Vec<Subscription> = state
+        .subscriptions
+        .lock()
+        .map_err(|e| format!("Failed to lock subscriptions: {}", e))?
+        .iter()
+        .filter(|s| s.enabled)
+        .cloned()
+        .collect();
+
+    let search_state = app.state::<TorrentSearchState>();
+    let sources = search_state.sources(Some(&config.sources));
+    if sources.is_empty() {
+        return Err("No matching torrent sources".to_string());
+    }
+
+    let profile_state = app.state::<ProfileState>();
+    let mut matches = Vec::new();
+
+    for subscription in subscriptions {
+        let query = build_query(&subscription);
+        let mut results = Vec::new();
+        for source in &sources {
+            match torrent_search::fetch_source(search_state.client(), source.as_ref(), &query, 1)
+                .await
+            {
+                Ok(found) => results.extend(found),
+                Err(e) => log::warn!("Subscription poll of '{}' failed: {}", source.id(), e),
+            }
+        }
+
+        let mut results = torrent_search::deduplicate(results);
+        let prefs = profiles::resolve_profile_settings(
+            app,
+            &profile_state,
+            Some(&subscription.profile_id),
+        )
+        .map(|settings| SearchPreferences::from_profile(&settings))
+        .unwrap_or_default();
+        torrent_search::rank_results(&mut results, &prefs);
+
+        let seen: HashSet<String> = state
+            .seen
+            .lock()
+            .map_err(|e| format!("Failed to lock seen releases: {}", e))?
+            .iter()
+            .cloned()
+            .collect();
+
+        for result in select_new_episodes(&subscription, &results, &seen) {
+            let job = match downloads::enqueue(
+                app,
+                result.title.clone(),
+                DownloadSource::Torrent {
+                    magnet: result.magnet.clone(),
+                    infohash: result.infohash.clone(),
+                },
+                Some(format!("subscription:{}", subscription.id)),
+            ) {
+                Ok(job) => job,
+                Err(e) => {
+                    log::warn!("Failed to queue '{}': {}", result.title, e);
+                    continue;
+                }
+            };
+
+            if let Ok(mut seen) = state.seen.lock() {
+                seen.push(result.infohash.clone());
+                let overflow = seen.len().saturating_sub(MAX_SEEN_ENTRIES);
+                seen.drain(..overflow);
+            }
+            if let Ok(mut all) = state.subscriptions.lock() {
+                if let Some(s) = all.iter_mut().find(|s| s.id == subscription.id) {
+                    s.last_episode = s.last_episode.max(result.release.episode);
+                    s.last_matched_at = Some(get_current_timestamp());
+                }
+            }
+
+            if config.notify {
+                notify_queued(app, &subscription.title, result.release.episode);
+            }
+
+            matches.push(SubscriptionMatch {
+                subscription_id: subscription.id.clone(),
+                title: result.title.clone(),
+                episode: result.release.episode,
+                download_id: job.id,
+            });
+        }
+    }
+
+    save_subscriptions_to_store(app, state)?;
+
+    if !matches.is_empty() {
+        log::info!("Subscriptions queued {} new episodes", matches.len());
+    }
+    Ok(matches)
+}
+
+/// Send a native notification for a queued episode
+fn notify_queued(app: &AppHandle, title: &str, episode: Option<u32>) {
+    let body = match episode {
+        Some(episode) => format!("Episode {} is downloading", episode),
+        None => "A new episode is downloading".to_string(),
+    };
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Start the background scheduler that polls subscriptions
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let state = app.state::<SubscriptionState>();
+            ensure_subscriptions_loaded(&app, &state);
+            let config = state
+                .config
+                .lock()
+                .map(|c| c.clone())
+                .unwrap_or_default();
+
+            if config.enabled {
+                if let Err(e) = check_subscriptions(&app).await {
+                    log::warn!("Subscription check failed: {}", e);
+                }
+            }
+
+            let minutes = config.poll_interval_minutes.max(MIN_POLL_INTERVAL_MINUTES);
+            tokio::time::sleep(Duration::from_secs(u64::from(minutes) * 60)).await;
+        }
+    });
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// List subscriptions (only those of the given profile, if set)
+#[tauri::command]
+pub fn subscription_list(
+    profile_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, SubscriptionState>,
+) -> Result<Vec<Subscription>, String> {
+    ensure_subscriptions_loaded(&app, &state);
+
+    let subscriptions = state
+        .subscriptions
+        .lock()
+        .map_err(|e| format!("Failed to lock subscriptions: {}", e))?;
+    Ok(subscriptions
+        .iter()
+        .filter(|s| profile_id.as_ref().map_or(true, |id| &s.profile_id == id))
+        .cloned()
+        .collect())
+}
+
+/// Subscribe a profile (the active profile if not given) to an anime
+#[tauri::command]
+pub fn subscription_add(
+    profile_id: Option<String>,
+    input: SubscriptionInput,
+    app: AppHandle,
+    state: State<'_, SubscriptionState>,
+    profile_state: State<'_, ProfileState>,
+) -> Result<Subscription, String> {
+    ensure_subscriptions_loaded(&app, &state);
+
+    let title = input
+        .title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .ok_or("Subscription title cannot be empty")?;
+
+    let profile_id = match profile_id {
+        Some(id) => id,
+        None => profiles::profile_get_active(app.clone(), profile_state)?
+            .map(|p| p.id)
+            .ok_or("No active profile")?,
+    };
+
+    let subscription = Subscription {
+        id: generate_subscription_id(),
+        profile_id,
+        title,
+        aliases: input.aliases.unwrap_or_default(),
+        anilist_id: input.anilist_id,
+        release_group: input.release_group.filter(|g| !g.trim().is_empty()),
+        resolution: input.resolution,
+        season: input.season,
+        last_episode: input.last_episode,
+        enabled: true,
+        created_at: get_current_timestamp(),
+        last_matched_at: None,
+    };
+
+    state
+        .subscriptions
+        .lock()
+        .map_err(|e| format!("Failed to lock subscriptions: {}", e))?
+        .push(subscription.clone());
+    save_subscriptions_to_store(&app, &state)?;
+
+    log::info!("Subscribed to '{}'", subscription.title);
+    Ok(subscription)
+}
+
+/// Update a subscription's matching rules (only the provided fields change)
+#[tauri::command]
+pub fn subscription_update(
+    id: String,
+    input: SubscriptionInput,
+    app: AppHandle,
+    state: State<'_, SubscriptionState>,
+) -> Result<Subscription, String> {
+    ensure_subscriptions_loaded(&app, &state);
+
+    let updated = {
+        let mut subscriptions = state
+            .subscriptions
+            .lock()
+            .map_err(|e| format!("Failed to lock subscriptions: {}", e))?;
+        let subscription = subscriptions
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("Subscription '{}' not found", id))?;
+
+        if let Some(title) = input.title.filter(|t| !t.trim().is_empty()) {
+            subscription.title = title.trim().to_string();
+        }
+        if let Some(aliases) = input.aliases {
+            subscription.aliases = aliases;
+        }
+        if input.anilist_id.is_some() {
+            subscription.anilist_id = input.anilist_id;
+        }
+        if let Some(group) = input.release_group {
+            // An empty group clears the restriction
+            subscription.release_group = Some(group).filter(|g| !g.trim().is_empty());
+        }
+        if input.resolution.is_some() {
+            subscription.resolution = input.resolution;
+        }
+        if input.season.is_some() {
+            subscription.season = input.season;
+        }
+        if input.last_episode.is_some() {
+            subscription.last_episode = input.last_episode;
+        }
+        subscription.clone()
+    };
+
+    save_subscriptions_to_store(&app, &state)?;
+    Ok(updated)
+}
+
+/// Delete a subscription
+#[tauri::command]
+pub fn subscription_remove(
+    id: String,
+    app: AppHandle,
+    state: State<'_, SubscriptionState>,
+) -> Result<(), String> {
+    ensure_subscriptions_loaded(&app, &state);
+
+    {
+        let mut subscriptions = state
+            .subscriptions
+            .lock()
+            .map_err(|e| format!("Failed to lock subscriptions: {}", e))?;
+        let before = subscriptions.len();
+        subscriptions.retain(|s| s.id != id);
+        if subscriptions.len() == before {
+            return Err(format!("Subscription '{}' not found", id));
+        }
+    }
+
+    save_subscriptions_to_store(&app, &state)
+}
+
+/// Pause or resume a subscription
+#[tauri::command]
+pub fn subscription_set_enabled(
+    id: String,
+    enabled: bool,
+    app: AppHandle,
+    state: State<'_, SubscriptionState>,
+) -> Result<Subscription, String> {
+    ensure_subscriptions_loaded(&app, &state);
+
+    let updated = {
+        let mut subscriptions = state
+            .subscriptions
+            .lock()
+            .map_err(|e| format!("Failed to lock subscriptions: {}", e))?;
+        let subscription = subscriptions
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("Subscription '{}' not found", id))?;
+        subscription.enabled = enabled;
+        subscription.clone()
+    };
+
+    save_subscriptions_to_store(&app, &state)?;
+    Ok(updated)
+}
+
+/// Get the scheduler configuration
+#[tauri::command]
+pub fn subscription_get_config(
+    app: AppHandle,
+    state: State<'_, SubscriptionState>,
+) -> Result<SubscriptionConfig, String> {
+    ensure_subscriptions_loaded(&app, &state);
+
+    state
+        .config
+        .lock()
+        .map(|c| c.clone())
+        .map_err(|e| format!("Failed to lock subscription config: {}", e))
+}
+
+/// Update the scheduler configuration (takes effect after the current interval)
+#[tauri::command]
+pub fn subscription_set_config(
+    config: SubscriptionConfig,
+    app: AppHandle,
+    state: State<'_, SubscriptionState>,
+) -> Result<SubscriptionConfig, String> {
+    ensure_subscriptions_loaded(&app, &state);
+
+    let mut config = config;
+    config.poll_interval_minutes = config.poll_interval_minutes.max(MIN_POLL_INTERVAL_MINUTES);
+
+    *state
+        .config
+        .lock()
+        .map_err(|e| format!("Failed to lock subscription config: {}", e))? = config.clone();
+    save_subscriptions_to_store(&app, &state)?;
+    Ok(config)
+}
+
+/// Check all enabled subscriptions now
+#[tauri::command]
+pub async fn subscription_check_now(app: AppHandle) -> Result<Vec<SubscriptionMatch>, String> {
+    check_subscriptions(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::release_parser::parse_release_name;
+
+    fn subscription() -> Subscription {
+        Subscription {
+            id: "subscription_1".to_string(),
+            profile_id: "profile_1".to_string(),
+            title: "Sousou no Frieren".to_string(),
+            aliases: vec!["Frieren: Beyond Journey's End".to_string()],
+            anilist_id: Some(154587),
+            release_group: Some("SubsPlease".to_string()),
+            resolution: Some(1080),
+            season: None,
+            last_episode: Some(11),
+            enabled: true,
+            created_at: 1_700_000_000_000,
+            last_matched_at: None,
+        }
+    }
+
+    fn result(title: &str, infohash: &str, published_at: Option<i64>) -> TorrentResult {
+        TorrentResult {
+            title: title.to_string(),
+            infohash: infohash.to_string(),
+            magnet: format!("magnet:?xt=urn:btih:{}", infohash),
+            torrent_url: None,
+            info_url: None,
+            size_bytes: 0,
+            seeders: 10,
+            leechers: 0,
+            downloads: 0,
+            published_at,
+            sources: vec!["nyaa".to_string()],
+            release: parse_release_name(title),
+            score: 0,
+        }
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(normalize_title("Frieren: Beyond Journey's End"), "frieren beyond journey s end");
+        assert_eq!(normalize_title("  Sousou  no Frieren "), "sousou no frieren");
+    }
+
+    #[test]
+    fn test_build_query() {
+        assert_eq!(build_query(&subscription()), "[SubsPlease] Sousou no Frieren 1080p");
+    }
+
+    #[test]
+    fn test_matches_subscription_rules() {
+        let sub = subscription();
+        assert!(matches_subscription(&sub, &result("[SubsPlease] Sousou no Frieren - 12 (1080p) [ABCD1234].mkv", "a", None)));
+        // Already downloaded episode
+        assert!(!matches_subscription(&sub, &result("[SubsPlease] Sousou no Frieren - 11 (1080p).mkv", "b", None)));
+        // Wrong resolution and wrong group
+        assert!(!matches_subscription(&sub, &result("[SubsPlease] Sousou no Frieren - 12 (720p).mkv", "c", None)));
+        assert!(!matches_subscription(&sub, &result("[Erai-raws] Sousou no Frieren - 12 [1080p].mkv", "d", None)));
+        // Batches never match
+        assert!(!matches_subscription(&sub, &result("[SubsPlease] Sousou no Frieren (01-12) (1080p) [Batch]", "e", None)));
+
+        // Without a last episode only releases published after subscribing match
+        let mut fresh = subscription();
+        fresh.last_episode = None;
+        let title = "[SubsPlease] Sousou no Frieren - 03 (1080p).mkv";
+        assert!(!matches_subscription(&fresh, &result(title, "f", Some(1_600_000_000))));
+        assert!(matches_subscription(&fresh, &result(title, "f", Some(1_700_000_100))));
+    }
+
+    #[test]
+    fn test_select_new_episodes_skips_seen_and_duplicates() {
+        let sub = subscription();
+        let results = vec![
+            result("[SubsPlease] Sousou no Frieren - 13 (1080p).mkv", "h13", None),
+            result("[SubsPlease] Sousou no Frieren - 12 (1080p) [v2].mkv", "h12b", None),
+            result("[SubsPlease] Sousou no Frieren - 12 (1080p).mkv", "h12", None),
+            result("[SubsPlease] Sousou no Frieren - 14 (1080p).mkv", "h14", None),
+        ];
+        let seen: HashSet<String> = ["h14".to_string()].into_iter().collect();
+
+        let selected = select_new_episodes(&sub, &results, &seen);
+        let hashes: Vec<&str> = selected.iter().map(|r| r.infohash.as_str()).collect();
+        assert_eq!(hashes, vec!["h12b", "h13"]);
+    }
+}
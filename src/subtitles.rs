@@ -0,0 +1,1189 @@
+//! Subtitle Extraction
+//!
+//! This module extracts subtitle tracks and attached fonts from MKV files with the
+//! [`matroska`](crate::matroska) demuxer, so subtitles no longer depend on what the
+//! player makes of the raw file.
+//!
+//! A source is either a local path or a URL (typically the torrent backend's
+//! `/streamfile` route). Each opened source gets a session that keeps reading in the
+//! background, so cues of a file that is still downloading show up as they arrive;
+//! `complete` tells whether the whole file was read. ASS/SSA tracks can be exported
+//! as-is (for libass based renderers) or converted to WebVTT.
+//!
+//! Everything is available through commands and on the local media server under
+//! `/subtitles` (which the torrent backend forwards from the backend port). Over
+//! HTTP only streams of the torrent backend, and sources the app already opened
+//! through the commands, are accepted, so websites can't make it read arbitrary
+//! files or URLs.
+
+use hyper::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_http::reqwest;
+
+use crate::matroska::{Attachment, MatroskaEvent, MatroskaParser, TrackInfo, TrackKind};
+use crate::media_probe::MediaSource;
+use crate::media_server::{self, HttpResponse};
+
+/// Event emitted when a session read new cues or finished
+pub const SUBTITLES_PROGRESS_EVENT: &str = "subtitles-progress";
+
+/// Size of a single read from a local file
+const READ_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Maximum number of open sessions (least recently used ones are closed first)
+const MAX_SESSIONS: usize = 8;
+
+/// How long to wait for the track list of a source
+const TRACKS_TIMEOUT_SECS: u64 = 30;
+
+/// Display time for cues without a duration
+const DEFAULT_CUE_DURATION_MS: i64 = 5000;
+
+/// Default `[Events]` format of ASS scripts
+const ASS_EVENT_FORMAT: &str =
+    "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+/// Subtitle codecs that can be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleCodec {
+    Ass,
+    Ssa,
+    Srt,
+    Vtt,
+}
+
+impl SubtitleCodec {
+    /// Map a Matroska codec ID (image based subtitles such as PGS aren't supported)
+    pub fn from_codec_id(codec_id: &str) -> Option<Self> {
+        match codec_id {
+            "S_TEXT/ASS" | "S_ASS" => Some(SubtitleCodec::Ass),
+            "S_TEXT/SSA" | "S_SSA" => Some(SubtitleCodec::Ssa),
+            "S_TEXT/UTF8" | "S_TEXT/ASCII" => Some(SubtitleCodec::Srt),
+            "S_TEXT/WEBVTT" => Some(SubtitleCodec::Vtt),
+            _ => None,
+        }
+    }
+
+    fn is_ass(self) -> bool {
+        matches!(self, SubtitleCodec::Ass | SubtitleCodec::Ssa)
+    }
+}
+
+/// Output format of an extracted track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    /// Full ASS script (ASS/SSA tracks only)
+    Ass,
+    /// WebVTT
+    Vtt,
+}
+
+/// A subtitle cue as stored in the file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleCue {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// Block payload (for ASS: `ReadOrder,Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text`)
+    pub text: String,
+}
+
+/// Extraction status of a subtitle track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrackStatus {
+    pub number: u64,
+    pub codec: Option<SubtitleCodec>,
+    pub name: Option<String>,
+    pub language: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+    pub cue_count: usize,
+}
+
+/// State of an opened source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleSessionInfo {
+    pub source: MediaSource,
+    /// All tracks of the file
+    pub tracks: Vec<TrackInfo>,
+    /// Subtitle tracks with the number of cues read so far
+    pub subtitles: Vec<SubtitleTrackStatus>,
+    pub attachments: Vec<Attachment>,
+    pub bytes_read: u64,
+    /// Whether the whole file was read
+    pub complete: bool,
+    pub error: Option<String>,
+}
+
+/// Rendered subtitle track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrackContent {
+    pub track: u64,
+    pub format: SubtitleFormat,
+    pub content: String,
+    pub cue_count: usize,
+    /// Whether more cues may still arrive
+    pub complete: bool,
+}
+
+/// A font written to the cache folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedFont {
+    pub name: String,
+    pub mime_type: String,
+    pub path: String,
+    pub size: u64,
+}
+
+/// An open source that is read in the background
+struct SubtitleSession {
+    source: MediaSource,
+    tracks: Vec<TrackInfo>,
+    cues: HashMap<u64, Vec<SubtitleCue>>,
+    attachments: Vec<Attachment>,
+    bytes_read: u64,
+    tracks_known: bool,
+    complete: bool,
+    error: Option<String>,
+    cancelled: bool,
+    last_access: i64,
+}
+
+impl SubtitleSession {
+    fn new(source: MediaSource) -> Self {
+        SubtitleSession {
+            source,
+            tracks: Vec::new(),
+            cues: HashMap::new(),
+            attachments: Vec::new(),
+            bytes_read: 0,
+            tracks_known: false,
+            complete: false,
+            error: None,
+            cancelled: false,
+            last_access: get_current_timestamp(),
+        }
+    }
+
+    fn info(&self) -> SubtitleSessionInfo {
+        let subtitles = self
+            .tracks
+            .iter()
+            .filter(|t| t.kind == TrackKind::Subtitle)
+            .map(|t| SubtitleTrackStatus {
+                number: t.number,
+                codec: SubtitleCodec::from_codec_id(&t.codec_id),
+                name: t.name.clone(),
+                language: t.language.clone(),
+                default: t.default,
+                forced: t.forced,
+                cue_count: self.cues.get(&t.number).map_or(0, |c| c.len()),
+            })
+            .collect();
+
+        SubtitleSessionInfo {
+            source: self.source.clone(),
+            tracks: self.tracks.clone(),
+            subtitles,
+            attachments: self.attachments.clone(),
+            bytes_read: self.bytes_read,
+            complete: self.complete,
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// Subtitle extraction state: open sessions by source
+pub struct SubtitleState {
+    sessions: Mutex<HashMap<String, SubtitleSession>>,
+}
+
+impl Default for SubtitleState {
+    fn default() -> Self {
+        SubtitleState {
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Progress payload of [`SUBTITLES_PROGRESS_EVENT`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SubtitleProgress<'a> {
+    source: &'a str,
+    cue_count: usize,
+    bytes_read: u64,
+    complete: bool,
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// ASS / WebVTT Conversion
+// =============================================================================
+
+/// Format milliseconds as an ASS timestamp (`H:MM:SS.cc`)
+pub fn format_ass_time(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000 / 10
+    )
+}
+
+/// Parse an ASS timestamp (`H:MM:SS.cc`) into milliseconds
+pub fn parse_ass_time(time: &str) -> Option<i64> {
+    let mut parts = time.trim().split(':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let seconds_part = parts.next()?;
+    let (seconds, fraction) = seconds_part.split_once('.').unwrap_or((seconds_part, "0"));
+    let seconds: i64 = seconds.parse().ok()?;
+    // Centiseconds normally, but accept any precision
+    let fraction_ms = format!("{:0<3}", fraction).get(..3)?.parse::<i64>().ok()?;
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + fraction_ms)
+}
+
+/// Format milliseconds as a WebVTT timestamp (`HH:MM:SS.mmm`)
+pub fn format_vtt_time(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Rebuild a complete ASS script from a track's header and its Matroska cues
+pub fn build_ass_script(header: &str, cues: &[SubtitleCue]) -> String {
+    let mut script = header.trim_end().to_string();
+    if !script.to_lowercase().contains("[events]") {
+        script.push_str("\n\n[Events]\n");
+        script.push_str(ASS_EVENT_FORMAT);
+    }
+    script.push('\n');
+
+    // Dialogue order matters for collisions, Matroska stores it as ReadOrder
+    let mut ordered: Vec<(i64, &SubtitleCue, &str)> = cues
+        .iter()
+        .map(|cue| match cue.text.split_once(',') {
+            Some((order, rest)) => (order.trim().parse().unwrap_or(i64::MAX), cue, rest),
+            None => (i64::MAX, cue, cue.text.as_str()),
+        })
+        .collect();
+    ordered.sort_by_key(|(order, cue, _)| (*order, cue.start_ms));
+
+    for (_, cue, rest) in ordered {
+        // rest = Layer,Style,Name,MarginL,MarginR,MarginV,Effect,Text
+        let (layer, fields) = rest.split_once(',').unwrap_or(("0", rest));
+        script.push_str(&format!(
+            "Dialogue: {},{},{},{}\n",
+            layer,
+            format_ass_time(cue.start_ms),
+            format_ass_time(cue.end_ms),
+            fields
+        ));
+    }
+    script
+}
+
+/// Open formatting tags, closed and reopened as needed to keep WebVTT nesting valid
+struct TagStack {
+    open: Vec<char>,
+}
+
+impl TagStack {
+    fn set(&mut self, out: &mut String, tag: char, on: bool) {
+        let is_open = self.open.contains(&tag);
+        if on && !is_open {
+            out.push_str(&format!("<{}>", tag));
+            self.open.push(tag);
+        } else if !on && is_open {
+            // Close down to the tag, then reopen the ones above it
+            let mut reopen = Vec::new();
+            while let Some(top) = self.open.pop() {
+                out.push_str(&format!("</{}>", top));
+                if top == tag {
+                    break;
+                }
+                reopen.push(top);
+            }
+            for t in reopen.into_iter().rev() {
+                out.push_str(&format!("<{}>", t));
+                self.open.push(t);
+            }
+        }
+    }
+
+    fn close_all(&mut self, out: &mut String) {
+        while let Some(top) = self.open.pop() {
+            out.push_str(&format!("</{}>", top));
+        }
+    }
+}
+
+/// Whether an override tag is `<name><digits>` (so `\i1` matches but `\iclip` doesn't)
+fn toggle_value(tag: &str, name: char) -> Option<u32> {
+    let rest = tag.strip_prefix(name)?;
+    if rest.is_empty() {
+        return Some(1);
+    }
+    if rest.chars().all(|c| c.is_ascii_digit()) {
+        rest.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Convert a legacy SSA alignment (`\a`) to numpad alignment (`\an`)
+fn legacy_alignment(value: u8) -> u8 {
+    match value {
+        5..=7 => value + 2,
+        9..=11 => value - 5,
+        other => other,
+    }
+}
+
+/// Convert ASS dialogue text to WebVTT cue text; returns the text and an alignment
+/// override. Drawings return `None`.
+pub fn convert_ass_text(text: &str) -> Option<(String, Option<u8>)> {
+    let mut out = String::new();
+    let mut tags = TagStack { open: Vec::new() };
+    let mut alignment = None;
+    let mut drawing = false;
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '{' => {
+                let close = match chars[i..].iter().position(|c| *c == '}') {
+                    Some(offset) => i + offset,
+                    None => {
+                        out.push('{');
+                        i += 1;
+                        continue;
+                    }
+                };
+                let block: String = chars[i + 1..close].iter().collect();
+                for tag in block.split('\\').map(str::trim).filter(|t| !t.is_empty()) {
+                    if let Some(value) = tag.strip_prefix("an").and_then(|v| v.parse().ok()) {
+                        alignment = Some(value);
+                    } else if let Some(value) = tag
+                        .strip_prefix('a')
+                        .filter(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()))
+                        .and_then(|v| v.parse().ok())
+                    {
+                        alignment = Some(legacy_alignment(value));
+                    } else if let Some(level) = toggle_value(tag, 'p') {
+                        drawing = level > 0;
+                    } else if let Some(value) = toggle_value(tag, 'i') {
+                        tags.set(&mut out, 'i', value != 0);
+                    } else if let Some(value) = toggle_value(tag, 'u') {
+                        tags.set(&mut out, 'u', value != 0);
+                    } else if let Some(value) = toggle_value(tag, 'b') {
+                        // \b1 or a font weight (\b700)
+                        tags.set(&mut out, 'b', value == 1 || value >= 600);
+                    } else if tag == "r" || tag.starts_with('r') && !tag.starts_with("rnd") {
+                        tags.close_all(&mut out);
+                    }
+                }
+                i = close + 1;
+            }
+            '\\' if i + 1 < chars.len() && matches!(chars[i + 1], 'N' | 'n' | 'h') => {
+                out.push(match chars[i + 1] {
+                    'N' => '\n',
+                    'h' => '\u{a0}',
+                    _ => ' ',
+                });
+                i += 2;
+            }
+            _ if drawing => i += 1,
+            '&' => {
+                out.push_str("&amp;");
+                i += 1;
+            }
+            '<' => {
+                out.push_str("&lt;");
+                i += 1;
+            }
+            '>' => {
+                out.push_str("&gt;");
+                i += 1;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    tags.close_all(&mut out);
+
+    let text = clean_cue_text(&out);
+    if text.is_empty() || text.replace(['<', '>', '/', 'i', 'b', 'u'], "").trim().is_empty() {
+        return None;
+    }
+    Some((text, alignment))
+}
+
+/// Trim lines and drop empty ones (a blank line would end a WebVTT cue)
+fn clean_cue_text(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// WebVTT cue settings for a numpad alignment
+fn vtt_settings(alignment: u8) -> String {
+    let mut settings = Vec::new();
+    match alignment {
+        7..=9 => settings.push("line:0"),
+        4..=6 => settings.push("line:50%"),
+        _ => {}
+    }
+    match alignment {
+        1 | 4 | 7 => settings.push("align:left"),
+        3 | 6 | 9 => settings.push("align:right"),
+        _ => {}
+    }
+    if settings.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", settings.join(" "))
+    }
+}
+
+/// Convert a complete ASS/SSA script to WebVTT
+pub fn ass_to_vtt(script: &str) -> String {
+    let mut section = String::new();
+    let mut legacy_styles = false;
+    let mut style_format: Vec<String> = Vec::new();
+    let mut style_alignment: HashMap<String, u8> = HashMap::new();
+    let mut event_format: Vec<String> = ASS_EVENT_FORMAT["Format:".len()..]
+        .split(',')
+        .map(|f| f.trim().to_lowercase())
+        .collect();
+    let mut cues: Vec<(i64, i64, String)> = Vec::new();
+
+    for line in script.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.starts_with('[') {
+            section = line.to_lowercase();
+            legacy_styles = section == "[v4 styles]";
+            continue;
+        }
+        let (kind, value) = match line.split_once(':') {
+            Some((kind, value)) => (kind.trim().to_lowercase(), value.trim()),
+            None => continue,
+        };
+
+        if section.ends_with("styles]") {
+            if kind == "format" {
+                style_format = value.split(',').map(|f| f.trim().to_lowercase()).collect();
+            } else if kind == "style" && !style_format.is_empty() {
+                let fields: Vec<&str> = value.splitn(style_format.len(), ',').collect();
+                let field = |name: &str| {
+                    style_format
+                        .iter()
+                        .position(|f| f == name)
+                        .and_then(|i| fields.get(i))
+                        .map(|v| v.trim())
+                };
+                if let (Some(name), Some(alignment)) =
+                    (field("name"), field("alignment").and_then(|a| a.parse::<u8>().ok()))
+                {
+                    let alignment =
+                        if legacy_styles { legacy_alignment(alignment) } else { alignment };
+                    style_alignment.insert(name.to_lowercase(), alignment);
+                }
+            }
+        } else if section == "[events]" {
+            if kind == "format" {
+                event_format = value.split(',').map(|f| f.trim().to_lowercase()).collect();
+            } else if kind == "dialogue" {
+                let fields: Vec<&str> = value.splitn(event_format.len(), ',').collect();
+                let field = |name: &str| {
+                    event_format
+                        .iter()
+                        .position(|f| f == name)
+                        .and_then(|i| fields.get(i))
+                        .copied()
+                };
+                let (start, end, text) = match (
+                    field("start").and_then(parse_ass_time),
+                    field("end").and_then(parse_ass_time),
+                    field("text"),
+                ) {
+                    (Some(start), Some(end), Some(text)) if end > start => (start, end, text),
+                    _ => continue,
+                };
+                if let Some((text, alignment)) = convert_ass_text(text) {
+                    let style = field("style")
+                        .map(|s| s.trim().trim_start_matches('*').to_lowercase())
+                        .unwrap_or_default();
+                    let alignment = alignment
+                        .or_else(|| style_alignment.get(&style).copied())
+                        .unwrap_or(2);
+                    cues.push((start, end, format!("{}\n{}", vtt_settings(alignment), text)));
+                }
+            }
+        }
+    }
+
+    cues.sort_by_key(|(start, end, _)| (*start, *end));
+    let mut vtt = String::from("WEBVTT\n\n");
+    for (start, end, body) in cues {
+        let (settings, text) = body.split_once('\n').unwrap_or(("", &body));
+        vtt.push_str(&format!(
+            "{} --> {}{}\n{}\n\n",
+            format_vtt_time(start),
+            format_vtt_time(end),
+            settings,
+            text
+        ));
+    }
+    vtt
+}
+
+/// Convert plain-text (SRT/WebVTT) cues to WebVTT
+fn plain_cues_to_vtt(cues: &[SubtitleCue]) -> String {
+    let mut ordered: Vec<&SubtitleCue> = cues.iter().collect();
+    ordered.sort_by_key(|cue| (cue.start_ms, cue.end_ms));
+
+    let mut vtt = String::from("WEBVTT\n\n");
+    for cue in ordered {
+        let text = clean_cue_text(&cue.text.replace("-->", "->"));
+        if text.is_empty() {
+            continue;
+        }
+        vtt.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_vtt_time(cue.start_ms),
+            format_vtt_time(cue.end_ms),
+            text
+        ));
+    }
+    vtt
+}
+
+/// Render a track's cues in the requested format
+pub fn render_track(
+    track: &TrackInfo,
+    cues: &[SubtitleCue],
+    format: SubtitleFormat,
+) -> Result<String, String> {
+    let codec = SubtitleCodec::from_codec_id(&track.codec_id)
+        .ok_or_else(|| format!("Subtitle codec '{}' is not supported", track.codec_id))?;
+
+    if codec.is_ass() {
+        let header = track
+            .codec_private
+            .as_deref()
+            .map(|p| String::from_utf8_lossy(p).to_string())
+            .unwrap_or_default();
+        let script = build_ass_script(&header, cues);
+        return Ok(match format {
+            SubtitleFormat::Ass => script,
+            SubtitleFormat::Vtt => ass_to_vtt(&script),
+        });
+    }
+
+    match format {
+        SubtitleFormat::Vtt => Ok(plain_cues_to_vtt(cues)),
+        SubtitleFormat::Ass => Err("Only ASS/SSA tracks can be exported as ASS".to_string()),
+    }
+}
+
+// =============================================================================
+// Sessions
+// =============================================================================
+
+/// Apply parser events to a session; returns `false` once the session was closed
+fn apply_events(app: &AppHandle, key: &str, events: Vec<MatroskaEvent>, read: u64) -> bool {
+    let state = app.state::<SubtitleState>();
+    let mut sessions = match state.sessions.lock() {
+        Ok(sessions) => sessions,
+        Err(_) => return false,
+    };
+    let session = match sessions.get_mut(key) {
+        Some(session) if !session.cancelled => session,
+        _ => return false,
+    };
+
+    session.bytes_read += read;
+    let mut new_cues = 0;
+    for event in events {
+        match event {
+            MatroskaEvent::Tracks(tracks) => {
+                session.tracks = tracks;
+                session.tracks_known = true;
+            }
+            MatroskaEvent::Attachments(attachments) => session.attachments.extend(attachments),
+            MatroskaEvent::Block(block) => {
+                let text = String::from_utf8_lossy(&block.data).trim_end_matches('\0').to_string();
+                let duration = block.duration_ms.unwrap_or(DEFAULT_CUE_DURATION_MS);
+                session.cues.entry(block.track).or_default().push(SubtitleCue {
+                    start_ms: block.timestamp_ms,
+                    end_ms: block.timestamp_ms + duration,
+                    text,
+                });
+                new_cues += 1;
+            }
+            // The first cluster means no track list is coming anymore
+            MatroskaEvent::Cluster(_) => session.tracks_known = true,
+            _ => {}
+        }
+    }
+
+    if new_cues > 0 {
+        let cue_count = session.cues.values().map(Vec::len).sum();
+        let _ = app.emit(
+            SUBTITLES_PROGRESS_EVENT,
+            SubtitleProgress { source: key, cue_count, bytes_read: session.bytes_read, complete: false },
+        );
+    }
+    true
+}
+
+/// Mark a session as finished
+fn finish_session(app: &AppHandle, key: &str, result: Result<(), String>) {
+    let state = app.state::<SubtitleState>();
+    let mut sessions = match state.sessions.lock() {
+        Ok(sessions) => sessions,
+        Err(_) => return,
+    };
+    if let Some(session) = sessions.get_mut(key) {
+        session.tracks_known = true;
+        match result {
+            Ok(()) => session.complete = true,
+            Err(e) => {
+                log::warn!("Subtitle extraction of '{}' failed: {}", key, e);
+                session.error = Some(e);
+            }
+        }
+        let cue_count = session.cues.values().map(Vec::len).sum();
+        let _ = app.emit(
+            SUBTITLES_PROGRESS_EVENT,
+            SubtitleProgress {
+                source: key,
+                cue_count,
+                bytes_read: session.bytes_read,
+                complete: session.complete,
+            },
+        );
+    }
+}
+
+/// Read a local file through the demuxer
+fn read_file(app: &AppHandle, key: &str, path: &str) -> Result<(), String> {
+    let mut file =
+        std::fs::File::open(path).map_err(|e| format!("Failed to open '{}': {}", path, e))?;
+    let mut parser = MatroskaParser::new();
+    let mut buf = vec![0u8; READ_CHUNK_SIZE];
+
+    loop {
+        let n = file
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+        if n == 0 {
+            return Ok(());
+        }
+        let events = parser.push(&buf[..n]).map_err(|e| e.to_string())?;
+        if !apply_events(app, key, events, n as u64) {
+            return Ok(());
+        }
+    }
+}
+
+/// Read a (possibly still downloading) stream through the demuxer
+async fn read_url(app: &AppHandle, key: &str, url: &str) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .user_agent(crate::torrent_search::HTTP_USER_AGENT)
+        .connect_timeout(Duration::from_secs(15))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to open stream: {}", e))?;
+
+    let mut parser = MatroskaParser::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Stream interrupted: {}", e))?
+    {
+        let events = parser.push(&chunk).map_err(|e| e.to_string())?;
+        if !apply_events(app, key, events, chunk.len() as u64) {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Open a session for a source (reusing a running one) and wait for its track list
+pub async fn open_session(app: &AppHandle, source: MediaSource) -> Result<SubtitleSessionInfo, String> {
+    let key = source.key().to_string();
+    let state = app.state::<SubtitleState>();
+
+    let is_new = {
+        let mut sessions = state
+            .sessions
+            .lock()
+            .map_err(|e| format!("Failed to lock subtitle sessions: {}", e))?;
+        if let Some(session) = sessions.get_mut(&key) {
+            session.last_access = get_current_timestamp();
+            // Retry sources that failed (e.g. the backend wasn't ready yet)
+            if session.error.is_some() {
+                sessions.remove(&key);
+            }
+        }
+
+        if sessions.contains_key(&key) {
+            false
+        } else {
+            if sessions.len() >= MAX_SESSIONS {
+                let oldest = sessions
+                    .iter()
+                    .min_by_key(|(_, s)| s.last_access)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    sessions.remove(&oldest);
+                }
+            }
+            sessions.insert(key.clone(), SubtitleSession::new(source.clone()));
+            true
+        }
+    };
+
+    if is_new {
+        let handle = app.clone();
+        let reader_key = key.clone();
+        match source {
+            MediaSource::File { path } => {
+                tauri::async_runtime::spawn_blocking(move || {
+                    let result = read_file(&handle, &reader_key, &path);
+                    finish_session(&handle, &reader_key, result);
+                });
+            }
+            MediaSource::Url { url } => {
+                tauri::async_runtime::spawn(async move {
+                    let result = read_url(&handle, &reader_key, &url).await;
+                    finish_session(&handle, &reader_key, result);
+                });
+            }
+        }
+    }
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(TRACKS_TIMEOUT_SECS);
+    loop {
+        {
+            let sessions = state
+                .sessions
+                .lock()
+                .map_err(|e| format!("Failed to lock subtitle sessions: {}", e))?;
+            let session = sessions
+                .get(&key)
+                .ok_or_else(|| "Subtitle session was closed".to_string())?;
+            if let Some(error) = &session.error {
+                if session.tracks.is_empty() {
+                    return Err(error.clone());
+                }
+            }
+            if session.tracks_known {
+                return Ok(session.info());
+            }
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err("Timed out waiting for the track list".to_string());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// Render a subtitle track of an open source
+pub async fn get_track(
+    app: &AppHandle,
+    source: MediaSource,
+    track: u64,
+    format: SubtitleFormat,
+) -> Result<SubtitleTrackContent, String> {
+    open_session(app, source.clone()).await?;
+
+    let state = app.state::<SubtitleState>();
+    let sessions = state
+        .sessions
+        .lock()
+        .map_err(|e| format!("Failed to lock subtitle sessions: {}", e))?;
+    let session = sessions
+        .get(source.key())
+        .ok_or_else(|| "Subtitle session was closed".to_string())?;
+    let info = session
+        .tracks
+        .iter()
+        .find(|t| t.number == track && t.kind == TrackKind::Subtitle)
+        .ok_or_else(|| format!("Subtitle track {} not found", track))?;
+    let cues = session.cues.get(&track).map(Vec::as_slice).unwrap_or(&[]);
+
+    Ok(SubtitleTrackContent {
+        track,
+        format,
+        content: render_track(info, cues, format)?,
+        cue_count: cues.len(),
+        complete: session.complete,
+    })
+}
+
+/// Find an attachment of an open source by file name
+fn find_attachment(app: &AppHandle, source: &MediaSource, name: &str) -> Option<Attachment> {
+    let state = app.state::<SubtitleState>();
+    let sessions = state.sessions.lock().ok()?;
+    sessions
+        .get(source.key())?
+        .attachments
+        .iter()
+        .find(|a| a.name == name)
+        .cloned()
+}
+
+/// Cache folder for the fonts of a source
+fn fonts_folder(app: &AppHandle, source: &MediaSource) -> Result<PathBuf, String> {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    source.key().hash(&mut hasher);
+
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("fonts").join(format!("{:016x}", hasher.finish())))
+        .map_err(|e| format!("Failed to resolve cache folder: {}", e))
+}
+
+/// Keep only the file name part of an attachment name
+fn sanitize_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || ":*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        "font".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Whether `url` is a stream of the torrent backend on `backend_port`
+pub fn is_backend_stream(url: &str, backend_port: u16) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    url.scheme() == "http"
+        && matches!(url.host_str(), Some("127.0.0.1") | Some("localhost"))
+        && url.port() == Some(backend_port)
+        && (url.path().starts_with("/streamfile/") || url.path().starts_with("/stream/"))
+}
+
+/// Whether HTTP clients may open `source`: backend streams, or sources the app
+/// opened through the commands
+fn is_known_source(app: &AppHandle, source: &MediaSource) -> bool {
+    if let MediaSource::Url { url } = source {
+        if is_backend_stream(url, crate::downloads::backend_port(app)) {
+            return true;
+        }
+    }
+    app.state::<SubtitleState>()
+        .sessions
+        .lock()
+        .map(|sessions| sessions.contains_key(source.key()))
+        .unwrap_or(false)
+}
+
+// =============================================================================
+// HTTP Endpoint
+// =============================================================================
+
+/// Handle `/subtitles/...` requests of the local media server:
+///
+/// - `GET /subtitles/tracks?src=` — session info (JSON)
+/// - `GET /subtitles/track/{number}.{vtt|ass}?src=` — rendered track
+/// - `GET /subtitles/fonts/{name}?src=` — attached font
+pub async fn handle_http(
+    app: &AppHandle,
+    method: &Method,
+    path: &str,
+    query: &HashMap<String, String>,
+) -> HttpResponse {
+    if method != Method::GET {
+        return media_server::text_response(405, "Method not allowed");
+    }
+    let source = match query.get("src").map(|s| MediaSource::parse(s)) {
+        Some(Ok(source)) => source,
+        Some(Err(e)) => return media_server::text_response(400, &e),
+        None => return media_server::text_response(400, "Missing src parameter"),
+    };
+    if !is_known_source(app, &source) {
+        return media_server::text_response(403, "Unknown media source");
+    }
+
+    if path == "tracks" {
+        return match open_session(app, source).await {
+            Ok(info) => media_server::json_response(&info),
+            Err(e) => media_server::text_response(502, &e),
+        };
+    }
+
+    if let Some(file) = path.strip_prefix("track/") {
+        let (number, format) = match file.rsplit_once('.') {
+            Some((number, "vtt")) => (number, SubtitleFormat::Vtt),
+            Some((number, "ass")) | Some((number, "ssa")) => (number, SubtitleFormat::Ass),
+            _ => (file, SubtitleFormat::Vtt),
+        };
+        let number = match number.parse::<u64>() {
+            Ok(number) => number,
+            Err(_) => return media_server::text_response(400, "Invalid track number"),
+        };
+        return match get_track(app, source, number, format).await {
+            Ok(track) => {
+                let content_type = match format {
+                    SubtitleFormat::Vtt => "text/vtt; charset=utf-8",
+                    SubtitleFormat::Ass => "text/x-ssa; charset=utf-8",
+                };
+                let mut response =
+                    media_server::bytes_response(200, content_type, track.content.into_bytes());
+                if let Ok(value) = track.complete.to_string().parse() {
+                    response.headers_mut().insert("x-subtitle-complete", value);
+                }
+                response
+            }
+            Err(e) => media_server::text_response(404, &e),
+        };
+    }
+
+    if let Some(name) = path.strip_prefix("fonts/") {
+        if let Err(e) = open_session(app, source.clone()).await {
+            return media_server::text_response(502, &e);
+        }
+        let name = media_server::percent_decode(name);
+        return match find_attachment(app, &source, &name) {
+            Some(font) => {
+                let mime = if font.mime_type.is_empty() {
+                    "application/octet-stream".to_string()
+                } else {
+                    font.mime_type.clone()
+                };
+                media_server::bytes_response(200, &mime, font.data)
+            }
+            None => media_server::text_response(404, "Attachment not found"),
+        };
+    }
+
+    media_server::text_response(404, "Not found")
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Open a path or URL and list its tracks and attachments
+#[tauri::command]
+pub async fn subtitle_open(source: String, app: AppHandle) -> Result<SubtitleSessionInfo, String> {
+    open_session(&app, MediaSource::parse(&source)?).await
+}
+
+/// Get a subtitle track as ASS or WebVTT (WebVTT by default). Call again while
+/// `complete` is false to pick up cues that arrived since.
+#[tauri::command]
+pub async fn subtitle_get_track(
+    source: String,
+    track: u64,
+    format: Option<SubtitleFormat>,
+    app: AppHandle,
+) -> Result<SubtitleTrackContent, String> {
+    get_track(
+        &app,
+        MediaSource::parse(&source)?,
+        track,
+        format.unwrap_or(SubtitleFormat::Vtt),
+    )
+    .await
+}
+
+/// Write the fonts attached to a source into the cache folder
+#[tauri::command]
+pub async fn subtitle_extract_fonts(source: String, app: AppHandle) -> Result<Vec<ExtractedFont>, String> {
+    let source = MediaSource::parse(&source)?;
+    let info = open_session(&app, source.clone()).await?;
+    let folder = fonts_folder(&app, &source)?;
+    std::fs::create_dir_all(&folder).map_err(|e| format!("Failed to create fonts folder: {}", e))?;
+
+    let mut fonts = Vec::new();
+    for attachment in info.attachments.iter().filter(|a| a.is_font()) {
+        let data = match find_attachment(&app, &source, &attachment.name) {
+            Some(found) => found.data,
+            None => continue,
+        };
+        let path = folder.join(sanitize_file_name(&attachment.name));
+        std::fs::write(&path, &data)
+            .map_err(|e| format!("Failed to write font '{}': {}", attachment.name, e))?;
+        fonts.push(ExtractedFont {
+            name: attachment.name.clone(),
+            mime_type: attachment.mime_type.clone(),
+            path: path.to_string_lossy().to_string(),
+            size: attachment.size,
+        });
+    }
+
+    log::info!("Extracted {} fonts from '{}'", fonts.len(), source.key());
+    Ok(fonts)
+}
+
+/// Stop reading a source and drop its cues
+#[tauri::command]
+pub fn subtitle_close(source: String, state: State<'_, SubtitleState>) -> Result<(), String> {
+    let source = MediaSource::parse(&source)?;
+    let mut sessions = state
+        .sessions
+        .lock()
+        .map_err(|e| format!("Failed to lock subtitle sessions: {}", e))?;
+    if let Some(mut session) = sessions.remove(source.key()) {
+        session.cancelled = true;
+    }
+    Ok(())
+}
+
+/// Convert an ASS/SSA script (e.g. an external `.ass` file) to WebVTT
+#[tauri::command]
+pub fn subtitle_convert_ass(content: String) -> String {
+    ass_to_vtt(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::test_support::sample_file;
+
+    fn sample_track_and_cues() -> (TrackInfo, Vec<SubtitleCue>) {
+        let mut parser = MatroskaParser::new();
+        let events = parser.push(&sample_file()).unwrap();
+        let track = parser.tracks()[1].clone();
+        let cues = events
+            .into_iter()
+            .filter_map(|e| match e {
+                MatroskaEvent::Block(block) => Some(SubtitleCue {
+                    start_ms: block.timestamp_ms,
+                    end_ms: block.timestamp_ms + block.duration_ms.unwrap(),
+                    text: String::from_utf8(block.data).unwrap(),
+                }),
+                _ => None,
+            })
+            .collect();
+        (track, cues)
+    }
+
+    #[test]
+    fn test_time_formats() {
+        assert_eq!(format_ass_time(3_723_450), "1:02:03.45");
+        assert_eq!(parse_ass_time("1:02:03.45"), Some(3_723_450));
+        assert_eq!(parse_ass_time("0:00:01.5"), Some(1500));
+        // Timestamps without a fraction keep their seconds
+        assert_eq!(parse_ass_time("0:00:05"), Some(5000));
+        assert_eq!(format_vtt_time(3_723_456), "01:02:03.456");
+    }
+
+    #[test]
+    fn test_convert_ass_text() {
+        assert_eq!(
+            convert_ass_text("{\\i1}Hello{\\i0}\\Nworld & <you>"),
+            Some(("<i>Hello</i>\nworld &amp; &lt;you&gt;".to_string(), None))
+        );
+        assert_eq!(
+            convert_ass_text("{\\an8\\b1\\i1}Sign{\\b0} text"),
+            Some(("<b><i>Sign</i></b><i> text</i>".to_string(), Some(8)))
+        );
+        // Drawings and effect-only lines are dropped
+        assert_eq!(convert_ass_text("{\\p1}m 0 0 l 100 0 100 100{\\p0}"), None);
+        assert_eq!(convert_ass_text("{\\fad(200,200)}"), None);
+        // Tags that merely start with i/b/u aren't formatting
+        assert_eq!(
+            convert_ass_text("{\\iclip(0,0,10,10)\\blur2\\bord3}Plain"),
+            Some(("Plain".to_string(), None))
+        );
+    }
+
+    #[test]
+    fn test_mkv_track_to_ass_and_vtt() {
+        let (track, cues) = sample_track_and_cues();
+
+        let ass = render_track(&track, &cues, SubtitleFormat::Ass).unwrap();
+        assert!(ass.starts_with("[Script Info]"));
+        assert!(ass.contains("Dialogue: 0,0:00:01.50,0:00:03.50,Default,,0,0,0,,{\\i1}Hello{\\i0}\\Nworld\n"));
+
+        let vtt = render_track(&track, &cues, SubtitleFormat::Vtt).unwrap();
+        assert_eq!(
+            vtt,
+            "WEBVTT\n\n00:00:01.500 --> 00:00:03.500\n<i>Hello</i>\nworld\n\n\
+             00:00:04.000 --> 00:00:05.500\nSecond line\n\n"
+        );
+    }
+
+    #[test]
+    fn test_ass_to_vtt_styles_and_format_order() {
+        let script = "\u{feff}[Script Info]\nTitle: Test\n\n\
+            [V4+ Styles]\nFormat: Name, Fontname, Alignment\nStyle: Default,Arial,2\nStyle: Top,Arial,8\n\n\
+            [Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+            Dialogue: 0,0:00:05.00,0:00:06.00,Top,,0,0,0,,Sign, with comma\n\
+            Comment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hidden\n\
+            Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\an7}First\n";
+
+        let vtt = ass_to_vtt(script);
+        assert_eq!(
+            vtt,
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.000 line:0 align:left\nFirst\n\n\
+             00:00:05.000 --> 00:00:06.000 line:0\nSign, with comma\n\n"
+        );
+    }
+
+    #[test]
+    fn test_is_backend_stream() {
+        assert!(is_backend_stream("http://127.0.0.1:64621/streamfile/abc/ep01.mkv", 64621));
+        assert!(is_backend_stream("http://localhost:64621/stream/abc", 64621));
+        assert!(!is_backend_stream("http://127.0.0.1:64622/streamfile/abc/ep01.mkv", 64621));
+        assert!(!is_backend_stream("http://169.254.169.254:64621/streamfile/x", 64621));
+        assert!(!is_backend_stream("http://127.0.0.1:64621/torrents", 64621));
+        assert!(!is_backend_stream("/home/user/.ssh/id_rsa", 64621));
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("../../evil/Font:Bold.ttf"), "Font_Bold.ttf");
+        assert_eq!(sanitize_file_name("C:\\Fonts\\Arial.ttf"), "Arial.ttf");
+        assert_eq!(sanitize_file_name(".."), "font");
+    }
+}
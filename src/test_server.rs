@@ -0,0 +1,106 @@
+//! Test HTTP Server
+//!
+//! A minimal HTTP/1.1 server on a random local port, for tests of code that makes
+//! real requests (update checks, stream probes, scrapers). Every request is answered
+//! by a handler and the connection is closed.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+
+/// A request received by the test server
+pub struct TestRequest {
+    /// Path and query
+    pub path: String,
+    /// Headers by lowercase name
+    pub headers: HashMap<String, String>,
+}
+
+impl TestRequest {
+    /// Value of a header (`name` in lowercase)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+/// A response sent by the test server
+pub struct TestResponse {
+    /// Status line (`200 OK`)
+    pub status: &'static str,
+    /// Headers besides `Content-Length` and `Connection`
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    /// `200 OK` with a body
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        TestResponse {
+            status: "200 OK",
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// An empty response with a status
+    pub fn status(status: &'static str) -> Self {
+        TestResponse {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Add a header
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// Answer requests with `handler` on a local port; returns the base URL
+pub fn serve<F>(handler: F) -> String
+where
+    F: Fn(&TestRequest) -> TestResponse + Send + 'static,
+{
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let mut headers = HashMap::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+                }
+                line.clear();
+            }
+            let request = TestRequest {
+                path: request_line.split_whitespace().nth(1).unwrap_or("/").to_string(),
+                headers,
+            };
+
+            let response = handler(&request);
+            let mut head = format!("HTTP/1.1 {}\r\n", response.status);
+            for (name, value) in &response.headers {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len()));
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&response.body);
+        }
+    });
+    base
+}
+
+/// Serve canned responses by path (404 for any other path); returns the base URL
+pub fn serve_routes(routes: HashMap<String, Vec<u8>>) -> String {
+    serve(move |request| match routes.get(&request.path) {
+        Some(body) => TestResponse::ok(body.clone()),
+        None => TestResponse::status("404 Not Found"),
+    })
+}
@@ -0,0 +1,915 @@
+//! Torrent Source Aggregation
+//!
+//! This module searches torrent indexes (Nyaa RSS and AnimeTosho JSON to start) from
+//! the Rust backend instead of scraping them from the webview through a worker.
+//!
+//! Every source implements [`TorrentSource`], which only builds request URLs and parses
+//! response bodies. Fetching, deduplication by infohash and ranking by profile
+//! preferences are shared, so adding another index only requires a new trait impl.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tauri_plugin_http::reqwest;
+
+use crate::profiles::{self, ProfileSettings, ProfileState};
+use crate::release_parser::{parse_release_name, ParsedRelease};
+
+/// Base URL of the Nyaa RSS feed
+const NYAA_BASE_URL: &str = "https://nyaa.si";
+
+/// Base URL of the AnimeTosho JSON feed
+const ANIMETOSHO_BASE_URL: &str = "https://feed.animetosho.org";
+
+/// User agent sent with every search request
+pub const HTTP_USER_AGENT: &str = concat!("zanshin/", env!("CARGO_PKG_VERSION"));
+
+/// Request timeout for a single source
+const SOURCE_TIMEOUT_SECS: u64 = 15;
+
+/// Public trackers appended to magnets that are built from a bare infohash
+const DEFAULT_TRACKERS: &[&str] = &[
+    "http://nyaa.tracker.wf:7777/announce",
+    "udp://open.stealth.si:80/announce",
+    "udp://tracker.opentrackr.org:1337/announce",
+    "udp://exodus.desync.com:6969/announce",
+];
+
+/// A single torrent search result, normalized across sources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TorrentResult {
+    /// Torrent title as listed by the source
+    pub title: String,
+    /// Lowercase hex BitTorrent v1 infohash
+    pub infohash: String,
+    /// Magnet URI
+    pub magnet: String,
+    /// Direct `.torrent` download URL (if available)
+    pub torrent_url: Option<String>,
+    /// Page describing the torrent on the source site
+    pub info_url: Option<String>,
+    /// Total size in bytes
+    pub size_bytes: u64,
+    /// Number of seeders
+    pub seeders: u32,
+    /// Number of leechers
+    pub leechers: u32,
+    /// Number of completed downloads
+    pub downloads: u32,
+    /// Publish time (Unix seconds)
+    pub published_at: Option<i64>,
+    /// IDs of the sources that returned this torrent
+    pub sources: Vec<String>,
+    /// Release information parsed from the title
+    pub release: ParsedRelease,
+    /// Ranking score (higher is better)
+    pub score: i64,
+}
+
+/// Description of a registered source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TorrentSourceInfo {
+    /// Unique source identifier
+    pub id: String,
+    /// Display name
+    pub name: String,
+}
+
+/// Error reported by a single source; other sources still return results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceError {
+    /// Source identifier
+    pub source: String,
+    /// Error message
+    pub message: String,
+}
+
+/// Aggregated search response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TorrentSearchResponse {
+    /// Deduplicated and ranked results
+    pub results: Vec<TorrentResult>,
+    /// Per-source failures
+    pub errors: Vec<SourceError>,
+}
+
+/// Preferences used to rank results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPreferences {
+    /// Preferred vertical resolution (e.g. 1080)
+    pub preferred_resolution: Option<u32>,
+    /// Release groups to boost, in order of preference
+    #[serde(default)]
+    pub preferred_groups: Vec<String>,
+    /// Whether batches should rank above single episodes
+    #[serde(default)]
+    pub prefer_batches: bool,
+}
+
+impl SearchPreferences {
+    /// Build ranking preferences from profile settings
+    pub fn from_profile(settings: &ProfileSettings) -> Self {
+        SearchPreferences {
+            preferred_resolution: settings
+                .preferred_quality
+                .as_deref()
+                .and_then(parse_quality),
+            preferred_groups: settings
+                .custom
+                .get("preferredReleaseGroups")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default(),
+            prefer_batches: false,
+        }
+    }
+}
+
+/// A searchable torrent index
+pub trait TorrentSource: Send + Sync {
+    /// Unique source identifier
+    fn id(&self) -> &'static str;
+
+    /// Display name
+    fn name(&self) -> &'static str;
+
+    /// Build the request URL for a query and 1-based page
+    fn search_url(&self, query: &str, page: u32) -> String;
+
+    /// Parse a response body into results (ranking fields are filled in later)
+    fn parse_response(&self, body: &str) -> Result<Vec<TorrentResult>, String>;
+}
+
+// =============================================================================
+// Nyaa
+// =============================================================================
+
+/// Nyaa.si RSS feed (anime category)
+pub struct NyaaSource {
+    base_url: String,
+}
+
+impl Default for NyaaSource {
+    fn default() -> Self {
+        NyaaSource { base_url: NYAA_BASE_URL.to_string() }
+    }
+}
+
+impl NyaaSource {
+    /// Create a source pointing at a different Nyaa mirror
+    pub fn with_base_url(base_url: &str) -> Self {
+        NyaaSource { base_url: base_url.trim_end_matches('/').to_string() }
+    }
+}
+
+impl TorrentSource for NyaaSource {
+    fn id(&self) -> &'static str {
+        "nyaa"
+    }
+
+    fn name(&self) -> &'static str {
+        "Nyaa"
+    }
+
+    fn search_url(&self, query: &str, page: u32) -> String {
+        format!(
+            "{}/?page=rss&c=1_0&f=0&q={}&p={}",
+            self.base_url,
+            encode_query(query),
+            page.max(1)
+        )
+    }
+
+    fn parse_response(&self, body: &str) -> Result<Vec<TorrentResult>, String> {
+        if !body.contains("<rss") {
+            return Err("Response is not an RSS feed".to_string());
+        }
+
+        let results = parse_rss_items(body)
+            .into_iter()
+            .filter_map(|item| {
+                let title = item.get("title")?.clone();
+                let infohash = normalize_infohash(item.get("nyaa:infoHash")?)?;
+                Some(TorrentResult {
+                    magnet: build_magnet(&infohash, &title),
+                    torrent_url: item.get("link").cloned(),
+                    info_url: item.get("guid").cloned(),
+                    size_bytes: item.get("nyaa:size").and_then(|s| parse_size(s)).unwrap_or(0),
+                    seeders: parse_count(item.get("nyaa:seeders")),
+                    leechers: parse_count(item.get("nyaa:leechers")),
+                    downloads: parse_count(item.get("nyaa:downloads")),
+                    published_at: item.get("pubDate").and_then(|d| parse_rfc2822(d)),
+                    sources: vec![self.id().to_string()],
+                    release: parse_release_name(&title),
+                    score: 0,
+                    title,
+                    infohash,
+                })
+            })
+            .collect();
+
+        Ok(results)
+    }
+}
+
+// =============================================================================
+// AnimeTosho
+// =============================================================================
+
+/// AnimeTosho JSON feed
+pub struct AnimeToshoSource {
+    base_url: String,
+}
+
+impl Default for AnimeToshoSource {
+    fn default() -> Self {
+        AnimeToshoSource { base_url: ANIMETOSHO_BASE_URL.to_string() }
+    }
+}
+
+impl AnimeToshoSource {
+    /// Create a source pointing at a different AnimeTosho feed host
+    pub fn with_base_url(base_url: &str) -> Self {
+        AnimeToshoSource { base_url: base_url.trim_end_matches('/').to_string() }
+    }
+}
+
+/// Subset of an AnimeTosho feed entry that we use
+#[derive(Debug, Deserialize)]
+struct ToshoEntry {
+    title: String,
+    link: Option<String>,
+    timestamp: Option<i64>,
+    torrent_url: Option<String>,
+    info_hash: Option<String>,
+    magnet_uri: Option<String>,
+    seeders: Option<u32>,
+    leechers: Option<u32>,
+    torrent_download_count: Option<u32>,
+    total_size: Option<u64>,
+}
+
+impl TorrentSource for AnimeToshoSource {
+    fn id(&self) -> &'static str {
+        "animetosho"
+    }
+
+    fn name(&self) -> &'static str {
+        "AnimeTosho"
+    }
+
+    fn search_url(&self, query: &str, page: u32) -> String {
+        format!(
+            "{}/json?qx=1&q={}&page={}",
+            self.base_url,
+            encode_query(query),
+            page.max(1)
+        )
+    }
+
+    fn parse_response(&self, body: &str) -> Result<Vec<TorrentResult>, String> {
+        let entries: Vec<ToshoEntry> = serde_json::from_str(body)
+            .map_err(|e| format!("Failed to parse AnimeTosho response: {}", e))?;
+
+        let results = entries
+            .into_iter()
+            .filter_map(|entry| {
+                let infohash = entry
+                    .info_hash
+                    .as_deref()
+                    .and_then(normalize_infohash)
+                    .or_else(|| entry.magnet_uri.as_deref().and_then(infohash_from_magnet))?;
+                let magnet = entry
+                    .magnet_uri
+                    .unwrap_or_else(|| build_magnet(&infohash, &entry.title));
+                Some(TorrentResult {
+                    magnet,
+                    torrent_url: entry.torrent_url,
+                    info_url: entry.link,
+                    size_bytes: entry.total_size.unwrap_or(0),
+                    seeders: entry.seeders.unwrap_or(0),
+                    leechers: entry.leechers.unwrap_or(0),
+                    downloads: entry.torrent_download_count.unwrap_or(0),
+                    published_at: entry.timestamp,
+                    sources: vec![self.id().to_string()],
+                    release: parse_release_name(&entry.title),
+                    score: 0,
+                    title: entry.title,
+                    infohash,
+                })
+            })
+            .collect();
+
+        Ok(results)
+    }
+}
+
+// =============================================================================
+// Aggregation and Ranking
+// =============================================================================
+
+/// Merge results from several sources, keeping one entry per infohash.
+/// Seeder/leecher counts take the highest reported value.
+pub fn deduplicate(results: Vec<TorrentResult>) -> Vec<TorrentResult> {
+    let mut merged: Vec<TorrentResult> = Vec::with_capacity(results.len());
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for result in results {
+        match index.get(&result.infohash) {
+            Some(&i) => {
+                let existing = &mut merged[i];
+                existing.seeders = existing.seeders.max(result.seeders);
+                existing.leechers = existing.leechers.max(result.leechers);
+                existing.downloads = existing.downloads.max(result.downloads);
+                if existing.size_bytes == 0 {
+                    existing.size_bytes = result.size_bytes;
+                }
+                if existing.torrent_url.is_none() {
+                    existing.torrent_url = result.torrent_url;
+                }
+                if existing.published_at.is_none() {
+                    existing.published_at = result.published_at;
+                }
+                for source in result.sources {
+                    if !existing.sources.contains(&source) {
+                        existing.sources.push(source);
+                    }
+                }
+            }
+            None => {
+                index.insert(result.infohash.clone(), merged.len());
+                merged.push(result);
+            }
+        }
+    }
+
+    merged
+}
+
+/// Score a result against the preferences (higher is better)
+pub fn score_result(result: &TorrentResult, prefs: &SearchPreferences) -> i64 {
+    let mut score: i64 = 0;
+
+    // Seeders matter most for torrent health, with diminishing returns
+    score += ((result.seeders as f64 + 1.0).ln() * 100.0) as i64;
+    if result.seeders == 0 {
+        score -= 1000;
+    }
+
+    if let (Some(wanted), Some(actual)) = (prefs.preferred_resolution, result.release.resolution) {
+        if wanted == actual {
+            score += 500;
+        } else {
+            // Penalize by distance, slightly favouring higher resolutions
+            let diff = (wanted as i64 - actual as i64).abs();
+            score -= if actual > wanted { diff / 4 } else { diff / 2 };
+        }
+    }
+
+    if let Some(group) = result.release.release_group.as_deref() {
+        if let Some(pos) = prefs
+            .preferred_groups
+            .iter()
+            .position(|g| g.eq_ignore_ascii_case(group))
+        {
+            score += 300 - (pos as i64 * 20).min(200);
+        }
+    }
+
+    if result.release.is_batch == prefs.prefer_batches {
+        score += 50;
+    }
+
+    // Re-releases fix problems of the original
+    if result.release.version.unwrap_or(1) > 1 {
+        score += 10;
+    }
+
+    score
+}
+
+/// Score and sort results, best first
+pub fn rank_results(results: &mut [TorrentResult], prefs: &SearchPreferences) {
+    for result in results.iter_mut() {
+        result.score = score_result(result, prefs);
+    }
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.seeders.cmp(&a.seeders))
+            .then_with(|| b.published_at.cmp(&a.published_at))
+    });
+}
+
+/// Fetch and parse one page of results from a single source
+pub async fn fetch_source(
+    client: &reqwest::Client,
+    source: &dyn TorrentSource,
+    query: &str,
+    page: u32,
+) -> Result<Vec<TorrentResult>, String> {
+    let url = source.search_url(query, page);
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Request failed: {}", e))?;
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    source.parse_response(&body)
+}
+
+/// Query the given sources concurrently, then deduplicate and rank the results
+pub async fn search_sources(
+    client: &reqwest::Client,
+    sources: Vec<Arc<dyn TorrentSource>>,
+    query: &str,
+    page: u32,
+    prefs: &SearchPreferences,
+) -> TorrentSearchResponse {
+    let handles: Vec<_> = sources
+        .into_iter()
+        .map(|source| {
+            let client = client.clone();
+            let query = query.to_string();
+            tauri::async_runtime::spawn(async move {
+                let result = fetch_source(&client, source.as_ref(), &query, page).await;
+                (source.id(), result)
+            })
+        })
+        .collect();
+
+    let mut all = Vec::new();
+    let mut errors = Vec::new();
+    for handle in handles {
+        match handle.await {
+            Ok((_, Ok(results))) => all.extend(results),
+            Ok((id, Err(message))) => {
+                log::warn!("Torrent source '{}' failed: {}", id, message);
+                errors.push(SourceError { source: id.to_string(), message });
+            }
+            Err(e) => errors.push(SourceError {
+                source: "unknown".to_string(),
+                message: format!("Search task failed: {}", e),
+            }),
+        }
+    }
+
+    let mut results = deduplicate(all);
+    rank_results(&mut results, prefs);
+    TorrentSearchResponse { results, errors }
+}
+
+/// Torrent search state: registered sources and a shared HTTP client
+pub struct TorrentSearchState {
+    sources: RwLock<Vec<Arc<dyn TorrentSource>>>,
+    client: reqwest::Client,
+}
+
+impl Default for TorrentSearchState {
+    fn default() -> Self {
+        TorrentSearchState {
+            sources: RwLock::new(vec![
+                Arc::new(NyaaSource::default()),
+                Arc::new(AnimeToshoSource::default()),
+            ]),
+            client: reqwest::Client::builder()
+                .user_agent(HTTP_USER_AGENT)
+                .timeout(Duration::from_secs(SOURCE_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl TorrentSearchState {
+    /// Register an additional source (replaces a source with the same ID)
+    pub fn register_source(&self, source: Arc<dyn TorrentSource>) {
+        if let Ok(mut sources) = self.sources.write() {
+            sources.retain(|s| s.id() != source.id());
+            sources.push(source);
+        }
+    }
+
+    /// Get the sources matching the given IDs (all sources if `None`)
+    pub fn sources(&self, ids: Option<&[String]>) -> Vec<Arc<dyn TorrentSource>> {
+        let sources = match self.sources.read() {
+            Ok(sources) => sources,
+            Err(_) => return Vec::new(),
+        };
+        sources
+            .iter()
+            .filter(|s| ids.map_or(true, |ids| ids.iter().any(|id| id == s.id())))
+            .cloned()
+            .collect()
+    }
+
+    /// Shared HTTP client for source requests
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Percent-encode a search query for use in a URL
+pub fn encode_query(query: &str) -> String {
+    let mut encoded = String::with_capacity(query.len());
+    for byte in query.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Parse a quality preference such as `1080p` or `720` into a resolution
+pub fn parse_quality(quality: &str) -> Option<u32> {
+    let digits: String = quality.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok().filter(|r| *r >= 240)
+}
+
+/// Parse the `<item>` elements of an RSS feed into tag → text maps.
+/// Only direct, non-nested child elements are collected, which is all RSS items use.
+pub fn parse_rss_items(xml: &str) -> Vec<HashMap<String, String>> {
+    let mut items = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find("<item>").or_else(|| rest.find("<item ")) {
+        let after = &rest[start..];
+        let Some(end) = after.find("</item>") else { break };
+        // The opening tag must close before the item does; skip broken items
+        if let Some(open_end) = after[..end].find('>') {
+            items.push(parse_rss_fields(&after[open_end + 1..end]));
+        }
+        rest = &after[end + "</item>".len()..];
+    }
+
+    items
+}
+
+/// Collect `<tag>text</tag>` pairs from the body of an RSS item
+fn parse_rss_fields(body: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut rest = body;
+
+    while let Some(lt) = rest.find('<') {
+        rest = &rest[lt + 1..];
+        let Some(gt) = rest.find('>') else { break };
+        let tag_decl = &rest[..gt];
+        if tag_decl.starts_with('/') || tag_decl.starts_with('!') || tag_decl.starts_with('?') {
+            rest = &rest[gt + 1..];
+            continue;
+        }
+        let name = tag_decl.split_whitespace().next().unwrap_or("").to_string();
+        if tag_decl.ends_with('/') {
+            rest = &rest[gt + 1..];
+            continue;
+        }
+        let content_start = &rest[gt + 1..];
+        let close = format!("</{}>", name);
+        let Some(close_pos) = content_start.find(&close) else {
+            rest = content_start;
+            continue;
+        };
+        let raw = &content_start[..close_pos];
+        fields.insert(name, decode_xml_text(raw));
+        rest = &content_start[close_pos + close.len()..];
+    }
+
+    fields
+}
+
+/// Decode CDATA sections and the predefined/numeric XML entities
+fn decode_xml_text(raw: &str) -> String {
+    let trimmed = raw.trim();
+    if let Some(inner) = trimmed
+        .strip_prefix("<![CDATA[")
+        .and_then(|s| s.strip_suffix("]]>"))
+    {
+        return inner.to_string();
+    }
+
+    let mut out = String::with_capacity(trimmed.len());
+    let mut rest = trimmed;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let after = &rest[amp..];
+        let Some(semi) = after.find(';').filter(|&s| s <= 10) else {
+            out.push('&');
+            rest = &after[1..];
+            continue;
+        };
+        let entity = &after[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &after[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &after[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Normalize an infohash to lowercase hex (accepts 40-char hex or 32-char base32)
+pub fn normalize_infohash(hash: &str) -> Option<String> {
+    let hash = hash.trim();
+    if hash.len() == 40 && hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Some(hash.to_ascii_lowercase());
+    }
+    if hash.len() == 32 {
+        let bytes = decode_base32(hash)?;
+        return Some(bytes.iter().map(|b| format!("{:02x}", b)).collect());
+    }
+    None
+}
+
+/// Extract the infohash from a magnet URI
+pub fn infohash_from_magnet(magnet: &str) -> Option<String> {
+    let start = magnet.find("urn:btih:")? + "urn:btih:".len();
+    let hash: String = magnet[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    normalize_infohash(&hash)
+}
+
+/// Build a magnet URI from an infohash and display name
+pub fn build_magnet(infohash: &str, name: &str) -> String {
+    let mut magnet = format!("magnet:?xt=urn:btih:{}&dn={}", infohash, encode_query(name));
+    for tracker in DEFAULT_TRACKERS {
+        magnet.push_str("&tr=");
+        magnet.push_str(&encode_query(tracker));
+    }
+    magnet
+}
+
+/// RFC 4648 base32 decoding (used by older magnet links)
+fn decode_base32(input: &str) -> Option<Vec<u8>> {
+    let mut bits: u64 = 0;
+    let mut bit_count = 0;
+    let mut out = Vec::with_capacity(input.len() * 5 / 8);
+    for c in input.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            b'A'..=b'Z' => c.to_ascii_uppercase() - b'A',
+            b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        bits = (bits << 5) | value as u64;
+        bit_count += 5;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// Parse sizes like `1.4 GiB`, `700 MiB` or `512 KiB` into bytes
+pub fn parse_size(text: &str) -> Option<u64> {
+    let mut parts = text.split_whitespace();
+    let value: f64 = parts.next()?.parse().ok()?;
+    let multiplier: f64 = match parts.next().unwrap_or("B").to_ascii_lowercase().as_str() {
+        "b" | "bytes" => 1.0,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        "kb" => 1e3,
+        "mb" => 1e6,
+        "gb" => 1e9,
+        "tb" => 1e12,
+        _ => return None,
+    };
+    Some((value * multiplier) as u64)
+}
+
+fn parse_count(value: Option<&String>) -> u32 {
+    value.and_then(|v| v.trim().parse().ok()).unwrap_or(0)
+}
+
+/// Parse an RFC 2822 date (`Sat, 18 Oct 2025 12:00:00 -0000`) into Unix seconds
+pub fn parse_rfc2822(date: &str) -> Option<i64> {
+    let mut parts: Vec<&str> = date.split_whitespace().collect();
+    if parts.first().is_some_and(|p| p.ends_with(',')) {
+        parts.remove(0);
+    }
+    if parts.len() < 4 {
+        return None;
+    }
+
+    let day: i64 = parts[0].parse().ok()?;
+    let month = match parts[1].to_ascii_lowercase().as_str() {
+        "jan" => 1, "feb" => 2, "mar" => 3, "apr" => 4, "may" => 5, "jun" => 6,
+        "jul" => 7, "aug" => 8, "sep" => 9, "oct" => 10, "nov" => 11, "dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = parts[2].parse().ok()?;
+
+    let mut time = parts[3].split(':').map(|p| p.parse::<i64>());
+    let hour = time.next()?.ok()?;
+    let minute = time.next()?.ok()?;
+    let second = time.next().and_then(|s| s.ok()).unwrap_or(0);
+
+    let offset_secs = match parts.get(4) {
+        Some(tz) if tz.len() == 5 && (tz.starts_with('+') || tz.starts_with('-')) => {
+            let hours: i64 = tz.get(1..3)?.parse().ok()?;
+            let minutes: i64 = tz.get(3..5)?.parse().ok()?;
+            let sign = if tz.starts_with('-') { -1 } else { 1 };
+            sign * (hours * 3600 + minutes * 60)
+        }
+        _ => 0,
+    };
+
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Absurd years or times overflow; treat them as unparseable
+    let days = days_from_civil(year, month, day)?;
+    days.checked_mul(86_400)?
+        .checked_add(hour.checked_mul(3600)?)?
+        .checked_add(minute.checked_mul(60)?)?
+        .checked_add(second)?
+        .checked_sub(offset_secs)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (`None` on overflow)
+fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    let y = if month <= 2 { year.checked_sub(1)? } else { year };
+    let era = if y >= 0 { y } else { y.checked_sub(399)? } / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era.checked_mul(146_097)?.checked_add(doe - 719_468)
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Search all (or the selected) torrent sources and return ranked, deduplicated results.
+/// Ranking uses the given profile's preferences, or the active profile's.
+#[tauri::command]
+pub async fn torrent_search(
+    query: String,
+    page: Option<u32>,
+    sources: Option<Vec<String>>,
+    profile_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, TorrentSearchState>,
+    profile_state: State<'_, ProfileState>,
+) -> Result<TorrentSearchResponse, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Search query cannot be empty".to_string());
+    }
+
+    let selected = state.sources(sources.as_deref());
+    if selected.is_empty() {
+        return Err("No matching torrent sources".to_string());
+    }
+
+    let prefs = profiles::resolve_profile_settings(&app, &profile_state, profile_id.as_deref())
+        .map(|settings| SearchPreferences::from_profile(&settings))
+        .unwrap_or_default();
+
+    let response = search_sources(state.client(), selected, query, page.unwrap_or(1), &prefs).await;
+
+    log::info!(
+        "Torrent search '{}' returned {} results ({} source errors)",
+        query,
+        response.results.len(),
+        response.errors.len()
+    );
+
+    Ok(response)
+}
+
+/// List the registered torrent sources
+#[tauri::command]
+pub fn torrent_get_sources(state: State<'_, TorrentSearchState>) -> Vec<TorrentSourceInfo> {
+    state
+        .sources(None)
+        .iter()
+        .map(|s| TorrentSourceInfo { id: s.id().to_string(), name: s.name().to_string() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NYAA_FIXTURE: &str = include_str!("../tests/fixtures/nyaa_rss.xml");
+    const TOSHO_FIXTURE: &str = include_str!("../tests/fixtures/animetosho.json");
+
+    #[test]
+    fn test_parse_nyaa_rss() {
+        let results = NyaaSource::default().parse_response(NYAA_FIXTURE).unwrap();
+        assert_eq!(results.len(), 3);
+
+        let first = &results[0];
+        assert_eq!(first.title, "[SubsPlease] Sousou no Frieren - 12 (1080p) [3F2A9C1B].mkv");
+        assert_eq!(first.infohash, "0123456789abcdef0123456789abcdef01234567");
+        assert_eq!(first.seeders, 812);
+        assert_eq!(first.size_bytes, (1.4 * 1024.0 * 1024.0 * 1024.0) as u64);
+        assert_eq!(first.published_at, Some(1_700_000_000));
+        assert_eq!(first.release.episode, Some(12));
+        assert!(first.magnet.starts_with("magnet:?xt=urn:btih:0123456789abcdef"));
+        // Entities are decoded
+        assert!(results[2].title.contains("Kaguya-sama & Friends"));
+    }
+
+    #[test]
+    fn test_parse_broken_rss_items() {
+        // An opening tag that only closes after `</item>` must not panic
+        assert!(parse_rss_items("<rss><item </item> foo>").is_empty());
+        let items = parse_rss_items("<item </item><item><title>Ok</title></item>");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["title"], "Ok");
+    }
+
+    #[test]
+    fn test_parse_animetosho_json() {
+        let results = AnimeToshoSource::default().parse_response(TOSHO_FIXTURE).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].seeders, 900);
+        assert_eq!(results[1].release.resolution, Some(720));
+        // Infohash taken from the base32 magnet when info_hash is missing
+        assert_eq!(results[1].infohash.len(), 40);
+
+        assert!(AnimeToshoSource::default().parse_response("<html>").is_err());
+    }
+
+    #[test]
+    fn test_deduplicate_and_rank() {
+        let mut all = NyaaSource::default().parse_response(NYAA_FIXTURE).unwrap();
+        all.extend(AnimeToshoSource::default().parse_response(TOSHO_FIXTURE).unwrap());
+        let mut merged = deduplicate(all);
+
+        // The Frieren 1080p release is listed by both sources
+        assert_eq!(merged.len(), 4);
+        let shared = merged
+            .iter()
+            .find(|r| r.infohash == "0123456789abcdef0123456789abcdef01234567")
+            .unwrap();
+        assert_eq!(shared.sources, vec!["nyaa", "animetosho"]);
+        assert_eq!(shared.seeders, 900);
+
+        let prefs = SearchPreferences { preferred_resolution: Some(720), ..Default::default() };
+        rank_results(&mut merged, &prefs);
+        assert_eq!(merged[0].release.resolution, Some(720));
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(encode_query("[SubsPlease] Frieren"), "%5BSubsPlease%5D+Frieren");
+        assert_eq!(parse_quality("1080p"), Some(1080));
+        assert_eq!(parse_quality("auto"), None);
+        assert_eq!(parse_size("700 MiB"), Some(700 * 1024 * 1024));
+        assert_eq!(parse_rfc2822("Thu, 01 Jan 1970 01:00:00 +0100"), Some(0));
+        // Multibyte timezone tokens must not panic on a char boundary
+        assert_eq!(parse_rfc2822("Thu, 01 Jan 1970 01:00:00 +1é1"), None);
+        // Absurd years overflow instead of wrapping or panicking
+        assert_eq!(parse_rfc2822("Thu, 01 Jan 9223372036854775807 00:00:00 +0000"), None);
+        assert_eq!(parse_rfc2822("Thu, 01 Jan 1970 9223372036854775807:00:00 +0000"), None);
+        assert_eq!(
+            infohash_from_magnet("magnet:?xt=urn:btih:AAAQEAYEAUDAOCAJBIFQYDIOB4IBCEQT&dn=x"),
+            Some("000102030405060708090a0b0c0d0e0f10111213".to_string())
+        );
+    }
+}
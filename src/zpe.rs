@@ -0,0 +1,920 @@
+//! ZPE Packages
+//!
+//! This module parses `.zpe` plugin packages and verifies them before any plugin code
+//! is handed to the webview. The layout matches `ZPEBuilder.js` and
+//! `scripts/build-plugin.mjs`:
+//!
+//! - a 16-byte header: magic `ZPE!`, format version (u16 LE), flags (u16 LE:
+//!   0x01 encrypted, 0x02 signed), compression type, encryption type, 6 reserved bytes
+//! - typed sections: `[type u8][length u32 LE][data]` (manifest, code, assets,
+//!   metadata and signature)
+//!
+//! Verification checks the ECDSA signature (P-384 over a SHA-256 digest of every byte
+//! before the signature section, which must come last) against the trusted key store,
+//! then the SHA-256 integrity hash of the code recorded in the manifest. Packages
+//! written with the old wrapped header layout are still read.
+
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL};
+use base64::Engine;
+use p384::ecdsa::signature::hazmat::PrehashVerifier;
+use p384::ecdsa::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+/// Magic bytes at the start of every package
+pub const ZPE_MAGIC: [u8; 4] = *b"ZPE!";
+
+/// Supported format version
+pub const ZPE_FORMAT_VERSION: u16 = 1;
+
+/// Size of the header
+pub const ZPE_HEADER_SIZE: usize = 16;
+
+/// Size of a section's type and length fields
+const SECTION_WRAPPER_SIZE: usize = 5;
+
+/// Largest package accepted
+pub const MAX_PACKAGE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Maximum length of a plugin version
+const MAX_VERSION_LEN: usize = 64;
+
+/// The only signature algorithm packages are signed with
+pub const SIGNATURE_ALGORITHM: &str = "ECDSA-P384-SHA256";
+
+/// Store file name for trusted signing keys
+const ZPE_STORE_FILE: &str = "zpe_keys.json";
+
+/// Store key for trusted signing keys
+const TRUSTED_KEYS_KEY: &str = "trustedKeys";
+
+/// Section types
+pub mod section {
+    /// Wrapped header of the legacy layout
+    pub const HEADER: u8 = 0x01;
+    pub const MANIFEST: u8 = 0x02;
+    pub const CODE: u8 = 0x03;
+    pub const ASSETS: u8 = 0x04;
+    pub const SIGNATURE: u8 = 0x05;
+    pub const METADATA: u8 = 0x06;
+}
+
+/// Header flags
+const FLAG_ENCRYPTED: u16 = 0x01;
+const FLAG_SIGNED: u16 = 0x02;
+
+/// Compression types
+const COMPRESSION_NONE: u8 = 0x00;
+
+/// Encryption types
+const ENCRYPTION_NONE: u8 = 0x00;
+const ENCRYPTION_AES_GCM: u8 = 0x01;
+
+/// Why a package was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZpeError {
+    /// The package is larger than [`MAX_PACKAGE_BYTES`]
+    TooLarge(usize),
+    /// The package is shorter than a header
+    TooSmall(usize),
+    /// The package doesn't start with `ZPE!`
+    InvalidMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    UnsupportedCompression(u8),
+    /// Unknown encryption type, or one that doesn't match the encrypted flag
+    UnsupportedEncryption(u8),
+    /// A section extends past the end of the package
+    TruncatedSection { kind: u8, offset: usize },
+    DuplicateSection(u8),
+    MissingSection(&'static str),
+    /// The manifest is not valid JSON or misses required fields
+    InvalidManifest(String),
+    /// The assets or metadata section is not valid JSON
+    InvalidSection { kind: u8, message: String },
+    /// The code is encrypted and can't be loaded
+    EncryptedCode,
+    /// The code is not valid UTF-8
+    InvalidCode,
+    MissingIntegrityHash,
+    IntegrityMismatch { expected: String, actual: String },
+    /// The signed flag and the presence of a signature section disagree
+    SignatureFlagMismatch,
+    /// Data follows the signature section
+    SignatureNotLast,
+    MalformedSignature(String),
+    UnsupportedSignatureAlgorithm(String),
+    /// The signing key is not in the trusted key store
+    UntrustedKey(String),
+    /// The signature doesn't match the package
+    InvalidSignature,
+    /// The package is unsigned but a signature is required
+    SignatureRequired,
+    /// A trusted key is not a valid P-384 public key
+    InvalidKey(String),
+}
+
+impl std::fmt::Display for ZpeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZpeError::TooLarge(size) => write!(f, "Package is too large ({} bytes)", size),
+            ZpeError::TooSmall(size) => write!(f, "Package is too small ({} bytes)", size),
+            ZpeError::InvalidMagic(found) => write!(
+                f,
+                "Not a ZPE package (starts with {:02X} {:02X} {:02X} {:02X}, expected \"ZPE!\")",
+                found[0], found[1], found[2], found[3]
+            ),
+            ZpeError::UnsupportedVersion(version) => write!(f, "Unsupported ZPE format version {}", version),
+            ZpeError::UnsupportedCompression(kind) => write!(f, "Unsupported compression type {}", kind),
+            ZpeError::UnsupportedEncryption(kind) => write!(f, "Unsupported encryption type {}", kind),
+            ZpeError::TruncatedSection { kind, offset } => {
+                write!(f, "Section {:#04x} at offset {} is truncated", kind, offset)
+            }
+            ZpeError::DuplicateSection(kind) => write!(f, "Duplicate section {:#04x}", kind),
+            ZpeError::MissingSection(name) => write!(f, "Missing {} section", name),
+            ZpeError::InvalidManifest(msg) => write!(f, "Invalid manifest: {}", msg),
+            ZpeError::InvalidSection { kind, message } => {
+                write!(f, "Invalid section {:#04x}: {}", kind, message)
+            }
+            ZpeError::EncryptedCode => write!(f, "Plugin code is encrypted"),
+            ZpeError::InvalidCode => write!(f, "Plugin code is not valid UTF-8"),
+            ZpeError::MissingIntegrityHash => write!(f, "Manifest has no integrity hash"),
+            ZpeError::IntegrityMismatch { expected, actual } => {
+                write!(f, "Code integrity check failed (expected {}, got {})", expected, actual)
+            }
+            ZpeError::SignatureFlagMismatch => {
+                write!(f, "Signed flag doesn't match the signature section")
+            }
+            ZpeError::SignatureNotLast => write!(f, "Signature section is not the last section"),
+            ZpeError::MalformedSignature(msg) => write!(f, "Malformed signature: {}", msg),
+            ZpeError::UnsupportedSignatureAlgorithm(algorithm) => {
+                write!(f, "Unsupported signature algorithm '{}'", algorithm)
+            }
+            ZpeError::UntrustedKey(key_id) => write!(f, "Package is signed by untrusted key '{}'", key_id),
+            ZpeError::InvalidSignature => write!(f, "Package signature is invalid"),
+            ZpeError::SignatureRequired => write!(f, "Package is not signed"),
+            ZpeError::InvalidKey(msg) => write!(f, "Invalid public key: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ZpeError {}
+
+/// Parsed package header
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZpeHeader {
+    pub version: u16,
+    pub encrypted: bool,
+    pub signed: bool,
+    pub compression: u8,
+    pub encryption: u8,
+    /// Written with the old layout (header wrapped in a section)
+    pub legacy_layout: bool,
+}
+
+/// Security settings of a manifest
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZpeSecurityInfo {
+    /// `sha256-{base64}` hash of the code, set by the builder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity_hash: Option<String>,
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+/// Plugin manifest (known fields; the rest is kept as is)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZpeManifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_type: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    /// Minimum app version (`x.y.z`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_app_version: Option<String>,
+    #[serde(default)]
+    pub security: ZpeSecurityInfo,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+/// Contents of the signature section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZpeSignature {
+    pub algorithm: String,
+    pub key_id: String,
+    /// Base64 raw `r || s` signature
+    pub signature: String,
+}
+
+/// A parsed (not yet verified) package
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZpePackage {
+    pub header: ZpeHeader,
+    pub manifest: ZpeManifest,
+    /// Code section (ciphertext when the package is encrypted)
+    pub code: Vec<u8>,
+    pub assets: Option<Value>,
+    pub metadata: Option<Value>,
+    pub signature: Option<ZpeSignature>,
+    /// Number of leading bytes covered by the signature
+    pub signed_len: usize,
+}
+
+/// EC public key in JWK format (as exported by WebCrypto and Node)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublicKeyJwk {
+    pub kty: String,
+    pub crv: String,
+    pub x: String,
+    pub y: String,
+}
+
+impl PublicKeyJwk {
+    fn verifying_key(&self) -> Result<VerifyingKey, ZpeError> {
+        if self.kty != "EC" || self.crv != "P-384" {
+            return Err(ZpeError::InvalidKey(format!("expected an EC P-384 key, got {} {}", self.kty, self.crv)));
+        }
+        let decode = |coordinate: &str| {
+            BASE64_URL
+                .decode(coordinate.trim_end_matches('='))
+                .ok()
+                .filter(|bytes| bytes.len() == 48)
+                .ok_or_else(|| ZpeError::InvalidKey("invalid coordinate".to_string()))
+        };
+        let mut point = vec![0x04];
+        point.extend(decode(&self.x)?);
+        point.extend(decode(&self.y)?);
+        VerifyingKey::from_sec1_bytes(&point).map_err(|_| ZpeError::InvalidKey("not a point on P-384".to_string()))
+    }
+
+    /// Check that this is a usable P-384 public key
+    pub fn validate(&self) -> Result<(), ZpeError> {
+        self.verifying_key().map(|_| ())
+    }
+}
+
+/// A signing key whose packages are trusted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedKey {
+    pub key_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub public_key: PublicKeyJwk,
+    /// When the key was trusted (Unix milliseconds)
+    #[serde(default)]
+    pub added_at: i64,
+}
+
+/// How strict verification is; by default only signed packages pass
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    /// Accept unsigned (developer) packages
+    pub allow_unsigned: bool,
+}
+
+/// A package that passed verification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedPackage {
+    pub manifest: ZpeManifest,
+    pub code: String,
+    pub assets: Option<Value>,
+    pub metadata: Option<Value>,
+    /// ID of the trusted key that signed the package
+    pub signed_by: Option<String>,
+    /// SHA-256 of the whole package (hex)
+    pub sha256: String,
+}
+
+/// Package summary for display before installing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZpeInfo {
+    pub header: ZpeHeader,
+    pub manifest: ZpeManifest,
+    pub signature: Option<ZpeSignature>,
+    pub size: usize,
+    pub sha256: String,
+}
+
+/// ZPE state: the trusted key store
+#[derive(Default)]
+pub struct ZpeState {
+    keys: Mutex<Vec<TrustedKey>>,
+    loaded: Mutex<bool>,
+}
+
+/// Get current timestamp in milliseconds
+fn get_current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Parse a `x.y.z` version (pre-release and build suffixes are ignored)
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let parsed = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(parsed)
+}
+
+/// Whether a version is `x.y.z` with optional suffixes, made of `[0-9A-Za-z.+-]` only.
+/// Versions name the folders plugins are installed to, so separators and `..` are
+/// rejected.
+pub fn is_valid_version(version: &str) -> bool {
+    version.len() <= MAX_VERSION_LEN
+        && version.bytes().all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b))
+        && !version.contains("..")
+        && parse_version(version).is_some()
+}
+
+/// Whether a plugin ID is 4-50 lowercase letters, digits, `-` and `_`, starting and ending alphanumeric
+pub fn is_valid_plugin_id(id: &str) -> bool {
+    let id = id.as_bytes();
+    (4..=50).contains(&id.len())
+        && id.iter().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || *b == b'-' || *b == b'_')
+        && id[0].is_ascii_alphanumeric()
+        && id[id.len() - 1].is_ascii_alphanumeric()
+}
+
+/// Check the manifest fields the app relies on
+pub(crate) fn validate_manifest(manifest: &ZpeManifest) -> Result<(), ZpeError> {
+    if !is_valid_plugin_id(&manifest.id) {
+        return Err(ZpeError::InvalidManifest(format!("invalid plugin id '{}'", manifest.id)));
+    }
+    if manifest.name.trim().is_empty() {
+        return Err(ZpeError::InvalidManifest("name is empty".to_string()));
+    }
+    if !is_valid_version(&manifest.version) {
+        return Err(ZpeError::InvalidManifest(format!("invalid version '{}'", manifest.version)));
+    }
+    if let Some(min) = &manifest.min_app_version {
+        if parse_version(min).is_none() {
+            return Err(ZpeError::InvalidManifest(format!("invalid minAppVersion '{}'", min)));
+        }
+    }
+    Ok(())
+}
+
+/// Locate and parse the header; returns it with the offset of the first section
+fn parse_header(data: &[u8]) -> Result<(ZpeHeader, usize), ZpeError> {
+    let (bytes, sections_offset, legacy_layout) = if data[..4] == ZPE_MAGIC {
+        (&data[..ZPE_HEADER_SIZE], ZPE_HEADER_SIZE, false)
+    } else if data[0] == section::HEADER
+        && read_u32(&data[1..5]) as usize == ZPE_HEADER_SIZE
+        && data.len() >= SECTION_WRAPPER_SIZE + ZPE_HEADER_SIZE
+        && data[5..9] == ZPE_MAGIC
+    {
+        let end = SECTION_WRAPPER_SIZE + ZPE_HEADER_SIZE;
+        (&data[SECTION_WRAPPER_SIZE..end], end, true)
+    } else {
+        return Err(ZpeError::InvalidMagic([data[0], data[1], data[2], data[3]]));
+    };
+
+    let version = read_u16(&bytes[4..6]);
+    if version != ZPE_FORMAT_VERSION {
+        return Err(ZpeError::UnsupportedVersion(version));
+    }
+    let flags = read_u16(&bytes[6..8]);
+    let (compression, encryption) = (bytes[8], bytes[9]);
+    if compression != COMPRESSION_NONE {
+        return Err(ZpeError::UnsupportedCompression(compression));
+    }
+    let encrypted = flags & FLAG_ENCRYPTED != 0;
+    match (encrypted, encryption) {
+        (false, ENCRYPTION_NONE) | (true, ENCRYPTION_AES_GCM) => {}
+        _ => return Err(ZpeError::UnsupportedEncryption(encryption)),
+    }
+
+    let header = ZpeHeader {
+        version,
+        encrypted,
+        signed: flags & FLAG_SIGNED != 0,
+        compression,
+        encryption,
+        legacy_layout,
+    };
+    Ok((header, sections_offset))
+}
+
+/// Parse a package without verifying it
+pub fn parse(data: &[u8]) -> Result<ZpePackage, ZpeError> {
+    if data.len() > MAX_PACKAGE_BYTES {
+        return Err(ZpeError::TooLarge(data.len()));
+    }
+    if data.len() < ZPE_HEADER_SIZE {
+        return Err(ZpeError::TooSmall(data.len()));
+    }
+    let (header, mut offset) = parse_header(data)?;
+
+    // (type, offset of the section, data)
+    let mut sections: Vec<(u8, usize, &[u8])> = Vec::new();
+    while offset < data.len() {
+        let kind = data[offset];
+        let truncated = ZpeError::TruncatedSection { kind, offset };
+        if data.len() - offset < SECTION_WRAPPER_SIZE {
+            return Err(truncated);
+        }
+        let start = offset + SECTION_WRAPPER_SIZE;
+        let end = start
+            .checked_add(read_u32(&data[offset + 1..start]) as usize)
+            .filter(|end| *end <= data.len())
+            .ok_or(truncated)?;
+        if sections.iter().any(|(k, _, _)| *k == kind) {
+            return Err(ZpeError::DuplicateSection(kind));
+        }
+        sections.push((kind, offset, &data[start..end]));
+        offset = end;
+    }
+    let find = |kind: u8| sections.iter().find(|(k, _, _)| *k == kind);
+
+    let (_, _, manifest) = find(section::MANIFEST).ok_or(ZpeError::MissingSection("manifest"))?;
+    let manifest: ZpeManifest =
+        serde_json::from_slice(manifest).map_err(|e| ZpeError::InvalidManifest(e.to_string()))?;
+    validate_manifest(&manifest)?;
+
+    let (_, _, code) = find(section::CODE).ok_or(ZpeError::MissingSection("code"))?;
+    let json_section = |kind: u8| {
+        find(kind)
+            .map(|(_, _, data)| {
+                serde_json::from_slice::<Value>(data)
+                    .map_err(|e| ZpeError::InvalidSection { kind, message: e.to_string() })
+            })
+            .transpose()
+    };
+    let assets = json_section(section::ASSETS)?;
+    let metadata = json_section(section::METADATA)?;
+
+    let (signature, signed_len) = match sections.iter().position(|(k, _, _)| *k == section::SIGNATURE) {
+        Some(index) if index != sections.len() - 1 => return Err(ZpeError::SignatureNotLast),
+        Some(index) => {
+            let (_, section_offset, data) = sections[index];
+            let signature: ZpeSignature = serde_json::from_slice(data)
+                .map_err(|e| ZpeError::MalformedSignature(e.to_string()))?;
+            (Some(signature), section_offset)
+        }
+        None => (None, data.len()),
+    };
+    if header.signed != signature.is_some() {
+        return Err(ZpeError::SignatureFlagMismatch);
+    }
+
+    Ok(ZpePackage {
+        header,
+        manifest,
+        code: code.to_vec(),
+        assets,
+        metadata,
+        signature,
+        signed_len,
+    })
+}
+
+// =============================================================================
+// Verification
+// =============================================================================
+
+/// Integrity hash of plugin code, as written by the builders (`sha256-{base64}`)
+pub fn integrity_hash(code: &str) -> String {
+    format!("sha256-{}", BASE64.encode(Sha256::digest(code.as_bytes())))
+}
+
+/// SHA-256 of data as lowercase hex
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check a signature against the trusted keys; returns the signing key's ID
+fn verify_signature(signed: &[u8], signature: &ZpeSignature, keys: &[TrustedKey]) -> Result<String, ZpeError> {
+    if signature.algorithm != SIGNATURE_ALGORITHM {
+        return Err(ZpeError::UnsupportedSignatureAlgorithm(signature.algorithm.clone()));
+    }
+    let key = keys
+        .iter()
+        .find(|key| key.key_id == signature.key_id)
+        .ok_or_else(|| ZpeError::UntrustedKey(signature.key_id.clone()))?;
+    let verifying_key = key.public_key.verifying_key()?;
+
+    let raw = BASE64
+        .decode(signature.signature.trim())
+        .map_err(|e| ZpeError::MalformedSignature(e.to_string()))?;
+    let signature = Signature::from_slice(&raw).map_err(|_| {
+        ZpeError::MalformedSignature(format!("expected a 96-byte signature, got {} bytes", raw.len()))
+    })?;
+
+    // WebCrypto and Node sign the SHA-256 digest with the P-384 key
+    verifying_key
+        .verify_prehash(&Sha256::digest(signed), &signature)
+        .map_err(|_| ZpeError::InvalidSignature)?;
+    Ok(key.key_id.clone())
+}
+
+/// Parse and verify a package: the signature first, then the code's integrity hash
+pub fn verify(data: &[u8], keys: &[TrustedKey], options: &VerifyOptions) -> Result<VerifiedPackage, ZpeError> {
+    let package = parse(data)?;
+
+    let signed_by = match &package.signature {
+        Some(signature) => Some(verify_signature(&data[..package.signed_len], signature, keys)?),
+        None if !options.allow_unsigned => return Err(ZpeError::SignatureRequired),
+        None => None,
+    };
+
+    if package.header.encrypted {
+        return Err(ZpeError::EncryptedCode);
+    }
+    let code = String::from_utf8(package.code).map_err(|_| ZpeError::InvalidCode)?;
+    let expected = package
+        .manifest
+        .security
+        .integrity_hash
+        .clone()
+        .ok_or(ZpeError::MissingIntegrityHash)?;
+    let actual = integrity_hash(&code);
+    if expected != actual {
+        return Err(ZpeError::IntegrityMismatch { expected, actual });
+    }
+
+    Ok(VerifiedPackage {
+        manifest: package.manifest,
+        code,
+        assets: package.assets,
+        metadata: package.metadata,
+        signed_by,
+        sha256: sha256_hex(data),
+    })
+}
+
+/// Read a package file, refusing files that are too large to be a plugin
+pub fn read_package(path: &Path) -> Result<Vec<u8>, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_PACKAGE_BYTES as u64 {
+        return Err(ZpeError::TooLarge(size as usize).to_string());
+    }
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+// =============================================================================
+// Trusted Keys
+// =============================================================================
+
+/// Load trusted keys from the store if not already loaded
+fn ensure_keys_loaded(app: &AppHandle, state: &ZpeState) {
+    let mut loaded = state.loaded.lock().unwrap();
+    if *loaded {
+        return;
+    }
+
+    if let Ok(store) = app.store(ZPE_STORE_FILE) {
+        if let Some(value) = store.get(TRUSTED_KEYS_KEY) {
+            match serde_json::from_value::<Vec<TrustedKey>>(value.clone()) {
+                Ok(keys) => *state.keys.lock().unwrap() = keys,
+                Err(e) => log::warn!("Failed to deserialize trusted keys: {}", e),
+            }
+        }
+    }
+
+    *loaded = true;
+}
+
+/// Save trusted keys to the store
+fn save_keys_to_store(app: &AppHandle, keys: &[TrustedKey]) -> Result<(), String> {
+    let store = app
+        .store(ZPE_STORE_FILE)
+        .map_err(|e| format!("Failed to open key store: {}", e))?;
+    let value = serde_json::to_value(keys).map_err(|e| format!("Failed to serialize trusted keys: {}", e))?;
+    store.set(TRUSTED_KEYS_KEY, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save key store: {}", e))
+}
+
+/// Get the trusted signing keys
+pub fn trusted_keys(app: &AppHandle) -> Vec<TrustedKey> {
+    let state = app.state::<ZpeState>();
+    ensure_keys_loaded(app, &state);
+    let keys = state.keys.lock().map(|keys| keys.clone()).unwrap_or_default();
+    keys
+}
+
+/// Verify a package against the trusted key store
+pub fn verify_with_store(app: &AppHandle, data: &[u8], options: &VerifyOptions) -> Result<VerifiedPackage, ZpeError> {
+    verify(data, &trusted_keys(app), options)
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+/// Read a package's header, manifest and signer without verifying it
+#[tauri::command]
+pub fn zpe_inspect(path: String) -> Result<ZpeInfo, String> {
+    let data = read_package(Path::new(&path))?;
+    let package = parse(&data).map_err(|e| e.to_string())?;
+    Ok(ZpeInfo {
+        header: package.header,
+        manifest: package.manifest,
+        signature: package.signature,
+        size: data.len(),
+        sha256: sha256_hex(&data),
+    })
+}
+
+/// Verify a package file and return its manifest and code. Unsigned packages are
+/// rejected unless `allow_unsigned` is set.
+#[tauri::command]
+pub fn zpe_verify(
+    path: String,
+    allow_unsigned: Option<bool>,
+    app: AppHandle,
+) -> Result<VerifiedPackage, String> {
+    let data = read_package(Path::new(&path))?;
+    let options = VerifyOptions {
+        allow_unsigned: allow_unsigned.unwrap_or(false),
+    };
+    verify_with_store(&app, &data, &options).map_err(|e| e.to_string())
+}
+
+/// List trusted signing keys
+#[tauri::command]
+pub fn zpe_list_trusted_keys(app: AppHandle) -> Result<Vec<TrustedKey>, String> {
+    Ok(trusted_keys(&app))
+}
+
+/// Trust a signing key (replacing a key with the same ID)
+#[tauri::command]
+pub fn zpe_trust_key(
+    key: TrustedKey,
+    app: AppHandle,
+    state: State<'_, ZpeState>,
+) -> Result<TrustedKey, String> {
+    if key.key_id.trim().is_empty() {
+        return Err("Key ID is required".to_string());
+    }
+    key.public_key.verifying_key().map_err(|e| e.to_string())?;
+    ensure_keys_loaded(&app, &state);
+
+    let key = TrustedKey {
+        added_at: get_current_timestamp(),
+        ..key
+    };
+    let mut keys = state
+        .keys
+        .lock()
+        .map_err(|e| format!("Failed to lock key store: {}", e))?;
+    keys.retain(|k| k.key_id != key.key_id);
+    keys.push(key.clone());
+    save_keys_to_store(&app, &keys)?;
+    log::info!("Trusted ZPE signing key '{}'", key.key_id);
+    Ok(key)
+}
+
+/// Remove a trusted signing key; returns whether it was trusted
+#[tauri::command]
+pub fn zpe_untrust_key(
+    key_id: String,
+    app: AppHandle,
+    state: State<'_, ZpeState>,
+) -> Result<bool, String> {
+    ensure_keys_loaded(&app, &state);
+    let mut keys = state
+        .keys
+        .lock()
+        .map_err(|e| format!("Failed to lock key store: {}", e))?;
+    let before = keys.len();
+    keys.retain(|k| k.key_id != key_id);
+    if keys.len() == before {
+        return Ok(false);
+    }
+    save_keys_to_store(&app, &keys)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::TrustedKey;
+
+    /// Packages built by `scripts/build-plugin.mjs` (see `tests/fixtures/zpe/README.md`)
+    pub const UNSIGNED: &[u8] = include_bytes!("../tests/fixtures/zpe/fixture-provider-unsigned.zpe");
+    pub const SIGNED: &[u8] = include_bytes!("../tests/fixtures/zpe/fixture-provider-signed.zpe");
+    const TRUSTED_KEYS: &str = include_str!("../tests/fixtures/zpe/trusted-keys.json");
+
+    /// The public part of the fixtures' test signing key
+    pub fn trusted_keys() -> Vec<TrustedKey> {
+        serde_json::from_str(TRUSTED_KEYS).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{trusted_keys, SIGNED, UNSIGNED};
+    use super::*;
+
+    /// Replace a section's data, fixing up its length
+    fn replace_section(data: &[u8], kind: u8, replacement: &[u8]) -> Vec<u8> {
+        let mut out = data[..ZPE_HEADER_SIZE].to_vec();
+        let mut offset = ZPE_HEADER_SIZE;
+        while offset < data.len() {
+            let length = read_u32(&data[offset + 1..offset + 5]) as usize;
+            let end = offset + SECTION_WRAPPER_SIZE + length;
+            if data[offset] == kind {
+                out.push(kind);
+                out.extend((replacement.len() as u32).to_le_bytes());
+                out.extend(replacement);
+            } else {
+                out.extend(&data[offset..end]);
+            }
+            offset = end;
+        }
+        out
+    }
+
+    #[test]
+    fn test_parse_js_built_fixtures() {
+        let package = parse(UNSIGNED).unwrap();
+        assert_eq!(package.header.version, 1);
+        assert!(!package.header.signed && !package.header.encrypted && !package.header.legacy_layout);
+        assert_eq!(package.manifest.id, "fixture-provider");
+        assert_eq!(package.manifest.version, "1.2.0");
+        assert_eq!(package.manifest.min_app_version.as_deref(), Some("2.5.0"));
+        assert_eq!(package.manifest.permissions, vec!["network:http", "storage:local"]);
+        assert_eq!(package.manifest.security.allowed_domains, vec!["example.com", "*.example.org"]);
+        assert!(package.manifest.extra.contains_key("capabilities"));
+        assert!(String::from_utf8_lossy(&package.code).contains("async search(query"));
+        assert!(package.metadata.is_some() && package.signature.is_none());
+        assert_eq!(package.signed_len, UNSIGNED.len());
+
+        let signed = parse(SIGNED).unwrap();
+        assert!(signed.header.signed);
+        let signature = signed.signature.unwrap();
+        assert_eq!(signature.algorithm, SIGNATURE_ALGORITHM);
+        assert_eq!(signature.key_id, "zanshin-test");
+        assert!(signed.signed_len < SIGNED.len());
+
+        // The old layout wraps the header in a section
+        let mut legacy = vec![section::HEADER, 16, 0, 0, 0];
+        legacy.extend_from_slice(UNSIGNED);
+        let package = parse(&legacy).unwrap();
+        assert!(package.header.legacy_layout);
+        assert_eq!(package.manifest.id, "fixture-provider");
+    }
+
+    #[test]
+    fn test_verify_signatures_and_integrity() {
+        let keys = trusted_keys();
+        let strict = VerifyOptions::default();
+        let developer = VerifyOptions { allow_unsigned: true };
+
+        let verified = verify(SIGNED, &keys, &strict).unwrap();
+        assert_eq!(verified.signed_by.as_deref(), Some("zanshin-test"));
+        assert_eq!(Some(integrity_hash(&verified.code)), verified.manifest.security.integrity_hash);
+        assert_eq!(verified.sha256.len(), 64);
+
+        assert_eq!(verify(SIGNED, &[], &strict).unwrap_err(), ZpeError::UntrustedKey("zanshin-test".into()));
+        assert_eq!(verify(UNSIGNED, &keys, &strict).unwrap_err(), ZpeError::SignatureRequired);
+        assert!(verify(UNSIGNED, &keys, &developer).unwrap().signed_by.is_none());
+
+        // Any change to signed bytes breaks the signature
+        let code = String::from_utf8(parse(SIGNED).unwrap().code).unwrap();
+        let tampered = replace_section(SIGNED, section::CODE, code.replace("example.com", "evil.test").as_bytes());
+        assert_eq!(verify(&tampered, &keys, &strict).unwrap_err(), ZpeError::InvalidSignature);
+
+        // Unsigned packages still fail the integrity check
+        let tampered = replace_section(UNSIGNED, section::CODE, b"module.exports = {}");
+        assert!(matches!(
+            verify(&tampered, &keys, &developer),
+            Err(ZpeError::IntegrityMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_typed_parse_errors() {
+        assert_eq!(parse(b"ZPE!").unwrap_err(), ZpeError::TooSmall(4));
+        assert_eq!(
+            parse(b"PK\x03\x04 not a plugin package").unwrap_err(),
+            ZpeError::InvalidMagic(*b"PK\x03\x04")
+        );
+
+        let mut version = UNSIGNED.to_vec();
+        version[4] = 2;
+        assert_eq!(parse(&version).unwrap_err(), ZpeError::UnsupportedVersion(2));
+
+        let mut encryption = UNSIGNED.to_vec();
+        encryption[9] = ENCRYPTION_AES_GCM;
+        assert_eq!(parse(&encryption).unwrap_err(), ZpeError::UnsupportedEncryption(1));
+
+        let truncated = &UNSIGNED[..UNSIGNED.len() - 1];
+        assert!(matches!(parse(truncated), Err(ZpeError::TruncatedSection { kind: section::METADATA, .. })));
+
+        let mut duplicate = UNSIGNED.to_vec();
+        duplicate.extend_from_slice(&[section::CODE, 0, 0, 0, 0]);
+        assert_eq!(parse(&duplicate).unwrap_err(), ZpeError::DuplicateSection(section::CODE));
+
+        // A signature section that isn't announced in the header (or the other way round)
+        let mut unflagged = SIGNED.to_vec();
+        unflagged[6] &= !(FLAG_SIGNED as u8);
+        assert_eq!(parse(&unflagged).unwrap_err(), ZpeError::SignatureFlagMismatch);
+        let stripped = &SIGNED[..parse(SIGNED).unwrap().signed_len];
+        assert_eq!(parse(stripped).unwrap_err(), ZpeError::SignatureFlagMismatch);
+
+        let mut trailing = SIGNED.to_vec();
+        trailing.extend_from_slice(&[section::ASSETS, 2, 0, 0, 0, b'{', b'}']);
+        assert_eq!(parse(&trailing).unwrap_err(), ZpeError::SignatureNotLast);
+
+        let manifest = replace_section(UNSIGNED, section::MANIFEST, br#"{"id":"X","name":"x","version":"1.0.0"}"#);
+        assert!(matches!(parse(&manifest), Err(ZpeError::InvalidManifest(_))));
+    }
+
+    #[test]
+    fn test_versions_and_keys() {
+        assert_eq!(parse_version("2.5.4"), Some((2, 5, 4)));
+        assert_eq!(parse_version("1.0.0-beta.1+build"), Some((1, 0, 0)));
+        assert_eq!(parse_version("1.0"), None);
+        assert_eq!(parse_version("1.0.0.0"), None);
+        assert!(is_valid_version("1.0.0-beta.1+build"));
+        assert!(!is_valid_version("1.0.1+/../../../x"));
+        assert!(!is_valid_version("1.0.1+..\\x"));
+        assert!(!is_valid_version("1.0.1-a..b"));
+        assert!(!is_valid_version(" 1.0.1"));
+
+        let mut key = trusted_keys().remove(0).public_key;
+        assert!(key.verifying_key().is_ok());
+        key.y = key.x.clone();
+        assert!(matches!(key.verifying_key(), Err(ZpeError::InvalidKey(_))));
+    }
+
+    /// Mutation fuzzing of the JS-built fixtures: parsing never panics, and no mutation
+    /// of a signed package verifies (`cargo fuzz run zpe_parse` covers arbitrary input)
+    #[test]
+    fn test_fuzz_mutated_packages() {
+        let keys = trusted_keys();
+        let strict = VerifyOptions::default();
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound.max(1) as u64) as usize
+        };
+
+        for iteration in 0..3000 {
+            let source = if iteration % 2 == 0 { SIGNED } else { UNSIGNED };
+            let mut data = source.to_vec();
+            for _ in 0..1 + next(3) {
+                let position = next(data.len());
+                match next(5) {
+                    0 => data[position] ^= 1 << next(8),
+                    1 => data[position] = next(256) as u8,
+                    2 => data.truncate(position),
+                    3 => data.insert(position, next(256) as u8),
+                    _ => {
+                        // Corrupt something that looks like a length field
+                        let value = (next(usize::MAX) as u32).to_le_bytes();
+                        let end = (position + 4).min(data.len());
+                        data[position..end].copy_from_slice(&value[..end - position]);
+                    }
+                }
+                if data.is_empty() {
+                    break;
+                }
+            }
+
+            let parsed = parse(&data);
+            if source == SIGNED && data != SIGNED && parsed.as_ref().is_ok_and(|p| p.signature.is_some()) {
+                assert!(verify(&data, &keys, &strict).is_err(), "mutation {} verified", iteration);
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+(function(){var s=document.createElement('script');s.async=true;
+new Image().src = '/.well-known/ddos-guard/id/WaEVx0qDRGWM3Vad';
+})();
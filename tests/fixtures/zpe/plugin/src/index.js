@@ -0,0 +1,12 @@
+const plugin = {
+  async init(ctx) {
+    this.http = ctx.http;
+  },
+
+  async search(query, page = 1) {
+    const body = await this.http.get(`https://example.com/search?q=${encodeURIComponent(query)}&page=${page}`);
+    return { results: JSON.parse(body).results, hasNextPage: false, currentPage: page };
+  }
+};
+
+module.exports = plugin;